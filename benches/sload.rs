@@ -0,0 +1,93 @@
+// Benchmark of the SLOAD read path with and without the flat snapshot.
+//
+// bench_sload fills the storage of one contract, commits it to a disk backed
+// state and then reads every slot through get_committed_state, which is what
+// op_sload and the SSTORE gas calculation hit on a cold slot. It runs the
+// reads once against a state that walks the storage trie and once against a
+// state reading from the snapshot, and reports both durations. Run it with
+//
+//     cargo bench --bench sload
+
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use minimal_blockchain::ethdb::{KeyValueStore, MemoryDatabase};
+use minimal_blockchain::memory_state_db::MemoryStateDB;
+use minimal_blockchain::snapshot::Tree;
+use minimal_blockchain::state_db::StateDB;
+use uint256::Uint256;
+
+#[derive(Debug, Clone, Copy)]
+struct SloadBenchResult {
+    reads: usize,
+    trie: Duration,
+    snapshot: Duration,
+}
+
+impl SloadBenchResult {
+    fn speedup(&self) -> f64 {
+        self.trie.as_secs_f64() / self.snapshot.as_secs_f64().max(f64::MIN_POSITIVE)
+    }
+}
+
+fn slot(i: usize) -> common::Hash {
+    let mut key = [0u8; 32];
+    key[24..].copy_from_slice(&(i as u64).to_be_bytes());
+    common::Hash(key)
+}
+
+// bench_sload reads `slots` storage slots `rounds` times through each read path.
+fn bench_sload(slots: usize, rounds: usize) -> SloadBenchResult {
+    let disk: Arc<dyn KeyValueStore> = Arc::new(MemoryDatabase::new());
+    let contract = common::Address([0xaa; 20]);
+
+    let mut state = MemoryStateDB::open_disk(disk.clone()).expect("empty state");
+    state.set_nonce(&contract, 1);
+    state.add_balance(&contract, &Uint256::from_uint64(1));
+    for i in 0..slots {
+        // Non-zero values with a distinct high byte.
+        let mut value = slot(i + 1);
+        value.0[0] = 0x01;
+        state.set_state(&contract, slot(i), value);
+    }
+    let root = state.commit(true).expect("commit");
+
+    let run = |state: &MemoryStateDB| {
+        let start = Instant::now();
+        for _ in 0..rounds {
+            for i in 0..slots {
+                let value = state.get_committed_state(&contract, &slot(i));
+                assert_ne!(value, common::Hash::default());
+            }
+        }
+        start.elapsed()
+    };
+
+    let trie_state = MemoryStateDB::open_disk(disk.clone()).expect("committed state");
+    let trie = run(&trie_state);
+
+    let snaps = Tree::new(disk.clone(), trie_state.database(), root).expect("snapshot generation");
+    let snap_state = MemoryStateDB::open_disk(disk)
+        .expect("committed state")
+        .with_snapshots(Arc::new(snaps));
+    let snapshot = run(&snap_state);
+
+    SloadBenchResult {
+        reads: slots * rounds,
+        trie,
+        snapshot,
+    }
+}
+
+fn main() {
+    for slots in [1_000, 10_000] {
+        let result = bench_sload(slots, 5);
+        println!(
+            "sload {} reads: trie {:?} snapshot {:?} ({:.1}x)",
+            result.reads,
+            result.trie,
+            result.snapshot,
+            result.speedup()
+        );
+    }
+}
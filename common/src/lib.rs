@@ -0,0 +1,128 @@
+// Package common contains the basic types shared by the whole node: the
+// 32-byte Keccak hash and the 20-byte account address, and a few byte slice
+// helpers.
+
+use std::fmt;
+
+// Lengths of hashes and addresses in bytes.
+pub const HASH_LENGTH: usize = 32;
+pub const ADDRESS_LENGTH: usize = 20;
+
+// Hash represents the 32 byte Keccak256 hash of arbitrary data.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Hash(pub [u8; HASH_LENGTH]);
+
+impl Hash {
+    // from_slice sets the hash to the value of b. If b is larger than the
+    // hash, b will be cropped from the left; if it is smaller, it is padded
+    // with zeros on the left.
+    pub fn from_slice(b: &[u8]) -> Self {
+        let b = if b.len() > HASH_LENGTH { &b[b.len() - HASH_LENGTH..] } else { b };
+        let mut h = [0u8; HASH_LENGTH];
+        h[HASH_LENGTH - b.len()..].copy_from_slice(b);
+        Hash(h)
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.0 == [0u8; HASH_LENGTH]
+    }
+}
+
+impl From<[u8; HASH_LENGTH]> for Hash {
+    fn from(b: [u8; HASH_LENGTH]) -> Self {
+        Hash(b)
+    }
+}
+
+impl AsRef<[u8]> for Hash {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl fmt::Debug for Hash {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "0x{}", to_hex(&self.0))
+    }
+}
+
+impl fmt::Display for Hash {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "0x{}", to_hex(&self.0))
+    }
+}
+
+// Address represents the 20 byte address of an Ethereum account.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Address(pub [u8; ADDRESS_LENGTH]);
+
+impl Address {
+    // from_slice sets the address to the value of b. If b is larger than
+    // the address, b will be cropped from the left; if it is smaller, it is
+    // padded with zeros on the left.
+    pub fn from_slice(b: &[u8]) -> Self {
+        let b = if b.len() > ADDRESS_LENGTH { &b[b.len() - ADDRESS_LENGTH..] } else { b };
+        let mut a = [0u8; ADDRESS_LENGTH];
+        a[ADDRESS_LENGTH - b.len()..].copy_from_slice(b);
+        Address(a)
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.0 == [0u8; ADDRESS_LENGTH]
+    }
+}
+
+impl From<[u8; ADDRESS_LENGTH]> for Address {
+    fn from(b: [u8; ADDRESS_LENGTH]) -> Self {
+        Address(b)
+    }
+}
+
+impl AsRef<[u8]> for Address {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl fmt::Debug for Address {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "0x{}", to_hex(&self.0))
+    }
+}
+
+impl fmt::Display for Address {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "0x{}", to_hex(&self.0))
+    }
+}
+
+fn to_hex(b: &[u8]) -> String {
+    b.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+// right_pad_bytes zero-pads slice to the right up to length l.
+pub fn right_pad_bytes(slice: &[u8], l: usize) -> Vec<u8> {
+    let mut padded = slice.to_vec();
+    if l > padded.len() {
+        padded.resize(l, 0);
+    }
+    padded
+}
+
+// left_pad_bytes zero-pads slice to the left up to length l.
+pub fn left_pad_bytes(slice: &[u8], l: usize) -> Vec<u8> {
+    if l <= slice.len() {
+        return slice.to_vec();
+    }
+    let mut padded = vec![0u8; l - slice.len()];
+    padded.extend_from_slice(slice);
+    padded
+}
@@ -0,0 +1,573 @@
+// Package block contains the block and header data structures of the chain
+// built on top of the VM.
+
+use chrono::{DateTime, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::bloom::Bloom;
+use crate::crypto;
+use crate::hexutil;
+use crate::rlp::{self, Decodable, DecoderError, Encodable, Rlp};
+use crate::transaction::Transaction;
+use crate::trie::{Trie, EMPTY_ROOT_HASH};
+use uint256::Uint256;
+
+// EMPTY_UNCLE_HASH is the known hash of the empty uncle set, keccak256(rlp([])).
+pub const EMPTY_UNCLE_HASH: common::Hash = common::Hash([
+    0x1d, 0xcc, 0x4d, 0xe8, 0xde, 0xc7, 0x5d, 0x7a, 0xab, 0x85, 0xb5, 0x67, 0xb6, 0xcc, 0xd4, 0x1a,
+    0xd3, 0x12, 0x45, 0x1b, 0x94, 0x8a, 0x74, 0x13, 0xf0, 0xa1, 0x42, 0xfd, 0x40, 0xd4, 0x93, 0x47,
+]);
+
+// EMPTY_TX_ROOT is the known root hash of an empty transaction trie.
+pub const EMPTY_TX_ROOT: common::Hash = EMPTY_ROOT_HASH;
+
+// EMPTY_RECEIPTS_ROOT is the known root hash of an empty receipt trie.
+pub const EMPTY_RECEIPTS_ROOT: common::Hash = EMPTY_ROOT_HASH;
+
+// EMPTY_WITHDRAWALS_ROOT is the known root hash of an empty withdrawal trie.
+pub const EMPTY_WITHDRAWALS_ROOT: common::Hash = EMPTY_ROOT_HASH;
+
+// BlockNonce is a 64-bit hash which proves (combined with the mix-hash) that
+// a sufficient amount of computation has been carried out on a block.
+pub type BlockNonce = [u8; 8];
+
+fn encode_nonce(nonce: &BlockNonce) -> String {
+    hexutil::encode(nonce)
+}
+
+fn decode_nonce(s: &str) -> Result<BlockNonce, hexutil::HexError> {
+    hexutil::decode_fixed::<8>(s)
+}
+
+mod nonce_hex {
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(nonce: &super::BlockNonce, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&super::encode_nonce(nonce))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<super::BlockNonce, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        super::decode_nonce(&s).map_err(D::Error::custom)
+    }
+}
+
+// Header represents a block header in the Ethereum blockchain.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Header {
+    #[serde(with = "hexutil::hash")]
+    pub parent_hash: common::Hash,
+    #[serde(rename = "sha3Uncles", with = "hexutil::hash")]
+    pub uncle_hash: common::Hash,
+    #[serde(rename = "miner", with = "hexutil::address")]
+    pub coinbase: common::Address,
+    #[serde(rename = "stateRoot", with = "hexutil::hash")]
+    pub root: common::Hash,
+    #[serde(rename = "transactionsRoot", with = "hexutil::hash")]
+    pub tx_hash: common::Hash,
+    #[serde(rename = "receiptsRoot", with = "hexutil::hash")]
+    pub receipt_hash: common::Hash,
+    #[serde(rename = "logsBloom")]
+    pub bloom: Bloom,
+    #[serde(with = "hexutil::big")]
+    pub difficulty: Uint256,
+    #[serde(with = "hexutil::quantity")]
+    pub number: u64,
+    #[serde(with = "hexutil::quantity")]
+    pub gas_limit: u64,
+    #[serde(with = "hexutil::quantity")]
+    pub gas_used: u64,
+    #[serde(rename = "timestamp", with = "hexutil::quantity")]
+    pub time: u64,
+    #[serde(rename = "extraData", with = "hexutil::bytes")]
+    pub extra: Vec<u8>,
+    #[serde(rename = "mixHash", with = "hexutil::hash")]
+    pub mix_digest: common::Hash,
+    #[serde(with = "nonce_hex")]
+    pub nonce: BlockNonce,
+
+    // base_fee was added by EIP-1559 and is ignored in legacy headers.
+    #[serde(rename = "baseFeePerGas", with = "hexutil::big::opt", default, skip_serializing_if = "Option::is_none")]
+    pub base_fee: Option<Uint256>,
+
+    // withdrawals_hash was added by EIP-4895 and is ignored in legacy headers.
+    #[serde(rename = "withdrawalsRoot", with = "hexutil::hash::opt", default, skip_serializing_if = "Option::is_none")]
+    pub withdrawals_hash: Option<common::Hash>,
+
+    // blob_gas_used was added by EIP-4844 and is ignored in legacy headers.
+    #[serde(with = "hexutil::quantity::opt", default, skip_serializing_if = "Option::is_none")]
+    pub blob_gas_used: Option<u64>,
+
+    // excess_blob_gas was added by EIP-4844 and is ignored in legacy headers.
+    #[serde(with = "hexutil::quantity::opt", default, skip_serializing_if = "Option::is_none")]
+    pub excess_blob_gas: Option<u64>,
+
+    // parent_beacon_root was added by EIP-4788 and is ignored in legacy headers.
+    #[serde(rename = "parentBeaconBlockRoot", with = "hexutil::hash::opt", default, skip_serializing_if = "Option::is_none")]
+    pub parent_beacon_root: Option<common::Hash>,
+
+    // requests_hash was added by EIP-7685 and is ignored in legacy headers.
+    #[serde(with = "hexutil::hash::opt", default, skip_serializing_if = "Option::is_none")]
+    pub requests_hash: Option<common::Hash>,
+}
+
+impl Default for Header {
+    fn default() -> Self {
+        Header {
+            parent_hash: common::Hash::default(),
+            uncle_hash: EMPTY_UNCLE_HASH,
+            coinbase: common::Address::default(),
+            root: EMPTY_ROOT_HASH,
+            tx_hash: EMPTY_TX_ROOT,
+            receipt_hash: EMPTY_RECEIPTS_ROOT,
+            bloom: Bloom::default(),
+            difficulty: Uint256::default(),
+            number: 0,
+            gas_limit: 0,
+            gas_used: 0,
+            time: 0,
+            extra: Vec::new(),
+            mix_digest: common::Hash::default(),
+            nonce: [0u8; 8],
+            base_fee: None,
+            withdrawals_hash: None,
+            blob_gas_used: None,
+            excess_blob_gas: None,
+            parent_beacon_root: None,
+            requests_hash: None,
+        }
+    }
+}
+
+impl Encodable for Header {
+    // The fork specific fields are appended in order and only if set, so a
+    // header encodes the same as it did in the fork that introduced it.
+    fn rlp_append(&self, out: &mut Vec<u8>) {
+        let mut payload = Vec::new();
+        self.parent_hash.rlp_append(&mut payload);
+        self.uncle_hash.rlp_append(&mut payload);
+        self.coinbase.rlp_append(&mut payload);
+        self.root.rlp_append(&mut payload);
+        self.tx_hash.rlp_append(&mut payload);
+        self.receipt_hash.rlp_append(&mut payload);
+        self.bloom.rlp_append(&mut payload);
+        self.difficulty.rlp_append(&mut payload);
+        self.number.rlp_append(&mut payload);
+        self.gas_limit.rlp_append(&mut payload);
+        self.gas_used.rlp_append(&mut payload);
+        self.time.rlp_append(&mut payload);
+        rlp::append_bytes(&self.extra, &mut payload);
+        self.mix_digest.rlp_append(&mut payload);
+        rlp::append_bytes(&self.nonce, &mut payload);
+
+        let optional: [Option<Vec<u8>>; 6] = [
+            self.base_fee.as_ref().map(rlp::encode),
+            self.withdrawals_hash.as_ref().map(rlp::encode),
+            self.blob_gas_used.as_ref().map(rlp::encode),
+            self.excess_blob_gas.as_ref().map(rlp::encode),
+            self.parent_beacon_root.as_ref().map(rlp::encode),
+            self.requests_hash.as_ref().map(rlp::encode),
+        ];
+        for field in optional.iter() {
+            match field {
+                Some(enc) => payload.extend_from_slice(enc),
+                None => break,
+            }
+        }
+        rlp::append_list_payload(&payload, out);
+    }
+}
+
+impl Decodable for Header {
+    fn decode(rlp: &Rlp) -> Result<Self, DecoderError> {
+        let items = rlp.items()?;
+        if items.len() < 15 || items.len() > 21 {
+            return Err(DecoderError::RlpIncorrectListLen);
+        }
+        let nonce = items[14].data()?;
+        if nonce.len() != 8 {
+            return Err(DecoderError::RlpInvalidLength);
+        }
+        let optional = |i: usize| items.get(i);
+        Ok(Header {
+            parent_hash: items[0].as_val()?,
+            uncle_hash: items[1].as_val()?,
+            coinbase: items[2].as_val()?,
+            root: items[3].as_val()?,
+            tx_hash: items[4].as_val()?,
+            receipt_hash: items[5].as_val()?,
+            bloom: items[6].as_val()?,
+            difficulty: items[7].as_val()?,
+            number: items[8].as_val()?,
+            gas_limit: items[9].as_val()?,
+            gas_used: items[10].as_val()?,
+            time: items[11].as_val()?,
+            extra: items[12].data()?.to_vec(),
+            mix_digest: items[13].as_val()?,
+            nonce: [nonce[0], nonce[1], nonce[2], nonce[3], nonce[4], nonce[5], nonce[6], nonce[7]],
+            base_fee: optional(15).map(|item| item.as_val()).transpose()?,
+            withdrawals_hash: optional(16).map(|item| item.as_val()).transpose()?,
+            blob_gas_used: optional(17).map(|item| item.as_val()).transpose()?,
+            excess_blob_gas: optional(18).map(|item| item.as_val()).transpose()?,
+            parent_beacon_root: optional(19).map(|item| item.as_val()).transpose()?,
+            requests_hash: optional(20).map(|item| item.as_val()).transpose()?,
+        })
+    }
+}
+
+impl Header {
+    // hash returns the block hash of the header, which is simply the keccak256
+    // hash of its RLP encoding.
+    pub fn hash(&self) -> common::Hash {
+        crypto::keccak256_hash(&rlp::encode(self))
+    }
+
+    // timestamp returns the block time as a UTC date.
+    pub fn timestamp(&self) -> DateTime<Utc> {
+        Utc.timestamp_opt(self.time as i64, 0).single().unwrap_or_default()
+    }
+
+    // empty_body returns true if there is no additional 'body' to complete the
+    // header that is: no transactions, no uncles and no withdrawals.
+    pub fn empty_body(&self) -> bool {
+        self.tx_hash == EMPTY_TX_ROOT
+            && self.uncle_hash == EMPTY_UNCLE_HASH
+            && self.withdrawals_hash.is_none_or(|hash| hash == EMPTY_WITHDRAWALS_ROOT)
+    }
+
+    // empty_receipts returns true if there are no receipts for this header/block.
+    pub fn empty_receipts(&self) -> bool {
+        self.receipt_hash == EMPTY_RECEIPTS_ROOT
+    }
+}
+
+// Withdrawal represents a validator withdrawal from the consensus layer.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Withdrawal {
+    #[serde(with = "hexutil::quantity")]
+    pub index: u64, // monotonically increasing identifier issued by consensus layer
+    #[serde(rename = "validatorIndex", with = "hexutil::quantity")]
+    pub validator: u64, // index of validator associated with withdrawal
+    #[serde(with = "hexutil::address")]
+    pub address: common::Address, // target address for withdrawn ether
+    #[serde(with = "hexutil::quantity")]
+    pub amount: u64, // value of withdrawal in Gwei
+}
+
+crate::impl_rlp_struct!(Withdrawal { index, validator, address, amount });
+
+// DerivableItem is implemented by the items of the transactions, receipts and
+// withdrawals tries. encode_index returns the value stored in the trie, which
+// for typed transactions and receipts is the EIP-2718 envelope itself rather
+// than the RLP string wrapping it in a block body.
+pub trait DerivableItem {
+    fn encode_index(&self) -> Vec<u8>;
+}
+
+impl DerivableItem for Transaction {
+    fn encode_index(&self) -> Vec<u8> {
+        self.marshal_binary()
+    }
+}
+
+impl DerivableItem for Withdrawal {
+    fn encode_index(&self) -> Vec<u8> {
+        rlp::encode(self)
+    }
+}
+
+// derive_sha computes the root of the trie of the given items, keyed by the
+// RLP encoding of their index, as used for the transactions, receipts and
+// withdrawals roots in the header.
+pub fn derive_sha<T: DerivableItem>(items: &[T]) -> common::Hash {
+    let mut trie = Trie::default();
+    for (i, item) in items.iter().enumerate() {
+        trie.insert(&rlp::encode(&(i as u64)), &item.encode_index())
+            .expect("in-memory trie without hash nodes");
+    }
+    trie.root_hash()
+}
+
+// Body is a simple (mutable, non-safe) data container for storing and moving
+// a block's data contents (transactions and uncles) together.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Body {
+    pub transactions: Vec<Transaction>,
+    pub uncles: Vec<Header>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub withdrawals: Option<Vec<Withdrawal>>,
+}
+
+impl Encodable for Body {
+    fn rlp_append(&self, out: &mut Vec<u8>) {
+        let mut payload = Vec::new();
+        rlp::append_list(&self.transactions, &mut payload);
+        rlp::append_list(&self.uncles, &mut payload);
+        if let Some(withdrawals) = &self.withdrawals {
+            rlp::append_list(withdrawals, &mut payload);
+        }
+        rlp::append_list_payload(&payload, out);
+    }
+}
+
+impl Decodable for Body {
+    fn decode(rlp: &Rlp) -> Result<Self, DecoderError> {
+        let count = rlp.item_count()?;
+        if count != 2 && count != 3 {
+            return Err(DecoderError::RlpIncorrectListLen);
+        }
+        Ok(Body {
+            transactions: rlp.list_at(0)?,
+            uncles: rlp.list_at(1)?,
+            withdrawals: if count == 3 { Some(rlp.list_at(2)?) } else { None },
+        })
+    }
+}
+
+// Block represents an Ethereum block.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct Block {
+    #[serde(flatten)]
+    pub header: Header,
+    #[serde(flatten)]
+    pub body: Body,
+}
+
+impl Block {
+    // new creates a new block. The values of tx_hash, uncle_hash and
+    // withdrawals_hash in header are ignored and set to values derived from
+    // the given transactions, uncles and withdrawals.
+    pub fn new(
+        mut header: Header,
+        transactions: Vec<Transaction>,
+        uncles: Vec<Header>,
+        withdrawals: Option<Vec<Withdrawal>>,
+    ) -> Self {
+        header.tx_hash = if transactions.is_empty() {
+            EMPTY_TX_ROOT
+        } else {
+            derive_sha(&transactions)
+        };
+        header.uncle_hash = calc_uncle_hash(&uncles);
+        header.withdrawals_hash = withdrawals.as_ref().map(|withdrawals| derive_sha(withdrawals));
+        Block {
+            header,
+            body: Body {
+                transactions,
+                uncles,
+                withdrawals,
+            },
+        }
+    }
+
+    // with_body returns a block with the given header and body, without
+    // recomputing the header's roots.
+    pub fn with_body(header: Header, body: Body) -> Self {
+        Block { header, body }
+    }
+
+    // hash returns the keccak256 hash of the block's header.
+    pub fn hash(&self) -> common::Hash {
+        self.header.hash()
+    }
+
+    pub fn number(&self) -> u64 {
+        self.header.number
+    }
+
+    pub fn parent_hash(&self) -> common::Hash {
+        self.header.parent_hash
+    }
+
+    pub fn transactions(&self) -> &[Transaction] {
+        &self.body.transactions
+    }
+
+    pub fn uncles(&self) -> &[Header] {
+        &self.body.uncles
+    }
+
+    pub fn withdrawals(&self) -> Option<&[Withdrawal]> {
+        self.body.withdrawals.as_deref()
+    }
+
+    // transaction returns the transaction with the given hash, if the block
+    // contains it.
+    pub fn transaction(&self, hash: &common::Hash) -> Option<&Transaction> {
+        self.body.transactions.iter().find(|tx| tx.hash() == *hash)
+    }
+}
+
+impl Encodable for Block {
+    // Blocks are encoded as [header, transactions, uncles(, withdrawals)].
+    fn rlp_append(&self, out: &mut Vec<u8>) {
+        let mut payload = Vec::new();
+        self.header.rlp_append(&mut payload);
+        rlp::append_list(&self.body.transactions, &mut payload);
+        rlp::append_list(&self.body.uncles, &mut payload);
+        if let Some(withdrawals) = &self.body.withdrawals {
+            rlp::append_list(withdrawals, &mut payload);
+        }
+        rlp::append_list_payload(&payload, out);
+    }
+}
+
+impl Decodable for Block {
+    fn decode(rlp: &Rlp) -> Result<Self, DecoderError> {
+        let count = rlp.item_count()?;
+        if count != 3 && count != 4 {
+            return Err(DecoderError::RlpIncorrectListLen);
+        }
+        Ok(Block {
+            header: rlp.val_at(0)?,
+            body: Body {
+                transactions: rlp.list_at(1)?,
+                uncles: rlp.list_at(2)?,
+                withdrawals: if count == 4 { Some(rlp.list_at(3)?) } else { None },
+            },
+        })
+    }
+}
+
+// calc_uncle_hash returns the hash of the RLP encoded list of uncles.
+pub fn calc_uncle_hash(uncles: &[Header]) -> common::Hash {
+    if uncles.is_empty() {
+        return EMPTY_UNCLE_HASH;
+    }
+    crypto::keccak256_hash(&rlp::encode_list(uncles))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hash(s: &str) -> common::Hash {
+        common::Hash(hexutil::decode_fixed::<32>(s).unwrap())
+    }
+
+    // Mainnet block 1 as returned by eth_getBlockByNumber.
+    const MAINNET_BLOCK_1: &str = r#"{
+        "parentHash": "0xd4e56740f876aef8c010b86a40d5f56745a118d0906a34e69aec8c0db1cb8fa3",
+        "sha3Uncles": "0x1dcc4de8dec75d7aab85b567b6ccd41ad312451b948a7413f0a142fd40d49347",
+        "miner": "0x05a56e2d52c817161883f50c441c3228cfe54d9f",
+        "stateRoot": "0xd67e4d450343046425ae4271474353857ab860dbc0a1dde64b41b5cd3a532bf3",
+        "transactionsRoot": "0x56e81f171bcc55a6ff8345e692c0f86e5b48e01b996cadc001622fb5e363b421",
+        "receiptsRoot": "0x56e81f171bcc55a6ff8345e692c0f86e5b48e01b996cadc001622fb5e363b421",
+        "logsBloom": "0x00000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000",
+        "difficulty": "0x3ff800000",
+        "number": "0x1",
+        "gasLimit": "0x1388",
+        "gasUsed": "0x0",
+        "timestamp": "0x55ba4224",
+        "extraData": "0x476574682f76312e302e302f6c696e75782f676f312e342e32",
+        "mixHash": "0x969b900de27b6ac6a67742365dd65f55a0526c41fd18e1b16f1a1215c2e66f59",
+        "nonce": "0x539bd4979fef1ec4"
+    }"#;
+
+    #[test]
+    fn test_mainnet_header_hash() {
+        let header: Header = serde_json::from_str(MAINNET_BLOCK_1).unwrap();
+        assert_eq!(header.number, 1);
+        assert_eq!(header.difficulty, Uint256::from_uint64(17171480576));
+        assert_eq!(header.extra, b"Geth/v1.0.0/linux/go1.4.2");
+        assert_eq!(header.hash(), hash("0x88e96d4537bea4d9c05d12549907b32561d3bf31f45aae734cdc119f13406cb6"));
+
+        let enc = rlp::encode(&header);
+        let dec: Header = rlp::decode(&enc).unwrap();
+        assert_eq!(dec, header);
+    }
+
+    // Taken from go-ethereum's TestBlockEncoding.
+    #[test]
+    fn test_block_encoding() {
+        let blob = hexutil::decode("0xf90260f901f9a083cafc574e1f51ba9dc0568fc617a08ea2429fb384059c972f13b19fa1c8dd55a01dcc4de8dec75d7aab85b567b6ccd41ad312451b948a7413f0a142fd40d49347948888f1f195afa192cfee860698584c030f4c9db1a0ef1552a40b7165c3cd773806b9e0c165b75356e0314bf0706f279c729f51e017a05fe50b260da6308036625b850b5d6ced6d0a9f814c0688bc91ffb7b7a3a54b67a0bc37d79753ad738a6dac4921e57392f145d8887476de3f783dfa7edae9283e52b90100000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000008302000001832fefd8825208845506eb0780a0bd4472abb6659ebe3ee06ee4d7b72a00a9f4d001caca51342001075469aff49888a13a5a8c8f2bb1c4f861f85f800a82c35094095e7baea6a6c7c4c2dfeb977efac326af552d870a801ba09bea4c4daac7c7c52e093e6a4c35dbbcf8856f1af7b059ba20253e70848d094fa08a8fae537ce25ed8cb5af9adac3f141af69bd515bd2ba031522df09b97dd72b1c0").unwrap();
+        let block: Block = rlp::decode(&blob).unwrap();
+
+        assert_eq!(block.header.gas_limit, 3141592);
+        assert_eq!(block.header.gas_used, 21000);
+        assert_eq!(block.header.difficulty, Uint256::from_uint64(131072));
+        assert_eq!(block.header.time, 1426516743);
+        assert_eq!(block.header.nonce, [0xa1, 0x3a, 0x5a, 0x8c, 0x8f, 0x2b, 0xb1, 0xc4]);
+        assert_eq!(block.hash(), hash("0x0a5843ac1cb04865017cb35a57b50b07084e5fcee39b5acadade33149f4fff9e"));
+        assert_eq!(block.transactions().len(), 1);
+        assert_eq!(
+            block.transactions()[0].hash(),
+            hash("0x77b19baa4de67e45a7b26e4a220bccdbb6731885aa9927064e239ca232023215")
+        );
+        assert_eq!(rlp::encode(&block), blob);
+    }
+
+    fn post_london_header() -> Header {
+        Header {
+            parent_hash: hash("0x0101010101010101010101010101010101010101010101010101010101010101"),
+            coinbase: common::Address([0xcb; 20]),
+            difficulty: Uint256::default(),
+            number: 0x1234,
+            gas_limit: 30_000_000,
+            gas_used: 21_000,
+            time: 1_700_000_000,
+            extra: vec![0xde, 0xad],
+            base_fee: Some(Uint256::from_uint64(7)),
+            withdrawals_hash: Some(EMPTY_WITHDRAWALS_ROOT),
+            blob_gas_used: Some(0x20000),
+            excess_blob_gas: Some(0x40000),
+            parent_beacon_root: Some(hash("0x0202020202020202020202020202020202020202020202020202020202020202")),
+            requests_hash: Some(hash("0x0303030303030303030303030303030303030303030303030303030303030303")),
+            ..Header::default()
+        }
+    }
+
+    #[test]
+    fn test_header_json_optional_fields() {
+        let header = post_london_header();
+        let json = serde_json::to_value(&header).unwrap();
+        assert_eq!(json["baseFeePerGas"], "0x7");
+        assert_eq!(json["withdrawalsRoot"], "0x56e81f171bcc55a6ff8345e692c0f86e5b48e01b996cadc001622fb5e363b421");
+        assert_eq!(json["blobGasUsed"], "0x20000");
+        assert_eq!(json["excessBlobGas"], "0x40000");
+        assert_eq!(
+            json["parentBeaconBlockRoot"],
+            "0x0202020202020202020202020202020202020202020202020202020202020202"
+        );
+        assert_eq!(json["requestsHash"], "0x0303030303030303030303030303030303030303030303030303030303030303");
+
+        let dec: Header = serde_json::from_value(json).unwrap();
+        assert_eq!(dec, header);
+        assert_eq!(dec.hash(), header.hash());
+
+        // Legacy headers leave the optional fields out entirely.
+        let legacy: Header = serde_json::from_str(MAINNET_BLOCK_1).unwrap();
+        let json = serde_json::to_value(&legacy).unwrap();
+        for key in ["baseFeePerGas", "withdrawalsRoot", "blobGasUsed", "excessBlobGas", "parentBeaconBlockRoot", "requestsHash"] {
+            assert!(json.get(key).is_none(), "{key} present in legacy header");
+        }
+        assert_eq!(serde_json::from_value::<Header>(json).unwrap(), legacy);
+    }
+
+    #[test]
+    fn test_header_rlp_optional_fields() {
+        let full = post_london_header();
+        // Every fork prefix of the optional fields must survive a round trip.
+        let mut headers = vec![full.clone()];
+        let mut h = full;
+        h.requests_hash = None;
+        headers.push(h.clone());
+        h.parent_beacon_root = None;
+        h.excess_blob_gas = None;
+        h.blob_gas_used = None;
+        headers.push(h.clone());
+        h.withdrawals_hash = None;
+        headers.push(h.clone());
+        h.base_fee = None;
+        headers.push(h);
+
+        for header in headers {
+            let enc = rlp::encode(&header);
+            let dec: Header = rlp::decode(&enc).unwrap();
+            assert_eq!(dec, header);
+        }
+    }
+}
@@ -0,0 +1,975 @@
+// Package blockchain maintains the chain of imported blocks.
+//
+// BlockChain stores blocks, headers, receipts and total difficulties by hash
+// and keeps the canonical number to hash index in sync with the current head.
+// Every block, side chain blocks included, is checked by the consensus engine
+// and executed on its parent's state before it is stored. Before the merge the
+// head is the block with the highest total difficulty; proof-of-stake blocks
+// only become the head when set externally with set_head. Every head change is
+// reported to the subscribers as removed and added blocks and logs.
+
+use std::fmt;
+use std::io;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Arc;
+
+use crate::block::{Block, Header};
+use crate::chain_config::ChainConfig;
+use crate::consensus::{ChainHeaderReader, ConsensusError, Engine};
+use crate::eip4844::calc_blob_fee;
+use crate::ethdb::{Batch, KeyValueStore};
+use crate::evm::GetHashFunc;
+use crate::genesis::{setup_genesis_block, Genesis, GenesisError};
+use crate::log::Log;
+use crate::memory_state_db::MemoryStateDB;
+use crate::pruner::{self, GcMode, Pruner};
+use crate::rawdb;
+use crate::receipt::{self, Receipt, ReceiptError};
+use crate::snapshot::{SnapshotError, Tree};
+use crate::state_db::StateDB;
+use crate::state_processor::{is_merged, process_block, ValidationError};
+use crate::transaction::Transaction;
+use crate::trie::{Database, TrieError};
+
+#[derive(Debug)]
+pub enum ChainError {
+    // UnknownAncestor is returned when validating a block requires an ancestor
+    // that is unknown.
+    UnknownAncestor(common::Hash),
+    UnknownBlock(common::Hash),
+    // MissingState is returned if the state of a block is not available.
+    MissingState(common::Hash),
+    // Consensus is returned if the consensus engine rejects a header.
+    Consensus(ConsensusError),
+    Validation(ValidationError),
+    Receipts(ReceiptError),
+    Genesis(GenesisError),
+    State(TrieError),
+    Snapshot(SnapshotError),
+    Io(io::Error),
+}
+
+impl fmt::Display for ChainError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ChainError::UnknownAncestor(hash) => write!(f, "unknown ancestor {:?}", hash),
+            ChainError::UnknownBlock(hash) => write!(f, "unknown block {:?}", hash),
+            ChainError::MissingState(root) => write!(f, "missing trie node for root {:?}", root),
+            ChainError::Consensus(err) => write!(f, "{}", err),
+            ChainError::Validation(err) => write!(f, "{}", err),
+            ChainError::Receipts(err) => write!(f, "{}", err),
+            ChainError::Genesis(err) => write!(f, "{}", err),
+            ChainError::State(err) => write!(f, "state: {:?}", err),
+            ChainError::Snapshot(err) => write!(f, "snapshot: {:?}", err),
+            ChainError::Io(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl std::error::Error for ChainError {}
+
+impl From<io::Error> for ChainError {
+    fn from(err: io::Error) -> Self {
+        ChainError::Io(err)
+    }
+}
+
+impl From<TrieError> for ChainError {
+    fn from(err: TrieError) -> Self {
+        ChainError::State(err)
+    }
+}
+
+impl From<SnapshotError> for ChainError {
+    fn from(err: SnapshotError) -> Self {
+        ChainError::Snapshot(err)
+    }
+}
+
+impl From<ConsensusError> for ChainError {
+    fn from(err: ConsensusError) -> Self {
+        ChainError::Consensus(err)
+    }
+}
+
+impl From<ValidationError> for ChainError {
+    fn from(err: ValidationError) -> Self {
+        ChainError::Validation(err)
+    }
+}
+
+impl From<GenesisError> for ChainError {
+    fn from(err: GenesisError) -> Self {
+        ChainError::Genesis(err)
+    }
+}
+
+// ChainEvent is sent to the subscribers whenever the canonical chain changes.
+// On a reorganisation the blocks of the old chain are removed from the
+// highest one down, then the blocks of the new chain are added from the
+// lowest one up, and finally the new head is announced.
+#[derive(Debug, Clone)]
+pub enum ChainEvent {
+    BlockRemoved(Block),
+    BlockAdded(Block),
+    // LogsRemoved carries the logs of a removed block, marked as removed.
+    LogsRemoved(Vec<Log>),
+    LogsAdded(Vec<Log>),
+    ChainHead(Header),
+}
+
+// get_hash_fn returns a GetHashFunc which retrieves header hashes by number
+// walking back from the given header, so that it also works on side chains.
+pub fn get_hash_fn(db: Arc<dyn KeyValueStore>, header: &Header) -> GetHashFunc {
+    let (head_hash, head_number) = (header.hash(), header.number);
+    Box::new(move |n| {
+        if n > head_number {
+            return common::Hash::default();
+        }
+        let (mut hash, mut number) = (head_hash, head_number);
+        while number > n {
+            match rawdb::read_header(db.as_ref(), &hash, number) {
+                Ok(Some(header)) => hash = header.parent_hash,
+                _ => return common::Hash::default(),
+            }
+            number -= 1;
+        }
+        hash
+    })
+}
+
+// BlockChain represents the canonical chain given a database with a genesis
+// block.
+pub struct BlockChain {
+    db: Arc<dyn KeyValueStore>,
+    chain_config: ChainConfig,
+    engine: Box<dyn Engine>,
+    genesis: Block,
+    // current_block is the head of the canonical chain.
+    current_block: Block,
+    subscribers: Vec<Sender<ChainEvent>>,
+    // snaps serves the account and storage reads of the recent states.
+    snaps: Arc<Tree>,
+    // pruner drops the trie nodes of expired states after each commit.
+    pruner: Pruner,
+}
+
+impl BlockChain {
+    // new returns a fully initialised block chain using information available
+    // in the database, writing the genesis block first if the database is
+    // empty. Headers are verified and blocks finalized by engine, and the
+    // states of old blocks are garbage collected according to gc_mode.
+    pub fn new(
+        db: Arc<dyn KeyValueStore>,
+        genesis: &Genesis,
+        engine: Box<dyn Engine>,
+        gc_mode: GcMode,
+    ) -> Result<Self, ChainError> {
+        let genesis_block = setup_genesis_block(db.clone(), genesis)?;
+        let head =
+            rawdb::read_head_block_hash(db.as_ref())?.unwrap_or_else(|| genesis_block.hash());
+        let number =
+            rawdb::read_header_number(db.as_ref(), &head)?.ok_or(ChainError::UnknownBlock(head))?;
+        let current_block =
+            rawdb::read_block(db.as_ref(), &head, number)?.ok_or(ChainError::UnknownBlock(head))?;
+        let triedb = Database::with_disk(db.clone());
+        let snaps = Arc::new(Tree::new(db.clone(), &triedb, current_block.header.root)?);
+        let pruner = Pruner::new(gc_mode, db.clone())?;
+        Ok(BlockChain {
+            db,
+            chain_config: genesis.config.clone(),
+            engine,
+            genesis: genesis_block,
+            current_block,
+            subscribers: Vec::new(),
+            snaps,
+            pruner,
+        })
+    }
+
+    pub fn chain_config(&self) -> &ChainConfig {
+        &self.chain_config
+    }
+
+    pub fn engine(&self) -> &dyn Engine {
+        self.engine.as_ref()
+    }
+
+    pub fn database(&self) -> &Arc<dyn KeyValueStore> {
+        &self.db
+    }
+
+    pub fn genesis(&self) -> &Block {
+        &self.genesis
+    }
+
+    pub fn gc_mode(&self) -> GcMode {
+        self.pruner.mode()
+    }
+
+    // set_sweep_interval sets the number of expired states between two
+    // sweeps of the pruner.
+    pub fn set_sweep_interval(&mut self, interval: u64) {
+        self.pruner.set_sweep_interval(interval);
+    }
+
+    // compact sweeps the nodes of the expired states, if pruning, and
+    // reclaims the space of the deleted entries. It returns the number of
+    // deleted trie nodes.
+    pub fn compact(&self) -> Result<usize, ChainError> {
+        Ok(pruner::compact(self.pruner.mode(), self.db.clone())?)
+    }
+
+    // current_block retrieves the current head block of the canonical chain.
+    pub fn current_block(&self) -> &Block {
+        &self.current_block
+    }
+
+    pub fn current_header(&self) -> &Header {
+        &self.current_block.header
+    }
+
+    // subscribe registers a receiver of the chain events.
+    pub fn subscribe(&mut self) -> Receiver<ChainEvent> {
+        let (sender, receiver) = mpsc::channel();
+        self.subscribers.push(sender);
+        receiver
+    }
+
+    fn send(&mut self, event: ChainEvent) {
+        self.subscribers
+            .retain(|sub| sub.send(event.clone()).is_ok());
+    }
+
+    // get_header retrieves a block header from the database by hash.
+    pub fn get_header(&self, hash: &common::Hash) -> Result<Option<Header>, ChainError> {
+        match rawdb::read_header_number(self.db.as_ref(), hash)? {
+            Some(number) => Ok(rawdb::read_header(self.db.as_ref(), hash, number)?),
+            None => Ok(None),
+        }
+    }
+
+    // get_block retrieves a block from the database by hash.
+    pub fn get_block(&self, hash: &common::Hash) -> Result<Option<Block>, ChainError> {
+        match rawdb::read_header_number(self.db.as_ref(), hash)? {
+            Some(number) => Ok(rawdb::read_block(self.db.as_ref(), hash, number)?),
+            None => Ok(None),
+        }
+    }
+
+    // get_canonical_hash returns the canonical hash for a given block number.
+    pub fn get_canonical_hash(&self, number: u64) -> Result<Option<common::Hash>, ChainError> {
+        Ok(rawdb::read_canonical_hash(self.db.as_ref(), number)?)
+    }
+
+    // get_header_by_number retrieves a block header from the database by
+    // number, if it's in the canonical chain.
+    pub fn get_header_by_number(&self, number: u64) -> Result<Option<Header>, ChainError> {
+        match self.get_canonical_hash(number)? {
+            Some(hash) => Ok(rawdb::read_header(self.db.as_ref(), &hash, number)?),
+            None => Ok(None),
+        }
+    }
+
+    // get_block_by_number retrieves a block from the database by number, if
+    // it's in the canonical chain.
+    pub fn get_block_by_number(&self, number: u64) -> Result<Option<Block>, ChainError> {
+        match self.get_canonical_hash(number)? {
+            Some(hash) => Ok(rawdb::read_block(self.db.as_ref(), &hash, number)?),
+            None => Ok(None),
+        }
+    }
+
+    // get_td retrieves a block's total difficulty in the canonical chain from
+    // the database by hash.
+    pub fn get_td(&self, hash: &common::Hash) -> Result<Option<uint256::Uint256>, ChainError> {
+        match rawdb::read_header_number(self.db.as_ref(), hash)? {
+            Some(number) => Ok(rawdb::read_td(self.db.as_ref(), hash, number)?),
+            None => Ok(None),
+        }
+    }
+
+    // get_receipts retrieves the receipts for all transactions in a given
+    // block, with their derived fields filled in.
+    pub fn get_receipts(&self, hash: &common::Hash) -> Result<Option<Vec<Receipt>>, ChainError> {
+        let block = match self.get_block(hash)? {
+            Some(block) => block,
+            None => return Ok(None),
+        };
+        let mut receipts = match rawdb::read_receipts(self.db.as_ref(), hash, block.number())? {
+            Some(receipts) => receipts,
+            None => return Ok(None),
+        };
+        let blob_gas_price = block.header.excess_blob_gas.map(calc_blob_fee);
+        receipt::derive_fields(
+            &mut receipts,
+            self.chain_config.chain_id,
+            *hash,
+            block.number(),
+            block.header.base_fee.as_ref(),
+            blob_gas_price.as_ref(),
+            block.transactions(),
+        )
+        .map_err(ChainError::Receipts)?;
+        Ok(Some(receipts))
+    }
+
+    // get_transaction retrieves a canonical transaction by hash along with
+    // the hash and number of its block and its index in the block.
+    pub fn get_transaction(
+        &self,
+        hash: &common::Hash,
+    ) -> Result<Option<(Transaction, common::Hash, u64, u64)>, ChainError> {
+        let number = match rawdb::read_tx_lookup_entry(self.db.as_ref(), hash)? {
+            Some(number) => number,
+            None => return Ok(None),
+        };
+        let block = match self.get_block_by_number(number)? {
+            Some(block) => block,
+            None => return Ok(None),
+        };
+        let found = block
+            .transactions()
+            .iter()
+            .position(|tx| tx.hash() == *hash)
+            .map(|index| {
+                (
+                    block.transactions()[index].clone(),
+                    block.hash(),
+                    number,
+                    index as u64,
+                )
+            });
+        Ok(found)
+    }
+
+    // has_state checks if the state trie of the given root is fully present
+    // in the database or not.
+    pub fn has_state(&self, root: &common::Hash) -> bool {
+        self.state_at(root).is_ok()
+    }
+
+    // snapshots returns the snapshot tree of the recent states.
+    pub fn snapshots(&self) -> &Arc<Tree> {
+        &self.snaps
+    }
+
+    // state_at returns a new mutable state based on a particular point in
+    // time. Reads are served by the snapshot layer of root if there is one.
+    pub fn state_at(&self, root: &common::Hash) -> Result<Box<dyn StateDB>, ChainError> {
+        match MemoryStateDB::open(*root, Database::with_disk(self.db.clone())) {
+            Ok(state) => Ok(Box::new(state.with_snapshots(self.snaps.clone()))),
+            Err(TrieError::MissingNode(_)) => Err(ChainError::MissingState(*root)),
+            Err(err) => Err(ChainError::State(err)),
+        }
+    }
+
+    // state returns a new mutable state based on the current head block.
+    pub fn state(&self) -> Result<Box<dyn StateDB>, ChainError> {
+        self.state_at(&self.current_block.header.root)
+    }
+
+    // get_hash_fn returns the BLOCKHASH lookup for blocks built on header.
+    pub fn get_hash_fn(&self, header: &Header) -> GetHashFunc {
+        get_hash_fn(self.db.clone(), header)
+    }
+
+    // is_executed reports whether the block was processed, that is whether
+    // its receipts and post-state are available.
+    fn is_executed(&self, block: &Block) -> Result<bool, ChainError> {
+        let receipts = rawdb::read_receipts_rlp(self.db.as_ref(), &block.hash(), block.number())?;
+        Ok(receipts.is_some() && self.has_state(&block.header.root))
+    }
+
+    // execute processes the block on top of its parent's state, commits the
+    // resulting state and writes the receipts. The committed root is handed
+    // to the pruner, which may sweep the states that fell out of its window.
+    fn execute(&mut self, block: &Block) -> Result<(), ChainError> {
+        let parent = self
+            .get_header(&block.parent_hash())?
+            .ok_or(ChainError::UnknownAncestor(block.parent_hash()))?;
+        let state = self.state_at(&parent.root)?;
+        let result = process_block(
+            &self.chain_config,
+            self.engine.as_ref(),
+            state,
+            block,
+            self.get_hash_fn(&parent),
+        )?;
+
+        let header = &block.header;
+        let rules = self
+            .chain_config
+            .rules(header.number, is_merged(header), header.time);
+        let mut state = result.state;
+        let root = state.commit(rules.is_eip158)?;
+        self.pruner.on_commit(block.number(), root)?;
+
+        let mut batch = Batch::new();
+        rawdb::write_receipts(&mut batch, &block.hash(), block.number(), &result.receipts);
+        self.db.write_batch(&batch)?;
+        Ok(())
+    }
+
+    // insert_chain attempts to insert the given batch of blocks in to the
+    // canonical chain or, otherwise, create a fork. It returns the index of
+    // the failing block along with the error.
+    pub fn insert_chain(&mut self, blocks: &[Block]) -> Result<(), (usize, ChainError)> {
+        for (i, block) in blocks.iter().enumerate() {
+            self.insert_block(block).map_err(|err| (i, err))?;
+        }
+        Ok(())
+    }
+
+    // insert_block imports a block. Every block, side chain blocks included,
+    // is executed and validated on its parent's state before it is stored, so
+    // a known block is always a valid one. Before the merge the block becomes
+    // the new head if it has a higher total difficulty than the current head.
+    pub fn insert_block(&mut self, block: &Block) -> Result<(), ChainError> {
+        let hash = block.hash();
+        // Ignore the block if it's already known
+        if rawdb::read_header_number(self.db.as_ref(), &hash)?.is_some() {
+            return Ok(());
+        }
+        let parent = self
+            .get_header(&block.parent_hash())?
+            .ok_or(ChainError::UnknownAncestor(block.parent_hash()))?;
+        self.engine.verify_header(self, &block.header)?;
+        let parent_td = self
+            .get_td(&parent.hash())?
+            .ok_or(ChainError::UnknownAncestor(parent.hash()))?;
+        let td = parent_td
+            .checked_add(&block.header.difficulty)
+            .unwrap_or_default();
+
+        let mut batch = Batch::new();
+        rawdb::write_td(&mut batch, &hash, block.number(), &td);
+        rawdb::write_block(&mut batch, block);
+        // Process the block and make sure it's valid before storing it
+        self.execute(block)?;
+        self.db.write_batch(&batch)?;
+
+        // Proof-of-stake blocks are chosen as head by the consensus layer.
+        if is_merged(&block.header) {
+            return Ok(());
+        }
+        let current_td = self.get_td(&self.current_block.hash())?.unwrap_or_default();
+        if td > current_td {
+            self.reorg(block.clone())?;
+        }
+        Ok(())
+    }
+
+    // set_head makes the given known block the head of the canonical chain,
+    // reorganising the chain if needed. This is how the head is chosen after
+    // the merge.
+    pub fn set_head(&mut self, hash: &common::Hash) -> Result<(), ChainError> {
+        let block = self
+            .get_block(hash)?
+            .ok_or(ChainError::UnknownBlock(*hash))?;
+        self.reorg(block)
+    }
+
+    // block_logs returns the logs of the given block with their derived
+    // fields, marked as removed if requested.
+    fn block_logs(&self, block: &Block, removed: bool) -> Result<Vec<Log>, ChainError> {
+        let receipts = self.get_receipts(&block.hash())?.unwrap_or_default();
+        let mut logs: Vec<Log> = receipts
+            .into_iter()
+            .flat_map(|receipt| receipt.logs)
+            .collect();
+        for log in logs.iter_mut() {
+            log.removed = removed;
+        }
+        Ok(logs)
+    }
+
+    // reorg takes the current head and the new head, rewinds the canonical
+    // chain to their common ancestor and rebuilds it up to the new head,
+    // executing the blocks of the new chain that weren't processed yet. It
+    // then emits the events for the removed and added blocks and logs.
+    fn reorg(&mut self, new_head: Block) -> Result<(), ChainError> {
+        if new_head.hash() == self.current_block.hash() {
+            return Ok(());
+        }
+        // Collect the blocks of the new chain down to the first canonical
+        // block, which is the common ancestor.
+        let mut new_chain = Vec::new();
+        let mut block = new_head.clone();
+        while self.get_canonical_hash(block.number())? != Some(block.hash()) {
+            let parent = self
+                .get_block(&block.parent_hash())?
+                .ok_or(ChainError::UnknownAncestor(block.parent_hash()))?;
+            new_chain.push(block);
+            block = parent;
+        }
+        let ancestor = block;
+
+        // Collect the canonical blocks above the common ancestor
+        let mut old_chain = Vec::new();
+        for number in (ancestor.number() + 1..=self.current_block.number()).rev() {
+            if let Some(block) = self.get_block_by_number(number)? {
+                old_chain.push(block);
+            }
+        }
+
+        // Execute the new chain from the ancestor up, failing before touching
+        // the canonical chain if any of its blocks is invalid.
+        for block in new_chain.iter().rev() {
+            if !self.is_executed(block)? {
+                self.execute(block)?;
+            }
+        }
+
+        let mut batch = Batch::new();
+        for block in old_chain.iter() {
+            for tx in block.transactions() {
+                rawdb::delete_tx_lookup_entry(&mut batch, &tx.hash());
+            }
+            if block.number() > new_head.number() {
+                rawdb::delete_canonical_hash(&mut batch, block.number());
+            }
+        }
+        for block in new_chain.iter().rev() {
+            rawdb::write_canonical_hash(&mut batch, &block.hash(), block.number());
+            rawdb::write_tx_lookup_entries(&mut batch, block);
+        }
+        rawdb::write_head_block_hash(&mut batch, &new_head.hash());
+        self.db.write_batch(&batch)?;
+        self.current_block = new_head;
+
+        for block in old_chain {
+            let logs = self.block_logs(&block, true)?;
+            self.send(ChainEvent::BlockRemoved(block));
+            if !logs.is_empty() {
+                self.send(ChainEvent::LogsRemoved(logs));
+            }
+        }
+        for block in new_chain.into_iter().rev() {
+            let logs = self.block_logs(&block, false)?;
+            self.send(ChainEvent::BlockAdded(block));
+            if !logs.is_empty() {
+                self.send(ChainEvent::LogsAdded(logs));
+            }
+        }
+        let head = self.current_block.header.clone();
+        self.send(ChainEvent::ChainHead(head));
+        Ok(())
+    }
+}
+
+impl ChainHeaderReader for BlockChain {
+    fn config(&self) -> &ChainConfig {
+        &self.chain_config
+    }
+
+    fn current_header(&self) -> Header {
+        self.current_block.header.clone()
+    }
+
+    fn get_header(&self, hash: &common::Hash, number: u64) -> Option<Header> {
+        rawdb::read_header(self.db.as_ref(), hash, number)
+            .ok()
+            .flatten()
+    }
+
+    fn get_header_by_number(&self, number: u64) -> Option<Header> {
+        BlockChain::get_header_by_number(self, number)
+            .ok()
+            .flatten()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    use crate::consensus::Beacon;
+    use crate::crypto::{self, PrivateKey};
+    use crate::ethdb::MemoryDatabase;
+    use crate::genesis::GenesisAccount;
+    use crate::miner;
+    use crate::pow::ProofOfWork;
+    use crate::pruner::DEFAULT_SWEEP_INTERVAL;
+    use crate::rlp;
+    use crate::snapshot::Layer;
+    use crate::transaction::{self, LegacyTx, TxData};
+    use uint256::Uint256;
+
+    // CONTRACT stores 0x2a in slot 0 when called.
+    const CONTRACT: common::Address = common::Address([0xcc; 20]);
+    // LOGGER emits an empty LOG0 when called.
+    const LOGGER: common::Address = common::Address([0xdd; 20]);
+
+    fn test_key() -> PrivateKey {
+        crypto::to_ecdsa(&[1; 32]).unwrap()
+    }
+
+    fn test_chain(gc_mode: GcMode) -> BlockChain {
+        let mut alloc = HashMap::new();
+        alloc.insert(
+            common::Address([0x11; 20]),
+            GenesisAccount {
+                balance: Uint256::from_uint64(1_000_000),
+                ..Default::default()
+            },
+        );
+        alloc.insert(
+            test_key().address(),
+            GenesisAccount {
+                balance: Uint256::from_uint64(1_000_000_000),
+                ..Default::default()
+            },
+        );
+        alloc.insert(
+            CONTRACT,
+            GenesisAccount {
+                code: vec![0x60, 0x2a, 0x60, 0x00, 0x55, 0x00],
+                storage: HashMap::from([(slot(1), slot(7))]),
+                ..Default::default()
+            },
+        );
+        alloc.insert(
+            LOGGER,
+            GenesisAccount {
+                code: vec![0x60, 0x00, 0x60, 0x00, 0xa0, 0x00],
+                ..Default::default()
+            },
+        );
+        let genesis = Genesis {
+            config: ChainConfig {
+                chain_id: 1337,
+                ..Default::default()
+            },
+            alloc,
+            ..Default::default()
+        };
+        BlockChain::new(Arc::new(MemoryDatabase::new()), &genesis, Box::new(ProofOfWork::fake()), gc_mode).unwrap()
+    }
+
+    fn slot(n: u8) -> common::Hash {
+        let mut hash = common::Hash::default();
+        hash.0[31] = n;
+        hash
+    }
+
+    // call_contract returns a signed call of CONTRACT.
+    fn call_contract(nonce: u64) -> Transaction {
+        call(nonce, CONTRACT)
+    }
+
+    fn call(nonce: u64, to: common::Address) -> Transaction {
+        let tx = Transaction::new(TxData::Legacy(LegacyTx {
+            nonce,
+            gas_price: Uint256::from_uint64(1),
+            gas: 100_000,
+            to: Some(to),
+            ..Default::default()
+        }));
+        transaction::sign_tx(&tx, &Uint256::from_uint64(1337), &test_key()).unwrap()
+    }
+
+    // make_block builds an empty sealed block on top of parent.
+    fn make_block(chain: &BlockChain, parent: &Header, timestamp: u64) -> Block {
+        make_block_with_txs(chain, parent, timestamp, Vec::new())
+    }
+
+    fn make_block_with_txs(chain: &BlockChain, parent: &Header, timestamp: u64, txs: Vec<Transaction>) -> Block {
+        let header = miner::prepare_header(chain.config(), &miner::Config::default(), parent, timestamp);
+        let state = chain.state_at(&parent.root).unwrap();
+        miner::build_block(chain, &ProofOfWork::fake(), header, state, txs, chain.get_hash_fn(parent))
+            .unwrap()
+            .block
+    }
+
+    #[test]
+    fn test_insert_side_chain() {
+        let mut chain = test_chain(GcMode::Archive);
+        let genesis = chain.current_header().clone();
+
+        let a1 = make_block(&chain, &genesis, genesis.time + 10);
+        chain.insert_block(&a1).unwrap();
+        assert_eq!(chain.current_header().hash(), a1.hash());
+
+        // A valid side chain block is stored without changing the head
+        let b1 = make_block(&chain, &genesis, genesis.time + 20);
+        chain.insert_block(&b1).unwrap();
+        assert_eq!(chain.current_header().hash(), a1.hash());
+        assert!(chain.get_block(&b1.hash()).unwrap().is_some());
+        assert!(chain.is_executed(&b1).unwrap());
+
+        // A side chain block with a bad state root is rejected and not stored,
+        // so importing it again fails as well.
+        let mut bad = b1.clone();
+        bad.header.root = common::Hash([0x01; 32]);
+        let bad = ProofOfWork::fake().seal(&chain, bad).unwrap();
+        assert!(chain.insert_block(&bad).is_err());
+        assert!(chain.get_block(&bad.hash()).unwrap().is_none());
+        assert!(chain.insert_block(&bad).is_err());
+        assert_eq!(chain.current_header().hash(), a1.hash());
+    }
+
+    #[test]
+    fn test_reorg() {
+        let mut chain = test_chain(GcMode::Archive);
+        let genesis = chain.current_header().clone();
+
+        // Chain A: a1 (logs) <- a2
+        let a1 = make_block_with_txs(&chain, &genesis, genesis.time + 10, vec![call(0, LOGGER)]);
+        chain.insert_block(&a1).unwrap();
+        let a2 = make_block(&chain, &a1.header, a1.header.time + 10);
+        chain.insert_block(&a2).unwrap();
+        let a_tx = a1.transactions()[0].hash();
+
+        // Chain B: b1 <- b2 (logs) <- b3, heavier than chain A only once b3
+        // is imported.
+        let events = chain.subscribe();
+        let b1 = make_block_with_txs(&chain, &genesis, genesis.time + 10, vec![call_contract(0)]);
+        chain.insert_block(&b1).unwrap();
+        let b2 = make_block_with_txs(&chain, &b1.header, b1.header.time + 10, vec![call(1, LOGGER)]);
+        chain.insert_block(&b2).unwrap();
+        assert_eq!(chain.current_header().hash(), a2.hash());
+        assert!(events.try_recv().is_err());
+        let b3 = make_block(&chain, &b2.header, b2.header.time + 10);
+        chain.insert_block(&b3).unwrap();
+        let b_tx = b2.transactions()[0].hash();
+
+        assert_eq!(chain.current_header().hash(), b3.hash());
+        for block in [&b1, &b2, &b3] {
+            assert_eq!(chain.get_canonical_hash(block.number()).unwrap(), Some(block.hash()));
+        }
+        assert_eq!(rawdb::read_tx_lookup_entry(chain.db.as_ref(), &a_tx).unwrap(), None);
+        assert_eq!(rawdb::read_tx_lookup_entry(chain.db.as_ref(), &b_tx).unwrap(), Some(2));
+
+        // The old chain is removed from the top down, the new one is added
+        // from the bottom up.
+        let events: Vec<ChainEvent> = events.try_iter().collect();
+        assert_eq!(events.len(), 8);
+        assert!(matches!(&events[0], ChainEvent::BlockRemoved(b) if b.hash() == a2.hash()));
+        assert!(matches!(&events[1], ChainEvent::BlockRemoved(b) if b.hash() == a1.hash()));
+        match &events[2] {
+            ChainEvent::LogsRemoved(logs) => {
+                assert_eq!(logs.len(), 1);
+                assert_eq!(logs[0].address, LOGGER);
+                assert_eq!(logs[0].block_hash, a1.hash());
+                assert_eq!(logs[0].tx_hash, a_tx);
+                assert!(logs[0].removed);
+            }
+            event => panic!("unexpected event {:?}", event),
+        }
+        assert!(matches!(&events[3], ChainEvent::BlockAdded(b) if b.hash() == b1.hash()));
+        assert!(matches!(&events[4], ChainEvent::BlockAdded(b) if b.hash() == b2.hash()));
+        match &events[5] {
+            ChainEvent::LogsAdded(logs) => {
+                assert_eq!(logs.len(), 1);
+                assert_eq!(logs[0].address, LOGGER);
+                assert_eq!(logs[0].block_hash, b2.hash());
+                assert_eq!(logs[0].block_number, 2);
+                assert_eq!(logs[0].tx_hash, b_tx);
+                assert!(!logs[0].removed);
+            }
+            event => panic!("unexpected event {:?}", event),
+        }
+        assert!(matches!(&events[6], ChainEvent::BlockAdded(b) if b.hash() == b3.hash()));
+        assert!(matches!(&events[7], ChainEvent::ChainHead(h) if h.hash() == b3.hash()));
+
+        // Going back to the shorter chain drops the canonical entry above it
+        chain.set_head(&a2.hash()).unwrap();
+        assert_eq!(chain.get_canonical_hash(1).unwrap(), Some(a1.hash()));
+        assert_eq!(chain.get_canonical_hash(2).unwrap(), Some(a2.hash()));
+        assert_eq!(chain.get_canonical_hash(3).unwrap(), None);
+        assert_eq!(rawdb::read_tx_lookup_entry(chain.db.as_ref(), &a_tx).unwrap(), Some(1));
+        assert_eq!(rawdb::read_tx_lookup_entry(chain.db.as_ref(), &b_tx).unwrap(), None);
+    }
+
+    // insert_blocks extends the head with n empty blocks and returns their
+    // state roots.
+    fn insert_blocks(chain: &mut BlockChain, n: u64) -> Vec<common::Hash> {
+        let mut roots = Vec::new();
+        for _ in 0..n {
+            let parent = chain.current_header().clone();
+            let block = make_block(chain, &parent, parent.time + 10);
+            chain.insert_block(&block).unwrap();
+            roots.push(block.header.root);
+        }
+        roots
+    }
+
+    #[test]
+    fn test_archive_keeps_all_states() {
+        let mut chain = test_chain(GcMode::Archive);
+        chain.set_sweep_interval(1);
+        let genesis_root = chain.current_header().root;
+        let roots = insert_blocks(&mut chain, 6);
+
+        assert!(chain.has_state(&genesis_root));
+        for root in roots.iter() {
+            assert!(chain.has_state(root));
+        }
+        assert_eq!(chain.compact().unwrap(), 0);
+        assert!(chain.has_state(&genesis_root));
+    }
+
+    #[test]
+    fn test_pruned_sweeps_expired_states() {
+        let mut chain = test_chain(GcMode::Pruned { retain: 2 });
+        chain.set_sweep_interval(1);
+        let genesis_root = chain.current_header().root;
+        let roots = insert_blocks(&mut chain, 6);
+
+        // Only the states of the last two blocks survive the sweeps
+        assert!(!chain.has_state(&genesis_root));
+        for root in roots[..4].iter() {
+            assert!(matches!(chain.state_at(root), Err(ChainError::MissingState(r)) if r == *root));
+        }
+        let coinbase = chain.current_header().coinbase;
+        for root in roots[4..].iter() {
+            let state = chain.state_at(root).unwrap();
+            assert_eq!(state.get_balance(&common::Address([0x11; 20])), Uint256::from_uint64(1_000_000));
+            assert!(!state.get_balance(&coinbase).is_zero());
+        }
+
+        // Nothing is left to sweep, compacting keeps the retained states
+        assert_eq!(chain.compact().unwrap(), 0);
+        for root in roots[4..].iter() {
+            assert!(chain.has_state(root));
+        }
+    }
+
+    #[test]
+    fn test_pruned_compact() {
+        let mut chain = test_chain(GcMode::Pruned { retain: 2 });
+        chain.set_sweep_interval(DEFAULT_SWEEP_INTERVAL);
+        let genesis_root = chain.current_header().root;
+        let roots = insert_blocks(&mut chain, 4);
+
+        // The expired states are kept until the next sweep
+        assert!(chain.has_state(&genesis_root));
+        assert!(chain.has_state(&roots[0]));
+        assert!(chain.compact().unwrap() > 0);
+        assert!(!chain.has_state(&genesis_root));
+        assert!(!chain.has_state(&roots[1]));
+        assert!(chain.has_state(&roots[2]));
+        assert!(chain.has_state(&roots[3]));
+    }
+
+    #[test]
+    fn test_state_reads_through_diff_layers() {
+        let mut chain = test_chain(GcMode::Archive);
+        let genesis = chain.current_header().clone();
+        let sender = test_key().address();
+
+        let b1 = make_block_with_txs(&chain, &genesis, genesis.time + 10, vec![call_contract(0)]);
+        assert_eq!(b1.transactions().len(), 1);
+        chain.insert_block(&b1).unwrap();
+        let b2 = make_block(&chain, &b1.header, b1.header.time + 10);
+        chain.insert_block(&b2).unwrap();
+
+        // The commits stacked a diff layer per block on the genesis disk layer
+        let layer = chain.snapshots().snapshot(&b2.header.root).unwrap();
+        assert_eq!(layer.parent().unwrap().root(), b1.header.root);
+        assert!(matches!(chain.snapshots().snapshot(&genesis.root).unwrap().as_ref(), Layer::Disk(_)));
+        let value = layer
+            .storage(&crypto::keccak256_hash(CONTRACT.as_bytes()), &crypto::keccak256_hash(slot(0).as_bytes()))
+            .unwrap();
+        assert_eq!(value, Some(rlp::encode(&Uint256::from_uint64(0x2a))));
+
+        // Accounts and slots written by the block are read from the diff
+        // layers, untouched ones fall through to the disk layer.
+        let state = chain.state_at(&b2.header.root).unwrap();
+        assert_eq!(state.get_nonce(&sender), 1);
+        assert_eq!(state.get_state(&CONTRACT, &slot(0)), slot(0x2a));
+        assert_eq!(state.get_state(&CONTRACT, &slot(1)), slot(7));
+        assert_eq!(state.get_balance(&common::Address([0x11; 20])), Uint256::from_uint64(1_000_000));
+
+        // The parent states still read their own values
+        let state = chain.state_at(&genesis.root).unwrap();
+        assert_eq!(state.get_nonce(&sender), 0);
+        assert_eq!(state.get_state(&CONTRACT, &slot(0)), common::Hash::default());
+        let state = chain.state_at(&b1.header.root).unwrap();
+        assert_eq!(state.get_state(&CONTRACT, &slot(0)), slot(0x2a));
+    }
+
+    // PUSH0_STORER stores 0x2a in slot 0 using the Shanghai PUSH0 opcode.
+    const PUSH0_STORER: common::Address = common::Address([0xee; 20]);
+
+    #[test]
+    fn test_beacon_chain() {
+        let mut alloc = HashMap::new();
+        alloc.insert(
+            test_key().address(),
+            GenesisAccount {
+                balance: Uint256::from_uint64(u64::MAX),
+                ..Default::default()
+            },
+        );
+        alloc.insert(
+            PUSH0_STORER,
+            GenesisAccount {
+                code: vec![0x60, 0x2a, 0x5f, 0x55, 0x00],
+                ..Default::default()
+            },
+        );
+        let genesis = Genesis {
+            config: ChainConfig {
+                chain_id: 1337,
+                homestead_block: Some(0),
+                eip150_block: Some(0),
+                eip155_block: Some(0),
+                eip158_block: Some(0),
+                byzantium_block: Some(0),
+                constantinople_block: Some(0),
+                petersburg_block: Some(0),
+                istanbul_block: Some(0),
+                berlin_block: Some(0),
+                london_block: Some(0),
+                shanghai_time: Some(0),
+                cancun_time: Some(0),
+                terminal_total_difficulty_passed: true,
+                ..Default::default()
+            },
+            difficulty: Some(Uint256::default()),
+            alloc,
+            ..Default::default()
+        };
+        let mut chain = BlockChain::new(Arc::new(MemoryDatabase::new()), &genesis, Box::new(Beacon), GcMode::Archive).unwrap();
+        let parent = chain.current_header().clone();
+        assert!(is_merged(&parent));
+
+        let tx = Transaction::new(TxData::Legacy(LegacyTx {
+            gas_price: Uint256::from_uint64(10_000_000_000),
+            gas: 100_000,
+            to: Some(PUSH0_STORER),
+            ..Default::default()
+        }));
+        let tx = transaction::sign_tx(&tx, &Uint256::from_uint64(1337), &test_key()).unwrap();
+        let header = miner::prepare_header(chain.config(), &miner::Config::default(), &parent, parent.time + 12);
+        let state = chain.state_at(&parent.root).unwrap();
+        let built = miner::build_block(&chain, &Beacon, header, state, vec![tx], chain.get_hash_fn(&parent)).unwrap();
+        let block = built.block;
+        assert!(is_merged(&block.header));
+        assert_eq!(block.header.nonce, [0u8; 8]);
+        assert_eq!(block.withdrawals(), Some(&[][..]));
+        assert_eq!(block.header.blob_gas_used, Some(0));
+        assert!(block.header.excess_blob_gas.is_some());
+        assert!(block.header.parent_beacon_root.is_some());
+        assert!(built.receipts[0].succeeded());
+
+        // Proof-of-stake blocks are imported without becoming the head until
+        // set_head picks them.
+        chain.insert_block(&block).unwrap();
+        assert_eq!(chain.current_header().hash(), parent.hash());
+        chain.set_head(&block.hash()).unwrap();
+        assert_eq!(chain.current_header().hash(), block.hash());
+        assert_eq!(chain.state().unwrap().get_state(&PUSH0_STORER, &slot(0)), slot(0x2a));
+
+        // A proof-of-work header is rejected by the Beacon engine
+        let mut bad = block.clone();
+        bad.header.difficulty = Uint256::one();
+        assert!(chain.insert_block(&bad).is_err());
+        let mut bad = block;
+        bad.header.nonce = [1; 8];
+        assert!(chain.insert_block(&bad).is_err());
+    }
+}
@@ -0,0 +1,212 @@
+// Bloom represents a 2048 bit bloom filter over the log addresses and topics
+// of a block or receipt.
+
+use std::fmt;
+use std::ops::BitOrAssign;
+
+use serde::{de::Error, Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::crypto;
+use crate::hexutil;
+use crate::log::Log;
+use crate::receipt::Receipt;
+use crate::rlp::{self, Decodable, DecoderError, Encodable, Rlp};
+
+// BLOOM_BYTE_LENGTH represents the number of bytes used in a header log bloom.
+pub const BLOOM_BYTE_LENGTH: usize = 256;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct Bloom(pub [u8; BLOOM_BYTE_LENGTH]);
+
+impl Default for Bloom {
+    fn default() -> Self {
+        Bloom([0u8; BLOOM_BYTE_LENGTH])
+    }
+}
+
+impl fmt::Debug for Bloom {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Bloom({})", hexutil::encode(&self.0))
+    }
+}
+
+// BLOOM_BIT_LENGTH represents the number of bits used in a header log bloom.
+pub const BLOOM_BIT_LENGTH: usize = 8 * BLOOM_BYTE_LENGTH;
+
+impl Bloom {
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    // add adds d to the filter. Future calls of test(d) will return true.
+    pub fn add(&mut self, d: &[u8]) {
+        for (i, v) in bloom_values(d) {
+            self.0[i] |= v;
+        }
+    }
+
+    // test checks if the given topic is present in the bloom filter
+    pub fn test(&self, topic: &[u8]) -> bool {
+        bloom_values(topic).iter().all(|(i, v)| self.0[*i] & v == *v)
+    }
+}
+
+impl BitOrAssign for Bloom {
+    fn bitor_assign(&mut self, other: Bloom) {
+        for (b, o) in self.0.iter_mut().zip(other.0.iter()) {
+            *b |= o;
+        }
+    }
+}
+
+// bloom_values returns the bytes (index-value pairs) to set for the given
+// data: three bits selected by the low 11 bits of the first three byte pairs
+// of keccak256(data).
+fn bloom_values(data: &[u8]) -> [(usize, u8); 3] {
+    let hash = crypto::keccak256(data);
+    let mut values = [(0usize, 0u8); 3];
+    for (i, value) in values.iter_mut().enumerate() {
+        // The actual bits to flip
+        let v = 1u8 << (hash[2 * i + 1] & 0x07);
+        // The indices for the bytes to OR in
+        let bit = (u16::from_be_bytes([hash[2 * i], hash[2 * i + 1]]) & 0x7ff) as usize;
+        *value = (BLOOM_BYTE_LENGTH - (bit >> 3) - 1, v);
+    }
+    values
+}
+
+// logs_bloom returns the bloom of the addresses and topics of the given logs.
+pub fn logs_bloom(logs: &[Log]) -> Bloom {
+    let mut bloom = Bloom::default();
+    for log in logs {
+        bloom.add(&log.address.0);
+        for topic in &log.topics {
+            bloom.add(topic.as_bytes());
+        }
+    }
+    bloom
+}
+
+// create_bloom creates a bloom filter out of the given receipts' logs, as
+// stored in the block header.
+pub fn create_bloom(receipts: &[Receipt]) -> Bloom {
+    let mut bloom = Bloom::default();
+    for receipt in receipts {
+        bloom |= logs_bloom(&receipt.logs);
+    }
+    bloom
+}
+
+impl Encodable for Bloom {
+    fn rlp_append(&self, out: &mut Vec<u8>) {
+        rlp::append_bytes(&self.0, out);
+    }
+}
+
+impl Decodable for Bloom {
+    fn decode(rlp: &Rlp) -> Result<Self, DecoderError> {
+        let data = rlp.data()?;
+        if data.len() != BLOOM_BYTE_LENGTH {
+            return Err(DecoderError::RlpInvalidLength);
+        }
+        let mut bloom = [0u8; BLOOM_BYTE_LENGTH];
+        bloom.copy_from_slice(data);
+        Ok(Bloom(bloom))
+    }
+}
+
+impl Serialize for Bloom {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&hexutil::encode(&self.0))
+    }
+}
+
+impl<'de> Deserialize<'de> for Bloom {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        hexutil::decode_fixed::<BLOOM_BYTE_LENGTH>(&s)
+            .map(Bloom)
+            .map_err(D::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Bit positions derived by hand from the byte pairs of the well known
+    // hashes keccak256("") = c5d2 4601 86f7... and
+    // keccak256(zero32) = 290d ecd9 548b...
+    #[test]
+    fn test_bloom_values() {
+        assert_eq!(bloom_values(&[]), [(69, 0x04), (63, 0x02), (33, 0x80)]);
+        assert_eq!(bloom_values(&[0; 32]), [(222, 0x20), (100, 0x02), (110, 0x08)]);
+
+        let mut bloom = Bloom::default();
+        bloom.add(&[]);
+        let mut want = [0u8; BLOOM_BYTE_LENGTH];
+        want[69] = 0x04;
+        want[63] = 0x02;
+        want[33] = 0x80;
+        assert_eq!(bloom, Bloom(want));
+    }
+
+    // Taken from go-ethereum's TestBloom.
+    #[test]
+    fn test_bloom() {
+        let positive = ["testtest", "test", "hallo", "other"];
+        let negative = ["tes", "lo"];
+
+        let mut bloom = Bloom::default();
+        for data in positive {
+            bloom.add(data.as_bytes());
+        }
+        for data in positive {
+            assert!(bloom.test(data.as_bytes()), "expected {data} to test true");
+        }
+        for data in negative {
+            assert!(!bloom.test(data.as_bytes()), "expected {data} to test false");
+        }
+    }
+
+    // Taken from go-ethereum's TestBloomExtensively.
+    #[test]
+    fn test_bloom_extensively() {
+        let mut bloom = Bloom::default();
+        for i in 0..100 {
+            bloom.add(format!("xxxxxxxxxx data {i} yyyyyyyyyyyyyy").as_bytes());
+        }
+        assert_eq!(
+            crypto::keccak256_hash(bloom.as_bytes()),
+            common::Hash(
+                hexutil::decode_fixed::<32>("0xc8d3ca65cdb4874300a9e39475508f23ed6da09fdbc487f89a2dcf50b09eb263").unwrap()
+            )
+        );
+        // Re-adding the same data doesn't change the filter
+        let mut again = bloom;
+        for i in 0..100 {
+            again.add(format!("xxxxxxxxxx data {i} yyyyyyyyyyyyyy").as_bytes());
+        }
+        assert_eq!(again, bloom);
+    }
+
+    #[test]
+    fn test_logs_bloom() {
+        let log = Log {
+            address: common::Address([0x11; 20]),
+            topics: vec![common::Hash([0; 32])],
+            ..Default::default()
+        };
+        let bloom = logs_bloom(std::slice::from_ref(&log));
+        assert!(bloom.test(&log.address.0));
+        assert!(bloom.test(&[0; 32]));
+        assert!(bloom.as_bytes()[222] & 0x20 != 0);
+
+        let receipts = [
+            Receipt { logs: vec![log.clone()], ..Default::default() },
+            Receipt::default(),
+        ];
+        assert_eq!(create_bloom(&receipts), bloom);
+        assert_eq!(create_bloom(&[]), Bloom::default());
+    }
+}
@@ -0,0 +1,229 @@
+// Encoding, decoding and group operations on BLS12-381 as required by the
+// EIP-2537 precompiles. The arithmetic is delegated to blst; this module owns
+// the EVM ABI: 64 byte field elements with 16 zero bytes of padding, G1 points
+// as two field elements, G2 points as two Fp2 elements (c0 || c1) and 32 byte
+// big endian scalars. The point at infinity is encoded as all zeroes.
+
+use blst::*;
+
+use crate::errors::Error;
+
+pub const FP_ENCODED_LENGTH: usize = 64;
+pub const G1_ENCODED_LENGTH: usize = 2 * FP_ENCODED_LENGTH;
+pub const G2_ENCODED_LENGTH: usize = 4 * FP_ENCODED_LENGTH;
+pub const SCALAR_LENGTH: usize = 32;
+
+const PADDING_LENGTH: usize = 16;
+const FP_LENGTH: usize = 48;
+
+// MODULUS is the base field modulus p, big endian.
+const MODULUS: [u8; FP_LENGTH] = [
+    0x1a, 0x01, 0x11, 0xea, 0x39, 0x7f, 0xe6, 0x9a, 0x4b, 0x1b, 0xa7, 0xb6, 0x43, 0x4b, 0xac, 0xd7,
+    0x64, 0x77, 0x4b, 0x84, 0xf3, 0x85, 0x12, 0xbf, 0x67, 0x30, 0xd2, 0xa0, 0xf6, 0xb0, 0xf6, 0x24,
+    0x1e, 0xab, 0xff, 0xfe, 0xb1, 0x53, 0xff, 0xff, 0xb9, 0xfe, 0xff, 0xff, 0xff, 0xff, 0xaa, 0xab,
+];
+
+// decode_fp decodes a padded field element, rejecting non-zero padding and
+// values that are not reduced modulo p.
+pub fn decode_fp(input: &[u8]) -> Result<blst_fp, Error> {
+    if input.len() != FP_ENCODED_LENGTH {
+        return Err(Error::InvalidPrecompileInput);
+    }
+    if input[..PADDING_LENGTH].iter().any(|b| *b != 0) {
+        return Err(Error::InvalidPrecompileInput);
+    }
+    let value = &input[PADDING_LENGTH..];
+    // Big endian byte strings of equal length compare like the numbers they encode.
+    if value >= &MODULUS[..] {
+        return Err(Error::InvalidPrecompileInput);
+    }
+    let mut fp = blst_fp::default();
+    unsafe { blst_fp_from_bendian(&mut fp, value.as_ptr()) };
+    Ok(fp)
+}
+
+fn encode_fp(fp: &blst_fp, out: &mut [u8]) {
+    unsafe { blst_bendian_from_fp(out[PADDING_LENGTH..].as_mut_ptr(), fp) };
+}
+
+pub fn decode_fp2(input: &[u8]) -> Result<blst_fp2, Error> {
+    if input.len() != 2 * FP_ENCODED_LENGTH {
+        return Err(Error::InvalidPrecompileInput);
+    }
+    Ok(blst_fp2 {
+        fp: [
+            decode_fp(&input[..FP_ENCODED_LENGTH])?,
+            decode_fp(&input[FP_ENCODED_LENGTH..])?,
+        ],
+    })
+}
+
+// decode_g1 decodes a G1 point and checks it is on the curve. The subgroup
+// check is only performed when requested, as G1ADD skips it.
+pub fn decode_g1(input: &[u8], subgroup_check: bool) -> Result<blst_p1_affine, Error> {
+    if input.len() != G1_ENCODED_LENGTH {
+        return Err(Error::InvalidPrecompileInput);
+    }
+    let p = blst_p1_affine {
+        x: decode_fp(&input[..FP_ENCODED_LENGTH])?,
+        y: decode_fp(&input[FP_ENCODED_LENGTH..])?,
+    };
+    if unsafe { blst_p1_affine_is_inf(&p) } {
+        return Ok(p);
+    }
+    if !unsafe { blst_p1_affine_on_curve(&p) } {
+        return Err(Error::InvalidPrecompileInput);
+    }
+    if subgroup_check && !unsafe { blst_p1_affine_in_g1(&p) } {
+        return Err(Error::InvalidPrecompileInput);
+    }
+    Ok(p)
+}
+
+// decode_g2 decodes a G2 point and checks it is on the curve. The subgroup
+// check is only performed when requested, as G2ADD skips it.
+pub fn decode_g2(input: &[u8], subgroup_check: bool) -> Result<blst_p2_affine, Error> {
+    if input.len() != G2_ENCODED_LENGTH {
+        return Err(Error::InvalidPrecompileInput);
+    }
+    let p = blst_p2_affine {
+        x: decode_fp2(&input[..2 * FP_ENCODED_LENGTH])?,
+        y: decode_fp2(&input[2 * FP_ENCODED_LENGTH..])?,
+    };
+    if unsafe { blst_p2_affine_is_inf(&p) } {
+        return Ok(p);
+    }
+    if !unsafe { blst_p2_affine_on_curve(&p) } {
+        return Err(Error::InvalidPrecompileInput);
+    }
+    if subgroup_check && !unsafe { blst_p2_affine_in_g2(&p) } {
+        return Err(Error::InvalidPrecompileInput);
+    }
+    Ok(p)
+}
+
+pub fn encode_g1(p: &blst_p1) -> Vec<u8> {
+    let mut affine = blst_p1_affine::default();
+    unsafe { blst_p1_to_affine(&mut affine, p) };
+    let mut out = vec![0u8; G1_ENCODED_LENGTH];
+    encode_fp(&affine.x, &mut out[..FP_ENCODED_LENGTH]);
+    encode_fp(&affine.y, &mut out[FP_ENCODED_LENGTH..]);
+    out
+}
+
+pub fn encode_g2(p: &blst_p2) -> Vec<u8> {
+    let mut affine = blst_p2_affine::default();
+    unsafe { blst_p2_to_affine(&mut affine, p) };
+    let mut out = vec![0u8; G2_ENCODED_LENGTH];
+    encode_fp(&affine.x.fp[0], &mut out[..FP_ENCODED_LENGTH]);
+    encode_fp(&affine.x.fp[1], &mut out[FP_ENCODED_LENGTH..2 * FP_ENCODED_LENGTH]);
+    encode_fp(&affine.y.fp[0], &mut out[2 * FP_ENCODED_LENGTH..3 * FP_ENCODED_LENGTH]);
+    encode_fp(&affine.y.fp[1], &mut out[3 * FP_ENCODED_LENGTH..]);
+    out
+}
+
+// scalar_le converts a big endian EVM scalar into the little endian form blst
+// expects. Scalars are not required to be reduced.
+fn scalar_le(input: &[u8]) -> [u8; SCALAR_LENGTH] {
+    let mut scalar = [0u8; SCALAR_LENGTH];
+    for (i, b) in input.iter().rev().enumerate() {
+        scalar[i] = *b;
+    }
+    scalar
+}
+
+pub fn g1_add(a: &blst_p1_affine, b: &blst_p1_affine) -> blst_p1 {
+    let mut pa = blst_p1::default();
+    let mut pb = blst_p1::default();
+    let mut out = blst_p1::default();
+    unsafe {
+        blst_p1_from_affine(&mut pa, a);
+        blst_p1_from_affine(&mut pb, b);
+        blst_p1_add_or_double(&mut out, &pa, &pb);
+    }
+    out
+}
+
+pub fn g2_add(a: &blst_p2_affine, b: &blst_p2_affine) -> blst_p2 {
+    let mut pa = blst_p2::default();
+    let mut pb = blst_p2::default();
+    let mut out = blst_p2::default();
+    unsafe {
+        blst_p2_from_affine(&mut pa, a);
+        blst_p2_from_affine(&mut pb, b);
+        blst_p2_add_or_double(&mut out, &pa, &pb);
+    }
+    out
+}
+
+// g1_msm computes sum(scalar_i * p_i) for already decoded points.
+pub fn g1_msm(pairs: &[(blst_p1_affine, &[u8])]) -> blst_p1 {
+    let mut acc = blst_p1::default();
+    for (point, scalar) in pairs {
+        let mut p = blst_p1::default();
+        let mut prod = blst_p1::default();
+        let scalar = scalar_le(scalar);
+        unsafe {
+            blst_p1_from_affine(&mut p, point);
+            blst_p1_mult(&mut prod, &p, scalar.as_ptr(), 8 * SCALAR_LENGTH);
+            let sum = acc;
+            blst_p1_add_or_double(&mut acc, &sum, &prod);
+        }
+    }
+    acc
+}
+
+// g2_msm computes sum(scalar_i * p_i) for already decoded points.
+pub fn g2_msm(pairs: &[(blst_p2_affine, &[u8])]) -> blst_p2 {
+    let mut acc = blst_p2::default();
+    for (point, scalar) in pairs {
+        let mut p = blst_p2::default();
+        let mut prod = blst_p2::default();
+        let scalar = scalar_le(scalar);
+        unsafe {
+            blst_p2_from_affine(&mut p, point);
+            blst_p2_mult(&mut prod, &p, scalar.as_ptr(), 8 * SCALAR_LENGTH);
+            let sum = acc;
+            blst_p2_add_or_double(&mut acc, &sum, &prod);
+        }
+    }
+    acc
+}
+
+// pairing_check returns whether the product of the pairings e(p_i, q_i) is
+// the identity in GT. Pairs containing a point at infinity contribute one.
+pub fn pairing_check(pairs: &[(blst_p1_affine, blst_p2_affine)]) -> bool {
+    let mut acc = unsafe { *blst_fp12_one() };
+    for (p, q) in pairs {
+        if unsafe { blst_p1_affine_is_inf(p) || blst_p2_affine_is_inf(q) } {
+            continue;
+        }
+        let mut ml = blst_fp12::default();
+        unsafe {
+            blst_miller_loop(&mut ml, q, p);
+            let prev = acc;
+            blst_fp12_mul(&mut acc, &prev, &ml);
+        }
+    }
+    let mut result = blst_fp12::default();
+    unsafe {
+        blst_final_exp(&mut result, &acc);
+        blst_fp12_is_one(&result)
+    }
+}
+
+// map_fp_to_g1 maps a field element to G1 with the simplified SWU map
+// followed by cofactor clearing.
+pub fn map_fp_to_g1(fp: &blst_fp) -> blst_p1 {
+    let mut out = blst_p1::default();
+    unsafe { blst_map_to_g1(&mut out, fp, std::ptr::null()) };
+    out
+}
+
+// map_fp2_to_g2 maps an Fp2 element to G2 with the simplified SWU map
+// followed by cofactor clearing.
+pub fn map_fp2_to_g2(fp2: &blst_fp2) -> blst_p2 {
+    let mut out = blst_p2::default();
+    unsafe { blst_map_to_g2(&mut out, fp2, std::ptr::null()) };
+    out
+}
@@ -0,0 +1,180 @@
+use serde::{Deserialize, Serialize};
+
+// ChainConfig is the core config which determines the blockchain settings.
+//
+// Forks up to the merge are activated by block number, later forks by block
+// timestamp. A fork set to None is never activated. The forks after the merge
+// only apply to proof-of-stake blocks, so they need the Beacon engine. The JSON encoding
+// follows the "config" object of geth genesis files.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ChainConfig {
+    pub chain_id: u64,
+
+    pub homestead_block: Option<u64>,
+    pub eip150_block: Option<u64>,
+    pub eip155_block: Option<u64>,
+    pub eip158_block: Option<u64>,
+    pub byzantium_block: Option<u64>,
+    pub constantinople_block: Option<u64>,
+    pub petersburg_block: Option<u64>,
+    pub istanbul_block: Option<u64>,
+    pub berlin_block: Option<u64>,
+    pub london_block: Option<u64>,
+
+    pub shanghai_time: Option<u64>,
+    pub cancun_time: Option<u64>,
+    pub prague_time: Option<u64>,
+
+    pub terminal_total_difficulty_passed: bool,
+
+    // clique is set on proof-of-authority chains sealed by Clique.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub clique: Option<CliqueConfig>,
+}
+
+// CliqueConfig is the consensus engine configs for proof-of-authority based
+// sealing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct CliqueConfig {
+    pub period: u64, // Number of seconds between blocks to enforce
+    pub epoch: u64,  // Epoch length to reset votes and checkpoint
+}
+
+// MAINNET_CHAIN_CONFIG is the chain parameters to run a node on the main network.
+pub const MAINNET_CHAIN_CONFIG: ChainConfig = ChainConfig {
+    chain_id: 1,
+    homestead_block: Some(1_150_000),
+    eip150_block: Some(2_463_000),
+    eip155_block: Some(2_675_000),
+    eip158_block: Some(2_675_000),
+    byzantium_block: Some(4_370_000),
+    constantinople_block: Some(7_280_000),
+    petersburg_block: Some(7_280_000),
+    istanbul_block: Some(9_069_000),
+    berlin_block: Some(12_244_000),
+    london_block: Some(12_965_000),
+    shanghai_time: Some(1_681_338_455),
+    cancun_time: Some(1_710_338_135),
+    prague_time: Some(1_746_612_311),
+    terminal_total_difficulty_passed: true,
+    clique: None,
+};
+
+fn is_block_forked(fork: Option<u64>, number: u64) -> bool {
+    match fork {
+        Some(block) => block <= number,
+        None => false,
+    }
+}
+
+fn is_timestamp_forked(fork: Option<u64>, time: u64) -> bool {
+    match fork {
+        Some(timestamp) => timestamp <= time,
+        None => false,
+    }
+}
+
+impl ChainConfig {
+    pub fn is_homestead(&self, number: u64) -> bool {
+        is_block_forked(self.homestead_block, number)
+    }
+
+    pub fn is_eip150(&self, number: u64) -> bool {
+        is_block_forked(self.eip150_block, number)
+    }
+
+    pub fn is_eip155(&self, number: u64) -> bool {
+        is_block_forked(self.eip155_block, number)
+    }
+
+    pub fn is_eip158(&self, number: u64) -> bool {
+        is_block_forked(self.eip158_block, number)
+    }
+
+    pub fn is_byzantium(&self, number: u64) -> bool {
+        is_block_forked(self.byzantium_block, number)
+    }
+
+    pub fn is_constantinople(&self, number: u64) -> bool {
+        is_block_forked(self.constantinople_block, number)
+    }
+
+    // is_petersburg returns whether num is either
+    // - equal to or greater than the petersburg_block fork block,
+    // - OR is None, and constantinople is active
+    pub fn is_petersburg(&self, number: u64) -> bool {
+        is_block_forked(self.petersburg_block, number)
+            || self.petersburg_block.is_none() && self.is_constantinople(number)
+    }
+
+    pub fn is_istanbul(&self, number: u64) -> bool {
+        is_block_forked(self.istanbul_block, number)
+    }
+
+    pub fn is_berlin(&self, number: u64) -> bool {
+        is_block_forked(self.berlin_block, number)
+    }
+
+    pub fn is_london(&self, number: u64) -> bool {
+        is_block_forked(self.london_block, number)
+    }
+
+    pub fn is_shanghai(&self, number: u64, time: u64) -> bool {
+        self.is_london(number) && is_timestamp_forked(self.shanghai_time, time)
+    }
+
+    pub fn is_cancun(&self, number: u64, time: u64) -> bool {
+        self.is_london(number) && is_timestamp_forked(self.cancun_time, time)
+    }
+
+    pub fn is_prague(&self, number: u64, time: u64) -> bool {
+        self.is_london(number) && is_timestamp_forked(self.prague_time, time)
+    }
+
+    // rules ensures c's chain_id is not None and returns the fork flags
+    // active at the given block.
+    pub fn rules(&self, number: u64, is_merge: bool, time: u64) -> Rules {
+        Rules {
+            chain_id: self.chain_id,
+            is_homestead: self.is_homestead(number),
+            is_eip150: self.is_eip150(number),
+            is_eip155: self.is_eip155(number),
+            is_eip158: self.is_eip158(number),
+            is_byzantium: self.is_byzantium(number),
+            is_constantinople: self.is_constantinople(number),
+            is_petersburg: self.is_petersburg(number),
+            is_istanbul: self.is_istanbul(number),
+            is_berlin: self.is_berlin(number),
+            is_london: self.is_london(number),
+            is_merge,
+            is_shanghai: is_merge && self.is_shanghai(number, time),
+            is_cancun: is_merge && self.is_cancun(number, time),
+            is_prague: is_merge && self.is_prague(number, time),
+        }
+    }
+}
+
+// Rules wraps ChainConfig and is merely syntactic sugar or can be used for functions
+// that do not have or require information about the block.
+//
+// Rules is a one time interface meaning that it shouldn't be used in between transition
+// phases.
+#[derive(Debug, Clone, Default)]
+pub struct Rules {
+    pub chain_id: u64,
+    pub is_homestead: bool,
+    pub is_eip150: bool,
+    pub is_eip155: bool,
+    pub is_eip158: bool,
+    pub is_byzantium: bool,
+    pub is_constantinople: bool,
+    pub is_petersburg: bool,
+    pub is_istanbul: bool,
+    pub is_berlin: bool,
+    pub is_london: bool,
+    pub is_merge: bool,
+    pub is_shanghai: bool,
+    pub is_cancun: bool,
+    pub is_prague: bool,
+}
@@ -0,0 +1,1205 @@
+// Package clique implements the proof-of-authority consensus engine.
+//
+// Blocks are sealed by a set of authorized signers, which sign the header
+// with secp256k1 and store the signature in the last 65 bytes of the extra
+// data. The signer whose turn it is seals with difficulty 2, the others with
+// difficulty 1 after a random delay, and no signer may seal more than one of
+// any floor(signers / 2) + 1 consecutive blocks. Signers vote to add or drop
+// an account by setting it as the coinbase of their blocks with the auth or
+// drop nonce; a proposal passes once more than half of the signers agree.
+// Every epoch the pending votes are discarded and the checkpoint block lists
+// the current signers in its extra data.
+//
+// https://eips.ethereum.org/EIPS/eip-225
+
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::fmt;
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::block::{Block, Header, EMPTY_UNCLE_HASH};
+use crate::chain_config::{ChainConfig, CliqueConfig};
+use crate::consensus::{
+    get_parent, verify_gas, verify_no_merge_fields, verify_timestamp, ChainHeaderReader,
+    ConsensusError, Engine,
+};
+use crate::crypto::{self, CryptoError, PrivateKey};
+use crate::rlp;
+use crate::state_db::StateDB;
+use uint256::Uint256;
+
+const INMEMORY_SNAPSHOTS: usize = 128; // Number of recent vote snapshots to keep in memory
+const INMEMORY_SIGNATURES: usize = 4096; // Number of recent block signatures to keep in memory
+
+// WIGGLE_TIME is the random delay (per signer) to allow concurrent signers.
+const WIGGLE_TIME: Duration = Duration::from_millis(500);
+
+pub const EPOCH_LENGTH: u64 = 30000; // Default number of blocks after which to checkpoint and reset the pending votes
+
+pub const EXTRA_VANITY: usize = 32; // Fixed number of extra-data prefix bytes reserved for signer vanity
+pub const EXTRA_SEAL: usize = crypto::SIGNATURE_LENGTH; // Fixed number of extra-data suffix bytes reserved for signer seal
+
+pub const NONCE_AUTH_VOTE: [u8; 8] = [0xff; 8]; // Magic nonce number to vote on adding a new signer
+pub const NONCE_DROP_VOTE: [u8; 8] = [0x00; 8]; // Magic nonce number to vote on removing a signer.
+
+pub const DIFF_IN_TURN: u64 = 2; // Block difficulty for in-turn signatures
+pub const DIFF_NO_TURN: u64 = 1; // Block difficulty for out-of-turn signatures
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CliqueError {
+    // UnknownBlock is returned when the list of signers is requested for a
+    // block that is not part of the local blockchain.
+    UnknownBlock,
+    // InvalidCheckpointBeneficiary is returned if a checkpoint/epoch
+    // transition block has a beneficiary set to non-zeroes.
+    InvalidCheckpointBeneficiary,
+    // InvalidVote is returned if a nonce value is something else that the
+    // two allowed constants of 0x00..0 or 0xff..f.
+    InvalidVote,
+    // InvalidCheckpointVote is returned if a checkpoint/epoch transition
+    // block has a vote nonce set to non-zeroes.
+    InvalidCheckpointVote,
+    // MissingVanity is returned if a block's extra-data section is shorter
+    // than 32 bytes, which is required to store the signer vanity.
+    MissingVanity,
+    // MissingSignature is returned if a block's extra-data section doesn't
+    // seem to contain a 65 byte secp256k1 signature.
+    MissingSignature,
+    // ExtraSigners is returned if non-checkpoint block contain signer data
+    // in their extra-data fields.
+    ExtraSigners,
+    // InvalidCheckpointSigners is returned if a checkpoint block contains an
+    // invalid list of signers (i.e. non divisible by 20 bytes).
+    InvalidCheckpointSigners,
+    // MismatchingCheckpointSigners is returned if a checkpoint block
+    // contains a list of signers different than the one the local node
+    // calculated.
+    MismatchingCheckpointSigners,
+    // InvalidUncleHash is returned if a block contains an non-empty uncle
+    // list.
+    InvalidUncleHash,
+    // InvalidDifficulty is returned if the difficulty of a block neither 1
+    // or 2.
+    InvalidDifficulty,
+    // WrongDifficulty is returned if the difficulty of a block doesn't match
+    // the turn of the signer.
+    WrongDifficulty,
+    // InvalidTimestamp is returned if the timestamp of a block is lower than
+    // the previous block's timestamp + the minimum block period.
+    InvalidTimestamp,
+    // InvalidVotingChain is returned if an authorization list is attempted
+    // to be modified via out-of-range or non-contiguous headers.
+    InvalidVotingChain,
+    // UnauthorizedSigner is returned if a header is signed by a non-authorized
+    // entity.
+    UnauthorizedSigner,
+    // RecentlySigned is returned if a header is signed by an authorized
+    // entity that already signed a header recently, thus is temporarily not
+    // allowed to.
+    RecentlySigned,
+    // NoSigningKey is returned when sealing without an authorized key.
+    NoSigningKey,
+    Signature(CryptoError),
+}
+
+impl fmt::Display for CliqueError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CliqueError::UnknownBlock => write!(f, "unknown block"),
+            CliqueError::InvalidCheckpointBeneficiary => {
+                write!(f, "beneficiary in checkpoint block non-zero")
+            }
+            CliqueError::InvalidVote => write!(f, "vote nonce not 0x00..0 or 0xff..f"),
+            CliqueError::InvalidCheckpointVote => {
+                write!(f, "vote nonce in checkpoint block non-zero")
+            }
+            CliqueError::MissingVanity => write!(f, "extra-data 32 byte vanity prefix missing"),
+            CliqueError::MissingSignature => {
+                write!(f, "extra-data 65 byte signature suffix missing")
+            }
+            CliqueError::ExtraSigners => {
+                write!(f, "non-checkpoint block contains extra signer list")
+            }
+            CliqueError::InvalidCheckpointSigners => {
+                write!(f, "invalid signer list on checkpoint block")
+            }
+            CliqueError::MismatchingCheckpointSigners => {
+                write!(f, "mismatching signer list on checkpoint block")
+            }
+            CliqueError::InvalidUncleHash => write!(f, "non empty uncle hash"),
+            CliqueError::InvalidDifficulty => write!(f, "invalid difficulty"),
+            CliqueError::WrongDifficulty => write!(f, "wrong difficulty"),
+            CliqueError::InvalidTimestamp => write!(f, "invalid timestamp"),
+            CliqueError::InvalidVotingChain => write!(f, "invalid voting chain"),
+            CliqueError::UnauthorizedSigner => write!(f, "unauthorized signer"),
+            CliqueError::RecentlySigned => write!(f, "recently signed"),
+            CliqueError::NoSigningKey => write!(f, "no signing key authorized"),
+            CliqueError::Signature(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl std::error::Error for CliqueError {}
+
+// Vote represents a single vote that an authorized signer made to modify the
+// list of authorizations.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Vote {
+    pub signer: common::Address,  // Authorized signer that cast this vote
+    pub block: u64,               // Block number the vote was cast in (expire old votes)
+    pub address: common::Address, // Account being voted on to change its authorization
+    pub authorize: bool,          // Whether to authorize or deauthorize the voted account
+}
+
+// Tally is a simple vote tally to keep the current score of votes. Votes
+// that go against the proposal aren't counted since it's equivalent to not
+// voting.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Tally {
+    pub authorize: bool, // Whether the vote is about authorizing or kicking someone
+    pub votes: usize,    // Number of votes until now wanting to pass the proposal
+}
+
+// Snapshot is the state of the authorization voting at a given point in time.
+#[derive(Debug, Clone)]
+pub struct Snapshot {
+    config: CliqueConfig,
+
+    pub number: u64,        // Block number where the snapshot was created
+    pub hash: common::Hash, // Block hash where the snapshot was created
+    pub signers: HashSet<common::Address>, // Set of authorized signers at this moment
+    pub recents: BTreeMap<u64, common::Address>, // Set of recent signers for spam protections
+    pub votes: Vec<Vote>,   // List of votes cast in chronological order
+    pub tally: HashMap<common::Address, Tally>, // Current vote tally to avoid recalculating
+}
+
+impl Snapshot {
+    // new creates a new snapshot with the specified startup parameters. This
+    // method does not initialize the set of recent signers, so only ever use
+    // if for the genesis block.
+    pub fn new(
+        config: CliqueConfig,
+        number: u64,
+        hash: common::Hash,
+        signers: &[common::Address],
+    ) -> Self {
+        Snapshot {
+            config,
+            number,
+            hash,
+            signers: signers.iter().copied().collect(),
+            recents: BTreeMap::new(),
+            votes: Vec::new(),
+            tally: HashMap::new(),
+        }
+    }
+
+    // valid_vote returns whether it makes sense to cast the specified vote
+    // in the given snapshot context (e.g. don't try to add an already
+    // authorized signer).
+    pub fn valid_vote(&self, address: &common::Address, authorize: bool) -> bool {
+        let signer = self.signers.contains(address);
+        (signer && !authorize) || (!signer && authorize)
+    }
+
+    // cast adds a new vote into the tally.
+    fn cast(&mut self, address: common::Address, authorize: bool) -> bool {
+        // Ensure the vote is meaningful
+        if !self.valid_vote(&address, authorize) {
+            return false;
+        }
+        // Cast the vote into an existing or new tally
+        self.tally
+            .entry(address)
+            .and_modify(|tally| tally.votes += 1)
+            .or_insert(Tally {
+                authorize,
+                votes: 1,
+            });
+        true
+    }
+
+    // uncast removes a previously cast vote from the tally.
+    fn uncast(&mut self, address: &common::Address, authorize: bool) -> bool {
+        // If there's no tally, it's a dangling vote, just drop
+        let tally = match self.tally.get_mut(address) {
+            Some(tally) => tally,
+            None => return false,
+        };
+        // Ensure we only revert counted votes
+        if tally.authorize != authorize {
+            return false;
+        }
+        // Otherwise revert the vote
+        if tally.votes > 1 {
+            tally.votes -= 1;
+        } else {
+            self.tally.remove(address);
+        }
+        true
+    }
+
+    // apply creates a new authorization snapshot by applying the given
+    // headers to the original one. recover returns the signer of a header.
+    pub fn apply(
+        &self,
+        headers: &[Header],
+        recover: &dyn Fn(&Header) -> Result<common::Address, CliqueError>,
+    ) -> Result<Snapshot, CliqueError> {
+        // Allow passing in no headers for cleaner code
+        let last = match headers.last() {
+            Some(last) => last,
+            None => return Ok(self.clone()),
+        };
+        // Sanity check that the headers can be applied
+        for pair in headers.windows(2) {
+            if pair[1].number != pair[0].number + 1 {
+                return Err(CliqueError::InvalidVotingChain);
+            }
+        }
+        if headers[0].number != self.number + 1 {
+            return Err(CliqueError::InvalidVotingChain);
+        }
+        // Iterate through the headers and create a new snapshot
+        let mut snap = self.clone();
+        for header in headers {
+            // Remove any votes on checkpoint blocks
+            let number = header.number;
+            if number % self.config.epoch == 0 {
+                snap.votes.clear();
+                snap.tally.clear();
+            }
+            // Delete the oldest signer from the recent list to allow it
+            // signing again
+            let limit = (snap.signers.len() / 2 + 1) as u64;
+            if number >= limit {
+                snap.recents.remove(&(number - limit));
+            }
+            // Resolve the authorization key and check against signers
+            let signer = recover(header)?;
+            if !snap.signers.contains(&signer) {
+                return Err(CliqueError::UnauthorizedSigner);
+            }
+            if snap.recents.values().any(|recent| *recent == signer) {
+                return Err(CliqueError::RecentlySigned);
+            }
+            snap.recents.insert(number, signer);
+
+            // Header authorized, discard any previous votes from the signer
+            if let Some(i) = snap
+                .votes
+                .iter()
+                .position(|vote| vote.signer == signer && vote.address == header.coinbase)
+            {
+                // Uncast the vote from the cached tally
+                let vote = snap.votes.remove(i);
+                snap.uncast(&vote.address, vote.authorize);
+            }
+            // Tally up the new vote from the signer
+            let authorize = if header.nonce == NONCE_AUTH_VOTE {
+                true
+            } else if header.nonce == NONCE_DROP_VOTE {
+                false
+            } else {
+                return Err(CliqueError::InvalidVote);
+            };
+            if snap.cast(header.coinbase, authorize) {
+                snap.votes.push(Vote {
+                    signer,
+                    block: number,
+                    address: header.coinbase,
+                    authorize,
+                });
+            }
+            // If the vote passed, update the list of signers
+            let passed = match snap.tally.get(&header.coinbase) {
+                Some(tally) => tally.votes > snap.signers.len() / 2,
+                None => false,
+            };
+            if passed {
+                let address = header.coinbase;
+                let tally = snap.tally[&address];
+                if tally.authorize {
+                    snap.signers.insert(address);
+                } else {
+                    snap.signers.remove(&address);
+
+                    // Signer list shrunk, delete any leftover recent caches
+                    let limit = (snap.signers.len() / 2 + 1) as u64;
+                    if number >= limit {
+                        snap.recents.remove(&(number - limit));
+                    }
+                    // Discard any previous votes the deauthorized signer cast
+                    let (dropped, kept): (Vec<Vote>, Vec<Vote>) = snap
+                        .votes
+                        .drain(..)
+                        .partition(|vote| vote.signer == address);
+                    snap.votes = kept;
+                    for vote in dropped {
+                        snap.uncast(&vote.address, vote.authorize);
+                    }
+                }
+                // Discard any previous votes around the just changed account
+                snap.votes.retain(|vote| vote.address != address);
+                snap.tally.remove(&address);
+            }
+        }
+        snap.number = last.number;
+        snap.hash = last.hash();
+        Ok(snap)
+    }
+
+    // signers retrieves the list of authorized signers in ascending order.
+    pub fn signers(&self) -> Vec<common::Address> {
+        let mut signers: Vec<common::Address> = self.signers.iter().copied().collect();
+        signers.sort_by_key(|a| a.0);
+        signers
+    }
+
+    // inturn returns if a signer at a given block height is in-turn or not.
+    pub fn inturn(&self, number: u64, signer: &common::Address) -> bool {
+        let signers = self.signers();
+        match signers.iter().position(|s| s == signer) {
+            Some(offset) => (number % signers.len() as u64) as usize == offset,
+            None => false,
+        }
+    }
+
+    // recently_signed reports whether signer sealed one of the blocks that
+    // prevent it from sealing block number.
+    fn recently_signed(&self, number: u64, signer: &common::Address) -> bool {
+        let limit = (self.signers.len() / 2 + 1) as u64;
+        self.recents
+            .iter()
+            .any(|(seen, recent)| recent == signer && (number < limit || *seen > number - limit))
+    }
+}
+
+// seal_hash returns the hash of a block prior to it being sealed, that is
+// of the header without the signature in its extra data.
+pub fn seal_hash(header: &Header) -> common::Hash {
+    let mut header = header.clone();
+    let len = header.extra.len().saturating_sub(EXTRA_SEAL);
+    header.extra.truncate(len);
+    crypto::keccak256_hash(&rlp::encode(&header))
+}
+
+// calc_difficulty returns the difficulty a block sealed by signer on top of
+// the given snapshot should have.
+pub fn calc_difficulty(snap: &Snapshot, signer: &common::Address) -> Uint256 {
+    if snap.inturn(snap.number + 1, signer) {
+        Uint256::from_uint64(DIFF_IN_TURN)
+    } else {
+        Uint256::from_uint64(DIFF_NO_TURN)
+    }
+}
+
+// checkpoint_signers extracts the signer list of a checkpoint header.
+fn checkpoint_signers(header: &Header) -> Result<Vec<common::Address>, CliqueError> {
+    if header.extra.len() < EXTRA_VANITY {
+        return Err(CliqueError::MissingVanity);
+    }
+    if header.extra.len() < EXTRA_VANITY + EXTRA_SEAL {
+        return Err(CliqueError::MissingSignature);
+    }
+    let signers = &header.extra[EXTRA_VANITY..header.extra.len() - EXTRA_SEAL];
+    if !signers.len().is_multiple_of(20) {
+        return Err(CliqueError::InvalidCheckpointSigners);
+    }
+    Ok(signers
+        .chunks(20)
+        .map(|chunk| {
+            let mut address = [0u8; 20];
+            address.copy_from_slice(chunk);
+            common::Address(address)
+        })
+        .collect())
+}
+
+// RecentCache keeps the values of the most recently inserted block hashes,
+// evicting the oldest entry once it holds limit entries.
+struct RecentCache<V> {
+    limit: usize,
+    entries: HashMap<common::Hash, V>,
+    order: VecDeque<common::Hash>, // Insertion order, oldest first
+}
+
+impl<V> RecentCache<V> {
+    fn new(limit: usize) -> Self {
+        RecentCache {
+            limit,
+            entries: HashMap::new(),
+            order: VecDeque::new(),
+        }
+    }
+
+    fn get(&self, hash: &common::Hash) -> Option<&V> {
+        self.entries.get(hash)
+    }
+
+    fn insert(&mut self, hash: common::Hash, value: V) {
+        if self.entries.insert(hash, value).is_some() {
+            return;
+        }
+        self.order.push_back(hash);
+        if self.order.len() > self.limit {
+            if let Some(oldest) = self.order.pop_front() {
+                self.entries.remove(&oldest);
+            }
+        }
+    }
+}
+
+// Clique is the proof-of-authority consensus engine proposed to support the
+// Ethereum testnet following the Ropsten attacks.
+//
+// Snapshots and recovered signers are cached in memory only, so a restarted
+// node rebuilds the snapshot of its head from the genesis block.
+pub struct Clique {
+    config: CliqueConfig, // Consensus engine configuration parameters
+
+    recents: Mutex<RecentCache<Snapshot>>, // Snapshots for recent block to speed up reorgs
+    signatures: Mutex<RecentCache<common::Address>>, // Signatures of recent blocks to speed up mining
+
+    proposals: Mutex<HashMap<common::Address, bool>>, // Current list of proposals we are pushing
+
+    signer: Mutex<Option<PrivateKey>>, // Key of the signer sealing the blocks
+}
+
+impl Clique {
+    // new creates a Clique proof-of-authority consensus engine with the
+    // initial signers set to the ones provided by the user.
+    pub fn new(config: CliqueConfig) -> Self {
+        // Set any missing consensus parameters to their defaults
+        let mut config = config;
+        if config.epoch == 0 {
+            config.epoch = EPOCH_LENGTH;
+        }
+        Clique {
+            config,
+            recents: Mutex::new(RecentCache::new(INMEMORY_SNAPSHOTS)),
+            signatures: Mutex::new(RecentCache::new(INMEMORY_SIGNATURES)),
+            proposals: Mutex::new(HashMap::new()),
+            signer: Mutex::new(None),
+        }
+    }
+
+    // authorize injects a private key into the consensus engine to mint new
+    // blocks with.
+    pub fn authorize(&self, key: PrivateKey) {
+        *self.signer.lock().expect("signer lock") = Some(key);
+    }
+
+    // signer returns the address of the authorized signing key, if any.
+    pub fn signer(&self) -> Option<common::Address> {
+        self.signer
+            .lock()
+            .expect("signer lock")
+            .as_ref()
+            .map(|key| key.address())
+    }
+
+    // propose injects a new authorization proposal that the signer will
+    // attempt to push through.
+    pub fn propose(&self, address: common::Address, auth: bool) {
+        self.proposals
+            .lock()
+            .expect("proposals lock")
+            .insert(address, auth);
+    }
+
+    // discard drops a currently running proposal, stopping the signer from
+    // casting further votes (either for or against).
+    pub fn discard(&self, address: &common::Address) {
+        self.proposals
+            .lock()
+            .expect("proposals lock")
+            .remove(address);
+    }
+
+    // proposals returns the current proposals the node tries to uphold and
+    // vote on.
+    pub fn proposals(&self) -> HashMap<common::Address, bool> {
+        self.proposals.lock().expect("proposals lock").clone()
+    }
+
+    // ecrecover extracts the Ethereum account address from a signed header.
+    pub fn ecrecover(&self, header: &Header) -> Result<common::Address, CliqueError> {
+        // If the signature's already cached, return that
+        let hash = header.hash();
+        if let Some(address) = self.signatures.lock().expect("signatures lock").get(&hash) {
+            return Ok(*address);
+        }
+        // Retrieve the signature from the header extra-data
+        if header.extra.len() < EXTRA_SEAL {
+            return Err(CliqueError::MissingSignature);
+        }
+        let signature = &header.extra[header.extra.len() - EXTRA_SEAL..];
+
+        // Recover the public key and the Ethereum address
+        let pubkey = crypto::ecrecover(seal_hash(header).as_bytes(), signature)
+            .map_err(CliqueError::Signature)?;
+        let signer = crypto::pubkey_to_address(&pubkey);
+
+        self.signatures
+            .lock()
+            .expect("signatures lock")
+            .insert(hash, signer);
+        Ok(signer)
+    }
+
+    // snapshot retrieves the authorization snapshot at a given point in time.
+    pub fn snapshot(
+        &self,
+        chain: &dyn ChainHeaderReader,
+        number: u64,
+        hash: common::Hash,
+    ) -> Result<Snapshot, ConsensusError> {
+        // Search for a snapshot in memory or the genesis block
+        let mut headers = Vec::new();
+        let (mut number, mut hash) = (number, hash);
+        let snap = loop {
+            // If an in-memory snapshot was found, use that
+            if let Some(snap) = self.recents.lock().expect("recents lock").get(&hash) {
+                break snap.clone();
+            }
+            // If we're at the genesis, snapshot the initial state
+            if number == 0 {
+                let genesis = chain
+                    .get_header(&hash, 0)
+                    .ok_or(CliqueError::UnknownBlock)?;
+                let signers = checkpoint_signers(&genesis)?;
+                break Snapshot::new(self.config, 0, hash, &signers);
+            }
+            // No snapshot for this header, gather the header and move backward
+            let header = chain
+                .get_header(&hash, number)
+                .ok_or(ConsensusError::UnknownAncestor)?;
+            hash = header.parent_hash;
+            number -= 1;
+            headers.push(header);
+        };
+        // Previous snapshot found, apply any pending headers on top of it
+        headers.reverse();
+        let snap = snap.apply(&headers, &|header| self.ecrecover(header))?;
+
+        self.recents
+            .lock()
+            .expect("recents lock")
+            .insert(snap.hash, snap.clone());
+        Ok(snap)
+    }
+
+    // verify_cascading_fields verifies all the header fields that are not
+    // standalone, rather depend on a batch of previous headers.
+    fn verify_cascading_fields(
+        &self,
+        chain: &dyn ChainHeaderReader,
+        header: &Header,
+    ) -> Result<(), ConsensusError> {
+        // Ensure that the block's timestamp isn't too close to its parent
+        let parent = get_parent(chain, header)?;
+        if header.number != parent.number + 1 {
+            return Err(ConsensusError::InvalidNumber);
+        }
+        if parent.time + self.config.period > header.time {
+            return Err(CliqueError::InvalidTimestamp.into());
+        }
+        // Verify the gas limit and the base fee
+        verify_gas(chain.config(), &parent, header)?;
+
+        // Retrieve the snapshot needed to verify this header and cache it
+        let snap = self.snapshot(chain, parent.number, header.parent_hash)?;
+
+        // If the block is a checkpoint block, verify the signer list
+        if header.number.is_multiple_of(self.config.epoch) {
+            let signers = checkpoint_signers(header)?;
+            if signers != snap.signers() {
+                return Err(CliqueError::MismatchingCheckpointSigners.into());
+            }
+        }
+        // All basic checks passed, verify the seal and return
+        self.verify_seal(&snap, header)
+    }
+
+    // verify_seal checks whether the signature contained in the header
+    // satisfies the consensus protocol requirements.
+    fn verify_seal(&self, snap: &Snapshot, header: &Header) -> Result<(), ConsensusError> {
+        // Resolve the authorization key and check against signers
+        let signer = self.ecrecover(header)?;
+        if !snap.signers.contains(&signer) {
+            return Err(CliqueError::UnauthorizedSigner.into());
+        }
+        if snap.recently_signed(header.number, &signer) {
+            return Err(CliqueError::RecentlySigned.into());
+        }
+        // Ensure that the difficulty corresponds to the turn-ness of the signer
+        let want = if snap.inturn(header.number, &signer) {
+            DIFF_IN_TURN
+        } else {
+            DIFF_NO_TURN
+        };
+        if header.difficulty != Uint256::from_uint64(want) {
+            return Err(CliqueError::WrongDifficulty.into());
+        }
+        Ok(())
+    }
+}
+
+impl Engine for Clique {
+    // author implements Engine, returning the Ethereum address recovered
+    // from the signature in the header's extra-data section.
+    fn author(&self, header: &Header) -> Result<common::Address, ConsensusError> {
+        Ok(self.ecrecover(header)?)
+    }
+
+    fn verify_header(
+        &self,
+        chain: &dyn ChainHeaderReader,
+        header: &Header,
+    ) -> Result<(), ConsensusError> {
+        if header.number == 0 {
+            return Err(CliqueError::UnknownBlock.into());
+        }
+        // Don't waste time checking blocks from the future
+        verify_timestamp(header)?;
+
+        // Checkpoint blocks need to enforce zero beneficiary
+        let checkpoint = header.number.is_multiple_of(self.config.epoch);
+        if checkpoint && header.coinbase != common::Address::default() {
+            return Err(CliqueError::InvalidCheckpointBeneficiary.into());
+        }
+        // Nonces must be 0x00..0 or 0xff..f, zeroes enforced on checkpoints
+        if header.nonce != NONCE_AUTH_VOTE && header.nonce != NONCE_DROP_VOTE {
+            return Err(CliqueError::InvalidVote.into());
+        }
+        if checkpoint && header.nonce != NONCE_DROP_VOTE {
+            return Err(CliqueError::InvalidCheckpointVote.into());
+        }
+        // Check that the extra-data contains both the vanity and signature
+        if header.extra.len() < EXTRA_VANITY {
+            return Err(CliqueError::MissingVanity.into());
+        }
+        if header.extra.len() < EXTRA_VANITY + EXTRA_SEAL {
+            return Err(CliqueError::MissingSignature.into());
+        }
+        // Ensure that the extra-data contains a signer list on checkpoint,
+        // but none otherwise
+        let signers_bytes = header.extra.len() - EXTRA_VANITY - EXTRA_SEAL;
+        if !checkpoint && signers_bytes != 0 {
+            return Err(CliqueError::ExtraSigners.into());
+        }
+        if checkpoint && !signers_bytes.is_multiple_of(20) {
+            return Err(CliqueError::InvalidCheckpointSigners.into());
+        }
+        // Ensure that the mix digest is zero as we don't have fork protection
+        // currently
+        if header.mix_digest != common::Hash::default() {
+            return Err(ConsensusError::InvalidMixDigest);
+        }
+        // Ensure that the block doesn't contain any uncles which are
+        // meaningless in PoA
+        if header.uncle_hash != EMPTY_UNCLE_HASH {
+            return Err(CliqueError::InvalidUncleHash.into());
+        }
+        // Ensure that the block's difficulty is meaningful (may not be
+        // correct at this point)
+        if header.difficulty != Uint256::from_uint64(DIFF_IN_TURN)
+            && header.difficulty != Uint256::from_uint64(DIFF_NO_TURN)
+        {
+            return Err(CliqueError::InvalidDifficulty.into());
+        }
+        verify_no_merge_fields(header)?;
+        // All basic checks passed, verify cascading fields
+        self.verify_cascading_fields(chain, header)
+    }
+
+    // prepare implements Engine, preparing all the consensus fields of the
+    // header for running the transactions on top.
+    fn prepare(
+        &self,
+        chain: &dyn ChainHeaderReader,
+        header: &mut Header,
+    ) -> Result<(), ConsensusError> {
+        // If the block isn't a checkpoint, cast a random vote (good enough for now)
+        header.coinbase = common::Address::default();
+        header.nonce = NONCE_DROP_VOTE;
+
+        // Assemble the voting snapshot to check which votes make sense
+        let parent = get_parent(chain, header)?;
+        let snap = self.snapshot(chain, parent.number, header.parent_hash)?;
+        let checkpoint = header.number.is_multiple_of(self.config.epoch);
+        if !checkpoint {
+            let proposals = self.proposals.lock().expect("proposals lock");
+            let vote = proposals
+                .iter()
+                .find(|(address, authorize)| snap.valid_vote(address, **authorize));
+            if let Some((address, authorize)) = vote {
+                header.coinbase = *address;
+                header.nonce = if *authorize {
+                    NONCE_AUTH_VOTE
+                } else {
+                    NONCE_DROP_VOTE
+                };
+            }
+        }
+        // Set the correct difficulty
+        let signer = self.signer().unwrap_or_default();
+        header.difficulty = calc_difficulty(&snap, &signer);
+
+        // Ensure the extra data has all its components
+        header.extra.resize(EXTRA_VANITY, 0);
+        if checkpoint {
+            for signer in snap.signers() {
+                header.extra.extend_from_slice(&signer.0);
+            }
+        }
+        header.extra.extend_from_slice(&[0u8; EXTRA_SEAL]);
+
+        // Mix digest is reserved for now, set to empty
+        header.mix_digest = common::Hash::default();
+
+        // Ensure the timestamp has the correct delay
+        header.time = header.time.max(parent.time + self.config.period);
+        Ok(())
+    }
+
+    // finalize implements Engine. There are no block rewards in
+    // proof-of-authority.
+    fn finalize(
+        &self,
+        _config: &ChainConfig,
+        _header: &Header,
+        _state: &mut dyn StateDB,
+        _uncles: &[Header],
+    ) {
+    }
+
+    // seal implements Engine, attempting to create a sealed block using the
+    // authorized signer key. It waits until the block's timestamp, plus a
+    // random delay if the signer is out of turn.
+    fn seal(&self, chain: &dyn ChainHeaderReader, block: Block) -> Result<Block, ConsensusError> {
+        let mut header = block.header;
+        // Sealing the genesis block is not supported
+        if header.number == 0 {
+            return Err(CliqueError::UnknownBlock.into());
+        }
+        let key = self
+            .signer
+            .lock()
+            .expect("signer lock")
+            .clone()
+            .ok_or(CliqueError::NoSigningKey)?;
+        let signer = key.address();
+
+        // Bail out if we're unauthorized to sign a block
+        let snap = self.snapshot(chain, header.number - 1, header.parent_hash)?;
+        if !snap.signers.contains(&signer) {
+            return Err(CliqueError::UnauthorizedSigner.into());
+        }
+        // If we're amongst the recent signers, wait for the next block
+        if snap.recently_signed(header.number, &signer) {
+            return Err(CliqueError::RecentlySigned.into());
+        }
+        // Sweet, the protocol permits us to sign the block, wait for our time
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        let mut delay = Duration::from_secs(header.time).saturating_sub(now);
+        if header.difficulty == Uint256::from_uint64(DIFF_NO_TURN) {
+            // It's not our turn explicitly to sign, delay it a bit
+            let wiggle = WIGGLE_TIME * (snap.signers.len() / 2 + 1) as u32;
+            let nanos = wiggle.as_nanos() as u64;
+            delay += Duration::from_nanos(now.subsec_nanos() as u64 % nanos.max(1));
+        }
+        thread::sleep(delay);
+
+        // Sign all the things!
+        let signature =
+            crypto::sign(seal_hash(&header).as_bytes(), &key).map_err(CliqueError::Signature)?;
+        let len = header.extra.len();
+        header.extra[len - EXTRA_SEAL..].copy_from_slice(&signature);
+        Ok(Block::with_body(header, block.body))
+    }
+
+    // calc_difficulty is the difficulty adjustment algorithm. It returns the
+    // difficulty that a new block should have based on the previous blocks
+    // in the chain and the current signer.
+    fn calc_difficulty(
+        &self,
+        chain: &dyn ChainHeaderReader,
+        _time: u64,
+        parent: &Header,
+    ) -> Uint256 {
+        match self.snapshot(chain, parent.number, parent.hash()) {
+            Ok(snap) => calc_difficulty(&snap, &self.signer().unwrap_or_default()),
+            Err(_) => Uint256::default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // TestChain is a header store to verify clique headers against.
+    struct TestChain {
+        config: ChainConfig,
+        headers: HashMap<common::Hash, Header>,
+        canonical: Vec<common::Hash>,
+    }
+
+    impl ChainHeaderReader for TestChain {
+        fn config(&self) -> &ChainConfig {
+            &self.config
+        }
+
+        fn current_header(&self) -> Header {
+            self.headers[self.canonical.last().unwrap()].clone()
+        }
+
+        fn get_header(&self, hash: &common::Hash, number: u64) -> Option<Header> {
+            self.headers.get(hash).filter(|header| header.number == number).cloned()
+        }
+
+        fn get_header_by_number(&self, number: u64) -> Option<Header> {
+            let hash = self.canonical.get(number as usize)?;
+            self.headers.get(hash).cloned()
+        }
+    }
+
+    // key returns the test account with the given name.
+    fn key(name: u8) -> PrivateKey {
+        crypto::to_ecdsa(&[name; 32]).unwrap()
+    }
+
+    fn addr(name: u8) -> common::Address {
+        key(name).address()
+    }
+
+    fn checkpoint_extra(signers: &[common::Address]) -> Vec<u8> {
+        let mut signers = signers.to_vec();
+        signers.sort_by_key(|a| a.0);
+        let mut extra = vec![0u8; EXTRA_VANITY];
+        for signer in signers {
+            extra.extend_from_slice(&signer.0);
+        }
+        extra.extend_from_slice(&[0u8; EXTRA_SEAL]);
+        extra
+    }
+
+    fn new_chain(signers: &[u8]) -> TestChain {
+        let signers: Vec<common::Address> = signers.iter().map(|name| addr(*name)).collect();
+        let genesis = Header {
+            gas_limit: 5_000_000,
+            extra: checkpoint_extra(&signers),
+            difficulty: Uint256::from_uint64(1),
+            uncle_hash: EMPTY_UNCLE_HASH,
+            ..Default::default()
+        };
+        let hash = genesis.hash();
+        TestChain {
+            config: ChainConfig::default(),
+            headers: HashMap::from([(hash, genesis)]),
+            canonical: vec![hash],
+        }
+    }
+
+    fn engine(epoch: u64) -> Clique {
+        Clique::new(CliqueConfig { period: 0, epoch })
+    }
+
+    // TestVote is a single header of a voting scenario: the signer, the
+    // voted account (zero for no vote) and whether the vote authorizes it.
+    struct TestVote {
+        signer: u8,
+        voted: u8,
+        auth: bool,
+    }
+
+    fn vote(signer: u8, voted: u8, auth: bool) -> TestVote {
+        TestVote { signer, voted, auth }
+    }
+
+    fn sign(header: &mut Header, signer: u8) {
+        let signature = crypto::sign(seal_hash(header).as_bytes(), &key(signer)).unwrap();
+        let len = header.extra.len();
+        header.extra[len - EXTRA_SEAL..].copy_from_slice(&signature);
+    }
+
+    // make_header builds the signed header of vote on top of the chain head,
+    // with the difficulty the signer's turn calls for.
+    fn make_header(engine: &Clique, chain: &TestChain, vote: &TestVote) -> Header {
+        let parent = chain.current_header();
+        let number = parent.number + 1;
+        let snap = engine.snapshot(chain, parent.number, parent.hash()).unwrap();
+        let checkpoint = number.is_multiple_of(engine.config.epoch);
+        let mut header = Header {
+            parent_hash: parent.hash(),
+            uncle_hash: EMPTY_UNCLE_HASH,
+            number,
+            gas_limit: parent.gas_limit,
+            time: parent.time + 1,
+            difficulty: calc_difficulty(&snap, &addr(vote.signer)),
+            extra: if checkpoint {
+                checkpoint_extra(&snap.signers())
+            } else {
+                checkpoint_extra(&[])
+            },
+            ..Default::default()
+        };
+        if vote.voted != 0 {
+            header.coinbase = addr(vote.voted);
+            header.nonce = if vote.auth { NONCE_AUTH_VOTE } else { NONCE_DROP_VOTE };
+        }
+        sign(&mut header, vote.signer);
+        header
+    }
+
+    fn import(engine: &Clique, chain: &mut TestChain, header: Header) -> Result<(), ConsensusError> {
+        engine.verify_header(chain, &header)?;
+        let hash = header.hash();
+        chain.headers.insert(hash, header);
+        chain.canonical.push(hash);
+        Ok(())
+    }
+
+    // run imports the votes and returns the signers at the head.
+    fn run(signers: &[u8], epoch: u64, votes: &[TestVote]) -> Vec<common::Address> {
+        let engine = engine(epoch);
+        let mut chain = new_chain(signers);
+        for (i, vote) in votes.iter().enumerate() {
+            let header = make_header(&engine, &chain, vote);
+            import(&engine, &mut chain, header).unwrap_or_else(|err| panic!("vote {}: {}", i, err));
+        }
+        let head = chain.current_header();
+        engine.snapshot(&chain, head.number, head.hash()).unwrap().signers()
+    }
+
+    fn sorted(names: &[u8]) -> Vec<common::Address> {
+        let mut signers: Vec<common::Address> = names.iter().map(|name| addr(*name)).collect();
+        signers.sort_by_key(|a| a.0);
+        signers
+    }
+
+    #[test]
+    fn test_difficulty() {
+        let engine = engine(EPOCH_LENGTH);
+        let mut chain = new_chain(&[1, 2, 3]);
+        let signers = sorted(&[1, 2, 3]);
+        let snap = engine.snapshot(&chain, 0, chain.canonical[0]).unwrap();
+
+        // Block 1 is the turn of the second signer in address order
+        assert!(snap.inturn(1, &signers[1]));
+        assert!(!snap.inturn(1, &signers[0]));
+        assert_eq!(calc_difficulty(&snap, &signers[1]), Uint256::from_uint64(DIFF_IN_TURN));
+        assert_eq!(calc_difficulty(&snap, &signers[0]), Uint256::from_uint64(DIFF_NO_TURN));
+        assert_eq!(calc_difficulty(&snap, &signers[2]), Uint256::from_uint64(DIFF_NO_TURN));
+
+        // A header claiming the wrong turn is rejected
+        let name = (1..=3).find(|name| addr(*name) == signers[0]).unwrap();
+        let mut header = make_header(&engine, &chain, &vote(name, 0, false));
+        header.difficulty = Uint256::from_uint64(DIFF_IN_TURN);
+        sign(&mut header, name);
+        assert!(matches!(
+            engine.verify_header(&chain, &header),
+            Err(ConsensusError::Clique(CliqueError::WrongDifficulty))
+        ));
+        let header = make_header(&engine, &chain, &vote(name, 0, false));
+        import(&engine, &mut chain, header).unwrap();
+    }
+
+    #[test]
+    fn test_voting() {
+        // Single signer, no votes cast
+        assert_eq!(run(&[1], EPOCH_LENGTH, &[vote(1, 0, false)]), sorted(&[1]));
+        // Single signer, voting to add two others (only accept first, second
+        // needs 2 votes)
+        assert_eq!(
+            run(&[1], EPOCH_LENGTH, &[vote(1, 2, true), vote(2, 0, false), vote(1, 3, true)]),
+            sorted(&[1, 2])
+        );
+        // Two signers, voting to add three others (only accept first two,
+        // third needs 3 votes already)
+        assert_eq!(
+            run(
+                &[1, 2],
+                EPOCH_LENGTH,
+                &[
+                    vote(1, 3, true),
+                    vote(2, 3, true),
+                    vote(1, 4, true),
+                    vote(2, 4, true),
+                    vote(3, 0, false),
+                    vote(1, 5, true),
+                    vote(2, 5, true),
+                ]
+            ),
+            sorted(&[1, 2, 3, 4])
+        );
+        // Single signer, dropping itself (weird, but one less cornercase by
+        // explicitly allowing this)
+        assert_eq!(run(&[1], EPOCH_LENGTH, &[vote(1, 1, false)]), Vec::new());
+        // Two signers, actually needing mutual consent to drop either of them
+        // (not fulfilled)
+        assert_eq!(run(&[1, 2], EPOCH_LENGTH, &[vote(1, 2, false)]), sorted(&[1, 2]));
+        // Two signers, actually needing mutual consent to drop either of them
+        // (fulfilled)
+        assert_eq!(
+            run(&[1, 2], EPOCH_LENGTH, &[vote(1, 2, false), vote(2, 2, false)]),
+            sorted(&[1])
+        );
+        // Three signers, two of them deciding to drop the third
+        assert_eq!(
+            run(&[1, 2, 3], EPOCH_LENGTH, &[vote(1, 3, false), vote(2, 3, false)]),
+            sorted(&[1, 2])
+        );
+        // Deauthorizing multiple accounts concurrently is permitted
+        assert_eq!(
+            run(
+                &[1, 2, 3, 4],
+                EPOCH_LENGTH,
+                &[
+                    vote(1, 3, false),
+                    vote(2, 0, false),
+                    vote(3, 0, false),
+                    vote(1, 4, false),
+                    vote(2, 3, false),
+                    vote(3, 0, false),
+                    vote(1, 0, false),
+                    vote(2, 4, false),
+                    vote(3, 4, false),
+                    // Dropping D leaves enough votes to drop C as well, which
+                    // happens once a vote on C is counted again.
+                    vote(1, 3, false),
+                ]
+            ),
+            sorted(&[1, 2])
+        );
+        // Votes from deauthorized signers are discarded immediately
+        assert_eq!(
+            run(
+                &[1, 2, 3],
+                EPOCH_LENGTH,
+                &[vote(3, 2, false), vote(1, 3, false), vote(2, 3, false), vote(1, 2, false)]
+            ),
+            sorted(&[1, 2])
+        );
+        // Changes reaching consensus out of bounds (via a deauth) execute on
+        // touch
+        let votes = [
+            vote(1, 4, false),
+            vote(2, 6, true),
+            vote(3, 4, false),
+            vote(1, 6, true),
+            vote(2, 4, false),
+            vote(3, 0, false),
+            vote(1, 6, true),
+        ];
+        assert_eq!(run(&[1, 2, 3, 4], EPOCH_LENGTH, &votes[..6]), sorted(&[1, 2, 3]));
+        assert_eq!(run(&[1, 2, 3, 4], EPOCH_LENGTH, &votes), sorted(&[1, 2, 3, 6]));
+    }
+
+    #[test]
+    fn test_epoch_resets_votes() {
+        // Votes cast before a checkpoint are forgotten after it
+        assert_eq!(
+            run(&[1, 2], 3, &[vote(1, 3, true), vote(2, 0, false), vote(1, 0, false), vote(2, 3, true)]),
+            sorted(&[1, 2])
+        );
+        // The same votes within an epoch pass
+        assert_eq!(
+            run(&[1, 2], EPOCH_LENGTH, &[vote(1, 3, true), vote(2, 0, false), vote(1, 0, false), vote(2, 3, true)]),
+            sorted(&[1, 2, 3])
+        );
+    }
+
+    #[test]
+    fn test_recently_signed() {
+        let engine = engine(EPOCH_LENGTH);
+        let mut chain = new_chain(&[1, 2, 3]);
+        let header = make_header(&engine, &chain, &vote(1, 0, false));
+        import(&engine, &mut chain, header).unwrap();
+
+        // With three signers one may only sign one of any two consecutive
+        // blocks.
+        let header = make_header(&engine, &chain, &vote(1, 0, false));
+        assert!(matches!(
+            import(&engine, &mut chain, header),
+            Err(ConsensusError::Clique(CliqueError::RecentlySigned))
+        ));
+        let snap = engine.snapshot(&chain, 1, chain.canonical[1]).unwrap();
+        assert!(snap.recently_signed(2, &addr(1)));
+        assert!(!snap.recently_signed(3, &addr(1)));
+
+        let header = make_header(&engine, &chain, &vote(2, 0, false));
+        import(&engine, &mut chain, header).unwrap();
+        let header = make_header(&engine, &chain, &vote(1, 0, false));
+        import(&engine, &mut chain, header).unwrap();
+
+        // An account outside the signer set can't seal at all
+        let header = make_header(&engine, &chain, &vote(4, 0, false));
+        assert!(matches!(
+            import(&engine, &mut chain, header),
+            Err(ConsensusError::Clique(CliqueError::UnauthorizedSigner))
+        ));
+    }
+
+    #[test]
+    fn test_checkpoint_signers() {
+        let engine = engine(3);
+        let mut chain = new_chain(&[1, 2]);
+        for signer in [1, 2] {
+            let header = make_header(&engine, &chain, &vote(signer, 0, false));
+            import(&engine, &mut chain, header).unwrap();
+        }
+        // The checkpoint must list the current signers
+        let mut header = make_header(&engine, &chain, &vote(1, 0, false));
+        assert_eq!(checkpoint_signers(&header).unwrap(), sorted(&[1, 2]));
+        header.extra = checkpoint_extra(&[addr(1)]);
+        sign(&mut header, 1);
+        assert!(matches!(
+            engine.verify_header(&chain, &header),
+            Err(ConsensusError::Clique(CliqueError::MismatchingCheckpointSigners))
+        ));
+
+        // and may not carry a vote
+        let mut header = make_header(&engine, &chain, &vote(1, 0, false));
+        header.nonce = NONCE_AUTH_VOTE;
+        sign(&mut header, 1);
+        assert!(matches!(
+            engine.verify_header(&chain, &header),
+            Err(ConsensusError::Clique(CliqueError::InvalidCheckpointVote))
+        ));
+
+        let header = make_header(&engine, &chain, &vote(1, 0, false));
+        import(&engine, &mut chain, header).unwrap();
+
+        // Non-checkpoint blocks may not list signers
+        let mut header = make_header(&engine, &chain, &vote(2, 0, false));
+        header.extra = checkpoint_extra(&[addr(2)]);
+        sign(&mut header, 2);
+        assert!(matches!(
+            engine.verify_header(&chain, &header),
+            Err(ConsensusError::Clique(CliqueError::ExtraSigners))
+        ));
+    }
+
+    #[test]
+    fn test_recent_cache_evicts_oldest() {
+        let mut cache = RecentCache::new(2);
+        let hash = |n: u8| common::Hash([n; 32]);
+        cache.insert(hash(1), 1);
+        cache.insert(hash(2), 2);
+        // Reinserting a key doesn't make it younger
+        cache.insert(hash(1), 10);
+        cache.insert(hash(3), 3);
+        assert_eq!(cache.get(&hash(1)), None);
+        assert_eq!(cache.get(&hash(2)), Some(&2));
+        assert_eq!(cache.get(&hash(3)), Some(&3));
+        cache.insert(hash(4), 4);
+        assert_eq!(cache.get(&hash(2)), None);
+        assert_eq!(cache.entries.len(), 2);
+    }
+}
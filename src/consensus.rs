@@ -0,0 +1,355 @@
+// Package consensus defines the interface of the consensus engines.
+//
+// An Engine decides who may produce a block and proves it in the header:
+// it verifies the headers it receives, fills the consensus fields of the
+// headers it builds, applies the consensus specific state changes (like the
+// block rewards) and seals finished blocks. Beacon implements the post-merge
+// rules, where the proof lives on the beacon chain; the proof-of-work and
+// proof-of-authority engines live in their own modules.
+
+use std::fmt;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::block::{Block, Header};
+use crate::chain_config::ChainConfig;
+use crate::clique::CliqueError;
+use crate::eip1559::{self, FeeError};
+use crate::eip4844::{self, BlobGasError};
+use crate::params;
+use crate::state_db::StateDB;
+use uint256::Uint256;
+
+// ALLOWED_FUTURE_BLOCK_TIME is the number of seconds a block's timestamp may
+// be ahead of the local clock.
+pub const ALLOWED_FUTURE_BLOCK_TIME: u64 = 15;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConsensusError {
+    // UnknownAncestor is returned when validating a block requires an ancestor
+    // that is unknown.
+    UnknownAncestor,
+    // FutureBlock is returned when a block's timestamp is in the future
+    // according to the current node.
+    FutureBlock,
+    // InvalidNumber is returned if a block's number doesn't equal its
+    // parent's plus one.
+    InvalidNumber,
+    // OlderBlockTime is returned if a block's timestamp is not newer than
+    // its parent's.
+    OlderBlockTime,
+    ExtraDataTooLong { have: usize, max: usize },
+    InvalidDifficulty { have: Uint256, want: Uint256 },
+    // InvalidMixDigest is returned if a block's mix digest doesn't match
+    // the one computed from its seal.
+    InvalidMixDigest,
+    // InvalidPoW is returned if a block's proof-of-work doesn't meet its
+    // difficulty.
+    InvalidPoW,
+    // InvalidNonce is returned if a proof-of-stake block has a non-zero
+    // nonce.
+    InvalidNonce,
+    // SealAborted is returned by seal if the search was aborted before a
+    // seal was found.
+    SealAborted,
+    // NoSealFound is returned by seal if no seal was found within its
+    // attempt budget.
+    NoSealFound { attempts: u64 },
+    // GasUsedExceedsLimit is returned if a block uses more gas than its gas
+    // limit.
+    GasUsedExceedsLimit { used: u64, limit: u64 },
+    // UnsupportedField is returned if a header carries a field of a fork the
+    // engine doesn't support, or misses one of an active fork.
+    UnsupportedField(&'static str),
+    Fee(FeeError),
+    BlobGas(BlobGasError),
+    // Clique is returned if a header breaks the proof-of-authority rules.
+    Clique(CliqueError),
+}
+
+impl fmt::Display for ConsensusError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConsensusError::UnknownAncestor => write!(f, "unknown ancestor"),
+            ConsensusError::FutureBlock => write!(f, "block in the future"),
+            ConsensusError::InvalidNumber => write!(f, "invalid block number"),
+            ConsensusError::OlderBlockTime => write!(f, "timestamp older than parent"),
+            ConsensusError::ExtraDataTooLong { have, max } => {
+                write!(f, "extra-data too long: {} > {}", have, max)
+            }
+            ConsensusError::InvalidDifficulty { have, want } => write!(
+                f,
+                "invalid difficulty: have {}, want {}",
+                have.low_u64(),
+                want.low_u64()
+            ),
+            ConsensusError::InvalidMixDigest => write!(f, "invalid mix digest"),
+            ConsensusError::InvalidPoW => write!(f, "invalid proof-of-work"),
+            ConsensusError::InvalidNonce => write!(f, "invalid nonce"),
+            ConsensusError::SealAborted => write!(f, "sealing aborted"),
+            ConsensusError::NoSealFound { attempts } => {
+                write!(f, "no seal found in {} attempts", attempts)
+            }
+            ConsensusError::GasUsedExceedsLimit { used, limit } => {
+                write!(f, "invalid gasUsed: have {}, gasLimit {}", used, limit)
+            }
+            ConsensusError::UnsupportedField(field) => write!(f, "invalid {}", field),
+            ConsensusError::Fee(err) => write!(f, "{}", err),
+            ConsensusError::BlobGas(err) => write!(f, "{}", err),
+            ConsensusError::Clique(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl std::error::Error for ConsensusError {}
+
+impl From<FeeError> for ConsensusError {
+    fn from(err: FeeError) -> Self {
+        ConsensusError::Fee(err)
+    }
+}
+
+impl From<BlobGasError> for ConsensusError {
+    fn from(err: BlobGasError) -> Self {
+        ConsensusError::BlobGas(err)
+    }
+}
+
+impl From<CliqueError> for ConsensusError {
+    fn from(err: CliqueError) -> Self {
+        ConsensusError::Clique(err)
+    }
+}
+
+// ChainHeaderReader defines a small collection of methods needed to access
+// the local blockchain during header verification.
+pub trait ChainHeaderReader {
+    // config retrieves the blockchain's chain configuration.
+    fn config(&self) -> &ChainConfig;
+    // current_header retrieves the current header from the local chain.
+    fn current_header(&self) -> Header;
+    // get_header retrieves a block header from the database by hash and
+    // number.
+    fn get_header(&self, hash: &common::Hash, number: u64) -> Option<Header>;
+    // get_header_by_number retrieves a canonical block header from the
+    // database by number.
+    fn get_header_by_number(&self, number: u64) -> Option<Header>;
+}
+
+// Engine is an algorithm agnostic consensus engine.
+pub trait Engine: Send + Sync {
+    // author retrieves the address of the account that minted the given
+    // block, which may be different from the header's coinbase if a
+    // consensus engine is based on signatures.
+    fn author(&self, header: &Header) -> Result<common::Address, ConsensusError>;
+
+    // verify_header checks whether a header conforms to the consensus rules
+    // of the engine. The parent of the header must be known to chain.
+    fn verify_header(
+        &self,
+        chain: &dyn ChainHeaderReader,
+        header: &Header,
+    ) -> Result<(), ConsensusError>;
+
+    // prepare initializes the consensus fields of a block header according
+    // to the rules of a particular engine.
+    fn prepare(
+        &self,
+        chain: &dyn ChainHeaderReader,
+        header: &mut Header,
+    ) -> Result<(), ConsensusError>;
+
+    // finalize runs any post-transaction state modifications (e.g. block
+    // rewards) on the state of the block. The header is not modified.
+    fn finalize(
+        &self,
+        config: &ChainConfig,
+        header: &Header,
+        state: &mut dyn StateDB,
+        uncles: &[Header],
+    );
+
+    // seal generates a sealing request for the given block and returns the
+    // sealed block. It blocks until the seal is found or the engine gives up.
+    fn seal(&self, chain: &dyn ChainHeaderReader, block: Block) -> Result<Block, ConsensusError>;
+
+    // calc_difficulty is the difficulty adjustment algorithm. It returns the
+    // difficulty that a new block should have.
+    fn calc_difficulty(&self, chain: &dyn ChainHeaderReader, time: u64, parent: &Header)
+        -> Uint256;
+}
+
+// get_parent returns the parent of header from chain.
+pub fn get_parent(
+    chain: &dyn ChainHeaderReader,
+    header: &Header,
+) -> Result<Header, ConsensusError> {
+    if header.number == 0 {
+        return Err(ConsensusError::UnknownAncestor);
+    }
+    chain
+        .get_header(&header.parent_hash, header.number - 1)
+        .ok_or(ConsensusError::UnknownAncestor)
+}
+
+// verify_chain_fields checks the fields every engine agrees on: the block
+// number and timestamp against the parent, the extra-data size and the gas
+// limit and base fee rules.
+pub fn verify_chain_fields(
+    config: &ChainConfig,
+    parent: &Header,
+    header: &Header,
+) -> Result<(), ConsensusError> {
+    if header.number != parent.number + 1 {
+        return Err(ConsensusError::InvalidNumber);
+    }
+    if header.time <= parent.time {
+        return Err(ConsensusError::OlderBlockTime);
+    }
+    if header.extra.len() > params::MAXIMUM_EXTRA_DATA_SIZE {
+        return Err(ConsensusError::ExtraDataTooLong {
+            have: header.extra.len(),
+            max: params::MAXIMUM_EXTRA_DATA_SIZE,
+        });
+    }
+    verify_gas(config, parent, header)
+}
+
+// verify_gas checks the gas used against the gas limit, and the gas limit
+// and base fee against the parent.
+pub fn verify_gas(
+    config: &ChainConfig,
+    parent: &Header,
+    header: &Header,
+) -> Result<(), ConsensusError> {
+    if header.gas_used > header.gas_limit {
+        return Err(ConsensusError::GasUsedExceedsLimit {
+            used: header.gas_used,
+            limit: header.gas_limit,
+        });
+    }
+    if config.is_london(header.number) {
+        eip1559::verify_eip1559_header(config, parent, header)?;
+    } else {
+        // Verify BaseFee not present before EIP-1559 fork.
+        if header.base_fee.is_some() {
+            return Err(ConsensusError::UnsupportedField("baseFee before fork"));
+        }
+        eip1559::verify_gaslimit(parent.gas_limit, header.gas_limit)?;
+    }
+    Ok(())
+}
+
+// verify_no_merge_fields checks that a header of a pre-merge engine carries
+// none of the fields introduced by the forks after the merge.
+pub fn verify_no_merge_fields(header: &Header) -> Result<(), ConsensusError> {
+    if header.withdrawals_hash.is_some() {
+        return Err(ConsensusError::UnsupportedField("withdrawalsHash"));
+    }
+    if header.blob_gas_used.is_some() || header.excess_blob_gas.is_some() {
+        return Err(ConsensusError::UnsupportedField("blob gas fields"));
+    }
+    if header.parent_beacon_root.is_some() {
+        return Err(ConsensusError::UnsupportedField("parentBeaconRoot"));
+    }
+    Ok(())
+}
+
+// verify_timestamp checks that the header isn't too far ahead of the local
+// clock.
+pub fn verify_timestamp(header: &Header) -> Result<(), ConsensusError> {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs());
+    if header.time > now + ALLOWED_FUTURE_BLOCK_TIME {
+        return Err(ConsensusError::FutureBlock);
+    }
+    Ok(())
+}
+
+// Beacon is the consensus engine of the chain after the merge: the block
+// proposer is selected by the beacon chain, so the execution header carries
+// a zero difficulty and nonce and its seal is implicit. The forks after the
+// merge (Shanghai, Cancun, Prague) only activate on such blocks. There is no
+// transition from proof-of-work: the chain must be merged from its genesis,
+// which has a zero difficulty, and the head is chosen with set_head.
+#[derive(Debug, Clone, Default)]
+pub struct Beacon;
+
+impl Engine for Beacon {
+    fn author(&self, header: &Header) -> Result<common::Address, ConsensusError> {
+        Ok(header.coinbase)
+    }
+
+    fn verify_header(
+        &self,
+        chain: &dyn ChainHeaderReader,
+        header: &Header,
+    ) -> Result<(), ConsensusError> {
+        let config = chain.config();
+        let parent = get_parent(chain, header)?;
+        verify_chain_fields(config, &parent, header)?;
+        // Verify the sealing fields, which are fixed after the merge
+        if !header.difficulty.is_zero() {
+            return Err(ConsensusError::InvalidDifficulty {
+                have: header.difficulty.clone(),
+                want: Uint256::default(),
+            });
+        }
+        if header.nonce != [0u8; 8] {
+            return Err(ConsensusError::InvalidNonce);
+        }
+        // Verify the existence / non-existence of the fork specific fields
+        let shanghai = config.is_shanghai(header.number, header.time);
+        if shanghai != header.withdrawals_hash.is_some() {
+            return Err(ConsensusError::UnsupportedField("withdrawalsHash"));
+        }
+        if config.is_cancun(header.number, header.time) {
+            eip4844::verify_eip4844_header(&parent, header)?;
+            if header.parent_beacon_root.is_none() {
+                return Err(ConsensusError::UnsupportedField("parentBeaconRoot"));
+            }
+        } else if header.blob_gas_used.is_some()
+            || header.excess_blob_gas.is_some()
+            || header.parent_beacon_root.is_some()
+        {
+            return Err(ConsensusError::UnsupportedField(
+                "cancun fields before fork",
+            ));
+        }
+        Ok(())
+    }
+
+    fn prepare(
+        &self,
+        _chain: &dyn ChainHeaderReader,
+        header: &mut Header,
+    ) -> Result<(), ConsensusError> {
+        header.difficulty = Uint256::default();
+        header.nonce = [0u8; 8];
+        Ok(())
+    }
+
+    // finalize is a no-op: there are no block rewards after the merge and
+    // the withdrawals are applied by the block processor.
+    fn finalize(
+        &self,
+        _config: &ChainConfig,
+        _header: &Header,
+        _state: &mut dyn StateDB,
+        _uncles: &[Header],
+    ) {
+    }
+
+    fn seal(&self, _chain: &dyn ChainHeaderReader, block: Block) -> Result<Block, ConsensusError> {
+        Ok(block)
+    }
+
+    fn calc_difficulty(
+        &self,
+        _chain: &dyn ChainHeaderReader,
+        _time: u64,
+        _parent: &Header,
+    ) -> Uint256 {
+        Uint256::default()
+    }
+}
@@ -0,0 +1,187 @@
+use crate::crypto;
+use crate::op_code::{self, OpCode};
+use uint256::Uint256;
+
+// ContractRef is a reference to the contract's backing object
+pub trait ContractRef {
+    fn address(&self) -> common::Address;
+}
+
+// AccountRef implements ContractRef.
+//
+// Account references are used during EVM initialisation and
+// its primary use is to fetch addresses. Removing this object
+// proves difficult because of the cached jump destinations which
+// are fetched from the parent contract (i.e. the caller), which
+// is a ContractRef.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct AccountRef(pub common::Address);
+
+impl ContractRef for AccountRef {
+    fn address(&self) -> common::Address {
+        self.0
+    }
+}
+
+// CodeAndHash pairs init or runtime code with its keccak hash, computing the
+// hash lazily the first time it is requested.
+#[derive(Debug, Clone, Default)]
+pub struct CodeAndHash {
+    pub code: Vec<u8>,
+    hash: Option<common::Hash>,
+}
+
+impl CodeAndHash {
+    pub fn new(code: Vec<u8>) -> Self {
+        CodeAndHash { code, hash: None }
+    }
+
+    pub fn hash(&mut self) -> common::Hash {
+        if self.hash.is_none() {
+            self.hash = Some(crypto::keccak256_hash(&self.code));
+        }
+        self.hash.unwrap()
+    }
+}
+
+// Contract represents an ethereum contract in the state database. It contains
+// the contract code, calling arguments.
+#[derive(Debug, Clone, Default)]
+pub struct Contract {
+    // caller_address is the result of the caller which initialised this
+    // contract. However when the "call method" is delegated this value
+    // needs to be initialised to that of the caller's caller.
+    pub caller_address: common::Address,
+    self_address: common::Address,
+
+    jumpdests: Option<Vec<bool>>, // Aggregated result of JUMPDEST analysis.
+
+    pub code: Vec<u8>,
+    pub code_hash: common::Hash,
+    pub code_addr: Option<common::Address>,
+    pub input: Vec<u8>,
+
+    pub gas: u64,
+    value: Uint256,
+}
+
+impl ContractRef for Contract {
+    fn address(&self) -> common::Address {
+        self.self_address
+    }
+}
+
+impl Contract {
+    // new returns a new contract environment for the execution of EVM.
+    pub fn new(
+        caller: &dyn ContractRef,
+        object: &dyn ContractRef,
+        value: Uint256,
+        gas: u64,
+    ) -> Self {
+        Contract {
+            caller_address: caller.address(),
+            self_address: object.address(),
+            gas,
+            value,
+            ..Default::default()
+        }
+    }
+
+    // as_delegate sets the contract to be a delegate call and returns the current
+    // contract (for chaining calls)
+    pub fn as_delegate(mut self, parent: &Contract) -> Self {
+        // NOTE: caller must, at all times be a contract. It should never happen
+        // that caller is something other than a Contract.
+        self.caller_address = parent.caller_address;
+        self.value = parent.value.clone();
+        self
+    }
+
+    // get_op returns the n'th element in the contract's byte array
+    pub fn get_op(&self, n: u64) -> Option<OpCode> {
+        if n < self.code.len() as u64 {
+            Some(self.code[n as usize])
+        } else {
+            None
+        }
+    }
+
+    // caller returns the caller of the contract.
+    //
+    // Caller will recursively call caller when the contract is a delegate
+    // call, including that of caller's caller.
+    pub fn caller(&self) -> common::Address {
+        self.caller_address
+    }
+
+    pub fn value(&self) -> &Uint256 {
+        &self.value
+    }
+
+    // use_gas attempts the use gas and subtracts it and returns true on success
+    pub fn use_gas(&mut self, gas: u64) -> bool {
+        if self.gas < gas {
+            return false;
+        }
+        self.gas -= gas;
+        true
+    }
+
+    pub fn valid_jumpdest(&mut self, dest: &Uint256) -> bool {
+        let (udest, overflow) = dest.uint64_with_overflow();
+        // PC cannot go beyond len(code) and certainly can't be bigger than 63bits.
+        // Don't bother checking for JUMPDEST in that case.
+        if overflow || udest >= self.code.len() as u64 {
+            return false;
+        }
+        // Only JUMPDESTs allowed for destinations
+        if self.code[udest as usize] != op_code::JUMPDEST {
+            return false;
+        }
+        self.is_code(udest)
+    }
+
+    // is_code returns true if the provided PC location is an actual opcode, as
+    // opposed to a data-segment following a PUSHN operation.
+    fn is_code(&mut self, udest: u64) -> bool {
+        if self.jumpdests.is_none() {
+            self.jumpdests = Some(code_bitmap(&self.code));
+        }
+        self.jumpdests.as_ref().unwrap()[udest as usize]
+    }
+
+    // set_call_code sets the code of the contract and address of the backing data
+    // object
+    pub fn set_call_code(&mut self, addr: common::Address, hash: common::Hash, code: Vec<u8>) {
+        self.code = code;
+        self.code_hash = hash;
+        self.code_addr = Some(addr);
+        self.jumpdests = None;
+    }
+
+    // set_code_optional_hash can be used to provide code, but it's optional to provide hash.
+    // In case hash is not provided, the jumpdest analysis will not be saved to the parent context
+    pub fn set_code_optional_hash(&mut self, addr: common::Address, mut code_and_hash: CodeAndHash) {
+        self.code_hash = code_and_hash.hash();
+        self.code = code_and_hash.code;
+        self.code_addr = Some(addr);
+        self.jumpdests = None;
+    }
+}
+
+// code_bitmap marks every byte of the code that is an opcode (as opposed to
+// PUSH data) with true.
+fn code_bitmap(code: &[u8]) -> Vec<bool> {
+    let mut bits = vec![false; code.len()];
+    let mut pc = 0usize;
+    while pc < code.len() {
+        let op = code[pc];
+        bits[pc] = true;
+        pc += 1;
+        if op_code::is_push(op) {
+            pc += (op - op_code::PUSH1 + 1) as usize;
+        }
+    }
+    bits
+}
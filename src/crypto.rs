@@ -0,0 +1,453 @@
+// Package crypto implements the secp256k1 elliptic curve operations used by
+// Ethereum: key generation, signing, public key recovery and address
+// derivation. It also holds the Keccak256 hashing helpers and the contract
+// address derivations, so that callers only need this one module.
+//
+// Signatures are 65 bytes in the [R || S || V] format, where V is the
+// recovery id (0 or 1). Signing is deterministic (RFC 6979) and always
+// produces the low-s form required by EIP-2. The curve arithmetic is
+// provided by the k256 crate.
+
+use std::fmt;
+
+use k256::ecdsa::signature::hazmat::PrehashVerifier;
+use k256::ecdsa::{RecoveryId, Signature, SigningKey, VerifyingKey};
+use sha3::{Digest, Keccak256};
+use uint256::Uint256;
+
+use crate::rlp;
+
+pub const DIGEST_LENGTH: usize = 32;
+pub const SIGNATURE_LENGTH: usize = 64 + 1; // 64 bytes ECDSA signature + 1 byte recovery id
+pub const RECOVERY_ID_OFFSET: usize = 64;
+
+// SECP256K1_N is the order of the base point.
+const SECP256K1_N: Uint256 =
+    Uint256::from_limbs([0xBFD25E8CD0364141, 0xBAAEDCE6AF48A03B, 0xFFFFFFFFFFFFFFFE, 0xFFFFFFFFFFFFFFFF]);
+
+// SECP256K1_HALF_N is N / 2, the upper bound of the s value of EIP-2
+// signatures.
+const SECP256K1_HALF_N: Uint256 =
+    Uint256::from_limbs([0xDFE92F46681B20A0, 0x5D576E7357A4501D, 0xFFFFFFFFFFFFFFFF, 0x7FFFFFFFFFFFFFFF]);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CryptoError {
+    InvalidPrivateKey,
+    InvalidPublicKey,
+    InvalidSignature,
+    InvalidRecoveryId,
+    InvalidHashLength(usize),
+    Random(String),
+}
+
+impl fmt::Display for CryptoError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CryptoError::InvalidPrivateKey => write!(f, "invalid private key"),
+            CryptoError::InvalidPublicKey => write!(f, "invalid public key"),
+            CryptoError::InvalidSignature => write!(f, "invalid signature"),
+            CryptoError::InvalidRecoveryId => write!(f, "invalid signature recovery id"),
+            CryptoError::InvalidHashLength(len) => {
+                write!(f, "hash is required to be exactly {} bytes ({})", DIGEST_LENGTH, len)
+            }
+            CryptoError::Random(err) => write!(f, "failed to read randomness: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for CryptoError {}
+
+// PrivateKey is a secp256k1 private key.
+#[derive(Clone, PartialEq, Eq)]
+pub struct PrivateKey {
+    key: SigningKey,
+}
+
+impl fmt::Debug for PrivateKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "PrivateKey(..)")
+    }
+}
+
+impl PrivateKey {
+    // public_key returns the public key belonging to the private key.
+    pub fn public_key(&self) -> PublicKey {
+        PublicKey {
+            key: *self.key.verifying_key(),
+        }
+    }
+
+    // address returns the Ethereum address of the key.
+    pub fn address(&self) -> common::Address {
+        self.public_key().address()
+    }
+}
+
+// PublicKey is a secp256k1 public key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PublicKey {
+    key: VerifyingKey,
+}
+
+impl PublicKey {
+    // address returns the Ethereum address of the key: the last 20 bytes of
+    // the keccak256 hash of the uncompressed key without its 0x04 prefix.
+    pub fn address(&self) -> common::Address {
+        pubkey_to_address(&from_ecdsa_pub(self))
+    }
+}
+
+// generate_key generates a new private key from the OS random source.
+pub fn generate_key() -> Result<PrivateKey, CryptoError> {
+    loop {
+        let mut b = [0u8; 32];
+        getrandom::getrandom(&mut b).map_err(|err| CryptoError::Random(err.to_string()))?;
+        if let Ok(key) = to_ecdsa(&b) {
+            return Ok(key);
+        }
+    }
+}
+
+// to_ecdsa creates a private key with the given D value. The key must be 32
+// bytes and lie within [1, N).
+pub fn to_ecdsa(d: &[u8]) -> Result<PrivateKey, CryptoError> {
+    if d.len() != 32 {
+        return Err(CryptoError::InvalidPrivateKey);
+    }
+    let key = SigningKey::from_slice(d).map_err(|_| CryptoError::InvalidPrivateKey)?;
+    Ok(PrivateKey { key })
+}
+
+// from_ecdsa exports a private key into a binary dump.
+pub fn from_ecdsa(key: &PrivateKey) -> [u8; 32] {
+    key.key.to_bytes().into()
+}
+
+// from_ecdsa_pub exports a public key in the uncompressed 65 byte format.
+pub fn from_ecdsa_pub(key: &PublicKey) -> [u8; 65] {
+    let mut out = [0u8; 65];
+    out.copy_from_slice(key.key.to_encoded_point(false).as_bytes());
+    out
+}
+
+// unmarshal_pubkey converts bytes in the uncompressed format to a public key,
+// checking that the point is on the curve.
+pub fn unmarshal_pubkey(pub_key: &[u8]) -> Result<PublicKey, CryptoError> {
+    if pub_key.len() != 65 || pub_key[0] != 0x04 {
+        return Err(CryptoError::InvalidPublicKey);
+    }
+    let key = VerifyingKey::from_sec1_bytes(pub_key).map_err(|_| CryptoError::InvalidPublicKey)?;
+    Ok(PublicKey { key })
+}
+
+// keccak256 calculates and returns the Keccak256 hash of the input data.
+pub fn keccak256(data: &[u8]) -> [u8; 32] {
+    Keccak256::digest(data).into()
+}
+
+// keccak256_hash calculates and returns the Keccak256 hash of the input data,
+// converting it to an internal Hash data structure.
+pub fn keccak256_hash(data: &[u8]) -> common::Hash {
+    common::Hash(keccak256(data))
+}
+
+// create_address creates an ethereum address given the bytes and the nonce
+pub fn create_address(b: &common::Address, nonce: u64) -> common::Address {
+    let mut payload = Vec::new();
+    rlp::Encodable::rlp_append(b, &mut payload);
+    rlp::Encodable::rlp_append(&nonce, &mut payload);
+    let mut data = Vec::new();
+    rlp::append_list_payload(&payload, &mut data);
+    common::Address::from_slice(&keccak256(&data)[12..])
+}
+
+// create_address2 creates an ethereum address given the address bytes, initial
+// contract code hash and a salt.
+pub fn create_address2(b: &common::Address, salt: &[u8; 32], inithash: &[u8]) -> common::Address {
+    let mut data = Vec::with_capacity(1 + 20 + 32 + inithash.len());
+    data.push(0xff);
+    data.extend_from_slice(b.as_bytes());
+    data.extend_from_slice(salt);
+    data.extend_from_slice(inithash);
+    common::Address::from_slice(&keccak256(&data)[12..])
+}
+
+// pubkey_to_address derives the address of an uncompressed public key.
+pub fn pubkey_to_address(pub_key: &[u8; 65]) -> common::Address {
+    let hash = keccak256(&pub_key[1..]);
+    let mut address = [0u8; 20];
+    address.copy_from_slice(&hash[12..]);
+    common::Address(address)
+}
+
+// sign calculates an ECDSA signature.
+//
+// This function is susceptible to chosen plaintext attacks that can leak
+// information about the private key that is used for signing. Callers must
+// be aware that the given digest cannot be chosen by an adversary. Common
+// solution is to hash any input before calculating the signature.
+//
+// The produced signature is in the [R || S || V] format where V is 0 or 1.
+pub fn sign(digest_hash: &[u8], key: &PrivateKey) -> Result<[u8; SIGNATURE_LENGTH], CryptoError> {
+    if digest_hash.len() != DIGEST_LENGTH {
+        return Err(CryptoError::InvalidHashLength(digest_hash.len()));
+    }
+    // k256 normalizes s to the lower half, adjusting the recovery id.
+    let (signature, recid) = key
+        .key
+        .sign_prehash_recoverable(digest_hash)
+        .map_err(|_| CryptoError::InvalidSignature)?;
+    let mut sig = [0u8; SIGNATURE_LENGTH];
+    sig[..64].copy_from_slice(&signature.to_bytes());
+    sig[RECOVERY_ID_OFFSET] = recid.to_byte();
+    Ok(sig)
+}
+
+// sig_to_pub returns the public key that created the given signature.
+pub fn sig_to_pub(hash: &[u8], sig: &[u8]) -> Result<PublicKey, CryptoError> {
+    if hash.len() != DIGEST_LENGTH {
+        return Err(CryptoError::InvalidHashLength(hash.len()));
+    }
+    if sig.len() != SIGNATURE_LENGTH {
+        return Err(CryptoError::InvalidSignature);
+    }
+    let mut recid = RecoveryId::from_byte(sig[RECOVERY_ID_OFFSET]).ok_or(CryptoError::InvalidRecoveryId)?;
+    let mut signature = Signature::from_slice(&sig[..64]).map_err(|_| CryptoError::InvalidSignature)?;
+    // Recovery accepts the full range of s, but k256 only verifies low-s
+    // signatures. N - s with the flipped parity recovers the same key.
+    if let Some(normalized) = signature.normalize_s() {
+        signature = normalized;
+        recid = RecoveryId::new(!recid.is_y_odd(), recid.is_x_reduced());
+    }
+    let key = VerifyingKey::recover_from_prehash(hash, &signature, recid)
+        .map_err(|_| CryptoError::InvalidSignature)?;
+    Ok(PublicKey { key })
+}
+
+// ecrecover returns the uncompressed public key that created the given
+// signature.
+pub fn ecrecover(hash: &[u8], sig: &[u8]) -> Result<[u8; 65], CryptoError> {
+    sig_to_pub(hash, sig).map(|pub_key| from_ecdsa_pub(&pub_key))
+}
+
+// verify_signature checks that the given public key created the signature
+// over hash. The signature must be in the 64 byte [R || S] format with s in
+// the lower half of the group order.
+pub fn verify_signature(pub_key: &PublicKey, hash: &[u8], sig: &[u8]) -> bool {
+    if hash.len() != DIGEST_LENGTH || sig.len() != 64 {
+        return false;
+    }
+    match Signature::from_slice(sig) {
+        // k256 rejects high-s signatures itself.
+        Ok(signature) => pub_key.key.verify_prehash(hash, &signature).is_ok(),
+        Err(_) => false,
+    }
+}
+
+// validate_signature_values verifies whether the signature values are valid
+// with the given chain rules. The v value is assumed to be either 0 or 1.
+pub fn validate_signature_values(v: u8, r: &Uint256, s: &Uint256, homestead: bool) -> bool {
+    if r.is_zero() || s.is_zero() {
+        return false;
+    }
+    // reject upper range of s values (ECDSA malleability)
+    // see discussion in secp256k1/libsecp256k1/include/secp256k1.h
+    if homestead && *s > SECP256K1_HALF_N {
+        return false;
+    }
+    // Frontier: allow s to be in full N range
+    *r < SECP256K1_N && *s < SECP256K1_N && (v == 0 || v == 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEST_ADDR_HEX: &str = "970e8128ab834e8eac17ab8e3812f010678cf791";
+    const TEST_PRIV_HEX: &str = "289c2857d4598e37fb9647507e47a309d6133539bf21a8b9cb6df88fd5232032";
+
+    fn unhex(s: &str) -> Vec<u8> {
+        hex::decode(s).unwrap()
+    }
+
+    fn sha256(data: &[u8]) -> Vec<u8> {
+        crypto_hash::digest(crypto_hash::Algorithm::SHA256, data)
+    }
+
+    fn key(hex_key: &str) -> PrivateKey {
+        to_ecdsa(&unhex(hex_key)).unwrap()
+    }
+
+    // Deterministic low-s signatures for secp256k1 with SHA-256, as
+    // published with the RFC 6979 implementations of python-ecdsa and
+    // trezor-crypto.
+    #[test]
+    fn test_rfc6979_vectors() {
+        let vectors = [
+            (
+                "0000000000000000000000000000000000000000000000000000000000000001",
+                "Satoshi Nakamoto",
+                "934b1ea10a4b3c1757e2b0c017d0b6143ce3c9a7e6a4a49860d7a6ab210ee3d8\
+                 2442ce9d2b916064108014783e923ec36b49743e2ffa1c4496f01a512aafd9e5",
+            ),
+            (
+                "0000000000000000000000000000000000000000000000000000000000000001",
+                "All those moments will be lost in time, like tears in rain. Time to die...",
+                "8600dbd41e348fe5c9465ab92d23e3db8b98b873beecd930736488696438cb6b\
+                 547fe64427496db33bf66019dacbf0039c04199abb0122918601db38a72cfc21",
+            ),
+            (
+                "f8b8af8ce3c7cca5e300d33939540c10d45ce001b8f252bfbc57ba0342904181",
+                "Alan Turing",
+                "7063ae83e7f62bbb171798131b4a0564b956930092b33b07b395615d9ec7e15c\
+                 58dfcc1e00a35e1572f366ffe34ba0fc47db1e7189759b9fb233c5b05ab388ea",
+            ),
+        ];
+        for (priv_hex, msg, sig) in vectors {
+            let key = key(priv_hex);
+            let hash = sha256(msg.as_bytes());
+            let signature = sign(&hash, &key).unwrap();
+            let expected: String = sig.split_whitespace().collect();
+            assert_eq!(hex::encode(&signature[..64]), expected, "{}: signature", msg);
+            assert_eq!(sig_to_pub(&hash, &signature).unwrap(), key.public_key(), "{}: recovery", msg);
+        }
+    }
+
+    #[test]
+    fn test_ecrecover() {
+        let msg = unhex("ce0677bb30baa8cf067c88db9811f4333d131bf8bcf12fe7065d211dce971008");
+        let sig = unhex(
+            "90f27b8b488db00b00606796d2987f6a5f59ae62ea05effe84fef5b8b0e549984a691139ad57a3f0b906637673aa2f63d1f55cb1a69199d4009eea23ceaddc9301",
+        );
+        let pubkey = unhex(
+            "04e32df42865e97135acfb65f3bae71bdc86f4d49150ad6a440b6f15878109880a0a2b2667f7e725ceea70c673093bf67663e0312623c8e091b13cf2c0f11ef652",
+        );
+        assert_eq!(ecrecover(&msg, &sig).unwrap().to_vec(), pubkey);
+        assert!(verify_signature(&unmarshal_pubkey(&pubkey).unwrap(), &msg, &sig[..64]));
+    }
+
+    #[test]
+    fn test_sign_recover() {
+        let key = key(TEST_PRIV_HEX);
+        let addr = common::Address::from_slice(&unhex(TEST_ADDR_HEX));
+        assert_eq!(key.address(), addr);
+
+        let msg = keccak256(b"foo");
+        let sig = sign(&msg, &key).unwrap();
+        // Signing is deterministic.
+        assert_eq!(sign(&msg, &key).unwrap(), sig);
+        assert!(sig[RECOVERY_ID_OFFSET] <= 1);
+
+        let recovered = ecrecover(&msg, &sig).unwrap();
+        assert_eq!(pubkey_to_address(&recovered), addr);
+        let recovered = sig_to_pub(&msg, &sig).unwrap();
+        assert_eq!(recovered.address(), addr);
+        assert!(verify_signature(&recovered, &msg, &sig[..64]));
+
+        // A signature over a different hash recovers a different key.
+        let other = keccak256(b"bar");
+        assert_ne!(sig_to_pub(&other, &sig).ok().map(|k| k.address()), Some(addr));
+        assert!(!verify_signature(&recovered, &other, &sig[..64]));
+
+        assert_eq!(sign(&msg[..31], &key), Err(CryptoError::InvalidHashLength(31)));
+        assert_eq!(ecrecover(&msg[..31], &sig), Err(CryptoError::InvalidHashLength(31)));
+    }
+
+    #[test]
+    fn test_high_s_rejected() {
+        let key = key(TEST_PRIV_HEX);
+        let msg = keccak256(b"foo");
+        let sig = sign(&msg, &key).unwrap();
+
+        // Flip s to its high form, N - s, which also flips the recovery id.
+        let s = Uint256::from_bytes(&sig[32..64]);
+        let mut high = sig;
+        high[32..64].copy_from_slice(&SECP256K1_N.wrapping_sub(&s).to_bytes32());
+        high[RECOVERY_ID_OFFSET] ^= 1;
+
+        let pubkey = key.public_key();
+        assert!(verify_signature(&pubkey, &msg, &sig[..64]));
+        assert!(!verify_signature(&pubkey, &msg, &high[..64]));
+
+        let r = Uint256::from_bytes(&high[..32]);
+        let s = Uint256::from_bytes(&high[32..64]);
+        assert!(!validate_signature_values(0, &r, &s, true));
+        // Frontier accepts the full range of s.
+        assert!(validate_signature_values(0, &r, &s, false));
+        // The high-s form is still the same signature for ecrecover.
+        assert_eq!(sig_to_pub(&msg, &high).unwrap(), pubkey);
+    }
+
+    #[test]
+    fn test_out_of_range_rejected() {
+        let key = key(TEST_PRIV_HEX);
+        let msg = keccak256(b"foo");
+        let sig = sign(&msg, &key).unwrap();
+        let pubkey = key.public_key();
+        let n = SECP256K1_N.to_bytes32();
+        let zero = [0u8; 32];
+        let one = Uint256::one().to_bytes32();
+
+        for (r, s) in [(&zero[..], &sig[32..64]), (&sig[..32], &zero[..]), (&n[..], &sig[32..64]), (&sig[..32], &n[..])] {
+            let mut bad = sig;
+            bad[..32].copy_from_slice(r);
+            bad[32..64].copy_from_slice(s);
+            assert_eq!(sig_to_pub(&msg, &bad), Err(CryptoError::InvalidSignature));
+            assert!(!verify_signature(&pubkey, &msg, &bad[..64]));
+            let (r, s) = (Uint256::from_bytes(r), Uint256::from_bytes(s));
+            assert!(!validate_signature_values(0, &r, &s, false));
+        }
+
+        let mut bad = sig;
+        bad[RECOVERY_ID_OFFSET] = 4;
+        assert_eq!(sig_to_pub(&msg, &bad), Err(CryptoError::InvalidRecoveryId));
+        assert_eq!(sig_to_pub(&msg, &sig[..64]), Err(CryptoError::InvalidSignature));
+        let (r, s) = (Uint256::from_bytes(&one), Uint256::from_bytes(&one));
+        assert!(!validate_signature_values(2, &r, &s, false));
+        assert!(validate_signature_values(1, &r, &s, true));
+
+        assert_eq!(to_ecdsa(&zero), Err(CryptoError::InvalidPrivateKey));
+        assert_eq!(to_ecdsa(&n), Err(CryptoError::InvalidPrivateKey));
+    }
+
+    #[test]
+    fn test_generate_key() {
+        let (a, b) = (generate_key().unwrap(), generate_key().unwrap());
+        assert_ne!(a, b);
+        assert_eq!(to_ecdsa(&from_ecdsa(&a)).unwrap(), a);
+        let msg = keccak256(b"foo");
+        let sig = sign(&msg, &a).unwrap();
+        assert_eq!(sig_to_pub(&msg, &sig).unwrap(), a.public_key());
+    }
+
+    #[test]
+    fn test_create_address() {
+        let addr = common::Address::from_slice(&unhex(TEST_ADDR_HEX));
+        let expected = [
+            "333c3310824b7c685133f2bedb2ca4b8b4df633d",
+            "8bda78331c916a08481428e4b07c96d3e916d165",
+            "c9ddedf451bc62ce88bf9292afb13df35b670699",
+        ];
+        for (nonce, want) in expected.iter().enumerate() {
+            assert_eq!(hex::encode(create_address(&addr, nonce as u64).as_bytes()), *want);
+        }
+    }
+
+    // Examples from EIP-1014.
+    #[test]
+    fn test_create_address2() {
+        let mut cafebabe = [0u8; 32];
+        cafebabe[28..].copy_from_slice(&[0xca, 0xfe, 0xba, 0xbe]);
+        let vectors = [
+            ("0000000000000000000000000000000000000000", [0u8; 32], "00", "4d1a2e2bb4f88f0250f26ffff098b0b30b26bf38"),
+            ("deadbeef00000000000000000000000000000000", [0u8; 32], "00", "b928f69bb1d91cd65274e3c79d8986362984fda3"),
+            ("00000000000000000000000000000000deadbeef", cafebabe, "deadbeef", "60f3f640a8508fc6a86d45df051962668e1e8ac7"),
+            ("0000000000000000000000000000000000000000", [0u8; 32], "", "e33c0c7f7df4809055c3eba6c09cfe4baf1bd9e0"),
+        ];
+        for (from, salt, code, want) in vectors {
+            let from = common::Address::from_slice(&unhex(from));
+            let addr = create_address2(&from, &salt, &keccak256(&unhex(code)));
+            assert_eq!(hex::encode(addr.as_bytes()), want);
+        }
+    }
+}
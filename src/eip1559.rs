@@ -0,0 +1,294 @@
+// Package eip1559 implements the fee market of "EIP-1559: Fee market change
+// for ETH 1.0 chain": the per-block base fee update rule and the header
+// checks of the gas limit and base fee.
+//
+// https://eips.ethereum.org/EIPS/eip-1559
+
+use std::fmt;
+
+use crate::block::Header;
+use crate::chain_config::ChainConfig;
+use crate::params;
+use uint256::Uint256;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FeeError {
+    // GasLimitInvalid is returned if the gas limit changed by more than
+    // 1/1024 of the parent's.
+    GasLimitInvalid {
+        have: u64,
+        want: u64,
+        limit: u64,
+    },
+    // GasLimitTooLow is returned if the gas limit is below MIN_GAS_LIMIT.
+    GasLimitTooLow {
+        have: u64,
+        min: u64,
+    },
+    MissingBaseFee,
+    // BaseFeeMismatch is returned if the header base fee doesn't follow the
+    // update rule.
+    BaseFeeMismatch {
+        have: Uint256,
+        want: Uint256,
+        parent_base_fee: Uint256,
+        parent_gas_used: u64,
+    },
+}
+
+impl fmt::Display for FeeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FeeError::GasLimitInvalid { have, want, limit } => {
+                write!(
+                    f,
+                    "invalid gas limit: have {}, want {} +-= {}",
+                    have,
+                    want,
+                    limit.saturating_sub(1)
+                )
+            }
+            FeeError::GasLimitTooLow { have, min } => {
+                write!(f, "invalid gas limit below {}: have {}", min, have)
+            }
+            FeeError::MissingBaseFee => write!(f, "header is missing baseFee"),
+            FeeError::BaseFeeMismatch {
+                have,
+                want,
+                parent_base_fee,
+                parent_gas_used,
+            } => write!(
+                f,
+                "invalid baseFee: have {}, want {}, parentBaseFee {}, parentGasUsed {}",
+                have.low_u64(),
+                want.low_u64(),
+                parent_base_fee.low_u64(),
+                parent_gas_used
+            ),
+        }
+    }
+}
+
+impl std::error::Error for FeeError {}
+
+// div_uint64 returns x / d, rounding towards zero. d must not be zero.
+pub(crate) fn div_uint64(x: &Uint256, d: u64) -> Uint256 {
+    let d = d as u128;
+    let mut quotient = [0u8; 32];
+    let mut rem = 0u128;
+    for (i, byte) in x.to_bytes32().iter().enumerate() {
+        rem = (rem << 8) | *byte as u128;
+        quotient[i] = (rem / d) as u8;
+        rem %= d;
+    }
+    Uint256::from_bytes(&quotient)
+}
+
+// mul_div returns x * m / d, rounding towards zero, or None if the result
+// doesn't fit 256 bits. The product is split over the quotient and the
+// remainder of x / d, so it never overflows while the result fits. d must
+// not be zero.
+pub(crate) fn mul_div(x: &Uint256, m: u64, d: &Uint256) -> Option<Uint256> {
+    let m = Uint256::from_uint64(m);
+    let (quotient, rem) = x.div_rem(d);
+    let high = quotient.checked_mul(&m)?;
+    // rem < d, so this can only overflow if d is wider than 192 bits.
+    let low = rem.checked_mul(&m)?.div_rem(d).0;
+    high.checked_add(&low)
+}
+
+// verify_gaslimit verifies the header gas limit according increase/decrease
+// in relation to the parent gas limit.
+pub fn verify_gaslimit(parent_gas_limit: u64, header_gas_limit: u64) -> Result<(), FeeError> {
+    // Verify that the gas limit remains within allowed bounds
+    let diff = parent_gas_limit.abs_diff(header_gas_limit);
+    let limit = parent_gas_limit / params::GAS_LIMIT_BOUND_DIVISOR;
+    if diff >= limit {
+        return Err(FeeError::GasLimitInvalid {
+            have: header_gas_limit,
+            want: parent_gas_limit,
+            limit,
+        });
+    }
+    if header_gas_limit < params::MIN_GAS_LIMIT {
+        return Err(FeeError::GasLimitTooLow {
+            have: header_gas_limit,
+            min: params::MIN_GAS_LIMIT,
+        });
+    }
+    Ok(())
+}
+
+// parent_gas_limit returns the gas limit the header following parent is
+// bound to. At the London transition the gas limit is scaled by the
+// elasticity multiplier, so that the gas target equals the former limit.
+pub fn parent_gas_limit(config: &ChainConfig, parent: &Header) -> u64 {
+    if !config.is_london(parent.number) && config.is_london(parent.number + 1) {
+        parent.gas_limit * params::ELASTICITY_MULTIPLIER
+    } else {
+        parent.gas_limit
+    }
+}
+
+// verify_eip1559_header verifies some header attributes which were changed in
+// EIP-1559:
+// - gas limit check
+// - basefee check
+pub fn verify_eip1559_header(
+    config: &ChainConfig,
+    parent: &Header,
+    header: &Header,
+) -> Result<(), FeeError> {
+    // Verify that the gas limit remains within allowed bounds
+    verify_gaslimit(parent_gas_limit(config, parent), header.gas_limit)?;
+    // Verify the header is not malformed
+    let base_fee = header.base_fee.as_ref().ok_or(FeeError::MissingBaseFee)?;
+    // Verify the baseFee is correct based on the parent header.
+    let expected = calc_base_fee(config, parent);
+    if *base_fee != expected {
+        return Err(FeeError::BaseFeeMismatch {
+            have: base_fee.clone(),
+            want: expected,
+            parent_base_fee: parent.base_fee.clone().unwrap_or_default(),
+            parent_gas_used: parent.gas_used,
+        });
+    }
+    Ok(())
+}
+
+// calc_base_fee calculates the basefee of the header.
+pub fn calc_base_fee(config: &ChainConfig, parent: &Header) -> Uint256 {
+    // If the current block is the first EIP-1559 block, return the InitialBaseFee.
+    if !config.is_london(parent.number) {
+        return Uint256::from_uint64(params::INITIAL_BASE_FEE);
+    }
+
+    let parent_gas_target = parent.gas_limit / params::ELASTICITY_MULTIPLIER;
+    let parent_base_fee = parent.base_fee.clone().unwrap_or_default();
+    // If the parent gasUsed is the same as the target, the baseFee remains unchanged.
+    if parent.gas_used == parent_gas_target || parent_gas_target == 0 {
+        return parent_base_fee;
+    }
+
+    let target = Uint256::from_uint64(parent_gas_target);
+    if parent.gas_used > parent_gas_target {
+        // If the parent block used more gas than its target, the baseFee should increase.
+        // max(1, parentBaseFee * gasUsedDelta / parentGasTarget / baseFeeChangeDenominator)
+        // A base fee that no longer fits 256 bits saturates.
+        let gas_used_delta = parent.gas_used - parent_gas_target;
+        let mut base_fee_delta = match mul_div(&parent_base_fee, gas_used_delta, &target) {
+            Some(num) => div_uint64(&num, params::BASE_FEE_CHANGE_DENOMINATOR),
+            None => return Uint256::max_value(),
+        };
+        if base_fee_delta.is_zero() {
+            base_fee_delta = Uint256::from_uint64(1);
+        }
+        parent_base_fee.saturating_add(&base_fee_delta)
+    } else {
+        // Otherwise if the parent block used less gas than its target, the baseFee should decrease.
+        // max(0, parentBaseFee * gasUsedDelta / parentGasTarget / baseFeeChangeDenominator)
+        // The delta is at most the parent base fee, so neither step overflows.
+        let gas_used_delta = parent_gas_target - parent.gas_used;
+        let num = mul_div(&parent_base_fee, gas_used_delta, &target).unwrap_or_default();
+        let base_fee_delta = div_uint64(&num, params::BASE_FEE_CHANGE_DENOMINATOR);
+        parent_base_fee.saturating_sub(&base_fee_delta)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> ChainConfig {
+        ChainConfig {
+            london_block: Some(5),
+            ..Default::default()
+        }
+    }
+
+    fn parent(number: u64, gas_limit: u64, gas_used: u64, base_fee: Uint256) -> Header {
+        Header {
+            number,
+            gas_limit,
+            gas_used,
+            base_fee: Some(base_fee),
+            ..Default::default()
+        }
+    }
+
+    // TestBlockGasLimits tests the gaslimit checks for blocks both across
+    // the EIP-1559 boundary and post-1559 blocks.
+    #[test]
+    fn test_block_gas_limits() {
+        let initial = Uint256::from_uint64(params::INITIAL_BASE_FEE);
+        let tests: [(u64, u64, u64, bool); 10] = [
+            // Transition from non-london to london
+            (10_000_000, 4, 20_000_000, true),  // No change
+            (10_000_000, 4, 20_019_530, true),  // Upper limit
+            (10_000_000, 4, 20_019_531, false), // Upper +1
+            (10_000_000, 4, 19_980_470, true),  // Lower limit
+            (10_000_000, 4, 19_980_469, false), // Lower limit -1
+            // London to London
+            (20_000_000, 5, 20_000_000, true),
+            (20_000_000, 5, 20_019_530, true),  // Upper limit
+            (20_000_000, 5, 20_019_531, false), // Upper limit +1
+            (20_000_000, 5, 19_980_470, true),  // Lower limit
+            (20_000_000, 5, 19_980_469, false), // Lower limit -1
+        ];
+        for (i, (gas_limit, number, header_gas_limit, ok)) in tests.into_iter().enumerate() {
+            let parent = parent(number, gas_limit, gas_limit / 2, initial.clone());
+            let header = Header {
+                number: number + 1,
+                gas_limit: header_gas_limit,
+                base_fee: Some(initial.clone()),
+                ..Default::default()
+            };
+            let res = verify_eip1559_header(&config(), &parent, &header);
+            assert_eq!(res.is_ok(), ok, "test {}: {:?}", i, res);
+        }
+    }
+
+    // TestCalcBaseFee assumes all blocks are 1559-blocks
+    #[test]
+    fn test_calc_base_fee() {
+        let tests: [(u64, u64, u64, u64); 3] = [
+            (params::INITIAL_BASE_FEE, 20_000_000, 10_000_000, params::INITIAL_BASE_FEE), // usage == target
+            (params::INITIAL_BASE_FEE, 20_000_000, 9_000_000, 987_500_000),               // usage below target
+            (params::INITIAL_BASE_FEE, 20_000_000, 11_000_000, 1_012_500_000),            // usage above target
+        ];
+        for (i, (base_fee, gas_limit, gas_used, want)) in tests.into_iter().enumerate() {
+            let parent = parent(32, gas_limit, gas_used, Uint256::from_uint64(base_fee));
+            assert_eq!(calc_base_fee(&config(), &parent), Uint256::from_uint64(want), "test {}", i);
+        }
+
+        // The first London block starts at the initial base fee
+        let parent = Header {
+            number: 4,
+            gas_limit: 10_000_000,
+            ..Default::default()
+        };
+        assert_eq!(calc_base_fee(&config(), &parent), Uint256::from_uint64(params::INITIAL_BASE_FEE));
+
+        // The increase is at least one wei
+        let parent = self::parent(32, 20_000_000, 10_000_001, Uint256::from_uint64(1));
+        assert_eq!(calc_base_fee(&config(), &parent), Uint256::from_uint64(2));
+    }
+
+    #[test]
+    fn test_calc_base_fee_overflow() {
+        // A product wider than 256 bits is still computed exactly
+        let big = Uint256::max_value().rsh(1);
+        let parent = parent(32, 20_000_000, 9_000_000, big.clone());
+        let delta = div_uint64(&div_uint64(&big, 10), params::BASE_FEE_CHANGE_DENOMINATOR);
+        assert_eq!(calc_base_fee(&config(), &parent), big.checked_sub(&delta).unwrap());
+        let parent = self::parent(32, 20_000_000, 11_000_000, big.clone());
+        assert_eq!(calc_base_fee(&config(), &parent), big.checked_add(&delta).unwrap());
+
+        // An increase past 256 bits saturates
+        let max = Uint256::max_value();
+        let parent = self::parent(32, 20_000_000, 20_000_000, max.clone());
+        assert_eq!(calc_base_fee(&config(), &parent), max);
+        let parent = self::parent(32, 20_000_000, 10_000_001, max.clone());
+        assert_eq!(calc_base_fee(&config(), &parent), max);
+    }
+}
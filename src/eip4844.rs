@@ -0,0 +1,224 @@
+// Package eip4844 implements the blob gas market of "EIP-4844: Shard Blob
+// Transactions": the excess blob gas accounting and the blob base fee
+// derived from it.
+//
+// https://eips.ethereum.org/EIPS/eip-4844
+
+use std::fmt;
+
+use crate::block::Header;
+use crate::eip1559::{div_uint64, mul_div};
+use crate::params;
+use uint256::Uint256;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BlobGasError {
+    MissingExcessBlobGas,
+    MissingBlobGasUsed,
+    // BlobGasUsedExceedsMax is returned if the block uses more blob gas than
+    // MAX_BLOB_GAS_PER_BLOCK.
+    BlobGasUsedExceedsMax {
+        have: u64,
+        max: u64,
+    },
+    // BlobGasUsedNotMultiple is returned if the blob gas used is not a
+    // multiple of the blob gas of a single blob.
+    BlobGasUsedNotMultiple {
+        have: u64,
+    },
+    ExcessBlobGasMismatch {
+        have: u64,
+        want: u64,
+        parent_excess: u64,
+        parent_used: u64,
+    },
+}
+
+impl fmt::Display for BlobGasError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BlobGasError::MissingExcessBlobGas => write!(f, "header is missing excessBlobGas"),
+            BlobGasError::MissingBlobGasUsed => write!(f, "header is missing blobGasUsed"),
+            BlobGasError::BlobGasUsedExceedsMax { have, max } => {
+                write!(f, "blob gas used {} exceeds maximum allowance {}", have, max)
+            }
+            BlobGasError::BlobGasUsedNotMultiple { have } => write!(
+                f,
+                "blob gas used {} not a multiple of blob gas per blob {}",
+                have,
+                params::BLOB_TX_BLOB_GAS_PER_BLOB
+            ),
+            BlobGasError::ExcessBlobGasMismatch { have, want, parent_excess, parent_used } => write!(
+                f,
+                "invalid excessBlobGas: have {}, want {}, parent excessBlobGas {}, parent blobDataUsed {}",
+                have, want, parent_excess, parent_used
+            ),
+        }
+    }
+}
+
+impl std::error::Error for BlobGasError {}
+
+// verify_eip4844_header verifies the presence of the excessBlobGas field and
+// that if the current block contains no transactions, the excessBlobGas is
+// updated accordingly.
+pub fn verify_eip4844_header(parent: &Header, header: &Header) -> Result<(), BlobGasError> {
+    // Verify the header is not malformed
+    let excess_blob_gas = header
+        .excess_blob_gas
+        .ok_or(BlobGasError::MissingExcessBlobGas)?;
+    let blob_gas_used = header
+        .blob_gas_used
+        .ok_or(BlobGasError::MissingBlobGasUsed)?;
+    // Verify that the blob gas used remains within reasonable limits.
+    if blob_gas_used > params::MAX_BLOB_GAS_PER_BLOCK {
+        return Err(BlobGasError::BlobGasUsedExceedsMax {
+            have: blob_gas_used,
+            max: params::MAX_BLOB_GAS_PER_BLOCK,
+        });
+    }
+    if blob_gas_used % params::BLOB_TX_BLOB_GAS_PER_BLOB != 0 {
+        return Err(BlobGasError::BlobGasUsedNotMultiple {
+            have: blob_gas_used,
+        });
+    }
+    // Verify the excessBlobGas is correct based on the parent header
+    let parent_excess = parent.excess_blob_gas.unwrap_or(0);
+    let parent_used = parent.blob_gas_used.unwrap_or(0);
+    let expected = calc_excess_blob_gas(parent_excess, parent_used);
+    if excess_blob_gas != expected {
+        return Err(BlobGasError::ExcessBlobGasMismatch {
+            have: excess_blob_gas,
+            want: expected,
+            parent_excess,
+            parent_used,
+        });
+    }
+    Ok(())
+}
+
+// calc_excess_blob_gas calculates the excess blob gas after applying the set
+// of blobs on top of the excess blob gas. The sum saturates instead of
+// wrapping around.
+pub fn calc_excess_blob_gas(parent_excess_blob_gas: u64, parent_blob_gas_used: u64) -> u64 {
+    let excess_blob_gas = parent_excess_blob_gas.saturating_add(parent_blob_gas_used);
+    excess_blob_gas.saturating_sub(params::BLOB_TX_TARGET_BLOB_GAS_PER_BLOCK)
+}
+
+// calc_blob_fee calculates the blobfee from the header's excess blob gas
+// field. A fee too large for 256 bits saturates, which prices out every blob.
+pub fn calc_blob_fee(excess_blob_gas: u64) -> Uint256 {
+    fake_exponential(
+        params::BLOB_TX_MIN_BLOB_GASPRICE,
+        excess_blob_gas,
+        params::BLOB_TX_BLOB_GASPRICE_UPDATE_FRACTION,
+    )
+    .unwrap_or_else(Uint256::max_value)
+}
+
+// fake_exponential approximates factor * e ** (numerator / denominator) using
+// Taylor expansion. It returns None if the sum of the series overflows 256
+// bits. denominator must not be zero.
+fn fake_exponential(factor: u64, numerator: u64, denominator: u64) -> Option<Uint256> {
+    let denom = Uint256::from_uint64(denominator);
+    let mut output = Uint256::default();
+    let mut accum = Uint256::from_uint64(factor).checked_mul(&denom)?;
+    let mut i = 1u64;
+    while !accum.is_zero() {
+        output = output.checked_add(&accum)?;
+
+        // The next term only exceeds 256 bits if the sum does as well.
+        let divisor = denom.checked_mul(&Uint256::from_uint64(i))?;
+        accum = mul_div(&accum, numerator, &divisor)?;
+        i += 1;
+    }
+    Some(div_uint64(&output, denominator))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_calc_excess_blob_gas() {
+        let per_blob = params::BLOB_TX_BLOB_GAS_PER_BLOB;
+        let target_blobs = params::BLOB_TX_TARGET_BLOB_GAS_PER_BLOCK / per_blob;
+        let tests: [(u64, u64, u64); 10] = [
+            // The excess blob gas should not increase from zero if the used
+            // blob slots are below - or equal - to the target.
+            (0, 0, 0),
+            (0, 1, 0),
+            (0, target_blobs, 0),
+            // If the target blob gas is exceeded, the excessBlobGas should
+            // increase by however much it was overshot
+            (0, target_blobs + 1, per_blob),
+            (1, target_blobs + 1, per_blob + 1),
+            (1, target_blobs + 2, 2 * per_blob + 1),
+            // The excess blob gas should decrease by however much the target
+            // was under-shot, capped at zero.
+            (params::BLOB_TX_TARGET_BLOB_GAS_PER_BLOCK, target_blobs, params::BLOB_TX_TARGET_BLOB_GAS_PER_BLOCK),
+            (params::BLOB_TX_TARGET_BLOB_GAS_PER_BLOCK, target_blobs - 1, params::BLOB_TX_TARGET_BLOB_GAS_PER_BLOCK - per_blob),
+            (params::BLOB_TX_TARGET_BLOB_GAS_PER_BLOCK, target_blobs - 2, params::BLOB_TX_TARGET_BLOB_GAS_PER_BLOCK - 2 * per_blob),
+            (per_blob - 1, target_blobs - 1, 0),
+        ];
+        for (i, (excess, blobs, want)) in tests.into_iter().enumerate() {
+            assert_eq!(calc_excess_blob_gas(excess, blobs * per_blob), want, "test {}", i);
+        }
+        // The sum saturates instead of wrapping around
+        assert_eq!(
+            calc_excess_blob_gas(u64::MAX, per_blob),
+            u64::MAX - params::BLOB_TX_TARGET_BLOB_GAS_PER_BLOCK
+        );
+    }
+
+    #[test]
+    fn test_calc_blob_fee() {
+        let tests: [(u64, u64); 4] = [(0, 1), (2314057, 1), (2314058, 2), (10 * 1024 * 1024, 23)];
+        for (excess, want) in tests {
+            assert_eq!(calc_blob_fee(excess), Uint256::from_uint64(want), "excess {}", excess);
+        }
+        // A fee too large for 256 bits saturates
+        assert_eq!(calc_blob_fee(u64::MAX), Uint256::max_value());
+    }
+
+    #[test]
+    fn test_fake_exponential() {
+        let tests: [(u64, u64, u64, u64); 15] = [
+            // When numerator == 0 the return value should always equal the value of factor
+            (1, 0, 1, 1),
+            (38493, 0, 1000, 38493),
+            (0, 1234, 2345, 0), // should be 0
+            (1, 2, 1, 6),       // approximate 7.389
+            (1, 4, 2, 6),
+            (1, 3, 1, 16), // approximate 20.09
+            (1, 6, 2, 18),
+            (1, 4, 1, 49), // approximate 54.60
+            (1, 8, 2, 50),
+            (10, 8, 2, 542), // approximate 540.598
+            (11, 8, 2, 596), // approximate 600.58
+            (1, 5, 1, 136),  // approximate 148.4
+            (1, 5, 2, 11),   // approximate 12.18
+            (2, 5, 2, 23),   // approximate 24.36
+            (1, 50000000, 2225652, 5709098764),
+        ];
+        for (factor, num, denom, want) in tests {
+            assert_eq!(
+                fake_exponential(factor, num, denom),
+                Some(Uint256::from_uint64(want)),
+                "factor {} num {} denom {}",
+                factor,
+                num,
+                denom
+            );
+        }
+
+        // factor * denominator is computed in 256 bits
+        assert_eq!(
+            fake_exponential(u64::MAX, 0, u64::MAX),
+            Some(Uint256::from_uint64(u64::MAX))
+        );
+        // e ** 180 is close to 2 ** 260
+        assert_eq!(fake_exponential(1, 180, 1), None);
+        assert!(fake_exponential(1, 170, 1).is_some());
+    }
+}
@@ -0,0 +1,141 @@
+use crate::gas::{GAS_FASTEST_STEP, GAS_FAST_STEP, GAS_QUICK_STEP};
+use crate::gas_table;
+use crate::instructions;
+use crate::jump_table::{JumpTable, Operation};
+use crate::memory_table;
+use crate::op_code::*;
+use crate::params;
+use crate::stack_table::{max_stack, min_stack};
+
+// enable_1884 applies EIP-1884 to the given jump table:
+// - Increase cost of BALANCE to 700
+// - Increase cost of EXTCODEHASH to 700
+// - Increase cost of SLOAD to 800
+// - Define SELFBALANCE, with cost GasFastStep (5)
+pub fn enable_1884(jt: &mut JumpTable) {
+    // Gas cost changes
+    jt[SLOAD as usize].constant_gas = params::SLOAD_GAS_EIP1884;
+    jt[BALANCE as usize].constant_gas = params::BALANCE_GAS_EIP1884;
+    jt[EXTCODEHASH as usize].constant_gas = params::EXTCODEHASH_GAS_EIP1884;
+
+    // New opcode
+    jt[SELFBALANCE as usize] = Operation::new(instructions::op_self_balance, GAS_FAST_STEP, 0, 1);
+}
+
+// enable_1344 applies EIP-1344 (ChainID Opcode)
+// - Adds an opcode that returns the current chain’s EIP-155 unique identifier
+pub fn enable_1344(jt: &mut JumpTable) {
+    // New opcode
+    jt[CHAINID as usize] = Operation::new(instructions::op_chain_id, GAS_QUICK_STEP, 0, 1);
+}
+
+// enable_2200 applies EIP-2200 (Rebalance net-metered SSTORE)
+pub fn enable_2200(jt: &mut JumpTable) {
+    jt[SLOAD as usize].constant_gas = params::SLOAD_GAS_EIP2200;
+    jt[SSTORE as usize].dynamic_gas = Some(gas_table::gas_sstore_eip2200);
+}
+
+// enable_2929 enables "EIP-2929: Gas cost increases for state access opcodes"
+// https://eips.ethereum.org/EIPS/eip-2929
+pub fn enable_2929(jt: &mut JumpTable) {
+    jt[SSTORE as usize].dynamic_gas = Some(gas_table::gas_sstore_eip2929);
+
+    jt[SLOAD as usize].constant_gas = 0;
+    jt[SLOAD as usize].dynamic_gas = Some(gas_table::gas_sload_eip2929);
+
+    jt[EXTCODECOPY as usize].constant_gas = params::WARM_STORAGE_READ_COST_EIP2929;
+    jt[EXTCODECOPY as usize].dynamic_gas = Some(gas_table::gas_ext_code_copy_eip2929);
+
+    jt[EXTCODESIZE as usize].constant_gas = params::WARM_STORAGE_READ_COST_EIP2929;
+    jt[EXTCODESIZE as usize].dynamic_gas = Some(gas_table::gas_eip2929_account_check);
+
+    jt[EXTCODEHASH as usize].constant_gas = params::WARM_STORAGE_READ_COST_EIP2929;
+    jt[EXTCODEHASH as usize].dynamic_gas = Some(gas_table::gas_eip2929_account_check);
+
+    jt[BALANCE as usize].constant_gas = params::WARM_STORAGE_READ_COST_EIP2929;
+    jt[BALANCE as usize].dynamic_gas = Some(gas_table::gas_eip2929_account_check);
+
+    jt[CALL as usize].constant_gas = params::WARM_STORAGE_READ_COST_EIP2929;
+    jt[CALL as usize].dynamic_gas = Some(gas_table::gas_call_eip2929);
+
+    jt[CALLCODE as usize].constant_gas = params::WARM_STORAGE_READ_COST_EIP2929;
+    jt[CALLCODE as usize].dynamic_gas = Some(gas_table::gas_call_code_eip2929);
+
+    jt[STATICCALL as usize].constant_gas = params::WARM_STORAGE_READ_COST_EIP2929;
+    jt[STATICCALL as usize].dynamic_gas = Some(gas_table::gas_static_call_eip2929);
+
+    jt[DELEGATECALL as usize].constant_gas = params::WARM_STORAGE_READ_COST_EIP2929;
+    jt[DELEGATECALL as usize].dynamic_gas = Some(gas_table::gas_delegate_call_eip2929);
+
+    // This was previously part of the dynamic cost, but we're using it as a constant_gas
+    // factor here
+    jt[SELFDESTRUCT as usize].constant_gas = params::SELFDESTRUCT_GAS_EIP150;
+    jt[SELFDESTRUCT as usize].dynamic_gas = Some(gas_table::gas_selfdestruct_eip2929);
+}
+
+// enable_3529 enabled "EIP-3529: Reduction in refunds":
+// - Removes refunds for selfdestructs
+// - Reduces refunds for SSTORE
+// - Reduces max refunds to 20% gas
+pub fn enable_3529(jt: &mut JumpTable) {
+    jt[SSTORE as usize].dynamic_gas = Some(gas_table::gas_sstore_eip3529);
+    jt[SELFDESTRUCT as usize].dynamic_gas = Some(gas_table::gas_selfdestruct_eip3529);
+}
+
+// enable_3198 applies EIP-3198 (BASEFEE Opcode)
+// - Adds an opcode that returns the current block's base fee.
+pub fn enable_3198(jt: &mut JumpTable) {
+    // New opcode
+    jt[BASEFEE as usize] = Operation::new(instructions::op_base_fee, GAS_QUICK_STEP, 0, 1);
+}
+
+// enable_1153 applies EIP-1153 "Transient Storage"
+// - Adds TLOAD that reads from transient storage
+// - Adds TSTORE that writes to transient storage
+pub fn enable_1153(jt: &mut JumpTable) {
+    jt[TLOAD as usize] = Operation::new(instructions::op_tload, params::TLOAD_GAS, 1, 1);
+
+    jt[TSTORE as usize] = Operation::new(instructions::op_tstore, params::TSTORE_GAS, 2, 0);
+}
+
+// enable_3855 applies EIP-3855 (PUSH0 opcode)
+pub fn enable_3855(jt: &mut JumpTable) {
+    // New opcode
+    jt[PUSH0 as usize] = Operation::new(instructions::op_push0, GAS_QUICK_STEP, 0, 1);
+}
+
+// enable_3860 enables "EIP-3860: Limit and meter initcode"
+// https://eips.ethereum.org/EIPS/eip-3860
+pub fn enable_3860(jt: &mut JumpTable) {
+    jt[CREATE as usize].dynamic_gas = Some(gas_table::gas_create_eip3860);
+    jt[CREATE2 as usize].dynamic_gas = Some(gas_table::gas_create2_eip3860);
+}
+
+// enable_5656 enables EIP-5656 (MCOPY opcode)
+// https://eips.ethereum.org/EIPS/eip-5656
+pub fn enable_5656(jt: &mut JumpTable) {
+    jt[MCOPY as usize] = Operation {
+        execute: instructions::op_mcopy,
+        constant_gas: GAS_FASTEST_STEP,
+        dynamic_gas: Some(gas_table::gas_mcopy),
+        min_stack: min_stack(3, 0),
+        max_stack: max_stack(3, 0),
+        memory_size: Some(memory_table::memory_mcopy),
+        undefined: false,
+    };
+}
+
+// enable_4844 applies EIP-4844 (BLOBHASH opcode)
+pub fn enable_4844(jt: &mut JumpTable) {
+    jt[BLOBHASH as usize] = Operation::new(instructions::op_blob_hash, GAS_FASTEST_STEP, 1, 1);
+}
+
+// enable_7516 applies EIP-7516 (BLOBBASEFEE opcode)
+pub fn enable_7516(jt: &mut JumpTable) {
+    jt[BLOBBASEFEE as usize] = Operation::new(instructions::op_blob_base_fee, GAS_QUICK_STEP, 0, 1);
+}
+
+// enable_6780 applies EIP-6780 (deactivate SELFDESTRUCT)
+pub fn enable_6780(jt: &mut JumpTable) {
+    jt[SELFDESTRUCT as usize].execute = instructions::op_selfdestruct6780;
+}
@@ -0,0 +1,74 @@
+use std::fmt;
+
+// Error lists the errors that can be returned by the interpreter and the EVM
+// while executing a frame.
+//
+// Apart from ExecutionReverted, every error is considered a
+// revert-and-consume-all-gas operation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    OutOfGas,
+    CodeStoreOutOfGas,
+    Depth,
+    InsufficientBalance,
+    ContractAddressCollision,
+    ExecutionReverted,
+    MaxCodeSizeExceeded,
+    InvalidJump,
+    WriteProtection,
+    ReturnDataOutOfBounds,
+    GasUintOverflow,
+    InvalidCode,
+    NonceUintOverflow,
+    MaxInitCodeSizeExceeded,
+    InvalidPrecompileInput,
+    BlobVerifyInvalidInputLength,
+    BlobMismatchedVersion,
+    BlobVerifyKZGProof,
+    StackUnderflow,
+    StackOverflow,
+    Overflow,
+    MissingOperand,
+    DivisionByZero,
+    ConversionError,
+    InvalidOpCode { opcode: u8 },
+
+    // StopToken is an internal token used to signal the interpreter loop
+    // to halt; it is never returned to the caller of run.
+    StopToken,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::OutOfGas => write!(f, "out of gas"),
+            Error::CodeStoreOutOfGas => write!(f, "contract creation code storage out of gas"),
+            Error::Depth => write!(f, "max call depth exceeded"),
+            Error::InsufficientBalance => write!(f, "insufficient balance for transfer"),
+            Error::ContractAddressCollision => write!(f, "contract address collision"),
+            Error::ExecutionReverted => write!(f, "execution reverted"),
+            Error::MaxCodeSizeExceeded => write!(f, "max code size exceeded"),
+            Error::InvalidJump => write!(f, "invalid jump destination"),
+            Error::WriteProtection => write!(f, "write protection"),
+            Error::ReturnDataOutOfBounds => write!(f, "return data out of bounds"),
+            Error::GasUintOverflow => write!(f, "gas uint64 overflow"),
+            Error::InvalidCode => write!(f, "invalid code: must not begin with 0xef"),
+            Error::NonceUintOverflow => write!(f, "nonce uint64 overflow"),
+            Error::MaxInitCodeSizeExceeded => write!(f, "max initcode size exceeded"),
+            Error::InvalidPrecompileInput => write!(f, "invalid precompile input"),
+            Error::BlobVerifyInvalidInputLength => write!(f, "invalid input length"),
+            Error::BlobMismatchedVersion => write!(f, "mismatched versioned hash"),
+            Error::BlobVerifyKZGProof => write!(f, "error verifying kzg proof"),
+            Error::StackUnderflow => write!(f, "stack underflow"),
+            Error::StackOverflow => write!(f, "stack limit reached"),
+            Error::Overflow => write!(f, "uint256 overflow"),
+            Error::MissingOperand => write!(f, "missing operand"),
+            Error::DivisionByZero => write!(f, "division by zero"),
+            Error::ConversionError => write!(f, "value conversion error"),
+            Error::InvalidOpCode { opcode } => write!(f, "invalid opcode: 0x{:x}", opcode),
+            Error::StopToken => write!(f, "stop token"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
@@ -0,0 +1,140 @@
+// Package ethdb defines the interfaces for an Ethereum data store.
+
+use std::collections::HashMap;
+use std::io;
+use std::sync::RwLock;
+
+// IDEAL_BATCH_SIZE defines the size of the data batches should ideally add in
+// one write.
+pub const IDEAL_BATCH_SIZE: usize = 100 * 1024;
+
+// KeyValueStore contains all the methods required to allow handling different
+// key-value data stores backing the high level database.
+pub trait KeyValueStore: Send + Sync {
+    // has retrieves if a key is present in the key-value data store.
+    fn has(&self, key: &[u8]) -> io::Result<bool>;
+    // get retrieves the given key if it's present in the key-value data store.
+    fn get(&self, key: &[u8]) -> io::Result<Option<Vec<u8>>>;
+    // put inserts the given value into the key-value data store.
+    fn put(&self, key: &[u8], value: &[u8]) -> io::Result<()>;
+    // delete removes the key from the key-value data store.
+    fn delete(&self, key: &[u8]) -> io::Result<()>;
+    // write_batch atomically applies all operations of the batch: after a
+    // crash either all or none of them are visible.
+    fn write_batch(&self, batch: &Batch) -> io::Result<()>;
+    // keys returns all keys starting with prefix, in no particular order.
+    fn keys(&self, prefix: &[u8]) -> io::Result<Vec<Vec<u8>>>;
+    // compact reclaims the space held by deleted and overwritten entries.
+    fn compact(&self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+// Batch is a write-only collection of operations that is committed to a
+// store atomically with write_batch.
+#[derive(Debug, Clone, Default)]
+pub struct Batch {
+    // None values are deletions
+    ops: Vec<(Vec<u8>, Option<Vec<u8>>)>,
+    size: usize,
+}
+
+impl Batch {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    // put inserts the given value into the batch for later committing.
+    pub fn put(&mut self, key: &[u8], value: &[u8]) {
+        self.ops.push((key.to_vec(), Some(value.to_vec())));
+        self.size += key.len() + value.len();
+    }
+
+    // delete inserts the key removal into the batch for later committing.
+    pub fn delete(&mut self, key: &[u8]) {
+        self.ops.push((key.to_vec(), None));
+        self.size += key.len();
+    }
+
+    // value_size retrieves the amount of data queued up for writing.
+    pub fn value_size(&self) -> usize {
+        self.size
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ops.is_empty()
+    }
+
+    pub fn ops(&self) -> &[(Vec<u8>, Option<Vec<u8>>)] {
+        &self.ops
+    }
+
+    // reset resets the batch for reuse.
+    pub fn reset(&mut self) {
+        self.ops.clear();
+        self.size = 0;
+    }
+}
+
+// MemoryDatabase is an ephemeral key-value store. Apart from basic data
+// storage functionality it also supports batch writes.
+#[derive(Debug, Default)]
+pub struct MemoryDatabase {
+    db: RwLock<HashMap<Vec<u8>, Vec<u8>>>,
+}
+
+impl MemoryDatabase {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.db.read().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl KeyValueStore for MemoryDatabase {
+    fn has(&self, key: &[u8]) -> io::Result<bool> {
+        Ok(self.db.read().unwrap().contains_key(key))
+    }
+
+    fn get(&self, key: &[u8]) -> io::Result<Option<Vec<u8>>> {
+        Ok(self.db.read().unwrap().get(key).cloned())
+    }
+
+    fn put(&self, key: &[u8], value: &[u8]) -> io::Result<()> {
+        self.db.write().unwrap().insert(key.to_vec(), value.to_vec());
+        Ok(())
+    }
+
+    fn delete(&self, key: &[u8]) -> io::Result<()> {
+        self.db.write().unwrap().remove(key);
+        Ok(())
+    }
+
+    fn write_batch(&self, batch: &Batch) -> io::Result<()> {
+        let mut db = self.db.write().unwrap();
+        for (key, value) in batch.ops() {
+            match value {
+                Some(value) => db.insert(key.clone(), value.clone()),
+                None => db.remove(key),
+            };
+        }
+        Ok(())
+    }
+
+    fn keys(&self, prefix: &[u8]) -> io::Result<Vec<Vec<u8>>> {
+        Ok(self
+            .db
+            .read()
+            .unwrap()
+            .keys()
+            .filter(|key| key.starts_with(prefix))
+            .cloned()
+            .collect())
+    }
+}
@@ -0,0 +1,829 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use crate::chain_config::{ChainConfig, Rules};
+use crate::contract::{AccountRef, CodeAndHash, Contract, ContractRef};
+use crate::crypto;
+use crate::errors::Error;
+use crate::interpreter::EVMInterpreter;
+use crate::jump_table::{self, JumpTable};
+use crate::op_code::{self, OpCode};
+use crate::params;
+use crate::precompiles::{self, run_precompiled_contract, PrecompiledContract, Precompiles};
+use crate::state_db::StateDB;
+use uint256::Uint256;
+
+// empty_code_hash is used by create to ensure deployment is disallowed to already
+// deployed contract addresses (relevant after the account abstraction).
+pub const EMPTY_CODE_HASH: common::Hash = common::Hash([
+    0xc5, 0xd2, 0x46, 0x01, 0x86, 0xf7, 0x23, 0x3c, 0x92, 0x7e, 0x7d, 0xb2, 0xdc, 0xc7, 0x03, 0xc0,
+    0xe5, 0x00, 0xb6, 0x53, 0xca, 0x82, 0x27, 0x3b, 0x7b, 0xfa, 0xd8, 0x04, 0x5d, 0x85, 0xa4, 0x70,
+]);
+
+// GetHashFunc returns the n'th block hash in the blockchain
+// and is used by the BLOCKHASH EVM op code.
+pub type GetHashFunc = Box<dyn Fn(u64) -> common::Hash>;
+
+// BlockContext provides the EVM with auxiliary information. Once provided
+// it shouldn't be modified.
+pub struct BlockContext {
+    // get_hash returns the hash corresponding to n
+    pub get_hash: GetHashFunc,
+
+    // Block information
+    pub coinbase: common::Address, // Provides information for COINBASE
+    pub gas_limit: u64,            // Provides information for GASLIMIT
+    pub block_number: Uint256,     // Provides information for NUMBER
+    pub time: u64,                 // Provides information for TIME
+    pub difficulty: Uint256,       // Provides information for DIFFICULTY
+    pub base_fee: Uint256,         // Provides information for BASEFEE
+    pub blob_base_fee: Uint256,    // Provides information for BLOBBASEFEE
+    pub random: Option<common::Hash>, // Provides information for PREVRANDAO
+}
+
+impl BlockContext {
+    pub fn get_hash(&self, n: u64) -> common::Hash {
+        (self.get_hash)(n)
+    }
+
+    pub fn block_number(&self) -> &Uint256 {
+        &self.block_number
+    }
+}
+
+// TxContext provides the EVM with information about a transaction.
+// All fields can change between transactions.
+#[derive(Debug, Clone, Default)]
+pub struct TxContext {
+    // Message information
+    pub origin: common::Address, // Provides information for ORIGIN
+    pub gas_price: Uint256,      // Provides information for GASPRICE
+    pub blob_hashes: Vec<common::Hash>, // Provides information for BLOBHASH
+}
+
+// Config are the configuration options for the Interpreter
+#[derive(Default)]
+pub struct Config {
+    pub tracer: Option<Box<dyn EVMLogger>>, // Opcode logger
+    pub no_base_fee: bool, // Forces the EIP-1559 base fee to 0 (needed for 0 price calls)
+    pub enable_preimage_recording: bool, // Enables recording of SHA3/keccak preimages
+}
+
+// EVMLogger is used to collect execution traces from an EVM transaction
+// execution. capture_enter and capture_exit are called around every
+// message call and contract creation.
+pub trait EVMLogger {
+    fn capture_enter(
+        &self,
+        typ: OpCode,
+        from: common::Address,
+        to: common::Address,
+        input: &[u8],
+        gas: u64,
+        value: Uint256,
+    );
+    fn capture_exit(&self, output: &[u8], gas_used: u64, err: Option<&Error>);
+}
+
+// can_transfer checks whether there are enough funds in the address' account to make a transfer.
+// This does not take the necessary gas in to account to make the transfer valid.
+pub fn can_transfer(db: &dyn StateDB, addr: &common::Address, amount: &Uint256) -> bool {
+    db.get_balance(addr) >= *amount
+}
+
+// transfer subtracts amount from sender and adds amount to recipient using the given Db
+pub fn transfer(
+    db: &mut dyn StateDB,
+    sender: &common::Address,
+    recipient: &common::Address,
+    amount: &Uint256,
+) {
+    db.sub_balance(sender, amount);
+    db.add_balance(recipient, amount);
+}
+
+// EVM is the Ethereum Virtual Machine base object and provides
+// the necessary tools to run a contract on the given state with
+// the provided context. It should be noted that any error
+// generated through any of the calls should be considered a
+// revert-state-and-consume-all-gas operation, no checks on
+// specific errors should ever be performed. The interpreter makes
+// sure that any errors generated are to be considered faulty code.
+//
+// The EVM should never be reused and is not thread safe.
+pub struct EVM {
+    // context provides auxiliary blockchain related information
+    pub context: BlockContext,
+    pub tx_context: TxContext,
+    // state_db gives access to the underlying state
+    pub state_db: Box<dyn StateDB>,
+    // depth is the current call stack
+    pub depth: i32,
+
+    // chain_config contains information about the current chain
+    pub chain_config: ChainConfig,
+    // chain_rules contains the chain rules for the current epoch
+    pub chain_rules: Rules,
+    // config are the configuration options for the interpreter
+    pub config: Config,
+    // read_only is set while executing below a STATICCALL frame so that
+    // nested frames inherit the write protection.
+    pub read_only: bool,
+    // abort is used to abort the EVM calling operations
+    pub abort: AtomicBool,
+    // call_gas_temp holds the gas available for the current call. This is needed because the
+    // available gas is calculated in gasCall* according to the 63/64 rule and later
+    // applied in op_call*.
+    pub call_gas_temp: u64,
+
+    precompiles: Precompiles,
+    // table is the instruction set of the active fork, shared by all frames
+    table: Arc<JumpTable>,
+}
+
+impl EVM {
+    // new returns a new EVM. The returned EVM is not thread safe and should
+    // only ever be used *once*.
+    pub fn new(
+        block_ctx: BlockContext,
+        tx_ctx: TxContext,
+        state_db: Box<dyn StateDB>,
+        chain_config: ChainConfig,
+        config: Config,
+    ) -> Self {
+        let chain_rules = chain_config.rules(
+            block_ctx.block_number.to_u64(),
+            block_ctx.random.is_some(),
+            block_ctx.time,
+        );
+        let precompiles = precompiles::activate_precompiles(&chain_rules);
+        let table = Arc::new(jump_table::new_instruction_set(&chain_rules));
+        EVM {
+            context: block_ctx,
+            tx_context: tx_ctx,
+            state_db,
+            depth: 0,
+            chain_config,
+            chain_rules,
+            config,
+            read_only: false,
+            abort: AtomicBool::new(false),
+            call_gas_temp: 0,
+            precompiles,
+            table,
+        }
+    }
+
+    // reset resets the EVM with a new transaction context.Reset
+    // This is not threadsafe and should only be done very cautiously.
+    pub fn reset(&mut self, tx_ctx: TxContext, state_db: Box<dyn StateDB>) {
+        self.tx_context = tx_ctx;
+        self.state_db = state_db;
+    }
+
+    // into_state_db consumes the EVM and returns the state it operated on.
+    pub fn into_state_db(self) -> Box<dyn StateDB> {
+        self.state_db
+    }
+
+    // cancel cancels any running EVM operation. This may be called concurrently and
+    // it's safe to be called multiple times.
+    pub fn cancel(&self) {
+        self.abort.store(true, Ordering::SeqCst);
+    }
+
+    // cancelled returns true if cancel has been called
+    pub fn cancelled(&self) -> bool {
+        self.abort.load(Ordering::SeqCst)
+    }
+
+    pub fn origin(&self) -> common::Address {
+        self.tx_context.origin
+    }
+
+    pub fn gas_price(&self) -> &Uint256 {
+        &self.tx_context.gas_price
+    }
+
+    // jump_table returns the instruction set used by the interpreter frames.
+    pub fn jump_table(&self) -> Arc<JumpTable> {
+        Arc::clone(&self.table)
+    }
+
+    fn precompile(&self, addr: &common::Address) -> Option<Arc<dyn PrecompiledContract>> {
+        self.precompiles.get(addr).cloned()
+    }
+
+    // run executes the contract code on a fresh interpreter frame. Reverted
+    // frames hand their REVERT data back through the interpreter's return
+    // data buffer.
+    fn run(&mut self, contract: &mut Contract, input: &[u8], read_only: bool) -> (Vec<u8>, Option<Error>) {
+        let outer_read_only = self.read_only;
+        let read_only = outer_read_only || read_only;
+        self.read_only = read_only;
+
+        let result = EVMInterpreter::new(self, read_only).run(contract, input);
+
+        self.read_only = outer_read_only;
+        result
+    }
+
+    // call executes the contract associated with the addr with the given input as
+    // parameters. It also handles any necessary value transfer required and takes
+    // the necessary steps to create accounts and reverses the state in case of an
+    // execution error or failed value transfer.
+    pub fn call(
+        &mut self,
+        caller: &dyn ContractRef,
+        addr: common::Address,
+        input: &[u8],
+        gas: u64,
+        value: Uint256,
+    ) -> (Vec<u8>, u64, Option<Error>) {
+        // Fail if we're trying to execute above the call depth limit
+        if self.depth > params::CALL_CREATE_DEPTH as i32 {
+            return (Vec::new(), gas, Some(Error::Depth));
+        }
+        // Fail if we're trying to transfer more than the available balance
+        if !value.is_zero() && !can_transfer(self.state_db.as_ref(), &caller.address(), &value) {
+            return (Vec::new(), gas, Some(Error::InsufficientBalance));
+        }
+        let snapshot = self.state_db.snapshot();
+        let precompile = self.precompile(&addr);
+
+        if !self.state_db.exist(&addr) {
+            if precompile.is_none() && self.chain_rules.is_eip158 && value.is_zero() {
+                // Calling a non existing account, don't do anything, but ping the tracer
+                if self.depth == 0 {
+                    if let Some(tracer) = &self.config.tracer {
+                        tracer.capture_enter(op_code::CALL, caller.address(), addr, input, gas, value);
+                        tracer.capture_exit(&[], 0, None);
+                    }
+                }
+                return (Vec::new(), gas, None);
+            }
+            self.state_db.create_account(&addr);
+        }
+        transfer(self.state_db.as_mut(), &caller.address(), &addr, &value);
+
+        let (ret, mut gas, err) = if let Some(p) = precompile {
+            run_precompiled_contract(p.as_ref(), input, gas)
+        } else {
+            // Initialise a new contract and set the code that is to be used by the EVM.
+            // The contract is a scoped environment for this execution context only.
+            let code = self.state_db.get_code(&addr);
+            if code.is_empty() {
+                (Vec::new(), gas, None)
+            } else {
+                let mut contract = Contract::new(caller, &AccountRef(addr), value, gas);
+                contract.set_call_code(addr, self.state_db.get_code_hash(&addr), code);
+                let (ret, err) = self.run(&mut contract, input, false);
+                (ret, contract.gas, err)
+            }
+        };
+        // When an error was returned by the EVM or when setting the creation code
+        // above we revert to the snapshot and consume any gas remaining. Additionally
+        // when we're in homestead this also counts for code storage gas errors.
+        if let Some(e) = &err {
+            self.state_db.revert_to_snapshot(snapshot);
+            if *e != Error::ExecutionReverted {
+                gas = 0;
+            }
+        }
+        (ret, gas, err)
+    }
+
+    // call_code executes the contract associated with the addr with the given input
+    // as parameters. It also handles any necessary value transfer required and takes
+    // the necessary steps to create accounts and reverses the state in case of an
+    // execution error or failed value transfer.
+    //
+    // call_code differs from call in the sense that it executes the given address'
+    // code with the caller as context.
+    pub fn call_code(
+        &mut self,
+        caller: &dyn ContractRef,
+        addr: common::Address,
+        input: &[u8],
+        gas: u64,
+        value: Uint256,
+    ) -> (Vec<u8>, u64, Option<Error>) {
+        // Fail if we're trying to execute above the call depth limit
+        if self.depth > params::CALL_CREATE_DEPTH as i32 {
+            return (Vec::new(), gas, Some(Error::Depth));
+        }
+        // Fail if we're trying to transfer more than the available balance
+        // Note although it's noop to transfer X ether to caller itself. But
+        // if caller doesn't have enough balance, it would be an error to allow
+        // over-charging itself. So the check here is necessary.
+        if !can_transfer(self.state_db.as_ref(), &caller.address(), &value) {
+            return (Vec::new(), gas, Some(Error::InsufficientBalance));
+        }
+        let snapshot = self.state_db.snapshot();
+
+        // It is allowed to call precompiles, even via delegatecall
+        let (ret, mut gas, err) = if let Some(p) = self.precompile(&addr) {
+            run_precompiled_contract(p.as_ref(), input, gas)
+        } else {
+            let caller_addr = caller.address();
+            // Initialise a new contract and set the code that is to be used by the EVM.
+            // The contract is a scoped environment for this execution context only.
+            let mut contract = Contract::new(caller, &AccountRef(caller_addr), value, gas);
+            contract.set_call_code(
+                addr,
+                self.state_db.get_code_hash(&addr),
+                self.state_db.get_code(&addr),
+            );
+            let (ret, err) = self.run(&mut contract, input, false);
+            (ret, contract.gas, err)
+        };
+        if let Some(e) = &err {
+            self.state_db.revert_to_snapshot(snapshot);
+            if *e != Error::ExecutionReverted {
+                gas = 0;
+            }
+        }
+        (ret, gas, err)
+    }
+
+    // delegate_call executes the contract associated with the addr with the given input
+    // as parameters. It reverses the state in case of an execution error.
+    //
+    // delegate_call differs from call_code in the sense that it executes the given address'
+    // code with the caller as context and the caller is set to the caller of the caller.
+    pub fn delegate_call(
+        &mut self,
+        caller: &Contract,
+        addr: common::Address,
+        input: &[u8],
+        gas: u64,
+    ) -> (Vec<u8>, u64, Option<Error>) {
+        // Fail if we're trying to execute above the call depth limit
+        if self.depth > params::CALL_CREATE_DEPTH as i32 {
+            return (Vec::new(), gas, Some(Error::Depth));
+        }
+        let snapshot = self.state_db.snapshot();
+
+        // It is allowed to call precompiles, even via delegatecall
+        let (ret, mut gas, err) = if let Some(p) = self.precompile(&addr) {
+            run_precompiled_contract(p.as_ref(), input, gas)
+        } else {
+            let caller_addr = caller.address();
+            // Initialise a new contract and make initialise the delegate values
+            let mut contract =
+                Contract::new(caller, &AccountRef(caller_addr), Uint256::zero(), gas)
+                    .as_delegate(caller);
+            contract.set_call_code(
+                addr,
+                self.state_db.get_code_hash(&addr),
+                self.state_db.get_code(&addr),
+            );
+            let (ret, err) = self.run(&mut contract, input, false);
+            (ret, contract.gas, err)
+        };
+        if let Some(e) = &err {
+            self.state_db.revert_to_snapshot(snapshot);
+            if *e != Error::ExecutionReverted {
+                gas = 0;
+            }
+        }
+        (ret, gas, err)
+    }
+
+    // static_call executes the contract associated with the addr with the given input
+    // as parameters while disallowing any modifications to the state during the call.
+    // Opcodes that attempt to perform such modifications will result in exceptions
+    // instead of performing the modifications.
+    pub fn static_call(
+        &mut self,
+        caller: &dyn ContractRef,
+        addr: common::Address,
+        input: &[u8],
+        gas: u64,
+    ) -> (Vec<u8>, u64, Option<Error>) {
+        // Fail if we're trying to execute above the call depth limit
+        if self.depth > params::CALL_CREATE_DEPTH as i32 {
+            return (Vec::new(), gas, Some(Error::Depth));
+        }
+        // We take a snapshot here. This is a bit counter-intuitive, and could probably be skipped.
+        // However, even a staticcall is considered a 'touch'. On mainnet, static calls were introduced
+        // after all empty accounts were deleted, so this is not required. However, if we omit this,
+        // then certain tests start failing; stRevertTest/RevertPrecompiledTouchExactOOG.json.
+        // We could change this, but for now it's left for legacy reasons
+        let snapshot = self.state_db.snapshot();
+
+        // We do an AddBalance of zero here, just in order to trigger a touch.
+        // This doesn't matter on Mainnet, where all empties are gone at the time of Byzantium,
+        // but is the correct thing to do and matters on other networks, in tests, and potential
+        // future scenarios
+        self.state_db.add_balance(&addr, &Uint256::zero());
+
+        let (ret, mut gas, err) = if let Some(p) = self.precompile(&addr) {
+            run_precompiled_contract(p.as_ref(), input, gas)
+        } else {
+            // Initialise a new contract and set the code that is to be used by the EVM.
+            // The contract is a scoped environment for this execution context only.
+            let mut contract = Contract::new(caller, &AccountRef(addr), Uint256::zero(), gas);
+            contract.set_call_code(
+                addr,
+                self.state_db.get_code_hash(&addr),
+                self.state_db.get_code(&addr),
+            );
+            // When an error was returned by the EVM or when setting the creation code
+            // above we revert to the snapshot and consume any gas remaining. Additionally
+            // when we're in Homestead this also counts for code storage gas errors.
+            let (ret, err) = self.run(&mut contract, input, true);
+            (ret, contract.gas, err)
+        };
+        if let Some(e) = &err {
+            self.state_db.revert_to_snapshot(snapshot);
+            if *e != Error::ExecutionReverted {
+                gas = 0;
+            }
+        }
+        (ret, gas, err)
+    }
+
+    // create_contract creates a new contract using code as deployment code.
+    fn create_contract(
+        &mut self,
+        caller: &dyn ContractRef,
+        code_and_hash: CodeAndHash,
+        gas: u64,
+        value: Uint256,
+        address: common::Address,
+        typ: OpCode,
+    ) -> (Vec<u8>, common::Address, u64, Option<Error>) {
+        // Depth check execution. Fail if we're trying to execute above the
+        // limit.
+        if self.depth > params::CALL_CREATE_DEPTH as i32 {
+            return (Vec::new(), common::Address::default(), gas, Some(Error::Depth));
+        }
+        if !can_transfer(self.state_db.as_ref(), &caller.address(), &value) {
+            return (
+                Vec::new(),
+                common::Address::default(),
+                gas,
+                Some(Error::InsufficientBalance),
+            );
+        }
+        let nonce = self.state_db.get_nonce(&caller.address());
+        if nonce.checked_add(1).is_none() {
+            return (
+                Vec::new(),
+                common::Address::default(),
+                gas,
+                Some(Error::NonceUintOverflow),
+            );
+        }
+        self.state_db.set_nonce(&caller.address(), nonce + 1);
+        // We add this to the access list _before_ taking a snapshot. Even if the creation fails,
+        // the access-list change should not be rolled back
+        if self.chain_rules.is_berlin {
+            self.state_db.add_address_to_access_list(&address);
+        }
+        // Ensure there's no existing contract already at the designated address
+        let contract_hash = self.state_db.get_code_hash(&address);
+        if self.state_db.get_nonce(&address) != 0
+            || (contract_hash != common::Hash::default() && contract_hash != EMPTY_CODE_HASH)
+        {
+            return (
+                Vec::new(),
+                common::Address::default(),
+                0,
+                Some(Error::ContractAddressCollision),
+            );
+        }
+        // Create a new account on the state
+        let snapshot = self.state_db.snapshot();
+        self.state_db.create_account(&address);
+        if self.chain_rules.is_eip158 {
+            self.state_db.set_nonce(&address, 1);
+        }
+        transfer(self.state_db.as_mut(), &caller.address(), &address, &value);
+
+        // Initialise a new contract and set the code that is to be used by the EVM.
+        // The contract is a scoped environment for this execution context only.
+        let mut contract = Contract::new(caller, &AccountRef(address), value.clone(), gas);
+        contract.set_code_optional_hash(address, code_and_hash);
+
+        if self.depth == 0 {
+            if let Some(tracer) = &self.config.tracer {
+                tracer.capture_enter(typ, caller.address(), address, &contract.code, gas, value);
+            }
+        }
+
+        let (ret, mut err) = self.run(&mut contract, &[], false);
+
+        // Check whether the max code size has been exceeded, assign err if the case.
+        if err.is_none() && self.chain_rules.is_eip158 && ret.len() > params::MAX_CODE_SIZE {
+            err = Some(Error::MaxCodeSizeExceeded);
+        }
+
+        // Reject code starting with 0xEF if EIP-3541 is enabled.
+        if err.is_none() && !ret.is_empty() && ret[0] == 0xEF && self.chain_rules.is_london {
+            err = Some(Error::InvalidCode);
+        }
+
+        // if the contract creation ran successfully and no errors were returned
+        // calculate the gas required to store the code. If the code could not
+        // be stored due to not enough gas set an error and let it be handled
+        // by the error checking condition below.
+        if err.is_none() {
+            let create_data_gas = ret.len() as u64 * params::CREATE_DATA_GAS;
+            if contract.use_gas(create_data_gas) {
+                self.state_db.set_code(&address, &ret);
+            } else {
+                err = Some(Error::CodeStoreOutOfGas);
+            }
+        }
+
+        // When an error was returned by the EVM or when setting the creation code
+        // above we revert to the snapshot and consume any gas remaining. Additionally
+        // when we're in homestead this also counts for code storage gas errors.
+        if let Some(e) = &err {
+            if self.chain_rules.is_homestead || *e != Error::CodeStoreOutOfGas {
+                self.state_db.revert_to_snapshot(snapshot);
+                if *e != Error::ExecutionReverted {
+                    let remaining = contract.gas;
+                    contract.use_gas(remaining);
+                }
+            }
+        }
+
+        if self.depth == 0 {
+            if let Some(tracer) = &self.config.tracer {
+                tracer.capture_exit(&ret, gas - contract.gas, err.as_ref());
+            }
+        }
+        (ret, address, contract.gas, err)
+    }
+
+    // create creates a new contract using code as deployment code.
+    pub fn create(
+        &mut self,
+        caller: &dyn ContractRef,
+        code: Vec<u8>,
+        gas: u64,
+        value: Uint256,
+    ) -> (Vec<u8>, common::Address, u64, Option<Error>) {
+        let contract_addr =
+            crypto::create_address(&caller.address(), self.state_db.get_nonce(&caller.address()));
+        self.create_contract(
+            caller,
+            CodeAndHash::new(code),
+            gas,
+            value,
+            contract_addr,
+            op_code::CREATE,
+        )
+    }
+
+    // create2 creates a new contract using code as deployment code.
+    //
+    // The different between create2 with create is create2 uses keccak256(0xff ++ msg.sender ++ salt ++ keccak256(init_code))[12:]
+    // instead of the usual sender-and-nonce-hash as the address where the contract is initialized at.
+    pub fn create2(
+        &mut self,
+        caller: &dyn ContractRef,
+        code: Vec<u8>,
+        gas: u64,
+        endowment: Uint256,
+        salt: &Uint256,
+    ) -> (Vec<u8>, common::Address, u64, Option<Error>) {
+        let mut code_and_hash = CodeAndHash::new(code);
+        let contract_addr = crypto::create_address2(
+            &caller.address(),
+            &salt.to_bytes32(),
+            code_and_hash.hash().as_bytes(),
+        );
+        self.create_contract(
+            caller,
+            code_and_hash,
+            gas,
+            endowment,
+            contract_addr,
+            op_code::CREATE2,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory_state_db::MemoryStateDB;
+
+    const CALLER: common::Address = common::Address([0xaa; 20]);
+    const CONTRACT: common::Address = common::Address([0xbb; 20]);
+    const CALLEE: common::Address = common::Address([0xcc; 20]);
+
+    fn new_test_evm(contracts: &[(common::Address, &[u8])]) -> EVM {
+        new_test_evm_at(contracts, true)
+    }
+
+    // new_test_evm_at returns an EVM of a London chain that has or hasn't
+    // activated Shanghai and Cancun yet.
+    fn new_test_evm_at(contracts: &[(common::Address, &[u8])], shanghai: bool) -> EVM {
+        let mut state_db = MemoryStateDB::new();
+        for (addr, code) in contracts {
+            state_db.create_account(addr);
+            state_db.set_code(addr, code);
+        }
+        let chain_config = ChainConfig {
+            chain_id: 1337,
+            homestead_block: Some(0),
+            eip150_block: Some(0),
+            eip155_block: Some(0),
+            eip158_block: Some(0),
+            byzantium_block: Some(0),
+            constantinople_block: Some(0),
+            petersburg_block: Some(0),
+            istanbul_block: Some(0),
+            berlin_block: Some(0),
+            london_block: Some(0),
+            shanghai_time: if shanghai { Some(0) } else { None },
+            cancun_time: if shanghai { Some(0) } else { None },
+            terminal_total_difficulty_passed: true,
+            ..Default::default()
+        };
+        let block_ctx = BlockContext {
+            get_hash: Box::new(|_| common::Hash::default()),
+            coinbase: common::Address::default(),
+            gas_limit: 30_000_000,
+            block_number: Uint256::one(),
+            time: 1,
+            difficulty: Uint256::zero(),
+            base_fee: Uint256::zero(),
+            blob_base_fee: Uint256::one(),
+            random: Some(common::Hash::default()),
+        };
+        EVM::new(
+            block_ctx,
+            TxContext::default(),
+            Box::new(state_db),
+            chain_config,
+            Config::default(),
+        )
+    }
+
+    fn word(v: u64) -> Vec<u8> {
+        Uint256::from_uint64(v).to_bytes32().to_vec()
+    }
+
+    // call_code_of returns the code of a contract calling CALLEE with the given
+    // call opcode (CALL or STATICCALL) and returning the success flag.
+    fn call_code_of(op: OpCode) -> Vec<u8> {
+        let mut code = vec![0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0x60, 0x00];
+        if op == op_code::CALL {
+            code.extend_from_slice(&[0x60, 0x00]); // value
+        }
+        code.push(op_code::PUSH20);
+        code.extend_from_slice(CALLEE.as_bytes());
+        code.extend_from_slice(&[op_code::GAS, op]);
+        code.extend_from_slice(&[0x60, 0x00, 0x52, 0x60, 0x20, 0x60, 0x00, 0xf3]);
+        code
+    }
+
+    #[test]
+    fn test_call_returns_output() {
+        // PUSH1 2, PUSH1 3, ADD, PUSH1 0, MSTORE, PUSH1 32, PUSH1 0, RETURN
+        let code = [0x60, 0x02, 0x60, 0x03, 0x01, 0x60, 0x00, 0x52, 0x60, 0x20, 0x60, 0x00, 0xf3];
+        let mut evm = new_test_evm(&[(CONTRACT, &code)]);
+        let (ret, gas, err) = evm.call(&AccountRef(CALLER), CONTRACT, &[], 100_000, Uint256::zero());
+        assert_eq!(err, None);
+        assert_eq!(ret, word(5));
+        assert!(gas < 100_000);
+    }
+
+    #[test]
+    fn test_jump_skips_invalid() {
+        // PUSH1 4, JUMP, INVALID, JUMPDEST, PUSH1 7, PUSH1 0, MSTORE, PUSH1 32, PUSH1 0, RETURN
+        let code = [
+            0x60, 0x04, 0x56, 0xfe, 0x5b, 0x60, 0x07, 0x60, 0x00, 0x52, 0x60, 0x20, 0x60, 0x00, 0xf3,
+        ];
+        let mut evm = new_test_evm(&[(CONTRACT, &code)]);
+        let (ret, _, err) = evm.call(&AccountRef(CALLER), CONTRACT, &[], 100_000, Uint256::zero());
+        assert_eq!(err, None);
+        assert_eq!(ret, word(7));
+
+        // Jumping into the INVALID opcode consumes all gas.
+        let code = [0x60, 0x03, 0x56, 0xfe];
+        let mut evm = new_test_evm(&[(CONTRACT, &code)]);
+        let (_, gas, err) = evm.call(&AccountRef(CALLER), CONTRACT, &[], 100_000, Uint256::zero());
+        assert_eq!(err, Some(Error::InvalidJump));
+        assert_eq!(gas, 0);
+    }
+
+    #[test]
+    fn test_revert_keeps_gas_and_data() {
+        // PUSH1 42, PUSH1 0, MSTORE, PUSH1 32, PUSH1 0, REVERT
+        let code = [0x60, 0x2a, 0x60, 0x00, 0x52, 0x60, 0x20, 0x60, 0x00, 0xfd];
+        let mut evm = new_test_evm(&[(CONTRACT, &code)]);
+        let (ret, gas, err) = evm.call(&AccountRef(CALLER), CONTRACT, &[], 100_000, Uint256::zero());
+        assert_eq!(err, Some(Error::ExecutionReverted));
+        assert_eq!(ret, word(42));
+        assert!(gas > 0);
+    }
+
+    #[test]
+    fn test_static_call_write_protection() {
+        // PUSH1 1, PUSH1 0, SSTORE, STOP
+        let callee = [0x60, 0x01, 0x60, 0x00, 0x55, 0x00];
+        let slot = common::Hash::default();
+
+        // A STATICCALL into a frame that writes to storage fails.
+        let caller = call_code_of(op_code::STATICCALL);
+        let mut evm = new_test_evm(&[(CONTRACT, &caller), (CALLEE, &callee)]);
+        let (ret, _, err) = evm.call(&AccountRef(CALLER), CONTRACT, &[], 100_000, Uint256::zero());
+        assert_eq!(err, None);
+        assert_eq!(ret, word(0));
+        assert_eq!(evm.state_db.get_state(&CALLEE, &slot), common::Hash::default());
+        assert!(!evm.read_only);
+
+        // The same frame called through CALL is allowed to write.
+        let caller = call_code_of(op_code::CALL);
+        let mut evm = new_test_evm(&[(CONTRACT, &caller), (CALLEE, &callee)]);
+        let (ret, _, err) = evm.call(&AccountRef(CALLER), CONTRACT, &[], 100_000, Uint256::zero());
+        assert_eq!(err, None);
+        assert_eq!(ret, word(1));
+        assert_eq!(
+            evm.state_db.get_state(&CALLEE, &slot),
+            common::Hash(Uint256::one().to_bytes32())
+        );
+    }
+
+    #[test]
+    fn test_create_deploys_code() {
+        // Init code returning the single byte runtime code 0x00 (STOP):
+        // PUSH1 0, PUSH1 0, MSTORE8, PUSH1 1, PUSH1 0, RETURN
+        let init = vec![0x60, 0x00, 0x60, 0x00, 0x53, 0x60, 0x01, 0x60, 0x00, 0xf3];
+        let mut evm = new_test_evm(&[]);
+        let expected = crypto::create_address(&CALLER, 0);
+        let (ret, addr, _, err) = evm.create(&AccountRef(CALLER), init, 100_000, Uint256::zero());
+        assert_eq!(err, None);
+        assert_eq!(addr, expected);
+        assert_eq!(ret, vec![0x00]);
+        assert_eq!(evm.state_db.get_code(&addr), vec![0x00]);
+        assert_eq!(evm.state_db.get_nonce(&CALLER), 1);
+    }
+
+    // create_code_of returns the code of a contract running CREATE, or
+    // CREATE2 with a zero salt, on size bytes of zeroed memory and returning
+    // the new address.
+    fn create_code_of(op: OpCode, size: u64) -> Vec<u8> {
+        let mut code = Vec::new();
+        if op == op_code::CREATE2 {
+            code.extend_from_slice(&[0x60, 0x00]); // salt
+        }
+        code.push(op_code::PUSH3);
+        code.extend_from_slice(&size.to_be_bytes()[5..]);
+        code.extend_from_slice(&[0x60, 0x00, 0x60, 0x00, op]);
+        code.extend_from_slice(&[0x60, 0x00, 0x52, 0x60, 0x20, 0x60, 0x00, 0xf3]);
+        code
+    }
+
+    // run_create runs create_code_of and returns the gas used and the error.
+    fn run_create(op: OpCode, size: u64, shanghai: bool) -> (u64, Option<Error>) {
+        let code = create_code_of(op, size);
+        let mut evm = new_test_evm_at(&[(CONTRACT, &code)], shanghai);
+        let (ret, gas, err) = evm.call(&AccountRef(CALLER), CONTRACT, &[], 1_000_000, Uint256::zero());
+        if err.is_none() {
+            assert_ne!(ret, word(0), "create of {} bytes failed", size);
+        }
+        (1_000_000 - gas, err)
+    }
+
+    #[test]
+    fn test_create_init_code_limit() {
+        for op in [op_code::CREATE, op_code::CREATE2] {
+            let (_, err) = run_create(op, params::MAX_INIT_CODE_SIZE, true);
+            assert_eq!(err, None);
+            // Initcode above the limit aborts the creating frame
+            let (gas, err) = run_create(op, params::MAX_INIT_CODE_SIZE + 1, true);
+            assert_eq!(err, Some(Error::MaxInitCodeSizeExceeded));
+            assert_eq!(gas, 1_000_000);
+            // There is no limit before Shanghai
+            let (_, err) = run_create(op, params::MAX_INIT_CODE_SIZE + 1, false);
+            assert_eq!(err, None);
+        }
+    }
+
+    #[test]
+    fn test_create_init_code_gas() {
+        // Each word of initcode costs 2 more gas from Shanghai on, on top of
+        // the memory expansion and the hashing of CREATE2.
+        for op in [op_code::CREATE, op_code::CREATE2] {
+            let word_cost = |shanghai: bool| {
+                let (small, err) = run_create(op, 32, shanghai);
+                assert_eq!(err, None);
+                let (large, err) = run_create(op, 32 * 11, shanghai);
+                assert_eq!(err, None);
+                (large - small) / 10
+            };
+            let before = word_cost(false);
+            assert_eq!(word_cost(true), before + params::INIT_CODE_WORD_GAS);
+            let hashing = if op == op_code::CREATE2 { params::KECCAK256_WORD_GAS } else { 0 };
+            assert_eq!(before, params::MEMORY_GAS + hashing);
+        }
+    }
+}
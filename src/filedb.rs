@@ -0,0 +1,499 @@
+// FileDatabase is a persistent key-value store backed by a single append-only
+// log file.
+//
+// Every write, single or batched, is appended to the log as one frame:
+//
+//     length (u32 LE) || payload || checksum (first 4 bytes of keccak256(payload))
+//
+// where the payload is a sequence of operations, each a kind byte (0 = put,
+// 1 = delete), the key length (u32 LE) and the key, followed for puts by the
+// value length (u32 LE) and the value. The file is synced before a write
+// returns. On open the log is replayed into an in-memory index of value
+// offsets; a torn or corrupt frame at the end of the log, left by a crash in
+// the middle of a write, is truncated so the store resumes from the last
+// complete write.
+
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufReader, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::crypto;
+use crate::ethdb::{Batch, KeyValueStore, IDEAL_BATCH_SIZE};
+
+const LOG_FILE: &str = "data.log";
+
+const OP_PUT: u8 = 0;
+const OP_DELETE: u8 = 1;
+
+const FRAME_HEADER_LEN: usize = 4;
+const CHECKSUM_LEN: usize = 4;
+
+// ValueRef locates a value inside the log file.
+#[derive(Debug, Clone, Copy)]
+struct ValueRef {
+    offset: u64,
+    len: u32,
+}
+
+#[derive(Debug)]
+struct Inner {
+    file: File,
+    index: HashMap<Vec<u8>, ValueRef>,
+    // size of the valid part of the log
+    size: u64,
+}
+
+#[derive(Debug)]
+pub struct FileDatabase {
+    dir: PathBuf,
+    inner: Mutex<Inner>,
+}
+
+fn checksum(payload: &[u8]) -> [u8; CHECKSUM_LEN] {
+    let hash = crypto::keccak256(payload);
+    let mut sum = [0u8; CHECKSUM_LEN];
+    sum.copy_from_slice(&hash[..CHECKSUM_LEN]);
+    sum
+}
+
+fn read_u32(buf: &[u8], pos: usize) -> Option<u32> {
+    let bytes = buf.get(pos..pos + 4)?;
+    Some(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+// encode_frame serializes a batch into a log frame.
+fn encode_frame(batch: &Batch) -> Vec<u8> {
+    let mut payload = Vec::with_capacity(batch.value_size() + batch.ops().len() * 9);
+    for (key, value) in batch.ops() {
+        match value {
+            Some(value) => {
+                payload.push(OP_PUT);
+                payload.extend_from_slice(&(key.len() as u32).to_le_bytes());
+                payload.extend_from_slice(key);
+                payload.extend_from_slice(&(value.len() as u32).to_le_bytes());
+                payload.extend_from_slice(value);
+            }
+            None => {
+                payload.push(OP_DELETE);
+                payload.extend_from_slice(&(key.len() as u32).to_le_bytes());
+                payload.extend_from_slice(key);
+            }
+        }
+    }
+    let mut frame = Vec::with_capacity(FRAME_HEADER_LEN + payload.len() + CHECKSUM_LEN);
+    frame.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+    frame.extend_from_slice(&payload);
+    frame.extend_from_slice(&checksum(&payload));
+    frame
+}
+
+// parse_frame decodes the operations of a frame payload that starts at
+// payload_offset in the log, pairing each key with the location of its new
+// value or None for a delete. It returns None if the payload is malformed.
+fn parse_frame(payload: &[u8], payload_offset: u64) -> Option<Vec<(&[u8], Option<ValueRef>)>> {
+    let mut ops = Vec::new();
+    let mut pos = 0;
+    while pos < payload.len() {
+        let kind = payload[pos];
+        let key_len = read_u32(payload, pos + 1)? as usize;
+        let key = payload.get(pos + 5..pos + 5 + key_len)?;
+        pos += 5 + key_len;
+        match kind {
+            OP_PUT => {
+                let len = read_u32(payload, pos)?;
+                payload.get(pos + 4..pos + 4 + len as usize)?;
+                let value_ref = ValueRef {
+                    offset: payload_offset + pos as u64 + 4,
+                    len,
+                };
+                ops.push((key, Some(value_ref)));
+                pos += 4 + len as usize;
+            }
+            OP_DELETE => ops.push((key, None)),
+            _ => return None,
+        }
+    }
+    Some(ops)
+}
+
+// apply_frame replays the parsed operations of a frame into the index.
+fn apply_frame(index: &mut HashMap<Vec<u8>, ValueRef>, ops: Vec<(&[u8], Option<ValueRef>)>) {
+    for (key, value_ref) in ops {
+        match value_ref {
+            Some(value_ref) => {
+                index.insert(key.to_vec(), value_ref);
+            }
+            None => {
+                index.remove(key);
+            }
+        }
+    }
+}
+
+impl FileDatabase {
+    // open opens the store in dir, creating it if it doesn't exist, and
+    // recovers from an interrupted write.
+    pub fn open<P: AsRef<Path>>(dir: P) -> io::Result<Self> {
+        let dir = dir.as_ref().to_path_buf();
+        fs::create_dir_all(&dir)?;
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(dir.join(LOG_FILE))?;
+
+        let log_len = file.metadata()?.len();
+        let mut reader = BufReader::new(&mut file);
+        let mut index = HashMap::new();
+        let mut pos = 0u64;
+        let mut header = [0u8; FRAME_HEADER_LEN];
+        let mut sum = [0u8; CHECKSUM_LEN];
+        let mut payload = Vec::new();
+        while pos + (FRAME_HEADER_LEN + CHECKSUM_LEN) as u64 <= log_len {
+            reader.read_exact(&mut header)?;
+            let len = u32::from_le_bytes(header) as u64;
+            let start = pos + FRAME_HEADER_LEN as u64;
+            let end = start + len;
+            // A length running past the end of the file marks a torn frame.
+            if end + CHECKSUM_LEN as u64 > log_len {
+                break;
+            }
+            payload.resize(len as usize, 0);
+            reader.read_exact(&mut payload)?;
+            reader.read_exact(&mut sum)?;
+            if sum != checksum(&payload) {
+                break;
+            }
+            // Parse the whole frame first so a malformed frame is dropped whole.
+            let ops = match parse_frame(&payload, start) {
+                Some(ops) => ops,
+                None => break,
+            };
+            apply_frame(&mut index, ops);
+            pos = end + CHECKSUM_LEN as u64;
+        }
+        drop(reader);
+        if pos < log_len {
+            // Drop the torn tail left by a crash.
+            file.set_len(pos)?;
+            file.sync_all()?;
+        }
+        Ok(FileDatabase {
+            dir,
+            inner: Mutex::new(Inner {
+                file,
+                index,
+                size: pos,
+            }),
+        })
+    }
+
+    // rewrite writes the live entries into a fresh log next to the current one
+    // and atomically replaces the current log with it.
+    fn rewrite(&self, inner: &mut Inner) -> io::Result<()> {
+        let tmp_path = self.dir.join(format!("{}.tmp", LOG_FILE));
+        let mut tmp = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(&tmp_path)?;
+
+        let mut index = HashMap::with_capacity(inner.index.len());
+        let mut size = 0u64;
+        let mut batch = Batch::new();
+        let mut keys: Vec<Vec<u8>> = inner.index.keys().cloned().collect();
+        keys.sort();
+        for (i, key) in keys.iter().enumerate() {
+            let value_ref = inner.index[key];
+            let mut value = vec![0u8; value_ref.len as usize];
+            inner.file.seek(SeekFrom::Start(value_ref.offset))?;
+            inner.file.read_exact(&mut value)?;
+            batch.put(key, &value);
+            if batch.value_size() >= IDEAL_BATCH_SIZE || i == keys.len() - 1 {
+                let frame = encode_frame(&batch);
+                tmp.write_all(&frame)?;
+                let payload = &frame[FRAME_HEADER_LEN..frame.len() - CHECKSUM_LEN];
+                if let Some(ops) = parse_frame(payload, size + FRAME_HEADER_LEN as u64) {
+                    apply_frame(&mut index, ops);
+                }
+                size += frame.len() as u64;
+                batch.reset();
+            }
+        }
+        tmp.sync_all()?;
+        fs::rename(&tmp_path, self.dir.join(LOG_FILE))?;
+        // Persist the rename itself.
+        File::open(&self.dir)?.sync_all()?;
+
+        inner.file = tmp;
+        inner.index = index;
+        inner.size = size;
+        Ok(())
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.dir
+    }
+
+    // len returns the number of live keys.
+    pub fn len(&self) -> usize {
+        self.inner.lock().unwrap().index.len()
+    }
+
+    // is_empty reports whether the database holds no live keys.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    // log_size returns the size of the log file in bytes.
+    pub fn log_size(&self) -> u64 {
+        self.inner.lock().unwrap().size
+    }
+}
+
+impl KeyValueStore for FileDatabase {
+    fn has(&self, key: &[u8]) -> io::Result<bool> {
+        Ok(self.inner.lock().unwrap().index.contains_key(key))
+    }
+
+    fn get(&self, key: &[u8]) -> io::Result<Option<Vec<u8>>> {
+        let mut inner = self.inner.lock().unwrap();
+        let value_ref = match inner.index.get(key) {
+            Some(value_ref) => *value_ref,
+            None => return Ok(None),
+        };
+        let mut value = vec![0u8; value_ref.len as usize];
+        inner.file.seek(SeekFrom::Start(value_ref.offset))?;
+        inner.file.read_exact(&mut value)?;
+        Ok(Some(value))
+    }
+
+    fn put(&self, key: &[u8], value: &[u8]) -> io::Result<()> {
+        let mut batch = Batch::new();
+        batch.put(key, value);
+        self.write_batch(&batch)
+    }
+
+    fn delete(&self, key: &[u8]) -> io::Result<()> {
+        let mut batch = Batch::new();
+        batch.delete(key);
+        self.write_batch(&batch)
+    }
+
+    fn write_batch(&self, batch: &Batch) -> io::Result<()> {
+        if batch.is_empty() {
+            return Ok(());
+        }
+        let frame = encode_frame(batch);
+        let mut inner = self.inner.lock().unwrap();
+        let start = inner.size;
+        inner.file.seek(SeekFrom::Start(start))?;
+        if let Err(err) = inner.file.write_all(&frame).and_then(|_| inner.file.sync_data()) {
+            // Don't leave a partial frame behind for the next write to follow.
+            let _ = inner.file.set_len(start);
+            return Err(err);
+        }
+        let payload = &frame[FRAME_HEADER_LEN..frame.len() - CHECKSUM_LEN];
+        let payload_offset = start + FRAME_HEADER_LEN as u64;
+        if let Some(ops) = parse_frame(payload, payload_offset) {
+            apply_frame(&mut inner.index, ops);
+        }
+        inner.size = start + frame.len() as u64;
+        Ok(())
+    }
+
+    // compact rewrites the log so it only holds the live entries. The new log
+    // replaces the old one with a rename, so a crash during compaction leaves
+    // the old log in place.
+    fn compact(&self) -> io::Result<()> {
+        let mut inner = self.inner.lock().unwrap();
+        self.rewrite(&mut inner)
+    }
+
+    fn keys(&self, prefix: &[u8]) -> io::Result<Vec<Vec<u8>>> {
+        Ok(self
+            .inner
+            .lock()
+            .unwrap()
+            .index
+            .keys()
+            .filter(|key| key.starts_with(prefix))
+            .cloned()
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+    // TestDir is a scratch directory that is removed when dropped.
+    struct TestDir(PathBuf);
+
+    impl TestDir {
+        fn new() -> Self {
+            let n = COUNTER.fetch_add(1, Ordering::SeqCst);
+            let dir = std::env::temp_dir().join(format!("filedb-test-{}-{}", std::process::id(), n));
+            let _ = fs::remove_dir_all(&dir);
+            TestDir(dir)
+        }
+    }
+
+    impl Drop for TestDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn append(dir: &Path, data: &[u8]) {
+        let mut file = OpenOptions::new().append(true).open(dir.join(LOG_FILE)).unwrap();
+        file.write_all(data).unwrap();
+    }
+
+    #[test]
+    fn test_resume_after_reopen() {
+        let dir = TestDir::new();
+        {
+            let db = FileDatabase::open(&dir.0).unwrap();
+            db.put(b"a", b"1").unwrap();
+            db.put(b"b", b"2").unwrap();
+            db.put(b"a", b"3").unwrap();
+            db.delete(b"b").unwrap();
+            db.put(b"c", b"").unwrap();
+        }
+        let db = FileDatabase::open(&dir.0).unwrap();
+        assert_eq!(db.get(b"a").unwrap(), Some(b"3".to_vec()));
+        assert_eq!(db.get(b"b").unwrap(), None);
+        assert_eq!(db.get(b"c").unwrap(), Some(Vec::new()));
+        assert_eq!(db.len(), 2);
+
+        // Writes after reopening append to the recovered log.
+        db.put(b"d", b"4").unwrap();
+        drop(db);
+        let db = FileDatabase::open(&dir.0).unwrap();
+        assert_eq!(db.get(b"a").unwrap(), Some(b"3".to_vec()));
+        assert_eq!(db.get(b"d").unwrap(), Some(b"4".to_vec()));
+    }
+
+    #[test]
+    fn test_batch_all_or_nothing() {
+        let dir = TestDir::new();
+        let db = FileDatabase::open(&dir.0).unwrap();
+        db.put(b"x", b"0").unwrap();
+        let size = db.log_size();
+
+        let mut batch = Batch::new();
+        batch.put(b"x", b"1");
+        batch.put(b"y", b"2");
+        batch.delete(b"z");
+        db.write_batch(&batch).unwrap();
+        let full = db.log_size();
+        drop(db);
+
+        let mut frame = vec![0u8; (full - size) as usize];
+        let mut file = OpenOptions::new().read(true).write(true).open(dir.0.join(LOG_FILE)).unwrap();
+        file.seek(SeekFrom::Start(size)).unwrap();
+        file.read_exact(&mut frame).unwrap();
+
+        // Cut the batch frame at every possible point: the whole batch must
+        // vanish and the earlier write must survive.
+        for cut in size..full {
+            file.set_len(cut).unwrap();
+            let db = FileDatabase::open(&dir.0).unwrap();
+            assert_eq!(db.log_size(), size, "cut at {}", cut);
+            assert_eq!(db.get(b"x").unwrap(), Some(b"0".to_vec()));
+            assert_eq!(db.get(b"y").unwrap(), None);
+            drop(db);
+            file.set_len(size).unwrap();
+            append(&dir.0, &frame);
+        }
+        let db = FileDatabase::open(&dir.0).unwrap();
+        assert_eq!(db.get(b"x").unwrap(), Some(b"1".to_vec()));
+        assert_eq!(db.get(b"y").unwrap(), Some(b"2".to_vec()));
+    }
+
+    #[test]
+    fn test_torn_tail_truncated() {
+        let dir = TestDir::new();
+        let db = FileDatabase::open(&dir.0).unwrap();
+        db.put(b"key", b"value").unwrap();
+        let size = db.log_size();
+        drop(db);
+
+        // A half-written frame header.
+        append(&dir.0, &[0x10, 0x00]);
+        let db = FileDatabase::open(&dir.0).unwrap();
+        assert_eq!(db.log_size(), size);
+        assert_eq!(fs::metadata(dir.0.join(LOG_FILE)).unwrap().len(), size);
+        assert_eq!(db.get(b"key").unwrap(), Some(b"value".to_vec()));
+        drop(db);
+
+        // A frame whose length runs past the end of the file.
+        append(&dir.0, &u32::MAX.to_le_bytes());
+        append(&dir.0, &[0u8; 16]);
+        let db = FileDatabase::open(&dir.0).unwrap();
+        assert_eq!(db.log_size(), size);
+        drop(db);
+
+        // A complete frame with a bad checksum.
+        let mut batch = Batch::new();
+        batch.put(b"key", b"other");
+        let mut frame = encode_frame(&batch);
+        let last = frame.len() - 1;
+        frame[last] ^= 0xff;
+        append(&dir.0, &frame);
+        let db = FileDatabase::open(&dir.0).unwrap();
+        assert_eq!(db.log_size(), size);
+        assert_eq!(db.get(b"key").unwrap(), Some(b"value".to_vec()));
+
+        // The store keeps working after recovery.
+        db.put(b"key", b"new").unwrap();
+        drop(db);
+        let db = FileDatabase::open(&dir.0).unwrap();
+        assert_eq!(db.get(b"key").unwrap(), Some(b"new".to_vec()));
+    }
+
+    #[test]
+    fn test_compact() {
+        let dir = TestDir::new();
+        let db = FileDatabase::open(&dir.0).unwrap();
+        for i in 0..100u32 {
+            db.put(&i.to_be_bytes(), &[i as u8; 32]).unwrap();
+        }
+        for i in 0..100u32 {
+            if i % 2 == 0 {
+                db.delete(&i.to_be_bytes()).unwrap();
+            } else {
+                db.put(&i.to_be_bytes(), &[i as u8 + 1; 32]).unwrap();
+            }
+        }
+        let before = db.log_size();
+        db.compact().unwrap();
+        assert!(db.log_size() < before);
+        assert_eq!(db.log_size(), fs::metadata(dir.0.join(LOG_FILE)).unwrap().len());
+        assert!(!dir.0.join(format!("{}.tmp", LOG_FILE)).exists());
+
+        let check = |db: &FileDatabase| {
+            assert_eq!(db.len(), 50);
+            for i in 0..100u32 {
+                let want = if i % 2 == 0 { None } else { Some(vec![i as u8 + 1; 32]) };
+                assert_eq!(db.get(&i.to_be_bytes()).unwrap(), want);
+            }
+        };
+        check(&db);
+        db.put(b"after", b"compact").unwrap();
+        drop(db);
+
+        let db = FileDatabase::open(&dir.0).unwrap();
+        assert_eq!(db.get(b"after").unwrap(), Some(b"compact".to_vec()));
+        db.delete(b"after").unwrap();
+        check(&db);
+    }
+}
@@ -1,3 +1,7 @@
+use crate::errors::Error;
+use uint256::Uint256;
+
+// Gas costs
 pub const GAS_QUICK_STEP: u64 = 2;
 pub const GAS_FASTEST_STEP: u64 = 3;
 pub const GAS_FAST_STEP: u64 = 5;
@@ -5,23 +9,33 @@ pub const GAS_MID_STEP: u64 = 8;
 pub const GAS_SLOW_STEP: u64 = 10;
 pub const GAS_EXT_STEP: u64 = 20;
 
-// TODO change this
-fn call_gas(
+// call_gas returns the actual gas cost of the call.
+//
+// The cost of gas was changed during the homestead price change HF.
+// As part of EIP 150 (TangerineWhistle), the returned gas is gas - base * 63 / 64.
+pub fn call_gas(
     is_eip150: bool,
     available_gas: u64,
     base: u64,
     call_cost: &Uint256,
-) -> Result<u64, Box<dyn std::error::Error>> {
-    let mut gas = available_gas;
+) -> Result<u64, Error> {
     if is_eip150 {
-        gas = gas.saturating_sub(base);
-        gas = gas.saturating_sub(gas / 64);
-        if call_cost.bits() > 64 || gas < call_cost.low_u64() {
+        let available_gas = available_gas - base;
+        let gas = available_gas - available_gas / 64;
+        // If the bit length exceeds 64 bit we know that the newly calculated "gas" for EIP150
+        // is smaller than the requested amount. Therefore we return the new gas instead
+        // of returning an error.
+        if !call_cost.is_u64() || gas < call_cost.low_u64() {
             return Ok(gas);
         }
     }
     if !call_cost.is_u64() {
-        return Err(Box::new(ErrGasUintOverflow));
+        return Err(Error::GasUintOverflow);
     }
     Ok(call_cost.low_u64())
 }
+
+// to_word_size returns the ceiled word size required for memory expansion.
+pub fn to_word_size(size: u64) -> u64 {
+    size.div_ceil(32)
+}
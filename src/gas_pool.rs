@@ -0,0 +1,54 @@
+use std::fmt;
+
+// GasLimitReached is returned if the gas required by a transaction is higher
+// than what's left in the block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GasLimitReached;
+
+impl fmt::Display for GasLimitReached {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "gas limit reached")
+    }
+}
+
+impl std::error::Error for GasLimitReached {}
+
+// GasPool tracks the amount of gas available during execution of the
+// transactions in a block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct GasPool(u64);
+
+impl GasPool {
+    pub fn new(gas: u64) -> Self {
+        GasPool(gas)
+    }
+
+    // add_gas makes gas available for execution.
+    pub fn add_gas(&mut self, amount: u64) {
+        self.0 = self
+            .0
+            .checked_add(amount)
+            .expect("gas pool pushed above u64");
+    }
+
+    // sub_gas deducts the given amount from the pool if enough gas is
+    // available and returns an error otherwise.
+    pub fn sub_gas(&mut self, amount: u64) -> Result<(), GasLimitReached> {
+        if self.0 < amount {
+            return Err(GasLimitReached);
+        }
+        self.0 -= amount;
+        Ok(())
+    }
+
+    // gas returns the amount of gas remaining in the pool.
+    pub fn gas(&self) -> u64 {
+        self.0
+    }
+}
+
+impl fmt::Display for GasPool {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
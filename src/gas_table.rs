@@ -1,57 +1,826 @@
-fn pure_memory_gas_cost(
-    evm: &EVM,
-    contract: &Contract,
+use crate::contract::{Contract, ContractRef};
+use crate::errors::Error;
+use crate::evm::EVM;
+use crate::gas::{call_gas, to_word_size};
+use crate::jump_table::GasFunc;
+use crate::memory::Memory;
+use crate::params;
+use crate::stack::Stack;
+
+// memory_gas_cost calculates the quadratic gas for memory expansion. It does so
+// only for the memory region that is expanded, not the total memory.
+pub fn memory_gas_cost(mem: &mut Memory, new_mem_size: u64) -> Result<u64, Error> {
+    if new_mem_size == 0 {
+        return Ok(0);
+    }
+    // The maximum that will fit in a u64 is max_word_size - 1. Anything above
+    // that will result in an overflow. Additionally, a new_mem_size which results in
+    // a new_mem_size_words larger than 0xFFFFFFFF will cause the square operation to
+    // overflow. The constant 0x1FFFFFFFE0 is the highest number that can be used
+    // without overflowing the gas calculation.
+    if new_mem_size > 0x1FFFFFFFE0 {
+        return Err(Error::GasUintOverflow);
+    }
+    let new_mem_size_words = to_word_size(new_mem_size);
+    let new_mem_size = new_mem_size_words * 32;
+
+    if new_mem_size > mem.len() as u64 {
+        let square = new_mem_size_words * new_mem_size_words;
+        let lin_coef = new_mem_size_words * params::MEMORY_GAS;
+        let quad_coef = square / params::QUAD_COEFF_DIV;
+        let new_total_fee = lin_coef + quad_coef;
+
+        let fee = new_total_fee - mem.last_gas_cost;
+        mem.last_gas_cost = new_total_fee;
+
+        return Ok(fee);
+    }
+    Ok(0)
+}
+
+// memory_copier_gas creates the gas functions for the following opcodes, and takes
+// the stack position of the operand which determines the size of the data to copy
+// as argument:
+// CALLDATACOPY (stack position 2)
+// CODECOPY (stack position 2)
+// MCOPY (stack position 2)
+// EXTCODECOPY (stack position 3)
+// RETURNDATACOPY (stack position 2)
+fn memory_copier_gas(
     stack: &Stack,
-    mem: &Memory,
+    mem: &mut Memory,
     memory_size: u64,
+    stackpos: usize,
 ) -> Result<u64, Error> {
-    memory_gas_cost(mem, memory_size)
+    // Gas for expanding the memory
+    let gas = memory_gas_cost(mem, memory_size)?;
+    // And gas for copying data, charged per word at param.CopyGas
+    let (words, overflow) = stack.back(stackpos).uint64_with_overflow();
+    if overflow {
+        return Err(Error::GasUintOverflow);
+    }
+    to_word_size(words)
+        .checked_mul(params::COPY_GAS)
+        .and_then(|words| gas.checked_add(words))
+        .ok_or(Error::GasUintOverflow)
+}
+
+pub fn gas_call_data_copy(
+    _evm: &mut EVM,
+    _contract: &mut Contract,
+    stack: &Stack,
+    mem: &mut Memory,
+    memory_size: u64,
+) -> Result<u64, Error> {
+    memory_copier_gas(stack, mem, memory_size, 2)
 }
-type GasCostFunc = fn(evm: &EVM, contract: &Contract, stack: &Stack, mem: &Memory, memorySize: u64) -> Result<u64, Error>;
 
-pub const gas_return: GasCostFunc = pure_memory_gas_cost
+pub fn gas_code_copy(
+    _evm: &mut EVM,
+    _contract: &mut Contract,
+    stack: &Stack,
+    mem: &mut Memory,
+    memory_size: u64,
+) -> Result<u64, Error> {
+    memory_copier_gas(stack, mem, memory_size, 2)
+}
 
-pub fn gas_static_call(
+pub fn gas_mcopy(
+    _evm: &mut EVM,
+    _contract: &mut Contract,
+    stack: &Stack,
+    mem: &mut Memory,
+    memory_size: u64,
+) -> Result<u64, Error> {
+    memory_copier_gas(stack, mem, memory_size, 2)
+}
+
+pub fn gas_ext_code_copy(
+    _evm: &mut EVM,
+    _contract: &mut Contract,
+    stack: &Stack,
+    mem: &mut Memory,
+    memory_size: u64,
+) -> Result<u64, Error> {
+    memory_copier_gas(stack, mem, memory_size, 3)
+}
+
+pub fn gas_return_data_copy(
+    _evm: &mut EVM,
+    _contract: &mut Contract,
+    stack: &Stack,
+    mem: &mut Memory,
+    memory_size: u64,
+) -> Result<u64, Error> {
+    memory_copier_gas(stack, mem, memory_size, 2)
+}
+
+// gas_sstore implements the legacy gas metering of SSTORE used before
+// Istanbul.
+pub fn gas_sstore(
+    evm: &mut EVM,
+    contract: &mut Contract,
+    stack: &Stack,
+    _mem: &mut Memory,
+    _memory_size: u64,
+) -> Result<u64, Error> {
+    let (y, x) = (stack.back(1), stack.back(0));
+    let current = evm
+        .state_db
+        .get_state(&contract.address(), &common::Hash(x.to_bytes32()));
+    // This checks for 3 scenarios and calculates gas accordingly:
+    //
+    // 1. From a zero-value address to a non-zero value         (NEW VALUE)
+    // 2. From a non-zero value address to a zero-value address (DELETE)
+    // 3. From a non-zero to a non-zero                         (CHANGE)
+    if current.is_zero() && !y.is_zero() {
+        // 0 => non 0
+        Ok(params::SSTORE_SET_GAS)
+    } else if !current.is_zero() && y.is_zero() {
+        // non 0 => 0
+        evm.state_db.add_refund(params::SSTORE_REFUND_GAS);
+        Ok(params::SSTORE_CLEAR_GAS)
+    } else {
+        // non 0 => non 0 (or 0 => 0)
+        Ok(params::SSTORE_RESET_GAS)
+    }
+}
+
+// gas_sstore_eip2200 implements the net gas metering of EIP-2200:
+//
+//  0. If *gasleft* is less than or equal to 2300, fail the current call.
+//  1. If current value equals new value (this is a no-op), SLOAD_GAS is deducted.
+//  2. If current value does not equal new value:
+//     2.1. If original value equals current value (this storage slot has not been changed by the current execution context):
+//     2.1.1. If original value is 0, SSTORE_SET_GAS (20K) gas is deducted.
+//     2.1.2. Otherwise, SSTORE_RESET_GAS gas is deducted. If new value is 0, add SSTORE_CLEARS_SCHEDULE to refund counter.
+//     2.2. If original value does not equal current value (this storage slot is dirty), SLOAD_GAS gas is deducted. Apply both of the following clauses:
+//     2.2.1. If original value is not 0:
+//     2.2.1.1. If current value is 0 (also means that new value is not 0), subtract SSTORE_CLEARS_SCHEDULE gas from refund counter.
+//     2.2.1.2. If new value is 0 (also means that current value is not 0), add SSTORE_CLEARS_SCHEDULE gas to refund counter.
+//     2.2.2. If original value equals new value (this storage slot is reset):
+//     2.2.2.1. If original value is 0, add SSTORE_SET_GAS - SLOAD_GAS to refund counter.
+//     2.2.2.2. Otherwise, add SSTORE_RESET_GAS - SLOAD_GAS gas to refund counter.
+pub fn gas_sstore_eip2200(
+    evm: &mut EVM,
+    contract: &mut Contract,
+    stack: &Stack,
+    _mem: &mut Memory,
+    _memory_size: u64,
+) -> Result<u64, Error> {
+    // If we fail the minimum gas availability invariant, fail (0)
+    if contract.gas <= params::SSTORE_SENTRY_GAS_EIP2200 {
+        return Err(Error::OutOfGas);
+    }
+    // Gas sentry honoured, do the actual gas calculation based on the stored value
+    let (x, y) = (stack.back(0), stack.back(1));
+    let addr = contract.address();
+    let key = common::Hash(x.to_bytes32());
+    let current = evm.state_db.get_state(&addr, &key);
+    let value = common::Hash(y.to_bytes32());
+
+    if current == value {
+        // noop (1)
+        return Ok(params::SLOAD_GAS_EIP2200);
+    }
+    let original = evm.state_db.get_committed_state(&addr, &key);
+    if original == current {
+        if original.is_zero() {
+            // create slot (2.1.1)
+            return Ok(params::SSTORE_SET_GAS_EIP2200);
+        }
+        if value.is_zero() {
+            // delete slot (2.1.2b)
+            evm.state_db
+                .add_refund(params::SSTORE_CLEARS_SCHEDULE_REFUND_EIP2200);
+        }
+        // write existing slot (2.1.2)
+        return Ok(params::SSTORE_RESET_GAS_EIP2200);
+    }
+    if !original.is_zero() {
+        if current.is_zero() {
+            // recreate slot (2.2.1.1)
+            evm.state_db
+                .sub_refund(params::SSTORE_CLEARS_SCHEDULE_REFUND_EIP2200);
+        } else if value.is_zero() {
+            // delete slot (2.2.1.2)
+            evm.state_db
+                .add_refund(params::SSTORE_CLEARS_SCHEDULE_REFUND_EIP2200);
+        }
+    }
+    if original == value {
+        if original.is_zero() {
+            // reset to original inexistent slot (2.2.2.1)
+            evm.state_db
+                .add_refund(params::SSTORE_SET_GAS_EIP2200 - params::SLOAD_GAS_EIP2200);
+        } else {
+            // reset to original existing slot (2.2.2.2)
+            evm.state_db
+                .add_refund(params::SSTORE_RESET_GAS_EIP2200 - params::SLOAD_GAS_EIP2200);
+        }
+    }
+    // dirty update (2.2)
+    Ok(params::SLOAD_GAS_EIP2200)
+}
+
+// sstore_eip2929 implements the EIP-2200 metering with the EIP-2929 cold
+// slot surcharge, refunding clearing_refund for cleared slots.
+fn sstore_eip2929(
     evm: &mut EVM,
     contract: &Contract,
     stack: &Stack,
-    mem: &Memory,
+    clearing_refund: u64,
+) -> Result<u64, Error> {
+    // If we fail the minimum gas availability invariant, fail (0)
+    if contract.gas <= params::SSTORE_SENTRY_GAS_EIP2200 {
+        return Err(Error::OutOfGas);
+    }
+    // Gas sentry honoured, do the actual gas calculation based on the stored value
+    let (x, y) = (stack.back(0), stack.back(1));
+    let addr = contract.address();
+    let slot = common::Hash(x.to_bytes32());
+    let mut cost = 0;
+    // Check slot presence in the access list
+    let (_, slot_present) = evm.state_db.slot_in_access_list(&addr, &slot);
+    if !slot_present {
+        cost = params::COLD_SLOAD_COST_EIP2929;
+        // If the caller cannot afford the cost, this change will be rolled back
+        evm.state_db.add_slot_to_access_list(&addr, &slot);
+    }
+    let value = common::Hash(y.to_bytes32());
+    let current = evm.state_db.get_state(&addr, &slot);
+
+    if current == value {
+        // noop (1)
+        // EIP 2200 original clause:
+        //		return params.SloadGasEIP2200, nil
+        return Ok(cost + params::WARM_STORAGE_READ_COST_EIP2929); // SLOAD_GAS
+    }
+    let original = evm.state_db.get_committed_state(&addr, &slot);
+    if original == current {
+        if original.is_zero() {
+            // create slot (2.1.1)
+            return Ok(cost + params::SSTORE_SET_GAS_EIP2200);
+        }
+        if value.is_zero() {
+            // delete slot (2.1.2b)
+            evm.state_db.add_refund(clearing_refund);
+        }
+        // EIP-2200 original clause:
+        //		return params.SstoreResetGasEIP2200, nil // write existing slot (2.1.2)
+        return Ok(cost + (params::SSTORE_RESET_GAS_EIP2200 - params::COLD_SLOAD_COST_EIP2929)); // write existing slot (2.1.2)
+    }
+    if !original.is_zero() {
+        if current.is_zero() {
+            // recreate slot (2.2.1.1)
+            evm.state_db.sub_refund(clearing_refund);
+        } else if value.is_zero() {
+            // delete slot (2.2.1.2)
+            evm.state_db.add_refund(clearing_refund);
+        }
+    }
+    if original == value {
+        if original.is_zero() {
+            // reset to original inexistent slot (2.2.2.1)
+            // EIP 2200 Original clause:
+            //evm.StateDB.AddRefund(params.SstoreSetGasEIP2200 - params.SloadGasEIP2200)
+            evm.state_db
+                .add_refund(params::SSTORE_SET_GAS_EIP2200 - params::WARM_STORAGE_READ_COST_EIP2929);
+        } else {
+            // reset to original existing slot (2.2.2.2)
+            // EIP 2200 Original clause:
+            //	evm.StateDB.AddRefund(params.SstoreResetGasEIP2200 - params.SloadGasEIP2200)
+            // - SSTORE_RESET_GAS redefined as (5000 - COLD_SLOAD_COST)
+            // - SLOAD_GAS redefined as WARM_STORAGE_READ_COST
+            // Final: (5000 - COLD_SLOAD_COST) - WARM_STORAGE_READ_COST
+            evm.state_db.add_refund(
+                (params::SSTORE_RESET_GAS_EIP2200 - params::COLD_SLOAD_COST_EIP2929)
+                    - params::WARM_STORAGE_READ_COST_EIP2929,
+            );
+        }
+    }
+    // EIP-2200 original clause:
+    //return params.SloadGasEIP2200, nil // dirty update (2.2)
+    Ok(cost + params::WARM_STORAGE_READ_COST_EIP2929) // dirty update (2.2)
+}
+
+// gas_sstore_eip2929 implements gas cost for SSTORE according to EIP-2929
+pub fn gas_sstore_eip2929(
+    evm: &mut EVM,
+    contract: &mut Contract,
+    stack: &Stack,
+    _mem: &mut Memory,
+    _memory_size: u64,
+) -> Result<u64, Error> {
+    sstore_eip2929(
+        evm,
+        contract,
+        stack,
+        params::SSTORE_CLEARS_SCHEDULE_REFUND_EIP2200,
+    )
+}
+
+// gas_sstore_eip3529 implements gas cost for SSTORE according to EIP-3529
+// Replace `SSTORE_CLEARS_SCHEDULE` with `SSTORE_RESET_GAS + ACCESS_LIST_STORAGE_KEY_COST` (4,800)
+pub fn gas_sstore_eip3529(
+    evm: &mut EVM,
+    contract: &mut Contract,
+    stack: &Stack,
+    _mem: &mut Memory,
+    _memory_size: u64,
+) -> Result<u64, Error> {
+    sstore_eip2929(
+        evm,
+        contract,
+        stack,
+        params::SSTORE_CLEARS_SCHEDULE_REFUND_EIP3529,
+    )
+}
+
+// gas_sload_eip2929 calculates dynamic gas for SLOAD according to EIP-2929
+// For SLOAD, if the (address, storage_key) pair (where address is the address of the contract
+// whose storage is being read) is not yet in accessed_storage_keys,
+// charge 2100 gas and add the pair to accessed_storage_keys.
+// If the pair is already in accessed_storage_keys, charge 100 gas.
+pub fn gas_sload_eip2929(
+    evm: &mut EVM,
+    contract: &mut Contract,
+    stack: &Stack,
+    _mem: &mut Memory,
+    _memory_size: u64,
+) -> Result<u64, Error> {
+    let addr = contract.address();
+    let slot = common::Hash(stack.back(0).to_bytes32());
+    // Check slot presence in the access list
+    let (_, slot_present) = evm.state_db.slot_in_access_list(&addr, &slot);
+    if !slot_present {
+        // If the caller cannot afford the cost, this change will be rolled back
+        // If he does afford it, we can skip checking the same thing later on, during execution
+        evm.state_db.add_slot_to_access_list(&addr, &slot);
+        return Ok(params::COLD_SLOAD_COST_EIP2929);
+    }
+    Ok(params::WARM_STORAGE_READ_COST_EIP2929)
+}
+
+// gas_ext_code_copy_eip2929 implements extcodecopy according to EIP-2929
+// EIP spec:
+// > If the target is not in accessed_addresses,
+// > charge COLD_ACCOUNT_ACCESS_COST gas, and add the address to accessed_addresses.
+// > Otherwise, charge WARM_STORAGE_READ_COST gas.
+pub fn gas_ext_code_copy_eip2929(
+    evm: &mut EVM,
+    contract: &mut Contract,
+    stack: &Stack,
+    mem: &mut Memory,
+    memory_size: u64,
+) -> Result<u64, Error> {
+    // memory expansion first (dynamic part of pre-2929 implementation)
+    let gas = gas_ext_code_copy(evm, contract, stack, mem, memory_size)?;
+    let addr = common::Address(stack.back(0).bytes20());
+    // Check slot presence in the access list
+    if !evm.state_db.address_in_access_list(&addr) {
+        evm.state_db.add_address_to_access_list(&addr);
+        // We charge (cold-warm), since 'warm' is already charged as constant_gas
+        return gas
+            .checked_add(params::COLD_ACCOUNT_ACCESS_COST_EIP2929 - params::WARM_STORAGE_READ_COST_EIP2929)
+            .ok_or(Error::GasUintOverflow);
+    }
+    Ok(gas)
+}
+
+// gas_eip2929_account_check checks whether the first stack item (as address) is present in the access list.
+// If it is, this method returns '0', otherwise 'cold-warm' gas, presuming that the opcode using it
+// is also using 'warm' as constant factor.
+// This method is used by:
+// - extcodehash,
+// - extcodesize,
+// - (ext) balance
+pub fn gas_eip2929_account_check(
+    evm: &mut EVM,
+    _contract: &mut Contract,
+    stack: &Stack,
+    _mem: &mut Memory,
+    _memory_size: u64,
+) -> Result<u64, Error> {
+    let addr = common::Address(stack.back(0).bytes20());
+    // Check slot presence in the access list
+    if !evm.state_db.address_in_access_list(&addr) {
+        // If the caller cannot afford the cost, this change will be rolled back
+        evm.state_db.add_address_to_access_list(&addr);
+        // The warm storage read cost is already charged as constant_gas
+        return Ok(params::COLD_ACCOUNT_ACCESS_COST_EIP2929 - params::WARM_STORAGE_READ_COST_EIP2929);
+    }
+    Ok(0)
+}
+
+// call_variant_gas_eip2929 charges the EIP-2929 cold account access on top of
+// the gas computed by old_calculator for the CALL family.
+fn call_variant_gas_eip2929(
+    evm: &mut EVM,
+    contract: &mut Contract,
+    stack: &Stack,
+    mem: &mut Memory,
+    memory_size: u64,
+    old_calculator: GasFunc,
+) -> Result<u64, Error> {
+    let addr = common::Address(stack.back(1).bytes20());
+    // Check slot presence in the access list
+    let warm_access = evm.state_db.address_in_access_list(&addr);
+    // The WarmStorageReadCostEIP2929 (100) is already deducted in the form of a constant cost, so
+    // the cost to charge for cold access, if any, is Cold - Warm
+    let cold_cost = params::COLD_ACCOUNT_ACCESS_COST_EIP2929 - params::WARM_STORAGE_READ_COST_EIP2929;
+    if !warm_access {
+        evm.state_db.add_address_to_access_list(&addr);
+        // Charge the remaining difference here already, to correctly calculate available
+        // gas for call
+        if !contract.use_gas(cold_cost) {
+            return Err(Error::OutOfGas);
+        }
+    }
+    // Now call the old calculator, which takes into account
+    // - create new account
+    // - transfer value
+    // - memory expansion
+    // - 63/64ths rule
+    let gas = old_calculator(evm, contract, stack, mem, memory_size);
+    if warm_access || gas.is_err() {
+        return gas;
+    }
+    // In case of a cold access, we temporarily add the cold charge back, and also
+    // add it to the returned gas. By adding it to the return, it will be charged
+    // outside of this function, as part of the dynamic gas, and that will make it
+    // also become correctly reported to tracers.
+    contract.gas += cold_cost;
+    gas?.checked_add(cold_cost).ok_or(Error::GasUintOverflow)
+}
+
+pub fn gas_call_eip2929(
+    evm: &mut EVM,
+    contract: &mut Contract,
+    stack: &Stack,
+    mem: &mut Memory,
     memory_size: u64,
+) -> Result<u64, Error> {
+    call_variant_gas_eip2929(evm, contract, stack, mem, memory_size, gas_call)
+}
+
+pub fn gas_call_code_eip2929(
+    evm: &mut EVM,
+    contract: &mut Contract,
+    stack: &Stack,
+    mem: &mut Memory,
+    memory_size: u64,
+) -> Result<u64, Error> {
+    call_variant_gas_eip2929(evm, contract, stack, mem, memory_size, gas_call_code)
+}
+
+pub fn gas_delegate_call_eip2929(
+    evm: &mut EVM,
+    contract: &mut Contract,
+    stack: &Stack,
+    mem: &mut Memory,
+    memory_size: u64,
+) -> Result<u64, Error> {
+    call_variant_gas_eip2929(evm, contract, stack, mem, memory_size, gas_delegate_call)
+}
+
+pub fn gas_static_call_eip2929(
+    evm: &mut EVM,
+    contract: &mut Contract,
+    stack: &Stack,
+    mem: &mut Memory,
+    memory_size: u64,
+) -> Result<u64, Error> {
+    call_variant_gas_eip2929(evm, contract, stack, mem, memory_size, gas_static_call)
+}
+
+fn exp_gas(stack: &Stack, byte_gas: u64) -> Result<u64, Error> {
+    let exp_byte_len = (stack.back(1).bits() as u64).div_ceil(8);
+    (exp_byte_len * byte_gas)
+        .checked_add(params::EXP_GAS)
+        .ok_or(Error::GasUintOverflow)
+}
+
+pub fn gas_exp_frontier(
+    _evm: &mut EVM,
+    _contract: &mut Contract,
+    stack: &Stack,
+    _mem: &mut Memory,
+    _memory_size: u64,
+) -> Result<u64, Error> {
+    exp_gas(stack, params::EXP_BYTE_FRONTIER)
+}
+
+pub fn gas_exp_eip158(
+    _evm: &mut EVM,
+    _contract: &mut Contract,
+    stack: &Stack,
+    _mem: &mut Memory,
+    _memory_size: u64,
+) -> Result<u64, Error> {
+    exp_gas(stack, params::EXP_BYTE_EIP158)
+}
+
+// words_gas adds word_gas for every word of the size operand at stackpos to
+// the memory expansion gas.
+fn words_gas(
+    stack: &Stack,
+    mem: &mut Memory,
+    memory_size: u64,
+    stackpos: usize,
+    word_gas: u64,
 ) -> Result<u64, Error> {
     let gas = memory_gas_cost(mem, memory_size)?;
-    evm.call_gas_temp = call_gas(evm.chain_rules.is_eip150, contract.gas, gas, stack.back(0))?;
-    let (gas, overflow) = math::safe_add(gas, evm.call_gas_temp);
+    let (words, overflow) = stack.back(stackpos).uint64_with_overflow();
     if overflow {
         return Err(Error::GasUintOverflow);
     }
-    Ok(gas)
+    to_word_size(words)
+        .checked_mul(word_gas)
+        .and_then(|words| gas.checked_add(words))
+        .ok_or(Error::GasUintOverflow)
 }
 
-pub fn gas_selfdestruct(
+pub fn gas_keccak256(
+    _evm: &mut EVM,
+    _contract: &mut Contract,
+    stack: &Stack,
+    mem: &mut Memory,
+    memory_size: u64,
+) -> Result<u64, Error> {
+    words_gas(stack, mem, memory_size, 1, params::KECCAK256_WORD_GAS)
+}
+
+// pure_memory_gas_cost is used by several operations, which aside from their
+// static cost have a dynamic cost which is solely based on the memory
+// expansion
+pub fn pure_memory_gas_cost(
+    _evm: &mut EVM,
+    _contract: &mut Contract,
+    _stack: &Stack,
+    mem: &mut Memory,
+    memory_size: u64,
+) -> Result<u64, Error> {
+    memory_gas_cost(mem, memory_size)
+}
+
+pub const GAS_RETURN: GasFunc = pure_memory_gas_cost;
+pub const GAS_REVERT: GasFunc = pure_memory_gas_cost;
+pub const GAS_MLOAD: GasFunc = pure_memory_gas_cost;
+pub const GAS_MSTORE8: GasFunc = pure_memory_gas_cost;
+pub const GAS_MSTORE: GasFunc = pure_memory_gas_cost;
+pub const GAS_CREATE: GasFunc = pure_memory_gas_cost;
+
+pub fn gas_create2(
+    _evm: &mut EVM,
+    _contract: &mut Contract,
+    stack: &Stack,
+    mem: &mut Memory,
+    memory_size: u64,
+) -> Result<u64, Error> {
+    words_gas(stack, mem, memory_size, 2, params::KECCAK256_WORD_GAS)
+}
+
+// init_code_gas meters the initcode of CREATE and CREATE2 per EIP-3860,
+// rejecting initcode above params::MAX_INIT_CODE_SIZE.
+fn init_code_gas(stack: &Stack, mem: &mut Memory, memory_size: u64, word_gas: u64) -> Result<u64, Error> {
+    let gas = memory_gas_cost(mem, memory_size)?;
+    let (size, overflow) = stack.back(2).uint64_with_overflow();
+    if overflow || size > params::MAX_INIT_CODE_SIZE {
+        return Err(Error::MaxInitCodeSizeExceeded);
+    }
+    // Since size <= params::MAX_INIT_CODE_SIZE, this multiplication cannot overflow
+    let more_gas = word_gas * to_word_size(size);
+    gas.checked_add(more_gas).ok_or(Error::GasUintOverflow)
+}
+
+pub fn gas_create_eip3860(
+    _evm: &mut EVM,
+    _contract: &mut Contract,
+    stack: &Stack,
+    mem: &mut Memory,
+    memory_size: u64,
+) -> Result<u64, Error> {
+    init_code_gas(stack, mem, memory_size, params::INIT_CODE_WORD_GAS)
+}
+
+pub fn gas_create2_eip3860(
+    _evm: &mut EVM,
+    _contract: &mut Contract,
+    stack: &Stack,
+    mem: &mut Memory,
+    memory_size: u64,
+) -> Result<u64, Error> {
+    init_code_gas(
+        stack,
+        mem,
+        memory_size,
+        params::INIT_CODE_WORD_GAS + params::KECCAK256_WORD_GAS,
+    )
+}
+
+// log_gas charges the LOG base gas, n topics and the logged data.
+fn log_gas(stack: &Stack, mem: &mut Memory, memory_size: u64, n: u64) -> Result<u64, Error> {
+    let (request_size, overflow) = stack.back(1).uint64_with_overflow();
+    if overflow {
+        return Err(Error::GasUintOverflow);
+    }
+    let gas = memory_gas_cost(mem, memory_size)?;
+    request_size
+        .checked_mul(params::LOG_DATA_GAS)
+        .and_then(|data_gas| gas.checked_add(data_gas))
+        .and_then(|gas| gas.checked_add(params::LOG_GAS))
+        .and_then(|gas| gas.checked_add(n * params::LOG_TOPIC_GAS))
+        .ok_or(Error::GasUintOverflow)
+}
+
+pub fn gas_log0(
+    _evm: &mut EVM,
+    _contract: &mut Contract,
+    stack: &Stack,
+    mem: &mut Memory,
+    memory_size: u64,
+) -> Result<u64, Error> {
+    log_gas(stack, mem, memory_size, 0)
+}
+
+pub fn gas_log1(
+    _evm: &mut EVM,
+    _contract: &mut Contract,
+    stack: &Stack,
+    mem: &mut Memory,
+    memory_size: u64,
+) -> Result<u64, Error> {
+    log_gas(stack, mem, memory_size, 1)
+}
+
+pub fn gas_log2(
+    _evm: &mut EVM,
+    _contract: &mut Contract,
+    stack: &Stack,
+    mem: &mut Memory,
+    memory_size: u64,
+) -> Result<u64, Error> {
+    log_gas(stack, mem, memory_size, 2)
+}
+
+pub fn gas_log3(
+    _evm: &mut EVM,
+    _contract: &mut Contract,
+    stack: &Stack,
+    mem: &mut Memory,
+    memory_size: u64,
+) -> Result<u64, Error> {
+    log_gas(stack, mem, memory_size, 3)
+}
+
+pub fn gas_log4(
+    _evm: &mut EVM,
+    _contract: &mut Contract,
+    stack: &Stack,
+    mem: &mut Memory,
+    memory_size: u64,
+) -> Result<u64, Error> {
+    log_gas(stack, mem, memory_size, 4)
+}
+
+// with_call_gas stores the gas forwarded to the callee in evm.call_gas_temp
+// and adds it to the gas of the calling frame.
+fn with_call_gas(evm: &mut EVM, contract: &Contract, stack: &Stack, gas: u64) -> Result<u64, Error> {
+    evm.call_gas_temp = call_gas(evm.chain_rules.is_eip150, contract.gas, gas, stack.back(0))?;
+    gas.checked_add(evm.call_gas_temp)
+        .ok_or(Error::GasUintOverflow)
+}
+
+pub fn gas_call(
     evm: &mut EVM,
-    contract: &Contract,
+    contract: &mut Contract,
+    stack: &Stack,
+    mem: &mut Memory,
+    memory_size: u64,
+) -> Result<u64, Error> {
+    let mut gas = 0u64;
+    let transfers_value = !stack.back(2).is_zero();
+    let address = common::Address(stack.back(1).bytes20());
+    if evm.chain_rules.is_eip158 {
+        if transfers_value && evm.state_db.empty(&address) {
+            gas += params::CALL_NEW_ACCOUNT_GAS;
+        }
+    } else if !evm.state_db.exist(&address) {
+        gas += params::CALL_NEW_ACCOUNT_GAS;
+    }
+    if transfers_value {
+        gas += params::CALL_VALUE_TRANSFER_GAS;
+    }
+    let memory_gas = memory_gas_cost(mem, memory_size)?;
+    let gas = gas.checked_add(memory_gas).ok_or(Error::GasUintOverflow)?;
+    with_call_gas(evm, contract, stack, gas)
+}
+
+pub fn gas_call_code(
+    evm: &mut EVM,
+    contract: &mut Contract,
+    stack: &Stack,
+    mem: &mut Memory,
+    memory_size: u64,
+) -> Result<u64, Error> {
+    let memory_gas = memory_gas_cost(mem, memory_size)?;
+    let mut gas = 0u64;
+    if !stack.back(2).is_zero() {
+        gas += params::CALL_VALUE_TRANSFER_GAS;
+    }
+    let gas = gas.checked_add(memory_gas).ok_or(Error::GasUintOverflow)?;
+    with_call_gas(evm, contract, stack, gas)
+}
+
+pub fn gas_delegate_call(
+    evm: &mut EVM,
+    contract: &mut Contract,
     stack: &Stack,
-    mem: &Memory,
+    mem: &mut Memory,
     memory_size: u64,
 ) -> Result<u64, Error> {
-    let mut gas: u64 = 0;
+    let gas = memory_gas_cost(mem, memory_size)?;
+    with_call_gas(evm, contract, stack, gas)
+}
+
+pub fn gas_static_call(
+    evm: &mut EVM,
+    contract: &mut Contract,
+    stack: &Stack,
+    mem: &mut Memory,
+    memory_size: u64,
+) -> Result<u64, Error> {
+    let gas = memory_gas_cost(mem, memory_size)?;
+    with_call_gas(evm, contract, stack, gas)
+}
+
+pub fn gas_selfdestruct(
+    evm: &mut EVM,
+    contract: &mut Contract,
+    stack: &Stack,
+    _mem: &mut Memory,
+    _memory_size: u64,
+) -> Result<u64, Error> {
+    let mut gas = 0u64;
+    // EIP150 homestead gas reprice fork:
     if evm.chain_rules.is_eip150 {
         gas = params::SELFDESTRUCT_GAS_EIP150;
         let address = common::Address(stack.back(0).bytes20());
 
         if evm.chain_rules.is_eip158 {
-            if evm.state_db.empty(address)
-                && evm.state_db.get_balance(contract.address()).sign() != 0
-            {
+            // if empty and transfers value
+            if evm.state_db.empty(&address) && !evm.state_db.get_balance(&contract.address()).is_zero() {
                 gas += params::CREATE_BY_SELFDESTRUCT_GAS;
             }
-        } else if !evm.state_db.exist(address) {
+        } else if !evm.state_db.exist(&address) {
             gas += params::CREATE_BY_SELFDESTRUCT_GAS;
         }
     }
 
-    if !evm.state_db.has_suicided(contract.address()) {
+    if !evm.state_db.has_suicided(&contract.address()) {
         evm.state_db.add_refund(params::SELFDESTRUCT_REFUND_GAS);
     }
     Ok(gas)
 }
+
+// selfdestruct_eip2929 charges the cold access of the beneficiary. Refunds
+// are only granted before EIP-3529.
+fn selfdestruct_eip2929(evm: &mut EVM, contract: &Contract, stack: &Stack, refunds_enabled: bool) -> u64 {
+    let mut gas = 0u64;
+    let address = common::Address(stack.back(0).bytes20());
+    if !evm.state_db.address_in_access_list(&address) {
+        // If the caller cannot afford the cost, this change will be rolled back
+        evm.state_db.add_address_to_access_list(&address);
+        gas = params::COLD_ACCOUNT_ACCESS_COST_EIP2929;
+    }
+    // if empty and transfers value
+    if evm.state_db.empty(&address) && !evm.state_db.get_balance(&contract.address()).is_zero() {
+        gas += params::CREATE_BY_SELFDESTRUCT_GAS;
+    }
+    if refunds_enabled && !evm.state_db.has_suicided(&contract.address()) {
+        evm.state_db.add_refund(params::SELFDESTRUCT_REFUND_GAS);
+    }
+    gas
+}
+
+pub fn gas_selfdestruct_eip2929(
+    evm: &mut EVM,
+    contract: &mut Contract,
+    stack: &Stack,
+    _mem: &mut Memory,
+    _memory_size: u64,
+) -> Result<u64, Error> {
+    Ok(selfdestruct_eip2929(evm, contract, stack, true))
+}
+
+// gas_selfdestruct_eip3529 implements the changes in EIP-3529 (no refunds)
+pub fn gas_selfdestruct_eip3529(
+    evm: &mut EVM,
+    contract: &mut Contract,
+    stack: &Stack,
+    _mem: &mut Memory,
+    _memory_size: u64,
+) -> Result<u64, Error> {
+    Ok(selfdestruct_eip2929(evm, contract, stack, false))
+}
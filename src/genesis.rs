@@ -0,0 +1,488 @@
+// Package genesis bootstraps a chain from a geth-compatible genesis file.
+//
+// A Genesis holds the chain config, the header fields of block zero and the
+// initial state (alloc). to_block hashes the initial state to produce the
+// genesis block, commit additionally writes the state and the block to a
+// database, and setup_genesis_block only does so if the database is empty,
+// checking the stored genesis otherwise.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+
+use crate::block::{Block, Header, Withdrawal};
+use crate::chain_config::ChainConfig;
+use crate::ethdb::{Batch, KeyValueStore};
+use crate::hexutil;
+use crate::memory_state_db::MemoryStateDB;
+use crate::params;
+use crate::rawdb;
+use crate::state_db::StateDB;
+use crate::trie::{Database, TrieError, EMPTY_ROOT_HASH};
+use uint256::Uint256;
+
+// Genesis hashes to enforce below configs on.
+pub const MAINNET_GENESIS_HASH: common::Hash = common::Hash([
+    0xd4, 0xe5, 0x67, 0x40, 0xf8, 0x76, 0xae, 0xf8, 0xc0, 0x10, 0xb8, 0x6a, 0x40, 0xd5, 0xf5, 0x67,
+    0x45, 0xa1, 0x18, 0xd0, 0x90, 0x6a, 0x34, 0xe6, 0x9a, 0xec, 0x8c, 0x0d, 0xb1, 0xcb, 0x8f, 0xa3,
+]);
+// MAINNET_GENESIS_ROOT is the state root of the mainnet genesis block.
+pub const MAINNET_GENESIS_ROOT: common::Hash = common::Hash([
+    0xd7, 0xf8, 0x97, 0x4f, 0xb5, 0xac, 0x78, 0xd9, 0xac, 0x09, 0x9b, 0x9a, 0xd5, 0x01, 0x8b, 0xed,
+    0xc2, 0xce, 0x0a, 0x72, 0xda, 0xd1, 0x82, 0x7a, 0x17, 0x09, 0xda, 0x30, 0x58, 0x0f, 0x05, 0x44,
+]);
+
+// GENESIS_GAS_LIMIT is the gas limit of the genesis block if none is set.
+pub const GENESIS_GAS_LIMIT: u64 = 4712388;
+// GENESIS_DIFFICULTY is the difficulty of the genesis block if none is set.
+pub const GENESIS_DIFFICULTY: u64 = 131072;
+
+#[derive(Debug)]
+pub enum GenesisError {
+    Io(io::Error),
+    // Json is returned if the genesis file is not valid JSON or doesn't match
+    // the genesis format.
+    Json(String),
+    State(TrieError),
+    // Mismatch is returned if the database holds a different genesis block.
+    Mismatch {
+        stored: common::Hash,
+        new: common::Hash,
+    },
+}
+
+impl fmt::Display for GenesisError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GenesisError::Io(err) => write!(f, "{}", err),
+            GenesisError::Json(err) => write!(f, "invalid genesis file: {}", err),
+            GenesisError::State(err) => write!(f, "state: {:?}", err),
+            GenesisError::Mismatch { stored, new } => write!(
+                f,
+                "database contains incompatible genesis (have {}, new {})",
+                hexutil::encode(stored.as_bytes()),
+                hexutil::encode(new.as_bytes())
+            ),
+        }
+    }
+}
+
+impl std::error::Error for GenesisError {}
+
+impl From<io::Error> for GenesisError {
+    fn from(err: io::Error) -> Self {
+        GenesisError::Io(err)
+    }
+}
+
+impl From<TrieError> for GenesisError {
+    fn from(err: TrieError) -> Self {
+        GenesisError::State(err)
+    }
+}
+
+// hex_or_decimal handles integers encoded either as hex strings with 0x
+// prefix, decimal strings or JSON numbers, as accepted by geth.
+mod hex_or_decimal {
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    use crate::hexutil;
+
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Raw {
+        Number(u64),
+        Text(String),
+    }
+
+    pub fn parse(s: &str) -> Option<u64> {
+        match s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+            Some(hex) => u64::from_str_radix(hex, 16).ok(),
+            None => s.parse().ok(),
+        }
+    }
+
+    pub fn serialize<S: Serializer>(value: &u64, serializer: S) -> Result<S::Ok, S::Error> {
+        hexutil::quantity::serialize(value, serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u64, D::Error> {
+        match Raw::deserialize(deserializer)? {
+            Raw::Number(n) => Ok(n),
+            Raw::Text(s) => {
+                parse(&s).ok_or_else(|| D::Error::custom(format!("invalid integer {:?}", s)))
+            }
+        }
+    }
+}
+
+// hex_or_decimal256 is hex_or_decimal for 256 bit integers.
+mod hex_or_decimal256 {
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+    use uint256::Uint256;
+
+    use crate::hexutil;
+
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Raw {
+        Number(u64),
+        Text(String),
+    }
+
+    pub fn parse(s: &str) -> Option<Uint256> {
+        if s.starts_with("0x") || s.starts_with("0X") {
+            return hexutil::decode_big(&s.to_lowercase()).ok();
+        }
+        if s.is_empty() || !s.bytes().all(|c| c.is_ascii_digit()) {
+            return None;
+        }
+        let ten = Uint256::from_uint64(10);
+        s.bytes().try_fold(Uint256::default(), |acc, c| {
+            acc.checked_mul(&ten)?
+                .checked_add(&Uint256::from_uint64((c - b'0') as u64))
+        })
+    }
+
+    pub fn serialize<S: Serializer>(value: &Uint256, serializer: S) -> Result<S::Ok, S::Error> {
+        hexutil::big::serialize(value, serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Uint256, D::Error> {
+        match Raw::deserialize(deserializer)? {
+            Raw::Number(n) => Ok(Uint256::from_uint64(n)),
+            Raw::Text(s) => {
+                parse(&s).ok_or_else(|| D::Error::custom(format!("invalid integer {:?}", s)))
+            }
+        }
+    }
+
+    pub mod opt {
+        use serde::{Deserialize, Deserializer, Serializer};
+        use uint256::Uint256;
+
+        pub fn serialize<S: Serializer>(
+            value: &Option<Uint256>,
+            serializer: S,
+        ) -> Result<S::Ok, S::Error> {
+            match value {
+                Some(value) => super::serialize(value, serializer),
+                None => serializer.serialize_none(),
+            }
+        }
+
+        pub fn deserialize<'de, D: Deserializer<'de>>(
+            deserializer: D,
+        ) -> Result<Option<Uint256>, D::Error> {
+            #[derive(Deserialize)]
+            struct Wrapper(#[serde(with = "super")] Uint256);
+            Ok(Option::<Wrapper>::deserialize(deserializer)?.map(|Wrapper(value)| value))
+        }
+    }
+}
+
+// decode_padded decodes a hex string, with or without 0x prefix, into a
+// value of N bytes, left-padding shorter inputs with zeros.
+fn decode_padded<const N: usize>(s: &str) -> Result<[u8; N], hexutil::HexError> {
+    let raw = s.strip_prefix("0x").unwrap_or(s);
+    if raw.len() > 2 * N {
+        return Err(hexutil::HexError::TooLong);
+    }
+    hexutil::decode_fixed::<N>(&format!("0x{:0>width$}", raw, width = 2 * N))
+}
+
+// alloc encodes the genesis alloc as a JSON object keyed by address. Like
+// geth, addresses are accepted with or without 0x prefix.
+mod alloc {
+    use std::collections::HashMap;
+
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    use super::{decode_padded, GenesisAccount};
+    use crate::hexutil;
+
+    pub fn serialize<S: Serializer>(
+        value: &HashMap<common::Address, GenesisAccount>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_map(
+            value
+                .iter()
+                .map(|(addr, account)| (hexutil::encode(&addr.0), account)),
+        )
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<HashMap<common::Address, GenesisAccount>, D::Error> {
+        HashMap::<String, GenesisAccount>::deserialize(deserializer)?
+            .into_iter()
+            .map(|(addr, account)| {
+                let addr = decode_padded::<20>(&addr).map_err(D::Error::custom)?;
+                Ok((common::Address(addr), account))
+            })
+            .collect()
+    }
+}
+
+// storage encodes account storage as a JSON object of 32 byte hex values.
+// Shorter keys and values are left-padded.
+mod storage {
+    use std::collections::HashMap;
+
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    use super::decode_padded;
+    use crate::hexutil;
+
+    pub fn serialize<S: Serializer>(
+        value: &HashMap<common::Hash, common::Hash>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_map(value.iter().map(|(key, value)| {
+            (
+                hexutil::encode(key.as_bytes()),
+                hexutil::encode(value.as_bytes()),
+            )
+        }))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<HashMap<common::Hash, common::Hash>, D::Error> {
+        HashMap::<String, String>::deserialize(deserializer)?
+            .into_iter()
+            .map(|(key, value)| {
+                let key = decode_padded::<32>(&key).map_err(D::Error::custom)?;
+                let value = decode_padded::<32>(&value).map_err(D::Error::custom)?;
+                Ok((common::Hash(key), common::Hash(value)))
+            })
+            .collect()
+    }
+}
+
+fn is_zero(v: &u64) -> bool {
+    *v == 0
+}
+
+// GenesisAccount is an account in the state of the genesis block.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct GenesisAccount {
+    #[serde(
+        with = "hexutil::bytes",
+        default,
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub code: Vec<u8>,
+    #[serde(with = "storage", default, skip_serializing_if = "HashMap::is_empty")]
+    pub storage: HashMap<common::Hash, common::Hash>,
+    #[serde(with = "hex_or_decimal256")]
+    pub balance: Uint256,
+    #[serde(with = "hex_or_decimal", default, skip_serializing_if = "is_zero")]
+    pub nonce: u64,
+}
+
+// Genesis specifies the header fields, state of a genesis block. It also
+// defines hard fork switch-over blocks through the chain configuration.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Genesis {
+    #[serde(default)]
+    pub config: ChainConfig,
+    #[serde(with = "hex_or_decimal", default)]
+    pub nonce: u64,
+    #[serde(with = "hex_or_decimal", default)]
+    pub timestamp: u64,
+    #[serde(with = "hexutil::bytes", default)]
+    pub extra_data: Vec<u8>,
+    #[serde(with = "hex_or_decimal", default)]
+    pub gas_limit: u64,
+    #[serde(with = "hex_or_decimal256::opt", default)]
+    pub difficulty: Option<Uint256>,
+    #[serde(with = "hexutil::hash", default)]
+    pub mix_hash: common::Hash,
+    #[serde(with = "hexutil::address", default)]
+    pub coinbase: common::Address,
+    #[serde(with = "alloc")]
+    pub alloc: HashMap<common::Address, GenesisAccount>,
+
+    // These fields are used for consensus tests. Please don't use them
+    // in actual genesis blocks.
+    #[serde(with = "hex_or_decimal", default)]
+    pub number: u64,
+    #[serde(with = "hex_or_decimal", default)]
+    pub gas_used: u64,
+    #[serde(with = "hexutil::hash", default)]
+    pub parent_hash: common::Hash,
+    #[serde(rename = "baseFeePerGas", with = "hex_or_decimal256::opt", default)]
+    pub base_fee: Option<Uint256>,
+    #[serde(with = "hexutil::quantity::opt", default)]
+    pub excess_blob_gas: Option<u64>,
+    #[serde(with = "hexutil::quantity::opt", default)]
+    pub blob_gas_used: Option<u64>,
+}
+
+impl Genesis {
+    // from_json decodes a genesis in the geth genesis file format.
+    pub fn from_json(data: &str) -> Result<Genesis, GenesisError> {
+        serde_json::from_str(data).map_err(|err| GenesisError::Json(err.to_string()))
+    }
+
+    // load reads and decodes the genesis file at path.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Genesis, GenesisError> {
+        Genesis::from_json(&fs::read_to_string(path)?)
+    }
+
+    // flush adds the genesis alloc to the given state.
+    fn flush(&self, state: &mut dyn StateDB) {
+        for (addr, account) in self.alloc.iter() {
+            state.create_account(addr);
+            state.add_balance(addr, &account.balance);
+            if !account.code.is_empty() {
+                state.set_code(addr, &account.code);
+            }
+            state.set_nonce(addr, account.nonce);
+            for (key, value) in account.storage.iter() {
+                state.set_state(addr, *key, *value);
+            }
+        }
+    }
+
+    // to_block_with_root constructs the genesis block with the given state
+    // root.
+    fn to_block_with_root(&self, root: common::Hash) -> Block {
+        let mut header = Header {
+            number: self.number,
+            nonce: self.nonce.to_be_bytes(),
+            time: self.timestamp,
+            parent_hash: self.parent_hash,
+            extra: self.extra_data.clone(),
+            gas_limit: self.gas_limit,
+            gas_used: self.gas_used,
+            base_fee: self.base_fee.clone(),
+            difficulty: self.difficulty.clone().unwrap_or_default(),
+            mix_digest: self.mix_hash,
+            coinbase: self.coinbase,
+            root,
+            ..Default::default()
+        };
+        if self.gas_limit == 0 {
+            header.gas_limit = GENESIS_GAS_LIMIT;
+        }
+        if self.difficulty.is_none() && self.mix_hash == common::Hash::default() {
+            header.difficulty = Uint256::from_uint64(GENESIS_DIFFICULTY);
+        }
+        let config = &self.config;
+        if config.is_london(self.number) && header.base_fee.is_none() {
+            header.base_fee = Some(Uint256::from_uint64(params::INITIAL_BASE_FEE));
+        }
+        let mut withdrawals: Option<Vec<Withdrawal>> = None;
+        if config.is_shanghai(self.number, self.timestamp) {
+            withdrawals = Some(Vec::new());
+        }
+        if config.is_cancun(self.number, self.timestamp) {
+            // EIP-4788: The parent_beacon_root of the genesis block is always
+            // the zero hash. This is because the genesis block does not have
+            // a parent by definition.
+            header.parent_beacon_root = Some(common::Hash::default());
+            header.excess_blob_gas = Some(self.excess_blob_gas.unwrap_or(0));
+            header.blob_gas_used = Some(self.blob_gas_used.unwrap_or(0));
+        }
+        Block::new(header, Vec::new(), Vec::new(), withdrawals)
+    }
+
+    // to_block returns the genesis block according to genesis specification.
+    // The state is hashed in memory and not persisted.
+    pub fn to_block(&self) -> Result<Block, GenesisError> {
+        let mut state = MemoryStateDB::new();
+        self.flush(&mut state);
+        let root = state.intermediate_root(false)?;
+        Ok(self.to_block_with_root(root))
+    }
+
+    // commit writes the genesis state and block to disk, marking the block as
+    // the canonical head.
+    pub fn commit(&self, disk: Arc<dyn KeyValueStore>) -> Result<Block, GenesisError> {
+        let mut state = MemoryStateDB::open(EMPTY_ROOT_HASH, Database::with_disk(disk.clone()))?;
+        self.flush(&mut state);
+        let root = state.commit(false)?;
+        let block = self.to_block_with_root(root);
+
+        let hash = block.hash();
+        let mut batch = Batch::new();
+        rawdb::write_td(&mut batch, &hash, block.number(), &block.header.difficulty);
+        rawdb::write_block(&mut batch, &block);
+        rawdb::write_receipts(&mut batch, &hash, block.number(), &[]);
+        rawdb::write_canonical_hash(&mut batch, &hash, block.number());
+        rawdb::write_head_block_hash(&mut batch, &hash);
+        disk.write_batch(&batch)?;
+        Ok(block)
+    }
+}
+
+// setup_genesis_block writes the genesis block to the database if it holds
+// no chain yet and returns it. Otherwise the stored genesis block is checked
+// against the given one and GenesisError::Mismatch is returned if they
+// differ.
+pub fn setup_genesis_block(
+    disk: Arc<dyn KeyValueStore>,
+    genesis: &Genesis,
+) -> Result<Block, GenesisError> {
+    let stored = match rawdb::read_canonical_hash(disk.as_ref(), 0)? {
+        Some(stored) => stored,
+        None => return genesis.commit(disk),
+    };
+    let block = genesis.to_block()?;
+    if block.hash() != stored {
+        return Err(GenesisError::Mismatch {
+            stored,
+            new: block.hash(),
+        });
+    }
+    Ok(block)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chain_config::MAINNET_CHAIN_CONFIG;
+    use crate::ethdb::MemoryDatabase;
+
+    fn mainnet_genesis() -> Genesis {
+        let mut genesis = Genesis::from_json(include_str!("testdata/genesis/mainnet.json")).unwrap();
+        genesis.config = MAINNET_CHAIN_CONFIG;
+        genesis
+    }
+
+    #[test]
+    fn test_mainnet_genesis() {
+        let genesis = mainnet_genesis();
+        assert_eq!(genesis.alloc.len(), 8893);
+        let block = genesis.to_block().unwrap();
+        assert_eq!(block.header.root, MAINNET_GENESIS_ROOT);
+        assert_eq!(block.hash(), MAINNET_GENESIS_HASH);
+    }
+
+    #[test]
+    fn test_setup_mainnet_genesis() {
+        let genesis = mainnet_genesis();
+        let disk: Arc<dyn KeyValueStore> = Arc::new(MemoryDatabase::new());
+        let block = setup_genesis_block(disk.clone(), &genesis).unwrap();
+        assert_eq!(block.header.root, MAINNET_GENESIS_ROOT);
+        assert_eq!(rawdb::read_canonical_hash(disk.as_ref(), 0).unwrap(), Some(MAINNET_GENESIS_HASH));
+
+        // A second setup checks the stored genesis instead of writing it.
+        assert_eq!(setup_genesis_block(disk.clone(), &genesis).unwrap().hash(), MAINNET_GENESIS_HASH);
+        let mut other = genesis;
+        other.extra_data.clear();
+        assert!(matches!(
+            setup_genesis_block(disk, &other),
+            Err(GenesisError::Mismatch { stored, .. }) if stored == MAINNET_GENESIS_HASH
+        ));
+    }
+}
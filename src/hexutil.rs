@@ -0,0 +1,225 @@
+// Package hexutil implements hex encoding with 0x prefix, as used by the
+// Ethereum JSON formats. Byte strings are encoded as even-length hex
+// ("0x", "0x00ff"), quantities without leading zeros ("0x0", "0x1a").
+//
+// The nested modules are meant for serde's `with` attribute:
+//
+//     #[serde(with = "hexutil::quantity")]
+//     pub gas_limit: u64,
+
+use std::fmt;
+
+use uint256::Uint256;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HexError {
+    MissingPrefix,
+    OddLength,
+    InvalidChar,
+    EmptyNumber,
+    LeadingZero,
+    TooLong,
+}
+
+impl fmt::Display for HexError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HexError::MissingPrefix => write!(f, "hex string without 0x prefix"),
+            HexError::OddLength => write!(f, "hex string of odd length"),
+            HexError::InvalidChar => write!(f, "invalid hex string"),
+            HexError::EmptyNumber => write!(f, "hex string \"0x\""),
+            HexError::LeadingZero => write!(f, "hex number with leading zero digits"),
+            HexError::TooLong => write!(f, "hex number too long"),
+        }
+    }
+}
+
+impl std::error::Error for HexError {}
+
+const CHARS: &[u8; 16] = b"0123456789abcdef";
+
+// encode encodes b as a hex string with 0x prefix.
+pub fn encode(b: &[u8]) -> String {
+    let mut s = String::with_capacity(2 + b.len() * 2);
+    s.push_str("0x");
+    for byte in b {
+        s.push(CHARS[(byte >> 4) as usize] as char);
+        s.push(CHARS[(byte & 0x0f) as usize] as char);
+    }
+    s
+}
+
+fn strip_prefix(input: &str) -> Result<&str, HexError> {
+    input
+        .strip_prefix("0x")
+        .or_else(|| input.strip_prefix("0X"))
+        .ok_or(HexError::MissingPrefix)
+}
+
+fn nibble(c: u8) -> Result<u8, HexError> {
+    match c {
+        b'0'..=b'9' => Ok(c - b'0'),
+        b'a'..=b'f' => Ok(c - b'a' + 10),
+        b'A'..=b'F' => Ok(c - b'A' + 10),
+        _ => Err(HexError::InvalidChar),
+    }
+}
+
+// decode decodes a hex string with 0x prefix.
+pub fn decode(input: &str) -> Result<Vec<u8>, HexError> {
+    let raw = strip_prefix(input)?.as_bytes();
+    if raw.len() % 2 != 0 {
+        return Err(HexError::OddLength);
+    }
+    raw.chunks(2)
+        .map(|pair| Ok(nibble(pair[0])? << 4 | nibble(pair[1])?))
+        .collect()
+}
+
+// decode_fixed decodes a hex string with 0x prefix into exactly N bytes.
+pub fn decode_fixed<const N: usize>(input: &str) -> Result<[u8; N], HexError> {
+    let b = decode(input)?;
+    if b.len() != N {
+        return Err(HexError::TooLong);
+    }
+    let mut out = [0u8; N];
+    out.copy_from_slice(&b);
+    Ok(out)
+}
+
+// encode_u64 encodes i as a hex quantity.
+pub fn encode_u64(i: u64) -> String {
+    format!("{:#x}", i)
+}
+
+// check_number validates a quantity and returns its digits.
+fn check_number(input: &str) -> Result<&str, HexError> {
+    let raw = strip_prefix(input)?;
+    if raw.is_empty() {
+        return Err(HexError::EmptyNumber);
+    }
+    if raw.len() > 1 && raw.starts_with('0') {
+        return Err(HexError::LeadingZero);
+    }
+    Ok(raw)
+}
+
+// decode_u64 decodes a hex quantity.
+pub fn decode_u64(input: &str) -> Result<u64, HexError> {
+    let raw = check_number(input)?;
+    if raw.len() > 16 {
+        return Err(HexError::TooLong);
+    }
+    raw.bytes().try_fold(0u64, |acc, c| Ok(acc << 4 | nibble(c)? as u64))
+}
+
+// encode_big encodes a 256 bit integer as a hex quantity.
+pub fn encode_big(i: &Uint256) -> String {
+    let hex = encode(&i.to_bytes32());
+    let digits = hex[2..].trim_start_matches('0');
+    if digits.is_empty() {
+        "0x0".to_string()
+    } else {
+        format!("0x{}", digits)
+    }
+}
+
+// decode_big decodes a hex quantity of at most 256 bits.
+pub fn decode_big(input: &str) -> Result<Uint256, HexError> {
+    let raw = check_number(input)?;
+    if raw.len() > 64 {
+        return Err(HexError::TooLong);
+    }
+    let padded = format!("0x{:0>64}", raw);
+    Ok(Uint256::from_bytes(&decode(&padded)?))
+}
+
+macro_rules! serde_hex_module {
+    ($name:ident, $ty:ty, $encode:expr, $decode:expr) => {
+        pub mod $name {
+            use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+            #[allow(unused_imports)]
+            use super::*;
+
+            pub fn serialize<S: Serializer>(value: &$ty, serializer: S) -> Result<S::Ok, S::Error> {
+                serializer.serialize_str(&$encode(value))
+            }
+
+            pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<$ty, D::Error> {
+                let s = String::deserialize(deserializer)?;
+                $decode(&s).map_err(D::Error::custom)
+            }
+
+            // opt handles optional fields, which are omitted when None.
+            pub mod opt {
+                use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+                #[allow(unused_imports)]
+                use super::super::*;
+
+                pub fn serialize<S: Serializer>(value: &Option<$ty>, serializer: S) -> Result<S::Ok, S::Error> {
+                    match value {
+                        Some(value) => serializer.serialize_str(&$encode(value)),
+                        None => serializer.serialize_none(),
+                    }
+                }
+
+                pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<$ty>, D::Error> {
+                    match Option::<String>::deserialize(deserializer)? {
+                        Some(s) => $decode(&s).map(Some).map_err(D::Error::custom),
+                        None => Ok(None),
+                    }
+                }
+            }
+        }
+    };
+}
+
+serde_hex_module!(bytes, Vec<u8>, |v: &Vec<u8>| encode(v), decode);
+serde_hex_module!(quantity, u64, |v: &u64| encode_u64(*v), decode_u64);
+serde_hex_module!(big, uint256::Uint256, encode_big, decode_big);
+serde_hex_module!(
+    hash,
+    common::Hash,
+    |v: &common::Hash| encode(v.as_bytes()),
+    |s: &str| decode_fixed::<32>(s).map(common::Hash)
+);
+serde_hex_module!(
+    address,
+    common::Address,
+    |v: &common::Address| encode(&v.0),
+    |s: &str| decode_fixed::<20>(s).map(common::Address)
+);
+
+// bytes_list encodes a list of byte strings as a JSON array of hex strings.
+pub mod bytes_list {
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(value: &[Vec<u8>], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(value.iter().map(|b| super::encode(b)))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<Vec<u8>>, D::Error> {
+        Vec::<String>::deserialize(deserializer)?
+            .iter()
+            .map(|s| super::decode(s).map_err(D::Error::custom))
+            .collect()
+    }
+}
+
+// hashes encodes a list of hashes as a JSON array of hex strings.
+pub mod hashes {
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(value: &[common::Hash], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(value.iter().map(|hash| super::encode(hash.as_bytes())))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<common::Hash>, D::Error> {
+        Vec::<String>::deserialize(deserializer)?
+            .iter()
+            .map(|s| super::decode_fixed::<32>(s).map(common::Hash).map_err(D::Error::custom))
+            .collect()
+    }
+}
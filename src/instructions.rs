@@ -1,1602 +1,1304 @@
-mod interpreter;
+use crate::contract::ContractRef;
+use crate::crypto;
+use crate::errors::Error;
+use crate::interpreter::{EVMInterpreter, ScopeContext};
+use crate::params;
+use crate::types;
+use uint256::Uint256;
 
 pub fn op_add(
-    pc: &mut u64,
-    interpreter: &mut interpreter::EVMInterpreter,
-    scope: &mut interpreter::ScopeContext,
-) -> Result<Vec<u8>, Error> {
-    let x = match scope.stack.pop() {
-        Some(value) => value,
-        None => return Err(Error::StackUnderflow),
-    };
-
-    let y = match scope.stack.peek() {
-        Some(value) => value,
-        None => return Err(Error::StackUnderflow),
-    };
-
-    if let Some(result) = y.checked_add(&x) {
-        *y = result;
-        Ok(Vec::new())
-    } else {
-        Err(Error::Overflow)
-    }
+    _pc: &mut u64,
+    _interpreter: &mut EVMInterpreter,
+    scope: &mut ScopeContext,
+) -> Result<(), Error> {
+    let x = scope.stack.pop();
+    let y = scope.stack.peek();
+    *y = x.wrapping_add(y);
+    Ok(())
 }
 
 pub fn op_sub(
-    pc: &mut u64,
-    interpreter: &mut interpreter::EVMInterpreter,
-    scope: &mut interpreter::ScopeContext,
-) -> Result<Vec<u8>, Error> {
-    let x = match scope.stack.pop() {
-        Some(value) => value,
-        None => return Err(Error::StackUnderflow),
-    };
-
-    let y = match scope.stack.peek() {
-        Some(value) => value,
-        None => return Err(Error::StackUnderflow),
-    };
-
-    if let (Some(y_value), Some(x_value)) = (y, x) {
-        *y = y_value.sub(x_value);
-        Ok(Vec::new())
-    } else {
-        Err(Error::MissingOperand)
-    }
+    _pc: &mut u64,
+    _interpreter: &mut EVMInterpreter,
+    scope: &mut ScopeContext,
+) -> Result<(), Error> {
+    let x = scope.stack.pop();
+    let y = scope.stack.peek();
+    *y = x.wrapping_sub(y);
+    Ok(())
 }
 
 pub fn op_mul(
-    pc: &mut u64,
-    interpreter: &mut interpreter::EVMInterpreter,
-    scope: &mut interpreter::ScopeContext,
-) -> Result<Vec<u8>, Error> {
-    let x = match scope.stack.pop() {
-        Some(value) => value,
-        None => return Err(Error::StackUnderflow),
-    };
-
-    let y = match scope.stack.peek() {
-        Some(value) => value,
-        None => return Err(Error::StackUnderflow),
-    };
-
-    if let (Some(y_value), Some(x_value)) = (y, x) {
-        *y = y_value.mul(x_value);
-        Ok(Vec::new())
-    } else {
-        Err(Error::MissingOperand)
-    }
+    _pc: &mut u64,
+    _interpreter: &mut EVMInterpreter,
+    scope: &mut ScopeContext,
+) -> Result<(), Error> {
+    let x = scope.stack.pop();
+    let y = scope.stack.peek();
+    *y = x.wrapping_mul(y);
+    Ok(())
 }
 
 pub fn op_div(
-    pc: &mut u64,
-    interpreter: &mut interpreter::EVMInterpreter,
-    scope: &mut interpreter::ScopeContext,
-) -> Result<Vec<u8>, Error> {
-    let x = match scope.stack.pop() {
-        Some(value) => value,
-        None => return Err(Error::StackUnderflow),
-    };
-
-    let y = match scope.stack.peek() {
-        Some(value) => value,
-        None => return Err(Error::StackUnderflow),
-    };
-
-    if let (Some(y_value), Some(x_value)) = (y, x) {
-        if x_value.is_zero() {
-            return Err(Error::DivisionByZero);
-        }
+    _pc: &mut u64,
+    _interpreter: &mut EVMInterpreter,
+    scope: &mut ScopeContext,
+) -> Result<(), Error> {
+    let x = scope.stack.pop();
+    let y = scope.stack.peek();
+    *y = x.udiv(y);
+    Ok(())
+}
 
-        *y = y_value.div(x_value);
-        Ok(Vec::new())
-    } else {
-        Err(Error::MissingOperand)
-    }
+pub fn op_sdiv(
+    _pc: &mut u64,
+    _interpreter: &mut EVMInterpreter,
+    scope: &mut ScopeContext,
+) -> Result<(), Error> {
+    let x = scope.stack.pop();
+    let y = scope.stack.peek();
+    *y = x.sdiv(y);
+    Ok(())
 }
 
 pub fn op_mod(
-    pc: &mut u64,
-    interpreter: &mut interpreter::EVMInterpreter,
-    scope: &mut interpreter::ScopeContext,
-) -> Result<Vec<u8>, Error> {
-    let x = match scope.stack.pop() {
-        Some(value) => value,
-        None => return Err(Error::StackUnderflow),
-    };
-
-    let y = match scope.stack.peek() {
-        Some(value) => value,
-        None => return Err(Error::StackUnderflow),
-    };
-
-    if let (Some(y_value), Some(x_value)) = (y, x) {
-        *y = y_value.modulo(x_value);
-        Ok(Vec::new())
-    } else {
-        Err(Error::MissingOperand)
-    }
+    _pc: &mut u64,
+    _interpreter: &mut EVMInterpreter,
+    scope: &mut ScopeContext,
+) -> Result<(), Error> {
+    let x = scope.stack.pop();
+    let y = scope.stack.peek();
+    *y = x.umod(y);
+    Ok(())
 }
 
 pub fn op_smod(
-    pc: &mut u64,
-    interpreter: &mut interpreter::EVMInterpreter,
-    scope: &mut interpreter::ScopeContext,
-) -> Result<Vec<u8>, Error> {
-    let x = match scope.stack.pop() {
-        Some(value) => value,
-        None => return Err(Error::StackUnderflow),
-    };
-
-    let y = match scope.stack.peek() {
-        Some(value) => value,
-        None => return Err(Error::StackUnderflow),
-    };
-
-    if let (Some(y_value), Some(x_value)) = (y, x) {
-        *y = y_value.signed_modulo(x_value);
-        Ok(Vec::new())
-    } else {
-        Err(Error::MissingOperand)
-    }
+    _pc: &mut u64,
+    _interpreter: &mut EVMInterpreter,
+    scope: &mut ScopeContext,
+) -> Result<(), Error> {
+    let x = scope.stack.pop();
+    let y = scope.stack.peek();
+    *y = x.smod(y);
+    Ok(())
 }
 
 pub fn op_exp(
-    pc: &mut u64,
-    interpreter: &mut interpreter::EVMInterpreter,
-    scope: &mut interpreter::ScopeContext,
-) -> Result<Vec<u8>, Error> {
-    let base = match scope.stack.pop() {
-        Some(value) => value,
-        None => return Err(Error::StackUnderflow),
-    };
-
-    let exponent = match scope.stack.peek() {
-        Some(value) => value,
-        None => return Err(Error::StackUnderflow),
-    };
-
-    if let (Some(exponent_value), Some(base_value)) = (exponent, base) {
-        *exponent = exponent_value.pow(base_value);
-        Ok(Vec::new())
-    } else {
-        Err(Error::MissingOperand)
-    }
+    _pc: &mut u64,
+    _interpreter: &mut EVMInterpreter,
+    scope: &mut ScopeContext,
+) -> Result<(), Error> {
+    let base = scope.stack.pop();
+    let exponent = scope.stack.peek();
+    *exponent = base.exp(exponent);
+    Ok(())
 }
 
 pub fn op_sign_extend(
-    pc: &mut u64,
-    interpreter: &mut interpreter::EVMInterpreter,
-    scope: &mut interpreter::ScopeContext,
-) -> Result<Vec<u8>, Error> {
-    let back = match scope.stack.pop() {
-        Some(value) => value,
-        None => return Err(Error::StackUnderflow),
-    };
-
-    let num = match scope.stack.peek() {
-        Some(value) => value,
-        None => return Err(Error::StackUnderflow),
-    };
-
-    if let (Some(num_value), Some(back_value)) = (num, back) {
-        num_value.extend_sign(&back_value);
-        Ok(Vec::new())
-    } else {
-        Err(Error::MissingOperand)
-    }
+    _pc: &mut u64,
+    _interpreter: &mut EVMInterpreter,
+    scope: &mut ScopeContext,
+) -> Result<(), Error> {
+    let back = scope.stack.pop();
+    let num = scope.stack.peek();
+    *num = num.sign_extend(&back);
+    Ok(())
 }
 
 pub fn op_not(
-    pc: &mut u64,
-    interpreter: &mut interpreter::EVMInterpreter,
-    scope: &mut interpreter::ScopeContext,
-) -> Result<Vec<u8>, Error> {
-    let x = match scope.stack.peek() {
-        Some(value) => value,
-        None => return Err(Error::StackUnderflow),
-    };
-
-    if let Some(x_value) = x {
-        x_value.not();
-        Ok(Vec::new())
-    } else {
-        Err(Error::MissingOperand)
-    }
+    _pc: &mut u64,
+    _interpreter: &mut EVMInterpreter,
+    scope: &mut ScopeContext,
+) -> Result<(), Error> {
+    let x = scope.stack.peek();
+    *x = x.not();
+    Ok(())
 }
 
 pub fn op_lt(
-    pc: &mut u64,
-    interpreter: &mut interpreter::EVMInterpreter,
-    scope: &mut interpreter::ScopeContext,
-) -> Result<Vec<u8>, Error> {
-    let x = match scope.stack.pop() {
-        Some(value) => value,
-        None => return Err(Error::StackUnderflow),
-    };
-
-    let y = match scope.stack.peek() {
-        Some(value) => value,
-        None => return Err(Error::StackUnderflow),
-    };
-
-    if let (Some(x_value), Some(y_value)) = (x, y) {
-        if x_value.lt(&y_value) {
-            y_value.set_one();
-        } else {
-            y_value.clear();
-        }
-        Ok(Vec::new())
-    } else {
-        Err(Error::MissingOperand)
-    }
+    _pc: &mut u64,
+    _interpreter: &mut EVMInterpreter,
+    scope: &mut ScopeContext,
+) -> Result<(), Error> {
+    let x = scope.stack.pop();
+    let y = scope.stack.peek();
+    *y = bool_to_uint(x < *y);
+    Ok(())
 }
 
 pub fn op_gt(
-    pc: &mut u64,
-    interpreter: &mut interpreter::EVMInterpreter,
-    scope: &mut interpreter::ScopeContext,
-) -> Result<Vec<u8>, Error> {
-    let x = match scope.stack.pop() {
-        Some(value) => value,
-        None => return Err(Error::StackUnderflow),
-    };
-
-    let y = match scope.stack.peek() {
-        Some(value) => value,
-        None => return Err(Error::StackUnderflow),
-    };
-
-    if let (Some(x_value), Some(y_value)) = (x, y) {
-        if x_value.gt(&y_value) {
-            y_value.set_one();
-        } else {
-            y_value.clear();
-        }
-        Ok(Vec::new())
-    } else {
-        Err(Error::MissingOperand)
-    }
+    _pc: &mut u64,
+    _interpreter: &mut EVMInterpreter,
+    scope: &mut ScopeContext,
+) -> Result<(), Error> {
+    let x = scope.stack.pop();
+    let y = scope.stack.peek();
+    *y = bool_to_uint(x > *y);
+    Ok(())
 }
 
 pub fn op_slt(
-    pc: &mut u64,
-    interpreter: &mut interpreter::EVMInterpreter,
-    scope: &mut interpreter::ScopeContext,
-) -> Result<Vec<u8>, Error> {
-    let x = match scope.stack.pop() {
-        Some(value) => value,
-        None => return Err(Error::StackUnderflow),
-    };
-
-    let y = match scope.stack.peek() {
-        Some(value) => value,
-        None => return Err(Error::StackUnderflow),
-    };
-
-    if let (Some(x_value), Some(y_value)) = (x, y) {
-        if x_value.slt(&y_value) {
-            y_value.set_one();
-        } else {
-            y_value.clear();
-        }
-        Ok(Vec::new())
-    } else {
-        Err(Error::MissingOperand)
-    }
+    _pc: &mut u64,
+    _interpreter: &mut EVMInterpreter,
+    scope: &mut ScopeContext,
+) -> Result<(), Error> {
+    let x = scope.stack.pop();
+    let y = scope.stack.peek();
+    *y = bool_to_uint(x.slt(y));
+    Ok(())
 }
 
 pub fn op_sgt(
-    pc: &mut u64,
-    interpreter: &mut interpreter::EVMInterpreter,
-    scope: &mut interpreter::ScopeContext,
-) -> Result<Vec<u8>, Error> {
-    let x = match scope.stack.pop() {
-        Some(value) => value,
-        None => return Err(Error::StackUnderflow),
-    };
-
-    let y = match scope.stack.peek() {
-        Some(value) => value,
-        None => return Err(Error::StackUnderflow),
-    };
-
-    if let (Some(x_value), Some(y_value)) = (x, y) {
-        if x_value.sgt(&y_value) {
-            y_value.set_one();
-        } else {
-            y_value.clear();
-        }
-        Ok(Vec::new())
-    } else {
-        Err(Error::MissingOperand)
-    }
+    _pc: &mut u64,
+    _interpreter: &mut EVMInterpreter,
+    scope: &mut ScopeContext,
+) -> Result<(), Error> {
+    let x = scope.stack.pop();
+    let y = scope.stack.peek();
+    *y = bool_to_uint(x.sgt(y));
+    Ok(())
 }
 
 pub fn op_eq(
-    pc: &mut u64,
-    interpreter: &mut interpreter::EVMInterpreter,
-    scope: &mut interpreter::ScopeContext,
-) -> Result<Vec<u8>, Error> {
-    let x = match scope.stack.pop() {
-        Some(value) => value,
-        None => return Err(Error::StackUnderflow),
-    };
-
-    let y = match scope.stack.peek() {
-        Some(value) => value,
-        None => return Err(Error::StackUnderflow),
-    };
-
-    if let (Some(x_value), Some(y_value)) = (x, y) {
-        if x_value.eq(y_value) {
-            y_value.set_one();
-        } else {
-            y_value.clear();
-        }
-        Ok(Vec::new())
-    } else {
-        Err(Error::MissingOperand)
-    }
+    _pc: &mut u64,
+    _interpreter: &mut EVMInterpreter,
+    scope: &mut ScopeContext,
+) -> Result<(), Error> {
+    let x = scope.stack.pop();
+    let y = scope.stack.peek();
+    *y = bool_to_uint(x == *y);
+    Ok(())
 }
 
 pub fn op_iszero(
-    pc: &mut u64,
-    interpreter: &mut interpreter::EVMInterpreter,
-    scope: &mut interpreter::ScopeContext,
-) -> Result<Vec<u8>, Error> {
-    let x = match scope.stack.peek() {
-        Some(value) => value,
-        None => return Err(Error::StackUnderflow),
-    };
-
-    if let Some(x_value) = x {
-        if x_value.is_zero() {
-            x_value.set_one();
-        } else {
-            x_value.clear();
-        }
-        Ok(Vec::new())
-    } else {
-        Err(Error::MissingOperand)
-    }
+    _pc: &mut u64,
+    _interpreter: &mut EVMInterpreter,
+    scope: &mut ScopeContext,
+) -> Result<(), Error> {
+    let x = scope.stack.peek();
+    *x = bool_to_uint(x.is_zero());
+    Ok(())
 }
 
 pub fn op_and(
-    pc: &mut u64,
-    interpreter: &mut interpreter::EVMInterpreter,
-    scope: &mut interpreter::ScopeContext,
-) -> Result<Vec<u8>, Error> {
-    let x = match scope.stack.pop() {
-        Some(value) => value,
-        None => return Err(Error::StackUnderflow),
-    };
-
-    let y = match scope.stack.peek() {
-        Some(value) => value,
-        None => return Err(Error::StackUnderflow),
-    };
-
-    if let (Some(x_value), Some(y_value)) = (x, y) {
-        y_value.and(&x_value, y_value);
-        Ok(Vec::new())
-    } else {
-        Err(Error::MissingOperand)
-    }
+    _pc: &mut u64,
+    _interpreter: &mut EVMInterpreter,
+    scope: &mut ScopeContext,
+) -> Result<(), Error> {
+    let x = scope.stack.pop();
+    let y = scope.stack.peek();
+    *y = x.and(y);
+    Ok(())
 }
 
 pub fn op_or(
-    pc: &mut u64,
-    interpreter: &mut interpreter::EVMInterpreter,
-    scope: &mut interpreter::ScopeContext,
-) -> Result<Vec<u8>, Error> {
-    let x = match scope.stack.pop() {
-        Some(value) => value,
-        None => return Err(Error::StackUnderflow),
-    };
-
-    let y = match scope.stack.peek() {
-        Some(value) => value,
-        None => return Err(Error::StackUnderflow),
-    };
-
-    if let (Some(x_value), Some(y_value)) = (x, y) {
-        y_value.or(&x_value, y_value);
-        Ok(Vec::new())
-    } else {
-        Err(Error::MissingOperand)
-    }
+    _pc: &mut u64,
+    _interpreter: &mut EVMInterpreter,
+    scope: &mut ScopeContext,
+) -> Result<(), Error> {
+    let x = scope.stack.pop();
+    let y = scope.stack.peek();
+    *y = x.or(y);
+    Ok(())
 }
 
 pub fn op_xor(
-    pc: &mut u64,
-    interpreter: &mut interpreter::EVMInterpreter,
-    scope: &mut interpreter::ScopeContext,
-) -> Result<Vec<u8>, Error> {
-    let x = match scope.stack.pop() {
-        Some(value) => value,
-        None => return Err(Error::StackUnderflow),
-    };
-
-    let y = match scope.stack.peek() {
-        Some(value) => value,
-        None => return Err(Error::StackUnderflow),
-    };
-
-    if let (Some(x_value), Some(y_value)) = (x, y) {
-        y_value.xor(&x_value, y_value);
-        Ok(Vec::new())
-    } else {
-        Err(Error::MissingOperand)
-    }
+    _pc: &mut u64,
+    _interpreter: &mut EVMInterpreter,
+    scope: &mut ScopeContext,
+) -> Result<(), Error> {
+    let x = scope.stack.pop();
+    let y = scope.stack.peek();
+    *y = x.xor(y);
+    Ok(())
 }
 
 pub fn op_byte(
-    pc: &mut u64,
-    interpreter: &mut interpreter::EVMInterpreter,
-    scope: &mut interpreter::ScopeContext,
-) -> Result<Vec<u8>, Error> {
-    let th = match scope.stack.pop() {
-        Some(value) => value,
-        None => return Err(Error::StackUnderflow),
-    };
-
-    let val = match scope.stack.peek() {
-        Some(value) => value,
-        None => return Err(Error::StackUnderflow),
-    };
-
-    if let (Some(th_value), Some(val_value)) = (th, val) {
-        val_value.byte(&th_value);
-        Ok(Vec::new())
-    } else {
-        Err(Error::MissingOperand)
-    }
+    _pc: &mut u64,
+    _interpreter: &mut EVMInterpreter,
+    scope: &mut ScopeContext,
+) -> Result<(), Error> {
+    let th = scope.stack.pop();
+    let val = scope.stack.peek();
+    *val = val.byte(&th);
+    Ok(())
 }
 
 pub fn op_addmod(
-    pc: &mut u64,
-    interpreter: &mut interpreter::EVMInterpreter,
-    scope: &mut interpreter::ScopeContext,
-) -> Result<Vec<u8>, Error> {
-    let x = match scope.stack.pop() {
-        Some(value) => value,
-        None => return Err(Error::StackUnderflow),
-    };
-
-    let y = match scope.stack.pop() {
-        Some(value) => value,
-        None => return Err(Error::StackUnderflow),
-    };
-
-    let z = match scope.stack.peek() {
-        Some(value) => value,
-        None => return Err(Error::StackUnderflow),
-    };
-
-    if let (Some(x_value), Some(y_value), Some(z_value)) = (x, y, z) {
-        if z_value.is_zero() {
-            z_value.clear();
-        } else {
-            z_value.add_mod(&x_value, &y_value, z_value);
-        }
-        Ok(Vec::new())
-    } else {
-        Err(Error::MissingOperand)
-    }
+    _pc: &mut u64,
+    _interpreter: &mut EVMInterpreter,
+    scope: &mut ScopeContext,
+) -> Result<(), Error> {
+    let x = scope.stack.pop();
+    let y = scope.stack.pop();
+    let z = scope.stack.peek();
+    *z = x.add_mod(&y, z);
+    Ok(())
 }
 
 pub fn op_mulmod(
-    pc: &mut u64,
-    interpreter: &mut interpreter::EVMInterpreter,
-    scope: &mut interpreter::ScopeContext,
-) -> Result<Vec<u8>, Error> {
-    let x = match scope.stack.pop() {
-        Some(value) => value,
-        None => return Err(Error::StackUnderflow),
-    };
-
-    let y = match scope.stack.pop() {
-        Some(value) => value,
-        None => return Err(Error::StackUnderflow),
-    };
-
-    let z = match scope.stack.peek() {
-        Some(value) => value,
-        None => return Err(Error::StackUnderflow),
-    };
-
-    if let (Some(x_value), Some(y_value), Some(z_value)) = (x, y, z) {
-        z_value.mul_mod(&x_value, &y_value, z_value);
-        Ok(Vec::new())
-    } else {
-        Err(Error::MissingOperand)
-    }
+    _pc: &mut u64,
+    _interpreter: &mut EVMInterpreter,
+    scope: &mut ScopeContext,
+) -> Result<(), Error> {
+    let x = scope.stack.pop();
+    let y = scope.stack.pop();
+    let z = scope.stack.peek();
+    *z = x.mul_mod(&y, z);
+    Ok(())
 }
 
+// op_shl implements Shift Left
+// The SHL instruction (shift left) pops 2 values from the stack, first arg1 and then arg2,
+// and pushes on the stack arg2 shifted to the left by arg1 number of bits.
 pub fn op_shl(
-    pc: &mut u64,
-    interpreter: &mut interpreter::EVMInterpreter,
-    scope: &mut interpreter::ScopeContext,
-) -> Result<Vec<u8>, Error> {
-    let shift = match scope.stack.pop() {
-        Some(value) => value,
-        None => return Err(Error::StackUnderflow),
-    };
-
-    let value = match scope.stack.peek_mut() {
-        Some(value) => value,
-        None => return Err(Error::StackUnderflow),
-    };
-
-    if let Some(shift_value) = shift {
-        if shift_value.lt_uint64(256) {
-            value.lsh(value, shift_value.uint64() as usize);
-        } else {
-            value.clear();
-        }
-        Ok(Vec::new())
-    } else {
-        Err(Error::MissingOperand)
-    }
+    _pc: &mut u64,
+    _interpreter: &mut EVMInterpreter,
+    scope: &mut ScopeContext,
+) -> Result<(), Error> {
+    // Note, second operand is left in the stack; accumulate result into it, and no need to push it afterwards
+    let shift = scope.stack.pop();
+    let value = scope.stack.peek();
+    *value = value.lsh(shift.shift_amount());
+    Ok(())
 }
 
+// op_shr implements Logical Shift Right
+// The SHR instruction (logical shift right) pops 2 values from the stack, first arg1 and then arg2,
+// and pushes on the stack arg2 shifted to the right by arg1 number of bits with zero fill.
 pub fn op_shr(
-    pc: &mut u64,
-    interpreter: &mut interpreter::EVMInterpreter,
-    scope: &mut interpreter::ScopeContext,
-) -> Result<Vec<u8>, Error> {
-    let shift = match scope.stack.pop() {
-        Some(value) => value,
-        None => return Err(Error::StackUnderflow),
-    };
-
-    let value = match scope.stack.peek_mut() {
-        Some(value) => value,
-        None => return Err(Error::StackUnderflow),
-    };
-
-    if let Some(shift_value) = shift {
-        if shift_value.lt_uint64(256) {
-            value.rsh(value, shift_value.uint64() as usize);
-        } else {
-            value.clear();
-        }
-        Ok(Vec::new())
-    } else {
-        Err(Error::MissingOperand)
-    }
+    _pc: &mut u64,
+    _interpreter: &mut EVMInterpreter,
+    scope: &mut ScopeContext,
+) -> Result<(), Error> {
+    // Note, second operand is left in the stack; accumulate result into it, and no need to push it afterwards
+    let shift = scope.stack.pop();
+    let value = scope.stack.peek();
+    *value = value.rsh(shift.shift_amount());
+    Ok(())
 }
 
+// op_sar implements Arithmetic Shift Right
+// The SAR instruction (arithmetic shift right) pops 2 values from the stack, first arg1 and then arg2,
+// and pushes on the stack arg2 shifted to the right by arg1 number of bits with sign extension.
 pub fn op_sar(
-    pc: &mut u64,
-    interpreter: &mut interpreter::EVMInterpreter,
-    scope: &mut interpreter::ScopeContext,
-) -> Result<Vec<u8>, Error> {
-    let shift = match scope.stack.pop() {
-        Some(value) => value,
-        None => return Err(Error::StackUnderflow),
-    };
-
-    let value = match scope.stack.peek_mut() {
-        Some(value) => value,
-        None => return Err(Error::StackUnderflow),
-    };
-
-    if let Some(shift_value) = shift {
-        if shift_value.gt_uint64(256) {
-            if value.sign() >= 0 {
-                value.clear();
-            } else {
-                value.set_all_one();
-            }
-        } else {
-            let n = shift_value.uint64() as usize;
-            value.srsh(value, n);
-        }
-        Ok(Vec::new())
-    } else {
-        Err(Error::MissingOperand)
-    }
+    _pc: &mut u64,
+    _interpreter: &mut EVMInterpreter,
+    scope: &mut ScopeContext,
+) -> Result<(), Error> {
+    let shift = scope.stack.pop();
+    let value = scope.stack.peek();
+    *value = value.srsh(shift.shift_amount());
+    Ok(())
 }
 
 pub fn op_keccak256(
-    pc: &mut u64,
-    interpreter: &mut interpreter::EVMInterpreter,
-    scope: &mut interpreter::ScopeContext,
-) -> Result<Vec<u8>, Error> {
-    let offset = match scope.stack.pop() {
-        Some(value) => value,
-        None => return Err(Error::StackUnderflow),
-    };
-
-    let size = match scope.stack.peek_mut() {
-        Some(value) => value,
-        None => return Err(Error::StackUnderflow),
-    };
-
-    let data = scope
-        .memory
-        .get_ptr(offset.uint64() as i64, size.uint64() as i64);
-
-    if interpreter.hasher.is_none() {
-        interpreter.hasher = Some(crypto::new_keccak_state());
-    } else {
-        interpreter.hasher.as_mut().unwrap().reset();
-    }
-
-    interpreter.hasher.as_mut().unwrap().write(data);
-    interpreter
-        .hasher
-        .as_mut()
-        .unwrap()
-        .read(&mut interpreter.hasher_buf);
-
-    let evm = interpreter.evm;
-    if evm.config.enable_preimage_recording {
-        evm.state_db.add_preimage(&interpreter.hasher_buf, data);
+    _pc: &mut u64,
+    interpreter: &mut EVMInterpreter,
+    scope: &mut ScopeContext,
+) -> Result<(), Error> {
+    let offset = scope.stack.pop();
+    let size = scope.stack.peek();
+    let data = scope.memory.get_ptr(offset.low_u64(), size.low_u64());
+    let hash = crypto::keccak256_hash(data);
+
+    if interpreter.evm.config.enable_preimage_recording {
+        interpreter.evm.state_db.add_preimage(&hash, data);
     }
-
-    size.set_bytes(&interpreter.hasher_buf);
-    Ok(Vec::new())
+    *size = Uint256::from_bytes(hash.as_bytes());
+    Ok(())
 }
 
 pub fn op_address(
-    pc: &mut u64,
-    interpreter: &mut interpreter::EVMInterpreter,
-    scope: &mut interpreter::ScopeContext,
-) -> Result<Vec<u8>, Error> {
-    let address = Address::from(scope.contract.address().bytes());
-    scope.stack.push(Uint256::from_bytes(&address));
-    Ok(Vec::new())
+    _pc: &mut u64,
+    _interpreter: &mut EVMInterpreter,
+    scope: &mut ScopeContext,
+) -> Result<(), Error> {
+    scope
+        .stack
+        .push(Uint256::from_bytes(scope.contract.address().as_bytes()));
+    Ok(())
 }
 
 pub fn op_balance(
-    pc: &mut u64,
-    interpreter: &mut interpreter::EVMInterpreter,
-    scope: &mut interpreter::ScopeContext,
-) -> Result<Vec<u8>, Error> {
-    let slot = match scope.stack.peek() {
-        Some(value) => value,
-        None => return Err(Error::StackUnderflow),
-    };
-
-    let address = Address::from(slot.bytes20());
-    let balance = interpreter.evm.state_db.get_balance(&address);
-    slot.set_from_big(&balance);
-    Ok(Vec::new())
+    _pc: &mut u64,
+    interpreter: &mut EVMInterpreter,
+    scope: &mut ScopeContext,
+) -> Result<(), Error> {
+    let slot = scope.stack.peek();
+    let address = common::Address(slot.bytes20());
+    *slot = interpreter.evm.state_db.get_balance(&address);
+    Ok(())
 }
 
 pub fn op_origin(
-    pc: &mut u64,
-    interpreter: &mut interpreter::EVMInterpreter,
-    scope: &mut interpreter::ScopeContext,
-) -> Result<Vec<u8>, Error> {
-    let origin = interpreter.evm.origin().bytes();
-    scope.stack.push(Uint256::from_bytes(&origin));
-    Ok(Vec::new())
+    _pc: &mut u64,
+    interpreter: &mut EVMInterpreter,
+    scope: &mut ScopeContext,
+) -> Result<(), Error> {
+    scope
+        .stack
+        .push(Uint256::from_bytes(interpreter.evm.origin().as_bytes()));
+    Ok(())
 }
 
 pub fn op_caller(
-    pc: &mut u64,
-    interpreter: &mut interpreter::EVMInterpreter,
-    scope: &mut interpreter::ScopeContext,
-) -> Result<Vec<u8>, Error> {
-    let caller = Address::from(scope.contract.caller().bytes());
-    scope.stack.push(Uint256::from_bytes(&caller));
-    Ok(Vec::new())
+    _pc: &mut u64,
+    _interpreter: &mut EVMInterpreter,
+    scope: &mut ScopeContext,
+) -> Result<(), Error> {
+    scope
+        .stack
+        .push(Uint256::from_bytes(scope.contract.caller().as_bytes()));
+    Ok(())
 }
 
 pub fn op_call_value(
-    pc: &mut u64,
-    interpreter: &mut interpreter::EVMInterpreter,
-    scope: &mut interpreter::ScopeContext,
-) -> Result<Vec<u8>, Error> {
-    let value = match uint256::FromBig(scope.contract.value()) {
-        Some(v) => v,
-        None => return Err(Error::ConversionError),
-    };
-    scope.stack.push(value);
-    Ok(Vec::new())
+    _pc: &mut u64,
+    _interpreter: &mut EVMInterpreter,
+    scope: &mut ScopeContext,
+) -> Result<(), Error> {
+    scope.stack.push(scope.contract.value().clone());
+    Ok(())
 }
 
 pub fn op_call_data_load(
-    pc: &mut u64,
-    interpreter: &mut interpreter::EVMInterpreter,
-    scope: &mut interpreter::ScopeContext,
-) -> Result<Vec<u8>, Error> {
-    let x = match scope.stack.peek() {
-        Some(value) => value,
-        None => return Err(Error::StackUnderflow),
-    };
-
-    if let Some(offset) = x.uint64() {
+    _pc: &mut u64,
+    _interpreter: &mut EVMInterpreter,
+    scope: &mut ScopeContext,
+) -> Result<(), Error> {
+    let x = scope.stack.peek();
+    let (offset, overflow) = x.uint64_with_overflow();
+    if !overflow {
         let data = get_data(&scope.contract.input, offset, 32);
-        x.set_bytes(&data);
+        *x = Uint256::from_bytes(&data);
     } else {
-        x.clear();
+        *x = Uint256::zero();
     }
-
-    Ok(Vec::new())
+    Ok(())
 }
 
 pub fn op_call_data_size(
-    pc: &mut u64,
-    interpreter: &mut interpreter::EVMInterpreter,
-    scope: &mut interpreter::ScopeContext,
-) -> Result<Vec<u8>, Error> {
-    let size = Uint256::from_uint64(scope.contract.input.len() as u64);
-    scope.stack.push(size);
-
-    Ok(Vec::new())
+    _pc: &mut u64,
+    _interpreter: &mut EVMInterpreter,
+    scope: &mut ScopeContext,
+) -> Result<(), Error> {
+    scope
+        .stack
+        .push(Uint256::from_uint64(scope.contract.input.len() as u64));
+    Ok(())
 }
 
 pub fn op_call_data_copy(
-    pc: &mut u64,
-    interpreter: &mut interpreter::EVMInterpreter,
-    scope: &mut interpreter::ScopeContext,
-) -> Result<Vec<u8>, Error> {
-    let mem_offset = match scope.stack.pop() {
-        Some(value) => value,
-        None => return Err(Error::StackUnderflow),
-    };
-    let data_offset = match scope.stack.pop() {
-        Some(value) => value,
-        None => return Err(Error::StackUnderflow),
-    };
-    let length = match scope.stack.pop() {
-        Some(value) => value,
-        None => return Err(Error::StackUnderflow),
-    };
-
-    let data_offset64 = data_offset.uint64().unwrap_or(u64::MAX);
-    let mem_offset64 = mem_offset.uint64().unwrap_or(0);
-    let length64 = length.uint64().unwrap_or(0);
-    scope.memory.set(
-        mem_offset64,
-        length64,
-        &get_data(&scope.contract.input, data_offset64, length64),
-    );
-
-    Ok(Vec::new())
+    _pc: &mut u64,
+    _interpreter: &mut EVMInterpreter,
+    scope: &mut ScopeContext,
+) -> Result<(), Error> {
+    let mem_offset = scope.stack.pop();
+    let data_offset = scope.stack.pop();
+    let length = scope.stack.pop();
+    let (data_offset64, overflow) = data_offset.uint64_with_overflow();
+    let data_offset64 = if overflow { u64::MAX } else { data_offset64 };
+    // These values are checked for overflow during gas cost calculation
+    let mem_offset64 = mem_offset.low_u64();
+    let length64 = length.low_u64();
+    let data = get_data(&scope.contract.input, data_offset64, length64);
+    scope.memory.set(mem_offset64, length64, &data);
+    Ok(())
 }
 
 pub fn op_return_data_size(
-    pc: &mut u64,
-    interpreter: &mut interpreter::EVMInterpreter,
-    scope: &mut interpreter::ScopeContext,
-) -> Result<Vec<u8>, Error> {
-    let size = Uint256::from_uint64(interpreter.return_data.len() as u64);
-    scope.stack.push(size);
-
-    Ok(Vec::new())
+    _pc: &mut u64,
+    interpreter: &mut EVMInterpreter,
+    scope: &mut ScopeContext,
+) -> Result<(), Error> {
+    scope
+        .stack
+        .push(Uint256::from_uint64(interpreter.return_data.len() as u64));
+    Ok(())
 }
 
 pub fn op_return_data_copy(
-    pc: &mut u64,
-    interpreter: &mut interpreter::EVMInterpreter,
-    scope: &mut interpreter::ScopeContext,
-) -> Result<Vec<u8>, Error> {
-    let mem_offset = match scope.stack.pop() {
-        Some(value) => value,
-        None => return Err(Error::StackUnderflow),
-    };
-    let data_offset = match scope.stack.pop() {
-        Some(value) => value,
-        None => return Err(Error::StackUnderflow),
-    };
-    let length = match scope.stack.pop() {
-        Some(value) => value,
-        None => return Err(Error::StackUnderflow),
-    };
-
-    let offset64 = match data_offset.uint64_with_overflow() {
-        Some((offset, _)) => offset,
-        None => return Err(ErrReturnDataOutOfBounds),
-    };
-    let end = data_offset.clone() + length.clone();
-    let end64 = match end.uint64_with_overflow() {
-        Some((end, _)) => end,
-        None => return Err(ErrReturnDataOutOfBounds),
-    };
+    _pc: &mut u64,
+    interpreter: &mut EVMInterpreter,
+    scope: &mut ScopeContext,
+) -> Result<(), Error> {
+    let mem_offset = scope.stack.pop();
+    let data_offset = scope.stack.pop();
+    let length = scope.stack.pop();
 
-    let return_data_len = interpreter.return_data.len();
-    if return_data_len < end64 as usize {
-        return Err(ErrReturnDataOutOfBounds);
+    let (offset64, overflow) = data_offset.uint64_with_overflow();
+    if overflow {
+        return Err(Error::ReturnDataOutOfBounds);
     }
-
+    let end64 = match offset64.checked_add(length.low_u64()) {
+        Some(end) if length.is_u64() && end <= interpreter.return_data.len() as u64 => end,
+        _ => return Err(Error::ReturnDataOutOfBounds),
+    };
     scope.memory.set(
-        mem_offset.uint64(),
-        length.uint64(),
+        mem_offset.low_u64(),
+        length.low_u64(),
         &interpreter.return_data[offset64 as usize..end64 as usize],
     );
-
-    Ok(Vec::new())
+    Ok(())
 }
 
 pub fn op_ext_code_size(
-    pc: &mut u64,
-    interpreter: &mut interpreter::EVMInterpreter,
-    scope: &mut interpreter::ScopeContext,
-) -> Result<Vec<u8>, Error> {
-    let slot = match scope.stack.peek() {
-        Some(value) => value,
-        None => return Err(Error::StackUnderflow),
-    };
-    let code_size =
-        Uint256::from_uint64(interpreter.evm.state_db.get_code_size(slot.bytes20()) as u64);
-    slot.set_from_big(&code_size);
-
-    Ok(Vec::new())
+    _pc: &mut u64,
+    interpreter: &mut EVMInterpreter,
+    scope: &mut ScopeContext,
+) -> Result<(), Error> {
+    let slot = scope.stack.peek();
+    let address = common::Address(slot.bytes20());
+    *slot = Uint256::from_uint64(interpreter.evm.state_db.get_code_size(&address) as u64);
+    Ok(())
 }
 
 pub fn op_code_size(
-    pc: &mut u64,
-    interpreter: &mut interpreter::EVMInterpreter,
-    scope: &mut interpreter::ScopeContext,
-) -> Result<Vec<u8>, Error> {
-    let code_size = Uint256::from_uint64(scope.contract.code.len() as u64);
-    scope.stack.push(code_size);
-
-    Ok(Vec::new())
+    _pc: &mut u64,
+    _interpreter: &mut EVMInterpreter,
+    scope: &mut ScopeContext,
+) -> Result<(), Error> {
+    scope
+        .stack
+        .push(Uint256::from_uint64(scope.contract.code.len() as u64));
+    Ok(())
 }
 
 pub fn op_code_copy(
-    pc: &mut u64,
-    interpreter: &mut interpreter::EVMInterpreter,
-    scope: &mut interpreter::ScopeContext,
-) -> Result<Vec<u8>, Error> {
-    let mem_offset = match scope.stack.pop() {
-        Some(value) => value,
-        None => return Err(Error::StackUnderflow),
-    };
-    let code_offset = match scope.stack.pop() {
-        Some(value) => value,
-        None => return Err(Error::StackUnderflow),
-    };
-    let length = match scope.stack.pop() {
-        Some(value) => value,
-        None => return Err(Error::StackUnderflow),
-    };
-
-    let uint64_code_offset = match code_offset.uint64_with_overflow() {
-        Some((offset, _)) => offset,
-        None => return Err(Error::Overflow),
-    };
-
-    let code_copy = get_data(&scope.contract.code, uint64_code_offset, length.uint64());
+    _pc: &mut u64,
+    _interpreter: &mut EVMInterpreter,
+    scope: &mut ScopeContext,
+) -> Result<(), Error> {
+    let mem_offset = scope.stack.pop();
+    let code_offset = scope.stack.pop();
+    let length = scope.stack.pop();
+    let (code_offset64, overflow) = code_offset.uint64_with_overflow();
+    let code_offset64 = if overflow { u64::MAX } else { code_offset64 };
+    let code_copy = get_data(&scope.contract.code, code_offset64, length.low_u64());
     scope
         .memory
-        .set(mem_offset.uint64(), length.uint64(), &code_copy);
-
-    Ok(Vec::new())
+        .set(mem_offset.low_u64(), length.low_u64(), &code_copy);
+    Ok(())
 }
 
 pub fn op_ext_code_copy(
-    pc: &mut u64,
-    interpreter: &mut interpreter::EVMInterpreter,
-    scope: &mut interpreter::ScopeContext,
-) -> Result<Vec<u8>, Error> {
-    let stack = &mut scope.stack;
-    let a = match stack.pop() {
-        Some(value) => value,
-        None => return Err(Error::StackUnderflow),
-    };
-    let mem_offset = match stack.pop() {
-        Some(value) => value,
-        None => return Err(Error::StackUnderflow),
-    };
-    let code_offset = match stack.pop() {
-        Some(value) => value,
-        None => return Err(Error::StackUnderflow),
-    };
-    let length = match stack.pop() {
-        Some(value) => value,
-        None => return Err(Error::StackUnderflow),
-    };
-
-    let uint64_code_offset = match code_offset.uint64_with_overflow() {
-        Some((offset, _)) => offset,
-        None => return Err(Error::Overflow),
-    };
-
-    let addr = common::Address::from_bytes20(a.bytes20());
-    let code_copy = get_data(
-        &interpreter.evm.state_db.get_code(&addr),
-        uint64_code_offset,
-        length.uint64(),
-    );
+    _pc: &mut u64,
+    interpreter: &mut EVMInterpreter,
+    scope: &mut ScopeContext,
+) -> Result<(), Error> {
+    let a = scope.stack.pop();
+    let mem_offset = scope.stack.pop();
+    let code_offset = scope.stack.pop();
+    let length = scope.stack.pop();
+    let (code_offset64, overflow) = code_offset.uint64_with_overflow();
+    let code_offset64 = if overflow { u64::MAX } else { code_offset64 };
+    let addr = common::Address(a.bytes20());
+    let code = interpreter.evm.state_db.get_code(&addr);
+    let code_copy = get_data(&code, code_offset64, length.low_u64());
     scope
         .memory
-        .set(mem_offset.uint64(), length.uint64(), &code_copy);
-
-    Ok(Vec::new())
-}
-
-fn get_data(data: &[u8], offset: u64, length: u64) -> Vec<u8> {
-    let start = offset as usize;
-    let end = (offset + length) as usize;
-
-    if start >= data.len() || end > data.len() {
-        Vec::new()
+        .set(mem_offset.low_u64(), length.low_u64(), &code_copy);
+    Ok(())
+}
+
+// op_ext_code_hash returns the code hash of a specified account.
+// There are several cases when the function is called, while we can relay everything
+// to `state.get_code_hash` function to ensure the correctness.
+//
+//  1. Caller tries to get the code hash of a normal contract account, state
+//     should return the relative code hash and set it as the result.
+//
+//  2. Caller tries to get the code hash of a non-existent account, state should
+//     return common::Hash::default() and zero will be set as the result.
+//
+//  3. Caller tries to get the code hash for an account without contract code, state
+//     should return EMPTY_CODE_HASH(0xc5d246...) as the result.
+//
+//  4. Caller tries to get the code hash of a precompiled account, the result should be
+//     zero or EMPTY_CODE_HASH.
+//
+// It is worth noting that in order to avoid unnecessary create and clean, all precompile
+// accounts on mainnet have been transferred 1 wei, so the return here should be
+// EMPTY_CODE_HASH. If the precompile account is not transferred any amount on a private
+// or customized chain, the return value will be zero.
+//
+//  5. Caller tries to get the code hash for an account which is marked as suicided
+//     in the current transaction, the code hash of this account should be returned.
+//
+//  6. Caller tries to get the code hash for an account which is marked as deleted, this
+//     account should be regarded as a non-existent account and zero should be returned.
+pub fn op_ext_code_hash(
+    _pc: &mut u64,
+    interpreter: &mut EVMInterpreter,
+    scope: &mut ScopeContext,
+) -> Result<(), Error> {
+    let slot = scope.stack.peek();
+    let address = common::Address(slot.bytes20());
+    if interpreter.evm.state_db.empty(&address) {
+        *slot = Uint256::zero();
     } else {
-        data[start..end].to_vec()
+        *slot = Uint256::from_bytes(interpreter.evm.state_db.get_code_hash(&address).as_bytes());
     }
+    Ok(())
 }
 
 pub fn op_gasprice(
-    pc: &mut u64,
-    interpreter: &mut interpreter::EVMInterpreter,
-    scope: &mut interpreter::ScopeContext,
-) -> Result<Vec<u8>, Error> {
-    let gas_price = interpreter.evm.gas_price.clone();
-    let v = uint256::Uint256::from_big(gas_price);
-    scope.stack.push(v);
-
-    Ok(Vec::new())
+    _pc: &mut u64,
+    interpreter: &mut EVMInterpreter,
+    scope: &mut ScopeContext,
+) -> Result<(), Error> {
+    scope.stack.push(interpreter.evm.gas_price().clone());
+    Ok(())
 }
 
 pub fn op_blockhash(
-    pc: &mut u64,
-    interpreter: &mut interpreter::EVMInterpreter,
-    scope: &mut interpreter::ScopeContext,
-) -> Result<Vec<u8>, Error> {
-    let num = match scope.stack.peek() {
-        Some(value) => value.clone(),
-        None => return Err(Error::StackUnderflow),
-    };
+    _pc: &mut u64,
+    interpreter: &mut EVMInterpreter,
+    scope: &mut ScopeContext,
+) -> Result<(), Error> {
+    let num = scope.stack.peek();
     let (num64, overflow) = num.uint64_with_overflow();
     if overflow {
-        num.clear();
-        return Ok(Vec::new());
+        *num = Uint256::zero();
+        return Ok(());
     }
-
-    let upper = interpreter.evm.context.block_number.uint64();
-    let (lower, hash) = if upper < 257 {
-        (0, uint256::Uint256::default())
-    } else {
-        (upper - 256, interpreter.evm.context.get_hash(upper))
-    };
-
+    let upper = interpreter.evm.context.block_number.low_u64();
+    let lower = if upper < 257 { 0 } else { upper - 256 };
     if num64 >= lower && num64 < upper {
-        num.set_bytes(hash.bytes());
+        *num = Uint256::from_bytes(interpreter.evm.context.get_hash(num64).as_bytes());
     } else {
-        num.clear();
+        *num = Uint256::zero();
     }
-
-    Ok(Vec::new())
+    Ok(())
 }
 
 pub fn op_coinbase(
-    pc: &mut u64,
-    interpreter: &mut interpreter::EVMInterpreter,
-    scope: &mut interpreter::ScopeContext,
-) -> Result<Vec<u8>, Error> {
-    let coinbase = interpreter.evm.context.coinbase.clone();
-    let v = uint256::Uint256::from_bytes(coinbase.bytes());
-    scope.stack.push(v);
-
-    Ok(Vec::new())
+    _pc: &mut u64,
+    interpreter: &mut EVMInterpreter,
+    scope: &mut ScopeContext,
+) -> Result<(), Error> {
+    scope.stack.push(Uint256::from_bytes(
+        interpreter.evm.context.coinbase.as_bytes(),
+    ));
+    Ok(())
 }
 
 pub fn op_timestamp(
-    pc: &mut u64,
-    interpreter: &mut interpreter::EVMInterpreter,
-    scope: &mut interpreter::ScopeContext,
-) -> Result<Vec<u8>, Error> {
-    let timestamp = interpreter.evm.context.time;
-    let v = uint256::Uint256::from_u64(timestamp);
-    scope.stack.push(v);
-
-    Ok(Vec::new())
+    _pc: &mut u64,
+    interpreter: &mut EVMInterpreter,
+    scope: &mut ScopeContext,
+) -> Result<(), Error> {
+    scope
+        .stack
+        .push(Uint256::from_uint64(interpreter.evm.context.time));
+    Ok(())
 }
 
 pub fn op_number(
-    pc: &mut u64,
-    interpreter: &mut interpreter::EVMInterpreter,
-    scope: &mut interpreter::ScopeContext,
-) -> Result<Vec<u8>, Error> {
-    let block_number = interpreter.evm.context.block_number.clone();
-    let v = uint256::Uint256::from_big(block_number);
-    scope.stack.push(v);
-
-    Ok(Vec::new())
+    _pc: &mut u64,
+    interpreter: &mut EVMInterpreter,
+    scope: &mut ScopeContext,
+) -> Result<(), Error> {
+    scope
+        .stack
+        .push(interpreter.evm.context.block_number.clone());
+    Ok(())
 }
 
 pub fn op_difficulty(
-    pc: &mut u64,
-    interpreter: &mut interpreter::EVMInterpreter,
-    scope: &mut interpreter::ScopeContext,
-) -> Result<Vec<u8>, Error> {
-    let difficulty = interpreter.evm.context.difficulty.clone();
-    let v = uint256::Uint256::from_big(difficulty);
-    scope.stack.push(v);
-
-    Ok(Vec::new())
+    _pc: &mut u64,
+    interpreter: &mut EVMInterpreter,
+    scope: &mut ScopeContext,
+) -> Result<(), Error> {
+    scope.stack.push(interpreter.evm.context.difficulty.clone());
+    Ok(())
 }
 
 pub fn op_random(
-    pc: &mut u64,
-    interpreter: &mut interpreter::EVMInterpreter,
-    scope: &mut interpreter::ScopeContext,
-) -> Result<Vec<u8>, Error> {
-    let random = interpreter.evm.context.random.clone();
-    let v = uint256::Uint256::from_bytes(random.bytes());
-    scope.stack.push(v);
-
-    Ok(Vec::new())
+    _pc: &mut u64,
+    interpreter: &mut EVMInterpreter,
+    scope: &mut ScopeContext,
+) -> Result<(), Error> {
+    let random = interpreter.evm.context.random.unwrap_or_default();
+    scope.stack.push(Uint256::from_bytes(random.as_bytes()));
+    Ok(())
 }
 
 pub fn op_gaslimit(
-    pc: &mut u64,
-    interpreter: &mut interpreter::EVMInterpreter,
-    scope: &mut interpreter::ScopeContext,
-) -> Result<Vec<u8>, Error> {
-    let gas_limit = interpreter.evm.context.gas_limit;
-    let v = uint256::Uint256::from_u64(gas_limit);
-    scope.stack.push(v);
-
-    Ok(Vec::new())
+    _pc: &mut u64,
+    interpreter: &mut EVMInterpreter,
+    scope: &mut ScopeContext,
+) -> Result<(), Error> {
+    scope
+        .stack
+        .push(Uint256::from_uint64(interpreter.evm.context.gas_limit));
+    Ok(())
 }
 
 pub fn op_pop(
-    pc: &mut u64,
-    interpreter: &mut interpreter::EVMInterpreter,
-    scope: &mut interpreter::ScopeContext,
-) -> Result<Vec<u8>, Error> {
+    _pc: &mut u64,
+    _interpreter: &mut EVMInterpreter,
+    scope: &mut ScopeContext,
+) -> Result<(), Error> {
     scope.stack.pop();
-
-    Ok(Vec::new())
+    Ok(())
 }
 
 pub fn op_mload(
-    pc: &mut u64,
-    interpreter: &mut interpreter::EVMInterpreter,
-    scope: &mut interpreter::ScopeContext,
-) -> Result<Vec<u8>, Error> {
+    _pc: &mut u64,
+    _interpreter: &mut EVMInterpreter,
+    scope: &mut ScopeContext,
+) -> Result<(), Error> {
     let v = scope.stack.peek();
-    let offset = v.to_u64();
-    let data = scope.memory.get(offset as i64, 32)?;
-    v.set_bytes(&data);
-
-    Ok(Vec::new())
+    let offset = v.low_u64();
+    *v = Uint256::from_bytes(scope.memory.get_ptr(offset, 32));
+    Ok(())
 }
 
 pub fn op_mstore(
-    pc: &mut u64,
-    interpreter: &mut interpreter::EVMInterpreter,
-    scope: &mut interpreter::ScopeContext,
-) -> Result<Vec<u8>, Error> {
-    let val = scope.stack.pop()?;
-    let mstart = scope.stack.pop()?;
-    scope.memory.set32(mstart.to_u64(), &val)?;
-
-    Ok(Vec::new())
+    _pc: &mut u64,
+    _interpreter: &mut EVMInterpreter,
+    scope: &mut ScopeContext,
+) -> Result<(), Error> {
+    // pop value of the stack
+    let m_start = scope.stack.pop();
+    let val = scope.stack.pop();
+    scope.memory.set32(m_start.low_u64(), &val);
+    Ok(())
 }
 
 pub fn op_mstore8(
-    pc: &mut u64,
-    interpreter: &mut interpreter::EVMInterpreter,
-    scope: &mut interpreter::ScopeContext,
-) -> Result<Vec<u8>, Error> {
-    let val = scope.stack.pop()?;
-    let off = scope.stack.pop()?;
-    scope.memory.store[off.to_u64() as usize] = val.to_u8() as u8;
-
-    Ok(Vec::new())
+    _pc: &mut u64,
+    _interpreter: &mut EVMInterpreter,
+    scope: &mut ScopeContext,
+) -> Result<(), Error> {
+    let off = scope.stack.pop();
+    let val = scope.stack.pop();
+    scope.memory.set(off.low_u64(), 1, &[val.low_u64() as u8]);
+    Ok(())
 }
 
 pub fn op_sload(
-    pc: &mut u64,
-    interpreter: &mut interpreter::EVMInterpreter,
-    scope: &mut interpreter::ScopeContext,
-) -> Result<Vec<u8>, Error> {
+    _pc: &mut u64,
+    interpreter: &mut EVMInterpreter,
+    scope: &mut ScopeContext,
+) -> Result<(), Error> {
     let loc = scope.stack.peek();
-    let hash = common::hash::Hash::from_slice(loc.to_bytes32().as_slice()).unwrap();
+    let hash = common::Hash(loc.to_bytes32());
     let val = interpreter
         .evm
         .state_db
-        .get_state(scope.contract.address(), &hash)?;
-    loc.set_bytes(val.as_slice());
-
-    Ok(Vec::new())
+        .get_state(&scope.contract.address(), &hash);
+    *loc = Uint256::from_bytes(val.as_bytes());
+    Ok(())
 }
 
 pub fn op_sstore(
-    pc: &mut u64,
-    interpreter: &mut interpreter::EVMInterpreter,
-    scope: &mut interpreter::ScopeContext,
-) -> Result<Vec<u8>, Error> {
+    _pc: &mut u64,
+    interpreter: &mut EVMInterpreter,
+    scope: &mut ScopeContext,
+) -> Result<(), Error> {
     if interpreter.read_only {
         return Err(Error::WriteProtection);
     }
-
-    let loc = scope.stack.pop()?;
-    let val = scope.stack.pop()?;
+    let loc = scope.stack.pop();
+    let val = scope.stack.pop();
     interpreter.evm.state_db.set_state(
-        scope.contract.address(),
-        loc.to_bytes32(),
-        val.to_bytes32(),
-    )?;
-
-    Ok(Vec::new())
+        &scope.contract.address(),
+        common::Hash(loc.to_bytes32()),
+        common::Hash(val.to_bytes32()),
+    );
+    Ok(())
 }
 
 pub fn op_jump(
     pc: &mut u64,
-    interpreter: &mut interpreter::EVMInterpreter,
-    scope: &mut interpreter::ScopeContext,
-) -> Result<Vec<u8>, Error> {
-    if interpreter.evm.abort.load(Ordering::SeqCst) {
+    interpreter: &mut EVMInterpreter,
+    scope: &mut ScopeContext,
+) -> Result<(), Error> {
+    if interpreter.evm.cancelled() {
         return Err(Error::StopToken);
     }
-
-    let pos = scope.stack.pop()?;
+    let pos = scope.stack.pop();
     if !scope.contract.valid_jumpdest(&pos) {
         return Err(Error::InvalidJump);
     }
-
-    *pc = pos.to_u64().wrapping_sub(1); // pc will be increased by the interpreter loop
-
-    Ok(Vec::new())
+    *pc = pos.low_u64().wrapping_sub(1); // pc will be increased by the interpreter loop
+    Ok(())
 }
 
 pub fn op_jumpi(
     pc: &mut u64,
-    interpreter: &mut interpreter::EVMInterpreter,
-    scope: &mut interpreter::ScopeContext,
-) -> Result<Vec<u8>, Error> {
-    if interpreter.evm.abort.load(Ordering::SeqCst) {
+    interpreter: &mut EVMInterpreter,
+    scope: &mut ScopeContext,
+) -> Result<(), Error> {
+    if interpreter.evm.cancelled() {
         return Err(Error::StopToken);
     }
-
-    let cond = scope.stack.pop()?;
-    let pos = scope.stack.pop()?;
+    let pos = scope.stack.pop();
+    let cond = scope.stack.pop();
     if !cond.is_zero() {
         if !scope.contract.valid_jumpdest(&pos) {
             return Err(Error::InvalidJump);
         }
-
-        *pc = pos.to_u64().wrapping_sub(1); // pc will be increased by the interpreter loop
+        *pc = pos.low_u64().wrapping_sub(1); // pc will be increased by the interpreter loop
     }
-
-    Ok(Vec::new())
+    Ok(())
 }
 
 pub fn op_jumpdest(
-    pc: &mut u64,
-    interpreter: &mut interpreter::EVMInterpreter,
-    scope: &mut interpreter::ScopeContext,
-) -> Result<Vec<u8>, Error> {
-    Ok(Vec::new())
+    _pc: &mut u64,
+    _interpreter: &mut EVMInterpreter,
+    _scope: &mut ScopeContext,
+) -> Result<(), Error> {
+    Ok(())
 }
 
 pub fn op_pc(
     pc: &mut u64,
-    interpreter: &mut interpreter::EVMInterpreter,
-    scope: &mut interpreter::ScopeContext,
-) -> Result<Vec<u8>, Error> {
-    let pc_value = uint256::Uint256::from(*pc);
-    scope.stack.push(pc_value);
-
-    Ok(Vec::new())
+    _interpreter: &mut EVMInterpreter,
+    scope: &mut ScopeContext,
+) -> Result<(), Error> {
+    scope.stack.push(Uint256::from_uint64(*pc));
+    Ok(())
 }
 
 pub fn op_msize(
-    pc: &mut u64,
-    interpreter: &mut interpreter::EVMInterpreter,
-    scope: &mut interpreter::ScopeContext,
-) -> Result<Vec<u8>, Error> {
-    let memory_size = uint256::Uint256::from(scope.memory.len());
-    scope.stack.push(memory_size);
-
-    Ok(Vec::new())
+    _pc: &mut u64,
+    _interpreter: &mut EVMInterpreter,
+    scope: &mut ScopeContext,
+) -> Result<(), Error> {
+    scope
+        .stack
+        .push(Uint256::from_uint64(scope.memory.len() as u64));
+    Ok(())
 }
 
 pub fn op_gas(
-    pc: &mut u64,
-    interpreter: &mut interpreter::EVMInterpreter,
-    scope: &mut interpreter::ScopeContext,
-) -> Result<Vec<u8>, Error> {
-    let contract_gas = uint256::Uint256::from(scope.contract.gas);
-    scope.stack.push(contract_gas);
-
-    Ok(Vec::new())
+    _pc: &mut u64,
+    _interpreter: &mut EVMInterpreter,
+    scope: &mut ScopeContext,
+) -> Result<(), Error> {
+    scope.stack.push(Uint256::from_uint64(scope.contract.gas));
+    Ok(())
 }
 
 pub fn op_create(
-    pc: &mut u64,
-    interpreter: &mut interpreter::EVMInterpreter,
-    scope: &mut interpreter::ScopeContext,
-) -> Result<Vec<u8>, Error> {
+    _pc: &mut u64,
+    interpreter: &mut EVMInterpreter,
+    scope: &mut ScopeContext,
+) -> Result<(), Error> {
     if interpreter.read_only {
         return Err(Error::WriteProtection);
     }
-
-    let value = scope.stack.pop()?;
-    let offset = scope.stack.pop()?;
-    let size = scope.stack.pop()?;
-    let input = scope
-        .memory
-        .get_copy(offset.to_u64() as i64, size.to_u64() as i64);
-    let gas = scope.contract.gas;
-
+    let value = scope.stack.pop();
+    let offset = scope.stack.pop();
+    let size = scope.stack.pop();
+    let input = scope.memory.get_copy(offset.low_u64(), size.low_u64());
+    let mut gas = scope.contract.gas;
     if interpreter.evm.chain_rules.is_eip150 {
-        // Adjust gas for EIP-150
-        let gas_adjustment = gas / 64;
-        gas -= gas_adjustment;
+        gas -= gas / 64;
     }
-
     scope.contract.use_gas(gas);
 
-    let big_val = if !value.is_zero() {
-        Some(value.to_bigint())
-    } else {
-        None
+    let (res, addr, return_gas, suberr) =
+        interpreter.evm.create(&*scope.contract, input, gas, value);
+    // Push item on the stack based on the returned error. If the ruleset is
+    // homestead we must check for CodeStoreOutOfGas (homestead only
+    // rule) and treat as an error, if the ruleset is frontier we must
+    // ignore this error and pretend the operation was successful.
+    let stack_value = match &suberr {
+        Some(Error::CodeStoreOutOfGas) if interpreter.evm.chain_rules.is_homestead => {
+            Uint256::zero()
+        }
+        Some(e) if *e != Error::CodeStoreOutOfGas => Uint256::zero(),
+        _ => Uint256::from_bytes(addr.as_bytes()),
     };
+    scope.stack.push(stack_value);
+    scope.contract.gas += return_gas;
+
+    if suberr == Some(Error::ExecutionReverted) {
+        interpreter.return_data = res; // set REVERT data to return data buffer
+    } else {
+        interpreter.return_data.clear(); // clear dirty return data buffer
+    }
+    Ok(())
+}
 
+pub fn op_create2(
+    _pc: &mut u64,
+    interpreter: &mut EVMInterpreter,
+    scope: &mut ScopeContext,
+) -> Result<(), Error> {
+    if interpreter.read_only {
+        return Err(Error::WriteProtection);
+    }
+    let endowment = scope.stack.pop();
+    let offset = scope.stack.pop();
+    let size = scope.stack.pop();
+    let salt = scope.stack.pop();
+    let input = scope.memory.get_copy(offset.low_u64(), size.low_u64());
+    let mut gas = scope.contract.gas;
+
+    // Apply EIP150
+    gas -= gas / 64;
+    scope.contract.use_gas(gas);
     let (res, addr, return_gas, suberr) =
         interpreter
             .evm
-            .create(scope.contract.clone(), input, gas, big_val);
-
-    let mut stackvalue = uint256::Uint256::zero();
-    if interpreter.evm.chain_rules.is_homestead && suberr == ErrCodeStoreOutOfGas {
-        stackvalue.clear();
-    } else if suberr.is_some() && suberr != Some(ErrCodeStoreOutOfGas) {
-        stackvalue.clear();
+            .create2(&*scope.contract, input, gas, endowment, &salt);
+    // Push item on the stack based on the returned error.
+    let stack_value = if suberr.is_some() {
+        Uint256::zero()
     } else {
-        stackvalue.set_bytes(addr.bytes());
-    }
-
-    scope.stack.push(stackvalue);
+        Uint256::from_bytes(addr.as_bytes())
+    };
+    scope.stack.push(stack_value);
     scope.contract.gas += return_gas;
 
-    if suberr == Some(ErrExecutionReverted) {
-        interpreter.return_data = res.clone(); // Set REVERT data to return data buffer
-        return Ok(res);
+    if suberr == Some(Error::ExecutionReverted) {
+        interpreter.return_data = res; // set REVERT data to return data buffer
+    } else {
+        interpreter.return_data.clear(); // clear dirty return data buffer
     }
-
-    interpreter.return_data = None; // Clear dirty return data buffer
-
-    Ok(Vec::new())
+    Ok(())
 }
 
 pub fn op_call(
-    pc: &mut u64,
-    interpreter: &mut interpreter::EVMInterpreter,
-    scope: &mut interpreter::ScopeContext,
-) -> Result<Vec<u8>, Error> {
-    let mut stack = &mut scope.stack;
-    let temp = stack.pop()?;
-    let gas = interpreter.evm.call_gas_temp;
-    let addr = stack.pop()?;
-    let value = stack.pop()?;
-    let in_offset = stack.pop()?;
-    let in_size = stack.pop()?;
-    let ret_offset = stack.pop()?;
-    let ret_size = stack.pop()?;
-    let to_addr = common::Address::from(addr.bytes20());
-    let args = scope
-        .memory
-        .get_ptr(in_offset.to_u64() as i64, in_size.to_u64() as i64);
+    _pc: &mut u64,
+    interpreter: &mut EVMInterpreter,
+    scope: &mut ScopeContext,
+) -> Result<(), Error> {
+    // Pop gas. The actual gas in interpreter.evm.call_gas_temp.
+    scope.stack.pop();
+    let mut gas = interpreter.evm.call_gas_temp;
+    // Pop other call parameters.
+    let addr = scope.stack.pop();
+    let value = scope.stack.pop();
+    let in_offset = scope.stack.pop();
+    let in_size = scope.stack.pop();
+    let ret_offset = scope.stack.pop();
+    let ret_size = scope.stack.pop();
+    let to_addr = common::Address(addr.bytes20());
+    // Get the arguments from the memory.
+    let args = scope.memory.get_copy(in_offset.low_u64(), in_size.low_u64());
 
     if interpreter.read_only && !value.is_zero() {
         return Err(Error::WriteProtection);
     }
-
-    let mut big_val = big0;
     if !value.is_zero() {
-        let call_stipend = params::CALL_STIPEND;
-        big_val = value.to_bigint();
-        gas += call_stipend;
-    }
-
-    let (ret, return_gas, err) =
-        interpreter
-            .evm
-            .call(scope.contract.clone(), to_addr, args, gas, big_val);
-
-    if err.is_some() {
-        temp.clear();
-    } else {
-        temp.set_one();
-    }
-
-    stack.push(temp.clone());
-
-    if err.is_none() || err == Some(ErrExecutionReverted) {
-        scope
-            .memory
-            .set(ret_offset.to_u64(), ret_size.to_u64(), &ret);
+        gas += params::CALL_STIPEND;
     }
-
-    scope.contract.gas += return_gas;
-
-    interpreter.return_data = Some(ret.clone());
-
-    Ok(ret)
+    let (ret, return_gas, err) = interpreter
+        .evm
+        .call(&*scope.contract, to_addr, &args, gas, value);
+    finish_call(interpreter, scope, ret, return_gas, err, &ret_offset, &ret_size);
+    Ok(())
 }
 
 pub fn op_call_code(
-    pc: &mut u64,
-    interpreter: &mut interpreter::EVMInterpreter,
-    scope: &mut interpreter::ScopeContext,
-) -> Result<Vec<u8>, Error> {
-    let mut stack = &mut scope.stack;
-    let temp = stack.pop()?;
-    let gas = interpreter.evm.call_gas_temp;
-    let addr = stack.pop()?;
-    let value = stack.pop()?;
-    let in_offset = stack.pop()?;
-    let in_size = stack.pop()?;
-    let ret_offset = stack.pop()?;
-    let ret_size = stack.pop()?;
-    let to_addr = common::Address::from(addr.bytes20());
-    let args = scope
-        .memory
-        .get_ptr(in_offset.to_u64() as i64, in_size.to_u64() as i64);
+    _pc: &mut u64,
+    interpreter: &mut EVMInterpreter,
+    scope: &mut ScopeContext,
+) -> Result<(), Error> {
+    // Pop gas. The actual gas is in interpreter.evm.call_gas_temp.
+    scope.stack.pop();
+    let mut gas = interpreter.evm.call_gas_temp;
+    // Pop other call parameters.
+    let addr = scope.stack.pop();
+    let value = scope.stack.pop();
+    let in_offset = scope.stack.pop();
+    let in_size = scope.stack.pop();
+    let ret_offset = scope.stack.pop();
+    let ret_size = scope.stack.pop();
+    let to_addr = common::Address(addr.bytes20());
+    // Get arguments from the memory.
+    let args = scope.memory.get_copy(in_offset.low_u64(), in_size.low_u64());
 
-    let mut big_val = big0;
     if !value.is_zero() {
-        let call_stipend = params::CALL_STIPEND;
-        big_val = value.to_bigint();
-        gas += call_stipend;
-    }
-
-    let (ret, return_gas, err) =
-        interpreter
-            .evm
-            .call_code(scope.contract.clone(), to_addr, args, gas, big_val);
-
-    if err.is_some() {
-        temp.clear();
-    } else {
-        temp.set_one();
-    }
-
-    stack.push(temp.clone());
-
-    if err.is_none() || err == Some(ErrExecutionReverted) {
-        scope
-            .memory
-            .set(ret_offset.to_u64(), ret_size.to_u64(), &ret);
+        gas += params::CALL_STIPEND;
     }
+    let (ret, return_gas, err) = interpreter
+        .evm
+        .call_code(&*scope.contract, to_addr, &args, gas, value);
+    finish_call(interpreter, scope, ret, return_gas, err, &ret_offset, &ret_size);
+    Ok(())
+}
 
-    scope.contract.gas += return_gas;
-
-    interpreter.return_data = Some(ret.clone());
-
-    Ok(ret)
+pub fn op_delegate_call(
+    _pc: &mut u64,
+    interpreter: &mut EVMInterpreter,
+    scope: &mut ScopeContext,
+) -> Result<(), Error> {
+    // Pop gas. The actual gas is in interpreter.evm.call_gas_temp.
+    scope.stack.pop();
+    let gas = interpreter.evm.call_gas_temp;
+    // Pop other call parameters.
+    let addr = scope.stack.pop();
+    let in_offset = scope.stack.pop();
+    let in_size = scope.stack.pop();
+    let ret_offset = scope.stack.pop();
+    let ret_size = scope.stack.pop();
+    let to_addr = common::Address(addr.bytes20());
+    // Get arguments from the memory.
+    let args = scope.memory.get_copy(in_offset.low_u64(), in_size.low_u64());
+
+    let (ret, return_gas, err) = interpreter
+        .evm
+        .delegate_call(scope.contract, to_addr, &args, gas);
+    finish_call(interpreter, scope, ret, return_gas, err, &ret_offset, &ret_size);
+    Ok(())
 }
 
 pub fn op_static_call(
-    pc: &mut u64,
-    interpreter: &mut interpreter::EVMInterpreter,
-    scope: &mut interpreter::ScopeContext,
-) -> Result<Vec<u8>, Error> {
-    let mut stack = &mut scope.stack;
-    let temp = stack.pop()?;
+    _pc: &mut u64,
+    interpreter: &mut EVMInterpreter,
+    scope: &mut ScopeContext,
+) -> Result<(), Error> {
+    // Pop gas. The actual gas is in interpreter.evm.call_gas_temp.
+    scope.stack.pop();
     let gas = interpreter.evm.call_gas_temp;
-    let addr = stack.pop()?;
-    let in_offset = stack.pop()?;
-    let in_size = stack.pop()?;
-    let ret_offset = stack.pop()?;
-    let ret_size = stack.pop()?;
-    let to_addr = common::Address::from(addr.bytes20());
-    let args = scope
-        .memory
-        .get_ptr(in_offset.to_u64() as i64, in_size.to_u64() as i64);
-
-    let (ret, return_gas, err) =
-        interpreter
-            .evm
-            .static_call(scope.contract.clone(), to_addr, args, gas);
-
-    if err.is_some() {
-        temp.clear();
-    } else {
-        temp.set_one();
-    }
-
-    stack.push(temp.clone());
+    // Pop other call parameters.
+    let addr = scope.stack.pop();
+    let in_offset = scope.stack.pop();
+    let in_size = scope.stack.pop();
+    let ret_offset = scope.stack.pop();
+    let ret_size = scope.stack.pop();
+    let to_addr = common::Address(addr.bytes20());
+    // Get arguments from the memory.
+    let args = scope.memory.get_copy(in_offset.low_u64(), in_size.low_u64());
+
+    let (ret, return_gas, err) = interpreter
+        .evm
+        .static_call(&*scope.contract, to_addr, &args, gas);
+    finish_call(interpreter, scope, ret, return_gas, err, &ret_offset, &ret_size);
+    Ok(())
+}
 
-    if err.is_none() || err == Some(ErrExecutionReverted) {
+// finish_call pushes the outcome of a message call, copies its output into
+// the caller's memory, refunds the unused gas and keeps the output as the
+// frame's return data.
+fn finish_call(
+    interpreter: &mut EVMInterpreter,
+    scope: &mut ScopeContext,
+    ret: Vec<u8>,
+    return_gas: u64,
+    err: Option<Error>,
+    ret_offset: &Uint256,
+    ret_size: &Uint256,
+) {
+    scope.stack.push(bool_to_uint(err.is_none()));
+    if err.is_none() || err == Some(Error::ExecutionReverted) {
         scope
             .memory
-            .set(ret_offset.to_u64(), ret_size.to_u64(), &ret);
+            .set(ret_offset.low_u64(), ret_size.low_u64(), &ret);
     }
-
     scope.contract.gas += return_gas;
 
-    interpreter.return_data = Some(ret.clone());
-
-    Ok(ret)
+    interpreter.return_data = ret;
 }
 
 pub fn op_return(
-    pc: &mut u64,
-    interpreter: &mut interpreter::EVMInterpreter,
-    scope: &mut interpreter::ScopeContext,
-) -> Result<Vec<u8>, Error> {
-    let offset = scope.stack.pop()?;
-    let size = scope.stack.pop()?;
-    let ret = scope
-        .memory
-        .get_ptr(offset.to_u64() as i64, size.to_u64() as i64);
-
+    _pc: &mut u64,
+    interpreter: &mut EVMInterpreter,
+    scope: &mut ScopeContext,
+) -> Result<(), Error> {
+    let offset = scope.stack.pop();
+    let size = scope.stack.pop();
+    interpreter.return_data = scope.memory.get_copy(offset.low_u64(), size.low_u64());
     Err(Error::StopToken)
 }
 
 pub fn op_revert(
-    pc: &mut u64,
-    interpreter: &mut interpreter::EVMInterpreter,
-    scope: &mut interpreter::ScopeContext,
-) -> Result<Vec<u8>, Error> {
-    let offset = scope.stack.pop()?;
-    let size = scope.stack.pop()?;
-    let ret = scope
-        .memory
-        .get_ptr(offset.to_u64() as i64, size.to_u64() as i64);
-
-    interpreter.return_data = Some(ret.clone());
-
+    _pc: &mut u64,
+    interpreter: &mut EVMInterpreter,
+    scope: &mut ScopeContext,
+) -> Result<(), Error> {
+    let offset = scope.stack.pop();
+    let size = scope.stack.pop();
+    interpreter.return_data = scope.memory.get_copy(offset.low_u64(), size.low_u64());
     Err(Error::ExecutionReverted)
 }
 
 pub fn op_undefined(
     pc: &mut u64,
-    interpreter: &mut interpreter::EVMInterpreter,
-    scope: &mut interpreter::ScopeContext,
-) -> Result<Vec<u8>, Error> {
-    let opcode = scope.contract.code[*pc] as u8;
-    Err(Error::InvalidOpCode { opcode })
+    _interpreter: &mut EVMInterpreter,
+    scope: &mut ScopeContext,
+) -> Result<(), Error> {
+    Err(Error::InvalidOpCode {
+        opcode: scope.contract.code[*pc as usize],
+    })
 }
 
 pub fn op_stop(
-    pc: &mut u64,
-    interpreter: &mut interpreter::EVMInterpreter,
-    scope: &mut interpreter::ScopeContext,
-) -> Result<Vec<u8>, Error> {
+    _pc: &mut u64,
+    interpreter: &mut EVMInterpreter,
+    _scope: &mut ScopeContext,
+) -> Result<(), Error> {
+    interpreter.return_data.clear();
     Err(Error::StopToken)
 }
 
 pub fn op_selfdestruct(
-    pc: &mut u64,
-    interpreter: &mut interpreter::EVMInterpreter,
-    scope: &mut interpreter::ScopeContext,
-) -> Result<Vec<u8>, Error> {
+    _pc: &mut u64,
+    interpreter: &mut EVMInterpreter,
+    scope: &mut ScopeContext,
+) -> Result<(), Error> {
     if interpreter.read_only {
         return Err(Error::WriteProtection);
     }
+    let beneficiary = common::Address(scope.stack.pop().bytes20());
+    let address = scope.contract.address();
+    let balance = interpreter.evm.state_db.get_balance(&address);
+    interpreter.evm.state_db.add_balance(&beneficiary, &balance);
+    interpreter.evm.state_db.suicide(&address);
+    interpreter.return_data.clear();
+    Err(Error::StopToken)
+}
 
-    let beneficiary = scope.stack.pop()?;
-    let balance = interpreter
-        .evm
-        .state_db
-        .get_balance(&scope.contract.address());
-    interpreter
-        .evm
-        .state_db
-        .add_balance(beneficiary.bytes20(), balance);
-    interpreter.evm.state_db.suicide(scope.contract.address());
-
-    if let Some(tracer) = &interpreter.evm.config.tracer {
-        tracer.capture_enter(
-            SELFDESTRUCT,
-            scope.contract.address(),
-            beneficiary.bytes20(),
-            &[],
-            0,
-            balance,
-        );
-        tracer.capture_exit(&[], 0, None);
+pub fn op_selfdestruct6780(
+    _pc: &mut u64,
+    interpreter: &mut EVMInterpreter,
+    scope: &mut ScopeContext,
+) -> Result<(), Error> {
+    if interpreter.read_only {
+        return Err(Error::WriteProtection);
     }
-
+    let beneficiary = common::Address(scope.stack.pop().bytes20());
+    let address = scope.contract.address();
+    let balance = interpreter.evm.state_db.get_balance(&address);
+    interpreter.evm.state_db.sub_balance(&address, &balance);
+    interpreter.evm.state_db.add_balance(&beneficiary, &balance);
+    interpreter.evm.state_db.selfdestruct6780(&address);
+    interpreter.return_data.clear();
     Err(Error::StopToken)
 }
 
-fn make_log(size: usize) -> ExecutionFunc {
-    Box::new(
-        move |pc: &mut u64,
-              interpreter: &mut EVMInterpreter,
-              scope: &mut ScopeContext|
-              -> Result<Vec<u8>, Error> {
-            if interpreter.read_only {
-                return Err(Error::WriteProtection);
-            }
-
-            let mut topics = vec![common::Hash::default(); size];
-            let mut stack = &mut scope.stack;
-            let m_start = stack.pop()?;
-            let m_size = stack.pop()?;
-
-            for i in 0..size {
-                let addr = stack.pop()?;
-                topics[i] = addr.bytes32();
-            }
-
-            let d = scope
-                .memory
-                .get_copy(m_start.to_u64() as i64, m_size.to_u64() as i64);
-            let log = types::Log {
-                address: scope.contract.address().clone(),
-                topics,
-                data: d,
-                block_number: interpreter.evm.context.block_number().to_u64(),
-                ..Default::default()
-            };
-
-            interpreter.evm.state_db.add_log(log);
-
-            Ok(vec![])
-        },
-    )
-}
-
-fn op_push1(
-    pc: &mut u64,
+// following functions are used by the instruction jump table
+
+// op_log is the LOG0..LOG4 instruction with N topics.
+pub fn op_log<const N: usize>(
+    _pc: &mut u64,
     interpreter: &mut EVMInterpreter,
     scope: &mut ScopeContext,
-) -> Result<Vec<u8>, Error> {
+) -> Result<(), Error> {
+    if interpreter.read_only {
+        return Err(Error::WriteProtection);
+    }
+    let m_start = scope.stack.pop();
+    let m_size = scope.stack.pop();
+    let topics = (0..N)
+        .map(|_| common::Hash(scope.stack.pop().to_bytes32()))
+        .collect();
+
+    let data = scope.memory.get_copy(m_start.low_u64(), m_size.low_u64());
+    interpreter.evm.state_db.add_log(types::Log {
+        address: scope.contract.address(),
+        topics,
+        data,
+        // This is a non-consensus field, but assigned here because
+        // core/state doesn't know the current block number.
+        block_number: interpreter.evm.context.block_number.low_u64(),
+        ..Default::default()
+    });
+    Ok(())
+}
+
+// op_push1 is a specialized version of op_push::<1>
+pub fn op_push1(
+    pc: &mut u64,
+    _interpreter: &mut EVMInterpreter,
+    scope: &mut ScopeContext,
+) -> Result<(), Error> {
     let code_len = scope.contract.code.len() as u64;
     *pc += 1;
-
     if *pc < code_len {
-        let push_byte = uint256::Int::from_uint64(scope.contract.code[*pc]);
-        scope.stack.push(push_byte);
+        scope
+            .stack
+            .push(Uint256::from_uint64(scope.contract.code[*pc as usize] as u64));
     } else {
-        scope.stack.push(uint256::Int::default());
+        scope.stack.push(Uint256::zero());
     }
+    Ok(())
+}
+
+// op_push is the PUSH instruction for N bytes of immediate data.
+pub fn op_push<const N: usize>(
+    pc: &mut u64,
+    _interpreter: &mut EVMInterpreter,
+    scope: &mut ScopeContext,
+) -> Result<(), Error> {
+    let code_len = scope.contract.code.len();
+    let start_min = (*pc as usize + 1).min(code_len);
+    let end_min = (start_min + N).min(code_len);
+
+    let value = common::right_pad_bytes(&scope.contract.code[start_min..end_min], N);
+    scope.stack.push(Uint256::from_bytes(&value));
+
+    *pc += N as u64;
+    Ok(())
+}
+
+// op_push0 implements the PUSH0 opcode
+pub fn op_push0(
+    _pc: &mut u64,
+    _interpreter: &mut EVMInterpreter,
+    scope: &mut ScopeContext,
+) -> Result<(), Error> {
+    scope.stack.push(Uint256::zero());
+    Ok(())
+}
 
-    Ok(vec![])
+// op_dup is the DUP instruction for the N'th stack item.
+pub fn op_dup<const N: usize>(
+    _pc: &mut u64,
+    _interpreter: &mut EVMInterpreter,
+    scope: &mut ScopeContext,
+) -> Result<(), Error> {
+    scope.stack.dup(N);
+    Ok(())
+}
+
+// op_swap is the SWAP instruction exchanging the top with the N'th item below it.
+pub fn op_swap<const N: usize>(
+    _pc: &mut u64,
+    _interpreter: &mut EVMInterpreter,
+    scope: &mut ScopeContext,
+) -> Result<(), Error> {
+    scope.stack.swap(N);
+    Ok(())
+}
+
+// op_self_balance implements the SELFBALANCE opcode (EIP-1884)
+pub fn op_self_balance(
+    _pc: &mut u64,
+    interpreter: &mut EVMInterpreter,
+    scope: &mut ScopeContext,
+) -> Result<(), Error> {
+    let balance = interpreter
+        .evm
+        .state_db
+        .get_balance(&scope.contract.address());
+    scope.stack.push(balance);
+    Ok(())
+}
+
+// op_chain_id implements CHAINID opcode (EIP-1344)
+pub fn op_chain_id(
+    _pc: &mut u64,
+    interpreter: &mut EVMInterpreter,
+    scope: &mut ScopeContext,
+) -> Result<(), Error> {
+    scope
+        .stack
+        .push(Uint256::from_uint64(interpreter.evm.chain_config.chain_id));
+    Ok(())
 }
 
-fn make_push(size: u64, push_byte_size: usize) -> ExecutionFunc {
-    Box::new(
-        move |pc: &mut u64,
-              interpreter: &mut EVMInterpreter,
-              scope: &mut ScopeContext|
-              -> Result<Vec<u8>, Error> {
-            let code_len = scope.contract.code.len();
-            let start_min = std::cmp::min(code_len, *pc as usize + 1);
-            let end_min = std::cmp::min(code_len, start_min + push_byte_size);
+// op_base_fee implements BASEFEE opcode (EIP-3198)
+pub fn op_base_fee(
+    _pc: &mut u64,
+    interpreter: &mut EVMInterpreter,
+    scope: &mut ScopeContext,
+) -> Result<(), Error> {
+    scope.stack.push(interpreter.evm.context.base_fee.clone());
+    Ok(())
+}
 
-            let push_bytes =
-                common::right_pad_bytes(&scope.contract.code[start_min..end_min], push_byte_size);
-            let integer = uint256::Int::from_bytes(&push_bytes);
-            scope.stack.push(integer);
+// op_tload implements TLOAD opcode (EIP-1153)
+pub fn op_tload(
+    _pc: &mut u64,
+    interpreter: &mut EVMInterpreter,
+    scope: &mut ScopeContext,
+) -> Result<(), Error> {
+    let loc = scope.stack.peek();
+    let hash = common::Hash(loc.to_bytes32());
+    let val = interpreter
+        .evm
+        .state_db
+        .get_transient_state(&scope.contract.address(), &hash);
+    *loc = Uint256::from_bytes(val.as_bytes());
+    Ok(())
+}
 
-            *pc += size;
+// op_tstore implements TSTORE opcode (EIP-1153)
+pub fn op_tstore(
+    _pc: &mut u64,
+    interpreter: &mut EVMInterpreter,
+    scope: &mut ScopeContext,
+) -> Result<(), Error> {
+    if interpreter.read_only {
+        return Err(Error::WriteProtection);
+    }
+    let loc = scope.stack.pop();
+    let val = scope.stack.pop();
+    interpreter.evm.state_db.set_transient_state(
+        &scope.contract.address(),
+        common::Hash(loc.to_bytes32()),
+        common::Hash(val.to_bytes32()),
+    );
+    Ok(())
+}
 
-            Ok(vec![])
-        },
-    )
+// op_mcopy implements MCOPY opcode (EIP-5656)
+pub fn op_mcopy(
+    _pc: &mut u64,
+    _interpreter: &mut EVMInterpreter,
+    scope: &mut ScopeContext,
+) -> Result<(), Error> {
+    let dst = scope.stack.pop();
+    let src = scope.stack.pop();
+    let length = scope.stack.pop();
+    // These values are checked for overflow during memory expansion calculation
+    // (the memory_mcopy function).
+    scope
+        .memory
+        .copy(dst.low_u64(), src.low_u64(), length.low_u64());
+    Ok(())
 }
 
-fn make_dup(size: i64) -> ExecutionFunc {
-    Box::new(
-        move |pc: &mut u64,
-              interpreter: &mut EVMInterpreter,
-              scope: &mut ScopeContext|
-              -> Result<Vec<u8>, Error> {
-            scope.stack.dup(size as usize);
+// op_blob_hash implements the BLOBHASH opcode (EIP-4844)
+pub fn op_blob_hash(
+    _pc: &mut u64,
+    interpreter: &mut EVMInterpreter,
+    scope: &mut ScopeContext,
+) -> Result<(), Error> {
+    let index = scope.stack.peek();
+    let hashes = &interpreter.evm.tx_context.blob_hashes;
+    if index.is_u64() && index.low_u64() < hashes.len() as u64 {
+        *index = Uint256::from_bytes(hashes[index.low_u64() as usize].as_bytes());
+    } else {
+        *index = Uint256::zero();
+    }
+    Ok(())
+}
 
-            Ok(vec![])
-        },
-    )
+// op_blob_base_fee implements BLOBBASEFEE opcode (EIP-7516)
+pub fn op_blob_base_fee(
+    _pc: &mut u64,
+    interpreter: &mut EVMInterpreter,
+    scope: &mut ScopeContext,
+) -> Result<(), Error> {
+    scope
+        .stack
+        .push(interpreter.evm.context.blob_base_fee.clone());
+    Ok(())
 }
 
-fn make_swap(size: i64) -> ExecutionFunc {
-    Box::new(
-        move |pc: &mut u64,
-              interpreter: &mut EVMInterpreter,
-              scope: &mut ScopeContext|
-              -> Result<Vec<u8>, Error> {
-            scope.stack.swap(size as usize);
+// get_data returns a slice from the data based on the start and size and pads
+// up to size with zero's. This function is overflow safe.
+fn get_data(data: &[u8], start: u64, size: u64) -> Vec<u8> {
+    let length = data.len() as u64;
+    let start = start.min(length);
+    let end = start.saturating_add(size).min(length);
+    common::right_pad_bytes(&data[start as usize..end as usize], size as usize)
+}
 
-            Ok(vec![])
-        },
-    )
+fn bool_to_uint(b: bool) -> Uint256 {
+    if b {
+        Uint256::one()
+    } else {
+        Uint256::zero()
+    }
 }
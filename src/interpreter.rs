@@ -1,122 +1,141 @@
-mod jump_table;
-
-pub struct ScopeContext {
-    memory: Memory,
-    stack: Stack,
-    contract: Contract,
+use std::sync::Arc;
+
+use crate::contract::Contract;
+use crate::errors::Error;
+use crate::evm::EVM;
+use crate::gas::to_word_size;
+use crate::jump_table::JumpTable;
+use crate::memory::Memory;
+use crate::op_code;
+use crate::stack::Stack;
+
+// ScopeContext contains the things that are per-call, such as stack and memory,
+// but not transients like pc and gas
+pub struct ScopeContext<'a> {
+    pub memory: Memory,
+    pub stack: Stack,
+    pub contract: &'a mut Contract,
 }
 
-pub struct EVM {} //TODO
-
+// EVMInterpreter represents an EVM interpreter executing a single frame.
 pub struct EVMInterpreter<'a> {
-    evm: &'a EVM,
-    table: jump_table::JumpTable,
-
-    hasher: crypto::KeccakState,
-    hasher_buf: common::Hash,
+    pub evm: &'a mut EVM,
+    table: Arc<JumpTable>,
 
-    read_only: bool,
-    return_data: Vec<u8>,
+    pub read_only: bool,      // Whether to throw on stateful modifications
+    pub return_data: Vec<u8>, // Last CALL's return data for subsequent reuse
 }
 
-impl EVMInterpreter {
-    fn new(evm: EVM) -> Self {
-        let table: Option<jump_table::JumpTable> = jump_table::JumpTable::new();
-        EVMInterpreter { evm, table }
+impl<'a> EVMInterpreter<'a> {
+    // new returns a new interpreter for a frame of evm. read_only must be
+    // set if the frame executes below a STATICCALL.
+    pub fn new(evm: &'a mut EVM, read_only: bool) -> Self {
+        let table = evm.jump_table();
+        EVMInterpreter {
+            evm,
+            table,
+            read_only,
+            return_data: Vec::new(),
+        }
     }
 
-    // Run loops and evaluates the contract's code with the given input data and returns
+    // run loops and evaluates the contract's code with the given input data and returns
     // the return byte-slice and an error if one occurred.
     //
     // It's important to note that any errors returned by the interpreter should be
     // considered a revert-and-consume-all-gas operation except for
-    // ErrExecutionReverted which means revert-and-keep-gas-left.
-    pub fn run(
-        &mut self,
-        contract: &Contract,
-        input: &[u8],
-        read_only: bool,
-    ) -> Result<Vec<u8>, Error> {
+    // ExecutionReverted which means revert-and-keep-gas-left. A reverted
+    // frame returns its REVERT data along with the error.
+    pub fn run(&mut self, contract: &mut Contract, input: &[u8]) -> (Vec<u8>, Option<Error>) {
         // Increment the call depth which is restricted to 1024
         self.evm.depth += 1;
-        defer! { self.evm.depth -= 1 };
-
-        // Make sure the read_only is only set if we aren't in read_only yet.
-        // This also makes sure that the read_only flag isn't removed for child calls.
-        if read_only && !self.read_only {
-            self.read_only = true;
-            defer! { self.read_only = false };
+        let result = self.execute(contract, input);
+        self.evm.depth -= 1;
+
+        // RETURN and REVERT leave their output in the return data buffer.
+        match result {
+            Ok(()) => (Vec::new(), None),
+            Err(Error::StopToken) => (std::mem::take(&mut self.return_data), None),
+            Err(Error::ExecutionReverted) => (
+                std::mem::take(&mut self.return_data),
+                Some(Error::ExecutionReverted),
+            ),
+            Err(err) => (Vec::new(), Some(err)),
         }
+    }
 
-        // Reset the previous call's return data. It's unimportant to preserve the old buffer
-        // as every returning call will return new data anyway.
-        self.return_data.clear();
-
+    fn execute(&mut self, contract: &mut Contract, input: &[u8]) -> Result<(), Error> {
         // Don't bother with the execution if there's no code.
         if contract.code.is_empty() {
-            return Ok(Vec::new());
+            return Ok(());
         }
 
-        let mut pc = 0u64; // program counter
-        let mut cost = 0u64;
-
-        defer! { stack::return_stack(stack) };
-
         contract.input = input.to_vec();
+        let mut scope = ScopeContext {
+            memory: Memory::new(),
+            stack: Stack::new(),
+            contract,
+        };
+        let mut pc = 0u64; // program counter
 
-        while let Some(op) = contract.get_op(pc) {
+        // The interpreter main run loop. This loop runs until either an
+        // explicit STOP, RETURN or SELFDESTRUCT is executed, an error occurred
+        // during the execution of one of the operations or until the abort
+        // flag is set by the parent context.
+        while !self.evm.cancelled() {
+            // Get the operation from the jump table and validate the stack to ensure there are
+            // enough stack items available to perform the operation.
+            let op = scope.contract.get_op(pc).unwrap_or(op_code::STOP);
             let operation = self.table[op as usize];
-            cost = operation.constant_gas;
-
             // Validate stack
-            if let Err(stack_error) =
-                validate_stack(stack.len(), operation.min_stack, operation.max_stack)
-            {
-                return Err(stack_error.into());
+            let s_len = scope.stack.len();
+            if s_len < operation.min_stack {
+                return Err(Error::StackUnderflow);
+            } else if s_len > operation.max_stack {
+                return Err(Error::StackOverflow);
             }
-
-            if !contract.use_gas(cost) {
+            if !scope.contract.use_gas(operation.constant_gas) {
                 return Err(Error::OutOfGas);
             }
 
             if let Some(dynamic_gas) = operation.dynamic_gas {
-                let memory_size = if let Some(memory_size_fn) = operation.memory_size {
-                    let (mem_size, overflow) = memory_size_fn(stack)?;
+                // All ops with a dynamic memory usage also has a dynamic gas cost.
+                let mut memory_size = 0u64;
+                if let Some(memory_size_fn) = operation.memory_size {
+                    // calculate the new memory size and expand the memory to fit
+                    // the operation
+                    // Memory check needs to be done prior to evaluating the dynamic gas portion,
+                    // to detect calculation overflows
+                    let (mem_size, overflow) = memory_size_fn(&scope.stack);
                     if overflow {
                         return Err(Error::GasUintOverflow);
                     }
-                    to_word_size(mem_size)
+                    // memory is expanded in words of 32 bytes. Gas
+                    // is also calculated in words.
+                    memory_size = to_word_size(mem_size)
                         .checked_mul(32)
-                        .ok_or(Error::GasUintOverflow)?
-                } else {
-                    0
-                };
-
-                let dynamic_cost =
-                    dynamic_gas(self.evm, contract, stack, &mut self.memory, memory_size)?;
-                cost += dynamic_cost;
-
-                if !contract.use_gas(dynamic_cost) {
+                        .ok_or(Error::GasUintOverflow)?;
+                }
+                // Consume the gas and return an error if not enough gas is available.
+                let dynamic_cost = dynamic_gas(
+                    self.evm,
+                    scope.contract,
+                    &scope.stack,
+                    &mut scope.memory,
+                    memory_size,
+                )?;
+                if !scope.contract.use_gas(dynamic_cost) {
                     return Err(Error::OutOfGas);
                 }
-
                 if memory_size > 0 {
-                    self.memory.resize(memory_size);
+                    scope.memory.resize(memory_size);
                 }
             }
-            let (result, execute_err) = operation.execute(&mut pc, self, call_context)?;
 
-            if let Some(err) = execute_err {
-                err.into()
-            }
-
-            pc += 1;
+            // execute the operation
+            (operation.execute)(&mut pc, self, &mut scope)?;
+            pc = pc.wrapping_add(1);
         }
-
-        if err == Some(ErrStopToken) {
-            err = None;
-        }
-
-        Ok(result)
+        Ok(())
     }
 }
@@ -1,603 +1,490 @@
-mod gas;
-mod gas_table;
-mod gas_table;
-mod instructions;
-mod interpreter;
-mod memory;
-mod memory_table;
-mod stack;
-mod stack_table;
+use crate::chain_config::Rules;
+use crate::contract::Contract;
+use crate::eips;
+use crate::errors::Error;
+use crate::evm::EVM;
+use crate::gas::{GAS_EXT_STEP, GAS_FASTEST_STEP, GAS_FAST_STEP, GAS_MID_STEP, GAS_QUICK_STEP, GAS_SLOW_STEP};
+use crate::gas_table;
+use crate::instructions;
+use crate::interpreter::{EVMInterpreter, ScopeContext};
+use crate::memory::Memory;
+use crate::memory_table;
+use crate::op_code::*;
+use crate::params;
+use crate::stack::Stack;
+use crate::stack_table::{max_dup_stack, max_stack, max_swap_stack, min_dup_stack, min_stack, min_swap_stack};
 
-type ExecutionFunc = fn(
+pub type ExecutionFunc = fn(
     pc: &mut u64,
-    interpreter: &mut interpreter::EVMInterpreter,
-    call_context: &mut interpreter::ScopeContext,
-) -> Result<Vec<u8>, Error>;
+    interpreter: &mut EVMInterpreter,
+    scope: &mut ScopeContext,
+) -> Result<(), Error>;
 
-type GasFunc = fn(
-    evm: &EVM,
-    contract: &Contract,
-    stack: &stack::Stack,
-    memory: &stack::Memory,
-    requested_memory_size: u64,
+pub type GasFunc = fn(
+    evm: &mut EVM,
+    contract: &mut Contract,
+    stack: &Stack,
+    mem: &mut Memory,
+    memory_size: u64,
 ) -> Result<u64, Error>;
 
-type MemorySizeFunc = fn(stack: &Stack) -> (u64, bool);
+// MemorySizeFunc returns the required size, and whether the operation overflowed a u64
+pub type MemorySizeFunc = fn(stack: &Stack) -> (u64, bool);
 
-struct Operation {
-    execute: ExecutionFunc,
-    constant_gas: u64,
-    dynamic_gas: GasFunc,
-    min_stack: i32,
-    max_stack: i32,
-    memory_size: MemorySizeFunc,
+#[derive(Clone, Copy)]
+pub struct Operation {
+    // execute is the operation function
+    pub execute: ExecutionFunc,
+    pub constant_gas: u64,
+    pub dynamic_gas: Option<GasFunc>,
+    // min_stack tells how many stack items are required
+    pub min_stack: usize,
+    // max_stack specifies the max length the stack can have for this operation
+    // to not overflow the stack.
+    pub max_stack: usize,
+
+    // memory_size returns the memory size required for the operation
+    pub memory_size: Option<MemorySizeFunc>,
+
+    // undefined denotes if the instruction is not officially defined in the jump table
+    pub undefined: bool,
 }
 
-pub type JumpTable = [Option<Operation>; 256];
-fn validate(jt: JumpTable) -> JumpTable {
-    for (i, op) in jt.iter().enumerate() {
-        if op.is_none() {
-            panic!(format!("op {:x} is not set", i));
+impl Operation {
+    // new returns a defined operation without dynamic gas or memory
+    // expansion.
+    pub fn new(execute: ExecutionFunc, constant_gas: u64, pops: usize, pushes: usize) -> Self {
+        Operation {
+            execute,
+            constant_gas,
+            dynamic_gas: None,
+            min_stack: min_stack(pops, pushes),
+            max_stack: max_stack(pops, pushes),
+            memory_size: None,
+            undefined: false,
         }
     }
-    jt
 }
 
-fn deafult_instruction_set() -> JumpTable {
-    let mut tbl: [Option<Operation>; 256] = [None; 256];
-    tbl[OpCode::STOP as usize] = Some(Operation {
-        execute: instructions::op_stop,
-        constant_gas: 0,
-        min_stack: stack_table::min_stack(0, 0),
-        max_stack: stack_table::max_stack(0, 0),
-        ..Default::default()
-    });
+impl Default for Operation {
+    // default returns an undefined operation, which aborts execution with
+    // an invalid opcode error.
+    fn default() -> Self {
+        Operation {
+            execute: instructions::op_undefined,
+            constant_gas: 0,
+            dynamic_gas: None,
+            min_stack: min_stack(0, 0),
+            max_stack: max_stack(0, 0),
+            memory_size: None,
+            undefined: true,
+        }
+    }
+}
 
-    jump_table[OpCode::ADD as usize] = Some(Operation {
-        execute: instructions::op_add,
-        constant_gas: gas::GasFastestStep,
-        min_stack: stack_table::min_stack(2, 1),
-        max_stack: stack_table::max_stack(2, 1),
-    });
+// JumpTable contains the EVM opcodes supported at a given fork.
+pub type JumpTable = [Operation; 256];
 
-    jump_table[OpCode::MUL as usize] = Some(Operation {
-        execute: instructions::op_mul,
-        constant_gas: gas::GasFastStep,
-        min_stack: stack_table::min_stack(2, 1),
-        max_stack: stack_table::max_stack(2, 1),
-    });
+// new_instruction_set returns the instruction set of the fork active under
+// the given rules.
+pub fn new_instruction_set(rules: &Rules) -> JumpTable {
+    if rules.is_cancun {
+        new_cancun_instruction_set()
+    } else if rules.is_shanghai {
+        new_shanghai_instruction_set()
+    } else if rules.is_merge {
+        new_merge_instruction_set()
+    } else if rules.is_london {
+        new_london_instruction_set()
+    } else if rules.is_berlin {
+        new_berlin_instruction_set()
+    } else if rules.is_istanbul {
+        new_istanbul_instruction_set()
+    } else if rules.is_constantinople {
+        new_constantinople_instruction_set()
+    } else if rules.is_byzantium {
+        new_byzantium_instruction_set()
+    } else if rules.is_eip158 {
+        new_spurious_dragon_instruction_set()
+    } else if rules.is_eip150 {
+        new_tangerine_whistle_instruction_set()
+    } else if rules.is_homestead {
+        new_homestead_instruction_set()
+    } else {
+        new_frontier_instruction_set()
+    }
+}
 
-    jump_table[OpCode::SUB as usize] = Some(Operation {
-        execute: instructions::op_sub,
-        constant_gas: gas::GasFastestStep,
-        min_stack: stack_table::min_stack(2, 1),
-        max_stack: stack_table::max_stack(2, 1),
-    });
+// new_cancun_instruction_set returns the frontier, homestead, byzantium,
+// constantinople, istanbul, petersburg, berlin, london, paris, shanghai and
+// cancun instructions.
+fn new_cancun_instruction_set() -> JumpTable {
+    let mut tbl = new_shanghai_instruction_set();
+    eips::enable_4844(&mut tbl); // EIP-4844 (BLOBHASH opcode)
+    eips::enable_7516(&mut tbl); // EIP-7516 (BLOBBASEFEE opcode)
+    eips::enable_1153(&mut tbl); // EIP-1153 "Transient Storage"
+    eips::enable_5656(&mut tbl); // EIP-5656 (MCOPY opcode)
+    eips::enable_6780(&mut tbl); // EIP-6780 SELFDESTRUCT only in same transaction
+    tbl
+}
 
-    jump_table[OpCode::DIV as usize] = Some(Operation {
-        execute: instructions::op_div,
-        constant_gas: gas::GasFastStep,
-        min_stack: stack_table::min_stack(2, 1),
-        max_stack: stack_table::max_stack(2, 1),
-    });
+fn new_shanghai_instruction_set() -> JumpTable {
+    let mut tbl = new_merge_instruction_set();
+    eips::enable_3855(&mut tbl); // PUSH0 instruction
+    eips::enable_3860(&mut tbl); // Limit and meter initcode
+    tbl
+}
 
-    jump_table[OpCode::SDIV as usize] = Some(Operation {
-        execute: instructions::opSdiv,
-        constant_gas: gas::GasFastStep,
-        min_stack: stack_table::min_stack(2, 1),
-        max_stack: stack_table::max_stack(2, 1),
-    });
+fn new_merge_instruction_set() -> JumpTable {
+    let mut tbl = new_london_instruction_set();
+    tbl[PREVRANDAO as usize] = Operation::new(instructions::op_random, GAS_QUICK_STEP, 0, 1);
+    tbl
+}
 
-    jump_table[OpCode::MOD as usize] = Some(Operation {
-        execute: instructions::op_mod,
-        constant_gas: gas::GasFastStep,
-        min_stack: stack_table::min_stack(2, 1),
-        max_stack: stack_table::max_stack(2, 1),
-    });
+// new_london_instruction_set returns the frontier, homestead, byzantium,
+// constantinople, istanbul, petersburg, berlin and london instructions.
+fn new_london_instruction_set() -> JumpTable {
+    let mut tbl = new_berlin_instruction_set();
+    eips::enable_3529(&mut tbl); // EIP-3529: Reduction in refunds
+    eips::enable_3198(&mut tbl); // Base fee opcode https://eips.ethereum.org/EIPS/eip-3198
+    tbl
+}
 
-    jump_table[OpCode::SMOD as usize] = Some(Operation {
-        execute: instructions::op_smod,
-        constant_gas: gas::GasFastStep,
-        min_stack: stack_table::min_stack(2, 1),
-        max_stack: stack_table::max_stack(2, 1),
-    });
+// new_berlin_instruction_set returns the frontier, homestead, byzantium,
+// constantinople, istanbul, petersburg and berlin instructions.
+fn new_berlin_instruction_set() -> JumpTable {
+    let mut tbl = new_istanbul_instruction_set();
+    eips::enable_2929(&mut tbl); // Gas cost increases for state access opcodes https://eips.ethereum.org/EIPS/eip-2929
+    tbl
+}
 
-    jump_table[OpCode::ADDMOD as usize] = Some(Operation {
-        execute: instructions::add_mod,
-        constant_gas: gas::GasMidStep,
-        min_stack: stack_table::min_stack(3, 1),
-        max_stack: stack_table::max_stack(3, 1),
-    });
+// new_istanbul_instruction_set returns the frontier, homestead, byzantium,
+// constantinople, istanbul and petersburg instructions.
+fn new_istanbul_instruction_set() -> JumpTable {
+    let mut tbl = new_constantinople_instruction_set();
+    eips::enable_1344(&mut tbl); // ChainID opcode - https://eips.ethereum.org/EIPS/eip-1344
+    eips::enable_1884(&mut tbl); // Reprice reader opcodes - https://eips.ethereum.org/EIPS/eip-1884
+    eips::enable_2200(&mut tbl); // Net metered SSTORE - https://eips.ethereum.org/EIPS/eip-2200
+    tbl
+}
 
-    jump_table[OpCode::MULMOD as usize] = Some(Operation {
-        execute: instructions::mul_mod,
-        constant_gas: gas::GasMidStep,
-        min_stack: stack_table::min_stack(3, 1),
-        max_stack: stack_table::max_stack(3, 1),
-    });
+// new_constantinople_instruction_set returns the frontier, homestead,
+// byzantium and constantinople instructions.
+fn new_constantinople_instruction_set() -> JumpTable {
+    let mut tbl = new_byzantium_instruction_set();
+    tbl[SHL as usize] = Operation::new(instructions::op_shl, GAS_FASTEST_STEP, 2, 1);
+    tbl[SHR as usize] = Operation::new(instructions::op_shr, GAS_FASTEST_STEP, 2, 1);
+    tbl[SAR as usize] = Operation::new(instructions::op_sar, GAS_FASTEST_STEP, 2, 1);
+    tbl[EXTCODEHASH as usize] = Operation::new(instructions::op_ext_code_hash, params::EXTCODEHASH_GAS_CONSTANTINOPLE, 1, 1);
+    tbl[CREATE2 as usize] = Operation {
+        execute: instructions::op_create2,
+        constant_gas: params::CREATE2_GAS,
+        dynamic_gas: Some(gas_table::gas_create2),
+        min_stack: min_stack(4, 1),
+        max_stack: max_stack(4, 1),
+        memory_size: Some(memory_table::memory_create2),
+        undefined: false,
+    };
+    tbl
+}
 
-    jump_table[OpCode::EXP as usize] = Some(Operation {
-        execute: instructions::op_exp,
-        constant_gas: gas::GasExpFrontier,
-        min_stack: stack_table::min_stack(2, 1),
-        max_stack: stack_table::max_stack(2, 1),
-    });
+// new_byzantium_instruction_set returns the frontier, homestead and
+// byzantium instructions.
+fn new_byzantium_instruction_set() -> JumpTable {
+    let mut tbl = new_spurious_dragon_instruction_set();
+    tbl[STATICCALL as usize] = Operation {
+        execute: instructions::op_static_call,
+        constant_gas: params::CALL_GAS_EIP150,
+        dynamic_gas: Some(gas_table::gas_static_call),
+        min_stack: min_stack(6, 1),
+        max_stack: max_stack(6, 1),
+        memory_size: Some(memory_table::memory_static_call),
+        undefined: false,
+    };
+    tbl[RETURNDATASIZE as usize] = Operation::new(instructions::op_return_data_size, GAS_QUICK_STEP, 0, 1);
+    tbl[RETURNDATACOPY as usize] = Operation {
+        execute: instructions::op_return_data_copy,
+        constant_gas: GAS_FASTEST_STEP,
+        dynamic_gas: Some(gas_table::gas_return_data_copy),
+        min_stack: min_stack(3, 0),
+        max_stack: max_stack(3, 0),
+        memory_size: Some(memory_table::memory_return_data_copy),
+        undefined: false,
+    };
+    tbl[REVERT as usize] = Operation {
+        execute: instructions::op_revert,
+        constant_gas: 0,
+        dynamic_gas: Some(gas_table::GAS_REVERT),
+        min_stack: min_stack(2, 0),
+        max_stack: max_stack(2, 0),
+        memory_size: Some(memory_table::memory_revert),
+        undefined: false,
+    };
+    tbl
+}
 
-    jump_table[OpCode::SIGNEXTEND as usize] = Some(Operation {
-        execute: instructions::op_sign_extend,
-        constant_gas: gas::GasMidStep,
-        min_stack: stack_table::min_stack(2, 1),
-        max_stack: stack_table::max_stack(2, 1),
-    });
+// EIP 158 a.k.a Spurious Dragon
+fn new_spurious_dragon_instruction_set() -> JumpTable {
+    let mut tbl = new_tangerine_whistle_instruction_set();
+    tbl[EXP as usize].dynamic_gas = Some(gas_table::gas_exp_eip158);
+    tbl
+}
 
-    jump_table[OpCode::LT as usize] = Some(Operation {
-        execute: instructions::op_lt,
-        constant_gas: gas::GasFastestStep,
-        min_stack: stack_table::min_stack(2, 1),
-        max_stack: stack_table::max_stack(2, 1),
-    });
+// EIP 150 a.k.a Tangerine Whistle
+fn new_tangerine_whistle_instruction_set() -> JumpTable {
+    let mut tbl = new_homestead_instruction_set();
+    tbl[BALANCE as usize].constant_gas = params::BALANCE_GAS_EIP150;
+    tbl[EXTCODESIZE as usize].constant_gas = params::EXTCODESIZE_GAS_EIP150;
+    tbl[SLOAD as usize].constant_gas = params::SLOAD_GAS_EIP150;
+    tbl[EXTCODECOPY as usize].constant_gas = params::EXTCODECOPY_BASE_EIP150;
+    tbl[CALL as usize].constant_gas = params::CALL_GAS_EIP150;
+    tbl[CALLCODE as usize].constant_gas = params::CALL_GAS_EIP150;
+    tbl[DELEGATECALL as usize].constant_gas = params::CALL_GAS_EIP150;
+    tbl
+}
 
-    jump_table[OpCode::GT as usize] = Some(Operation {
-        execute: instructions::op_gt,
-        constant_gas: gas::GasFastestStep,
-        min_stack: stack_table::min_stack(2, 1),
-        max_stack: stack_table::max_stack(2, 1),
-    });
+// new_homestead_instruction_set returns the frontier and homestead
+// instructions that can be executed during the homestead phase.
+fn new_homestead_instruction_set() -> JumpTable {
+    let mut tbl = new_frontier_instruction_set();
+    tbl[DELEGATECALL as usize] = Operation {
+        execute: instructions::op_delegate_call,
+        constant_gas: params::CALL_GAS_FRONTIER,
+        dynamic_gas: Some(gas_table::gas_delegate_call),
+        min_stack: min_stack(6, 1),
+        max_stack: max_stack(6, 1),
+        memory_size: Some(memory_table::memory_delegate_call),
+        undefined: false,
+    };
+    tbl
+}
 
-    jump_table[OpCode::SLT as usize] = Some(Operation {
-        execute: instructions::op_slt,
-        constant_gas: gas::GasFastestStep,
-        min_stack: stack_table::min_stack(2, 1),
-        max_stack: stack_table::max_stack(2, 1),
-    });
+// new_frontier_instruction_set returns the frontier instructions
+// that can be executed during the frontier phase.
+fn new_frontier_instruction_set() -> JumpTable {
+    let mut tbl: JumpTable = [Operation::default(); 256];
 
-    jump_table[OpCode::SGT as usize] = Some(Operation {
-        execute: instructions::op_sgt,
-        constant_gas: gas::GasFastestStep,
-        min_stack: stack_table::min_stack(2, 1),
-        max_stack: stack_table::max_stack(2, 1),
-    });
+    tbl[STOP as usize] = Operation::new(instructions::op_stop, 0, 0, 0);
+    tbl[ADD as usize] = Operation::new(instructions::op_add, GAS_FASTEST_STEP, 2, 1);
+    tbl[MUL as usize] = Operation::new(instructions::op_mul, GAS_FAST_STEP, 2, 1);
+    tbl[SUB as usize] = Operation::new(instructions::op_sub, GAS_FASTEST_STEP, 2, 1);
+    tbl[DIV as usize] = Operation::new(instructions::op_div, GAS_FAST_STEP, 2, 1);
+    tbl[SDIV as usize] = Operation::new(instructions::op_sdiv, GAS_FAST_STEP, 2, 1);
+    tbl[MOD as usize] = Operation::new(instructions::op_mod, GAS_FAST_STEP, 2, 1);
+    tbl[SMOD as usize] = Operation::new(instructions::op_smod, GAS_FAST_STEP, 2, 1);
+    tbl[ADDMOD as usize] = Operation::new(instructions::op_addmod, GAS_MID_STEP, 3, 1);
+    tbl[MULMOD as usize] = Operation::new(instructions::op_mulmod, GAS_MID_STEP, 3, 1);
+    tbl[EXP as usize] = Operation {
+        dynamic_gas: Some(gas_table::gas_exp_frontier),
+        ..Operation::new(instructions::op_exp, 0, 2, 1)
+    };
+    tbl[SIGNEXTEND as usize] = Operation::new(instructions::op_sign_extend, GAS_FAST_STEP, 2, 1);
+    tbl[LT as usize] = Operation::new(instructions::op_lt, GAS_FASTEST_STEP, 2, 1);
+    tbl[GT as usize] = Operation::new(instructions::op_gt, GAS_FASTEST_STEP, 2, 1);
+    tbl[SLT as usize] = Operation::new(instructions::op_slt, GAS_FASTEST_STEP, 2, 1);
+    tbl[SGT as usize] = Operation::new(instructions::op_sgt, GAS_FASTEST_STEP, 2, 1);
+    tbl[EQ as usize] = Operation::new(instructions::op_eq, GAS_FASTEST_STEP, 2, 1);
+    tbl[ISZERO as usize] = Operation::new(instructions::op_iszero, GAS_FASTEST_STEP, 1, 1);
+    tbl[AND as usize] = Operation::new(instructions::op_and, GAS_FASTEST_STEP, 2, 1);
+    tbl[XOR as usize] = Operation::new(instructions::op_xor, GAS_FASTEST_STEP, 2, 1);
+    tbl[OR as usize] = Operation::new(instructions::op_or, GAS_FASTEST_STEP, 2, 1);
+    tbl[NOT as usize] = Operation::new(instructions::op_not, GAS_FASTEST_STEP, 1, 1);
+    tbl[BYTE as usize] = Operation::new(instructions::op_byte, GAS_FASTEST_STEP, 2, 1);
+    tbl[KECCAK256 as usize] = Operation {
+        dynamic_gas: Some(gas_table::gas_keccak256),
+        memory_size: Some(memory_table::memory_keccak256),
+        ..Operation::new(instructions::op_keccak256, params::KECCAK256_GAS, 2, 1)
+    };
+    tbl[ADDRESS as usize] = Operation::new(instructions::op_address, GAS_QUICK_STEP, 0, 1);
+    tbl[BALANCE as usize] = Operation::new(instructions::op_balance, params::BALANCE_GAS_FRONTIER, 1, 1);
+    tbl[ORIGIN as usize] = Operation::new(instructions::op_origin, GAS_QUICK_STEP, 0, 1);
+    tbl[CALLER as usize] = Operation::new(instructions::op_caller, GAS_QUICK_STEP, 0, 1);
+    tbl[CALLVALUE as usize] = Operation::new(instructions::op_call_value, GAS_QUICK_STEP, 0, 1);
+    tbl[CALLDATALOAD as usize] = Operation::new(instructions::op_call_data_load, GAS_FASTEST_STEP, 1, 1);
+    tbl[CALLDATASIZE as usize] = Operation::new(instructions::op_call_data_size, GAS_QUICK_STEP, 0, 1);
+    tbl[CALLDATACOPY as usize] = Operation {
+        dynamic_gas: Some(gas_table::gas_call_data_copy),
+        memory_size: Some(memory_table::memory_call_data_copy),
+        ..Operation::new(instructions::op_call_data_copy, GAS_FASTEST_STEP, 3, 0)
+    };
+    tbl[CODESIZE as usize] = Operation::new(instructions::op_code_size, GAS_QUICK_STEP, 0, 1);
+    tbl[CODECOPY as usize] = Operation {
+        dynamic_gas: Some(gas_table::gas_code_copy),
+        memory_size: Some(memory_table::memory_code_copy),
+        ..Operation::new(instructions::op_code_copy, GAS_FASTEST_STEP, 3, 0)
+    };
+    tbl[GASPRICE as usize] = Operation::new(instructions::op_gasprice, GAS_QUICK_STEP, 0, 1);
+    tbl[EXTCODESIZE as usize] =
+        Operation::new(instructions::op_ext_code_size, params::EXTCODESIZE_GAS_FRONTIER, 1, 1);
+    tbl[EXTCODECOPY as usize] = Operation {
+        dynamic_gas: Some(gas_table::gas_ext_code_copy),
+        memory_size: Some(memory_table::memory_ext_code_copy),
+        ..Operation::new(instructions::op_ext_code_copy, params::EXTCODECOPY_BASE_FRONTIER, 4, 0)
+    };
+    tbl[BLOCKHASH as usize] = Operation::new(instructions::op_blockhash, GAS_EXT_STEP, 1, 1);
+    tbl[COINBASE as usize] = Operation::new(instructions::op_coinbase, GAS_QUICK_STEP, 0, 1);
+    tbl[TIMESTAMP as usize] = Operation::new(instructions::op_timestamp, GAS_QUICK_STEP, 0, 1);
+    tbl[NUMBER as usize] = Operation::new(instructions::op_number, GAS_QUICK_STEP, 0, 1);
+    tbl[DIFFICULTY as usize] = Operation::new(instructions::op_difficulty, GAS_QUICK_STEP, 0, 1);
+    tbl[GASLIMIT as usize] = Operation::new(instructions::op_gaslimit, GAS_QUICK_STEP, 0, 1);
+    tbl[POP as usize] = Operation::new(instructions::op_pop, GAS_QUICK_STEP, 1, 0);
+    tbl[MLOAD as usize] = Operation {
+        dynamic_gas: Some(gas_table::GAS_MLOAD),
+        memory_size: Some(memory_table::memory_mload),
+        ..Operation::new(instructions::op_mload, GAS_FASTEST_STEP, 1, 1)
+    };
+    tbl[MSTORE as usize] = Operation {
+        dynamic_gas: Some(gas_table::GAS_MSTORE),
+        memory_size: Some(memory_table::memory_mstore),
+        ..Operation::new(instructions::op_mstore, GAS_FASTEST_STEP, 2, 0)
+    };
+    tbl[MSTORE8 as usize] = Operation {
+        dynamic_gas: Some(gas_table::GAS_MSTORE8),
+        memory_size: Some(memory_table::memory_mstore8),
+        ..Operation::new(instructions::op_mstore8, GAS_FASTEST_STEP, 2, 0)
+    };
+    tbl[SLOAD as usize] = Operation::new(instructions::op_sload, params::SLOAD_GAS_FRONTIER, 1, 1);
+    tbl[SSTORE as usize] = Operation {
+        dynamic_gas: Some(gas_table::gas_sstore),
+        ..Operation::new(instructions::op_sstore, 0, 2, 0)
+    };
+    tbl[JUMP as usize] = Operation::new(instructions::op_jump, GAS_MID_STEP, 1, 0);
+    tbl[JUMPI as usize] = Operation::new(instructions::op_jumpi, GAS_SLOW_STEP, 2, 0);
+    tbl[PC as usize] = Operation::new(instructions::op_pc, GAS_QUICK_STEP, 0, 1);
+    tbl[MSIZE as usize] = Operation::new(instructions::op_msize, GAS_QUICK_STEP, 0, 1);
+    tbl[GAS as usize] = Operation::new(instructions::op_gas, GAS_QUICK_STEP, 0, 1);
+    tbl[JUMPDEST as usize] = Operation::new(instructions::op_jumpdest, params::JUMPDEST_GAS, 0, 0);
 
-    jump_table[OpCode::EQ as usize] = Some(Operation {
-        execute: instructions::op_eq,
-        constant_gas: gas::GasFastestStep,
-        min_stack: stack_table::min_stack(2, 1),
-        max_stack: stack_table::max_stack(2, 1),
-    });
+    tbl[PUSH1 as usize] = Operation::new(instructions::op_push1, GAS_FASTEST_STEP, 0, 1);
+    tbl[PUSH2 as usize] = Operation::new(instructions::op_push::<2>, GAS_FASTEST_STEP, 0, 1);
+    tbl[PUSH3 as usize] = Operation::new(instructions::op_push::<3>, GAS_FASTEST_STEP, 0, 1);
+    tbl[PUSH4 as usize] = Operation::new(instructions::op_push::<4>, GAS_FASTEST_STEP, 0, 1);
+    tbl[PUSH5 as usize] = Operation::new(instructions::op_push::<5>, GAS_FASTEST_STEP, 0, 1);
+    tbl[PUSH6 as usize] = Operation::new(instructions::op_push::<6>, GAS_FASTEST_STEP, 0, 1);
+    tbl[PUSH7 as usize] = Operation::new(instructions::op_push::<7>, GAS_FASTEST_STEP, 0, 1);
+    tbl[PUSH8 as usize] = Operation::new(instructions::op_push::<8>, GAS_FASTEST_STEP, 0, 1);
+    tbl[PUSH9 as usize] = Operation::new(instructions::op_push::<9>, GAS_FASTEST_STEP, 0, 1);
+    tbl[PUSH10 as usize] = Operation::new(instructions::op_push::<10>, GAS_FASTEST_STEP, 0, 1);
+    tbl[PUSH11 as usize] = Operation::new(instructions::op_push::<11>, GAS_FASTEST_STEP, 0, 1);
+    tbl[PUSH12 as usize] = Operation::new(instructions::op_push::<12>, GAS_FASTEST_STEP, 0, 1);
+    tbl[PUSH13 as usize] = Operation::new(instructions::op_push::<13>, GAS_FASTEST_STEP, 0, 1);
+    tbl[PUSH14 as usize] = Operation::new(instructions::op_push::<14>, GAS_FASTEST_STEP, 0, 1);
+    tbl[PUSH15 as usize] = Operation::new(instructions::op_push::<15>, GAS_FASTEST_STEP, 0, 1);
+    tbl[PUSH16 as usize] = Operation::new(instructions::op_push::<16>, GAS_FASTEST_STEP, 0, 1);
+    tbl[PUSH17 as usize] = Operation::new(instructions::op_push::<17>, GAS_FASTEST_STEP, 0, 1);
+    tbl[PUSH18 as usize] = Operation::new(instructions::op_push::<18>, GAS_FASTEST_STEP, 0, 1);
+    tbl[PUSH19 as usize] = Operation::new(instructions::op_push::<19>, GAS_FASTEST_STEP, 0, 1);
+    tbl[PUSH20 as usize] = Operation::new(instructions::op_push::<20>, GAS_FASTEST_STEP, 0, 1);
+    tbl[PUSH21 as usize] = Operation::new(instructions::op_push::<21>, GAS_FASTEST_STEP, 0, 1);
+    tbl[PUSH22 as usize] = Operation::new(instructions::op_push::<22>, GAS_FASTEST_STEP, 0, 1);
+    tbl[PUSH23 as usize] = Operation::new(instructions::op_push::<23>, GAS_FASTEST_STEP, 0, 1);
+    tbl[PUSH24 as usize] = Operation::new(instructions::op_push::<24>, GAS_FASTEST_STEP, 0, 1);
+    tbl[PUSH25 as usize] = Operation::new(instructions::op_push::<25>, GAS_FASTEST_STEP, 0, 1);
+    tbl[PUSH26 as usize] = Operation::new(instructions::op_push::<26>, GAS_FASTEST_STEP, 0, 1);
+    tbl[PUSH27 as usize] = Operation::new(instructions::op_push::<27>, GAS_FASTEST_STEP, 0, 1);
+    tbl[PUSH28 as usize] = Operation::new(instructions::op_push::<28>, GAS_FASTEST_STEP, 0, 1);
+    tbl[PUSH29 as usize] = Operation::new(instructions::op_push::<29>, GAS_FASTEST_STEP, 0, 1);
+    tbl[PUSH30 as usize] = Operation::new(instructions::op_push::<30>, GAS_FASTEST_STEP, 0, 1);
+    tbl[PUSH31 as usize] = Operation::new(instructions::op_push::<31>, GAS_FASTEST_STEP, 0, 1);
+    tbl[PUSH32 as usize] = Operation::new(instructions::op_push::<32>, GAS_FASTEST_STEP, 0, 1);
 
-    jump_table[OpCode::ISZERO as usize] = Some(Operation {
-        execute: instructions::op_iszero,
-        constant_gas: gas::GasFastestStep,
-        min_stack: stack_table::min_stack(1, 1),
-        max_stack: stack_table::max_stack(1, 1),
-    });
+    tbl[DUP1 as usize] = dup(instructions::op_dup::<1>, 1);
+    tbl[DUP2 as usize] = dup(instructions::op_dup::<2>, 2);
+    tbl[DUP3 as usize] = dup(instructions::op_dup::<3>, 3);
+    tbl[DUP4 as usize] = dup(instructions::op_dup::<4>, 4);
+    tbl[DUP5 as usize] = dup(instructions::op_dup::<5>, 5);
+    tbl[DUP6 as usize] = dup(instructions::op_dup::<6>, 6);
+    tbl[DUP7 as usize] = dup(instructions::op_dup::<7>, 7);
+    tbl[DUP8 as usize] = dup(instructions::op_dup::<8>, 8);
+    tbl[DUP9 as usize] = dup(instructions::op_dup::<9>, 9);
+    tbl[DUP10 as usize] = dup(instructions::op_dup::<10>, 10);
+    tbl[DUP11 as usize] = dup(instructions::op_dup::<11>, 11);
+    tbl[DUP12 as usize] = dup(instructions::op_dup::<12>, 12);
+    tbl[DUP13 as usize] = dup(instructions::op_dup::<13>, 13);
+    tbl[DUP14 as usize] = dup(instructions::op_dup::<14>, 14);
+    tbl[DUP15 as usize] = dup(instructions::op_dup::<15>, 15);
+    tbl[DUP16 as usize] = dup(instructions::op_dup::<16>, 16);
 
-    jump_table[OpCode::AND as usize] = Some(Operation {
-        execute: instructions::op_and,
-        constant_gas: gas::GasFastestStep,
-        min_stack: stack_table::min_stack(2, 1),
-        max_stack: stack_table::max_stack(2, 1),
-    });
-    jump_table[OpCode::XOR as usize] = Some(Operation {
-        execute: instructions::op_xor,
-        constant_gas: gas::GasFastestStep,
-        min_stack: stack_table::min_stack(2, 1),
-        max_stack: stack_table::max_stack(2, 1),
-    });
-    jump_table[OpCode::OR as usize] = Some(Operation {
-        execute: instructions::op_or,
-        constant_gas: gas::GasFastestStep,
-        min_stack: stack_table::min_stack(2, 1),
-        max_stack: stack_table::max_stack(2, 1),
-    });
-    jump_table[OpCode::NOT as usize] = Some(Operation {
-        execute: instructions::op_not,
-        constant_gas: gas::GasFastestStep,
-        min_stack: stack_table::min_stack(1, 1),
-        max_stack: stack_table::max_stack(1, 1),
-    });
-    jump_table[OpCode::BYTE as usize] = Some(Operation {
-        execute: instructions::op_byte,
-        constant_gas: gas::GasFastestStep,
-        min_stack: stack_table::min_stack(2, 1),
-        max_stack: stack_table::max_stack(2, 1),
-    });
-    jump_table[OpCode::KECCAK256 as usize] = Some(Operation {
-        execute: instructions::op_keccak256,
-        constant_gas: params.Keccak256Gas,
-        dynamic_gas: gasKeccak256,
-        min_stack: stack_table::min_stack(2, 1),
-        max_stack: stack_table::max_stack(2, 1),
-        memory_size: memoryKeccak256,
-    });
+    tbl[SWAP1 as usize] = swap(instructions::op_swap::<1>, 1);
+    tbl[SWAP2 as usize] = swap(instructions::op_swap::<2>, 2);
+    tbl[SWAP3 as usize] = swap(instructions::op_swap::<3>, 3);
+    tbl[SWAP4 as usize] = swap(instructions::op_swap::<4>, 4);
+    tbl[SWAP5 as usize] = swap(instructions::op_swap::<5>, 5);
+    tbl[SWAP6 as usize] = swap(instructions::op_swap::<6>, 6);
+    tbl[SWAP7 as usize] = swap(instructions::op_swap::<7>, 7);
+    tbl[SWAP8 as usize] = swap(instructions::op_swap::<8>, 8);
+    tbl[SWAP9 as usize] = swap(instructions::op_swap::<9>, 9);
+    tbl[SWAP10 as usize] = swap(instructions::op_swap::<10>, 10);
+    tbl[SWAP11 as usize] = swap(instructions::op_swap::<11>, 11);
+    tbl[SWAP12 as usize] = swap(instructions::op_swap::<12>, 12);
+    tbl[SWAP13 as usize] = swap(instructions::op_swap::<13>, 13);
+    tbl[SWAP14 as usize] = swap(instructions::op_swap::<14>, 14);
+    tbl[SWAP15 as usize] = swap(instructions::op_swap::<15>, 15);
+    tbl[SWAP16 as usize] = swap(instructions::op_swap::<16>, 16);
 
-    jump_table[OpCode: ADDRESS as usize] = Some(Operation {
-        execute: instructions::op_address,
-        constant_gas: gas::GasQuickStep,
-        min_stack: stack_table::min_stack(0, 1),
-        max_stack: stack_table::max_stack(0, 1),
-    });
+    tbl[LOG0 as usize] = log(instructions::op_log::<0>, gas_table::gas_log0, 0);
+    tbl[LOG1 as usize] = log(instructions::op_log::<1>, gas_table::gas_log1, 1);
+    tbl[LOG2 as usize] = log(instructions::op_log::<2>, gas_table::gas_log2, 2);
+    tbl[LOG3 as usize] = log(instructions::op_log::<3>, gas_table::gas_log3, 3);
+    tbl[LOG4 as usize] = log(instructions::op_log::<4>, gas_table::gas_log4, 4);
 
-    jump_table[OpCode: BALANCE as usize] = Some(Operation {
-        execute: instructions::op_balance,
-        constant_gas: params.BalanceGasFrontier,
-        min_stack: stack_table::min_stack(1, 1),
-        max_stack: stack_table::max_stack(1, 1),
-    });
-    jump_table[OpCode: ORIGIN as usize] = Some(Operation {
-        execute: instructions::op_origin,
-        constant_gas: gas::GasQuickStep,
-        min_stack: stack_table::min_stack(0, 1),
-        max_stack: stack_table::max_stack(0, 1),
-    });
-    jump_table[OpCode: CALLER as usize] = Some(Operation {
-        execute: instructions::op_caller,
-        constant_gas: gas::GasQuickStep,
-        min_stack: stack_table::min_stack(0, 1),
-        max_stack: stack_table::max_stack(0, 1),
-    });
-    jump_table[OpCode: CALLVALUE as usize] = Some(Operation {
-        execute: instructions::op_call_value,
-        constant_gas: gas::GasQuickStep,
-        min_stack: stack_table::min_stack(0, 1),
-        max_stack: stack_table::max_stack(0, 1),
-    });
-    jump_table[OpCode: CALLDATALOAD as usize] = Some(Operation {
-        execute: instructions::op_call_data_load,
-        constant_gas: gas::GasFastestStep,
-        min_stack: stack_table::min_stack(1, 1),
-        max_stack: stack_table::max_stack(1, 1),
-    });
-    jump_table[OpCode: CALLDATASIZE as usize] = Some(Operation {
-        execute: instructions::op_call_data_size,
-        constant_gas: gas::GasQuickStep,
-        min_stack: stack_table::min_stack(0, 1),
-        max_stack: stack_table::max_stack(0, 1),
-    });
-    jump_table[OpCode: CALLDATACOPY as usize] = Some(Operation {
-        execute: instructions::op_call_data_copy,
-        constant_gas: gas::GasFastestStep,
-        dynamic_gas: gasCallDataCopy,
-        min_stack: stack_table::min_stack(3, 0),
-        max_stack: stack_table::max_stack(3, 0),
-        memory_size: memoryCallDataCopy,
-    });
-    jump_table[OpCode: CODESIZE as usize] = Some(Operation {
-        execute: instructions::op_code_size,
-        constant_gas: gas::GasQuickStep,
-        min_stack: stack_table::min_stack(0, 1),
-        max_stack: stack_table::max_stack(0, 1),
-    });
-    jump_table[OpCode: CODECOPY as usize] = Some(Operation {
-        execute: instructions::op_code_copy,
-        constant_gas: gas::GasFastestStep,
-        dynamic_gas: gasCodeCopy,
-        min_stack: stack_table::min_stack(3, 0),
-        max_stack: stack_table::max_stack(3, 0),
-        memory_size: memoryCodeCopy,
-    });
-    jump_table[OpCode: GASPRICE as usize] = Some(Operation {
-        execute: instructions::op_gasprice,
-        constant_gas: gas::GasQuickStep,
-        min_stack: stack_table::min_stack(0, 1),
-        max_stack: stack_table::max_stack(0, 1),
-    });
-    jump_table[OpCode: CODESIZE as usize] = Some(Operation {
-        execute: instructions::op_code_size,
-        constant_gas: params.ExtcodeSizeGasFrontier,
-        min_stack: stack_table::min_stack(1, 1),
-        max_stack: stack_table::max_stack(1, 1),
-    });
-    // duplicate
-    jump_table[OpCode: CODECOPY as usize] = Some(Operation {
-        execute: instructions::op_code_copy,
-        constant_gas: params.ExtcodeCopyBaseFrontier,
-        dynamic_gas: gasExtCodeCopy,
-        min_stack: stack_table::min_stack(4, 0),
-        max_stack: stack_table::max_stack(4, 0),
-        memory_size: memoryExtCodeCopy,
-    });
-    jump_table[OpCode: BLOCKHASH as usize] = Some(Operation {
-        execute: instructions::op_blockhash,
-        constant_gas: gas::GasExtStep,
-        min_stack: stack_table::min_stack(1, 1),
-        max_stack: stack_table::max_stack(1, 1),
-    });
-    jump_table[OpCode: COINBASE as usize] = Some(Operation {
-        execute: instructions::coinbase,
-        constant_gas: gas::GasQuickStep,
-        min_stack: stack_table::min_stack(0, 1),
-        max_stack: stack_table::max_stack(0, 1),
-    });
-    jump_table[OpCode: TIMESTAMP as usize] = Some(Operation {
-        execute: instructions::op_timestamp,
-        constant_gas: gas::GasQuickStep,
-        min_stack: stack_table::min_stack(0, 1),
-        max_stack: stack_table::max_stack(0, 1),
-    });
-    jump_table[OpCode: NUMBER as usize] = Some(Operation {
-        execute: instructions::op_number,
-        constant_gas: gas::GasQuickStep,
-        min_stack: stack_table::min_stack(0, 1),
-        max_stack: stack_table::max_stack(0, 1),
-    });
-    jump_table[OpCode: DIFFICULTY as usize] = Some(Operation {
-        execute: instructions::op_difficulty,
-        constant_gas: gas::GasQuickStep,
-        min_stack: stack_table::min_stack(0, 1),
-        max_stack: stack_table::max_stack(0, 1),
-    });
-    jump_table[OpCode: GASLIMIT as usize] = Some(Operation {
-        execute: instructions::op_gaslimit,
-        constant_gas: gas::GasQuickStep,
-        min_stack: stack_table::min_stack(0, 1),
-        max_stack: stack_table::max_stack(0, 1),
-    });
-    jump_table[OpCode: POP as usize] = Some(Operation {
-        execute: instructions::op_pop,
-        constant_gas: gas::GasQuickStep,
-        min_stack: stack_table::min_stack(1, 0),
-        max_stack: stack_table::max_stack(1, 0),
-    });
-    jump_table[OpCode: MLOAD as usize] = Some(Operation {
-        execute: instructions::op_mload,
-        constant_gas: gas::GasFastestStep,
-        dynamic_gas: gasMLoad,
-        min_stack: stack_table::min_stack(1, 1),
-        max_stack: stack_table::max_stack(1, 1),
-        memory_size: memoryMLoad,
-    });
-    jump_table[OpCode: MSTORE as usize] = Some(Operation {
-        execute: instructions::op_mstore,
-        constant_gas: gas::GasFastestStep,
-        dynamic_gas: gasMStore,
-        min_stack: stack_table::min_stack(2, 0),
-        max_stack: stack_table::max_stack(2, 0),
-        memory_size: memoryMStore,
-    });
-    jump_table[OpCode: MSTORE8 as usize] = Some(Operation {
-        execute: instructions::op_mstore8,
-        constant_gas: gas::GasFastestStep,
-        dynamic_gas: gasMStore8,
-        memory_size: memoryMStore8,
-        min_stack: stack_table::min_stack(2, 0),
-        max_stack: stack_table::max_stack(2, 0),
-    });
-    jump_table[OpCode: SLOAD as usize] = Some(Operation {
-        execute: instructions::op_sload,
-        constant_gas: params.SloadGasFrontier,
-        min_stack: stack_table::min_stack(1, 1),
-        max_stack: stack_table::max_stack(1, 1),
-    });
-    jump_table[OpCode: SSTORE as usize] = Some(Operation {
-        execute: instructions::op_sstore,
-        dynamic_gas: gasSStore,
-        min_stack: stack_table::min_stack(2, 0),
-        max_stack: stack_table::max_stack(2, 0),
-    });
-    jump_table[OpCode: JUMP as usize] = Some(Operation {
-        execute: instructions::op_jump,
-        constant_gas: gas::GasMidStep,
-        min_stack: stack_table::min_stack(1, 0),
-        max_stack: stack_table::max_stack(1, 0),
-    });
-    jump_table[OpCode: JUMPI as usize] = Some(Operation {
-        execute: instructions::op_jumpi,
-        constant_gas: gas::GasSlowStep,
-        min_stack: stack_table::min_stack(2, 0),
-        max_stack: stack_table::max_stack(2, 0),
-    });
-    jump_table[OpCode: PC as usize] = Some(Operation {
-        execute: instructions::op_pc,
-        constant_gas: gas::GasQuickStep,
-        min_stack: stack_table::min_stack(0, 1),
-        max_stack: stack_table::max_stack(0, 1),
-    });
-    jump_table[OpCode: MSIZE as usize] = Some(Operation {
-        execute: instructions::op_msize,
-        constant_gas: gas::GasQuickStep,
-        min_stack: stack_table::min_stack(0, 1),
-        max_stack: stack_table::max_stack(0, 1),
-    });
-    jump_table[OpCode: GAS as usize] = Some(Operation {
-        execute: instructions::op_gas,
-        constant_gas: gas::GasQuickStep,
-        min_stack: stack_table::min_stack(0, 1),
-        max_stack: stack_table::max_stack(0, 1),
-    });
-    jump_table[OpCode: JUMPDEST as usize] = Some(Operation {
-        execute: instructions::op_jumpdest,
-        constant_gas: params.JumpdestGas,
-        min_stack: stack_table::min_stack(0, 0),
-        max_stack: stack_table::max_stack(0, 0),
-    });
-    let push_opcodes = [
-        Opcode::PUSH1,
-        Opcode::PUSH2,
-        Opcode::PUSH3,
-        Opcode::PUSH4,
-        Opcode::PUSH5,
-        Opcode::PUSH6,
-        Opcode::PUSH7,
-        Opcode::PUSH8,
-        Opcode::PUSH9,
-        Opcode::PUSH10,
-        Opcode::PUSH11,
-        Opcode::PUSH12,
-        Opcode::PUSH13,
-        Opcode::PUSH14,
-        Opcode::PUSH15,
-        Opcode::PUSH16,
-        Opcode::PUSH17,
-        Opcode::PUSH18,
-        Opcode::PUSH19,
-        Opcode::PUSH20,
-        Opcode::PUSH21,
-        Opcode::PUSH22,
-        Opcode::PUSH23,
-        Opcode::PUSH24,
-        Opcode::PUSH25,
-        Opcode::PUSH26,
-        Opcode::PUSH27,
-        Opcode::PUSH28,
-        Opcode::PUSH29,
-        Opcode::PUSH30,
-        Opcode::PUSH31,
-        Opcode::PUSH32,
-    ];
+    tbl[CREATE as usize] = Operation {
+        dynamic_gas: Some(gas_table::GAS_CREATE),
+        memory_size: Some(memory_table::memory_create),
+        ..Operation::new(instructions::op_create, params::CREATE_GAS, 3, 1)
+    };
+    tbl[CALL as usize] = Operation {
+        dynamic_gas: Some(gas_table::gas_call),
+        memory_size: Some(memory_table::memory_call),
+        ..Operation::new(instructions::op_call, params::CALL_GAS_FRONTIER, 7, 1)
+    };
+    tbl[CALLCODE as usize] = Operation {
+        dynamic_gas: Some(gas_table::gas_call_code),
+        memory_size: Some(memory_table::memory_call),
+        ..Operation::new(instructions::op_call_code, params::CALL_GAS_FRONTIER, 7, 1)
+    };
+    tbl[RETURN as usize] = Operation {
+        dynamic_gas: Some(gas_table::GAS_RETURN),
+        memory_size: Some(memory_table::memory_return),
+        ..Operation::new(instructions::op_return, 0, 2, 0)
+    };
+    tbl[SELFDESTRUCT as usize] = Operation {
+        dynamic_gas: Some(gas_table::gas_selfdestruct),
+        ..Operation::new(instructions::op_selfdestruct, 0, 1, 0)
+    };
+    tbl
+}
 
-    for (index, opcode) in push_opcodes.iter().enumerate().take(32) {
-        jump_table[opcode] = Some(Operation {
-            execute: instructions::make_push(index + 1, index + 1),
-            constant_gas: gas::GasFastestStep,
-            min_stack: stack_table::min_stack(0, 1),
-            max_stack: stack_table::max_stack(0, 1),
-        });
-    }
-    let dup_opcodes = [
-        Opcode::DUP1,
-        Opcode::DUP2,
-        Opcode::DUP3,
-        Opcode::DUP4,
-        Opcode::DUP5,
-        Opcode::DUP6,
-        Opcode::DUP7,
-        Opcode::DUP8,
-        Opcode::DUP8,
-        Opcode::DUP9,
-        Opcode::DUP10,
-        Opcode::DUP11,
-        Opcode::DUP12,
-        Opcode::DUP13,
-        Opcode::DUP14,
-        Opcode::DUP15,
-        Opcode::DUP16,
-    ];
-    for (index, opcode) in dup_opcodes.iter().enumerate().take(16) {
-        jump_table[opcode] = Some(Operation {
-            execute: instructions::make_dup(index + 1),
-            constant_gas: gas::GasFastestStep,
-            min_stack: stack_table::minDupStack(index + 1),
-            max_stack: stack_table::maxDupStack(index + 1),
-        });
-    }
-    let swap_opcodes = [
-        Opcode::SWAP1,
-        Opcode::SWAP2,
-        Opcode::SWAP3,
-        Opcode::SWAP4,
-        Opcode::SWAP5,
-        Opcode::SWAP6,
-        Opcode::SWAP7,
-        Opcode::SWAP8,
-        Opcode::SWAP8,
-        Opcode::SWAP9,
-        Opcode::SWAP10,
-        Opcode::SWAP11,
-        Opcode::SWAP12,
-        Opcode::SWAP13,
-        Opcode::SWAP14,
-        Opcode::SWAP15,
-        Opcode::SWAP16,
-    ];
-    for (index, opcode) in swap_opcodes.iter().enumerate().take(16) {
-        jump_table[opcode] = Some(Operation {
-            execute: instructions::make_swap(index + 1),
-            constant_gas: gas::GasFastestStep,
-            min_stack: stack_table::minSwapStack(index + 1),
-            max_stack: stack_table::maxSwapStack(index + 1),
-        });
+fn dup(execute: ExecutionFunc, n: usize) -> Operation {
+    Operation {
+        execute,
+        constant_gas: GAS_FASTEST_STEP,
+        min_stack: min_dup_stack(n),
+        max_stack: max_dup_stack(n),
+        undefined: false,
+        ..Default::default()
     }
-    jump_table[OpCode: LOG0 as usize] = Some(Operation {
-        execute: instructions::makeLog(0),
-        dynamic_gas: instructions::makeGasLog(0),
-        min_stack: stack_table::min_stack(2, 0),
-        max_stack: stack_table::max_stack(2, 0),
-        memory_size: memoryLog,
-    });
-    jump_table[OpCode: LOG1 as usize] = Some(Operation {
-        execute: instructions::makeLog(1),
-        dynamic_gas: instructions::makeGasLog(1),
-        min_stack: stack_table::min_stack(3, 0),
-        max_stack: stack_table::max_stack(3, 0),
-        memory_size: memoryLog,
-    });
-    jump_table[OpCode: LOG2 as usize] = Some(Operation {
-        execute: instructions::makeLog(2),
-        dynamic_gas: instructions::makeGasLog(2),
-        min_stack: stack_table::min_stack(4, 0),
-        max_stack: stack_table::max_stack(4, 0),
-        memory_size: memoryLog,
-    });
-    jump_table[OpCode: LOG3 as usize] = Some(Operation {
-        execute: instructions::makeLog(3),
-        dynamic_gas: instructions::makeGasLog(3),
-        min_stack: stack_table::min_stack(5, 0),
-        max_stack: stack_table::max_stack(5, 0),
-        memory_size: memoryLog,
-    });
-    jump_table[OpCode: LOG4 as usize] = Some(Operation {
-        execute: instructions::makeLog(4),
-        dynamic_gas: instructions::makeGasLog(4),
-        min_stack: stack_table::min_stack(6, 0),
-        max_stack: stack_table::max_stack(6, 0),
-        memory_size: memoryLog,
-    });
-    jump_table[OpCode: CREATE as usize] = Some(Operation {
-        execute: instructions::op_create,
-        constant_gas: params.CreateGas,
-        dynamic_gas: gasCreate,
-        min_stack: stack_table::min_stack(3, 1),
-        max_stack: stack_table::max_stack(3, 1),
-        memory_size: memoryCreate,
-    });
-    jump_table[OpCode: CALL as usize] = Some(Operation {
-        execute: instructions::op_call,
-        constant_gas: params.CallGasFrontier,
-        dynamic_gas: gasCall,
-        min_stack: stack_table::min_stack(7, 1),
-        max_stack: stack_table::max_stack(7, 1),
-        memory_size: memoryCall,
-    });
-    jump_table[OpCode: CALLCODE as usize] = Some(Operation {
-        execute: instructions::op_call_code,
-        constant_gas: params.CallGasFrontier,
-        dynamic_gas: gasCallCode,
-        min_stack: stack_table::min_stack(7, 1),
-        max_stack: stack_table::max_stack(7, 1),
-        memory_size: memory_table::memory_call,
-    });
-    jump_table[OpCode: RETURN as usize] = Some(Operation {
-        execute: instructions::op_return,
-        dynamic_gas: gas_table::gas_return,
-        min_stack: stack_table::min_stack(2, 0),
-        max_stack: stack_table::max_stack(2, 0),
-        memory_size: memory_table::memory_return,
-    });
-    jump_table[OpCode: SELFDESTRUCT as usize] = Some(Operation {
-        execute: instructions::op_selfdestruct,
-        dynamic_gas: gas_table::gasSelfdestruct,
-        min_stack: stack_table::min_stack(1, 0),
-        max_stack: stack_table::max_stack(1, 0),
-    });
+}
 
-    // Fill all unassigned slots with opUndefined.
-    for (i, entry) in tbl.iter_mut().enumerate() {
-        if entry.is_none() {
-            *entry = Some(Operation {
-                execute: instructions::op_undefined,
-                max_stack: stack_table::max_stack(0, 0),
-            });
-        }
+fn swap(execute: ExecutionFunc, n: usize) -> Operation {
+    Operation {
+        execute,
+        constant_gas: GAS_FASTEST_STEP,
+        min_stack: min_swap_stack(n + 1),
+        max_stack: max_swap_stack(n + 1),
+        undefined: false,
+        ..Default::default()
     }
-
-    validate(tbl)
 }
 
-type JumpTable = [Option<ExecutionFunc>; OpCode::OpCodeCount];
-
-impl JumpTable {
-    fn new() -> Self {
-        deafult_instruction_set()
+fn log(execute: ExecutionFunc, dynamic_gas: GasFunc, n: usize) -> Operation {
+    Operation {
+        execute,
+        constant_gas: 0,
+        dynamic_gas: Some(dynamic_gas),
+        min_stack: min_stack(n + 2, 0),
+        max_stack: max_stack(n + 2, 0),
+        memory_size: Some(memory_table::memory_log),
+        undefined: false,
     }
 }
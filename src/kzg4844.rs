@@ -0,0 +1,77 @@
+use c_kzg::{Bytes32, Bytes48, KzgProof, KzgSettings, BYTES_PER_G1_POINT, BYTES_PER_G2_POINT};
+
+// TRUSTED_SETUP is the output of the Ethereum KZG ceremony, bundled in the
+// format used by the c-kzg reference implementation: the number of G1 points,
+// the number of G2 points, followed by one hex encoded point per line.
+const TRUSTED_SETUP: &str = include_str!("trusted_setup.txt");
+
+pub const BLOB_COMMITMENT_VERSION_KZG: u8 = 0x01;
+pub const FIELD_ELEMENTS_PER_BLOB: u64 = 4096;
+
+// BLS_MODULUS is the order of the BLS12-381 scalar field, big endian.
+pub const BLS_MODULUS: [u8; 32] = [
+    0x73, 0xed, 0xa7, 0x53, 0x29, 0x9d, 0x7d, 0x48, 0x33, 0x39, 0xd8, 0x08, 0x09, 0xa1, 0xd8, 0x05,
+    0x53, 0xbd, 0xa4, 0x02, 0xff, 0xfe, 0x5b, 0xfe, 0xff, 0xff, 0xff, 0xff, 0x00, 0x00, 0x00, 0x01,
+];
+
+lazy_static::lazy_static! {
+    static ref KZG_SETTINGS: KzgSettings = load_trusted_setup(TRUSTED_SETUP);
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KzgError {
+    InvalidEncoding,
+    InvalidProof,
+}
+
+fn parse_points<const N: usize>(lines: &mut std::str::Lines, count: usize) -> Vec<[u8; N]> {
+    let mut points = Vec::with_capacity(count);
+    for _ in 0..count {
+        let line = lines.next().expect("trusted setup: missing point");
+        let bytes = hex::decode(line.trim()).expect("trusted setup: invalid hex");
+        let mut point = [0u8; N];
+        point.copy_from_slice(&bytes);
+        points.push(point);
+    }
+    points
+}
+
+// load_trusted_setup parses the bundled trusted setup. The setup is part of
+// the binary, so a malformed file is a build defect and panics.
+fn load_trusted_setup(setup: &str) -> KzgSettings {
+    let mut lines = setup.lines();
+    let g1_count: usize = lines.next().unwrap().trim().parse().unwrap();
+    let g2_count: usize = lines.next().unwrap().trim().parse().unwrap();
+
+    let g1 = parse_points::<BYTES_PER_G1_POINT>(&mut lines, g1_count);
+    let g2 = parse_points::<BYTES_PER_G2_POINT>(&mut lines, g2_count);
+    KzgSettings::load_trusted_setup(&g1, &g2).expect("trusted setup: invalid points")
+}
+
+// calc_blob_hash_v1 calculates the 'versioned blob hash' of a commitment.
+pub fn calc_blob_hash_v1(commitment: &[u8; 48]) -> common::Hash {
+    let mut hash = [0u8; 32];
+    hash.copy_from_slice(&crypto_hash::digest(crypto_hash::Algorithm::SHA256, commitment));
+    hash[0] = BLOB_COMMITMENT_VERSION_KZG;
+    common::Hash(hash)
+}
+
+// verify_proof verifies the KZG proof that the polynomial represented by the
+// commitment evaluates to claim at point.
+pub fn verify_proof(
+    commitment: &[u8; 48],
+    point: &[u8; 32],
+    claim: &[u8; 32],
+    proof: &[u8; 48],
+) -> Result<(), KzgError> {
+    let commitment = Bytes48::from_bytes(commitment).map_err(|_| KzgError::InvalidEncoding)?;
+    let point = Bytes32::from_bytes(point).map_err(|_| KzgError::InvalidEncoding)?;
+    let claim = Bytes32::from_bytes(claim).map_err(|_| KzgError::InvalidEncoding)?;
+    let proof = Bytes48::from_bytes(proof).map_err(|_| KzgError::InvalidEncoding)?;
+
+    match KzgProof::verify_kzg_proof(&commitment, &point, &claim, &proof, &KZG_SETTINGS) {
+        Ok(true) => Ok(()),
+        Ok(false) => Err(KzgError::InvalidProof),
+        Err(_) => Err(KzgError::InvalidEncoding),
+    }
+}
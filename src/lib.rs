@@ -0,0 +1,51 @@
+pub mod block;
+pub mod blockchain;
+pub mod bloom;
+pub mod bls12_381;
+pub mod chain_config;
+pub mod clique;
+pub mod consensus;
+pub mod contract;
+pub mod crypto;
+pub mod eip1559;
+pub mod eip4844;
+pub mod eips;
+pub mod errors;
+pub mod ethdb;
+pub mod evm;
+pub mod filedb;
+pub mod gas;
+pub mod gas_pool;
+pub mod gas_table;
+pub mod genesis;
+pub mod hexutil;
+pub mod instructions;
+pub mod interpreter;
+pub mod jump_table;
+pub mod kzg4844;
+pub mod log;
+pub mod memory;
+pub mod memory_state_db;
+pub mod memory_table;
+pub mod miner;
+pub mod op_code;
+pub mod params;
+pub mod pow;
+pub mod precompiles;
+pub mod proof;
+pub mod pruner;
+pub mod rawdb;
+pub mod receipt;
+pub mod rlp;
+pub mod rpc;
+pub mod secure_trie;
+pub mod snapshot;
+pub mod stack;
+pub mod stack_table;
+pub mod state_db;
+pub mod state_processor;
+pub mod state_transition;
+pub mod transaction;
+pub mod trie;
+pub mod txpool;
+pub mod types;
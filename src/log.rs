@@ -0,0 +1,70 @@
+// Package log contains the log entries emitted by the LOG opcodes.
+
+use serde::{Deserialize, Serialize};
+
+use crate::hexutil;
+use crate::rlp::{self, Decodable, DecoderError, Encodable, Rlp};
+
+// Log represents a contract log event. These events are generated by the LOG
+// opcode and stored/indexed by the node.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Log {
+    // Consensus fields:
+    // address of the contract that generated the event
+    #[serde(with = "hexutil::address")]
+    pub address: common::Address,
+    // list of topics provided by the contract.
+    #[serde(with = "hexutil::hashes")]
+    pub topics: Vec<common::Hash>,
+    // supplied by the contract, usually ABI-encoded
+    #[serde(with = "hexutil::bytes")]
+    pub data: Vec<u8>,
+
+    // Derived fields. These fields are filled in by the node
+    // but not secured by consensus.
+    // block in which the transaction was included
+    #[serde(with = "hexutil::quantity")]
+    pub block_number: u64,
+    // hash of the transaction
+    #[serde(rename = "transactionHash", with = "hexutil::hash")]
+    pub tx_hash: common::Hash,
+    // index of the transaction in the block
+    #[serde(rename = "transactionIndex", with = "hexutil::quantity")]
+    pub tx_index: u64,
+    // hash of the block in which the transaction was included
+    #[serde(with = "hexutil::hash")]
+    pub block_hash: common::Hash,
+    // index of the log in the block
+    #[serde(rename = "logIndex", with = "hexutil::quantity")]
+    pub index: u64,
+
+    // The removed field is true if this log was reverted due to a chain reorganisation.
+    // You must pay attention to this field if you receive logs through a filter query.
+    pub removed: bool,
+}
+
+// Only the consensus fields are encoded.
+impl Encodable for Log {
+    fn rlp_append(&self, out: &mut Vec<u8>) {
+        let mut payload = Vec::new();
+        self.address.rlp_append(&mut payload);
+        rlp::append_list(&self.topics, &mut payload);
+        self.data.rlp_append(&mut payload);
+        rlp::append_list_payload(&payload, out);
+    }
+}
+
+impl Decodable for Log {
+    fn decode(rlp: &Rlp) -> Result<Self, DecoderError> {
+        if rlp.item_count()? != 3 {
+            return Err(DecoderError::RlpIncorrectListLen);
+        }
+        Ok(Log {
+            address: rlp.val_at(0)?,
+            topics: rlp.list_at(1)?,
+            data: rlp.val_at(2)?,
+            ..Default::default()
+        })
+    }
+}
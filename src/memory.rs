@@ -1,64 +1,89 @@
 use uint256::Uint256;
 
+// Memory implements a simple memory model for the ethereum virtual machine.
+#[derive(Debug, Default)]
 pub struct Memory {
     store: Vec<u8>,
-    last_gas_cost: u64,
+    pub last_gas_cost: u64,
 }
 
 impl Memory {
     pub fn new() -> Self {
-        Memory {
-            store: Vec::new(),
-            last_gas_cost: 0,
-        }
+        Memory::default()
     }
+
+    // set sets offset + size to value
     pub fn set(&mut self, offset: u64, size: u64, value: &[u8]) {
+        // It's possible the offset is greater than 0 and size equals 0. This is because
+        // the calc_mem_size (memory_table.rs) will return 0 when size is zero (NO-OP)
         if size > 0 {
-            let end = offset + size;
-            if end > self.store.len() as u64 {
+            // length of store may never be less than offset + size.
+            // The store should be resized PRIOR to setting the memory
+            if offset + size > self.store.len() as u64 {
                 panic!("invalid memory: store empty");
             }
-            self.store[offset as usize..end as usize].copy_from_slice(value);
+            // Only the leading bytes of value that fit the region are copied, the rest of
+            // the region is left untouched.
+            let region = &mut self.store[offset as usize..(offset + size) as usize];
+            let n = region.len().min(value.len());
+            region[..n].copy_from_slice(&value[..n]);
         }
     }
 
+    // set32 sets the 32 bytes starting at offset to the value of val, left-padded with zeroes to
+    // 32 bytes.
     pub fn set32(&mut self, offset: u64, val: &Uint256) {
-        let end = offset + 32;
-        if end > self.store.len() as u64 {
+        // length of store may never be less than offset + size.
+        // The store should be resized PRIOR to setting the memory
+        if offset + 32 > self.store.len() as u64 {
             panic!("invalid memory: store empty");
         }
-        let b32 = val.to_bytes32();
-        self.store[offset as usize..end as usize].copy_from_slice(&b32[..]);
+        self.store[offset as usize..offset as usize + 32].copy_from_slice(&val.to_bytes32());
     }
 
+    // resize resizes the memory to size
     pub fn resize(&mut self, size: u64) {
-        if self.store.len() < size as usize {
+        if (self.store.len() as u64) < size {
             self.store.resize(size as usize, 0);
         }
     }
 
-    pub fn get_copy(&self, offset: i64, size: usize) -> Option<Vec<u8>> {
-        if size == 0 || offset < 0 || offset >= self.store.len() as i64 {
-            return None;
+    // get_copy returns offset + size as a new slice
+    pub fn get_copy(&self, offset: u64, size: u64) -> Vec<u8> {
+        if size == 0 {
+            return Vec::new();
         }
-
-        let end = (offset + size as i64).min(self.store.len() as i64);
-        Some(self.store[offset as usize..end as usize].to_vec())
+        self.store[offset as usize..(offset + size) as usize].to_vec()
     }
 
-    pub fn get_ptr(&self, offset: i64, size: usize) -> Option<&[u8]> {
-        if size == 0 || offset < 0 || offset >= self.store.len() as i64 {
-            return None;
+    // get_ptr returns the offset + size
+    pub fn get_ptr(&self, offset: u64, size: u64) -> &[u8] {
+        if size == 0 {
+            return &[];
         }
+        &self.store[offset as usize..(offset + size) as usize]
+    }
 
-        let end = (offset + size as i64).min(self.store.len() as i64);
-        Some(&self.store[offset as usize..end as usize])
+    // copy copies data from the src position slice into the dst position.
+    // The source and destination may overlap.
+    pub fn copy(&mut self, dst: u64, src: u64, len: u64) {
+        if len == 0 {
+            return;
+        }
+        self.store
+            .copy_within(src as usize..(src + len) as usize, dst as usize);
     }
 
+    // len returns the length of the backing slice
     pub fn len(&self) -> usize {
         self.store.len()
     }
 
+    pub fn is_empty(&self) -> bool {
+        self.store.is_empty()
+    }
+
+    // data returns the backing slice
     pub fn data(&self) -> &[u8] {
         &self.store
     }
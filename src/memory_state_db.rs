@@ -0,0 +1,765 @@
+// MemoryStateDB is an in-memory implementation of the StateDB interface. It
+// keeps accounts and storage in maps, records every change in a journal so
+// snapshots can be reverted, and commits the state into secure tries to
+// produce the state root.
+
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+use crate::chain_config::Rules;
+use crate::crypto;
+use crate::ethdb::{Batch, KeyValueStore};
+use crate::evm::EMPTY_CODE_HASH;
+use crate::proof::{AccountResult, StorageResult};
+use crate::rawdb;
+use crate::rlp;
+use crate::secure_trie::SecureTrie;
+use crate::snapshot::{Layer, Tree, DIFF_LAYERS};
+use crate::state_db::StateDB;
+use crate::trie::{Database, TrieError, EMPTY_ROOT_HASH};
+use crate::types;
+use uint256::Uint256;
+
+// StateAccount is the Ethereum consensus representation of accounts.
+// These objects are stored in the main account trie.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StateAccount {
+    pub nonce: u64,
+    pub balance: Uint256,
+    pub root: common::Hash, // merkle root of the storage trie
+    pub code_hash: common::Hash,
+}
+
+crate::impl_rlp_struct!(StateAccount { nonce, balance, root, code_hash });
+
+impl Default for StateAccount {
+    fn default() -> Self {
+        StateAccount {
+            nonce: 0,
+            balance: Uint256::default(),
+            root: EMPTY_ROOT_HASH,
+            code_hash: EMPTY_CODE_HASH,
+        }
+    }
+}
+
+impl StateAccount {
+    // empty returns whether the account is considered empty (EIP-161).
+    pub fn empty(&self) -> bool {
+        self.nonce == 0 && self.balance.is_zero() && self.code_hash == EMPTY_CODE_HASH
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+struct StateObject {
+    account: StateAccount,
+    // storage holds the slots written by the current transaction.
+    storage: HashMap<common::Hash, common::Hash>,
+    // committed_storage holds the slots written by earlier transactions that
+    // are not yet committed to the storage trie.
+    committed_storage: HashMap<common::Hash, common::Hash>,
+    suicided: bool,
+    // created is set if the account was (re)created since the last commit,
+    // wiping any storage it had before.
+    created: bool,
+    // new_contract is set if the account was created in the current
+    // transaction (EIP-6780).
+    new_contract: bool,
+}
+
+// JournalEntry is a modification to the state that can be reverted.
+#[derive(Debug, Clone)]
+enum JournalEntry {
+    CreateObject { addr: common::Address, prev: Option<Box<StateObject>> },
+    BalanceChange { addr: common::Address, prev: Uint256 },
+    NonceChange { addr: common::Address, prev: u64 },
+    CodeChange { addr: common::Address, prev_hash: common::Hash },
+    StorageChange { addr: common::Address, key: common::Hash, prev: Option<common::Hash> },
+    Suicide { addr: common::Address, prev: bool, prev_balance: Uint256 },
+    RefundChange { prev: u64 },
+    TransientStorageChange { addr: common::Address, key: common::Hash, prev: common::Hash },
+    AddLog,
+    AccessListAddAccount { addr: common::Address },
+    AccessListAddSlot { addr: common::Address, slot: common::Hash },
+}
+
+// MemoryStateDB keeps the accounts touched since the last commit in memory
+// and reads everything else from the tries under the last committed root.
+// Committing writes the modified tries to the trie database and, if that is
+// backed by a disk store, flushes the nodes, code and the new head state root
+// to disk in one atomic batch.
+#[derive(Debug, Clone)]
+pub struct MemoryStateDB {
+    db: Database,
+    // root of the last committed state
+    root: common::Hash,
+    objects: HashMap<common::Address, StateObject>,
+    // destructed holds the accounts deleted since the last commit.
+    destructed: HashSet<common::Address>,
+    // code written since the last flush, by code hash
+    code: HashMap<common::Hash, Vec<u8>>,
+    refund: u64,
+    logs: Vec<types::Log>,
+    preimages: HashMap<common::Hash, Vec<u8>>,
+    access_list: HashMap<common::Address, HashSet<common::Hash>>,
+    // transient_storage holds the EIP-1153 slots of the current transaction.
+    transient_storage: HashMap<(common::Address, common::Hash), common::Hash>,
+
+    journal: Vec<JournalEntry>,
+    // valid_revisions maps snapshot ids to journal lengths.
+    valid_revisions: Vec<usize>,
+
+    // snapshot tree and the layer of the last committed root, used to read
+    // accounts and slots without walking the tries.
+    snaps: Option<Arc<Tree>>,
+    snap: Option<Arc<Layer>>,
+}
+
+impl Default for MemoryStateDB {
+    fn default() -> Self {
+        MemoryStateDB::new()
+    }
+}
+
+impl MemoryStateDB {
+    // new creates an empty state held entirely in memory.
+    pub fn new() -> Self {
+        MemoryStateDB::with_database(Database::new(), EMPTY_ROOT_HASH)
+    }
+
+    fn with_database(db: Database, root: common::Hash) -> Self {
+        MemoryStateDB {
+            db,
+            root,
+            objects: HashMap::new(),
+            destructed: HashSet::new(),
+            code: HashMap::new(),
+            refund: 0,
+            logs: Vec::new(),
+            preimages: HashMap::new(),
+            access_list: HashMap::new(),
+            transient_storage: HashMap::new(),
+            journal: Vec::new(),
+            valid_revisions: Vec::new(),
+            snaps: None,
+            snap: None,
+        }
+    }
+
+    // with_snapshots makes the state read accounts and slots from the flat
+    // snapshot and push a diff layer to the snapshot tree on every commit.
+    pub fn with_snapshots(mut self, snaps: Arc<Tree>) -> Self {
+        self.snap = snaps.snapshot(&self.root);
+        self.snaps = Some(snaps);
+        self
+    }
+
+    // open creates a state from the given trie database at the given root.
+    pub fn open(root: common::Hash, db: Database) -> Result<Self, TrieError> {
+        // Fail early if the root is unknown.
+        SecureTrie::from_root(root, db.clone())?;
+        Ok(MemoryStateDB::with_database(db, root))
+    }
+
+    // open_disk resumes from the last state committed to disk, or starts from
+    // an empty state if nothing was committed yet.
+    pub fn open_disk(disk: Arc<dyn KeyValueStore>) -> Result<Self, TrieError> {
+        let root = rawdb::read_head_state_root(disk.as_ref())?.unwrap_or(EMPTY_ROOT_HASH);
+        MemoryStateDB::open(root, Database::with_disk(disk))
+    }
+
+    // root returns the root of the last committed state.
+    pub fn root(&self) -> common::Hash {
+        self.root
+    }
+
+    pub fn database(&self) -> &Database {
+        &self.db
+    }
+
+    // account returns the current account at addr, loading it from the state
+    // trie if it hasn't been touched since the last commit.
+    fn account(&self, addr: &common::Address) -> Option<StateAccount> {
+        if let Some(obj) = self.objects.get(addr) {
+            return Some(obj.account.clone());
+        }
+        if self.destructed.contains(addr) {
+            return None;
+        }
+        if let Some(snap) = &self.snap {
+            // Fall back to the trie if the layer went stale.
+            if let Ok(account) = snap.account(&crypto::keccak256_hash(addr.as_bytes())) {
+                return account;
+            }
+        }
+        let trie = SecureTrie::from_root(self.root, self.db.clone()).ok()?;
+        let blob = trie.get(addr.as_bytes()).ok()??;
+        rlp::decode(&blob).ok()
+    }
+
+    fn object_mut(&mut self, addr: &common::Address) -> &mut StateObject {
+        if !self.objects.contains_key(addr) {
+            let account = self.account(addr).unwrap_or_default();
+            self.journal.push(JournalEntry::CreateObject { addr: *addr, prev: None });
+            self.objects.insert(
+                *addr,
+                StateObject {
+                    account,
+                    ..Default::default()
+                },
+            );
+        }
+        self.objects.get_mut(addr).unwrap()
+    }
+
+    // trie_state reads a committed storage slot of addr, whose storage trie
+    // has the given root, from the snapshot or the storage trie.
+    fn trie_state(&self, addr: &common::Address, root: common::Hash, key: &common::Hash) -> common::Hash {
+        // The account was created after the last commit, or has no storage.
+        if root == EMPTY_ROOT_HASH {
+            return common::Hash::default();
+        }
+        let snap_value = self.snap.as_ref().and_then(|snap| {
+            snap.storage(
+                &crypto::keccak256_hash(addr.as_bytes()),
+                &crypto::keccak256_hash(key.as_bytes()),
+            )
+            .ok()
+        });
+        let blob = match snap_value {
+            Some(blob) => blob,
+            None => SecureTrie::from_root(root, self.db.clone())
+                .ok()
+                .and_then(|trie| trie.get(key.as_bytes()).ok().flatten()),
+        };
+        let value = blob.and_then(|blob| rlp::decode::<Uint256>(&blob).ok());
+        match value {
+            Some(value) => common::Hash(value.to_bytes32()),
+            None => common::Hash::default(),
+        }
+    }
+
+    pub fn set_balance(&mut self, addr: &common::Address, amount: Uint256) {
+        let obj = self.object_mut(addr);
+        let prev = std::mem::replace(&mut obj.account.balance, amount);
+        self.journal.push(JournalEntry::BalanceChange { addr: *addr, prev });
+    }
+
+    fn write_transient_state(&mut self, addr: common::Address, key: common::Hash, value: common::Hash) {
+        if value == common::Hash::default() {
+            self.transient_storage.remove(&(addr, key));
+        } else {
+            self.transient_storage.insert((addr, key), value);
+        }
+    }
+
+    pub fn preimages(&self) -> &HashMap<common::Hash, Vec<u8>> {
+        &self.preimages
+    }
+
+    // update_snapshot pushes the changes since the last commit as a diff
+    // layer for root and flattens the layers beyond DIFF_LAYERS.
+    fn update_snapshot(&self, snaps: &Tree, root: common::Hash) -> Result<(), TrieError> {
+        let mut destructs = HashSet::new();
+        let mut accounts = HashMap::new();
+        let mut storage = HashMap::new();
+        for addr in self.destructed.iter() {
+            let hash = crypto::keccak256_hash(addr.as_bytes());
+            destructs.insert(hash);
+            accounts.insert(hash, None);
+        }
+        for (addr, obj) in self.objects.iter() {
+            let hash = crypto::keccak256_hash(addr.as_bytes());
+            if obj.created {
+                destructs.insert(hash);
+            }
+            accounts.insert(hash, Some(rlp::encode(&obj.account)));
+            let slots: HashMap<common::Hash, Option<Vec<u8>>> = obj
+                .committed_storage
+                .iter()
+                .map(|(key, value)| {
+                    let entry = if *value == common::Hash::default() {
+                        None
+                    } else {
+                        Some(rlp::encode(&Uint256::from_bytes(value.as_bytes())))
+                    };
+                    (crypto::keccak256_hash(key.as_bytes()), entry)
+                })
+                .collect();
+            if !slots.is_empty() {
+                storage.insert(hash, slots);
+            }
+        }
+        let update = snaps
+            .update(root, self.root, destructs, accounts, storage)
+            .and_then(|_| snaps.cap(&root, DIFF_LAYERS));
+        update.map_err(|err| TrieError::Database(format!("snapshot update failed: {:?}", err)))
+    }
+
+    // update_state_trie applies the pending changes to the tries under the
+    // last committed root. If commit_storage is set the storage tries are
+    // committed to the trie database and the new storage roots are recorded
+    // in the state objects; otherwise nothing is written.
+    fn update_state_trie(&mut self, commit_storage: bool) -> Result<SecureTrie, TrieError> {
+        let mut trie = SecureTrie::from_root(self.root, self.db.clone())?;
+        for addr in self.destructed.iter() {
+            if !self.objects.contains_key(addr) {
+                trie.delete(addr.as_bytes())?;
+            }
+        }
+        for (addr, obj) in self.objects.iter_mut() {
+            let mut storage = storage_trie(&self.db, obj)?;
+            let mut account = obj.account.clone();
+            if commit_storage {
+                account.root = storage.commit();
+                obj.account.root = account.root;
+            } else {
+                account.root = storage.root_hash();
+            }
+            trie.insert(addr.as_bytes(), &rlp::encode(&account))?;
+        }
+        Ok(trie)
+    }
+
+    // get_proof returns the merkle proof of the account at addr and of the given
+    // storage slots, in the shape of the eth_getProof result (EIP-1186). The
+    // proof is made against the current intermediate root.
+    pub fn get_proof(
+        &mut self,
+        addr: &common::Address,
+        storage_keys: &[common::Hash],
+    ) -> Result<AccountResult, TrieError> {
+        self.finalise(false);
+        let trie = self.update_state_trie(false)?;
+        let account_proof = trie.prove(addr.as_bytes())?;
+
+        let (mut account, storage) = match self.objects.get(addr) {
+            Some(obj) => (obj.account.clone(), Some(storage_trie(&self.db, obj)?)),
+            None => match self.account(addr) {
+                Some(account) => {
+                    let storage = SecureTrie::from_root(account.root, self.db.clone())?;
+                    (account, Some(storage))
+                }
+                None => (StateAccount::default(), None),
+            },
+        };
+        if let Some(storage) = &storage {
+            account.root = storage.root_hash();
+        }
+        let mut storage_proof = Vec::with_capacity(storage_keys.len());
+        for key in storage_keys {
+            let proof = match &storage {
+                Some(storage) => storage.prove(key.as_bytes())?,
+                None => Vec::new(),
+            };
+            storage_proof.push(StorageResult {
+                key: *key,
+                value: Uint256::from_bytes(self.get_state(addr, key).as_bytes()),
+                proof,
+            });
+        }
+        Ok(AccountResult {
+            address: *addr,
+            account_proof,
+            balance: account.balance,
+            code_hash: account.code_hash,
+            nonce: account.nonce,
+            storage_hash: account.root,
+            storage_proof,
+        })
+    }
+
+    fn revert(&mut self, entry: JournalEntry) {
+        match entry {
+            JournalEntry::CreateObject { addr, prev } => match prev {
+                Some(prev) => {
+                    self.objects.insert(addr, *prev);
+                }
+                None => {
+                    self.objects.remove(&addr);
+                }
+            },
+            JournalEntry::BalanceChange { addr, prev } => {
+                if let Some(obj) = self.objects.get_mut(&addr) {
+                    obj.account.balance = prev;
+                }
+            }
+            JournalEntry::NonceChange { addr, prev } => {
+                if let Some(obj) = self.objects.get_mut(&addr) {
+                    obj.account.nonce = prev;
+                }
+            }
+            JournalEntry::CodeChange { addr, prev_hash } => {
+                if let Some(obj) = self.objects.get_mut(&addr) {
+                    obj.account.code_hash = prev_hash;
+                }
+            }
+            JournalEntry::StorageChange { addr, key, prev } => {
+                if let Some(obj) = self.objects.get_mut(&addr) {
+                    match prev {
+                        Some(prev) => obj.storage.insert(key, prev),
+                        None => obj.storage.remove(&key),
+                    };
+                }
+            }
+            JournalEntry::Suicide { addr, prev, prev_balance } => {
+                if let Some(obj) = self.objects.get_mut(&addr) {
+                    obj.suicided = prev;
+                    obj.account.balance = prev_balance;
+                }
+            }
+            JournalEntry::TransientStorageChange { addr, key, prev } => {
+                self.write_transient_state(addr, key, prev);
+            }
+            JournalEntry::RefundChange { prev } => self.refund = prev,
+            JournalEntry::AddLog => {
+                self.logs.pop();
+            }
+            JournalEntry::AccessListAddAccount { addr } => {
+                self.access_list.remove(&addr);
+            }
+            JournalEntry::AccessListAddSlot { addr, slot } => {
+                if let Some(slots) = self.access_list.get_mut(&addr) {
+                    slots.remove(&slot);
+                }
+            }
+        }
+    }
+}
+
+// storage_trie opens the storage trie of a state object and applies the slots
+// written since the last commit. Zero values are deleted.
+fn storage_trie(db: &Database, obj: &StateObject) -> Result<SecureTrie, TrieError> {
+    let mut storage = SecureTrie::from_root(obj.account.root, db.clone())?;
+    for (key, value) in obj.committed_storage.iter().chain(obj.storage.iter()) {
+        if *value == common::Hash::default() {
+            storage.delete(key.as_bytes())?;
+        } else {
+            storage.insert(key.as_bytes(), &rlp::encode(&Uint256::from_bytes(value.as_bytes())))?;
+        }
+    }
+    Ok(storage)
+}
+
+impl StateDB for MemoryStateDB {
+    // create_account explicitly creates a state object. If a state object with
+    // the address already exists the balance is carried over to the new account.
+    fn create_account(&mut self, addr: &common::Address) {
+        let mut obj = StateObject {
+            created: true,
+            new_contract: true,
+            ..Default::default()
+        };
+        obj.account.balance = self.get_balance(addr);
+        let prev = self.objects.remove(addr);
+        self.objects.insert(*addr, obj);
+        self.journal.push(JournalEntry::CreateObject {
+            addr: *addr,
+            prev: prev.map(Box::new),
+        });
+    }
+
+    fn sub_balance(&mut self, addr: &common::Address, amount: &Uint256) {
+        let balance = self.get_balance(addr);
+        let balance = balance.checked_sub(amount).unwrap_or_default();
+        self.set_balance(addr, balance);
+    }
+
+    fn add_balance(&mut self, addr: &common::Address, amount: &Uint256) {
+        let balance = self.get_balance(addr);
+        // Adding zero still touches the account (EIP-161).
+        let balance = balance.checked_add(amount).unwrap_or(balance);
+        self.set_balance(addr, balance);
+    }
+
+    fn get_balance(&self, addr: &common::Address) -> Uint256 {
+        self.account(addr).map(|account| account.balance).unwrap_or_default()
+    }
+
+    fn get_nonce(&self, addr: &common::Address) -> u64 {
+        self.account(addr).map(|account| account.nonce).unwrap_or(0)
+    }
+
+    fn set_nonce(&mut self, addr: &common::Address, nonce: u64) {
+        let obj = self.object_mut(addr);
+        let prev = std::mem::replace(&mut obj.account.nonce, nonce);
+        self.journal.push(JournalEntry::NonceChange { addr: *addr, prev });
+    }
+
+    fn get_code_hash(&self, addr: &common::Address) -> common::Hash {
+        self.account(addr).map(|account| account.code_hash).unwrap_or_default()
+    }
+
+    fn get_code(&self, addr: &common::Address) -> Vec<u8> {
+        let hash = match self.account(addr) {
+            Some(account) if account.code_hash != EMPTY_CODE_HASH => account.code_hash,
+            _ => return Vec::new(),
+        };
+        if let Some(code) = self.code.get(&hash) {
+            return code.clone();
+        }
+        self.db
+            .disk()
+            .and_then(|disk| rawdb::read_code(disk.as_ref(), &hash).ok().flatten())
+            .unwrap_or_default()
+    }
+
+    fn set_code(&mut self, addr: &common::Address, code: &[u8]) {
+        let hash = crypto::keccak256_hash(code);
+        self.code.insert(hash, code.to_vec());
+        let obj = self.object_mut(addr);
+        let prev_hash = std::mem::replace(&mut obj.account.code_hash, hash);
+        self.journal.push(JournalEntry::CodeChange { addr: *addr, prev_hash });
+    }
+
+    fn get_code_size(&self, addr: &common::Address) -> usize {
+        self.get_code(addr).len()
+    }
+
+    fn add_refund(&mut self, gas: u64) {
+        self.journal.push(JournalEntry::RefundChange { prev: self.refund });
+        self.refund += gas;
+    }
+
+    fn sub_refund(&mut self, gas: u64) {
+        self.journal.push(JournalEntry::RefundChange { prev: self.refund });
+        if gas > self.refund {
+            panic!("Refund counter below zero (gas: {} > refund: {})", gas, self.refund);
+        }
+        self.refund -= gas;
+    }
+
+    fn get_refund(&self) -> u64 {
+        self.refund
+    }
+
+    fn get_committed_state(&self, addr: &common::Address, key: &common::Hash) -> common::Hash {
+        match self.objects.get(addr) {
+            Some(obj) => match obj.committed_storage.get(key) {
+                Some(value) => *value,
+                None => self.trie_state(addr, obj.account.root, key),
+            },
+            None => match self.account(addr) {
+                Some(account) => self.trie_state(addr, account.root, key),
+                None => common::Hash::default(),
+            },
+        }
+    }
+
+    fn get_state(&self, addr: &common::Address, key: &common::Hash) -> common::Hash {
+        match self.objects.get(addr).and_then(|obj| obj.storage.get(key)) {
+            Some(value) => *value,
+            None => self.get_committed_state(addr, key),
+        }
+    }
+
+    fn set_state(&mut self, addr: &common::Address, key: common::Hash, value: common::Hash) {
+        let obj = self.object_mut(addr);
+        let prev = obj.storage.insert(key, value);
+        self.journal.push(JournalEntry::StorageChange { addr: *addr, key, prev });
+    }
+
+    fn get_transient_state(&self, addr: &common::Address, key: &common::Hash) -> common::Hash {
+        self.transient_storage.get(&(*addr, *key)).copied().unwrap_or_default()
+    }
+
+    fn set_transient_state(&mut self, addr: &common::Address, key: common::Hash, value: common::Hash) {
+        let prev = self.get_transient_state(addr, &key);
+        if prev == value {
+            return;
+        }
+        self.journal.push(JournalEntry::TransientStorageChange { addr: *addr, key, prev });
+        self.write_transient_state(*addr, key, value);
+    }
+
+    // suicide marks the given account as suicided and clears its balance.
+    // The account's state object is still available until the state is
+    // finalised.
+    fn suicide(&mut self, addr: &common::Address) -> bool {
+        if !self.exist(addr) {
+            return false;
+        }
+        let obj = self.object_mut(addr);
+        let prev = std::mem::replace(&mut obj.suicided, true);
+        let prev_balance = std::mem::take(&mut obj.account.balance);
+        self.journal.push(JournalEntry::Suicide { addr: *addr, prev, prev_balance });
+        true
+    }
+
+    fn has_suicided(&self, addr: &common::Address) -> bool {
+        self.objects.get(addr).map(|obj| obj.suicided).unwrap_or(false)
+    }
+
+    fn selfdestruct6780(&mut self, addr: &common::Address) {
+        if self.objects.get(addr).map(|obj| obj.new_contract).unwrap_or(false) {
+            self.suicide(addr);
+        }
+    }
+
+    fn exist(&self, addr: &common::Address) -> bool {
+        self.account(addr).is_some()
+    }
+
+    fn empty(&self, addr: &common::Address) -> bool {
+        self.account(addr).map(|account| account.empty()).unwrap_or(true)
+    }
+
+    fn address_in_access_list(&self, addr: &common::Address) -> bool {
+        self.access_list.contains_key(addr)
+    }
+
+    fn slot_in_access_list(&self, addr: &common::Address, slot: &common::Hash) -> (bool, bool) {
+        match self.access_list.get(addr) {
+            Some(slots) => (true, slots.contains(slot)),
+            None => (false, false),
+        }
+    }
+
+    fn add_address_to_access_list(&mut self, addr: &common::Address) {
+        if !self.access_list.contains_key(addr) {
+            self.access_list.insert(*addr, HashSet::new());
+            self.journal.push(JournalEntry::AccessListAddAccount { addr: *addr });
+        }
+    }
+
+    fn add_slot_to_access_list(&mut self, addr: &common::Address, slot: &common::Hash) {
+        self.add_address_to_access_list(addr);
+        let slots = self.access_list.get_mut(addr).unwrap();
+        if slots.insert(*slot) {
+            self.journal.push(JournalEntry::AccessListAddSlot { addr: *addr, slot: *slot });
+        }
+    }
+
+    // revert_to_snapshot reverts all state changes made since the given revision.
+    fn revert_to_snapshot(&mut self, id: usize) {
+        if id >= self.valid_revisions.len() {
+            panic!("revision id {} cannot be reverted", id);
+        }
+        let journal_len = self.valid_revisions[id];
+        while self.journal.len() > journal_len {
+            let entry = self.journal.pop().unwrap();
+            self.revert(entry);
+        }
+        self.valid_revisions.truncate(id);
+    }
+
+    // snapshot returns an identifier for the current revision of the state.
+    fn snapshot(&mut self) -> usize {
+        self.valid_revisions.push(self.journal.len());
+        self.valid_revisions.len() - 1
+    }
+
+    fn add_log(&mut self, log: types::Log) {
+        self.journal.push(JournalEntry::AddLog);
+        self.logs.push(log);
+    }
+
+    fn add_preimage(&mut self, hash: &common::Hash, preimage: &[u8]) {
+        self.preimages.entry(*hash).or_insert_with(|| preimage.to_vec());
+    }
+
+    fn prepare(
+        &mut self,
+        rules: &Rules,
+        sender: &common::Address,
+        coinbase: &common::Address,
+        dest: Option<&common::Address>,
+        precompiles: &[common::Address],
+        tx_accesses: &types::AccessList,
+    ) {
+        self.refund = 0;
+        self.logs.clear();
+        // Reset transient storage at the beginning of transaction execution
+        self.transient_storage.clear();
+        if !rules.is_berlin {
+            return;
+        }
+        // Clear out any leftover from previous executions
+        self.access_list.clear();
+
+        self.add_address_to_access_list(sender);
+        if let Some(dest) = dest {
+            self.add_address_to_access_list(dest);
+            // If it's a create-tx, the destination will be added inside evm.create
+        }
+        for addr in precompiles {
+            self.add_address_to_access_list(addr);
+        }
+        for el in tx_accesses.iter() {
+            self.add_address_to_access_list(&el.address);
+            for key in &el.storage_keys {
+                self.add_slot_to_access_list(&el.address, key);
+            }
+        }
+        if rules.is_shanghai {
+            // EIP-3651: warm coinbase
+            self.add_address_to_access_list(coinbase);
+        }
+    }
+
+    fn logs(&self) -> &[types::Log] {
+        &self.logs
+    }
+
+    // finalise removes suicided accounts and, if delete_empty_objects is set
+    // (EIP-158), accounts that are empty. It also clears the journal, so
+    // changes made so far can no longer be reverted.
+    fn finalise(&mut self, delete_empty_objects: bool) {
+        let destructed: Vec<common::Address> = self
+            .objects
+            .iter()
+            .filter(|(_, obj)| obj.suicided || (delete_empty_objects && obj.account.empty()))
+            .map(|(addr, _)| *addr)
+            .collect();
+        for addr in destructed {
+            self.objects.remove(&addr);
+            self.destructed.insert(addr);
+        }
+        for obj in self.objects.values_mut() {
+            let storage = std::mem::take(&mut obj.storage);
+            obj.committed_storage.extend(storage);
+            obj.new_contract = false;
+        }
+        self.journal.clear();
+        self.valid_revisions.clear();
+    }
+
+    // intermediate_root computes the current root hash of the state trie
+    // without writing any trie nodes.
+    fn intermediate_root(&mut self, delete_empty_objects: bool) -> Result<common::Hash, TrieError> {
+        self.finalise(delete_empty_objects);
+        Ok(self.update_state_trie(false)?.root_hash())
+    }
+
+    // commit writes the state into the trie database and returns the new
+    // state root. If the trie database is backed by disk the trie nodes, the
+    // new contract code and the state root are flushed in one batch, so after
+    // a crash the store holds either the previous or the new state.
+    fn commit(&mut self, delete_empty_objects: bool) -> Result<common::Hash, TrieError> {
+        self.finalise(delete_empty_objects);
+        let mut trie = self.update_state_trie(true)?;
+        let root = trie.commit();
+        // The parent layer may have been flattened away by a commit on another
+        // branch, the state is then only kept in the tries.
+        if let Some(snaps) = self.snaps.as_ref().filter(|snaps| snaps.snapshot(&self.root).is_some()) {
+            self.update_snapshot(snaps, root)?;
+        }
+
+        if let Some(disk) = self.db.disk() {
+            let mut batch = Batch::new();
+            self.db.write_dirty(&mut batch);
+            for (hash, code) in self.code.iter() {
+                rawdb::write_code(&mut batch, hash, code);
+            }
+            rawdb::write_head_state_root(&mut batch, &root);
+            disk.write_batch(&batch)?;
+            self.db.clear_dirty();
+            self.code.clear();
+        }
+        self.root = root;
+        self.objects.clear();
+        self.destructed.clear();
+        self.snap = self.snaps.as_ref().and_then(|snaps| snaps.snapshot(&root));
+        Ok(root)
+    }
+}
@@ -1,29 +1,118 @@
-fn memory_return(stack: &Stack) -> (u64, bool) {
-    calc_mem_size_64(stack.back(0), stack.back(1))
+use crate::stack::Stack;
+use uint256::Uint256;
+
+// calc_mem_size64 calculates the required memory size, and returns
+// the size and whether the result overflowed uint64
+pub fn calc_mem_size64(off: &Uint256, l: &Uint256) -> (u64, bool) {
+    if !l.is_u64() {
+        return (0, true);
+    }
+    calc_mem_size64_with_uint(off, l.low_u64())
+}
+
+// calc_mem_size64_with_uint calculates the required memory size, and returns
+// the size and whether the result overflowed uint64
+// Identical to calc_mem_size64, but length is a u64
+pub fn calc_mem_size64_with_uint(off: &Uint256, length64: u64) -> (u64, bool) {
+    // if length is zero, memsize is always zero, regardless of offset
+    if length64 == 0 {
+        return (0, false);
+    }
+    // Check that offset doesn't overflow
+    let (offset64, overflow) = off.uint64_with_overflow();
+    if overflow {
+        return (0, true);
+    }
+    match offset64.checked_add(length64) {
+        Some(val) => (val, false),
+        None => (0, true),
+    }
 }
 
-fn memory_revert(stack: &Stack) -> (u64, bool) {
-    calc_mem_size_64(stack.back(0), stack.back(1))
+pub fn memory_keccak256(stack: &Stack) -> (u64, bool) {
+    calc_mem_size64(stack.back(0), stack.back(1))
 }
 
-fn memory_log(stack: &Stack) -> (u64, bool) {
-    calc_mem_size_64(stack.back(0), stack.back(1))
+pub fn memory_call_data_copy(stack: &Stack) -> (u64, bool) {
+    calc_mem_size64(stack.back(0), stack.back(2))
 }
 
-fn memory_call(stack: &Stack) -> (u64, bool) {
-    let (x, overflow1) = calc_mem_size64(stack.back(5), stack.back(6));
-    if overflow1 {
-        return (0, true);
+pub fn memory_return_data_copy(stack: &Stack) -> (u64, bool) {
+    calc_mem_size64(stack.back(0), stack.back(2))
+}
+
+pub fn memory_code_copy(stack: &Stack) -> (u64, bool) {
+    calc_mem_size64(stack.back(0), stack.back(2))
+}
+
+pub fn memory_ext_code_copy(stack: &Stack) -> (u64, bool) {
+    calc_mem_size64(stack.back(1), stack.back(3))
+}
+
+pub fn memory_mload(stack: &Stack) -> (u64, bool) {
+    calc_mem_size64_with_uint(stack.back(0), 32)
+}
+
+pub fn memory_mstore8(stack: &Stack) -> (u64, bool) {
+    calc_mem_size64_with_uint(stack.back(0), 1)
+}
+
+pub fn memory_mstore(stack: &Stack) -> (u64, bool) {
+    calc_mem_size64_with_uint(stack.back(0), 32)
+}
+
+pub fn memory_mcopy(stack: &Stack) -> (u64, bool) {
+    let mut mstart = stack.back(0); // stack[0]: dest
+    if stack.back(1) > mstart {
+        mstart = stack.back(1); // stack[1]: source
     }
+    calc_mem_size64(mstart, stack.back(2)) // stack[2]: length
+}
+
+pub fn memory_create(stack: &Stack) -> (u64, bool) {
+    calc_mem_size64(stack.back(1), stack.back(2))
+}
 
-    let (y, overflow2) = calc_mem_size64(stack.back(3), stack.back(4));
-    if overflow2 {
+pub fn memory_create2(stack: &Stack) -> (u64, bool) {
+    calc_mem_size64(stack.back(1), stack.back(2))
+}
+
+pub fn memory_call(stack: &Stack) -> (u64, bool) {
+    let (x, overflow) = calc_mem_size64(stack.back(5), stack.back(6));
+    if overflow {
         return (0, true);
     }
+    let (y, overflow) = calc_mem_size64(stack.back(3), stack.back(4));
+    if overflow {
+        return (0, true);
+    }
+    (x.max(y), false)
+}
 
-    if x > y {
-        return (x, false);
+pub fn memory_delegate_call(stack: &Stack) -> (u64, bool) {
+    let (x, overflow) = calc_mem_size64(stack.back(4), stack.back(5));
+    if overflow {
+        return (0, true);
+    }
+    let (y, overflow) = calc_mem_size64(stack.back(2), stack.back(3));
+    if overflow {
+        return (0, true);
     }
+    (x.max(y), false)
+}
+
+pub fn memory_static_call(stack: &Stack) -> (u64, bool) {
+    memory_delegate_call(stack)
+}
+
+pub fn memory_return(stack: &Stack) -> (u64, bool) {
+    calc_mem_size64(stack.back(0), stack.back(1))
+}
+
+pub fn memory_revert(stack: &Stack) -> (u64, bool) {
+    calc_mem_size64(stack.back(0), stack.back(1))
+}
 
-    (y, false)
+pub fn memory_log(stack: &Stack) -> (u64, bool) {
+    calc_mem_size64(stack.back(0), stack.back(1))
 }
@@ -0,0 +1,803 @@
+// Package miner implements the local block producer of the devnet.
+//
+// build_block assembles a new block on top of a parent header: it fills the
+// block with pending transactions ordered by effective tip (keeping the nonce
+// order of every sender), skips transactions that can't be applied, computes
+// all the roots the header commits to and seals the block. Miner drives the
+// builder on a configurable interval, or whenever mine_now is called through
+// its MinerHandle.
+//
+// The consensus fields of the header, the block rewards and the seal are left
+// to the consensus engine of the backend. With the Beacon engine blocks are
+// produced in the post-merge format: a zero difficulty and nonce, so a block
+// is sealed as soon as its header is complete.
+
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, VecDeque};
+use std::fmt;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::block::{derive_sha, Block, Header, Withdrawal};
+use crate::bloom::create_bloom;
+use crate::chain_config::ChainConfig;
+use crate::consensus::{ChainHeaderReader, ConsensusError, Engine};
+use crate::eip1559;
+use crate::eip4844;
+use crate::evm::{Config as VmConfig, GetHashFunc, TxContext, EVM};
+use crate::gas_pool::GasPool;
+use crate::params;
+use crate::receipt::{self, Receipt, ReceiptError};
+use crate::state_db::StateDB;
+use crate::state_processor::{is_merged, new_block_context, process_beacon_block_root};
+use crate::state_transition::{apply_transaction, StateTransitionError};
+use crate::transaction::{self, Transaction};
+use crate::trie::TrieError;
+use uint256::Uint256;
+
+// DEFAULT_GAS_CEIL is the gas limit the miner targets if none is configured.
+pub const DEFAULT_GAS_CEIL: u64 = 30_000_000;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MinerError {
+    // State is returned if the parent state can't be read or hashed.
+    State(TrieError),
+    Receipts(ReceiptError),
+    // Consensus is returned if the engine fails to prepare or seal the block.
+    Consensus(ConsensusError),
+    // Backend is returned if the backend fails to provide the parent block
+    // or to insert the sealed one.
+    Backend(String),
+    // Stopped is returned by mine_now once the miner loop has exited.
+    Stopped,
+}
+
+impl fmt::Display for MinerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MinerError::State(err) => write!(f, "state: {:?}", err),
+            MinerError::Receipts(err) => write!(f, "{}", err),
+            MinerError::Consensus(err) => write!(f, "{}", err),
+            MinerError::Backend(err) => write!(f, "backend: {}", err),
+            MinerError::Stopped => write!(f, "miner stopped"),
+        }
+    }
+}
+
+impl std::error::Error for MinerError {}
+
+impl From<TrieError> for MinerError {
+    fn from(err: TrieError) -> Self {
+        MinerError::State(err)
+    }
+}
+
+impl From<ConsensusError> for MinerError {
+    fn from(err: ConsensusError) -> Self {
+        MinerError::Consensus(err)
+    }
+}
+
+// Config is the configuration parameters of mining.
+#[derive(Debug, Clone)]
+pub struct Config {
+    pub etherbase: common::Address, // Public address for block mining rewards
+    pub extra_data: Vec<u8>,        // Block extra data set by the miner
+    pub gas_ceil: u64,              // Target gas ceiling for mined blocks.
+    // Time interval between two blocks. Blocks are only mined on demand if
+    // None.
+    pub period: Option<Duration>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            etherbase: common::Address::default(),
+            extra_data: Vec::new(),
+            gas_ceil: DEFAULT_GAS_CEIL,
+            period: None,
+        }
+    }
+}
+
+// BuiltBlock is a sealed block along with the receipts of its transactions
+// and its post-state, which is not committed yet.
+pub struct BuiltBlock {
+    pub block: Block,
+    pub receipts: Vec<Receipt>,
+    pub state: Box<dyn StateDB>,
+}
+
+// calc_gas_limit computes the gas limit of the next block after parent. It
+// aims to keep the baseline gas close to the provided target, and increase it
+// towards the target if the baseline gas is lower.
+pub fn calc_gas_limit(parent_gas_limit: u64, desired_limit: u64) -> u64 {
+    let delta = (parent_gas_limit / params::GAS_LIMIT_BOUND_DIVISOR).saturating_sub(1);
+    let desired_limit = desired_limit.max(params::MIN_GAS_LIMIT);
+    // If we're outside our allowed gas range, we try to hone towards them
+    if parent_gas_limit < desired_limit {
+        (parent_gas_limit + delta).min(desired_limit)
+    } else if parent_gas_limit > desired_limit {
+        (parent_gas_limit - delta).max(desired_limit)
+    } else {
+        parent_gas_limit
+    }
+}
+
+// TransactionsByPriceAndNonce represents a set of transactions that can
+// return transactions in a profit-maximizing sorted order, while supporting
+// removing entire batches of transactions for non-executable accounts.
+struct TransactionsByPriceAndNonce {
+    txs: HashMap<common::Address, VecDeque<(usize, Transaction)>>, // Per account nonce-sorted list of transactions and their arrival order
+    heads: BinaryHeap<(Uint256, Reverse<usize>, common::Address)>, // Next transaction of each account by tip and arrival order
+    base_fee: Option<Uint256>,                                     // Current base fee
+}
+
+impl TransactionsByPriceAndNonce {
+    // new creates a transaction set that can retrieve price sorted
+    // transactions in a nonce-honouring way. Transactions with an invalid
+    // signature are dropped.
+    fn new(pending: Vec<Transaction>, chain_id: u64, base_fee: Option<Uint256>) -> Self {
+        let chain_id = Uint256::from_uint64(chain_id);
+        let mut txs: HashMap<common::Address, VecDeque<(usize, Transaction)>> = HashMap::new();
+        for (arrival, tx) in pending.into_iter().enumerate() {
+            if let Ok(from) = transaction::sender(&tx, &chain_id) {
+                txs.entry(from).or_default().push_back((arrival, tx));
+            }
+        }
+        let mut set = TransactionsByPriceAndNonce {
+            txs,
+            heads: BinaryHeap::new(),
+            base_fee,
+        };
+        let accounts: Vec<common::Address> = set.txs.keys().copied().collect();
+        for from in accounts {
+            let list = set.txs.get_mut(&from).expect("account listed");
+            list.make_contiguous().sort_by_key(|(_, tx)| tx.nonce());
+            set.push_head(from);
+        }
+        set
+    }
+
+    // push_head queues the lowest nonce transaction of the account, dropping
+    // the account if that transaction can't pay the base fee.
+    fn push_head(&mut self, from: common::Address) {
+        let head = match self.txs.get(&from).and_then(|list| list.front()) {
+            Some((arrival, tx)) => tx
+                .effective_gas_tip(self.base_fee.as_ref())
+                .ok()
+                .map(|tip| (tip, *arrival)),
+            None => None,
+        };
+        match head {
+            Some((tip, arrival)) => self.heads.push((tip, Reverse(arrival), from)),
+            None => {
+                self.txs.remove(&from);
+            }
+        }
+    }
+
+    // peek returns the next transaction by price. Ties are broken in favour
+    // of the transaction that arrived first.
+    fn peek(&self) -> Option<Transaction> {
+        let (_, _, from) = self.heads.peek()?;
+        self.txs
+            .get(from)
+            .and_then(|list| list.front())
+            .map(|(_, tx)| tx.clone())
+    }
+
+    // shift replaces the current best head with the next one from the same
+    // account.
+    fn shift(&mut self) {
+        if let Some((_, _, from)) = self.heads.pop() {
+            if let Some(list) = self.txs.get_mut(&from) {
+                list.pop_front();
+            }
+            self.push_head(from);
+        }
+    }
+
+    // pop removes the best transaction, *not* replacing it with the next one
+    // from the same account. This should be used when a transaction cannot
+    // be executed and hence all subsequent ones should be discarded from the
+    // same account.
+    fn pop(&mut self) {
+        if let Some((_, _, from)) = self.heads.pop() {
+            self.txs.remove(&from);
+        }
+    }
+}
+
+// prepare_header constructs the header of the block following parent at the
+// given timestamp.
+pub fn prepare_header(
+    chain_config: &ChainConfig,
+    config: &Config,
+    parent: &Header,
+    timestamp: u64,
+) -> Header {
+    let number = parent.number + 1;
+    let time = timestamp.max(parent.time + 1);
+    let mut header = Header {
+        parent_hash: parent.hash(),
+        coinbase: config.etherbase,
+        number,
+        gas_limit: calc_gas_limit(
+            eip1559::parent_gas_limit(chain_config, parent),
+            config.gas_ceil,
+        ),
+        time,
+        extra: config.extra_data.clone(),
+        // The randomness of a block is provided by the beacon chain, which
+        // the devnet doesn't have.
+        mix_digest: common::Hash::default(),
+        ..Default::default()
+    };
+    // Set the base fee if London is active. The blob fields are set by
+    // build_block once the engine has decided whether this is a
+    // proof-of-stake block.
+    if chain_config.is_london(number) {
+        header.base_fee = Some(eip1559::calc_base_fee(chain_config, parent));
+    }
+    header
+}
+
+// build_block assembles and seals the block of the prepared header on top of
+// its parent's post-state, filling it with the pending transactions up to the
+// gas limit. Transactions that can't be applied are skipped along with the
+// following transactions of the same sender.
+pub fn build_block(
+    chain: &dyn ChainHeaderReader,
+    engine: &dyn Engine,
+    mut header: Header,
+    state: Box<dyn StateDB>,
+    pending: Vec<Transaction>,
+    get_hash: GetHashFunc,
+) -> Result<BuiltBlock, MinerError> {
+    let chain_config = chain.config();
+    engine.prepare(chain, &mut header)?;
+    // Cancun only activates after the merge, like in validate_body.
+    if is_merged(&header) && chain_config.is_cancun(header.number, header.time) {
+        let parent = chain
+            .get_header(&header.parent_hash, header.number - 1)
+            .ok_or(ConsensusError::UnknownAncestor)?;
+        header.excess_blob_gas = Some(eip4844::calc_excess_blob_gas(
+            parent.excess_blob_gas.unwrap_or(0),
+            parent.blob_gas_used.unwrap_or(0),
+        ));
+        header.blob_gas_used = Some(0);
+        header.parent_beacon_root = Some(common::Hash::default());
+    }
+    let mut evm = EVM::new(
+        new_block_context(&header, get_hash),
+        TxContext::default(),
+        state,
+        chain_config.clone(),
+        VmConfig::default(),
+    );
+    let rules = evm.chain_rules.clone();
+    if let Some(beacon_root) = header.parent_beacon_root {
+        process_beacon_block_root(&mut evm, beacon_root);
+    }
+
+    let mut txs =
+        TransactionsByPriceAndNonce::new(pending, chain_config.chain_id, header.base_fee.clone());
+    let mut gp = GasPool::new(header.gas_limit);
+    let mut used_gas = 0u64;
+    let mut blob_gas_used = 0u64;
+    let mut included = Vec::new();
+    let mut receipts = Vec::new();
+    loop {
+        // If we don't have enough gas for any further transactions then we're done.
+        if gp.gas() < params::TX_GAS {
+            break;
+        }
+        // Retrieve the next transaction and abort if all done.
+        let tx = match txs.peek() {
+            Some(tx) => tx,
+            None => break,
+        };
+        // If we don't have enough space for the next transaction, skip the account.
+        if tx.gas() > gp.gas() {
+            txs.pop();
+            continue;
+        }
+        let blob_gas = tx.blob_gas();
+        if blob_gas > 0
+            && (!rules.is_cancun || blob_gas_used + blob_gas > params::MAX_BLOB_GAS_PER_BLOCK)
+        {
+            txs.pop();
+            continue;
+        }
+        // Start executing the transaction, rolling the state and the gas
+        // pool back if it can't be included.
+        let snapshot = evm.state_db.snapshot();
+        let gas_pool = gp;
+        match apply_transaction(&mut evm, &mut gp, &tx, &mut used_gas) {
+            Ok(receipt) => {
+                // Everything ok, shift in the next transaction from the same account
+                blob_gas_used += blob_gas;
+                receipts.push(receipt);
+                included.push(tx);
+                txs.shift();
+            }
+            Err(StateTransitionError::NonceTooLow { .. }) => {
+                // New head notification data race between the transaction pool and miner, shift
+                evm.state_db.revert_to_snapshot(snapshot);
+                gp = gas_pool;
+                txs.shift();
+            }
+            Err(_) => {
+                // Transaction is regarded as invalid, drop all consecutive transactions from
+                // the same sender because of `nonce-too-high` clause.
+                evm.state_db.revert_to_snapshot(snapshot);
+                gp = gas_pool;
+                txs.pop();
+            }
+        }
+    }
+
+    // Withdrawals are processed by the beacon chain, so post-Shanghai blocks
+    // of the devnet carry an empty list.
+    let withdrawals: Option<Vec<Withdrawal>> = if rules.is_shanghai {
+        Some(Vec::new())
+    } else {
+        None
+    };
+    let mut state = evm.into_state_db();
+    engine.finalize(chain_config, &header, state.as_mut(), &[]);
+
+    header.gas_used = used_gas;
+    if header.blob_gas_used.is_some() {
+        header.blob_gas_used = Some(blob_gas_used);
+    }
+    header.bloom = create_bloom(&receipts);
+    header.receipt_hash = derive_sha(&receipts);
+    header.root = state.intermediate_root(rules.is_eip158)?;
+    let block = engine.seal(chain, Block::new(header, included, Vec::new(), withdrawals))?;
+
+    let blob_gas_price = block.header.excess_blob_gas.map(eip4844::calc_blob_fee);
+    receipt::derive_fields(
+        &mut receipts,
+        chain_config.chain_id,
+        block.hash(),
+        block.number(),
+        block.header.base_fee.as_ref(),
+        blob_gas_price.as_ref(),
+        block.transactions(),
+    )
+    .map_err(MinerError::Receipts)?;
+    Ok(BuiltBlock {
+        block,
+        receipts,
+        state,
+    })
+}
+
+// Backend wraps all methods required for mining: access to the chain and
+// its state, the consensus engine, the pending transactions and the
+// insertion of sealed blocks.
+pub trait Backend: ChainHeaderReader {
+    // engine returns the consensus engine preparing and sealing the blocks.
+    fn engine(&self) -> &dyn Engine;
+    // state_at returns the post-state of the given header.
+    fn state_at(&self, header: &Header) -> Result<Box<dyn StateDB>, MinerError>;
+    // get_hash_fn returns the BLOCKHASH lookup for blocks built on header.
+    fn get_hash_fn(&self, header: &Header) -> GetHashFunc;
+    // pending_transactions returns the transactions ready for inclusion.
+    fn pending_transactions(&self) -> Vec<Transaction>;
+    // insert_block writes the sealed block and makes it the new head.
+    fn insert_block(&mut self, built: BuiltBlock) -> Result<(), MinerError>;
+}
+
+enum Command {
+    MineNow(Sender<Result<Block, MinerError>>),
+    Stop,
+}
+
+// MinerHandle controls a running Miner from any thread.
+#[derive(Clone)]
+pub struct MinerHandle {
+    commands: Sender<Command>,
+}
+
+impl MinerHandle {
+    // mine_now seals a new block on top of the current head right away and
+    // waits for it to be inserted.
+    pub fn mine_now(&self) -> Result<Block, MinerError> {
+        let (reply, result) = mpsc::channel();
+        self.commands
+            .send(Command::MineNow(reply))
+            .map_err(|_| MinerError::Stopped)?;
+        result.recv().map_err(|_| MinerError::Stopped)?
+    }
+
+    // stop makes the miner loop return.
+    pub fn stop(&self) {
+        let _ = self.commands.send(Command::Stop);
+    }
+}
+
+// Miner produces the blocks of the devnet on top of the backend's head.
+pub struct Miner<B: Backend> {
+    backend: B,
+    config: Config,
+    commands: Receiver<Command>,
+}
+
+impl<B: Backend> Miner<B> {
+    pub fn new(backend: B, config: Config) -> (Self, MinerHandle) {
+        let (sender, commands) = mpsc::channel();
+        let miner = Miner {
+            backend,
+            config,
+            commands,
+        };
+        (miner, MinerHandle { commands: sender })
+    }
+
+    pub fn backend(&self) -> &B {
+        &self.backend
+    }
+
+    // mine builds a block on top of the current head, inserts it into the
+    // backend and returns it.
+    pub fn mine(&mut self) -> Result<Block, MinerError> {
+        let parent = self.backend.current_header();
+        let state = self.backend.state_at(&parent)?;
+        let get_hash = self.backend.get_hash_fn(&parent);
+        let pending = self.backend.pending_transactions();
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_secs());
+        let header = prepare_header(self.backend.config(), &self.config, &parent, timestamp);
+        let built = build_block(
+            &self.backend,
+            self.backend.engine(),
+            header,
+            state,
+            pending,
+            get_hash,
+        )?;
+        let block = built.block.clone();
+        self.backend.insert_block(built)?;
+        Ok(block)
+    }
+
+    // run is the main loop of the miner: it seals a block every period and
+    // whenever mine_now is called, until stop is called or all handles are
+    // dropped. Errors of periodic blocks are logged and the tick skipped,
+    // those of on-demand blocks are returned to the caller.
+    pub fn run(mut self) -> Result<B, MinerError> {
+        loop {
+            let command = match self.config.period {
+                Some(period) => match self.commands.recv_timeout(period) {
+                    Ok(command) => Some(command),
+                    Err(RecvTimeoutError::Timeout) => None,
+                    Err(RecvTimeoutError::Disconnected) => Some(Command::Stop),
+                },
+                None => Some(self.commands.recv().unwrap_or(Command::Stop)),
+            };
+            match command {
+                None => {
+                    if let Err(err) = self.mine() {
+                        eprintln!("miner: failed to mine block: {}", err);
+                    }
+                }
+                Some(Command::MineNow(reply)) => {
+                    let _ = reply.send(self.mine());
+                }
+                Some(Command::Stop) => return Ok(self.backend),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+    use std::sync::Arc;
+    use std::thread;
+
+    use crate::blockchain::BlockChain;
+    use crate::crypto::{self, PrivateKey};
+    use crate::ethdb::MemoryDatabase;
+    use crate::genesis::{Genesis, GenesisAccount};
+    use crate::pow::ProofOfWork;
+    use crate::pruner::GcMode;
+    use crate::state_processor::process_block;
+    use crate::transaction::{LegacyTx, TxData};
+
+    fn transaction(nonce: u64, gas_price: u64, key: &PrivateKey) -> Transaction {
+        let tx = Transaction::new(TxData::Legacy(LegacyTx {
+            nonce,
+            gas_price: Uint256::from_uint64(gas_price),
+            gas: 21000,
+            to: Some(common::Address([0x01; 20])),
+            ..Default::default()
+        }));
+        transaction::sign_tx(&tx, &Uint256::from_uint64(1), key).unwrap()
+    }
+
+    #[test]
+    fn test_transactions_by_price_and_nonce() {
+        let (a, b, c) = (
+            crypto::to_ecdsa(&[1; 32]).unwrap(),
+            crypto::to_ecdsa(&[2; 32]).unwrap(),
+            crypto::to_ecdsa(&[3; 32]).unwrap(),
+        );
+        // The higher priced nonce 1 of a has to wait for its nonce 0
+        let pending = vec![
+            transaction(0, 5, &b),
+            transaction(1, 9, &a),
+            transaction(0, 5, &c),
+            transaction(0, 7, &a),
+        ];
+        let mut txs = TransactionsByPriceAndNonce::new(pending.clone(), 1, None);
+        let mut order = Vec::new();
+        while let Some(tx) = txs.peek() {
+            order.push(tx.hash());
+            txs.shift();
+        }
+        let want: Vec<common::Hash> = [3, 1, 0, 2].iter().map(|i| pending[*i].hash()).collect();
+        assert_eq!(order, want);
+
+        // Popping drops the rest of the account's transactions
+        let mut txs = TransactionsByPriceAndNonce::new(pending.clone(), 1, None);
+        txs.pop();
+        assert_eq!(txs.peek().map(|tx| tx.hash()), Some(pending[0].hash()));
+        txs.shift();
+        assert_eq!(txs.peek().map(|tx| tx.hash()), Some(pending[2].hash()));
+        txs.shift();
+        assert!(txs.peek().is_none());
+    }
+
+    fn keys() -> [PrivateKey; 3] {
+        [
+            crypto::to_ecdsa(&[1; 32]).unwrap(),
+            crypto::to_ecdsa(&[2; 32]).unwrap(),
+            crypto::to_ecdsa(&[3; 32]).unwrap(),
+        ]
+    }
+
+    fn transfer(nonce: u64, gas_price: u64, value: u64, key: &PrivateKey) -> Transaction {
+        let tx = Transaction::new(TxData::Legacy(LegacyTx {
+            nonce,
+            gas_price: Uint256::from_uint64(gas_price),
+            gas: params::TX_GAS,
+            to: Some(common::Address([0x99; 20])),
+            value: Uint256::from_uint64(value),
+            ..Default::default()
+        }));
+        transaction::sign_tx(&tx, &Uint256::from_uint64(1), key).unwrap()
+    }
+
+    // test_chain returns a chain sealed by a fake engine whose genesis funds
+    // the test keys with 1 ether each.
+    fn test_chain(config: ChainConfig) -> BlockChain {
+        let alloc = keys()
+            .iter()
+            .map(|key| {
+                let account = GenesisAccount {
+                    balance: Uint256::from_uint64(1_000_000_000_000_000_000),
+                    ..Default::default()
+                };
+                (key.address(), account)
+            })
+            .collect();
+        let genesis = Genesis {
+            config: ChainConfig { chain_id: 1, ..config },
+            gas_limit: 8_000_000,
+            alloc,
+            ..Default::default()
+        };
+        let db = Arc::new(MemoryDatabase::new());
+        BlockChain::new(db, &genesis, Box::new(ProofOfWork::fake()), GcMode::Archive).unwrap()
+    }
+
+    fn build(chain: &BlockChain, gas_limit: Option<u64>, pending: Vec<Transaction>) -> BuiltBlock {
+        let parent = chain.current_header().clone();
+        let mut header = prepare_header(chain.config(), &Config::default(), &parent, parent.time + 10);
+        if let Some(gas_limit) = gas_limit {
+            header.gas_limit = gas_limit;
+        }
+        let state = chain.state_at(&parent.root).unwrap();
+        build_block(chain, chain.engine(), header, state, pending, chain.get_hash_fn(&parent)).unwrap()
+    }
+
+    fn hashes(txs: &[Transaction]) -> Vec<common::Hash> {
+        txs.iter().map(|tx| tx.hash()).collect()
+    }
+
+    #[test]
+    fn test_build_block_skips_failing_sender() {
+        let chain = test_chain(ChainConfig::default());
+        let [a, b, _] = keys();
+        // The first transaction of a can't pay its value, so its second one
+        // has a nonce gap and is skipped as well.
+        let pending = vec![
+            transfer(0, 10, u64::MAX, &a),
+            transfer(1, 10, 1, &a),
+            transfer(0, 5, 1, &b),
+            transfer(1, 5, 1, &b),
+        ];
+        let built = build(&chain, None, pending.clone());
+        assert_eq!(hashes(built.block.transactions()), hashes(&pending[2..]));
+        assert_eq!(built.block.header.gas_used, 2 * params::TX_GAS);
+        assert_eq!(built.state.get_nonce(&a.address()), 0);
+        assert_eq!(built.state.get_nonce(&b.address()), 2);
+    }
+
+    #[test]
+    fn test_build_block_gas_limit() {
+        let chain = test_chain(ChainConfig::default());
+        let [a, b, c] = keys();
+        let pending = vec![transfer(0, 9, 1, &a), transfer(0, 8, 1, &b), transfer(0, 7, 1, &c)];
+        // Only two transfers fit, the cheapest one is left out
+        let built = build(&chain, Some(3 * params::TX_GAS - 1), pending.clone());
+        assert_eq!(hashes(built.block.transactions()), hashes(&pending[..2]));
+        assert_eq!(built.block.header.gas_used, 2 * params::TX_GAS);
+        assert_eq!(built.receipts.len(), 2);
+    }
+
+    #[test]
+    fn test_build_block_roots() {
+        let chain = test_chain(ChainConfig::default());
+        let [a, b, _] = keys();
+        let pending = vec![transfer(0, 9, 1, &a), transfer(0, 8, 1, &b), transfer(1, 7, 1, &a)];
+        let mut built = build(&chain, None, pending.clone());
+        let header = built.block.header.clone();
+        assert_eq!(built.block.transactions().len(), 3);
+        assert_eq!(header.tx_hash, derive_sha(built.block.transactions()));
+        assert_eq!(header.receipt_hash, derive_sha(&built.receipts));
+        assert_eq!(header.bloom, create_bloom(&built.receipts));
+        assert_eq!(header.root, built.state.intermediate_root(true).unwrap());
+        assert_eq!(built.receipts[2].cumulative_gas_used, header.gas_used);
+
+        // Processing the block from the parent state yields the same roots
+        let parent = chain.current_header().clone();
+        let state = chain.state_at(&parent.root).unwrap();
+        let result = process_block(chain.config(), chain.engine(), state, &built.block, chain.get_hash_fn(&parent)).unwrap();
+        assert_eq!(result.root, header.root);
+        assert_eq!(result.gas_used, header.gas_used);
+    }
+
+    #[test]
+    fn test_build_block_cancun_before_merge() {
+        // Cancun can't activate on a proof-of-work chain, the blob fields
+        // stay unset and the block is valid.
+        let mut chain = test_chain(ChainConfig {
+            london_block: Some(0),
+            shanghai_time: Some(0),
+            cancun_time: Some(0),
+            ..Default::default()
+        });
+        let built = build(&chain, None, Vec::new());
+        let header = &built.block.header;
+        assert!(header.excess_blob_gas.is_none());
+        assert!(header.blob_gas_used.is_none());
+        assert!(header.parent_beacon_root.is_none());
+        chain.insert_block(&built.block).unwrap();
+    }
+
+    // TestBackend mines on a BlockChain. The state of the next fail_state
+    // blocks can't be read.
+    struct TestBackend {
+        chain: BlockChain,
+        pending: Vec<Transaction>,
+        fail_state: Cell<usize>,
+    }
+
+    impl ChainHeaderReader for TestBackend {
+        fn config(&self) -> &ChainConfig {
+            self.chain.config()
+        }
+
+        fn current_header(&self) -> Header {
+            self.chain.current_header().clone()
+        }
+
+        fn get_header(&self, hash: &common::Hash, number: u64) -> Option<Header> {
+            ChainHeaderReader::get_header(&self.chain, hash, number)
+        }
+
+        fn get_header_by_number(&self, number: u64) -> Option<Header> {
+            ChainHeaderReader::get_header_by_number(&self.chain, number)
+        }
+    }
+
+    impl Backend for TestBackend {
+        fn engine(&self) -> &dyn Engine {
+            self.chain.engine()
+        }
+
+        fn state_at(&self, header: &Header) -> Result<Box<dyn StateDB>, MinerError> {
+            if self.fail_state.get() > 0 {
+                self.fail_state.set(self.fail_state.get() - 1);
+                return Err(MinerError::Backend("state unavailable".to_string()));
+            }
+            self.chain.state_at(&header.root).map_err(|err| MinerError::Backend(err.to_string()))
+        }
+
+        fn get_hash_fn(&self, header: &Header) -> GetHashFunc {
+            self.chain.get_hash_fn(header)
+        }
+
+        fn pending_transactions(&self) -> Vec<Transaction> {
+            self.pending.clone()
+        }
+
+        fn insert_block(&mut self, built: BuiltBlock) -> Result<(), MinerError> {
+            self.pending.retain(|tx| !built.block.transactions().contains(tx));
+            self.chain.insert_block(&built.block).map_err(|err| MinerError::Backend(err.to_string()))
+        }
+    }
+
+    fn test_backend(fail_state: usize) -> TestBackend {
+        let [a, ..] = keys();
+        TestBackend {
+            chain: test_chain(ChainConfig::default()),
+            pending: vec![transfer(0, 1, 1, &a)],
+            fail_state: Cell::new(fail_state),
+        }
+    }
+
+    #[test]
+    fn test_mine_now() {
+        let (miner, handle) = Miner::new(test_backend(0), Config::default());
+        let client = thread::spawn(move || {
+            let first = handle.mine_now();
+            let second = handle.mine_now();
+            handle.stop();
+            (first, second)
+        });
+        let backend = miner.run().unwrap();
+        let (first, second) = client.join().unwrap();
+        let (first, second) = (first.unwrap(), second.unwrap());
+        assert_eq!(first.number(), 1);
+        assert_eq!(first.transactions().len(), 1);
+        assert_eq!(second.number(), 2);
+        assert_eq!(second.parent_hash(), first.hash());
+        assert!(second.transactions().is_empty());
+        assert_eq!(backend.chain.current_block().hash(), second.hash());
+        assert!(backend.pending.is_empty());
+    }
+
+    #[test]
+    fn test_mine_now_error() {
+        let (miner, handle) = Miner::new(test_backend(1), Config::default());
+        let client = thread::spawn(move || {
+            let failed = handle.mine_now();
+            let mined = handle.mine_now();
+            drop(handle);
+            (failed, mined)
+        });
+        let backend = miner.run().unwrap();
+        let (failed, mined) = client.join().unwrap();
+        assert!(matches!(failed, Err(MinerError::Backend(_))));
+        assert_eq!(mined.unwrap().number(), 1);
+        assert_eq!(backend.chain.current_block().number(), 1);
+    }
+
+    #[test]
+    fn test_mine_interval() {
+        // The first two ticks fail, the loop keeps going and mines the next
+        // ones.
+        let config = Config {
+            period: Some(Duration::from_millis(20)),
+            ..Default::default()
+        };
+        let (miner, handle) = Miner::new(test_backend(2), config);
+        let client = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(500));
+            handle.stop();
+        });
+        let backend = miner.run().unwrap();
+        client.join().unwrap();
+        assert_eq!(backend.fail_state.get(), 0);
+        assert!(backend.chain.current_block().number() >= 2);
+        assert!(backend.pending.is_empty());
+    }
+}
@@ -3,7 +3,7 @@ pub type OpCode = u8;
 
 // IsPush specifies if an opcode is a PUSH opcode.
 pub fn is_push(op: OpCode) -> bool {
-    OpCode::PUSH1 <= op && op <= OpCode::PUSH32
+    (PUSH1..=PUSH32).contains(&op)
 }
 
 // 0x0 range - arithmetic ops.
@@ -70,6 +70,7 @@ pub const CHAINID: OpCode = 0x46;
 pub const SELFBALANCE: OpCode = 0x47;
 pub const BASEFEE: OpCode = 0x48;
 pub const BLOBHASH: OpCode = 0x49;
+pub const BLOBBASEFEE: OpCode = 0x4a;
 
 // 0x50 range - 'storage' and execution.
 pub const POP: OpCode = 0x50;
@@ -84,6 +85,9 @@ pub const PC: OpCode = 0x58;
 pub const MSIZE: OpCode = 0x59;
 pub const GAS: OpCode = 0x5a;
 pub const JUMPDEST: OpCode = 0x5b;
+pub const TLOAD: OpCode = 0x5c;
+pub const TSTORE: OpCode = 0x5d;
+pub const MCOPY: OpCode = 0x5e;
 pub const PUSH0: OpCode = 0x5f;
 
 // 0x60 range - pushes.
@@ -163,10 +167,6 @@ pub const LOG2: OpCode = 0xa2;
 pub const LOG3: OpCode = 0xa3;
 pub const LOG4: OpCode = 0xa4;
 
-// 0xb0 range.
-pub const TLOAD: OpCode = 0xb3;
-pub const TSTORE: OpCode = 0xb4;
-
 // 0xf0 range - closures.
 pub const CREATE: OpCode = 0xf0;
 pub const CALL: OpCode = 0xf1;
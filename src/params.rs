@@ -0,0 +1,117 @@
+// Protocol parameters shared by the interpreter, the gas table and the EVM.
+
+pub const CALL_CREATE_DEPTH: u64 = 1024; // Maximum depth of call/create stack.
+pub const STACK_LIMIT: u64 = 1024; // Maximum size of VM stack allowed.
+
+pub const MEMORY_GAS: u64 = 3; // Times the address of the (highest referenced byte in memory + 1). NOTE: referencing happens on read, write and in instructions such as RETURN and CALL.
+pub const QUAD_COEFF_DIV: u64 = 512; // Divisor for the quadratic particle of the memory cost equation.
+pub const COPY_GAS: u64 = 3; // Paid per word copied by the *COPY operations.
+pub const JUMPDEST_GAS: u64 = 1; // Once per JUMPDEST operation.
+pub const KECCAK256_GAS: u64 = 30; // Once per KECCAK256 operation.
+pub const LOG_GAS: u64 = 375; // Per LOG* operation.
+pub const LOG_DATA_GAS: u64 = 8; // Per byte in a LOG* operation's data.
+pub const LOG_TOPIC_GAS: u64 = 375; // Multiplied by the * of the LOG*, per LOG transaction. e.g. LOG0 incurs 0 * c_txLogTopicGas, LOG4 incurs 4 * c_txLogTopicGas.
+
+pub const EXP_GAS: u64 = 10; // Once per EXP instruction
+pub const EXP_BYTE_FRONTIER: u64 = 10; // was set to 10 in Frontier
+pub const EXP_BYTE_EIP158: u64 = 50; // was raised to 50 during Eip158 (Spurious Dragon)
+
+pub const SLOAD_GAS_FRONTIER: u64 = 50;
+pub const SLOAD_GAS_EIP150: u64 = 200;
+pub const SLOAD_GAS_EIP1884: u64 = 800; // Cost of SLOAD after EIP 1884 (part of Istanbul)
+pub const SLOAD_GAS_EIP2200: u64 = 800; // Cost of SLOAD after EIP 2200 (part of Istanbul)
+pub const BALANCE_GAS_FRONTIER: u64 = 20; // The cost of a BALANCE operation
+pub const BALANCE_GAS_EIP150: u64 = 400; // The cost of a BALANCE operation after Tangerine
+pub const BALANCE_GAS_EIP1884: u64 = 700; // The cost of a BALANCE operation after EIP 1884 (part of Istanbul)
+pub const EXTCODESIZE_GAS_FRONTIER: u64 = 20; // Cost of EXTCODESIZE before EIP 150 (Tangerine)
+pub const EXTCODESIZE_GAS_EIP150: u64 = 700; // Cost of EXTCODESIZE after EIP 150 (Tangerine)
+pub const EXTCODECOPY_BASE_FRONTIER: u64 = 20;
+pub const EXTCODECOPY_BASE_EIP150: u64 = 700;
+pub const EXTCODEHASH_GAS_CONSTANTINOPLE: u64 = 400; // Cost of EXTCODEHASH (introduced in Constantinople)
+pub const EXTCODEHASH_GAS_EIP1884: u64 = 700; // Cost of EXTCODEHASH after EIP 1884 (part in Istanbul)
+
+pub const SSTORE_SET_GAS: u64 = 20000; // Once per SSTORE operation.
+pub const SSTORE_RESET_GAS: u64 = 5000; // Once per SSTORE operation if the zeroness changes from zero.
+pub const SSTORE_CLEAR_GAS: u64 = 5000; // Once per SSTORE operation if the zeroness doesn't change.
+pub const SSTORE_REFUND_GAS: u64 = 15000; // Once per SSTORE operation if the zeroness changes to zero.
+
+// In EIP-2200: SstoreResetGas was 5000.
+// In EIP-2929: SstoreResetGas was changed to '5000 - COLD_SLOAD_COST'.
+// In EIP-3529: SSTORE_CLEARS_SCHEDULE is defined as SSTORE_RESET_GAS + ACCESS_LIST_STORAGE_KEY_COST
+// Which becomes: 5000 - 2100 + 1900 = 4800
+pub const SSTORE_SENTRY_GAS_EIP2200: u64 = 2300; // Minimum gas required to be present for an SSTORE call, not consumed
+pub const SSTORE_SET_GAS_EIP2200: u64 = 20000; // Once per SSTORE operation from clean zero to non-zero
+pub const SSTORE_RESET_GAS_EIP2200: u64 = 5000; // Once per SSTORE operation from clean non-zero to something else
+pub const SSTORE_CLEARS_SCHEDULE_REFUND_EIP2200: u64 = 15000; // Once per SSTORE operation for clearing an originally existing storage slot
+pub const SSTORE_CLEARS_SCHEDULE_REFUND_EIP3529: u64 =
+    SSTORE_RESET_GAS_EIP2200 - COLD_SLOAD_COST_EIP2929 + TX_ACCESS_LIST_STORAGE_KEY_GAS;
+
+pub const COLD_ACCOUNT_ACCESS_COST_EIP2929: u64 = 2600; // COLD_ACCOUNT_ACCESS_COST
+pub const COLD_SLOAD_COST_EIP2929: u64 = 2100; // COLD_SLOAD_COST
+pub const WARM_STORAGE_READ_COST_EIP2929: u64 = 100; // WARM_STORAGE_READ_COST
+
+pub const TLOAD_GAS: u64 = 100; // Cost of TLOAD (EIP-1153), same as a warm SLOAD
+pub const TSTORE_GAS: u64 = 100; // Cost of TSTORE (EIP-1153), same as a warm SLOAD
+
+pub const CALL_STIPEND: u64 = 2300; // Free gas given at beginning of call.
+pub const CALL_VALUE_TRANSFER_GAS: u64 = 9000; // Paid for CALL when the value transfer is non-zero.
+pub const CALL_NEW_ACCOUNT_GAS: u64 = 25000; // Paid for CALL when the destination address didn't exist prior.
+pub const CALL_GAS_FRONTIER: u64 = 40; // Once per CALL operation & message call transaction.
+pub const CALL_GAS_EIP150: u64 = 700; // Static portion of gas for CALL-derivates after EIP 150 (Tangerine)
+
+pub const CREATE_GAS: u64 = 32000; // Once per CREATE operation & contract-creation transaction.
+pub const CREATE2_GAS: u64 = 32000; // Once per CREATE2 operation
+pub const CREATE_DATA_GAS: u64 = 200; // Paid per byte of code deposited by a successful creation.
+pub const KECCAK256_WORD_GAS: u64 = 6; // Once per word of the KECCAK256 operation's data.
+
+pub const SELFDESTRUCT_GAS_EIP150: u64 = 5000; // Cost of SELFDESTRUCT post EIP 150 (Tangerine)
+pub const SELFDESTRUCT_REFUND_GAS: u64 = 24000; // Refunded following a selfdestruct operation.
+pub const CREATE_BY_SELFDESTRUCT_GAS: u64 = 25000;
+
+pub const MAX_CODE_SIZE: usize = 24576; // Maximum bytecode to permit for a contract (EIP-170)
+pub const MAX_INIT_CODE_SIZE: u64 = 2 * MAX_CODE_SIZE as u64; // Maximum initcode to permit in a creation transaction and create instructions (EIP-3860)
+pub const INIT_CODE_WORD_GAS: u64 = 2; // Once per word of the init code when creating a contract (EIP-3860)
+
+pub const TX_GAS: u64 = 21000; // Per transaction not creating a contract. NOTE: Not payable on data of calls between transactions.
+pub const TX_GAS_CONTRACT_CREATION: u64 = 53000; // Per transaction that creates a contract. NOTE: Not payable on data of calls between transactions.
+pub const TX_DATA_ZERO_GAS: u64 = 4; // Per byte of data attached to a transaction that equals zero. NOTE: Not payable on data of calls between transactions.
+pub const TX_DATA_NON_ZERO_GAS_FRONTIER: u64 = 68; // Per byte of data attached to a transaction that is not equal to zero. NOTE: Not payable on data of calls between transactions.
+pub const TX_DATA_NON_ZERO_GAS_EIP2028: u64 = 16; // Per byte of non zero data attached to a transaction after EIP 2028 (part in Istanbul)
+pub const TX_ACCESS_LIST_ADDRESS_GAS: u64 = 2400; // Per address specified in EIP 2930 access list
+pub const TX_ACCESS_LIST_STORAGE_KEY_GAS: u64 = 1900; // Per storage key specified in EIP 2930 access list
+
+// The Refund Quotient is the cap on how much of the used gas can be refunded. Before EIP-3529,
+// up to half the consumed gas could be refunded. Redefined as 1/5th in EIP-3529
+pub const REFUND_QUOTIENT: u64 = 2;
+pub const REFUND_QUOTIENT_EIP3529: u64 = 5;
+
+pub const BLOB_TX_BYTES_PER_FIELD_ELEMENT: u64 = 32; // Size in bytes of a field element
+pub const BLOB_TX_FIELD_ELEMENTS_PER_BLOB: u64 = 4096; // Number of field elements stored in a single data blob
+pub const BLOB_TX_BLOB_GAS_PER_BLOB: u64 = 1 << 17; // Gas consumption of a single data blob (== blob byte size)
+pub const BLOB_TX_TARGET_BLOB_GAS_PER_BLOCK: u64 = 3 * BLOB_TX_BLOB_GAS_PER_BLOB; // Target consumable blob gas for data blobs per block (for 1559-like pricing)
+pub const MAX_BLOB_GAS_PER_BLOCK: u64 = 6 * BLOB_TX_BLOB_GAS_PER_BLOB; // Maximum consumable blob gas for data blobs per block
+pub const BLOB_TX_MIN_BLOB_GASPRICE: u64 = 1; // Minimum gas price for data blobs
+pub const BLOB_TX_BLOB_GASPRICE_UPDATE_FRACTION: u64 = 3338477; // Controls the maximum rate of change for blob gas price
+
+pub const GAS_LIMIT_BOUND_DIVISOR: u64 = 1024; // The bound divisor of the gas limit, used in update calculations.
+pub const MIN_GAS_LIMIT: u64 = 5000; // Minimum the gas limit may ever be.
+
+pub const BASE_FEE_CHANGE_DENOMINATOR: u64 = 8; // Bounds the amount the base fee can change between blocks.
+pub const ELASTICITY_MULTIPLIER: u64 = 2; // Bounds the maximum gas limit an EIP-1559 block may have.
+pub const INITIAL_BASE_FEE: u64 = 1_000_000_000; // Initial base fee for EIP-1559 blocks.
+
+pub const MAXIMUM_EXTRA_DATA_SIZE: usize = 32; // Maximum size extra data may be after Genesis.
+pub const DIFFICULTY_BOUND_DIVISOR: u64 = 2048; // The bound divisor of the difficulty, used in the update calculations.
+pub const MINIMUM_DIFFICULTY: u64 = 131072; // The minimum that the difficulty may ever be.
+pub const DURATION_LIMIT: u64 = 13; // The decision boundary on the blocktime duration used to determine whether difficulty should go up or not.
+
+// SYSTEM_ADDRESS is where the system-transaction is sent from as per EIP-4788
+pub const SYSTEM_ADDRESS: common::Address = common::Address([
+    0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+    0xff, 0xfe,
+]);
+// BEACON_ROOTS_ADDRESS is the address where historical beacon roots are stored as per EIP-4788
+pub const BEACON_ROOTS_ADDRESS: common::Address = common::Address([
+    0x00, 0x0f, 0x3d, 0xf6, 0xd7, 0x32, 0x80, 0x7e, 0xf1, 0x31, 0x9f, 0xb7, 0xb8, 0xbb, 0x85, 0x22, 0xd0, 0xbe,
+    0xac, 0x02,
+]);
@@ -0,0 +1,384 @@
+// Package pow implements a proof-of-work consensus engine for the minimal
+// chain.
+//
+// The work is the Keccak-256 hash of the header's seal hash and its nonce,
+// which must be at most 2^256 / difficulty; the hash is recorded as the mix
+// digest. Unlike ethash there is no memory hard dataset, so blocks can be
+// mined and verified on a single machine. The difficulty follows the
+// Frontier, Homestead and Byzantium adjustment rules without the difficulty
+// bomb.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::block::{Block, Header, EMPTY_UNCLE_HASH};
+use crate::chain_config::ChainConfig;
+use crate::consensus::{
+    get_parent, verify_chain_fields, verify_no_merge_fields, verify_timestamp, ChainHeaderReader,
+    ConsensusError, Engine,
+};
+use crate::crypto;
+use crate::eip1559::div_uint64;
+use crate::params;
+use crate::rlp::{self, Encodable};
+use crate::state_db::StateDB;
+use crate::state_processor::accumulate_rewards;
+use uint256::Uint256;
+
+// DEFAULT_SEAL_ATTEMPTS is the number of nonces seal tries before giving up.
+// It is far above the expected number of attempts of any devnet difficulty.
+pub const DEFAULT_SEAL_ATTEMPTS: u64 = 1 << 32;
+
+// ProofOfWork is the proof-of-work consensus engine.
+#[derive(Debug, Clone)]
+pub struct ProofOfWork {
+    max_attempts: u64,
+    // bumped by abort, seals started under an older epoch stop searching
+    epoch: Arc<AtomicU64>,
+    // fake engines accept all seals and seal blocks without doing any work
+    fake: bool,
+}
+
+impl Default for ProofOfWork {
+    fn default() -> Self {
+        ProofOfWork {
+            max_attempts: DEFAULT_SEAL_ATTEMPTS,
+            epoch: Arc::new(AtomicU64::new(0)),
+            fake: false,
+        }
+    }
+}
+
+impl ProofOfWork {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    // fake creates an engine that checks the difficulty but accepts any seal,
+    // and seals blocks immediately. It is meant for tests that need many
+    // blocks.
+    pub fn fake() -> Self {
+        ProofOfWork {
+            fake: true,
+            ..Default::default()
+        }
+    }
+
+    // with_seal_attempts returns an engine whose seal tries at most attempts
+    // nonces.
+    pub fn with_seal_attempts(mut self, attempts: u64) -> Self {
+        self.max_attempts = attempts;
+        self
+    }
+
+    // abort stops the seals in progress on this engine and its clones, which
+    // return ConsensusError::SealAborted. Later seals are not affected.
+    pub fn abort(&self) {
+        self.epoch.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+// seal_hash returns the hash of a block prior to it being sealed, that is of
+// all the header fields but the mix digest and the nonce.
+pub fn seal_hash(header: &Header) -> common::Hash {
+    let mut payload = Vec::new();
+    header.parent_hash.rlp_append(&mut payload);
+    header.uncle_hash.rlp_append(&mut payload);
+    header.coinbase.rlp_append(&mut payload);
+    header.root.rlp_append(&mut payload);
+    header.tx_hash.rlp_append(&mut payload);
+    header.receipt_hash.rlp_append(&mut payload);
+    header.bloom.rlp_append(&mut payload);
+    header.difficulty.rlp_append(&mut payload);
+    header.number.rlp_append(&mut payload);
+    header.gas_limit.rlp_append(&mut payload);
+    header.gas_used.rlp_append(&mut payload);
+    header.time.rlp_append(&mut payload);
+    rlp::append_bytes(&header.extra, &mut payload);
+    if let Some(base_fee) = &header.base_fee {
+        base_fee.rlp_append(&mut payload);
+    }
+    let mut enc = Vec::new();
+    rlp::append_list_payload(&payload, &mut enc);
+    crypto::keccak256_hash(&enc)
+}
+
+// compute_pow returns the proof-of-work digest of a seal hash and a nonce.
+fn compute_pow(hash: &common::Hash, nonce: u64) -> common::Hash {
+    let mut seed = Vec::with_capacity(40);
+    seed.extend_from_slice(hash.as_bytes());
+    seed.extend_from_slice(&nonce.to_be_bytes());
+    crypto::keccak256_hash(&seed)
+}
+
+// meets_target reports whether digest <= 2^256 / difficulty, which holds
+// exactly when digest * difficulty doesn't overflow 256 bits.
+fn meets_target(digest: &common::Hash, difficulty: &Uint256) -> bool {
+    Uint256::from_bytes(digest.as_bytes())
+        .checked_mul(difficulty)
+        .is_some()
+}
+
+// calc_difficulty is the difficulty adjustment algorithm. It returns the
+// difficulty that a new block should have when created at time given the
+// parent block's time and difficulty.
+pub fn calc_difficulty(config: &ChainConfig, time: u64, parent: &Header) -> Uint256 {
+    let next = parent.number + 1;
+    if config.is_byzantium(next) {
+        calc_difficulty_byzantium(time, parent)
+    } else if config.is_homestead(next) {
+        calc_difficulty_homestead(time, parent)
+    } else {
+        calc_difficulty_frontier(time, parent)
+    }
+}
+
+// calc_difficulty_byzantium is the difficulty adjustment algorithm of
+// EIP-100. It takes the uncles of the parent into account.
+fn calc_difficulty_byzantium(time: u64, parent: &Header) -> Uint256 {
+    // (2 if len(parent_uncles) else 1) - (block_timestamp - parent_timestamp) // 9
+    let uncles = if parent.uncle_hash == EMPTY_UNCLE_HASH {
+        1
+    } else {
+        2
+    };
+    let factor = uncles - (time.saturating_sub(parent.time) / 9) as i64;
+    adjust_difficulty(&parent.difficulty, factor)
+}
+
+// calc_difficulty_homestead is the difficulty adjustment algorithm of
+// EIP-2.
+fn calc_difficulty_homestead(time: u64, parent: &Header) -> Uint256 {
+    // 1 - (block_timestamp - parent_timestamp) // 10
+    let factor = 1 - (time.saturating_sub(parent.time) / 10) as i64;
+    adjust_difficulty(&parent.difficulty, factor)
+}
+
+// calc_difficulty_frontier is the difficulty adjustment algorithm of the
+// Frontier release: the difficulty goes up if the block was mined faster
+// than DURATION_LIMIT and down otherwise.
+fn calc_difficulty_frontier(time: u64, parent: &Header) -> Uint256 {
+    let factor = if time.saturating_sub(parent.time) < params::DURATION_LIMIT {
+        1
+    } else {
+        -1
+    };
+    adjust_difficulty(&parent.difficulty, factor)
+}
+
+// adjust_difficulty returns
+// max(parent_diff + parent_diff // 2048 * max(factor, -99), MINIMUM_DIFFICULTY).
+fn adjust_difficulty(parent_difficulty: &Uint256, factor: i64) -> Uint256 {
+    let factor = factor.max(-99);
+    let step = div_uint64(parent_difficulty, params::DIFFICULTY_BOUND_DIVISOR)
+        .checked_mul(&Uint256::from_uint64(factor.unsigned_abs()))
+        .unwrap_or_default();
+    let difficulty = if factor >= 0 {
+        parent_difficulty.checked_add(&step).unwrap_or_default()
+    } else {
+        parent_difficulty.checked_sub(&step).unwrap_or_default()
+    };
+    let minimum = Uint256::from_uint64(params::MINIMUM_DIFFICULTY);
+    if difficulty < minimum {
+        minimum
+    } else {
+        difficulty
+    }
+}
+
+// verify_seal checks whether a block satisfies the proof-of-work
+// requirements of its difficulty.
+pub fn verify_seal(header: &Header) -> Result<(), ConsensusError> {
+    // Ensure that we have a valid difficulty for the block
+    if header.difficulty.is_zero() {
+        return Err(ConsensusError::InvalidDifficulty {
+            have: header.difficulty.clone(),
+            want: Uint256::from_uint64(params::MINIMUM_DIFFICULTY),
+        });
+    }
+    let digest = compute_pow(&seal_hash(header), u64::from_be_bytes(header.nonce));
+    if digest != header.mix_digest {
+        return Err(ConsensusError::InvalidMixDigest);
+    }
+    if !meets_target(&digest, &header.difficulty) {
+        return Err(ConsensusError::InvalidPoW);
+    }
+    Ok(())
+}
+
+impl Engine for ProofOfWork {
+    // author implements Engine, returning the header's coinbase as the
+    // proof-of-work verified author of the block.
+    fn author(&self, header: &Header) -> Result<common::Address, ConsensusError> {
+        Ok(header.coinbase)
+    }
+
+    fn verify_header(
+        &self,
+        chain: &dyn ChainHeaderReader,
+        header: &Header,
+    ) -> Result<(), ConsensusError> {
+        let parent = get_parent(chain, header)?;
+        verify_timestamp(header)?;
+        verify_chain_fields(chain.config(), &parent, header)?;
+        verify_no_merge_fields(header)?;
+        // Verify the block's difficulty based on its timestamp and parent's difficulty
+        let expected = self.calc_difficulty(chain, header.time, &parent);
+        if expected != header.difficulty {
+            return Err(ConsensusError::InvalidDifficulty {
+                have: header.difficulty.clone(),
+                want: expected,
+            });
+        }
+        if self.fake {
+            return Ok(());
+        }
+        verify_seal(header)
+    }
+
+    // prepare implements Engine, initializing the difficulty field of a
+    // header to conform to the proof-of-work protocol.
+    fn prepare(
+        &self,
+        chain: &dyn ChainHeaderReader,
+        header: &mut Header,
+    ) -> Result<(), ConsensusError> {
+        let parent = get_parent(chain, header)?;
+        header.difficulty = self.calc_difficulty(chain, header.time, &parent);
+        Ok(())
+    }
+
+    // finalize implements Engine, accumulating the block and uncle rewards.
+    fn finalize(
+        &self,
+        config: &ChainConfig,
+        header: &Header,
+        state: &mut dyn StateDB,
+        uncles: &[Header],
+    ) {
+        let rules = config.rules(header.number, false, header.time);
+        accumulate_rewards(&rules, state, header, uncles);
+    }
+
+    // seal implements Engine, searching for a nonce that satisfies the
+    // block's difficulty requirements. It gives up after max_attempts nonces
+    // or when the engine is aborted.
+    fn seal(&self, _chain: &dyn ChainHeaderReader, block: Block) -> Result<Block, ConsensusError> {
+        if self.fake {
+            return Ok(block);
+        }
+        let epoch = self.epoch.load(Ordering::SeqCst);
+        let mut header = block.header;
+        let hash = seal_hash(&header);
+        // Start from a time based nonce so that concurrent miners don't
+        // search the same range.
+        let mut nonce = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_nanos() as u64);
+        for _ in 0..self.max_attempts {
+            if self.epoch.load(Ordering::Relaxed) != epoch {
+                return Err(ConsensusError::SealAborted);
+            }
+            let digest = compute_pow(&hash, nonce);
+            if meets_target(&digest, &header.difficulty) {
+                header.nonce = nonce.to_be_bytes();
+                header.mix_digest = digest;
+                return Ok(Block::with_body(header, block.body));
+            }
+            nonce = nonce.wrapping_add(1);
+        }
+        Err(ConsensusError::NoSealFound {
+            attempts: self.max_attempts,
+        })
+    }
+
+    fn calc_difficulty(
+        &self,
+        chain: &dyn ChainHeaderReader,
+        time: u64,
+        parent: &Header,
+    ) -> Uint256 {
+        calc_difficulty(chain.config(), time, parent)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+    use std::time::Duration;
+
+    struct NoChain(ChainConfig);
+
+    impl ChainHeaderReader for NoChain {
+        fn config(&self) -> &ChainConfig {
+            &self.0
+        }
+
+        fn current_header(&self) -> Header {
+            Header::default()
+        }
+
+        fn get_header(&self, _hash: &common::Hash, _number: u64) -> Option<Header> {
+            None
+        }
+
+        fn get_header_by_number(&self, _number: u64) -> Option<Header> {
+            None
+        }
+    }
+
+    fn block(difficulty: Uint256) -> Block {
+        let header = Header {
+            number: 1,
+            difficulty,
+            ..Default::default()
+        };
+        Block::new(header, Vec::new(), Vec::new(), None)
+    }
+
+    #[test]
+    fn test_seal() {
+        let chain = NoChain(ChainConfig::default());
+        let sealed = ProofOfWork::new().seal(&chain, block(Uint256::from_uint64(16))).unwrap();
+        verify_seal(&sealed.header).unwrap();
+    }
+
+    #[test]
+    fn test_fake_seal() {
+        let chain = NoChain(ChainConfig::default());
+        let unsealed = block(Uint256::from_uint64(u64::MAX));
+        let sealed = ProofOfWork::fake().seal(&chain, unsealed.clone()).unwrap();
+        assert_eq!(sealed.hash(), unsealed.hash());
+        assert_eq!(verify_seal(&sealed.header).unwrap_err(), ConsensusError::InvalidMixDigest);
+    }
+
+    #[test]
+    fn test_seal_attempts() {
+        let chain = NoChain(ChainConfig::default());
+        let engine = ProofOfWork::new().with_seal_attempts(8);
+        // A 2^64 difficulty is out of reach in a handful of attempts.
+        let difficulty = Uint256::from_uint64(u64::MAX);
+        assert_eq!(
+            engine.seal(&chain, block(difficulty)).unwrap_err(),
+            ConsensusError::NoSealFound { attempts: 8 }
+        );
+    }
+
+    #[test]
+    fn test_seal_abort() {
+        let engine = ProofOfWork::new();
+        let sealer = engine.clone();
+        let handle = thread::spawn(move || {
+            let chain = NoChain(ChainConfig::default());
+            sealer.seal(&chain, block(Uint256::from_uint64(u64::MAX)))
+        });
+        // Keep aborting in case the seal hadn't started yet.
+        while !handle.is_finished() {
+            engine.abort();
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(handle.join().unwrap().unwrap_err(), ConsensusError::SealAborted);
+    }
+}
@@ -0,0 +1,226 @@
+// Account and storage proofs in the shape of the eth_getProof result
+// (EIP-1186). An account proof proves the RLP encoded account under the
+// keccak256 hash of the address in the state trie; every storage proof proves
+// the RLP encoded slot value under the keccak256 hash of the slot key in the
+// account's storage trie.
+
+use serde::{Deserialize, Serialize};
+
+use crate::crypto;
+use crate::hexutil;
+use crate::memory_state_db::StateAccount;
+use crate::rlp;
+use crate::trie::{self, TrieError, EMPTY_ROOT_HASH};
+use uint256::Uint256;
+
+// StorageResult provides a proof for a key-value pair.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StorageResult {
+    #[serde(with = "hexutil::hash")]
+    pub key: common::Hash,
+    #[serde(with = "hexutil::big")]
+    pub value: Uint256,
+    #[serde(with = "hexutil::bytes_list")]
+    pub proof: Vec<Vec<u8>>,
+}
+
+// AccountResult is the result of a get_proof call. Its JSON encoding is the
+// one of the eth_getProof result.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AccountResult {
+    #[serde(with = "hexutil::address")]
+    pub address: common::Address,
+    #[serde(with = "hexutil::bytes_list")]
+    pub account_proof: Vec<Vec<u8>>,
+    #[serde(with = "hexutil::big")]
+    pub balance: Uint256,
+    #[serde(with = "hexutil::hash")]
+    pub code_hash: common::Hash,
+    #[serde(with = "hexutil::quantity")]
+    pub nonce: u64,
+    #[serde(with = "hexutil::hash")]
+    pub storage_hash: common::Hash,
+    pub storage_proof: Vec<StorageResult>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ProofError {
+    Trie(TrieError),
+    Rlp(rlp::DecoderError),
+    // AccountMismatch is returned when the proven account differs from the
+    // fields in the result.
+    AccountMismatch,
+    // StorageMismatch is returned when a proven slot value differs from the
+    // value in the result.
+    StorageMismatch(common::Hash),
+}
+
+impl From<TrieError> for ProofError {
+    fn from(err: TrieError) -> Self {
+        ProofError::Trie(err)
+    }
+}
+
+impl From<rlp::DecoderError> for ProofError {
+    fn from(err: rlp::DecoderError) -> Self {
+        ProofError::Rlp(err)
+    }
+}
+
+impl AccountResult {
+    // account returns the account as committed to the state trie.
+    pub fn account(&self) -> StateAccount {
+        StateAccount {
+            nonce: self.nonce,
+            balance: self.balance.clone(),
+            root: self.storage_hash,
+            code_hash: self.code_hash,
+        }
+    }
+
+    // verify checks the account proof against state_root and every storage
+    // proof against the account's storage hash.
+    pub fn verify(&self, state_root: &common::Hash) -> Result<(), ProofError> {
+        let key = crypto::keccak256_hash(self.address.as_bytes());
+        match trie::verify_proof(state_root, key.as_bytes(), &self.account_proof)? {
+            Some(blob) => {
+                if rlp::decode::<StateAccount>(&blob)? != self.account() {
+                    return Err(ProofError::AccountMismatch);
+                }
+            }
+            // A missing account must be reported as empty.
+            None => {
+                if self.account() != StateAccount::default() {
+                    return Err(ProofError::AccountMismatch);
+                }
+            }
+        }
+
+        for slot in self.storage_proof.iter() {
+            let key = crypto::keccak256_hash(slot.key.as_bytes());
+            let value = if self.storage_hash == EMPTY_ROOT_HASH {
+                None
+            } else {
+                trie::verify_proof(&self.storage_hash, key.as_bytes(), &slot.proof)?
+            };
+            let value = match value {
+                Some(blob) => rlp::decode::<Uint256>(&blob)?,
+                None => Uint256::default(),
+            };
+            if value != slot.value {
+                return Err(ProofError::StorageMismatch(slot.key));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    use crate::chain_config::MAINNET_CHAIN_CONFIG;
+    use crate::ethdb::{KeyValueStore, MemoryDatabase};
+    use crate::genesis::{Genesis, MAINNET_GENESIS_ROOT};
+    use crate::memory_state_db::MemoryStateDB;
+    use crate::state_db::StateDB;
+    use crate::trie::Database;
+
+    // MAINNET_PROOFS holds the eth_getProof results at the mainnet genesis
+    // block for a funded account and for an absent one, with the proof of
+    // slot 0.
+    const MAINNET_PROOFS: &str = include_str!("testdata/proof/mainnet_genesis.json");
+
+    fn mainnet_genesis_state() -> MemoryStateDB {
+        let mut genesis = Genesis::from_json(include_str!("testdata/genesis/mainnet.json")).unwrap();
+        genesis.config = MAINNET_CHAIN_CONFIG;
+        let disk: Arc<dyn KeyValueStore> = Arc::new(MemoryDatabase::new());
+        let block = genesis.commit(disk.clone()).unwrap();
+        MemoryStateDB::open(block.header.root, Database::with_disk(disk)).unwrap()
+    }
+
+    fn slot(n: u8) -> common::Hash {
+        let mut hash = common::Hash::default();
+        hash.0[31] = n;
+        hash
+    }
+
+    #[test]
+    fn test_mainnet_genesis_proofs() {
+        let proofs: Vec<AccountResult> = serde_json::from_str(MAINNET_PROOFS).unwrap();
+        let json: serde_json::Value = serde_json::from_str(MAINNET_PROOFS).unwrap();
+        let mut state = mainnet_genesis_state();
+        for (i, want) in proofs.iter().enumerate() {
+            want.verify(&MAINNET_GENESIS_ROOT).unwrap();
+            let got = state.get_proof(&want.address, &[slot(0)]).unwrap();
+            assert_eq!(&got, want);
+            assert_eq!(serde_json::to_value(&got).unwrap(), json[i]);
+        }
+        assert_eq!(proofs[0].balance, Uint256::from_dec_str("200000000000000000000").unwrap());
+        assert_eq!(proofs[1].account(), StateAccount::default());
+    }
+
+    #[test]
+    fn test_prove_verify() {
+        let contract = common::Address([0xcc; 20]);
+        let mut state = MemoryStateDB::new();
+        state.set_balance(&contract, Uint256::from_uint64(1000));
+        state.set_nonce(&contract, 3);
+        state.set_state(&contract, slot(1), slot(0x2a));
+        state.set_state(&contract, slot(2), slot(0x2b));
+        for i in 0..32 {
+            state.set_balance(&common::Address([i; 20]), Uint256::from_uint64(i as u64 + 1));
+        }
+        let root = state.commit(true).unwrap();
+
+        // A present account with a present and an absent slot
+        let result = state.get_proof(&contract, &[slot(1), slot(3)]).unwrap();
+        assert_eq!(result.nonce, 3);
+        assert_eq!(result.storage_proof[0].value, Uint256::from_uint64(0x2a));
+        assert!(result.storage_proof[1].value.is_zero());
+        assert!(!result.storage_proof[1].proof.is_empty());
+        result.verify(&root).unwrap();
+
+        // An absent account proves the empty account
+        let absent = state.get_proof(&common::Address([0xee; 20]), &[slot(1)]).unwrap();
+        assert_eq!(absent.account(), StateAccount::default());
+        absent.verify(&root).unwrap();
+
+        // The proofs don't hold for another root
+        let other = common::Hash([0x01; 32]);
+        assert_eq!(result.verify(&other), Err(ProofError::Trie(TrieError::MissingNode(other))));
+
+        // Tampered fields don't match the proven account or slot
+        let mut bad = result.clone();
+        bad.balance = Uint256::from_uint64(1001);
+        assert_eq!(bad.verify(&root), Err(ProofError::AccountMismatch));
+        let mut bad = absent.clone();
+        bad.nonce = 1;
+        assert_eq!(bad.verify(&root), Err(ProofError::AccountMismatch));
+        let mut bad = result.clone();
+        bad.storage_proof[0].value = Uint256::from_uint64(0x2b);
+        assert_eq!(bad.verify(&root), Err(ProofError::StorageMismatch(slot(1))));
+        let mut bad = result.clone();
+        bad.storage_proof[1].value = Uint256::one();
+        assert_eq!(bad.verify(&root), Err(ProofError::StorageMismatch(slot(3))));
+        // A proof of another slot doesn't prove this one
+        let mut bad = result.clone();
+        bad.storage_proof[0].key = slot(2);
+        assert!(bad.verify(&root).is_err());
+
+        // Tampered or truncated proofs break the hash chain
+        let mut bad = result.clone();
+        let last = bad.account_proof.len() - 1;
+        let node = bad.account_proof[last].len() - 1;
+        bad.account_proof[last][node] ^= 1;
+        assert!(matches!(bad.verify(&root), Err(ProofError::Trie(TrieError::MissingNode(_)))));
+        let mut bad = result.clone();
+        bad.account_proof.pop();
+        assert!(matches!(bad.verify(&root), Err(ProofError::Trie(TrieError::MissingNode(_)))));
+        let mut bad = result;
+        bad.storage_proof[0].proof.clear();
+        assert!(matches!(bad.verify(&root), Err(ProofError::Trie(TrieError::MissingNode(_)))));
+    }
+}
@@ -0,0 +1,197 @@
+// Pruner removes trie nodes that are no longer reachable from the recent
+// state roots.
+//
+// Every block commit leaves the nodes replaced on the path to each modified
+// account and slot behind. In archive mode those nodes are kept, so the state
+// at every historical root stays available. In pruned mode the pruner keeps
+// the roots of the last `retain` blocks and, once every `sweep_interval`
+// expired roots, marks all nodes reachable from them (including the storage
+// tries referenced by the accounts), then sweeps every other trie node from
+// the store. Batching the sweeps bounds the garbage left behind to the nodes
+// of the last `sweep_interval` expired roots while amortizing the full walk.
+
+use std::collections::HashSet;
+use std::io;
+use std::sync::Arc;
+
+use crate::ethdb::{Batch, KeyValueStore, IDEAL_BATCH_SIZE};
+use crate::memory_state_db::StateAccount;
+use crate::rawdb::{self, RetainedRoot};
+use crate::rlp;
+use crate::trie::{self, Node, TrieError, EMPTY_ROOT_HASH};
+
+// DEFAULT_RETAIN is the number of recent state roots kept in pruned mode.
+pub const DEFAULT_RETAIN: u64 = 128;
+
+// DEFAULT_SWEEP_INTERVAL is the number of roots that leave the retention
+// window between two sweeps.
+pub const DEFAULT_SWEEP_INTERVAL: u64 = 32;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GcMode {
+    // Archive keeps every historical state.
+    Archive,
+    // Pruned keeps the state of the last `retain` blocks.
+    Pruned { retain: u64 },
+}
+
+impl Default for GcMode {
+    fn default() -> Self {
+        GcMode::Pruned { retain: DEFAULT_RETAIN }
+    }
+}
+
+pub struct Pruner {
+    mode: GcMode,
+    disk: Arc<dyn KeyValueStore>,
+    roots: Vec<RetainedRoot>,
+    sweep_interval: u64,
+    // number of roots expired since the last sweep
+    expired: u64,
+}
+
+impl Pruner {
+    pub fn new(mode: GcMode, disk: Arc<dyn KeyValueStore>) -> io::Result<Self> {
+        let roots = rawdb::read_pruner_roots(disk.as_ref())?;
+        Ok(Pruner {
+            mode,
+            disk,
+            roots,
+            sweep_interval: DEFAULT_SWEEP_INTERVAL,
+            expired: 0,
+        })
+    }
+
+    pub fn mode(&self) -> GcMode {
+        self.mode
+    }
+
+    // set_sweep_interval sets the number of expired roots between two sweeps.
+    // An interval of 1 sweeps as soon as a root leaves the retention window.
+    pub fn set_sweep_interval(&mut self, interval: u64) {
+        self.sweep_interval = interval.max(1);
+    }
+
+    // on_commit records the state root committed for block number and, in
+    // pruned mode, sweeps the nodes that fell out of the retention window once
+    // sweep_interval roots have expired. It returns the number of deleted
+    // nodes.
+    pub fn on_commit(&mut self, number: u64, root: common::Hash) -> Result<usize, TrieError> {
+        let retain = match self.mode {
+            GcMode::Archive => return Ok(0),
+            GcMode::Pruned { retain } => retain,
+        };
+        // Side chain blocks commit other roots at heights already seen, keep
+        // all of them so the canonical state survives until it expires.
+        if !self.roots.iter().any(|r| r.root == root) {
+            self.roots.push(RetainedRoot { number, root });
+        }
+        let head = self.roots.iter().map(|r| r.number).max().unwrap_or(number);
+        let oldest = head.saturating_sub(retain.saturating_sub(1));
+        let retained = self.roots.len();
+        self.roots.retain(|r| r.number >= oldest);
+        self.expired += (retained - self.roots.len()) as u64;
+
+        let mut deleted = 0;
+        let mut batch = Batch::new();
+        if self.expired >= self.sweep_interval {
+            deleted = self.sweep(&mut batch)?;
+            self.expired = 0;
+        }
+        rawdb::write_pruner_roots(&mut batch, &self.roots);
+        self.disk.write_batch(&batch)?;
+        Ok(deleted)
+    }
+
+    // sweep adds the deletion of every unreachable trie node to batch.
+    fn sweep(&self, batch: &mut Batch) -> Result<usize, TrieError> {
+        let mut marked = HashSet::new();
+        for retained in self.roots.iter() {
+            mark(self.disk.as_ref(), retained.root, &mut marked)?;
+        }
+        let mut deleted = 0;
+        for hash in rawdb::read_trie_node_hashes(self.disk.as_ref())? {
+            if !marked.contains(&hash) {
+                rawdb::delete_trie_node(batch, &hash);
+                deleted += 1;
+                // Flush large sweeps in parts, deletions are safe to apply
+                // out of order.
+                if batch.value_size() >= IDEAL_BATCH_SIZE {
+                    self.disk.write_batch(batch)?;
+                    batch.reset();
+                }
+            }
+        }
+        Ok(deleted)
+    }
+}
+
+// mark adds the hashes of all nodes of the state trie under root, and of the
+// storage tries of its accounts, to marked.
+fn mark(disk: &dyn KeyValueStore, root: common::Hash, marked: &mut HashSet<common::Hash>) -> Result<(), TrieError> {
+    let mut storage_roots = Vec::new();
+    mark_trie(disk, root, marked, &mut |value| {
+        if let Ok(account) = rlp::decode::<StateAccount>(value) {
+            storage_roots.push(account.root);
+        }
+    })?;
+    for storage_root in storage_roots {
+        mark_trie(disk, storage_root, marked, &mut |_| {})?;
+    }
+    Ok(())
+}
+
+// mark_trie walks the trie under root, adding every stored node to marked and
+// calling on_value for every value. Subtries whose root is already marked are
+// shared with an earlier walk and skipped.
+fn mark_trie(
+    disk: &dyn KeyValueStore,
+    root: common::Hash,
+    marked: &mut HashSet<common::Hash>,
+    on_value: &mut dyn FnMut(&[u8]),
+) -> Result<(), TrieError> {
+    if root == EMPTY_ROOT_HASH {
+        return Ok(());
+    }
+    let mut stack = vec![Node::Hash(root)];
+    while let Some(node) = stack.pop() {
+        match node {
+            Node::Empty => {}
+            Node::Hash(hash) => {
+                if !marked.insert(hash) {
+                    continue;
+                }
+                match rawdb::read_trie_node(disk, &hash)? {
+                    Some(blob) => stack.push(trie::decode_node(&blob)?),
+                    None => return Err(TrieError::MissingNode(hash)),
+                }
+            }
+            Node::Leaf { value, .. } => on_value(&value),
+            Node::Extension { child, .. } => stack.push(*child),
+            Node::Branch { children, value } => {
+                if let Some(value) = value {
+                    on_value(&value);
+                }
+                stack.extend(*children);
+            }
+        }
+    }
+    Ok(())
+}
+
+// compact is the maintenance command to shrink a store: in pruned mode it
+// first sweeps the nodes unreachable from the retained roots, then rewrites
+// the store to reclaim the space held by deleted entries.
+pub fn compact(mode: GcMode, disk: Arc<dyn KeyValueStore>) -> Result<usize, TrieError> {
+    let mut deleted = 0;
+    if let GcMode::Pruned { .. } = mode {
+        let pruner = Pruner::new(mode, disk.clone())?;
+        if !pruner.roots.is_empty() {
+            let mut batch = Batch::new();
+            deleted = pruner.sweep(&mut batch)?;
+            disk.write_batch(&batch)?;
+        }
+    }
+    disk.compact()?;
+    Ok(deleted)
+}
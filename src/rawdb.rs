@@ -0,0 +1,418 @@
+// Package rawdb contains a collection of low level database accessors. It
+// defines the key schema of the data stored in a KeyValueStore: trie nodes,
+// contract code, block data and the markers needed to resume after a restart.
+
+use std::io;
+
+use crate::block::{Block, Body, Header};
+use crate::ethdb::{Batch, KeyValueStore};
+use crate::receipt::Receipt;
+use crate::rlp::{self, Decodable, DecoderError};
+use uint256::Uint256;
+
+// The fields below define the low level database schema prefixing.
+const HEAD_STATE_ROOT_KEY: &[u8] = b"LastStateRoot"; // tracks the latest committed state root
+const HEAD_BLOCK_KEY: &[u8] = b"LastBlock"; // tracks the latest known full block's hash
+const PRUNER_ROOTS_KEY: &[u8] = b"PrunerRoots"; // tracks the state roots retained by the pruner
+const SNAPSHOT_ROOT_KEY: &[u8] = b"SnapshotRoot"; // tracks the state root of the flat snapshot on disk
+
+const TRIE_NODE_PREFIX: &[u8] = b"n"; // TRIE_NODE_PREFIX + hash -> trie node
+const CODE_PREFIX: &[u8] = b"c"; // CODE_PREFIX + code hash -> contract code
+const SNAPSHOT_ACCOUNT_PREFIX: &[u8] = b"a"; // SNAPSHOT_ACCOUNT_PREFIX + account hash -> account trie value
+const SNAPSHOT_STORAGE_PREFIX: &[u8] = b"o"; // SNAPSHOT_STORAGE_PREFIX + account hash + storage hash -> storage trie value
+const HEADER_PREFIX: &[u8] = b"h"; // HEADER_PREFIX + num (u64 big endian) + hash -> header
+const HEADER_HASH_SUFFIX: &[u8] = b"n"; // HEADER_PREFIX + num (u64 big endian) + HEADER_HASH_SUFFIX -> hash
+const HEADER_TD_SUFFIX: &[u8] = b"t"; // HEADER_PREFIX + num (u64 big endian) + hash + HEADER_TD_SUFFIX -> td
+const HEADER_NUMBER_PREFIX: &[u8] = b"H"; // HEADER_NUMBER_PREFIX + hash -> num (u64 big endian)
+const BLOCK_BODY_PREFIX: &[u8] = b"b"; // BLOCK_BODY_PREFIX + num (u64 big endian) + hash -> block body
+const BLOCK_RECEIPTS_PREFIX: &[u8] = b"r"; // BLOCK_RECEIPTS_PREFIX + num (u64 big endian) + hash -> block receipts
+const TX_LOOKUP_PREFIX: &[u8] = b"l"; // TX_LOOKUP_PREFIX + hash -> transaction lookup metadata (block number)
+
+fn concat_key(parts: &[&[u8]]) -> Vec<u8> {
+    parts.concat()
+}
+
+pub fn trie_node_key(hash: &common::Hash) -> Vec<u8> {
+    concat_key(&[TRIE_NODE_PREFIX, hash.as_bytes()])
+}
+
+pub fn code_key(hash: &common::Hash) -> Vec<u8> {
+    concat_key(&[CODE_PREFIX, hash.as_bytes()])
+}
+
+pub fn snapshot_account_key(account_hash: &common::Hash) -> Vec<u8> {
+    concat_key(&[SNAPSHOT_ACCOUNT_PREFIX, account_hash.as_bytes()])
+}
+
+// snapshot_storage_prefix is the key prefix of all snapshot storage slots of
+// an account.
+pub fn snapshot_storage_prefix(account_hash: &common::Hash) -> Vec<u8> {
+    concat_key(&[SNAPSHOT_STORAGE_PREFIX, account_hash.as_bytes()])
+}
+
+pub fn snapshot_storage_key(account_hash: &common::Hash, storage_hash: &common::Hash) -> Vec<u8> {
+    concat_key(&[
+        SNAPSHOT_STORAGE_PREFIX,
+        account_hash.as_bytes(),
+        storage_hash.as_bytes(),
+    ])
+}
+
+pub fn header_key(number: u64, hash: &common::Hash) -> Vec<u8> {
+    concat_key(&[HEADER_PREFIX, &number.to_be_bytes(), hash.as_bytes()])
+}
+
+pub fn header_hash_key(number: u64) -> Vec<u8> {
+    concat_key(&[HEADER_PREFIX, &number.to_be_bytes(), HEADER_HASH_SUFFIX])
+}
+
+pub fn header_td_key(number: u64, hash: &common::Hash) -> Vec<u8> {
+    concat_key(&[
+        HEADER_PREFIX,
+        &number.to_be_bytes(),
+        hash.as_bytes(),
+        HEADER_TD_SUFFIX,
+    ])
+}
+
+pub fn header_number_key(hash: &common::Hash) -> Vec<u8> {
+    concat_key(&[HEADER_NUMBER_PREFIX, hash.as_bytes()])
+}
+
+pub fn block_body_key(number: u64, hash: &common::Hash) -> Vec<u8> {
+    concat_key(&[BLOCK_BODY_PREFIX, &number.to_be_bytes(), hash.as_bytes()])
+}
+
+pub fn block_receipts_key(number: u64, hash: &common::Hash) -> Vec<u8> {
+    concat_key(&[
+        BLOCK_RECEIPTS_PREFIX,
+        &number.to_be_bytes(),
+        hash.as_bytes(),
+    ])
+}
+
+pub fn tx_lookup_key(hash: &common::Hash) -> Vec<u8> {
+    concat_key(&[TX_LOOKUP_PREFIX, hash.as_bytes()])
+}
+
+fn invalid_data(err: DecoderError) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, err.to_string())
+}
+
+// decode_entry decodes an RLP encoded database entry, reporting corrupt
+// entries as InvalidData errors.
+fn decode_entry<T: Decodable>(data: Option<Vec<u8>>) -> io::Result<Option<T>> {
+    data.map(|data| rlp::decode(&data).map_err(invalid_data))
+        .transpose()
+}
+
+fn to_hash(data: &[u8]) -> Option<common::Hash> {
+    if data.len() != 32 {
+        return None;
+    }
+    let mut hash = [0u8; 32];
+    hash.copy_from_slice(data);
+    Some(common::Hash(hash))
+}
+
+pub fn read_trie_node(db: &dyn KeyValueStore, hash: &common::Hash) -> io::Result<Option<Vec<u8>>> {
+    db.get(&trie_node_key(hash))
+}
+
+pub fn write_trie_node(batch: &mut Batch, hash: &common::Hash, node: &[u8]) {
+    batch.put(&trie_node_key(hash), node);
+}
+
+pub fn delete_trie_node(batch: &mut Batch, hash: &common::Hash) {
+    batch.delete(&trie_node_key(hash));
+}
+
+// read_trie_node_hashes returns the hashes of all trie nodes in the store.
+pub fn read_trie_node_hashes(db: &dyn KeyValueStore) -> io::Result<Vec<common::Hash>> {
+    Ok(db
+        .keys(TRIE_NODE_PREFIX)?
+        .iter()
+        .filter(|key| key.len() == TRIE_NODE_PREFIX.len() + 32)
+        .filter_map(|key| to_hash(&key[TRIE_NODE_PREFIX.len()..]))
+        .collect())
+}
+
+pub fn read_code(db: &dyn KeyValueStore, hash: &common::Hash) -> io::Result<Option<Vec<u8>>> {
+    db.get(&code_key(hash))
+}
+
+pub fn write_code(batch: &mut Batch, hash: &common::Hash, code: &[u8]) {
+    batch.put(&code_key(hash), code);
+}
+
+// read_head_state_root retrieves the root of the last committed state.
+pub fn read_head_state_root(db: &dyn KeyValueStore) -> io::Result<Option<common::Hash>> {
+    Ok(db.get(HEAD_STATE_ROOT_KEY)?.as_deref().and_then(to_hash))
+}
+
+pub fn write_head_state_root(batch: &mut Batch, root: &common::Hash) {
+    batch.put(HEAD_STATE_ROOT_KEY, root.as_bytes());
+}
+
+// read_snapshot_root retrieves the root of the state the flat snapshot on
+// disk corresponds to.
+pub fn read_snapshot_root(db: &dyn KeyValueStore) -> io::Result<Option<common::Hash>> {
+    Ok(db.get(SNAPSHOT_ROOT_KEY)?.as_deref().and_then(to_hash))
+}
+
+pub fn write_snapshot_root(batch: &mut Batch, root: &common::Hash) {
+    batch.put(SNAPSHOT_ROOT_KEY, root.as_bytes());
+}
+
+pub fn read_account_snapshot(
+    db: &dyn KeyValueStore,
+    account_hash: &common::Hash,
+) -> io::Result<Option<Vec<u8>>> {
+    db.get(&snapshot_account_key(account_hash))
+}
+
+pub fn write_account_snapshot(batch: &mut Batch, account_hash: &common::Hash, entry: &[u8]) {
+    batch.put(&snapshot_account_key(account_hash), entry);
+}
+
+pub fn delete_account_snapshot(batch: &mut Batch, account_hash: &common::Hash) {
+    batch.delete(&snapshot_account_key(account_hash));
+}
+
+pub fn read_storage_snapshot(
+    db: &dyn KeyValueStore,
+    account_hash: &common::Hash,
+    storage_hash: &common::Hash,
+) -> io::Result<Option<Vec<u8>>> {
+    db.get(&snapshot_storage_key(account_hash, storage_hash))
+}
+
+pub fn write_storage_snapshot(
+    batch: &mut Batch,
+    account_hash: &common::Hash,
+    storage_hash: &common::Hash,
+    entry: &[u8],
+) {
+    batch.put(&snapshot_storage_key(account_hash, storage_hash), entry);
+}
+
+pub fn delete_storage_snapshot(
+    batch: &mut Batch,
+    account_hash: &common::Hash,
+    storage_hash: &common::Hash,
+) {
+    batch.delete(&snapshot_storage_key(account_hash, storage_hash));
+}
+
+// read_storage_snapshot_keys returns the keys of all snapshot storage slots
+// of an account.
+pub fn read_storage_snapshot_keys(
+    db: &dyn KeyValueStore,
+    account_hash: &common::Hash,
+) -> io::Result<Vec<Vec<u8>>> {
+    db.keys(&snapshot_storage_prefix(account_hash))
+}
+
+// read_snapshot_keys returns the keys of all snapshot entries.
+pub fn read_snapshot_keys(db: &dyn KeyValueStore) -> io::Result<Vec<Vec<u8>>> {
+    let mut keys = db.keys(SNAPSHOT_ACCOUNT_PREFIX)?;
+    keys.extend(db.keys(SNAPSHOT_STORAGE_PREFIX)?);
+    Ok(keys)
+}
+
+// RetainedRoot is a state root committed at a block number, as tracked by
+// the pruner.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RetainedRoot {
+    pub number: u64,
+    pub root: common::Hash,
+}
+
+crate::impl_rlp_struct!(RetainedRoot { number, root });
+
+pub fn read_pruner_roots(db: &dyn KeyValueStore) -> io::Result<Vec<RetainedRoot>> {
+    match db.get(PRUNER_ROOTS_KEY)? {
+        Some(data) => rlp::decode_list(&data).map_err(invalid_data),
+        None => Ok(Vec::new()),
+    }
+}
+
+pub fn write_pruner_roots(batch: &mut Batch, roots: &[RetainedRoot]) {
+    batch.put(PRUNER_ROOTS_KEY, &rlp::encode_list(roots));
+}
+
+// read_head_block_hash retrieves the hash of the current canonical head block.
+pub fn read_head_block_hash(db: &dyn KeyValueStore) -> io::Result<Option<common::Hash>> {
+    Ok(db.get(HEAD_BLOCK_KEY)?.as_deref().and_then(to_hash))
+}
+
+pub fn write_head_block_hash(batch: &mut Batch, hash: &common::Hash) {
+    batch.put(HEAD_BLOCK_KEY, hash.as_bytes());
+}
+
+// read_canonical_hash retrieves the hash assigned to a canonical block number.
+pub fn read_canonical_hash(
+    db: &dyn KeyValueStore,
+    number: u64,
+) -> io::Result<Option<common::Hash>> {
+    Ok(db
+        .get(&header_hash_key(number))?
+        .as_deref()
+        .and_then(to_hash))
+}
+
+pub fn write_canonical_hash(batch: &mut Batch, hash: &common::Hash, number: u64) {
+    batch.put(&header_hash_key(number), hash.as_bytes());
+}
+
+pub fn delete_canonical_hash(batch: &mut Batch, number: u64) {
+    batch.delete(&header_hash_key(number));
+}
+
+// read_header_number returns the header number assigned to a hash.
+pub fn read_header_number(db: &dyn KeyValueStore, hash: &common::Hash) -> io::Result<Option<u64>> {
+    Ok(db.get(&header_number_key(hash))?.and_then(|data| {
+        let bytes: [u8; 8] = data.as_slice().try_into().ok()?;
+        Some(u64::from_be_bytes(bytes))
+    }))
+}
+
+// read_header_rlp retrieves a block header in its raw RLP database encoding.
+pub fn read_header_rlp(
+    db: &dyn KeyValueStore,
+    hash: &common::Hash,
+    number: u64,
+) -> io::Result<Option<Vec<u8>>> {
+    db.get(&header_key(number, hash))
+}
+
+// write_header_rlp stores an RLP encoded header and the hash to number mapping.
+pub fn write_header_rlp(batch: &mut Batch, hash: &common::Hash, number: u64, header: &[u8]) {
+    batch.put(&header_number_key(hash), &number.to_be_bytes());
+    batch.put(&header_key(number, hash), header);
+}
+
+pub fn read_body_rlp(
+    db: &dyn KeyValueStore,
+    hash: &common::Hash,
+    number: u64,
+) -> io::Result<Option<Vec<u8>>> {
+    db.get(&block_body_key(number, hash))
+}
+
+pub fn write_body_rlp(batch: &mut Batch, hash: &common::Hash, number: u64, body: &[u8]) {
+    batch.put(&block_body_key(number, hash), body);
+}
+
+pub fn read_receipts_rlp(
+    db: &dyn KeyValueStore,
+    hash: &common::Hash,
+    number: u64,
+) -> io::Result<Option<Vec<u8>>> {
+    db.get(&block_receipts_key(number, hash))
+}
+
+pub fn write_receipts_rlp(batch: &mut Batch, hash: &common::Hash, number: u64, receipts: &[u8]) {
+    batch.put(&block_receipts_key(number, hash), receipts);
+}
+
+// read_header retrieves the block header corresponding to the hash.
+pub fn read_header(
+    db: &dyn KeyValueStore,
+    hash: &common::Hash,
+    number: u64,
+) -> io::Result<Option<Header>> {
+    decode_entry(read_header_rlp(db, hash, number)?)
+}
+
+// write_header stores a block header and the hash to number mapping.
+pub fn write_header(batch: &mut Batch, header: &Header) {
+    write_header_rlp(batch, &header.hash(), header.number, &rlp::encode(header));
+}
+
+// read_body retrieves the block body corresponding to the hash.
+pub fn read_body(
+    db: &dyn KeyValueStore,
+    hash: &common::Hash,
+    number: u64,
+) -> io::Result<Option<Body>> {
+    decode_entry(read_body_rlp(db, hash, number)?)
+}
+
+pub fn write_body(batch: &mut Batch, hash: &common::Hash, number: u64, body: &Body) {
+    write_body_rlp(batch, hash, number, &rlp::encode(body));
+}
+
+// read_block retrieves an entire block corresponding to the hash, assembling
+// it back from the stored header and body. If either the header or body could
+// not be retrieved None is returned.
+pub fn read_block(
+    db: &dyn KeyValueStore,
+    hash: &common::Hash,
+    number: u64,
+) -> io::Result<Option<Block>> {
+    let header = match read_header(db, hash, number)? {
+        Some(header) => header,
+        None => return Ok(None),
+    };
+    Ok(read_body(db, hash, number)?.map(|body| Block::with_body(header, body)))
+}
+
+// write_block serializes a block into the database, header and body separately.
+pub fn write_block(batch: &mut Batch, block: &Block) {
+    write_body(batch, &block.hash(), block.number(), &block.body);
+    write_header(batch, &block.header);
+}
+
+// read_receipts retrieves the consensus fields of the receipts of a block.
+// The derived fields are filled in by receipt::derive_fields.
+pub fn read_receipts(
+    db: &dyn KeyValueStore,
+    hash: &common::Hash,
+    number: u64,
+) -> io::Result<Option<Vec<Receipt>>> {
+    read_receipts_rlp(db, hash, number)?
+        .map(|data| rlp::decode_list(&data).map_err(invalid_data))
+        .transpose()
+}
+
+pub fn write_receipts(batch: &mut Batch, hash: &common::Hash, number: u64, receipts: &[Receipt]) {
+    write_receipts_rlp(batch, hash, number, &rlp::encode_list(receipts));
+}
+
+// read_td retrieves a block's total difficulty corresponding to the hash.
+pub fn read_td(
+    db: &dyn KeyValueStore,
+    hash: &common::Hash,
+    number: u64,
+) -> io::Result<Option<Uint256>> {
+    decode_entry(db.get(&header_td_key(number, hash))?)
+}
+
+pub fn write_td(batch: &mut Batch, hash: &common::Hash, number: u64, td: &Uint256) {
+    batch.put(&header_td_key(number, hash), &rlp::encode(td));
+}
+
+// read_tx_lookup_entry retrieves the number of the canonical block holding
+// the transaction with the given hash.
+pub fn read_tx_lookup_entry(
+    db: &dyn KeyValueStore,
+    hash: &common::Hash,
+) -> io::Result<Option<u64>> {
+    Ok(db.get(&tx_lookup_key(hash))?.and_then(|data| {
+        let bytes: [u8; 8] = data.as_slice().try_into().ok()?;
+        Some(u64::from_be_bytes(bytes))
+    }))
+}
+
+// write_tx_lookup_entries stores a positional metadata for every transaction
+// of a block, enabling hash based transaction and receipt lookups.
+pub fn write_tx_lookup_entries(batch: &mut Batch, block: &Block) {
+    for tx in block.transactions() {
+        batch.put(&tx_lookup_key(&tx.hash()), &block.number().to_be_bytes());
+    }
+}
+
+pub fn delete_tx_lookup_entry(batch: &mut Batch, hash: &common::Hash) {
+    batch.delete(&tx_lookup_key(hash));
+}
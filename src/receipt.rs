@@ -0,0 +1,436 @@
+// Package receipt contains the receipts produced by executing transactions.
+//
+// The consensus encoding of a receipt is [status, cumulativeGasUsed, bloom,
+// logs], where status is the intermediate state root before Byzantium and the
+// status code after it. Like transactions, receipts of typed transactions are
+// prefixed with the transaction type (EIP-2718).
+
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::bloom::Bloom;
+use crate::hexutil;
+use crate::log::Log;
+use crate::rlp::{self, Decodable, DecoderError, Encodable, Rlp};
+use crate::transaction::{self, Transaction, TxError, LEGACY_TX_TYPE};
+use uint256::Uint256;
+
+// Receipt status codes.
+pub const RECEIPT_STATUS_FAILED: u64 = 0;
+pub const RECEIPT_STATUS_SUCCESSFUL: u64 = 1;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReceiptError {
+    // TxCountMismatch is returned if the number of receipts doesn't match the
+    // number of transactions of the block.
+    TxCountMismatch { receipts: usize, txs: usize },
+    // CumulativeGasDecreasing is returned if a receipt's cumulative gas used
+    // is lower than the one of the receipt before it.
+    CumulativeGasDecreasing { index: usize, cumulative: u64, previous: u64 },
+    InvalidSender(TxError),
+}
+
+impl fmt::Display for ReceiptError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReceiptError::TxCountMismatch { receipts, txs } => {
+                write!(
+                    f,
+                    "transaction and receipt count mismatch, tx count = {}, receipts count = {}",
+                    txs, receipts
+                )
+            }
+            ReceiptError::CumulativeGasDecreasing {
+                index,
+                cumulative,
+                previous,
+            } => write!(
+                f,
+                "decreasing cumulative gas used in receipt {}: {} < {}",
+                index, cumulative, previous
+            ),
+            ReceiptError::InvalidSender(err) => write!(f, "invalid sender: {:?}", err),
+        }
+    }
+}
+
+impl std::error::Error for ReceiptError {}
+
+mod tx_type_hex {
+    use serde::{de::Error, Deserializer, Serializer};
+
+    use crate::hexutil;
+
+    pub fn serialize<S: Serializer>(tx_type: &u8, serializer: S) -> Result<S::Ok, S::Error> {
+        hexutil::quantity::serialize(&(*tx_type as u64), serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u8, D::Error> {
+        let tx_type = hexutil::quantity::deserialize(deserializer)?;
+        u8::try_from(tx_type).map_err(|_| D::Error::custom("transaction type out of range"))
+    }
+}
+
+fn is_zero(v: &u64) -> bool {
+    *v == 0
+}
+
+// Receipt represents the results of a transaction.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Receipt {
+    // Consensus fields: These fields are defined by the Yellow Paper
+    #[serde(rename = "type", with = "tx_type_hex", default)]
+    pub tx_type: u8,
+    #[serde(
+        rename = "root",
+        with = "hexutil::bytes",
+        default,
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub post_state: Vec<u8>,
+    #[serde(with = "hexutil::quantity")]
+    pub status: u64,
+    #[serde(with = "hexutil::quantity")]
+    pub cumulative_gas_used: u64,
+    #[serde(rename = "logsBloom")]
+    pub bloom: Bloom,
+    pub logs: Vec<Log>,
+
+    // Implementation fields: These fields are added by the state transition
+    // when processing a transaction.
+    #[serde(rename = "transactionHash", with = "hexutil::hash")]
+    pub tx_hash: common::Hash,
+    #[serde(with = "hexutil::address::opt", default)]
+    pub contract_address: Option<common::Address>,
+    #[serde(with = "hexutil::quantity")]
+    pub gas_used: u64,
+    #[serde(with = "hexutil::big")]
+    pub effective_gas_price: Uint256,
+    #[serde(with = "hexutil::quantity", default, skip_serializing_if = "is_zero")]
+    pub blob_gas_used: u64,
+    #[serde(
+        with = "hexutil::big::opt",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub blob_gas_price: Option<Uint256>,
+
+    // Inclusion information: These fields provide information about the
+    // inclusion of the transaction corresponding to this receipt.
+    #[serde(with = "hexutil::hash", default)]
+    pub block_hash: common::Hash,
+    #[serde(with = "hexutil::quantity", default)]
+    pub block_number: u64,
+    #[serde(with = "hexutil::quantity", default)]
+    pub transaction_index: u64,
+}
+
+impl Receipt {
+    // succeeded reports whether the transaction executed without error.
+    pub fn succeeded(&self) -> bool {
+        self.status == RECEIPT_STATUS_SUCCESSFUL
+    }
+
+    // append_status appends the first consensus field: the intermediate state
+    // root if set, the status code otherwise.
+    fn append_status(&self, out: &mut Vec<u8>) {
+        if !self.post_state.is_empty() {
+            self.post_state.rlp_append(out);
+        } else if self.status == RECEIPT_STATUS_FAILED {
+            rlp::append_bytes(&[], out);
+        } else {
+            rlp::append_bytes(&[0x01], out);
+        }
+    }
+
+    fn append_consensus(&self, out: &mut Vec<u8>) {
+        let mut payload = Vec::new();
+        self.append_status(&mut payload);
+        self.cumulative_gas_used.rlp_append(&mut payload);
+        self.bloom.rlp_append(&mut payload);
+        rlp::append_list(&self.logs, &mut payload);
+        rlp::append_list_payload(&payload, out);
+    }
+
+    fn decode_consensus(tx_type: u8, rlp: &Rlp) -> Result<Self, DecoderError> {
+        if rlp.item_count()? != 4 {
+            return Err(DecoderError::RlpIncorrectListLen);
+        }
+        let mut receipt = Receipt {
+            tx_type,
+            cumulative_gas_used: rlp.val_at(1)?,
+            bloom: rlp.val_at(2)?,
+            logs: rlp.list_at(3)?,
+            ..Default::default()
+        };
+        let status = rlp.at(0)?.data()?;
+        match status {
+            [] => receipt.status = RECEIPT_STATUS_FAILED,
+            [0x01] => receipt.status = RECEIPT_STATUS_SUCCESSFUL,
+            _ if status.len() == 32 => receipt.post_state = status.to_vec(),
+            _ => return Err(DecoderError::Custom("invalid receipt status")),
+        }
+        Ok(receipt)
+    }
+
+    // marshal_binary returns the consensus encoding of the receipt: the RLP
+    // list for legacy receipts, the type followed by the RLP list otherwise.
+    pub fn marshal_binary(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        if self.tx_type != LEGACY_TX_TYPE {
+            out.push(self.tx_type);
+        }
+        self.append_consensus(&mut out);
+        out
+    }
+
+    // unmarshal_binary decodes the consensus encoding of receipts.
+    pub fn unmarshal_binary(b: &[u8]) -> Result<Self, DecoderError> {
+        match b.first() {
+            None => Err(DecoderError::Custom("receipt too short")),
+            Some(first) if *first > 0x7f => Receipt::decode_consensus(LEGACY_TX_TYPE, &Rlp::new(b)),
+            Some(&tx_type) => Receipt::decode_consensus(tx_type, &Rlp::new(&b[1..])),
+        }
+    }
+}
+
+// Inside a list of receipts legacy receipts are embedded as a list, typed
+// receipts as a string holding the envelope.
+impl Encodable for Receipt {
+    fn rlp_append(&self, out: &mut Vec<u8>) {
+        if self.tx_type == LEGACY_TX_TYPE {
+            self.append_consensus(out);
+        } else {
+            rlp::append_bytes(&self.marshal_binary(), out);
+        }
+    }
+}
+
+impl Decodable for Receipt {
+    fn decode(rlp: &Rlp) -> Result<Self, DecoderError> {
+        if rlp.is_list() {
+            return Receipt::decode_consensus(LEGACY_TX_TYPE, rlp);
+        }
+        Receipt::unmarshal_binary(rlp.data()?)
+    }
+}
+
+impl crate::block::DerivableItem for Receipt {
+    fn encode_index(&self) -> Vec<u8> {
+        self.marshal_binary()
+    }
+}
+
+// derive_fields fills the receipts with their computed fields based on
+// consensus data and contextual infos like containing block and transactions.
+pub fn derive_fields(
+    receipts: &mut [Receipt],
+    chain_id: u64,
+    hash: common::Hash,
+    number: u64,
+    base_fee: Option<&Uint256>,
+    blob_gas_price: Option<&Uint256>,
+    txs: &[Transaction],
+) -> Result<(), ReceiptError> {
+    if txs.len() != receipts.len() {
+        return Err(ReceiptError::TxCountMismatch {
+            receipts: receipts.len(),
+            txs: txs.len(),
+        });
+    }
+    let chain_id = Uint256::from_uint64(chain_id);
+    let mut log_index = 0u64;
+    let mut cumulative_gas_used = 0u64;
+    for (i, (receipt, tx)) in receipts.iter_mut().zip(txs).enumerate() {
+        // The transaction type and hash can be retrieved from the transaction itself
+        receipt.tx_type = tx.tx_type();
+        receipt.tx_hash = tx.hash();
+        receipt.effective_gas_price = tx.effective_gas_price(base_fee);
+
+        // EIP-4844 blob transaction fields
+        if tx.tx_type() == transaction::BLOB_TX_TYPE {
+            receipt.blob_gas_used = tx.blob_gas();
+            receipt.blob_gas_price = blob_gas_price.cloned();
+        }
+
+        // block location fields
+        receipt.block_hash = hash;
+        receipt.block_number = number;
+        receipt.transaction_index = i as u64;
+
+        // The contract address can be derived from the transaction itself
+        receipt.contract_address = None;
+        if tx.to().is_none() {
+            // Deriving the signer is expensive, only do if it's actually needed
+            let from = transaction::sender(tx, &chain_id).map_err(ReceiptError::InvalidSender)?;
+            receipt.contract_address = Some(crate::crypto::create_address(&from, tx.nonce()));
+        }
+        // The used gas can be calculated based on previous receipts
+        receipt.gas_used = receipt
+            .cumulative_gas_used
+            .checked_sub(cumulative_gas_used)
+            .ok_or(ReceiptError::CumulativeGasDecreasing {
+                index: i,
+                cumulative: receipt.cumulative_gas_used,
+                previous: cumulative_gas_used,
+            })?;
+        cumulative_gas_used = receipt.cumulative_gas_used;
+
+        // The derived log fields can simply be set from the block and transaction
+        for log in receipt.logs.iter_mut() {
+            log.block_number = number;
+            log.block_hash = hash;
+            log.tx_hash = receipt.tx_hash;
+            log.tx_index = i as u64;
+            log.index = log_index;
+            log_index += 1;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block::derive_sha;
+    use crate::bloom::logs_bloom;
+    use crate::crypto;
+    use crate::transaction::{ACCESS_LIST_TX_TYPE, DYNAMIC_FEE_TX_TYPE, LegacyTx, TxData};
+
+    fn receipts_and_txs(cumulative: &[u64]) -> (Vec<Receipt>, Vec<Transaction>) {
+        let receipts = cumulative
+            .iter()
+            .map(|gas| Receipt {
+                cumulative_gas_used: *gas,
+                ..Default::default()
+            })
+            .collect();
+        let txs = (0..cumulative.len() as u64)
+            .map(|nonce| {
+                Transaction::new(TxData::Legacy(LegacyTx {
+                    nonce,
+                    gas: 21000,
+                    to: Some(common::Address([0x01; 20])),
+                    ..Default::default()
+                }))
+            })
+            .collect();
+        (receipts, txs)
+    }
+
+    #[test]
+    fn test_derive_gas_used() {
+        let (mut receipts, txs) = receipts_and_txs(&[21000, 50000]);
+        derive_fields(&mut receipts, 1, common::Hash::default(), 1, None, None, &txs).unwrap();
+        assert_eq!(receipts[0].gas_used, 21000);
+        assert_eq!(receipts[1].gas_used, 29000);
+
+        let (mut receipts, txs) = receipts_and_txs(&[21000, 10000]);
+        assert_eq!(
+            derive_fields(&mut receipts, 1, common::Hash::default(), 1, None, None, &txs),
+            Err(ReceiptError::CumulativeGasDecreasing {
+                index: 1,
+                cumulative: 10000,
+                previous: 21000,
+            })
+        );
+    }
+
+    // test_receipt returns the receipt of go-ethereum's receipt encoding tests:
+    // a failed transaction with two identical logs.
+    fn test_receipt(tx_type: u8) -> Receipt {
+        let mut dead = common::Hash::default();
+        dead.0[30..].copy_from_slice(&[0xde, 0xad]);
+        let mut beef = common::Hash::default();
+        beef.0[30..].copy_from_slice(&[0xbe, 0xef]);
+        let mut address = common::Address::default();
+        address.0[19] = 0x11;
+        let log = Log {
+            address,
+            topics: vec![dead, beef],
+            data: vec![0x01, 0x00, 0xff],
+            ..Default::default()
+        };
+        let logs = vec![log.clone(), log];
+        Receipt {
+            tx_type,
+            status: RECEIPT_STATUS_FAILED,
+            cumulative_gas_used: 1,
+            bloom: logs_bloom(&logs),
+            logs,
+            ..Default::default()
+        }
+    }
+
+    // legacy_encoding returns the expected consensus encoding of test_receipt.
+    fn legacy_encoding(receipt: &Receipt) -> Vec<u8> {
+        let log = "f85d940000000000000000000000000000000000000011f842a0000000000000000000000000000000000000000000000000000000000000deada0000000000000000000000000000000000000000000000000000000000000beef830100ff";
+        let mut want = hexutil::decode("0xf901c58001b90100").unwrap();
+        want.extend_from_slice(receipt.bloom.as_bytes());
+        want.extend(hexutil::decode(&format!("0xf8be{log}{log}")).unwrap());
+        want
+    }
+
+    #[test]
+    fn test_receipt_encoding() {
+        let legacy = test_receipt(LEGACY_TX_TYPE);
+        let want = legacy_encoding(&legacy);
+        assert_eq!(legacy.marshal_binary(), want);
+        assert_eq!(rlp::encode(&legacy), want);
+        assert_eq!(Receipt::unmarshal_binary(&want).unwrap(), legacy);
+
+        for tx_type in [ACCESS_LIST_TX_TYPE, DYNAMIC_FEE_TX_TYPE] {
+            let typed = test_receipt(tx_type);
+            let mut envelope = vec![tx_type];
+            envelope.extend_from_slice(&want);
+            assert_eq!(typed.marshal_binary(), envelope);
+            assert_eq!(Receipt::unmarshal_binary(&envelope).unwrap(), typed);
+
+            // Inside a list typed receipts are a string holding the envelope
+            let mut list = hexutil::decode("0xf901ccb901c9").unwrap();
+            list.extend_from_slice(&envelope);
+            assert_eq!(rlp::encode_list(std::slice::from_ref(&typed)), list);
+            let decoded: Vec<Receipt> = rlp::decode_list(&list).unwrap();
+            assert_eq!(decoded, vec![typed]);
+        }
+    }
+
+    #[test]
+    fn test_receipt_status_encoding() {
+        let mut receipt = test_receipt(LEGACY_TX_TYPE);
+        receipt.status = RECEIPT_STATUS_SUCCESSFUL;
+        let enc = receipt.marshal_binary();
+        assert_eq!(&enc[3..5], &[0x01, 0x01]);
+        assert_eq!(Receipt::unmarshal_binary(&enc).unwrap(), receipt);
+
+        // Pre-Byzantium receipts carry the intermediate state root instead
+        receipt.post_state = vec![0x22; 32];
+        receipt.status = 0;
+        let enc = receipt.marshal_binary();
+        assert_eq!(&enc[3..5], &[0xa0, 0x22]);
+        assert_eq!(Receipt::unmarshal_binary(&enc).unwrap(), receipt);
+
+        // Any other status is invalid
+        let mut bad = legacy_encoding(&test_receipt(LEGACY_TX_TYPE));
+        bad[3] = 0x02;
+        assert!(Receipt::unmarshal_binary(&bad).is_err());
+    }
+
+    #[test]
+    fn test_receipts_root() {
+        // A trie with a single receipt is one leaf keyed by rlp(0) = 0x80,
+        // whose hex prefix encoding is 0x20 0x80. Typed receipts store the
+        // envelope as the leaf value.
+        for tx_type in [LEGACY_TX_TYPE, DYNAMIC_FEE_TX_TYPE] {
+            let receipt = test_receipt(tx_type);
+            let mut leaf = Vec::new();
+            rlp::append_bytes(&[0x20, 0x80], &mut leaf);
+            rlp::append_bytes(&receipt.marshal_binary(), &mut leaf);
+            let mut node = Vec::new();
+            rlp::append_list_payload(&leaf, &mut node);
+            assert_eq!(derive_sha(&[receipt]), crypto::keccak256_hash(&node));
+        }
+    }
+}
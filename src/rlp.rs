@@ -0,0 +1,636 @@
+// Package rlp implements the RLP serialization format.
+//
+// The purpose of RLP (Recursive Linear Prefix) is to encode arbitrarily nested arrays of
+// binary data, and RLP is the main encoding method used to serialize objects in Ethereum.
+// The only purpose of RLP is to encode structure; encoding specific atomic data types
+// (eg. strings, ints, floats) is left up to higher-order protocols.
+//
+// Decoding is strict: only the canonical encoding of a value is accepted. Integers
+// must not have leading zero bytes, single bytes below 0x80 must not be wrapped in a
+// string header and lengths must use the shortest possible form.
+
+use std::fmt;
+
+use uint256::Uint256;
+
+// EMPTY_STRING is the encoding of an empty byte string.
+pub const EMPTY_STRING: u8 = 0x80;
+// EMPTY_LIST is the encoding of an empty list.
+pub const EMPTY_LIST: u8 = 0xC0;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DecoderError {
+    // Data has additional data at the end
+    RlpIsTooBig,
+    // Data has too few bytes
+    RlpIsTooShort,
+    // Expect an encoded list, RLP was something else
+    RlpExpectedToBeList,
+    // Expect encoded data, RLP was something else
+    RlpExpectedToBeData,
+    // Expected a list of a different length
+    RlpIncorrectListLen,
+    // Data length number has a prefixed zero byte, invalid for numbers
+    RlpDataLenWithZeroPrefix,
+    // List length number has a prefixed zero byte, invalid for numbers
+    RlpListLenWithZeroPrefix,
+    // Non-canonical (longer than necessary) representation used for data or list
+    RlpInvalidIndirection,
+    // Integer or fixed size value has a leading zero byte
+    RlpInvalidLeadingZero,
+    // Declared length is inconsistent with data specified after
+    RlpInconsistentLengthAndData,
+    // Declared length is invalid and results in overflow
+    RlpInvalidLength,
+    // Custom rlp decoding error
+    Custom(&'static str),
+}
+
+impl fmt::Display for DecoderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecoderError::RlpIsTooBig => write!(f, "rlp: input contains more than one value"),
+            DecoderError::RlpIsTooShort => write!(f, "rlp: value size exceeds available input length"),
+            DecoderError::RlpExpectedToBeList => write!(f, "rlp: expected List"),
+            DecoderError::RlpExpectedToBeData => write!(f, "rlp: expected String or Byte"),
+            DecoderError::RlpIncorrectListLen => write!(f, "rlp: incorrect list length"),
+            DecoderError::RlpDataLenWithZeroPrefix => write!(f, "rlp: non-canonical size information"),
+            DecoderError::RlpListLenWithZeroPrefix => write!(f, "rlp: non-canonical size information"),
+            DecoderError::RlpInvalidIndirection => write!(f, "rlp: non-canonical size information"),
+            DecoderError::RlpInvalidLeadingZero => write!(f, "rlp: non-canonical integer (leading zero bytes)"),
+            DecoderError::RlpInconsistentLengthAndData => write!(f, "rlp: declared length is inconsistent with data"),
+            DecoderError::RlpInvalidLength => write!(f, "rlp: invalid length"),
+            DecoderError::Custom(msg) => write!(f, "rlp: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for DecoderError {}
+
+// Encodable is implemented by types that have a canonical RLP encoding.
+pub trait Encodable {
+    // rlp_append appends the encoding of the value to out.
+    fn rlp_append(&self, out: &mut Vec<u8>);
+
+    // rlp_bytes returns the encoding of the value.
+    fn rlp_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        self.rlp_append(&mut out);
+        out
+    }
+}
+
+// Decodable is implemented by types that can be decoded from a single RLP item.
+pub trait Decodable: Sized {
+    fn decode(rlp: &Rlp) -> Result<Self, DecoderError>;
+}
+
+// encode returns the RLP encoding of val.
+pub fn encode<E: Encodable + ?Sized>(val: &E) -> Vec<u8> {
+    val.rlp_bytes()
+}
+
+// encode_list returns the RLP encoding of the items as a list.
+pub fn encode_list<E: Encodable>(items: &[E]) -> Vec<u8> {
+    let mut out = Vec::new();
+    append_list(items, &mut out);
+    out
+}
+
+// append_list appends the RLP encoding of the items as a list to out.
+pub fn append_list<E: Encodable>(items: &[E], out: &mut Vec<u8>) {
+    let mut payload = Vec::new();
+    for item in items {
+        item.rlp_append(&mut payload);
+    }
+    append_list_payload(&payload, out);
+}
+
+// append_list_payload wraps an already encoded list payload in a list header.
+pub fn append_list_payload(payload: &[u8], out: &mut Vec<u8>) {
+    append_header(payload.len(), EMPTY_LIST, out);
+    out.extend_from_slice(payload);
+}
+
+// append_bytes appends the encoding of a byte string.
+pub fn append_bytes(b: &[u8], out: &mut Vec<u8>) {
+    if b.len() == 1 && b[0] < EMPTY_STRING {
+        // fits single byte, no string header
+        out.push(b[0]);
+    } else {
+        append_header(b.len(), EMPTY_STRING, out);
+        out.extend_from_slice(b);
+    }
+}
+
+// append_header writes a string (offset 0x80) or list (offset 0xC0) header
+// for a payload of the given size.
+fn append_header(size: usize, offset: u8, out: &mut Vec<u8>) {
+    if size < 56 {
+        out.push(offset + size as u8);
+    } else {
+        let len_bytes = trim_leading_zeros(&(size as u64).to_be_bytes()).to_vec();
+        out.push(offset + 55 + len_bytes.len() as u8);
+        out.extend_from_slice(&len_bytes);
+    }
+}
+
+fn trim_leading_zeros(b: &[u8]) -> &[u8] {
+    let start = b.iter().position(|byte| *byte != 0).unwrap_or(b.len());
+    &b[start..]
+}
+
+// decode parses RLP-encoded data and returns the decoded value. The input must
+// contain exactly one value and no trailing data.
+pub fn decode<T: Decodable>(bytes: &[u8]) -> Result<T, DecoderError> {
+    let rlp = Rlp::new(bytes);
+    let info = rlp.payload_info()?;
+    if info.total() != bytes.len() {
+        return Err(DecoderError::RlpIsTooBig);
+    }
+    T::decode(&rlp)
+}
+
+// decode_list parses a RLP-encoded list of homogeneous values.
+pub fn decode_list<T: Decodable>(bytes: &[u8]) -> Result<Vec<T>, DecoderError> {
+    let rlp = Rlp::new(bytes);
+    let info = rlp.payload_info()?;
+    if info.total() != bytes.len() {
+        return Err(DecoderError::RlpIsTooBig);
+    }
+    rlp.as_list()
+}
+
+// PayloadInfo describes the header of a single RLP item.
+#[derive(Debug, Clone, Copy)]
+pub struct PayloadInfo {
+    pub header_len: usize,
+    pub value_len: usize,
+    pub is_list: bool,
+}
+
+impl PayloadInfo {
+    pub fn total(&self) -> usize {
+        self.header_len + self.value_len
+    }
+}
+
+// Rlp is a view on a single encoded RLP item.
+#[derive(Debug, Clone, Copy)]
+pub struct Rlp<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Rlp<'a> {
+    pub fn new(bytes: &'a [u8]) -> Self {
+        Rlp { bytes }
+    }
+
+    // as_raw returns the full encoding of the item, header included.
+    pub fn as_raw(&self) -> &'a [u8] {
+        self.bytes
+    }
+
+    // payload_info parses and validates the item header.
+    pub fn payload_info(&self) -> Result<PayloadInfo, DecoderError> {
+        let b = self.bytes;
+        if b.is_empty() {
+            return Err(DecoderError::RlpIsTooShort);
+        }
+        let info = match b[0] {
+            0x00..=0x7F => PayloadInfo { header_len: 0, value_len: 1, is_list: false },
+            0x80..=0xB7 => {
+                let value_len = (b[0] - EMPTY_STRING) as usize;
+                // Reject strings that should've been single bytes.
+                if value_len == 1 && b.len() > 1 && b[1] < EMPTY_STRING {
+                    return Err(DecoderError::RlpInvalidIndirection);
+                }
+                PayloadInfo { header_len: 1, value_len, is_list: false }
+            }
+            0xB8..=0xBF => {
+                let len_of_len = (b[0] - 0xB7) as usize;
+                let value_len = read_size(&b[1..], len_of_len, DecoderError::RlpDataLenWithZeroPrefix)?;
+                PayloadInfo { header_len: 1 + len_of_len, value_len, is_list: false }
+            }
+            0xC0..=0xF7 => PayloadInfo {
+                header_len: 1,
+                value_len: (b[0] - EMPTY_LIST) as usize,
+                is_list: true,
+            },
+            0xF8..=0xFF => {
+                let len_of_len = (b[0] - 0xF7) as usize;
+                let value_len = read_size(&b[1..], len_of_len, DecoderError::RlpListLenWithZeroPrefix)?;
+                PayloadInfo { header_len: 1 + len_of_len, value_len, is_list: true }
+            }
+        };
+        if info.header_len.checked_add(info.value_len).is_none() {
+            return Err(DecoderError::RlpInvalidLength);
+        }
+        if info.total() > b.len() {
+            return Err(DecoderError::RlpIsTooShort);
+        }
+        Ok(info)
+    }
+
+    pub fn is_list(&self) -> bool {
+        !self.bytes.is_empty() && self.bytes[0] >= EMPTY_LIST
+    }
+
+    pub fn is_data(&self) -> bool {
+        !self.bytes.is_empty() && self.bytes[0] < EMPTY_LIST
+    }
+
+    pub fn is_empty(&self) -> bool {
+        !self.bytes.is_empty() && (self.bytes[0] == EMPTY_STRING || self.bytes[0] == EMPTY_LIST)
+    }
+
+    // data returns the payload of a string item.
+    pub fn data(&self) -> Result<&'a [u8], DecoderError> {
+        let info = self.payload_info()?;
+        if info.is_list {
+            return Err(DecoderError::RlpExpectedToBeData);
+        }
+        Ok(&self.bytes[info.header_len..info.total()])
+    }
+
+    // payload returns the concatenated encodings of the items of a list.
+    fn list_payload(&self) -> Result<&'a [u8], DecoderError> {
+        let info = self.payload_info()?;
+        if !info.is_list {
+            return Err(DecoderError::RlpExpectedToBeList);
+        }
+        Ok(&self.bytes[info.header_len..info.total()])
+    }
+
+    // items returns views on every item of a list.
+    pub fn items(&self) -> Result<Vec<Rlp<'a>>, DecoderError> {
+        let mut payload = self.list_payload()?;
+        let mut items = Vec::new();
+        while !payload.is_empty() {
+            let item = Rlp::new(payload);
+            let total = item.payload_info()?.total();
+            items.push(Rlp::new(&payload[..total]));
+            payload = &payload[total..];
+        }
+        Ok(items)
+    }
+
+    pub fn item_count(&self) -> Result<usize, DecoderError> {
+        Ok(self.items()?.len())
+    }
+
+    // at returns the index'th item of a list.
+    pub fn at(&self, index: usize) -> Result<Rlp<'a>, DecoderError> {
+        self.items()?
+            .get(index)
+            .copied()
+            .ok_or(DecoderError::RlpIncorrectListLen)
+    }
+
+    pub fn as_val<T: Decodable>(&self) -> Result<T, DecoderError> {
+        T::decode(self)
+    }
+
+    pub fn val_at<T: Decodable>(&self, index: usize) -> Result<T, DecoderError> {
+        self.at(index)?.as_val()
+    }
+
+    pub fn as_list<T: Decodable>(&self) -> Result<Vec<T>, DecoderError> {
+        self.items()?.iter().map(|item| item.as_val()).collect()
+    }
+
+    pub fn list_at<T: Decodable>(&self, index: usize) -> Result<Vec<T>, DecoderError> {
+        self.at(index)?.as_list()
+    }
+}
+
+// read_size reads a big endian length of len_of_len bytes, rejecting leading
+// zeros and lengths that should have used the short form.
+fn read_size(b: &[u8], len_of_len: usize, zero_prefix: DecoderError) -> Result<usize, DecoderError> {
+    if b.len() < len_of_len {
+        return Err(DecoderError::RlpIsTooShort);
+    }
+    if len_of_len > std::mem::size_of::<usize>() {
+        return Err(DecoderError::RlpInvalidLength);
+    }
+    if b[0] == 0 {
+        return Err(zero_prefix);
+    }
+    let size = b[..len_of_len]
+        .iter()
+        .fold(0usize, |acc, byte| (acc << 8) | *byte as usize);
+    // Reject sizes < 56 (should have been short form).
+    if size < 56 {
+        return Err(DecoderError::RlpInvalidIndirection);
+    }
+    Ok(size)
+}
+
+// decode_uint decodes a canonical big endian integer of at most max_len bytes.
+fn decode_uint(rlp: &Rlp, max_len: usize) -> Result<u128, DecoderError> {
+    let data = rlp.data()?;
+    if data.len() > max_len {
+        return Err(DecoderError::RlpIsTooBig);
+    }
+    if !data.is_empty() && data[0] == 0 {
+        return Err(DecoderError::RlpInvalidLeadingZero);
+    }
+    Ok(data.iter().fold(0u128, |acc, byte| (acc << 8) | *byte as u128))
+}
+
+macro_rules! impl_uint_rlp {
+    ($t:ty) => {
+        impl Encodable for $t {
+            fn rlp_append(&self, out: &mut Vec<u8>) {
+                append_bytes(trim_leading_zeros(&self.to_be_bytes()), out);
+            }
+        }
+
+        impl Decodable for $t {
+            fn decode(rlp: &Rlp) -> Result<Self, DecoderError> {
+                decode_uint(rlp, std::mem::size_of::<$t>()).map(|v| v as $t)
+            }
+        }
+    };
+}
+
+impl_uint_rlp!(u8);
+impl_uint_rlp!(u16);
+impl_uint_rlp!(u32);
+impl_uint_rlp!(u64);
+impl_uint_rlp!(u128);
+impl_uint_rlp!(usize);
+
+impl Encodable for bool {
+    fn rlp_append(&self, out: &mut Vec<u8>) {
+        (*self as u8).rlp_append(out);
+    }
+}
+
+impl Decodable for bool {
+    fn decode(rlp: &Rlp) -> Result<Self, DecoderError> {
+        match u8::decode(rlp)? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(DecoderError::Custom("invalid boolean value")),
+        }
+    }
+}
+
+impl Encodable for [u8] {
+    fn rlp_append(&self, out: &mut Vec<u8>) {
+        append_bytes(self, out);
+    }
+}
+
+impl Encodable for Vec<u8> {
+    fn rlp_append(&self, out: &mut Vec<u8>) {
+        append_bytes(self, out);
+    }
+}
+
+impl Decodable for Vec<u8> {
+    fn decode(rlp: &Rlp) -> Result<Self, DecoderError> {
+        rlp.data().map(|data| data.to_vec())
+    }
+}
+
+impl Encodable for str {
+    fn rlp_append(&self, out: &mut Vec<u8>) {
+        append_bytes(self.as_bytes(), out);
+    }
+}
+
+impl Encodable for String {
+    fn rlp_append(&self, out: &mut Vec<u8>) {
+        append_bytes(self.as_bytes(), out);
+    }
+}
+
+impl Decodable for String {
+    fn decode(rlp: &Rlp) -> Result<Self, DecoderError> {
+        String::from_utf8(rlp.data()?.to_vec()).map_err(|_| DecoderError::Custom("invalid utf-8"))
+    }
+}
+
+impl<T: Encodable> Encodable for Option<T> {
+    // None is encoded as the empty string, used for the optional "to" field of
+    // transactions.
+    fn rlp_append(&self, out: &mut Vec<u8>) {
+        match self {
+            Some(value) => value.rlp_append(out),
+            None => out.push(EMPTY_STRING),
+        }
+    }
+}
+
+impl<T: Decodable> Decodable for Option<T> {
+    fn decode(rlp: &Rlp) -> Result<Self, DecoderError> {
+        if rlp.as_raw().first() == Some(&EMPTY_STRING) {
+            return Ok(None);
+        }
+        T::decode(rlp).map(Some)
+    }
+}
+
+impl<T: Encodable + ?Sized> Encodable for &T {
+    fn rlp_append(&self, out: &mut Vec<u8>) {
+        (**self).rlp_append(out);
+    }
+}
+
+// decode_fixed decodes a byte string of exactly N bytes.
+fn decode_fixed<const N: usize>(rlp: &Rlp) -> Result<[u8; N], DecoderError> {
+    let data = rlp.data()?;
+    if data.len() < N {
+        return Err(DecoderError::RlpIsTooShort);
+    }
+    if data.len() > N {
+        return Err(DecoderError::RlpIsTooBig);
+    }
+    let mut out = [0u8; N];
+    out.copy_from_slice(data);
+    Ok(out)
+}
+
+impl Encodable for common::Address {
+    fn rlp_append(&self, out: &mut Vec<u8>) {
+        append_bytes(self.as_bytes(), out);
+    }
+}
+
+impl Decodable for common::Address {
+    fn decode(rlp: &Rlp) -> Result<Self, DecoderError> {
+        decode_fixed::<20>(rlp).map(common::Address)
+    }
+}
+
+impl Encodable for common::Hash {
+    fn rlp_append(&self, out: &mut Vec<u8>) {
+        append_bytes(self.as_bytes(), out);
+    }
+}
+
+impl Decodable for common::Hash {
+    fn decode(rlp: &Rlp) -> Result<Self, DecoderError> {
+        decode_fixed::<32>(rlp).map(common::Hash)
+    }
+}
+
+impl Encodable for Uint256 {
+    fn rlp_append(&self, out: &mut Vec<u8>) {
+        append_bytes(trim_leading_zeros(&self.to_bytes32()), out);
+    }
+}
+
+impl Decodable for Uint256 {
+    fn decode(rlp: &Rlp) -> Result<Self, DecoderError> {
+        let data = rlp.data()?;
+        if data.len() > 32 {
+            return Err(DecoderError::RlpIsTooBig);
+        }
+        if !data.is_empty() && data[0] == 0 {
+            return Err(DecoderError::RlpInvalidLeadingZero);
+        }
+        Ok(Uint256::from_bytes(data))
+    }
+}
+
+// impl_rlp_struct implements Encodable and Decodable for a struct by encoding
+// its fields, in the given order, as an RLP list. Every listed field must
+// itself implement both traits; fields holding lists of values implement
+// the traits by hand with append_list and Rlp::list_at.
+//
+//     impl_rlp_struct!(Withdrawal { index, validator, address, amount });
+#[macro_export]
+macro_rules! impl_rlp_struct {
+    ($name:ident { $($field:ident),* $(,)? }) => {
+        impl $crate::rlp::Encodable for $name {
+            fn rlp_append(&self, out: &mut Vec<u8>) {
+                let mut payload = Vec::new();
+                $( $crate::rlp::Encodable::rlp_append(&self.$field, &mut payload); )*
+                $crate::rlp::append_list_payload(&payload, out);
+            }
+        }
+
+        impl $crate::rlp::Decodable for $name {
+            fn decode(rlp: &$crate::rlp::Rlp) -> Result<Self, $crate::rlp::DecoderError> {
+                let items = rlp.items()?;
+                let expected = [$(stringify!($field)),*].len();
+                if items.len() != expected {
+                    return Err($crate::rlp::DecoderError::RlpIncorrectListLen);
+                }
+                let mut items = items.into_iter();
+                Ok($name {
+                    $( $field: items.next().unwrap().as_val()?, )*
+                })
+            }
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use num_bigint::BigUint;
+    use serde_json::Value;
+    use std::collections::BTreeMap;
+
+    // Item is an untyped RLP value: a byte string or a list of items.
+    #[derive(Debug, PartialEq, Eq)]
+    enum Item {
+        Bytes(Vec<u8>),
+        List(Vec<Item>),
+    }
+
+    impl Encodable for Item {
+        fn rlp_append(&self, out: &mut Vec<u8>) {
+            match self {
+                Item::Bytes(b) => append_bytes(b, out),
+                Item::List(items) => append_list(items, out),
+            }
+        }
+    }
+
+    impl Decodable for Item {
+        fn decode(rlp: &Rlp) -> Result<Self, DecoderError> {
+            if rlp.is_list() {
+                Ok(Item::List(rlp.as_list()?))
+            } else {
+                Ok(Item::Bytes(rlp.data()?.to_vec()))
+            }
+        }
+    }
+
+    // RlpTest is a test case from the ethereum/tests RLP suite. Strings
+    // starting with '#' are big integers, INVALID marks a decoding failure.
+    #[derive(serde::Deserialize)]
+    struct RlpTest {
+        #[serde(rename = "in")]
+        input: Value,
+        out: String,
+    }
+
+    fn to_item(v: &Value) -> Item {
+        match v {
+            Value::String(s) => match s.strip_prefix('#') {
+                Some(num) => Item::Bytes(trim_big(num.parse::<BigUint>().unwrap().to_bytes_be())),
+                None => Item::Bytes(s.as_bytes().to_vec()),
+            },
+            Value::Number(n) => Item::Bytes(trim_leading_zeros(&n.as_u64().unwrap().to_be_bytes()).to_vec()),
+            Value::Array(items) => Item::List(items.iter().map(to_item).collect()),
+            _ => panic!("unexpected test input {}", v),
+        }
+    }
+
+    // trim_big drops the single zero byte BigUint uses to encode zero.
+    fn trim_big(b: Vec<u8>) -> Vec<u8> {
+        if b == [0] {
+            Vec::new()
+        } else {
+            b
+        }
+    }
+
+    fn load(data: &str) -> BTreeMap<String, RlpTest> {
+        serde_json::from_str(data).unwrap()
+    }
+
+    fn unhex(s: &str) -> Vec<u8> {
+        hex::decode(s.trim_start_matches("0x")).unwrap()
+    }
+
+    #[test]
+    fn test_rlp_vectors() {
+        for (name, test) in load(include_str!("testdata/rlp/rlptest.json")) {
+            let item = to_item(&test.input);
+            let out = unhex(&test.out);
+            assert_eq!(encode(&item), out, "{}: encoding mismatch", name);
+            assert_eq!(decode::<Item>(&out).unwrap(), item, "{}: decoding mismatch", name);
+        }
+    }
+
+    #[test]
+    fn test_invalid_rlp_vectors() {
+        for (name, test) in load(include_str!("testdata/rlp/invalidRLPTest.json")) {
+            assert_eq!(test.input, "INVALID", "{}", name);
+            let res = decode::<Item>(&unhex(&test.out));
+            assert!(res.is_err(), "{}: decoded invalid input to {:?}", name, res);
+        }
+    }
+
+    #[test]
+    fn test_strict_integers() {
+        // Integers must not have leading zero bytes.
+        assert_eq!(decode::<u64>(&[0x82, 0x00, 0x01]), Err(DecoderError::RlpInvalidLeadingZero));
+        assert_eq!(decode::<Uint256>(&[0x81, 0x00]), Err(DecoderError::RlpInvalidIndirection));
+        assert_eq!(decode::<u64>(&[0x00]), Err(DecoderError::RlpInvalidLeadingZero));
+        // Values that don't fit the integer type are rejected.
+        assert_eq!(decode::<u8>(&[0x82, 0x01, 0x00]), Err(DecoderError::RlpIsTooBig));
+        // Single bytes below 0x80 must not be wrapped in a string header.
+        assert_eq!(decode::<Vec<u8>>(&[0x81, 0x7f]), Err(DecoderError::RlpInvalidIndirection));
+        // Trailing data after the value is rejected.
+        assert_eq!(decode::<u64>(&[0x01, 0x02]), Err(DecoderError::RlpIsTooBig));
+        assert_eq!(decode::<u64>(&[0x82, 0x04, 0x00]), Ok(1024));
+    }
+}
@@ -0,0 +1,1434 @@
+// Package rpc serves the eth namespace of the Ethereum JSON-RPC API over
+// HTTP.
+//
+// EthApi answers the requests from the chain store, executing eth_call and
+// eth_estimateGas with the VM on the state of the requested block, and
+// hands raw transactions to the transaction pool. Server is a minimal
+// HTTP/1.1 server for local use: it reads one POST request per connection
+// and replies with the JSON-RPC response, handling one connection at a
+// time. Requests may be batched as defined by JSON-RPC 2.0.
+
+use std::fmt;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::time::{Duration, Instant};
+
+use serde::Deserialize;
+use serde_json::{json, Map, Value};
+
+use crate::block::{Block, Header};
+use crate::blockchain::{BlockChain, ChainError};
+use crate::evm::{Config, TxContext, EVM};
+use crate::gas_pool::GasPool;
+use crate::hexutil;
+use crate::log::Log;
+use crate::params;
+use crate::receipt::Receipt;
+use crate::rlp;
+use crate::state_db::StateDB;
+use crate::state_processor::new_block_context;
+use crate::state_transition::{apply_message, ExecutionResult, Message};
+use crate::transaction::{self, Transaction};
+use crate::txpool::TxPool;
+use uint256::Uint256;
+
+// Error codes defined by JSON-RPC 2.0, and the ones used by geth for
+// failing requests and reverted calls.
+pub const PARSE_ERROR: i64 = -32700;
+pub const INVALID_REQUEST: i64 = -32600;
+pub const METHOD_NOT_FOUND: i64 = -32601;
+pub const INVALID_PARAMS: i64 = -32602;
+pub const SERVER_ERROR: i64 = -32000;
+pub const REVERT_ERROR: i64 = 3;
+
+// MAX_REQUEST_CONTENT_LENGTH is the maximum size of a request body.
+pub const MAX_REQUEST_CONTENT_LENGTH: usize = 5 * 1024 * 1024;
+
+// MAX_HEADER_SIZE is the maximum size of the request line and headers.
+pub const MAX_HEADER_SIZE: u64 = 1024 * 1024;
+
+// READ_TIMEOUT bounds the time spent reading a whole request, headers and
+// body, and WRITE_TIMEOUT each write of its response. The server handles one
+// connection at a time, so without them a stalled client would block every
+// other one.
+pub const READ_TIMEOUT: Duration = Duration::from_secs(30);
+pub const WRITE_TIMEOUT: Duration = Duration::from_secs(30);
+
+// MAX_LOG_BLOCK_RANGE caps the number of blocks a single eth_getLogs request
+// may scan.
+pub const MAX_LOG_BLOCK_RANGE: u64 = 10_000;
+
+// RpcError is the error object of a JSON-RPC response.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RpcError {
+    pub code: i64,
+    pub message: String,
+    pub data: Option<Value>,
+}
+
+impl RpcError {
+    pub fn new(code: i64, message: impl Into<String>) -> Self {
+        RpcError {
+            code,
+            message: message.into(),
+            data: None,
+        }
+    }
+
+    fn invalid_params(message: impl Into<String>) -> Self {
+        RpcError::new(INVALID_PARAMS, message)
+    }
+
+    fn server(message: impl Into<String>) -> Self {
+        RpcError::new(SERVER_ERROR, message)
+    }
+
+    // reverted returns the error of a call aborted by REVERT, carrying the
+    // revert data so that clients can decode the reason.
+    fn reverted(data: &[u8]) -> Self {
+        RpcError {
+            code: REVERT_ERROR,
+            message: "execution reverted".to_string(),
+            data: Some(Value::String(hexutil::encode(data))),
+        }
+    }
+
+    fn to_json(&self) -> Value {
+        let mut obj = json!({ "code": self.code, "message": self.message });
+        if let Some(data) = &self.data {
+            obj["data"] = data.clone();
+        }
+        obj
+    }
+}
+
+impl fmt::Display for RpcError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({})", self.message, self.code)
+    }
+}
+
+impl std::error::Error for RpcError {}
+
+impl From<ChainError> for RpcError {
+    fn from(err: ChainError) -> Self {
+        RpcError::server(err.to_string())
+    }
+}
+
+// TransactionArgs represents the arguments to construct a new transaction
+// or a message call.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TransactionArgs {
+    #[serde(with = "hexutil::address::opt", default)]
+    pub from: Option<common::Address>,
+    #[serde(with = "hexutil::address::opt", default)]
+    pub to: Option<common::Address>,
+    #[serde(with = "hexutil::quantity::opt", default)]
+    pub gas: Option<u64>,
+    #[serde(with = "hexutil::big::opt", default)]
+    pub gas_price: Option<Uint256>,
+    #[serde(with = "hexutil::big::opt", default)]
+    pub max_fee_per_gas: Option<Uint256>,
+    #[serde(with = "hexutil::big::opt", default)]
+    pub max_priority_fee_per_gas: Option<Uint256>,
+    #[serde(with = "hexutil::big::opt", default)]
+    pub value: Option<Uint256>,
+    #[serde(with = "hexutil::quantity::opt", default)]
+    pub nonce: Option<u64>,
+    // "input" is the newer name of "data", it is preferred by clients.
+    #[serde(with = "hexutil::bytes::opt", default)]
+    pub data: Option<Vec<u8>>,
+    #[serde(with = "hexutil::bytes::opt", default)]
+    pub input: Option<Vec<u8>>,
+}
+
+impl TransactionArgs {
+    // to_message converts the arguments to the Message of a call executed
+    // on top of header, with gas as its gas limit. The account checks are
+    // skipped and the fees default to zero, so that calls from any address
+    // can be made without funds.
+    fn to_message(&self, header: &Header, gas: u64) -> Result<Message, RpcError> {
+        if self.gas_price.is_some()
+            && (self.max_fee_per_gas.is_some() || self.max_priority_fee_per_gas.is_some())
+        {
+            return Err(RpcError::invalid_params(
+                "both gasPrice and (maxFeePerGas or maxPriorityFeePerGas) specified",
+            ));
+        }
+        let (gas_price, gas_fee_cap, gas_tip_cap) = match &self.gas_price {
+            Some(price) => (price.clone(), price.clone(), price.clone()),
+            None => {
+                let fee_cap = self.max_fee_per_gas.clone().unwrap_or_default();
+                let tip_cap = self.max_priority_fee_per_gas.clone().unwrap_or_default();
+                // The effective price is min(tip + base fee, fee cap)
+                let price = match &header.base_fee {
+                    Some(base_fee) if !fee_cap.is_zero() || !tip_cap.is_zero() => {
+                        match tip_cap.checked_add(base_fee) {
+                            Some(price) if price < fee_cap => price,
+                            _ => fee_cap.clone(),
+                        }
+                    }
+                    _ => Uint256::default(),
+                };
+                (price, fee_cap, tip_cap)
+            }
+        };
+        Ok(Message {
+            to: self.to,
+            from: self.from.unwrap_or_default(),
+            nonce: self.nonce.unwrap_or_default(),
+            value: self.value.clone().unwrap_or_default(),
+            gas_limit: gas,
+            gas_price,
+            gas_fee_cap,
+            gas_tip_cap,
+            data: self
+                .input
+                .clone()
+                .or_else(|| self.data.clone())
+                .unwrap_or_default(),
+            skip_account_checks: true,
+            ..Default::default()
+        })
+    }
+}
+
+// FilterQuery contains the options of a log filter: a block range or a
+// single block hash, the emitting addresses and the topics per position.
+// An empty address list matches any address, an empty topic list any topic
+// at that position.
+#[derive(Debug, Clone, Default)]
+pub struct FilterQuery {
+    pub block_hash: Option<common::Hash>,
+    pub from_block: Option<Value>,
+    pub to_block: Option<Value>,
+    pub addresses: Vec<common::Address>,
+    pub topics: Vec<Vec<common::Hash>>,
+}
+
+impl FilterQuery {
+    fn from_json(value: &Value) -> Result<Self, RpcError> {
+        let obj = value
+            .as_object()
+            .ok_or_else(|| RpcError::invalid_params("filter must be an object"))?;
+        let mut query = FilterQuery {
+            block_hash: match obj.get("blockHash") {
+                Some(Value::Null) | None => None,
+                Some(hash) => Some(parse_hash(hash)?),
+            },
+            from_block: obj.get("fromBlock").filter(|v| !v.is_null()).cloned(),
+            to_block: obj.get("toBlock").filter(|v| !v.is_null()).cloned(),
+            ..Default::default()
+        };
+        if query.block_hash.is_some() && (query.from_block.is_some() || query.to_block.is_some()) {
+            return Err(RpcError::invalid_params(
+                "cannot specify both blockHash and fromBlock/toBlock",
+            ));
+        }
+        match obj.get("address") {
+            Some(Value::Null) | None => {}
+            Some(Value::Array(addrs)) => {
+                for addr in addrs {
+                    query.addresses.push(parse_address(addr)?);
+                }
+            }
+            Some(addr) => query.addresses.push(parse_address(addr)?),
+        }
+        match obj.get("topics") {
+            Some(Value::Null) | None => {}
+            Some(Value::Array(positions)) => {
+                for position in positions {
+                    let topics = match position {
+                        Value::Null => Vec::new(),
+                        Value::Array(topics) => {
+                            topics.iter().map(parse_hash).collect::<Result<_, _>>()?
+                        }
+                        topic => vec![parse_hash(topic)?],
+                    };
+                    query.topics.push(topics);
+                }
+            }
+            Some(_) => return Err(RpcError::invalid_params("topics must be an array")),
+        }
+        Ok(query)
+    }
+
+    // bloom_matches reports whether a block with the given bloom may hold
+    // logs matching the filter.
+    fn bloom_matches(&self, header: &Header) -> bool {
+        if !self.addresses.is_empty()
+            && !self.addresses.iter().any(|addr| header.bloom.test(&addr.0))
+        {
+            return false;
+        }
+        self.topics.iter().all(|topics| {
+            topics.is_empty()
+                || topics
+                    .iter()
+                    .any(|topic| header.bloom.test(topic.as_bytes()))
+        })
+    }
+
+    // matches reports whether a log matches the addresses and topics of the
+    // filter.
+    fn matches(&self, log: &Log) -> bool {
+        if !self.addresses.is_empty() && !self.addresses.contains(&log.address) {
+            return false;
+        }
+        if self.topics.len() > log.topics.len() {
+            return false;
+        }
+        self.topics
+            .iter()
+            .zip(log.topics.iter())
+            .all(|(topics, topic)| topics.is_empty() || topics.contains(topic))
+    }
+}
+
+fn parse_hash(value: &Value) -> Result<common::Hash, RpcError> {
+    value
+        .as_str()
+        .and_then(|s| hexutil::decode_fixed::<32>(s).ok())
+        .map(common::Hash)
+        .ok_or_else(|| RpcError::invalid_params(format!("invalid hash: {}", value)))
+}
+
+fn parse_address(value: &Value) -> Result<common::Address, RpcError> {
+    value
+        .as_str()
+        .and_then(|s| hexutil::decode_fixed::<20>(s).ok())
+        .map(common::Address)
+        .ok_or_else(|| RpcError::invalid_params(format!("invalid address: {}", value)))
+}
+
+fn parse_bool(value: Option<&Value>) -> Result<bool, RpcError> {
+    match value {
+        None | Some(Value::Null) => Ok(false),
+        Some(Value::Bool(b)) => Ok(*b),
+        Some(value) => Err(RpcError::invalid_params(format!("invalid bool: {}", value))),
+    }
+}
+
+// param returns the i-th positional parameter, failing if it is missing.
+fn param(params: &[Value], i: usize) -> Result<&Value, RpcError> {
+    params.get(i).filter(|v| !v.is_null()).ok_or_else(|| {
+        RpcError::invalid_params(format!("missing value for required argument {}", i))
+    })
+}
+
+// is_pending reports whether a block parameter refers to the pending state.
+fn is_pending(value: Option<&Value>) -> bool {
+    value.and_then(Value::as_str) == Some("pending")
+}
+
+// rpc_marshal_header converts a header to its JSON representation, adding
+// the block hash.
+fn rpc_marshal_header(header: &Header) -> Value {
+    let mut fields = serde_json::to_value(header).unwrap_or_else(|_| json!({}));
+    fields["hash"] = Value::String(hexutil::encode(header.hash().as_bytes()));
+    fields
+}
+
+// rpc_marshal_transaction converts a transaction to its JSON
+// representation, adding the sender and the inclusion information.
+fn rpc_marshal_transaction(
+    tx: &Transaction,
+    chain_id: u64,
+    block: Option<(&Header, u64)>,
+) -> Value {
+    let mut fields = serde_json::to_value(tx).unwrap_or_else(|_| json!({}));
+    let from = transaction::sender(tx, &Uint256::from_uint64(chain_id)).unwrap_or_default();
+    fields["from"] = Value::String(hexutil::encode(&from.0));
+    match block {
+        Some((header, index)) => {
+            fields["blockHash"] = Value::String(hexutil::encode(header.hash().as_bytes()));
+            fields["blockNumber"] = Value::String(hexutil::encode_u64(header.number));
+            fields["transactionIndex"] = Value::String(hexutil::encode_u64(index));
+            // The gas price of an included dynamic fee transaction is the
+            // effective price it paid.
+            if fields.get("gasPrice").is_none() {
+                let price = tx.effective_gas_price(header.base_fee.as_ref());
+                fields["gasPrice"] = Value::String(hexutil::encode_big(&price));
+            }
+        }
+        None => {
+            fields["blockHash"] = Value::Null;
+            fields["blockNumber"] = Value::Null;
+            fields["transactionIndex"] = Value::Null;
+        }
+    }
+    fields
+}
+
+// rpc_marshal_block converts a block to its JSON representation. If
+// full_tx is true the transactions are returned in full, otherwise only
+// their hashes are.
+fn rpc_marshal_block(block: &Block, full_tx: bool, chain_id: u64) -> Value {
+    let mut fields = rpc_marshal_header(&block.header);
+    fields["size"] = Value::String(hexutil::encode_u64(rlp::encode(block).len() as u64));
+    let txs = block
+        .transactions()
+        .iter()
+        .enumerate()
+        .map(|(i, tx)| {
+            if full_tx {
+                rpc_marshal_transaction(tx, chain_id, Some((&block.header, i as u64)))
+            } else {
+                Value::String(hexutil::encode(tx.hash().as_bytes()))
+            }
+        })
+        .collect();
+    fields["transactions"] = Value::Array(txs);
+    fields["uncles"] = Value::Array(
+        block
+            .uncles()
+            .iter()
+            .map(|uncle| Value::String(hexutil::encode(uncle.hash().as_bytes())))
+            .collect(),
+    );
+    if let Some(withdrawals) = block.withdrawals() {
+        fields["withdrawals"] = serde_json::to_value(withdrawals).unwrap_or(Value::Null);
+    }
+    fields
+}
+
+// rpc_marshal_receipt converts a receipt to its JSON representation, adding
+// the sender and recipient of its transaction.
+fn rpc_marshal_receipt(receipt: &Receipt, tx: &Transaction, chain_id: u64) -> Value {
+    let mut fields = serde_json::to_value(receipt).unwrap_or_else(|_| json!({}));
+    let from = transaction::sender(tx, &Uint256::from_uint64(chain_id)).unwrap_or_default();
+    fields["from"] = Value::String(hexutil::encode(&from.0));
+    fields["to"] = match tx.to() {
+        Some(to) => Value::String(hexutil::encode(&to.0)),
+        None => Value::Null,
+    };
+    if fields.get("contractAddress").is_none() {
+        fields["contractAddress"] = Value::Null;
+    }
+    fields
+}
+
+// EthApi implements the eth namespace on top of a chain and a transaction
+// pool.
+pub struct EthApi {
+    chain: BlockChain,
+    pool: TxPool,
+    // pool_head is the hash of the head the pool was last reset to.
+    pool_head: common::Hash,
+}
+
+impl EthApi {
+    pub fn new(chain: BlockChain, pool: TxPool) -> Self {
+        let pool_head = chain.current_header().hash();
+        EthApi {
+            chain,
+            pool,
+            pool_head,
+        }
+    }
+
+    pub fn chain(&self) -> &BlockChain {
+        &self.chain
+    }
+
+    // chain_mut gives access to the chain, e.g. to import blocks. The pool
+    // follows the new head on the next request.
+    pub fn chain_mut(&mut self) -> &mut BlockChain {
+        &mut self.chain
+    }
+
+    pub fn pool(&self) -> &TxPool {
+        &self.pool
+    }
+
+    pub fn pool_mut(&mut self) -> &mut TxPool {
+        &mut self.pool
+    }
+
+    // sync_pool resets the pool to the chain head if the head changed since
+    // the last reset, dropping the transactions included in the new blocks.
+    fn sync_pool(&mut self) -> Result<(), RpcError> {
+        let head = self.chain.current_header().clone();
+        let hash = head.hash();
+        if hash != self.pool_head {
+            let state = self.chain.state_at(&head.root)?;
+            self.pool.reset(head, state);
+            self.pool_head = hash;
+        }
+        Ok(())
+    }
+
+    fn chain_id(&self) -> u64 {
+        self.chain.chain_config().chain_id
+    }
+
+    // handle answers a JSON-RPC request body, which is either a single
+    // request or a batch, and returns the response body. Notifications,
+    // requests without an id, get no response.
+    pub fn handle(&mut self, body: &str) -> Option<String> {
+        let request: Value = match serde_json::from_str(body) {
+            Ok(request) => request,
+            Err(err) => {
+                return Some(
+                    error_response(Value::Null, &RpcError::new(PARSE_ERROR, err.to_string()))
+                        .to_string(),
+                )
+            }
+        };
+        let response = match request {
+            Value::Array(batch) => {
+                if batch.is_empty() {
+                    error_response(Value::Null, &RpcError::new(INVALID_REQUEST, "empty batch"))
+                } else {
+                    let responses: Vec<Value> = batch
+                        .iter()
+                        .filter_map(|req| self.handle_call(req))
+                        .collect();
+                    if responses.is_empty() {
+                        return None;
+                    }
+                    Value::Array(responses)
+                }
+            }
+            request => self.handle_call(&request)?,
+        };
+        Some(response.to_string())
+    }
+
+    // handle_call answers a single request object.
+    fn handle_call(&mut self, request: &Value) -> Option<Value> {
+        let obj = match request.as_object() {
+            Some(obj) => obj,
+            None => {
+                return Some(error_response(
+                    Value::Null,
+                    &RpcError::new(INVALID_REQUEST, "invalid request"),
+                ))
+            }
+        };
+        let id = obj.get("id").cloned();
+        let method = match obj.get("method").and_then(Value::as_str) {
+            Some(method) => method,
+            None => {
+                return Some(error_response(
+                    id.unwrap_or(Value::Null),
+                    &RpcError::new(INVALID_REQUEST, "invalid request"),
+                ))
+            }
+        };
+        let params = match obj.get("params") {
+            None | Some(Value::Null) => Vec::new(),
+            Some(Value::Array(params)) => params.clone(),
+            Some(_) => {
+                return Some(error_response(
+                    id.unwrap_or(Value::Null),
+                    &RpcError::invalid_params("non-array args"),
+                ))
+            }
+        };
+        let result = self.call(method, &params);
+        let id = id?;
+        Some(match result {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            Err(err) => error_response(id, &err),
+        })
+    }
+
+    // call dispatches a method call with positional parameters.
+    pub fn call(&mut self, method: &str, params: &[Value]) -> Result<Value, RpcError> {
+        self.sync_pool()?;
+        match method {
+            "eth_chainId" => Ok(Value::String(hexutil::encode_u64(self.chain_id()))),
+            "eth_blockNumber" => Ok(Value::String(hexutil::encode_u64(
+                self.chain.current_header().number,
+            ))),
+            "eth_getBalance" => {
+                let addr = parse_address(param(params, 0)?)?;
+                let state = self.state_at_block(params.get(1))?;
+                Ok(Value::String(hexutil::encode_big(
+                    &state.get_balance(&addr),
+                )))
+            }
+            "eth_getCode" => {
+                let addr = parse_address(param(params, 0)?)?;
+                let state = self.state_at_block(params.get(1))?;
+                Ok(Value::String(hexutil::encode(&state.get_code(&addr))))
+            }
+            "eth_getStorageAt" => {
+                let addr = parse_address(param(params, 0)?)?;
+                let key = param(params, 1)?
+                    .as_str()
+                    .and_then(|s| hexutil::decode_big(s).ok())
+                    .ok_or_else(|| RpcError::invalid_params("invalid storage key"))?;
+                let state = self.state_at_block(params.get(2))?;
+                let value = state.get_state(&addr, &common::Hash(key.to_bytes32()));
+                Ok(Value::String(hexutil::encode(value.as_bytes())))
+            }
+            "eth_getTransactionCount" => {
+                let addr = parse_address(param(params, 0)?)?;
+                // The pending nonce accounts for the transactions in the pool
+                if is_pending(params.get(1)) {
+                    return Ok(Value::String(hexutil::encode_u64(self.pool.nonce(&addr))));
+                }
+                let state = self.state_at_block(params.get(1))?;
+                Ok(Value::String(hexutil::encode_u64(state.get_nonce(&addr))))
+            }
+            "eth_call" => {
+                let args = parse_args(param(params, 0)?)?;
+                let header = self.header_by_param(params.get(1))?;
+                let gas = args.gas.unwrap_or(header.gas_limit);
+                let result = self.do_call(&args, &header, gas)?;
+                if let Some(data) = result.revert() {
+                    return Err(RpcError::reverted(data));
+                }
+                if let Some(err) = &result.err {
+                    return Err(RpcError::server(err.to_string()));
+                }
+                Ok(Value::String(hexutil::encode(&result.return_data)))
+            }
+            "eth_estimateGas" => {
+                let args = parse_args(param(params, 0)?)?;
+                let header = self.header_by_param(params.get(1))?;
+                let gas = self.estimate_gas(&args, &header)?;
+                Ok(Value::String(hexutil::encode_u64(gas)))
+            }
+            "eth_sendRawTransaction" => {
+                let input = param(params, 0)?
+                    .as_str()
+                    .and_then(|s| hexutil::decode(s).ok())
+                    .ok_or_else(|| RpcError::invalid_params("invalid raw transaction"))?;
+                let tx = Transaction::unmarshal_binary(&input)
+                    .map_err(|err| RpcError::server(err.to_string()))?;
+                let hash = tx.hash();
+                self.pool
+                    .add(tx)
+                    .map_err(|err| RpcError::server(err.to_string()))?;
+                Ok(Value::String(hexutil::encode(hash.as_bytes())))
+            }
+            "eth_getTransactionByHash" => {
+                let hash = parse_hash(param(params, 0)?)?;
+                if let Some((tx, block_hash, _, index)) = self.chain.get_transaction(&hash)? {
+                    let header = self
+                        .chain
+                        .get_header(&block_hash)?
+                        .ok_or(ChainError::UnknownBlock(block_hash))?;
+                    return Ok(rpc_marshal_transaction(
+                        &tx,
+                        self.chain_id(),
+                        Some((&header, index)),
+                    ));
+                }
+                Ok(match self.pool.get(&hash) {
+                    Some(tx) => rpc_marshal_transaction(tx, self.chain_id(), None),
+                    None => Value::Null,
+                })
+            }
+            "eth_getTransactionReceipt" => {
+                let hash = parse_hash(param(params, 0)?)?;
+                let (tx, block_hash, _, index) = match self.chain.get_transaction(&hash)? {
+                    Some(found) => found,
+                    None => return Ok(Value::Null),
+                };
+                let receipts = self
+                    .chain
+                    .get_receipts(&block_hash)?
+                    .ok_or(ChainError::UnknownBlock(block_hash))?;
+                Ok(match receipts.get(index as usize) {
+                    Some(receipt) => rpc_marshal_receipt(receipt, &tx, self.chain_id()),
+                    None => Value::Null,
+                })
+            }
+            "eth_getBlockByNumber" => {
+                let full_tx = parse_bool(params.get(1))?;
+                let block = match self.header_by_tag(param(params, 0)?)? {
+                    Some(header) => self.chain.get_block(&header.hash())?,
+                    None => None,
+                };
+                Ok(block.map_or(Value::Null, |block| {
+                    rpc_marshal_block(&block, full_tx, self.chain_id())
+                }))
+            }
+            "eth_getBlockByHash" => {
+                let hash = parse_hash(param(params, 0)?)?;
+                let full_tx = parse_bool(params.get(1))?;
+                Ok(self.chain.get_block(&hash)?.map_or(Value::Null, |block| {
+                    rpc_marshal_block(&block, full_tx, self.chain_id())
+                }))
+            }
+            "eth_getLogs" => {
+                let query = FilterQuery::from_json(param(params, 0)?)?;
+                let logs = self.get_logs(&query)?;
+                Ok(serde_json::to_value(logs).unwrap_or(Value::Null))
+            }
+            _ => Err(RpcError::new(
+                METHOD_NOT_FOUND,
+                format!("the method {} does not exist/is not available", method),
+            )),
+        }
+    }
+
+    // header_by_tag resolves a block number or tag to a canonical header.
+    // "pending", "safe" and "finalized" resolve to the head, as there is no
+    // pending block and no finality in a local chain.
+    fn header_by_tag(&self, value: &Value) -> Result<Option<Header>, RpcError> {
+        let tag = value
+            .as_str()
+            .ok_or_else(|| RpcError::invalid_params(format!("invalid block number: {}", value)))?;
+        match tag {
+            "latest" | "pending" | "safe" | "finalized" => {
+                Ok(Some(self.chain.current_header().clone()))
+            }
+            "earliest" => Ok(self.chain.get_header_by_number(0)?),
+            number => {
+                let number = hexutil::decode_u64(number)
+                    .map_err(|err| RpcError::invalid_params(err.to_string()))?;
+                Ok(self.chain.get_header_by_number(number)?)
+            }
+        }
+    }
+
+    // header_by_param resolves a block parameter: a number or tag, a block
+    // hash or an EIP-1898 object. A missing parameter means "latest".
+    fn header_by_param(&self, value: Option<&Value>) -> Result<Header, RpcError> {
+        let header = match value {
+            None | Some(Value::Null) => Some(self.chain.current_header().clone()),
+            Some(Value::Object(obj)) => match (obj.get("blockHash"), obj.get("blockNumber")) {
+                (Some(hash), None) => self.chain.get_header(&parse_hash(hash)?)?,
+                (None, Some(number)) => self.header_by_tag(number)?,
+                _ => {
+                    return Err(RpcError::invalid_params(
+                        "exactly one of blockHash and blockNumber must be specified",
+                    ))
+                }
+            },
+            Some(Value::String(s)) if s.len() == 66 => self
+                .chain
+                .get_header(&parse_hash(&Value::String(s.clone()))?)?,
+            Some(value) => self.header_by_tag(value)?,
+        };
+        header.ok_or_else(|| RpcError::server("header not found"))
+    }
+
+    fn state_at_block(&self, value: Option<&Value>) -> Result<Box<dyn StateDB>, RpcError> {
+        let header = self.header_by_param(value)?;
+        Ok(self.chain.state_at(&header.root)?)
+    }
+
+    // do_call executes a message call on the state of header. The state
+    // changes are discarded.
+    fn do_call(
+        &self,
+        args: &TransactionArgs,
+        header: &Header,
+        gas: u64,
+    ) -> Result<ExecutionResult, RpcError> {
+        let msg = args.to_message(header, gas)?;
+        let state = self.chain.state_at(&header.root)?;
+        let tx_ctx = TxContext {
+            origin: msg.from,
+            gas_price: msg.gas_price.clone(),
+            blob_hashes: msg.blob_hashes.clone(),
+        };
+        let mut evm = EVM::new(
+            new_block_context(header, self.chain.get_hash_fn(header)),
+            tx_ctx,
+            state,
+            self.chain.chain_config().clone(),
+            Config {
+                no_base_fee: true,
+                ..Default::default()
+            },
+        );
+        let mut gp = GasPool::new(gas);
+        apply_message(&mut evm, &msg, &mut gp).map_err(|err| RpcError::server(err.to_string()))
+    }
+
+    // estimate_gas returns the lowest gas limit at which the call succeeds,
+    // searching between the cost of a plain transfer and the given gas or
+    // the block gas limit.
+    fn estimate_gas(&self, args: &TransactionArgs, header: &Header) -> Result<u64, RpcError> {
+        let mut hi = match args.gas {
+            Some(gas) if gas >= params::TX_GAS => gas.min(header.gas_limit),
+            _ => header.gas_limit,
+        };
+        let mut lo = params::TX_GAS - 1;
+        // Make sure the call succeeds at all with the highest allowance
+        let result = self.do_call(args, header, hi)?;
+        if let Some(data) = result.revert() {
+            return Err(RpcError::reverted(data));
+        }
+        if let Some(err) = &result.err {
+            return Err(RpcError::server(format!(
+                "gas required exceeds allowance ({}): {}",
+                hi, err
+            )));
+        }
+        // The used gas is a lower bound, but the refunds and the 63/64 rule
+        // of the nested calls may require more, so search from there.
+        lo = lo.max(result.used_gas.saturating_sub(1));
+        while lo + 1 < hi {
+            let mid = lo + (hi - lo) / 2;
+            match self.do_call(args, header, mid) {
+                Ok(result) if !result.failed() => hi = mid,
+                _ => lo = mid,
+            }
+        }
+        Ok(hi)
+    }
+
+    // get_logs returns the logs matching the filter, either of a single
+    // block or of a range of canonical blocks.
+    fn get_logs(&self, query: &FilterQuery) -> Result<Vec<Log>, RpcError> {
+        if let Some(hash) = &query.block_hash {
+            let header = self
+                .chain
+                .get_header(hash)?
+                .ok_or_else(|| RpcError::server("unknown block"))?;
+            return self.block_logs(&header, query);
+        }
+        let head = self.chain.current_header().number;
+        let resolve = |value: &Option<Value>| -> Result<u64, RpcError> {
+            match value {
+                None => Ok(head),
+                Some(value) => Ok(self.header_by_tag(value)?.map_or(head, |h| h.number)),
+            }
+        };
+        let from = resolve(&query.from_block)?;
+        let to = resolve(&query.to_block)?;
+        if from > to {
+            return Err(RpcError::invalid_params("invalid block range"));
+        }
+        if to - from >= MAX_LOG_BLOCK_RANGE {
+            return Err(RpcError::server(format!(
+                "block range too large, max {} blocks",
+                MAX_LOG_BLOCK_RANGE
+            )));
+        }
+        let mut logs = Vec::new();
+        for number in from..=to {
+            if let Some(header) = self.chain.get_header_by_number(number)? {
+                logs.extend(self.block_logs(&header, query)?);
+            }
+        }
+        Ok(logs)
+    }
+
+    fn block_logs(&self, header: &Header, query: &FilterQuery) -> Result<Vec<Log>, RpcError> {
+        if !query.bloom_matches(header) {
+            return Ok(Vec::new());
+        }
+        let hash = header.hash();
+        let receipts = self.chain.get_receipts(&hash)?.unwrap_or_default();
+        Ok(receipts
+            .into_iter()
+            .flat_map(|receipt| receipt.logs)
+            .filter(|log| query.matches(log))
+            .collect())
+    }
+}
+
+fn parse_args(value: &Value) -> Result<TransactionArgs, RpcError> {
+    TransactionArgs::deserialize(value).map_err(|err| RpcError::invalid_params(err.to_string()))
+}
+
+fn error_response(id: Value, err: &RpcError) -> Value {
+    let mut response = Map::new();
+    response.insert("jsonrpc".to_string(), Value::String("2.0".to_string()));
+    response.insert("id".to_string(), id);
+    response.insert("error".to_string(), err.to_json());
+    Value::Object(response)
+}
+
+// Server serves an EthApi over HTTP.
+pub struct Server {
+    api: EthApi,
+    listener: TcpListener,
+    read_timeout: Duration,
+    write_timeout: Duration,
+}
+
+impl Server {
+    // listen binds the server to addr, e.g. "127.0.0.1:8545". Port 0 picks
+    // a free port, which local_addr reports.
+    pub fn listen(api: EthApi, addr: impl ToSocketAddrs) -> io::Result<Self> {
+        Ok(Server {
+            api,
+            listener: TcpListener::bind(addr)?,
+            read_timeout: READ_TIMEOUT,
+            write_timeout: WRITE_TIMEOUT,
+        })
+    }
+
+    // set_timeouts overrides the default request read deadline and response
+    // write timeout.
+    pub fn set_timeouts(&mut self, read: Duration, write: Duration) {
+        self.read_timeout = read;
+        self.write_timeout = write;
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    pub fn api(&self) -> &EthApi {
+        &self.api
+    }
+
+    pub fn api_mut(&mut self) -> &mut EthApi {
+        &mut self.api
+    }
+
+    // serve answers incoming connections until accepting one fails.
+    pub fn serve(&mut self) -> io::Result<()> {
+        loop {
+            self.serve_one()?;
+        }
+    }
+
+    // serve_one accepts a single connection and answers its request. Errors
+    // of the connection itself are not returned, the client just sees it
+    // closed.
+    pub fn serve_one(&mut self) -> io::Result<()> {
+        let (stream, _) = self.listener.accept()?;
+        let _ = self.handle_connection(stream);
+        Ok(())
+    }
+
+    fn handle_connection(&mut self, stream: TcpStream) -> io::Result<()> {
+        stream.set_write_timeout(Some(self.write_timeout))?;
+        let deadline = DeadlineReader {
+            stream: stream.try_clone()?,
+            deadline: Instant::now() + self.read_timeout,
+        };
+        let mut reader = BufReader::new(deadline.take(MAX_HEADER_SIZE));
+        let mut stream = stream;
+        let mut request_line = String::new();
+        reader.read_line(&mut request_line)?;
+        let method = request_line
+            .split_whitespace()
+            .next()
+            .unwrap_or_default()
+            .to_string();
+
+        // A malformed length, or several differing ones, leaves the body
+        // boundary unknown and fails the request.
+        let mut content_length: Option<Option<usize>> = None;
+        loop {
+            let mut line = String::new();
+            if reader.read_line(&mut line)? == 0 {
+                // The headers ran into the size limit before their end
+                if reader.get_ref().limit() == 0 {
+                    return write_response(
+                        &mut stream,
+                        "431 Request Header Fields Too Large",
+                        "text/plain",
+                        b"request headers too large\n",
+                    );
+                }
+                break;
+            }
+            if line.trim_end().is_empty() {
+                break;
+            }
+            if let Some((name, value)) = line.split_once(':') {
+                if name.trim().eq_ignore_ascii_case("content-length") {
+                    let length = parse_content_length(value);
+                    content_length = match content_length {
+                        Some(prev) if prev != length => Some(None),
+                        _ => Some(length),
+                    };
+                }
+            }
+        }
+
+        if method != "POST" {
+            return write_response(
+                &mut stream,
+                "405 Method Not Allowed",
+                "text/plain",
+                b"method not allowed\n",
+            );
+        }
+        let content_length = match content_length {
+            Some(Some(length)) => length,
+            Some(None) => {
+                return write_response(
+                    &mut stream,
+                    "400 Bad Request",
+                    "text/plain",
+                    b"invalid content length\n",
+                )
+            }
+            None => {
+                return write_response(
+                    &mut stream,
+                    "411 Length Required",
+                    "text/plain",
+                    b"missing content length\n",
+                )
+            }
+        };
+        if content_length > MAX_REQUEST_CONTENT_LENGTH {
+            return write_response(
+                &mut stream,
+                "413 Payload Too Large",
+                "text/plain",
+                format!(
+                    "content length too large ({}>{})\n",
+                    content_length, MAX_REQUEST_CONTENT_LENGTH
+                )
+                .as_bytes(),
+            );
+        }
+        let mut body = vec![0u8; content_length];
+        reader.get_mut().set_limit(content_length as u64);
+        reader.read_exact(&mut body)?;
+        let body = String::from_utf8_lossy(&body);
+        let response = self.api.handle(&body).unwrap_or_default();
+        write_response(
+            &mut stream,
+            "200 OK",
+            "application/json",
+            response.as_bytes(),
+        )
+    }
+}
+
+// DeadlineReader reads a request from a stream until a fixed deadline. The
+// socket read timeout is shrunk to the time left before every read, so a
+// client trickling bytes can't hold the connection past the deadline.
+struct DeadlineReader {
+    stream: TcpStream,
+    deadline: Instant,
+}
+
+impl Read for DeadlineReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let left = self.deadline.saturating_duration_since(Instant::now());
+        if left.is_zero() {
+            return Err(io::Error::new(
+                io::ErrorKind::TimedOut,
+                "request read deadline exceeded",
+            ));
+        }
+        self.stream.set_read_timeout(Some(left))?;
+        self.stream.read(buf)
+    }
+}
+
+// parse_content_length parses a Content-Length header value, which must be
+// a plain decimal number.
+fn parse_content_length(value: &str) -> Option<usize> {
+    let value = value.trim();
+    if value.is_empty() || !value.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    value.parse().ok()
+}
+
+fn write_response(
+    stream: &mut TcpStream,
+    status: &str,
+    content_type: &str,
+    body: &[u8],
+) -> io::Result<()> {
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        status,
+        content_type,
+        body.len()
+    )?;
+    stream.write_all(body)?;
+    stream.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use std::net::Shutdown;
+    use std::sync::Arc;
+    use std::thread;
+
+    use crate::chain_config::ChainConfig;
+    use crate::crypto::{self, PrivateKey};
+    use crate::ethdb::MemoryDatabase;
+    use crate::genesis::{Genesis, GenesisAccount};
+    use crate::pow::ProofOfWork;
+    use crate::miner;
+    use crate::pruner::GcMode;
+    use crate::transaction::{LegacyTx, TxData};
+    use crate::txpool;
+
+    // Contracts of the test chain: RETURNER returns 42 as a word, REVERTER
+    // reverts with 0xdeadbeef, STORER writes a storage slot, and LOGGER1 and
+    // LOGGER2 emit an empty log with topic 1 and 2.
+    const RETURNER: common::Address = common::Address([0xa1; 20]);
+    const REVERTER: common::Address = common::Address([0xa2; 20]);
+    const STORER: common::Address = common::Address([0xa3; 20]);
+    const LOGGER1: common::Address = common::Address([0xa4; 20]);
+    const LOGGER2: common::Address = common::Address([0xa5; 20]);
+
+    fn test_key() -> PrivateKey {
+        crypto::to_ecdsa(&[1; 32]).unwrap()
+    }
+
+    fn test_api() -> EthApi {
+        let mut alloc = HashMap::new();
+        let accounts: [(common::Address, u64, Vec<u8>); 7] = [
+            (common::Address([0x11; 20]), 1_000_000, Vec::new()),
+            (test_key().address(), 1_000_000_000, Vec::new()),
+            (RETURNER, 0, vec![0x60, 0x2a, 0x60, 0x00, 0x52, 0x60, 0x20, 0x60, 0x00, 0xf3]),
+            (REVERTER, 0, vec![0x63, 0xde, 0xad, 0xbe, 0xef, 0x60, 0x00, 0x52, 0x60, 0x04, 0x60, 0x1c, 0xfd]),
+            (STORER, 0, vec![0x60, 0x2a, 0x60, 0x00, 0x55, 0x00]),
+            (LOGGER1, 0, vec![0x60, 0x01, 0x60, 0x00, 0x60, 0x00, 0xa1, 0x00]),
+            (LOGGER2, 0, vec![0x60, 0x02, 0x60, 0x00, 0x60, 0x00, 0xa1, 0x00]),
+        ];
+        for (addr, balance, code) in accounts {
+            alloc.insert(
+                addr,
+                GenesisAccount {
+                    balance: Uint256::from_uint64(balance),
+                    code,
+                    ..Default::default()
+                },
+            );
+        }
+        let genesis = Genesis {
+            config: ChainConfig {
+                chain_id: 1337,
+                homestead_block: Some(0),
+                eip150_block: Some(0),
+                eip155_block: Some(0),
+                eip158_block: Some(0),
+                byzantium_block: Some(0),
+                constantinople_block: Some(0),
+                petersburg_block: Some(0),
+                istanbul_block: Some(0),
+                berlin_block: Some(0),
+                ..Default::default()
+            },
+            alloc,
+            ..Default::default()
+        };
+        let chain = BlockChain::new(Arc::new(MemoryDatabase::new()), &genesis, Box::new(ProofOfWork::fake()), GcMode::default()).unwrap();
+        let head = chain.current_header().clone();
+        let state = chain.state_at(&head.root).unwrap();
+        let pool = TxPool::new(txpool::Config::default(), genesis.config.clone(), head, state);
+        EthApi::new(chain, pool)
+    }
+
+    fn test_server() -> (Server, common::Address) {
+        let addr = common::Address([0x11; 20]);
+        (Server::listen(test_api(), "127.0.0.1:0").unwrap(), addr)
+    }
+
+    // call_tx returns a signed call of to from the test key.
+    fn call_tx(nonce: u64, to: common::Address) -> Transaction {
+        let tx = Transaction::new(TxData::Legacy(LegacyTx {
+            nonce,
+            gas_price: Uint256::from_uint64(1),
+            gas: 100_000,
+            to: Some(to),
+            ..Default::default()
+        }));
+        transaction::sign_tx(&tx, &Uint256::from_uint64(1337), &test_key()).unwrap()
+    }
+
+    fn send(api: &mut EthApi, tx: &Transaction) -> Value {
+        api.call("eth_sendRawTransaction", &[json!(hexutil::encode(&tx.marshal_binary()))])
+            .unwrap()
+    }
+
+    // mine seals the pending transactions of the pool into a new head.
+    fn mine(api: &mut EthApi) -> Block {
+        let parent = api.chain().current_header().clone();
+        let header = miner::prepare_header(api.chain().chain_config(), &miner::Config::default(), &parent, parent.time + 10);
+        let state = api.chain().state_at(&parent.root).unwrap();
+        let get_hash = api.chain().get_hash_fn(&parent);
+        let pending = api.pool().pending();
+        let block = miner::build_block(api.chain(), &ProofOfWork::fake(), header, state, pending, get_hash)
+            .unwrap()
+            .block;
+        api.chain_mut().insert_block(&block).unwrap();
+        block
+    }
+
+    fn call_args(to: common::Address) -> Value {
+        json!({"from": hexutil::encode(&test_key().address().0), "to": hexutil::encode(&to.0)})
+    }
+
+    #[test]
+    fn test_eth_call() {
+        let mut api = test_api();
+        let res = api.call("eth_call", &[call_args(RETURNER), json!("latest")]).unwrap();
+        assert_eq!(res, json!(format!("0x{}2a", "0".repeat(62))));
+        let res = api.call("eth_call", &[call_args(STORER), json!("0x0")]).unwrap();
+        assert_eq!(res, json!("0x"));
+
+        // A revert carries the revert data
+        let err = api.call("eth_call", &[call_args(REVERTER), json!("latest")]).unwrap_err();
+        assert_eq!(err.code, REVERT_ERROR);
+        assert_eq!(err.message, "execution reverted");
+        assert_eq!(err.data, Some(json!("0xdeadbeef")));
+
+        // Running out of gas is a plain error
+        let mut args = call_args(STORER);
+        args["gas"] = json!("0x5300");
+        let err = api.call("eth_call", &[args, json!("latest")]).unwrap_err();
+        assert_eq!(err.code, SERVER_ERROR);
+
+        let err = api.call("eth_call", &[call_args(RETURNER), json!("0x5")]).unwrap_err();
+        assert_eq!(err.message, "header not found");
+    }
+
+    #[test]
+    fn test_eth_estimate_gas() {
+        let mut api = test_api();
+        let res = api.call("eth_estimateGas", &[call_args(common::Address([0x22; 20]))]).unwrap();
+        assert_eq!(res, json!(hexutil::encode_u64(params::TX_GAS)));
+
+        // The estimate is the lowest gas limit the call succeeds with
+        let res = api.call("eth_estimateGas", &[call_args(STORER)]).unwrap();
+        let gas = hexutil::decode_u64(res.as_str().unwrap()).unwrap();
+        assert!(gas > params::TX_GAS);
+        let mut args = call_args(STORER);
+        args["gas"] = json!(hexutil::encode_u64(gas));
+        assert!(api.call("eth_call", &[args.clone(), json!("latest")]).is_ok());
+        args["gas"] = json!(hexutil::encode_u64(gas - 1));
+        assert!(api.call("eth_call", &[args, json!("latest")]).is_err());
+
+        let err = api.call("eth_estimateGas", &[call_args(REVERTER)]).unwrap_err();
+        assert_eq!(err.code, REVERT_ERROR);
+        assert_eq!(err.data, Some(json!("0xdeadbeef")));
+    }
+
+    #[test]
+    fn test_send_raw_transaction_receipt() {
+        let mut api = test_api();
+        let tx = call_tx(0, LOGGER1);
+        let hash = hexutil::encode(tx.hash().as_bytes());
+        assert_eq!(send(&mut api, &tx), json!(hash));
+
+        // A pending transaction has no receipt yet
+        assert_eq!(api.call("eth_getTransactionReceipt", &[json!(hash)]).unwrap(), Value::Null);
+        let pending = api.call("eth_getTransactionByHash", &[json!(hash)]).unwrap();
+        assert_eq!(pending["blockHash"], Value::Null);
+
+        let block = mine(&mut api);
+        assert_eq!(block.transactions().len(), 1);
+        let receipt = api.call("eth_getTransactionReceipt", &[json!(hash)]).unwrap();
+        assert_eq!(receipt["status"], "0x1");
+        assert_eq!(receipt["transactionHash"], json!(hash));
+        assert_eq!(receipt["blockHash"], json!(hexutil::encode(block.hash().as_bytes())));
+        assert_eq!(receipt["blockNumber"], "0x1");
+        assert_eq!(receipt["from"], json!(hexutil::encode(&test_key().address().0)));
+        assert_eq!(receipt["to"], json!(hexutil::encode(&LOGGER1.0)));
+        assert_eq!(receipt["contractAddress"], Value::Null);
+        assert_eq!(receipt["logs"].as_array().unwrap().len(), 1);
+        assert_eq!(receipt["logs"][0]["address"], json!(hexutil::encode(&LOGGER1.0)));
+
+        // The included transaction is dropped from the pool
+        let err = api
+            .call("eth_sendRawTransaction", &[json!(hexutil::encode(&tx.marshal_binary()))])
+            .unwrap_err();
+        assert_eq!(err.code, SERVER_ERROR);
+        let err = api.call("eth_sendRawTransaction", &[json!("0x1234")]).unwrap_err();
+        assert_eq!(err.code, SERVER_ERROR);
+    }
+
+    #[test]
+    fn test_eth_get_logs() {
+        let mut api = test_api();
+        send(&mut api, &call_tx(0, LOGGER1));
+        send(&mut api, &call_tx(1, LOGGER2));
+        let block1 = mine(&mut api);
+        send(&mut api, &call_tx(2, LOGGER1));
+        send(&mut api, &call_tx(3, STORER));
+        mine(&mut api);
+
+        let topic = |n: u8| {
+            let mut hash = common::Hash::default();
+            hash.0[31] = n;
+            json!(hexutil::encode(hash.as_bytes()))
+        };
+        let logger1 = json!(hexutil::encode(&LOGGER1.0));
+        let logger2 = json!(hexutil::encode(&LOGGER2.0));
+        let tests = [
+            (json!({"fromBlock": "0x0"}), 3),
+            (json!({"fromBlock": "earliest", "toBlock": "latest"}), 3),
+            (json!({}), 1),
+            (json!({"fromBlock": "0x1", "toBlock": "0x1"}), 2),
+            (json!({"fromBlock": "0x0", "address": logger2}), 1),
+            (json!({"fromBlock": "0x0", "address": [logger1, logger2]}), 3),
+            (json!({"fromBlock": "0x0", "topics": [topic(1)]}), 2),
+            (json!({"fromBlock": "0x0", "topics": [[topic(1), topic(2)]]}), 3),
+            (json!({"fromBlock": "0x0", "topics": [null]}), 3),
+            (json!({"fromBlock": "0x0", "topics": [null, topic(1)]}), 0),
+            (json!({"fromBlock": "0x0", "address": logger2, "topics": [topic(1)]}), 0),
+            (json!({"blockHash": hexutil::encode(block1.hash().as_bytes())}), 2),
+            (json!({"blockHash": hexutil::encode(block1.hash().as_bytes()), "address": logger1}), 1),
+        ];
+        for (i, (query, want)) in tests.into_iter().enumerate() {
+            let logs = api.call("eth_getLogs", &[query]).unwrap();
+            assert_eq!(logs.as_array().unwrap().len(), want, "query {}", i);
+        }
+
+        let logs = api.call("eth_getLogs", &[json!({"fromBlock": "0x2", "address": logger1})]).unwrap();
+        assert_eq!(logs[0]["blockNumber"], "0x2");
+        assert_eq!(logs[0]["topics"], json!([topic(1)]));
+        assert_eq!(logs[0]["removed"], false);
+
+        let err = api.call("eth_getLogs", &[json!({"fromBlock": "0x2", "toBlock": "0x1"})]).unwrap_err();
+        assert_eq!(err.code, INVALID_PARAMS);
+    }
+
+    // exchange sends a raw request to the server on a client thread, lets
+    // the server answer it and returns the raw response.
+    fn exchange(server: &mut Server, request: Vec<u8>) -> String {
+        let local = server.local_addr().unwrap();
+        let client = thread::spawn(move || {
+            let mut stream = TcpStream::connect(local).unwrap();
+            stream.write_all(&request).unwrap();
+            let mut response = String::new();
+            let _ = stream.read_to_string(&mut response);
+            response
+        });
+        server.serve_one().unwrap();
+        client.join().unwrap()
+    }
+
+    fn post(server: &mut Server, body: &str) -> String {
+        let request = format!(
+            "POST / HTTP/1.1\r\nHost: localhost\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+            body.len(),
+            body
+        );
+        exchange(server, request.into_bytes())
+    }
+
+    fn response_body(response: &str) -> Value {
+        let (head, body) = response.split_once("\r\n\r\n").unwrap();
+        assert!(head.starts_with("HTTP/1.1 200 OK"), "{}", head);
+        serde_json::from_str(body).unwrap()
+    }
+
+    #[test]
+    fn test_http_requests() {
+        let (mut server, addr) = test_server();
+
+        let res = response_body(&post(&mut server, r#"{"jsonrpc":"2.0","id":1,"method":"eth_chainId"}"#));
+        assert_eq!(res, json!({"jsonrpc": "2.0", "id": 1, "result": "0x539"}));
+
+        let body = format!(
+            r#"[{{"jsonrpc":"2.0","id":1,"method":"eth_getBalance","params":["{}","latest"]}},{{"jsonrpc":"2.0","id":2,"method":"eth_blockNumber"}},{{"jsonrpc":"2.0","id":3,"method":"eth_foo"}}]"#,
+            hexutil::encode(&addr.0)
+        );
+        let res = response_body(&post(&mut server, &body));
+        assert_eq!(res[0]["result"], "0xf4240");
+        assert_eq!(res[1]["result"], "0x0");
+        assert_eq!(res[2]["error"]["code"], METHOD_NOT_FOUND);
+
+        let res = response_body(&post(&mut server, "{"));
+        assert_eq!(res["error"]["code"], PARSE_ERROR);
+    }
+
+    #[test]
+    fn test_http_rejects_bad_requests() {
+        let (mut server, _) = test_server();
+
+        let response = exchange(&mut server, b"GET / HTTP/1.1\r\n\r\n".to_vec());
+        assert!(response.starts_with("HTTP/1.1 405"), "{}", response);
+
+        let request = format!("POST / HTTP/1.1\r\nContent-Length: {}\r\n\r\n", MAX_REQUEST_CONTENT_LENGTH + 1);
+        let response = exchange(&mut server, request.into_bytes());
+        assert!(response.starts_with("HTTP/1.1 413"), "{}", response);
+
+        let response = exchange(&mut server, b"POST / HTTP/1.1\r\n\r\n".to_vec());
+        assert!(response.starts_with("HTTP/1.1 411"), "{}", response);
+
+        for length in ["abc", "-1", "+2", "", "1 2", "99999999999999999999999"] {
+            let request = format!("POST / HTTP/1.1\r\nContent-Length: {}\r\n\r\n{{}}", length);
+            let response = exchange(&mut server, request.into_bytes());
+            assert!(response.starts_with("HTTP/1.1 400"), "{:?}: {}", length, response);
+        }
+        let request = b"POST / HTTP/1.1\r\nContent-Length: 2\r\nContent-Length: 3\r\n\r\n{}".to_vec();
+        let response = exchange(&mut server, request);
+        assert!(response.starts_with("HTTP/1.1 400"), "{}", response);
+    }
+
+    #[test]
+    fn test_http_read_timeout() {
+        let (mut server, _) = test_server();
+        server.set_timeouts(Duration::from_millis(100), Duration::from_millis(100));
+        let local = server.local_addr().unwrap();
+
+        // A client that announces a body but never sends it is dropped
+        // once the read timeout expires.
+        let client = thread::spawn(move || {
+            let mut stream = TcpStream::connect(local).unwrap();
+            stream
+                .write_all(b"POST / HTTP/1.1\r\nContent-Length: 10\r\n\r\n{}")
+                .unwrap();
+            let mut response = String::new();
+            let _ = stream.read_to_string(&mut response);
+            let _ = stream.shutdown(Shutdown::Both);
+            response
+        });
+        server.serve_one().unwrap();
+        assert_eq!(client.join().unwrap(), "");
+
+        // The server keeps answering other clients afterwards.
+        let res = response_body(&post(&mut server, r#"{"jsonrpc":"2.0","id":7,"method":"eth_blockNumber"}"#));
+        assert_eq!(res["result"], "0x0");
+    }
+
+    #[test]
+    fn test_http_header_size_limit() {
+        let (mut server, _) = test_server();
+
+        // Headers filling the limit without ending are rejected
+        let mut request = b"POST / HTTP/1.1\r\nX-Padding: ".to_vec();
+        request.resize(MAX_HEADER_SIZE as usize, b'a');
+        let response = exchange(&mut server, request);
+        assert!(response.starts_with("HTTP/1.1 431"), "{}", response);
+
+        // The limit doesn't apply to the body
+        let padding = "a".repeat(MAX_HEADER_SIZE as usize / 2);
+        let body = format!(r#"{{"jsonrpc":"2.0","id":1,"method":"eth_blockNumber","params":[],"pad":"{}"}}"#, padding);
+        let request = format!("POST / HTTP/1.1\r\nX-Padding: {}\r\nContent-Length: {}\r\n\r\n{}", padding, body.len(), body);
+        let res = response_body(&exchange(&mut server, request.into_bytes()));
+        assert_eq!(res["result"], "0x0");
+    }
+
+    #[test]
+    fn test_http_request_deadline() {
+        let (mut server, _) = test_server();
+        server.set_timeouts(Duration::from_millis(300), Duration::from_millis(300));
+        let local = server.local_addr().unwrap();
+
+        // A client trickling the headers faster than the read timeout but
+        // never finishing is dropped once the request deadline expires.
+        let client = thread::spawn(move || {
+            let mut stream = TcpStream::connect(local).unwrap();
+            let _ = stream.write_all(b"POST / HTTP/1.1\r\n");
+            for _ in 0..20 {
+                if stream.write_all(b"X-Slow: 1\r\n").is_err() {
+                    break;
+                }
+                thread::sleep(Duration::from_millis(50));
+            }
+            let mut response = String::new();
+            let _ = stream.read_to_string(&mut response);
+            response
+        });
+        let start = Instant::now();
+        server.serve_one().unwrap();
+        let elapsed = start.elapsed();
+        assert!(elapsed < Duration::from_millis(900), "{:?}", elapsed);
+        assert_eq!(client.join().unwrap(), "");
+    }
+}
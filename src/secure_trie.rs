@@ -0,0 +1,88 @@
+// SecureTrie wraps a trie with key hashing. In a secure trie, all access
+// operations hash the key using keccak256. This prevents calling code from
+// creating long chains of nodes that increase the access time.
+//
+// Contrary to a regular trie, a SecureTrie can only be created with new or
+// from_root and must have an attached database.
+
+use std::collections::HashMap;
+
+use crate::crypto;
+use crate::trie::{Database, Leaf, Trie, TrieError};
+
+#[derive(Debug, Clone, Default)]
+pub struct SecureTrie {
+    trie: Trie,
+    // preimages of the hashed keys, kept so callers can map them back.
+    preimages: HashMap<common::Hash, Vec<u8>>,
+}
+
+impl SecureTrie {
+    pub fn new(db: Database) -> Self {
+        SecureTrie {
+            trie: Trie::new(db),
+            preimages: HashMap::new(),
+        }
+    }
+
+    pub fn from_root(root: common::Hash, db: Database) -> Result<Self, TrieError> {
+        Ok(SecureTrie {
+            trie: Trie::from_root(root, db)?,
+            preimages: HashMap::new(),
+        })
+    }
+
+    // get returns the value for key stored in the trie.
+    pub fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>, TrieError> {
+        self.trie.get(crypto::keccak256_hash(key).as_bytes())
+    }
+
+    // insert associates key with value in the trie. If value has length zero,
+    // any existing value is deleted from the trie.
+    pub fn insert(&mut self, key: &[u8], value: &[u8]) -> Result<(), TrieError> {
+        let hash = crypto::keccak256_hash(key);
+        self.trie.insert(hash.as_bytes(), value)?;
+        self.preimages.insert(hash, key.to_vec());
+        Ok(())
+    }
+
+    // delete removes any existing value for key from the trie.
+    pub fn delete(&mut self, key: &[u8]) -> Result<(), TrieError> {
+        let hash = crypto::keccak256_hash(key);
+        self.preimages.remove(&hash);
+        self.trie.delete(hash.as_bytes())
+    }
+
+    // prove constructs a merkle proof for key, see Trie::prove. The proof is
+    // for the hashed key, verify it with the keccak256 hash of key.
+    pub fn prove(&self, key: &[u8]) -> Result<Vec<Vec<u8>>, TrieError> {
+        self.trie.prove(crypto::keccak256_hash(key).as_bytes())
+    }
+
+    // get_key returns the preimage of a hashed key that was previously used
+    // to store a value.
+    pub fn get_key(&self, hash: &common::Hash) -> Option<&Vec<u8>> {
+        self.preimages.get(hash)
+    }
+
+    // leaves returns all hashed keys and their values, ordered by hashed key.
+    pub fn leaves(&self) -> Result<Vec<Leaf>, TrieError> {
+        self.trie.leaves()
+    }
+
+    pub fn root_hash(&self) -> common::Hash {
+        self.trie.root_hash()
+    }
+
+    pub fn commit(&mut self) -> common::Hash {
+        self.trie.commit()
+    }
+
+    pub fn trie(&self) -> &Trie {
+        &self.trie
+    }
+
+    pub fn into_database(self) -> Database {
+        self.trie.into_database()
+    }
+}
@@ -0,0 +1,401 @@
+// Package snapshot implements a journalled, dynamic state dump.
+//
+// The snapshot is a flat copy of the state trie leaves, keyed by the hashed
+// account address and the hashed storage slot, so reads don't have to walk
+// the trie. It consists of a disk layer, persisted in the key-value store and
+// matching one state root, and an in-memory diff layer per block on top of
+// it. Reads go through the diff layers from the requested root down to the
+// disk layer. When the diff stack grows beyond a limit, the bottom layers are
+// flattened into the disk layer.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::io;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, RwLock};
+
+use crate::ethdb::{Batch, KeyValueStore};
+use crate::memory_state_db::StateAccount;
+use crate::rawdb;
+use crate::rlp;
+use crate::secure_trie::SecureTrie;
+use crate::trie::{self, TrieError};
+
+// DIFF_LAYERS is the number of diff layers kept on top of the disk layer.
+pub const DIFF_LAYERS: usize = 128;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SnapshotError {
+    // Stale is returned from data accessors if the underlying snapshot layer
+    // had been invalidated due to the chain progressing forward far enough
+    // to not maintain the layer's original state.
+    Stale,
+    // MissingParent is returned by update if the parent root is unknown.
+    MissingParent(common::Hash),
+    // MissingSnapshot is returned by cap if the root is unknown.
+    MissingSnapshot(common::Hash),
+    Database(String),
+    Trie(TrieError),
+}
+
+impl From<io::Error> for SnapshotError {
+    fn from(err: io::Error) -> Self {
+        SnapshotError::Database(err.to_string())
+    }
+}
+
+impl From<TrieError> for SnapshotError {
+    fn from(err: TrieError) -> Self {
+        SnapshotError::Trie(err)
+    }
+}
+
+// DiskLayer is the snapshot persisted in the key-value store.
+pub struct DiskLayer {
+    db: Arc<dyn KeyValueStore>,
+    root: common::Hash,
+    // stale is set once the layer was replaced by flattening diffs into it.
+    stale: AtomicBool,
+}
+
+// DiffLayer holds the accounts and slots changed by one block. A None value
+// marks a deletion; destructs lists the accounts whose storage was wiped
+// before the changes were applied.
+pub struct DiffLayer {
+    parent: Arc<Layer>,
+    root: common::Hash,
+    destructs: HashSet<common::Hash>,
+    accounts: HashMap<common::Hash, Option<Vec<u8>>>,
+    storage: HashMap<common::Hash, HashMap<common::Hash, Option<Vec<u8>>>>,
+}
+
+pub enum Layer {
+    Disk(DiskLayer),
+    Diff(DiffLayer),
+}
+
+impl fmt::Debug for Layer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Layer::Disk(disk) => f.debug_struct("DiskLayer").field("root", &disk.root).finish(),
+            Layer::Diff(diff) => f
+                .debug_struct("DiffLayer")
+                .field("root", &diff.root)
+                .field("parent", &diff.parent.root())
+                .field("accounts", &diff.accounts.len())
+                .finish(),
+        }
+    }
+}
+
+impl Layer {
+    // root returns the root hash of the state this layer represents.
+    pub fn root(&self) -> common::Hash {
+        match self {
+            Layer::Disk(disk) => disk.root,
+            Layer::Diff(diff) => diff.root,
+        }
+    }
+
+    // parent returns the layer this one is stacked on, or None for the disk
+    // layer.
+    pub fn parent(&self) -> Option<Arc<Layer>> {
+        match self {
+            Layer::Disk(_) => None,
+            Layer::Diff(diff) => Some(diff.parent.clone()),
+        }
+    }
+
+    // account_rlp returns the RLP encoded account trie value of the account
+    // with the given address hash.
+    pub fn account_rlp(&self, account_hash: &common::Hash) -> Result<Option<Vec<u8>>, SnapshotError> {
+        let mut layer = self;
+        loop {
+            match layer {
+                Layer::Diff(diff) => {
+                    if let Some(entry) = diff.accounts.get(account_hash) {
+                        return Ok(entry.clone());
+                    }
+                    if diff.destructs.contains(account_hash) {
+                        return Ok(None);
+                    }
+                    layer = &diff.parent;
+                }
+                Layer::Disk(disk) => {
+                    if disk.stale.load(Ordering::SeqCst) {
+                        return Err(SnapshotError::Stale);
+                    }
+                    return Ok(rawdb::read_account_snapshot(disk.db.as_ref(), account_hash)?);
+                }
+            }
+        }
+    }
+
+    // account returns the account with the given address hash.
+    pub fn account(&self, account_hash: &common::Hash) -> Result<Option<StateAccount>, SnapshotError> {
+        match self.account_rlp(account_hash)? {
+            Some(blob) => rlp::decode(&blob)
+                .map(Some)
+                .map_err(|err| SnapshotError::Trie(TrieError::InvalidNode(err))),
+            None => Ok(None),
+        }
+    }
+
+    // storage returns the RLP encoded storage trie value of a slot.
+    pub fn storage(
+        &self,
+        account_hash: &common::Hash,
+        storage_hash: &common::Hash,
+    ) -> Result<Option<Vec<u8>>, SnapshotError> {
+        let mut layer = self;
+        loop {
+            match layer {
+                Layer::Diff(diff) => {
+                    if let Some(entry) = diff.storage.get(account_hash).and_then(|slots| slots.get(storage_hash)) {
+                        return Ok(entry.clone());
+                    }
+                    if diff.destructs.contains(account_hash) {
+                        return Ok(None);
+                    }
+                    layer = &diff.parent;
+                }
+                Layer::Disk(disk) => {
+                    if disk.stale.load(Ordering::SeqCst) {
+                        return Err(SnapshotError::Stale);
+                    }
+                    return Ok(rawdb::read_storage_snapshot(disk.db.as_ref(), account_hash, storage_hash)?);
+                }
+            }
+        }
+    }
+}
+
+// write_diff adds the changes of a diff layer to a disk batch.
+fn write_diff(db: &dyn KeyValueStore, diff: &DiffLayer, batch: &mut Batch) -> io::Result<()> {
+    for account_hash in diff.destructs.iter() {
+        rawdb::delete_account_snapshot(batch, account_hash);
+        let mut keys = rawdb::read_storage_snapshot_keys(db, account_hash)?;
+        // Older diffs flattened into the same batch may have staged slots
+        // that aren't on disk yet, clear them as well.
+        let prefix = rawdb::snapshot_storage_prefix(account_hash);
+        keys.extend(
+            batch
+                .ops()
+                .iter()
+                .filter(|(key, value)| value.is_some() && key.starts_with(&prefix))
+                .map(|(key, _)| key.clone())
+                .collect::<Vec<_>>(),
+        );
+        for key in keys {
+            batch.delete(&key);
+        }
+    }
+    for (account_hash, entry) in diff.accounts.iter() {
+        match entry {
+            Some(entry) => rawdb::write_account_snapshot(batch, account_hash, entry),
+            None => rawdb::delete_account_snapshot(batch, account_hash),
+        }
+    }
+    for (account_hash, slots) in diff.storage.iter() {
+        for (storage_hash, entry) in slots.iter() {
+            match entry {
+                Some(entry) => rawdb::write_storage_snapshot(batch, account_hash, storage_hash, entry),
+                None => rawdb::delete_storage_snapshot(batch, account_hash, storage_hash),
+            }
+        }
+    }
+    Ok(())
+}
+
+// generate rebuilds the flat snapshot on disk from the state trie at root.
+fn generate(db: &dyn KeyValueStore, triedb: &trie::Database, root: common::Hash) -> Result<(), SnapshotError> {
+    let mut batch = Batch::new();
+    for key in rawdb::read_snapshot_keys(db)? {
+        batch.delete(&key);
+    }
+    let state = SecureTrie::from_root(root, triedb.clone())?;
+    for (account_key, value) in state.leaves()? {
+        let account_hash = to_hash(&account_key);
+        let account: StateAccount = rlp::decode(&value).map_err(|err| SnapshotError::Trie(TrieError::InvalidNode(err)))?;
+        rawdb::write_account_snapshot(&mut batch, &account_hash, &value);
+
+        let storage = SecureTrie::from_root(account.root, triedb.clone())?;
+        for (storage_key, value) in storage.leaves()? {
+            rawdb::write_storage_snapshot(&mut batch, &account_hash, &to_hash(&storage_key), &value);
+        }
+    }
+    rawdb::write_snapshot_root(&mut batch, &root);
+    db.write_batch(&batch)?;
+    Ok(())
+}
+
+fn to_hash(key: &[u8]) -> common::Hash {
+    let mut hash = [0u8; 32];
+    hash.copy_from_slice(&key[..32]);
+    common::Hash(hash)
+}
+
+// Tree is an Ethereum state snapshot tree. It consists of one persistent base
+// layer backed by a key-value store, on top of which arbitrarily many in-memory
+// diff layers are stacked, one per block.
+pub struct Tree {
+    db: Arc<dyn KeyValueStore>,
+    layers: RwLock<HashMap<common::Hash, Arc<Layer>>>,
+}
+
+impl fmt::Debug for Tree {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Tree").field("layers", &self.layers.read().unwrap().len()).finish()
+    }
+}
+
+impl Tree {
+    // new opens the snapshot in db for the state at root, regenerating it from
+    // the tries in triedb if the persisted snapshot is missing or belongs to a
+    // different root.
+    pub fn new(db: Arc<dyn KeyValueStore>, triedb: &trie::Database, root: common::Hash) -> Result<Self, SnapshotError> {
+        if rawdb::read_snapshot_root(db.as_ref())? != Some(root) {
+            generate(db.as_ref(), triedb, root)?;
+        }
+        let base = Layer::Disk(DiskLayer {
+            db: db.clone(),
+            root,
+            stale: AtomicBool::new(false),
+        });
+        let mut layers = HashMap::new();
+        layers.insert(root, Arc::new(base));
+        Ok(Tree {
+            db,
+            layers: RwLock::new(layers),
+        })
+    }
+
+    // snapshot retrieves a snapshot belonging to the given state root, or None
+    // if no snapshot is maintained for that root.
+    pub fn snapshot(&self, root: &common::Hash) -> Option<Arc<Layer>> {
+        self.layers.read().unwrap().get(root).cloned()
+    }
+
+    // update adds a new diff layer on top of the snapshot of parent_root.
+    pub fn update(
+        &self,
+        root: common::Hash,
+        parent_root: common::Hash,
+        destructs: HashSet<common::Hash>,
+        accounts: HashMap<common::Hash, Option<Vec<u8>>>,
+        storage: HashMap<common::Hash, HashMap<common::Hash, Option<Vec<u8>>>>,
+    ) -> Result<(), SnapshotError> {
+        // Reject noop updates to avoid self-loops in the snapshot tree.
+        if root == parent_root {
+            return Ok(());
+        }
+        let parent = self
+            .snapshot(&parent_root)
+            .ok_or(SnapshotError::MissingParent(parent_root))?;
+        let layer = Layer::Diff(DiffLayer {
+            parent,
+            root,
+            destructs,
+            accounts,
+            storage,
+        });
+        self.layers.write().unwrap().insert(root, Arc::new(layer));
+        Ok(())
+    }
+
+    // cap traverses downwards the snapshot tree from a head block hash until
+    // the number of allowed diff layers are crossed. All layers beyond the
+    // permitted number are flattened downwards into the disk layer, and all
+    // layers not descending from the new disk layer are dropped.
+    pub fn cap(&self, root: &common::Hash, keep: usize) -> Result<(), SnapshotError> {
+        let head = self.snapshot(root).ok_or(SnapshotError::MissingSnapshot(*root))?;
+
+        // Collect the diff chain from the head down to the disk layer.
+        let mut diffs = Vec::new();
+        let mut layer = head;
+        let base = loop {
+            let parent = match layer.as_ref() {
+                Layer::Diff(diff) => diff.parent.clone(),
+                Layer::Disk(_) => break layer,
+            };
+            diffs.push(layer);
+            layer = parent;
+        };
+        if diffs.len() <= keep {
+            return Ok(());
+        }
+        let flatten = diffs.split_off(keep);
+
+        // Persist the flattened layers, oldest first, in one batch.
+        let mut batch = Batch::new();
+        for layer in flatten.iter().rev() {
+            if let Layer::Diff(diff) = layer.as_ref() {
+                write_diff(self.db.as_ref(), diff, &mut batch)?;
+            }
+        }
+        let new_root = flatten[0].root();
+        rawdb::write_snapshot_root(&mut batch, &new_root);
+        self.db.write_batch(&batch)?;
+        if let Layer::Disk(disk) = base.as_ref() {
+            disk.stale.store(true, Ordering::SeqCst);
+        }
+
+        // Rebuild the kept diff layers on top of the new disk layer.
+        let mut parent = Arc::new(Layer::Disk(DiskLayer {
+            db: self.db.clone(),
+            root: new_root,
+            stale: AtomicBool::new(false),
+        }));
+        let mut layers = HashMap::new();
+        layers.insert(new_root, parent.clone());
+        for layer in diffs.iter().rev() {
+            if let Layer::Diff(diff) = layer.as_ref() {
+                let rebuilt = Arc::new(Layer::Diff(DiffLayer {
+                    parent,
+                    root: diff.root,
+                    destructs: diff.destructs.clone(),
+                    accounts: diff.accounts.clone(),
+                    storage: diff.storage.clone(),
+                }));
+                layers.insert(diff.root, rebuilt.clone());
+                parent = rebuilt;
+            }
+        }
+        *self.layers.write().unwrap() = layers;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ethdb::MemoryDatabase;
+    use crate::trie::EMPTY_ROOT_HASH;
+
+    #[test]
+    fn test_cap_destruct_clears_staged_storage() {
+        let db: Arc<dyn KeyValueStore> = Arc::new(MemoryDatabase::new());
+        let snaps = Tree::new(db.clone(), &trie::Database::new(), EMPTY_ROOT_HASH).unwrap();
+
+        let account = common::Hash([0xaa; 32]);
+        let slot = common::Hash([0x01; 32]);
+        let root1 = common::Hash([0x11; 32]);
+        let root2 = common::Hash([0x22; 32]);
+
+        // The first layer creates the account with a slot, the second one
+        // destructs it again.
+        let mut storage = HashMap::new();
+        storage.insert(account, HashMap::from([(slot, Some(vec![0x2a]))]));
+        let accounts = HashMap::from([(account, Some(vec![0xc0]))]);
+        snaps.update(root1, EMPTY_ROOT_HASH, HashSet::new(), accounts, storage).unwrap();
+        let destructs = HashSet::from([account]);
+        snaps.update(root2, root1, destructs, HashMap::new(), HashMap::new()).unwrap();
+        assert_eq!(snaps.snapshot(&root2).unwrap().storage(&account, &slot).unwrap(), None);
+
+        // Flattening both layers in one batch must not resurrect the slot.
+        snaps.cap(&root2, 0).unwrap();
+        assert_eq!(rawdb::read_account_snapshot(db.as_ref(), &account).unwrap(), None);
+        assert_eq!(rawdb::read_storage_snapshot(db.as_ref(), &account, &slot).unwrap(), None);
+        assert_eq!(snaps.snapshot(&root2).unwrap().storage(&account, &slot).unwrap(), None);
+    }
+}
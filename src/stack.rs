@@ -1,76 +1,62 @@
-use std::sync::{Arc, Mutex};
 use uint256::Uint256;
 
-struct Stack {
+// Stack is an object for basic stack operations. Items popped to the stack are
+// expected to be changed and modified. stack does not take care of adding newly
+// initialised objects.
+//
+// The interpreter validates the stack height of every operation against the
+// jump table before executing it, so the operations below never see an
+// underflow or overflow.
+#[derive(Debug, Default)]
+pub struct Stack {
     data: Vec<Uint256>,
 }
 
 impl Stack {
-    fn new() -> Self {
-        Stack { data: Vec::new() }
+    pub fn new() -> Self {
+        Stack {
+            data: Vec::with_capacity(16),
+        }
     }
 
-    fn push(&mut self, d: Uint256) {
+    pub fn push(&mut self, d: Uint256) {
         self.data.push(d);
     }
 
-    fn pop(&mut self) -> Option<Uint256> {
-        self.data.pop()
-    }
-
-    fn clear(&mut self) {
-        self.data.clear();
+    pub fn pop(&mut self) -> Uint256 {
+        self.data.pop().expect("stack underflow")
     }
 
-    fn data(&self) -> &[Uint256] {
+    pub fn data(&self) -> &[Uint256] {
         &self.data
     }
 
-    fn len(&self) -> usize {
+    pub fn len(&self) -> usize {
         self.data.len()
     }
 
-    fn swap(&mut self, n: usize) {
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    // swap swaps the top of the stack with the n'th item below it.
+    pub fn swap(&mut self, n: usize) {
         let len = self.data.len();
-        self.data.swap(len - n, len - 1);
+        self.data.swap(len - n - 1, len - 1);
     }
 
-    fn dup(&mut self, n: usize) {
-        let value = self.data[len - n].clone();
+    // dup pushes a copy of the n'th item from the top of the stack.
+    pub fn dup(&mut self, n: usize) {
+        let value = self.data[self.data.len() - n].clone();
         self.push(value);
     }
 
-    fn peek(&self) -> Option<&Uint256> {
-        self.data.last()
+    pub fn peek(&mut self) -> &mut Uint256 {
+        self.data.last_mut().expect("stack underflow")
     }
 
-    fn back(&self, n: usize) -> Option<&Uint256> {
-        let len = self.data.len();
-        if n < len {
-            self.data.get(len - n - 1)
-        } else {
-            None
-        }
+    // back returns the n'th item in stack
+    pub fn back(&self, n: usize) -> &Uint256 {
+        &self.data[self.data.len() - n - 1]
     }
 }
-
-lazy_static::lazy_static! {
-    static ref STACK_POOL: Mutex<parking_lot::Mutex<Option<Stack>>> = Mutex::new(parking_lot::Mutex::new(None));
-}
-
-fn new_stack() -> Stack {
-    let mut stack_pool = STACK_POOL.lock().unwrap();
-    if let Some(stack) = stack_pool.take() {
-        stack
-    } else {
-        Stack {
-            data: Vec::with_capacity(16),
-        }
-    }
-}
-
-fn return_stack(mut stack: Stack) {
-    stack.data.clear();
-    let mut stack_pool = STACK_POOL.lock().unwrap();
-    *stack_pool = Some(stack);
-}
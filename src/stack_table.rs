@@ -1,24 +1,27 @@
-const STACK_LIMIT: i32 = 100; // Assuming a constant stack limit
+use crate::params;
 
-pub fn min_swap_stack(n: i32) -> i32 {
+const STACK_LIMIT: usize = params::STACK_LIMIT as usize;
+
+pub const fn min_swap_stack(n: usize) -> usize {
     min_stack(n, n)
 }
 
-pub fn max_swap_stack(n: i32) -> i32 {
+pub const fn max_swap_stack(n: usize) -> usize {
     max_stack(n, n)
 }
 
-pub fn min_dup_stack(n: i32) -> i32 {
+pub const fn min_dup_stack(n: usize) -> usize {
     min_stack(n, n + 1)
 }
-pub fn max_dup_stack(n: i32) -> i32 {
+
+pub const fn max_dup_stack(n: usize) -> usize {
     max_stack(n, n + 1)
 }
 
-pub fn max_stack(pop: i32, push: i32) -> i32 {
+pub const fn max_stack(pop: usize, push: usize) -> usize {
     STACK_LIMIT + pop - push
 }
 
-pub fn min_stack(pops: i32, push: i32) -> i32 {
+pub const fn min_stack(pops: usize, _push: usize) -> usize {
     pops
 }
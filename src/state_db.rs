@@ -0,0 +1,93 @@
+use crate::chain_config::Rules;
+use crate::types;
+use crate::trie::TrieError;
+use uint256::Uint256;
+
+// StateDB is an EVM database for full state querying.
+pub trait StateDB {
+    fn create_account(&mut self, addr: &common::Address);
+
+    fn sub_balance(&mut self, addr: &common::Address, amount: &Uint256);
+    fn add_balance(&mut self, addr: &common::Address, amount: &Uint256);
+    fn get_balance(&self, addr: &common::Address) -> Uint256;
+
+    fn get_nonce(&self, addr: &common::Address) -> u64;
+    fn set_nonce(&mut self, addr: &common::Address, nonce: u64);
+
+    fn get_code_hash(&self, addr: &common::Address) -> common::Hash;
+    fn get_code(&self, addr: &common::Address) -> Vec<u8>;
+    fn set_code(&mut self, addr: &common::Address, code: &[u8]);
+    fn get_code_size(&self, addr: &common::Address) -> usize;
+
+    fn add_refund(&mut self, gas: u64);
+    fn sub_refund(&mut self, gas: u64);
+    fn get_refund(&self) -> u64;
+
+    fn get_committed_state(&self, addr: &common::Address, key: &common::Hash) -> common::Hash;
+    fn get_state(&self, addr: &common::Address, key: &common::Hash) -> common::Hash;
+    fn set_state(&mut self, addr: &common::Address, key: common::Hash, value: common::Hash);
+
+    fn get_transient_state(&self, addr: &common::Address, key: &common::Hash) -> common::Hash;
+    fn set_transient_state(&mut self, addr: &common::Address, key: common::Hash, value: common::Hash);
+
+    fn suicide(&mut self, addr: &common::Address) -> bool;
+    fn has_suicided(&self, addr: &common::Address) -> bool;
+    // selfdestruct6780 only destructs the account if it was created in the
+    // current transaction (EIP-6780).
+    fn selfdestruct6780(&mut self, addr: &common::Address);
+
+    // exist reports whether the given account exists in state.
+    // Notably this should also return true for suicided accounts.
+    fn exist(&self, addr: &common::Address) -> bool;
+    // empty returns whether the given account is empty. Empty
+    // is defined according to EIP161 (balance = nonce = code = 0).
+    fn empty(&self, addr: &common::Address) -> bool;
+
+    fn address_in_access_list(&self, addr: &common::Address) -> bool;
+    fn slot_in_access_list(&self, addr: &common::Address, slot: &common::Hash) -> (bool, bool);
+    // add_address_to_access_list adds the given address to the access list. This operation is safe to perform
+    // even if the feature/fork is not active yet
+    fn add_address_to_access_list(&mut self, addr: &common::Address);
+    // add_slot_to_access_list adds the given (address,slot) to the access list. This operation is safe to perform
+    // even if the feature/fork is not active yet
+    fn add_slot_to_access_list(&mut self, addr: &common::Address, slot: &common::Hash);
+
+    fn revert_to_snapshot(&mut self, id: usize);
+    fn snapshot(&mut self) -> usize;
+
+    fn add_log(&mut self, log: types::Log);
+    fn add_preimage(&mut self, hash: &common::Hash, preimage: &[u8]);
+
+    // prepare handles the preparatory steps for executing a state transition
+    // with regards to both EIP-2929 and EIP-2930:
+    //
+    // - Add sender to access list (2929)
+    // - Add destination to access list (2929)
+    // - Add precompiles to access list (2929)
+    // - Add the contents of the optional tx access list (2930)
+    // - Add coinbase to access list (EIP-3651)
+    //
+    // It also resets the refund counter and the logs of the previous
+    // transaction.
+    fn prepare(
+        &mut self,
+        rules: &Rules,
+        sender: &common::Address,
+        coinbase: &common::Address,
+        dest: Option<&common::Address>,
+        precompiles: &[common::Address],
+        tx_accesses: &types::AccessList,
+    );
+    // logs returns the logs emitted by the current transaction.
+    fn logs(&self) -> &[types::Log];
+    // finalise finalises the state by removing the destructed objects and
+    // clears the journal. Changes made so far can no longer be reverted.
+    fn finalise(&mut self, delete_empty_objects: bool);
+    // intermediate_root computes the current root hash of the state. It is
+    // called in between transactions to get the root hash that goes into
+    // pre-Byzantium transaction receipts.
+    fn intermediate_root(&mut self, delete_empty_objects: bool) -> Result<common::Hash, TrieError>;
+    // commit writes the state to the underlying database and returns the new
+    // state root.
+    fn commit(&mut self, delete_empty_objects: bool) -> Result<common::Hash, TrieError>;
+}
@@ -0,0 +1,637 @@
+// Package state_processor executes the transactions of a block on top of its
+// parent state and validates the outcome against the block header.
+//
+// process_block is the entry point of block import: it runs every transaction
+// through the state transition, lets the consensus engine finalize the state
+// (e.g. with the block rewards), applies the withdrawals (from Shanghai), and
+// then checks the gas used, bloom, receipts root and state root the header
+// commits to. Any mismatch is reported as a ValidationError and the returned
+// state must be discarded.
+
+use std::fmt;
+
+use crate::block::{calc_uncle_hash, derive_sha, Block, Header};
+use crate::bloom::create_bloom;
+use crate::chain_config::{ChainConfig, Rules};
+use crate::consensus::Engine;
+use crate::contract::AccountRef;
+use crate::eip4844::calc_blob_fee;
+use crate::evm::{BlockContext, Config, GetHashFunc, TxContext, EVM};
+use crate::gas_pool::GasPool;
+use crate::log::Log;
+use crate::params;
+use crate::receipt::{self, Receipt, ReceiptError};
+use crate::state_db::StateDB;
+use crate::state_transition::{apply_transaction, StateTransitionError};
+use crate::trie::TrieError;
+use uint256::Uint256;
+
+// Block rewards in wei for successfully mining a block.
+pub const FRONTIER_BLOCK_REWARD: u64 = 5_000_000_000_000_000_000;
+pub const BYZANTIUM_BLOCK_REWARD: u64 = 3_000_000_000_000_000_000;
+pub const CONSTANTINOPLE_BLOCK_REWARD: u64 = 2_000_000_000_000_000_000;
+
+// GWEI is the number of wei in a gwei, the unit of withdrawal amounts.
+const GWEI: u64 = 1_000_000_000;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ValidationError {
+    // Transaction is returned if a transaction of the block can't be applied.
+    Transaction {
+        index: usize,
+        hash: common::Hash,
+        err: Box<StateTransitionError>,
+    },
+    // GasLimitExceeded is returned if the header claims to use more gas than
+    // its gas limit.
+    GasLimitExceeded {
+        used: u64,
+        limit: u64,
+    },
+    GasUsedMismatch {
+        header: u64,
+        computed: u64,
+    },
+    BloomMismatch,
+    ReceiptsRootMismatch {
+        header: common::Hash,
+        computed: common::Hash,
+    },
+    StateRootMismatch {
+        header: common::Hash,
+        computed: common::Hash,
+    },
+    TxRootMismatch {
+        header: common::Hash,
+        computed: common::Hash,
+    },
+    UncleHashMismatch {
+        header: common::Hash,
+        computed: common::Hash,
+    },
+    // UnclesNotAllowed is returned if a post-merge block carries uncles.
+    UnclesNotAllowed,
+    WithdrawalsRootMismatch {
+        header: Option<common::Hash>,
+        computed: Option<common::Hash>,
+    },
+    BlobGasUsedMismatch {
+        header: Option<u64>,
+        computed: u64,
+    },
+    Receipts(ReceiptError),
+    State(TrieError),
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ValidationError::Transaction { index, hash, err } => {
+                write!(f, "could not apply tx {} [{:?}]: {}", index, hash, err)
+            }
+            ValidationError::GasLimitExceeded { used, limit } => {
+                write!(f, "invalid gas used: have {}, gas limit {}", used, limit)
+            }
+            ValidationError::GasUsedMismatch { header, computed } => {
+                write!(
+                    f,
+                    "invalid gas used (remote: {} local: {})",
+                    header, computed
+                )
+            }
+            ValidationError::BloomMismatch => {
+                write!(f, "invalid bloom (remote and local bloom differ)")
+            }
+            ValidationError::ReceiptsRootMismatch { header, computed } => {
+                write!(
+                    f,
+                    "invalid receipt root hash (remote: {:?} local: {:?})",
+                    header, computed
+                )
+            }
+            ValidationError::StateRootMismatch { header, computed } => {
+                write!(
+                    f,
+                    "invalid merkle root (remote: {:?} local: {:?})",
+                    header, computed
+                )
+            }
+            ValidationError::TxRootMismatch { header, computed } => {
+                write!(
+                    f,
+                    "transaction root hash mismatch (header value {:?}, calculated {:?})",
+                    header, computed
+                )
+            }
+            ValidationError::UncleHashMismatch { header, computed } => {
+                write!(
+                    f,
+                    "uncle root hash mismatch (header value {:?}, calculated {:?})",
+                    header, computed
+                )
+            }
+            ValidationError::UnclesNotAllowed => write!(f, "uncles not allowed after the merge"),
+            ValidationError::WithdrawalsRootMismatch { header, computed } => {
+                write!(
+                    f,
+                    "withdrawals root hash mismatch (header value {:?}, calculated {:?})",
+                    header, computed
+                )
+            }
+            ValidationError::BlobGasUsedMismatch { header, computed } => {
+                write!(
+                    f,
+                    "blob gas used mismatch (header {:?}, calculated {})",
+                    header, computed
+                )
+            }
+            ValidationError::Receipts(err) => write!(f, "{}", err),
+            ValidationError::State(err) => write!(f, "state: {:?}", err),
+        }
+    }
+}
+
+impl std::error::Error for ValidationError {}
+
+impl From<TrieError> for ValidationError {
+    fn from(err: TrieError) -> Self {
+        ValidationError::State(err)
+    }
+}
+
+// ProcessResult is the outcome of a successfully processed and validated
+// block. state holds the post-state, which is not committed yet.
+pub struct ProcessResult {
+    pub receipts: Vec<Receipt>,
+    pub logs: Vec<Log>,
+    pub gas_used: u64,
+    pub root: common::Hash,
+    pub state: Box<dyn StateDB>,
+}
+
+// is_merged reports whether the header belongs to a proof-of-stake block,
+// whose difficulty is zero and whose mix digest carries the beacon chain
+// randomness.
+pub fn is_merged(header: &Header) -> bool {
+    header.difficulty.is_zero()
+}
+
+// new_block_context creates a new context for use in the EVM.
+pub fn new_block_context(header: &Header, get_hash: GetHashFunc) -> BlockContext {
+    BlockContext {
+        get_hash,
+        coinbase: header.coinbase,
+        gas_limit: header.gas_limit,
+        block_number: Uint256::from_uint64(header.number),
+        time: header.time,
+        difficulty: header.difficulty.clone(),
+        base_fee: header.base_fee.clone().unwrap_or_default(),
+        blob_base_fee: header
+            .excess_blob_gas
+            .map(calc_blob_fee)
+            .unwrap_or_default(),
+        random: if is_merged(header) {
+            Some(header.mix_digest)
+        } else {
+            None
+        },
+    }
+}
+
+// process_block processes the block by running its transactions on top of
+// parent_state, finalizes it with the consensus engine, applies the
+// withdrawals and validates the resulting gas used, bloom, receipts root and
+// state root against the block header. get_hash resolves ancestor hashes for
+// the BLOCKHASH opcode.
+pub fn process_block(
+    chain_config: &ChainConfig,
+    engine: &dyn Engine,
+    parent_state: Box<dyn StateDB>,
+    block: &Block,
+    get_hash: GetHashFunc,
+) -> Result<ProcessResult, ValidationError> {
+    let header = &block.header;
+    validate_body(chain_config, block)?;
+
+    let mut evm = EVM::new(
+        new_block_context(header, get_hash),
+        TxContext::default(),
+        parent_state,
+        chain_config.clone(),
+        Config::default(),
+    );
+    let rules = evm.chain_rules.clone();
+    if rules.is_cancun {
+        if let Some(beacon_root) = header.parent_beacon_root {
+            process_beacon_block_root(&mut evm, beacon_root);
+        }
+    }
+
+    // Iterate over and process the individual transactions
+    let mut gp = GasPool::new(header.gas_limit);
+    let mut used_gas = 0u64;
+    let mut receipts = Vec::with_capacity(block.transactions().len());
+    for (index, tx) in block.transactions().iter().enumerate() {
+        let receipt = apply_transaction(&mut evm, &mut gp, tx, &mut used_gas).map_err(|err| {
+            ValidationError::Transaction {
+                index,
+                hash: tx.hash(),
+                err: Box::new(err),
+            }
+        })?;
+        receipts.push(receipt);
+    }
+
+    // Finalize the block, applying any consensus engine specific extras
+    // (e.g. block rewards and withdrawals)
+    let mut state = evm.into_state_db();
+    engine.finalize(chain_config, header, state.as_mut(), block.uncles());
+    if let Some(withdrawals) = block.withdrawals() {
+        for withdrawal in withdrawals {
+            let amount = Uint256::from_uint64(withdrawal.amount)
+                .checked_mul(&Uint256::from_uint64(GWEI))
+                .expect("gwei amount fits 256 bits");
+            state.add_balance(&withdrawal.address, &amount);
+        }
+    }
+
+    let blob_gas_price = header.excess_blob_gas.map(calc_blob_fee);
+    receipt::derive_fields(
+        &mut receipts,
+        chain_config.chain_id,
+        block.hash(),
+        header.number,
+        header.base_fee.as_ref(),
+        blob_gas_price.as_ref(),
+        block.transactions(),
+    )
+    .map_err(ValidationError::Receipts)?;
+    let root = validate_state(&rules, state.as_mut(), header, &receipts, used_gas)?;
+
+    let logs = receipts
+        .iter()
+        .flat_map(|receipt| receipt.logs.iter().cloned())
+        .collect();
+    Ok(ProcessResult {
+        receipts,
+        logs,
+        gas_used: used_gas,
+        root,
+        state,
+    })
+}
+
+// validate_body validates the given block's uncles and verifies the block
+// header's transaction, uncle and withdrawals roots. The headers are assumed
+// to be already validated at this point.
+pub fn validate_body(chain_config: &ChainConfig, block: &Block) -> Result<(), ValidationError> {
+    let header = &block.header;
+    if header.gas_used > header.gas_limit {
+        return Err(ValidationError::GasLimitExceeded {
+            used: header.gas_used,
+            limit: header.gas_limit,
+        });
+    }
+    // Header validity is known at this point. Here we verify that uncles,
+    // transactions and withdrawals given in the block body match the header.
+    if is_merged(header) && !block.uncles().is_empty() {
+        return Err(ValidationError::UnclesNotAllowed);
+    }
+    let uncle_hash = calc_uncle_hash(block.uncles());
+    if uncle_hash != header.uncle_hash {
+        return Err(ValidationError::UncleHashMismatch {
+            header: header.uncle_hash,
+            computed: uncle_hash,
+        });
+    }
+    let tx_hash = derive_sha(block.transactions());
+    if tx_hash != header.tx_hash {
+        return Err(ValidationError::TxRootMismatch {
+            header: header.tx_hash,
+            computed: tx_hash,
+        });
+    }
+
+    // Withdrawals are present after the Shanghai fork.
+    let withdrawals_hash = block.withdrawals().map(derive_sha);
+    let shanghai = is_merged(header) && chain_config.is_shanghai(header.number, header.time);
+    if withdrawals_hash != header.withdrawals_hash || withdrawals_hash.is_some() != shanghai {
+        return Err(ValidationError::WithdrawalsRootMismatch {
+            header: header.withdrawals_hash,
+            computed: withdrawals_hash,
+        });
+    }
+
+    // Blob transactions may be present after the Cancun fork.
+    let blob_gas_used: u64 = block.transactions().iter().map(|tx| tx.blob_gas()).sum();
+    let cancun = is_merged(header) && chain_config.is_cancun(header.number, header.time);
+    let header_blob_gas = header.blob_gas_used;
+    if (cancun && header_blob_gas != Some(blob_gas_used))
+        || (!cancun && (header_blob_gas.is_some() || blob_gas_used > 0))
+    {
+        return Err(ValidationError::BlobGasUsedMismatch {
+            header: header_blob_gas,
+            computed: blob_gas_used,
+        });
+    }
+    Ok(())
+}
+
+// validate_state validates the various changes that happen after a state
+// transition, such as amount of used gas, the receipt roots and the state
+// root itself. It returns the computed state root.
+pub fn validate_state(
+    rules: &Rules,
+    state: &mut dyn StateDB,
+    header: &Header,
+    receipts: &[Receipt],
+    used_gas: u64,
+) -> Result<common::Hash, ValidationError> {
+    if header.gas_used != used_gas {
+        return Err(ValidationError::GasUsedMismatch {
+            header: header.gas_used,
+            computed: used_gas,
+        });
+    }
+    // Validate the received block's bloom with the one derived from the generated receipts.
+    // For valid blocks this should always validate to true.
+    if create_bloom(receipts) != header.bloom {
+        return Err(ValidationError::BloomMismatch);
+    }
+    // The receipt Trie's root (R = (Tr [[H1, R1], ... [Hn, Rn]]))
+    let receipt_hash = derive_sha(receipts);
+    if receipt_hash != header.receipt_hash {
+        return Err(ValidationError::ReceiptsRootMismatch {
+            header: header.receipt_hash,
+            computed: receipt_hash,
+        });
+    }
+    // Validate the state root against the received state root and throw
+    // an error if they don't match.
+    let root = state.intermediate_root(rules.is_eip158)?;
+    if root != header.root {
+        return Err(ValidationError::StateRootMismatch {
+            header: header.root,
+            computed: root,
+        });
+    }
+    Ok(root)
+}
+
+// block_reward returns the reward of the miner of a block under the given
+// rules.
+pub fn block_reward(rules: &Rules) -> u64 {
+    if rules.is_constantinople {
+        CONSTANTINOPLE_BLOCK_REWARD
+    } else if rules.is_byzantium {
+        BYZANTIUM_BLOCK_REWARD
+    } else {
+        FRONTIER_BLOCK_REWARD
+    }
+}
+
+// accumulate_rewards credits the coinbase of the given block with the mining
+// reward. The total reward consists of the static block reward and rewards for
+// included uncles. The coinbase of each uncle block is also rewarded.
+pub fn accumulate_rewards(
+    rules: &Rules,
+    state: &mut dyn StateDB,
+    header: &Header,
+    uncles: &[Header],
+) {
+    let block_reward = block_reward(rules);
+    // Accumulate the rewards for the miner and any included uncles
+    let mut reward = Uint256::from_uint64(block_reward);
+    for uncle in uncles {
+        // The rewards are multiples of 8, so (uncle + 8 - header) * reward / 8
+        // doesn't lose precision and fits 64 bits.
+        let uncle_reward = (uncle.number + 8).saturating_sub(header.number) * (block_reward / 8);
+        state.add_balance(&uncle.coinbase, &Uint256::from_uint64(uncle_reward));
+
+        reward = reward
+            .checked_add(&Uint256::from_uint64(block_reward / 32))
+            .expect("block reward fits 256 bits");
+    }
+    state.add_balance(&header.coinbase, &reward);
+}
+
+// process_beacon_block_root applies the EIP-4788 system call to the beacon
+// block root contract. This method is exported to be used in tests.
+pub fn process_beacon_block_root(evm: &mut EVM, beacon_root: common::Hash) {
+    evm.tx_context = TxContext {
+        origin: params::SYSTEM_ADDRESS,
+        gas_price: Uint256::default(),
+        blob_hashes: Vec::new(),
+    };
+    evm.state_db
+        .add_address_to_access_list(&params::BEACON_ROOTS_ADDRESS);
+    evm.call(
+        &AccountRef(params::SYSTEM_ADDRESS),
+        params::BEACON_ROOTS_ADDRESS,
+        beacon_root.as_bytes(),
+        30_000_000,
+        Uint256::default(),
+    );
+    evm.state_db.finalise(true);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use std::sync::Arc;
+
+    use crate::block::EMPTY_TX_ROOT;
+    use crate::blockchain::BlockChain;
+    use crate::bloom::Bloom;
+    use crate::consensus::ChainHeaderReader;
+    use crate::crypto::{self, PrivateKey};
+    use crate::ethdb::MemoryDatabase;
+    use crate::genesis::{Genesis, GenesisAccount};
+    use crate::miner;
+    use crate::pow::ProofOfWork;
+    use crate::pruner::GcMode;
+    use crate::transaction::{self, LegacyTx, Transaction, TxData};
+
+    // LOGGER emits an empty LOG0 when called.
+    const LOGGER: common::Address = common::Address([0xdd; 20]);
+
+    fn test_key() -> PrivateKey {
+        crypto::to_ecdsa(&[1; 32]).unwrap()
+    }
+
+    fn test_chain() -> BlockChain {
+        let mut alloc = HashMap::new();
+        alloc.insert(
+            test_key().address(),
+            GenesisAccount {
+                balance: Uint256::from_uint64(1_000_000_000),
+                ..Default::default()
+            },
+        );
+        alloc.insert(
+            LOGGER,
+            GenesisAccount {
+                code: vec![0x60, 0x00, 0x60, 0x00, 0xa0, 0x00],
+                ..Default::default()
+            },
+        );
+        let genesis = Genesis {
+            config: ChainConfig {
+                chain_id: 1337,
+                ..Default::default()
+            },
+            alloc,
+            ..Default::default()
+        };
+        BlockChain::new(Arc::new(MemoryDatabase::new()), &genesis, Box::new(ProofOfWork::fake()), GcMode::Archive).unwrap()
+    }
+
+    fn call(nonce: u64, to: common::Address) -> Transaction {
+        let tx = Transaction::new(TxData::Legacy(LegacyTx {
+            nonce,
+            gas_price: Uint256::from_uint64(1),
+            gas: 100_000,
+            to: Some(to),
+            ..Default::default()
+        }));
+        transaction::sign_tx(&tx, &Uint256::from_uint64(1337), &test_key()).unwrap()
+    }
+
+    // make_block builds a block on top of the genesis with two transactions,
+    // one of which emits a log.
+    fn make_block(chain: &BlockChain) -> Block {
+        let parent = chain.current_header().clone();
+        let header = miner::prepare_header(chain.config(), &miner::Config::default(), &parent, parent.time + 10);
+        let state = chain.state_at(&parent.root).unwrap();
+        let txs = vec![call(0, LOGGER), call(1, common::Address([0x11; 20]))];
+        miner::build_block(chain, &ProofOfWork::fake(), header, state, txs, chain.get_hash_fn(&parent))
+            .unwrap()
+            .block
+    }
+
+    fn process(chain: &BlockChain, block: &Block) -> Result<ProcessResult, ValidationError> {
+        let parent = chain.current_header().clone();
+        process_block(
+            chain.config(),
+            &ProofOfWork::fake(),
+            chain.state_at(&parent.root).unwrap(),
+            block,
+            chain.get_hash_fn(&parent),
+        )
+    }
+
+    #[test]
+    fn test_process_block() {
+        let chain = test_chain();
+        let block = make_block(&chain);
+        let result = process(&chain, &block).unwrap();
+        assert_eq!(result.root, block.header.root);
+        assert_eq!(result.gas_used, block.header.gas_used);
+        assert_eq!(result.receipts.len(), 2);
+        assert_eq!(result.logs.len(), 1);
+        assert_eq!(result.logs[0].address, LOGGER);
+    }
+
+    #[test]
+    fn test_validate_state() {
+        let chain = test_chain();
+        let block = make_block(&chain);
+
+        let mut bad = block.clone();
+        bad.header.gas_used += 1;
+        assert_eq!(
+            process(&chain, &bad).err(),
+            Some(ValidationError::GasUsedMismatch {
+                header: block.header.gas_used + 1,
+                computed: block.header.gas_used,
+            })
+        );
+
+        let mut bad = block.clone();
+        bad.header.bloom = Bloom::default();
+        assert_eq!(process(&chain, &bad).err(), Some(ValidationError::BloomMismatch));
+
+        let mut bad = block.clone();
+        bad.header.receipt_hash = common::Hash([0x01; 32]);
+        assert_eq!(
+            process(&chain, &bad).err(),
+            Some(ValidationError::ReceiptsRootMismatch {
+                header: common::Hash([0x01; 32]),
+                computed: block.header.receipt_hash,
+            })
+        );
+
+        let mut bad = block.clone();
+        bad.header.root = common::Hash([0x02; 32]);
+        assert_eq!(
+            process(&chain, &bad).err(),
+            Some(ValidationError::StateRootMismatch {
+                header: common::Hash([0x02; 32]),
+                computed: block.header.root,
+            })
+        );
+    }
+
+    #[test]
+    fn test_validate_body() {
+        let chain = test_chain();
+        let block = make_block(&chain);
+        let config = chain.config();
+        assert_eq!(validate_body(config, &block), Ok(()));
+
+        let mut bad = block.clone();
+        bad.header.gas_used = bad.header.gas_limit + 1;
+        assert_eq!(
+            validate_body(config, &bad),
+            Err(ValidationError::GasLimitExceeded {
+                used: bad.header.gas_limit + 1,
+                limit: bad.header.gas_limit,
+            })
+        );
+
+        // A transaction dropped from the body no longer matches the root
+        let mut bad = block.clone();
+        bad.body.transactions.pop();
+        assert_eq!(
+            validate_body(config, &bad),
+            Err(ValidationError::TxRootMismatch {
+                header: block.header.tx_hash,
+                computed: derive_sha(bad.transactions()),
+            })
+        );
+
+        let mut bad = block.clone();
+        bad.header.tx_hash = EMPTY_TX_ROOT;
+        assert_eq!(
+            validate_body(config, &bad),
+            Err(ValidationError::TxRootMismatch {
+                header: EMPTY_TX_ROOT,
+                computed: block.header.tx_hash,
+            })
+        );
+
+        let mut bad = block.clone();
+        bad.body.uncles.push(chain.current_header().clone());
+        assert!(matches!(
+            validate_body(config, &bad),
+            Err(ValidationError::UncleHashMismatch { .. })
+        ));
+
+        // Withdrawals are only allowed from Shanghai
+        let mut bad = block.clone();
+        bad.body.withdrawals = Some(Vec::new());
+        assert_eq!(
+            validate_body(config, &bad),
+            Err(ValidationError::WithdrawalsRootMismatch {
+                header: None,
+                computed: Some(EMPTY_TX_ROOT),
+            })
+        );
+
+        // Body errors are reported before any transaction is executed
+        let mut bad = block;
+        bad.body.transactions.reverse();
+        assert!(matches!(process(&chain, &bad), Err(ValidationError::TxRootMismatch { .. })));
+    }
+}
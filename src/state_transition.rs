@@ -0,0 +1,1113 @@
+use std::fmt;
+
+use crate::chain_config::Rules;
+use crate::bloom::logs_bloom;
+use crate::contract::AccountRef;
+use crate::crypto;
+use crate::errors::Error;
+use crate::evm::{can_transfer, TxContext, EMPTY_CODE_HASH, EVM};
+use crate::gas_pool::GasPool;
+use crate::hexutil;
+use crate::kzg4844;
+use crate::params;
+use crate::precompiles;
+use crate::receipt::{Receipt, RECEIPT_STATUS_FAILED, RECEIPT_STATUS_SUCCESSFUL};
+use crate::transaction::{self, Transaction, TxError};
+use crate::trie::TrieError;
+use crate::types;
+use uint256::Uint256;
+
+// StateTransitionError lists the reasons for which a transaction can't be
+// applied to the state. Contrary to the EVM errors in ExecutionResult, any of
+// these makes the block containing the transaction invalid.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StateTransitionError {
+    // InvalidSender is returned if the sender can't be recovered from the
+    // transaction signature.
+    InvalidSender(TxError),
+    // NonceTooLow is returned if the nonce of a transaction is lower than the
+    // one present in the local chain.
+    NonceTooLow {
+        address: common::Address,
+        tx: u64,
+        state: u64,
+    },
+    // NonceTooHigh is returned if the nonce of a transaction is higher than the
+    // next one expected based on the local chain.
+    NonceTooHigh {
+        address: common::Address,
+        tx: u64,
+        state: u64,
+    },
+    // NonceMax is returned if the nonce of a transaction sender account has
+    // maximum allowed value and would become invalid if incremented.
+    NonceMax {
+        address: common::Address,
+        nonce: u64,
+    },
+    // SenderNoEOA is returned if the sender of a transaction is a contract.
+    SenderNoEOA {
+        address: common::Address,
+        code_hash: common::Hash,
+    },
+    // GasLimitReached is returned by the gas pool if the amount of gas required
+    // by a transaction is higher than what's left in the block.
+    GasLimitReached {
+        have: u64,
+        want: u64,
+    },
+    // InsufficientFundsForTransfer is returned if the transaction sender doesn't
+    // have enough funds for transfer(topmost call only).
+    InsufficientFundsForTransfer(common::Address),
+    // InsufficientFunds is returned if the total cost of executing a transaction
+    // is higher than the balance of the user's account.
+    InsufficientFunds {
+        address: common::Address,
+        have: Uint256,
+        want: Uint256,
+    },
+    // BalanceOverflow is returned if the cost of a transaction exceeds 256 bits.
+    BalanceOverflow(common::Address),
+    // GasUintOverflow is returned when calculating gas usage.
+    GasUintOverflow,
+    // IntrinsicGas is returned if the transaction is specified to use less gas
+    // than required to start the invocation.
+    IntrinsicGas {
+        have: u64,
+        want: u64,
+    },
+    // MaxInitCodeSizeExceeded is returned if creation transaction provides the
+    // init code bigger than init code size limit.
+    MaxInitCodeSizeExceeded {
+        have: usize,
+        max: u64,
+    },
+    // TipAboveFeeCap is a sanity error to ensure no one is able to specify a
+    // transaction with a tip higher than the total fee cap.
+    TipAboveFeeCap {
+        address: common::Address,
+        tip: Uint256,
+        fee_cap: Uint256,
+    },
+    // FeeCapTooLow is returned if the transaction fee cap is less than the
+    // base fee of the block.
+    FeeCapTooLow {
+        address: common::Address,
+        fee_cap: Uint256,
+        base_fee: Uint256,
+    },
+    // MissingBlobHashes is returned if a blob transaction has no blobs.
+    MissingBlobHashes(common::Address),
+    // InvalidBlobHashVersion is returned if a blob hash doesn't carry the KZG
+    // version byte.
+    InvalidBlobHashVersion {
+        index: usize,
+        hash: common::Hash,
+    },
+    // BlobFeeCapTooLow is returned if the transaction fee cap is less than the
+    // blob gas fee of the block.
+    BlobFeeCapTooLow {
+        address: common::Address,
+        blob_fee_cap: Uint256,
+        blob_base_fee: Uint256,
+    },
+    // State is returned if the state could not be read or hashed.
+    State(TrieError),
+}
+
+impl fmt::Display for StateTransitionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StateTransitionError::InvalidSender(err) => write!(f, "invalid sender: {:?}", err),
+            StateTransitionError::NonceTooLow { address, tx, state } => write!(
+                f,
+                "nonce too low: address {}, tx: {} state: {}",
+                hexutil::encode(&address.0),
+                tx,
+                state
+            ),
+            StateTransitionError::NonceTooHigh { address, tx, state } => write!(
+                f,
+                "nonce too high: address {}, tx: {} state: {}",
+                hexutil::encode(&address.0),
+                tx,
+                state
+            ),
+            StateTransitionError::NonceMax { address, nonce } => write!(
+                f,
+                "nonce has max value: address {}, nonce: {}",
+                hexutil::encode(&address.0),
+                nonce
+            ),
+            StateTransitionError::SenderNoEOA { address, code_hash } => write!(
+                f,
+                "sender not an eoa: address {}, codehash: {}",
+                hexutil::encode(&address.0),
+                hexutil::encode(code_hash.as_bytes())
+            ),
+            StateTransitionError::GasLimitReached { have, want } => {
+                write!(f, "gas limit reached: have {}, want {}", have, want)
+            }
+            StateTransitionError::InsufficientFundsForTransfer(address) => write!(
+                f,
+                "insufficient funds for transfer: address {}",
+                hexutil::encode(&address.0)
+            ),
+            StateTransitionError::InsufficientFunds { address, have, want } => write!(
+                f,
+                "insufficient funds for gas * price + value: address {} have {} want {}",
+                hexutil::encode(&address.0),
+                hexutil::encode_big(have),
+                hexutil::encode_big(want)
+            ),
+            StateTransitionError::BalanceOverflow(address) => write!(
+                f,
+                "insufficient funds for gas * price + value: address {} required balance exceeds 256 bits",
+                hexutil::encode(&address.0)
+            ),
+            StateTransitionError::GasUintOverflow => write!(f, "gas uint64 overflow"),
+            StateTransitionError::IntrinsicGas { have, want } => {
+                write!(f, "intrinsic gas too low: have {}, want {}", have, want)
+            }
+            StateTransitionError::MaxInitCodeSizeExceeded { have, max } => {
+                write!(f, "max initcode size exceeded: code size {} limit {}", have, max)
+            }
+            StateTransitionError::TipAboveFeeCap { address, tip, fee_cap } => write!(
+                f,
+                "max priority fee per gas higher than max fee per gas: address {}, maxPriorityFeePerGas: {}, maxFeePerGas: {}",
+                hexutil::encode(&address.0),
+                hexutil::encode_big(tip),
+                hexutil::encode_big(fee_cap)
+            ),
+            StateTransitionError::FeeCapTooLow { address, fee_cap, base_fee } => write!(
+                f,
+                "max fee per gas less than block base fee: address {}, maxFeePerGas: {}, baseFee: {}",
+                hexutil::encode(&address.0),
+                hexutil::encode_big(fee_cap),
+                hexutil::encode_big(base_fee)
+            ),
+            StateTransitionError::MissingBlobHashes(address) => write!(
+                f,
+                "blob transaction missing blob hashes: address {}",
+                hexutil::encode(&address.0)
+            ),
+            StateTransitionError::InvalidBlobHashVersion { index, hash } => write!(
+                f,
+                "blob {} has invalid hash version: {}",
+                index,
+                hexutil::encode(hash.as_bytes())
+            ),
+            StateTransitionError::BlobFeeCapTooLow { address, blob_fee_cap, blob_base_fee } => write!(
+                f,
+                "max fee per blob gas less than block blob gas fee: address {} blobGasFeeCap: {}, blobBaseFee: {}",
+                hexutil::encode(&address.0),
+                hexutil::encode_big(blob_fee_cap),
+                hexutil::encode_big(blob_base_fee)
+            ),
+            StateTransitionError::State(err) => write!(f, "state: {:?}", err),
+        }
+    }
+}
+
+impl std::error::Error for StateTransitionError {}
+
+impl From<TrieError> for StateTransitionError {
+    fn from(err: TrieError) -> Self {
+        StateTransitionError::State(err)
+    }
+}
+
+// to_word_size returns the ceiled word size required for init code payment calculation.
+fn to_word_size(size: u64) -> u64 {
+    if size > u64::MAX - 31 {
+        return u64::MAX / 32 + 1;
+    }
+    size.div_ceil(32)
+}
+
+// intrinsic_gas computes the 'intrinsic gas' for a message with the given data.
+pub fn intrinsic_gas(
+    data: &[u8],
+    access_list: &types::AccessList,
+    is_contract_creation: bool,
+    is_homestead: bool,
+    is_eip2028: bool,
+    is_eip3860: bool,
+) -> Result<u64, Error> {
+    // Set the starting gas for the raw transaction
+    let mut gas = if is_contract_creation && is_homestead {
+        params::TX_GAS_CONTRACT_CREATION
+    } else {
+        params::TX_GAS
+    };
+    let data_len = data.len() as u64;
+    // Bump the required gas by the amount of transactional data
+    if data_len > 0 {
+        // Zero and non-zero bytes are priced differently
+        let nz = data.iter().filter(|b| **b != 0).count() as u64;
+        // Make sure we don't exceed u64 for all data combinations
+        let non_zero_gas = if is_eip2028 {
+            params::TX_DATA_NON_ZERO_GAS_EIP2028
+        } else {
+            params::TX_DATA_NON_ZERO_GAS_FRONTIER
+        };
+        if (u64::MAX - gas) / non_zero_gas < nz {
+            return Err(Error::GasUintOverflow);
+        }
+        gas += nz * non_zero_gas;
+
+        let z = data_len - nz;
+        if (u64::MAX - gas) / params::TX_DATA_ZERO_GAS < z {
+            return Err(Error::GasUintOverflow);
+        }
+        gas += z * params::TX_DATA_ZERO_GAS;
+
+        if is_contract_creation && is_eip3860 {
+            let len_words = to_word_size(data_len);
+            if (u64::MAX - gas) / params::INIT_CODE_WORD_GAS < len_words {
+                return Err(Error::GasUintOverflow);
+            }
+            gas += len_words * params::INIT_CODE_WORD_GAS;
+        }
+    }
+    gas += access_list.len() as u64 * params::TX_ACCESS_LIST_ADDRESS_GAS;
+    gas += access_list.storage_keys() as u64 * params::TX_ACCESS_LIST_STORAGE_KEY_GAS;
+    Ok(gas)
+}
+
+// check_init_code_size rejects creation transactions whose initcode exceeds
+// params::MAX_INIT_CODE_SIZE once Shanghai is active (EIP-3860).
+pub fn check_init_code_size(
+    rules: &Rules,
+    is_contract_creation: bool,
+    data: &[u8],
+) -> Result<(), Error> {
+    if rules.is_shanghai && is_contract_creation && data.len() as u64 > params::MAX_INIT_CODE_SIZE {
+        return Err(Error::MaxInitCodeSizeExceeded);
+    }
+    Ok(())
+}
+
+// ExecutionResult includes all output after executing given evm
+// message no matter the execution itself is successful or not.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExecutionResult {
+    pub used_gas: u64,        // Total used gas, not including the refunded gas
+    pub refunded_gas: u64,    // Total gas refunded after execution
+    pub err: Option<Error>,   // Any error encountered during the execution(listed in errors.rs)
+    pub return_data: Vec<u8>, // Returned data from evm(function result or data supplied with revert opcode)
+}
+
+impl ExecutionResult {
+    // failed returns the indicator whether the execution is successful or not
+    pub fn failed(&self) -> bool {
+        self.err.is_some()
+    }
+
+    // revert returns the concrete revert reason if the execution is aborted by `REVERT`
+    // opcode. Note the reason can be empty if the contract doesn't provide one.
+    pub fn revert(&self) -> Option<&[u8]> {
+        match self.err {
+            Some(Error::ExecutionReverted) => Some(&self.return_data),
+            _ => None,
+        }
+    }
+}
+
+// Message represents a message sent to a contract.
+#[derive(Debug, Clone, Default)]
+pub struct Message {
+    pub to: Option<common::Address>,
+    pub from: common::Address,
+    pub nonce: u64,
+    pub value: Uint256,
+    pub gas_limit: u64,
+    pub gas_price: Uint256,
+    pub gas_fee_cap: Uint256,
+    pub gas_tip_cap: Uint256,
+    pub data: Vec<u8>,
+    pub access_list: types::AccessList,
+    pub blob_gas_fee_cap: Option<Uint256>,
+    pub blob_hashes: Vec<common::Hash>,
+
+    // When skip_account_checks is true, the message nonce is not checked against the
+    // account nonce in state. It also disables checking that the sender is an EOA.
+    // This field will be set to true for operations like RPC eth_call.
+    pub skip_account_checks: bool,
+}
+
+impl Message {
+    // blob_gas returns the blob gas used by the message.
+    pub fn blob_gas(&self) -> u64 {
+        params::BLOB_TX_BLOB_GAS_PER_BLOB * self.blob_hashes.len() as u64
+    }
+}
+
+// transaction_to_message converts a transaction into a Message, recovering
+// the sender with the given chain id. If base_fee is set the gas price is the
+// effective gas price paid by the transaction.
+pub fn transaction_to_message(
+    tx: &Transaction,
+    chain_id: u64,
+    base_fee: Option<&Uint256>,
+) -> Result<Message, StateTransitionError> {
+    let from = transaction::sender(tx, &Uint256::from_uint64(chain_id))
+        .map_err(StateTransitionError::InvalidSender)?;
+    // If base_fee provided, set gas_price to effective_gas_price.
+    let gas_price = tx.effective_gas_price(base_fee);
+    Ok(Message {
+        to: tx.to(),
+        from,
+        nonce: tx.nonce(),
+        value: tx.value(),
+        gas_limit: tx.gas(),
+        gas_price,
+        gas_fee_cap: tx.gas_fee_cap(),
+        gas_tip_cap: tx.gas_tip_cap(),
+        data: tx.data().to_vec(),
+        access_list: tx.access_list(),
+        blob_gas_fee_cap: tx.blob_gas_fee_cap(),
+        blob_hashes: tx.blob_hashes().to_vec(),
+        skip_account_checks: false,
+    })
+}
+
+// apply_transaction attempts to apply a transaction to the state held by the
+// EVM, whose block context describes the block the transaction is included
+// in. used_gas is the gas used by the preceding transactions of the block.
+// It returns the receipt for the transaction, or an error if the transaction
+// can't be included, which makes the block invalid.
+pub fn apply_transaction(
+    evm: &mut EVM,
+    gp: &mut GasPool,
+    tx: &Transaction,
+    used_gas: &mut u64,
+) -> Result<Receipt, StateTransitionError> {
+    let base_fee = evm.context.base_fee.clone();
+    let msg = transaction_to_message(tx, evm.chain_config.chain_id, Some(&base_fee))?;
+
+    // Create a new context to be used in the EVM environment
+    evm.tx_context = TxContext {
+        origin: msg.from,
+        gas_price: msg.gas_price.clone(),
+        blob_hashes: msg.blob_hashes.clone(),
+    };
+    let result = apply_message(evm, &msg, gp)?;
+
+    // Update the state with pending changes.
+    let mut post_state = Vec::new();
+    if evm.chain_rules.is_byzantium {
+        evm.state_db.finalise(true);
+    } else {
+        let delete_empty = evm.chain_rules.is_eip158;
+        post_state = evm
+            .state_db
+            .intermediate_root(delete_empty)?
+            .as_bytes()
+            .to_vec();
+    }
+    *used_gas += result.used_gas;
+
+    // Create a new receipt for the transaction, storing the intermediate root
+    // and gas used by the tx.
+    let mut receipt = Receipt {
+        tx_type: tx.tx_type(),
+        post_state,
+        status: if result.failed() {
+            RECEIPT_STATUS_FAILED
+        } else {
+            RECEIPT_STATUS_SUCCESSFUL
+        },
+        cumulative_gas_used: *used_gas,
+        tx_hash: tx.hash(),
+        gas_used: result.used_gas,
+        effective_gas_price: msg.gas_price.clone(),
+        ..Default::default()
+    };
+    if tx.tx_type() == transaction::BLOB_TX_TYPE {
+        receipt.blob_gas_used = tx.blob_gas();
+        receipt.blob_gas_price = Some(evm.context.blob_base_fee.clone());
+    }
+    // If the transaction created a contract, store the creation address in the receipt.
+    if msg.to.is_none() {
+        receipt.contract_address = Some(crypto::create_address(&msg.from, tx.nonce()));
+    }
+
+    // Set the receipt logs and create the bloom filter. The block hash and
+    // the positions of the transaction and logs within the block are filled
+    // in by receipt::derive_fields once the block is known.
+    receipt.logs = evm.state_db.logs().to_vec();
+    for log in receipt.logs.iter_mut() {
+        log.tx_hash = receipt.tx_hash;
+    }
+    receipt.bloom = logs_bloom(&receipt.logs);
+    receipt.block_number = evm.context.block_number().to_u64();
+    Ok(receipt)
+}
+
+// apply_message computes the new state by applying the given message
+// against the old state within the environment.
+//
+// apply_message returns the bytes returned by any EVM execution (if it took place),
+// the gas used (which includes gas refunds) and an error if it failed. An error always
+// indicates a core error meaning that the message would always fail for that particular
+// state and would never be accepted within a block.
+pub fn apply_message(
+    evm: &mut EVM,
+    msg: &Message,
+    gp: &mut GasPool,
+) -> Result<ExecutionResult, StateTransitionError> {
+    StateTransition {
+        gp,
+        evm,
+        msg,
+        gas_remaining: 0,
+        initial_gas: 0,
+    }
+    .transition_db()
+}
+
+// StateTransition represents a state transition.
+//
+// == The State Transitioning Model
+//
+// A state transition is a change made when a transaction is applied to the current world
+// state. The state transitioning model does all the necessary work to work out a valid new
+// state root.
+//
+//  1. Nonce handling
+//  2. Pre pay gas
+//  3. Create a new state object if the recipient is nil
+//  4. Value transfer
+//
+// == If contract creation ==
+//
+//  4a. Attempt to run transaction data
+//  4b. If valid, use result as code for the new state object
+//
+// == end ==
+//
+//  5. Run Script section
+//  6. Derive new state root
+struct StateTransition<'a> {
+    gp: &'a mut GasPool,
+    evm: &'a mut EVM,
+    msg: &'a Message,
+    gas_remaining: u64,
+    initial_gas: u64,
+}
+
+impl StateTransition<'_> {
+    fn buy_gas(&mut self) -> Result<(), StateTransitionError> {
+        let msg = self.msg;
+        let overflow = || StateTransitionError::BalanceOverflow(msg.from);
+        let gas = Uint256::from_uint64(msg.gas_limit);
+        let mut mgval = gas.checked_mul(&msg.gas_price).ok_or_else(overflow)?;
+        let mut balance_check = gas
+            .checked_mul(&msg.gas_fee_cap)
+            .and_then(|cost| cost.checked_add(&msg.value))
+            .ok_or_else(overflow)?;
+        if self.evm.chain_rules.is_cancun {
+            if let Some(blob_fee_cap) = &msg.blob_gas_fee_cap {
+                let blob_gas = Uint256::from_uint64(msg.blob_gas());
+                // Check that the user has enough funds to cover blob_gas * blob_fee_cap
+                balance_check = blob_gas
+                    .checked_mul(blob_fee_cap)
+                    .and_then(|cost| balance_check.checked_add(&cost))
+                    .ok_or_else(overflow)?;
+                // Pay for blob_gas * blob_base_fee
+                mgval = blob_gas
+                    .checked_mul(&self.evm.context.blob_base_fee)
+                    .and_then(|cost| mgval.checked_add(&cost))
+                    .ok_or_else(overflow)?;
+            }
+        }
+        let have = self.evm.state_db.get_balance(&msg.from);
+        if have < balance_check {
+            return Err(StateTransitionError::InsufficientFunds {
+                address: msg.from,
+                have,
+                want: balance_check,
+            });
+        }
+        let available = self.gp.gas();
+        self.gp
+            .sub_gas(msg.gas_limit)
+            .map_err(|_| StateTransitionError::GasLimitReached {
+                have: available,
+                want: msg.gas_limit,
+            })?;
+
+        self.gas_remaining = msg.gas_limit;
+        self.initial_gas = msg.gas_limit;
+        self.evm.state_db.sub_balance(&msg.from, &mgval);
+        Ok(())
+    }
+
+    fn pre_check(&mut self) -> Result<(), StateTransitionError> {
+        let msg = self.msg;
+        if !msg.skip_account_checks {
+            // Make sure this transaction's nonce is correct.
+            let state_nonce = self.evm.state_db.get_nonce(&msg.from);
+            if state_nonce < msg.nonce {
+                return Err(StateTransitionError::NonceTooHigh {
+                    address: msg.from,
+                    tx: msg.nonce,
+                    state: state_nonce,
+                });
+            } else if state_nonce > msg.nonce {
+                return Err(StateTransitionError::NonceTooLow {
+                    address: msg.from,
+                    tx: msg.nonce,
+                    state: state_nonce,
+                });
+            } else if state_nonce.checked_add(1).is_none() {
+                return Err(StateTransitionError::NonceMax {
+                    address: msg.from,
+                    nonce: state_nonce,
+                });
+            }
+            // Make sure the sender is an EOA
+            let code_hash = self.evm.state_db.get_code_hash(&msg.from);
+            if code_hash != common::Hash::default() && code_hash != EMPTY_CODE_HASH {
+                return Err(StateTransitionError::SenderNoEOA {
+                    address: msg.from,
+                    code_hash,
+                });
+            }
+        }
+        // Make sure that transaction gas_fee_cap is greater than the base_fee (post london)
+        if self.evm.chain_rules.is_london {
+            // Skip the checks if gas fields are zero and base_fee was explicitly disabled (eth_call)
+            let skip_check = self.evm.config.no_base_fee
+                && msg.gas_fee_cap.is_zero()
+                && msg.gas_tip_cap.is_zero();
+            if !skip_check {
+                if msg.gas_fee_cap < msg.gas_tip_cap {
+                    return Err(StateTransitionError::TipAboveFeeCap {
+                        address: msg.from,
+                        tip: msg.gas_tip_cap.clone(),
+                        fee_cap: msg.gas_fee_cap.clone(),
+                    });
+                }
+                // This will panic if base_fee is nil, but base_fee presence is verified
+                // as part of header validation.
+                if msg.gas_fee_cap < self.evm.context.base_fee {
+                    return Err(StateTransitionError::FeeCapTooLow {
+                        address: msg.from,
+                        fee_cap: msg.gas_fee_cap.clone(),
+                        base_fee: self.evm.context.base_fee.clone(),
+                    });
+                }
+            }
+        }
+        // Check the blob version validity
+        if let Some(blob_fee_cap) = &msg.blob_gas_fee_cap {
+            if msg.blob_hashes.is_empty() {
+                return Err(StateTransitionError::MissingBlobHashes(msg.from));
+            }
+            for (index, hash) in msg.blob_hashes.iter().enumerate() {
+                if hash.0[0] != kzg4844::BLOB_COMMITMENT_VERSION_KZG {
+                    return Err(StateTransitionError::InvalidBlobHashVersion {
+                        index,
+                        hash: *hash,
+                    });
+                }
+            }
+            // Check that the user is paying at least the current blob fee
+            if self.evm.chain_rules.is_cancun {
+                // Skip the checks if gas fields are zero and blob_base_fee was explicitly disabled (eth_call)
+                let skip_check = self.evm.config.no_base_fee && blob_fee_cap.is_zero();
+                if !skip_check && *blob_fee_cap < self.evm.context.blob_base_fee {
+                    return Err(StateTransitionError::BlobFeeCapTooLow {
+                        address: msg.from,
+                        blob_fee_cap: blob_fee_cap.clone(),
+                        blob_base_fee: self.evm.context.blob_base_fee.clone(),
+                    });
+                }
+            }
+        }
+        self.buy_gas()
+    }
+
+    // transition_db will transition the state by applying the current message and
+    // returning the evm execution result with following fields.
+    //
+    //   - used gas: total gas used (including gas being refunded)
+    //   - returndata: the returned data from evm
+    //   - concrete execution error: various EVM errors which abort the execution, e.g.
+    //     ErrOutOfGas, ErrExecutionReverted
+    //
+    // However if any consensus issue encountered, return the error directly with
+    // nil evm execution result.
+    fn transition_db(mut self) -> Result<ExecutionResult, StateTransitionError> {
+        // First check this message satisfies all consensus rules before
+        // applying the message. The rules include these clauses
+        //
+        // 1. the nonce of the message caller is correct
+        // 2. caller has enough balance to cover transaction fee(gaslimit * gasprice)
+        // 3. the amount of gas required is available in the block
+        // 4. the purchased gas is enough to cover intrinsic usage
+        // 5. there is no overflow when calculating intrinsic gas
+        // 6. caller has enough balance to cover asset transfer for **topmost** call
+
+        // Check clauses 1-3, buy gas if everything is correct
+        self.pre_check()?;
+
+        let msg = self.msg;
+        let rules = self.evm.chain_rules.clone();
+        let contract_creation = msg.to.is_none();
+
+        // Check clauses 4-5, subtract intrinsic gas if everything is correct
+        let gas = intrinsic_gas(
+            &msg.data,
+            &msg.access_list,
+            contract_creation,
+            rules.is_homestead,
+            rules.is_istanbul,
+            rules.is_shanghai,
+        )
+        .map_err(|_| StateTransitionError::GasUintOverflow)?;
+        if self.gas_remaining < gas {
+            return Err(StateTransitionError::IntrinsicGas {
+                have: self.gas_remaining,
+                want: gas,
+            });
+        }
+        self.gas_remaining -= gas;
+
+        // Check clause 6
+        if !msg.value.is_zero() && !can_transfer(self.evm.state_db.as_ref(), &msg.from, &msg.value)
+        {
+            return Err(StateTransitionError::InsufficientFundsForTransfer(msg.from));
+        }
+
+        // Check whether the init code size has been exceeded.
+        if check_init_code_size(&rules, contract_creation, &msg.data).is_err() {
+            return Err(StateTransitionError::MaxInitCodeSizeExceeded {
+                have: msg.data.len(),
+                max: params::MAX_INIT_CODE_SIZE,
+            });
+        }
+
+        // Execute the preparatory steps for state transition which includes:
+        // - prepare access_list(post-berlin)
+        let coinbase = self.evm.context.coinbase;
+        self.evm.state_db.prepare(
+            &rules,
+            &msg.from,
+            &coinbase,
+            msg.to.as_ref(),
+            &precompiles::active_precompiles(&rules),
+            &msg.access_list,
+        );
+
+        let sender = AccountRef(msg.from);
+        let (ret, vm_err) = match msg.to {
+            None => {
+                let (ret, _, gas_left, err) = self.evm.create(
+                    &sender,
+                    msg.data.clone(),
+                    self.gas_remaining,
+                    msg.value.clone(),
+                );
+                self.gas_remaining = gas_left;
+                (ret, err)
+            }
+            Some(to) => {
+                // Increment the nonce for the next transaction
+                let nonce = self.evm.state_db.get_nonce(&msg.from);
+                self.evm.state_db.set_nonce(&msg.from, nonce + 1);
+                let (ret, gas_left, err) = self.evm.call(
+                    &sender,
+                    to,
+                    &msg.data,
+                    self.gas_remaining,
+                    msg.value.clone(),
+                );
+                self.gas_remaining = gas_left;
+                (ret, err)
+            }
+        };
+
+        let gas_refund = if rules.is_london {
+            // After EIP-3529: refunds are capped to gas_used / 5
+            self.refund_gas(params::REFUND_QUOTIENT_EIP3529)
+        } else {
+            // Before EIP-3529: refunds were capped to gas_used / 2
+            self.refund_gas(params::REFUND_QUOTIENT)
+        };
+
+        let mut effective_tip = msg.gas_price.clone();
+        if rules.is_london {
+            let fee_cap_tip = msg
+                .gas_fee_cap
+                .checked_sub(&self.evm.context.base_fee)
+                .unwrap_or_default();
+            if fee_cap_tip < msg.gas_tip_cap {
+                effective_tip = fee_cap_tip;
+            } else {
+                effective_tip = msg.gas_tip_cap.clone();
+            }
+        }
+
+        if self.evm.config.no_base_fee && msg.gas_fee_cap.is_zero() && msg.gas_tip_cap.is_zero() {
+            // Skip fee payment when no_base_fee is set and the fee fields
+            // are 0. This avoids a negative effective_tip being applied to
+            // the coinbase when simulating calls.
+        } else {
+            // The base fee part of the gas price is burnt: the sender paid
+            // gas_price per gas and the coinbase only receives the tip.
+            let fee = Uint256::from_uint64(self.gas_used())
+                .checked_mul(&effective_tip)
+                .unwrap_or_default();
+            self.evm.state_db.add_balance(&coinbase, &fee);
+        }
+
+        Ok(ExecutionResult {
+            used_gas: self.gas_used(),
+            refunded_gas: gas_refund,
+            err: vm_err,
+            return_data: ret,
+        })
+    }
+
+    fn refund_gas(&mut self, refund_quotient: u64) -> u64 {
+        // Apply refund counter, capped to a refund quotient
+        let refund = (self.gas_used() / refund_quotient).min(self.evm.state_db.get_refund());
+        self.gas_remaining += refund;
+
+        // Return wei for remaining gas, exchanged at the original rate.
+        let remaining = Uint256::from_uint64(self.gas_remaining)
+            .checked_mul(&self.msg.gas_price)
+            .unwrap_or_default();
+        self.evm.state_db.add_balance(&self.msg.from, &remaining);
+
+        // Also return remaining gas to the block gas counter so it is
+        // available for the next transaction.
+        self.gp.add_gas(self.gas_remaining);
+
+        refund
+    }
+
+    // gas_used returns the amount of gas used up by the state transition.
+    fn gas_used(&self) -> u64 {
+        self.initial_gas - self.gas_remaining
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chain_config::ChainConfig;
+    use crate::evm::{BlockContext, Config};
+    use crate::memory_state_db::MemoryStateDB;
+    use crate::state_db::StateDB;
+
+    const SENDER: common::Address = common::Address([0xaa; 20]);
+    const COINBASE: common::Address = common::Address([0xcb; 20]);
+
+    // new_evm returns an EVM on a London chain that has or hasn't activated
+    // Shanghai, with the given base fee and a funded SENDER.
+    fn new_evm(shanghai: bool, base_fee: u64) -> EVM {
+        let mut state_db = MemoryStateDB::new();
+        state_db.add_balance(&SENDER, &Uint256::from_uint64(1_000_000_000_000));
+        let chain_config = ChainConfig {
+            chain_id: 1337,
+            homestead_block: Some(0),
+            eip150_block: Some(0),
+            eip155_block: Some(0),
+            eip158_block: Some(0),
+            byzantium_block: Some(0),
+            constantinople_block: Some(0),
+            petersburg_block: Some(0),
+            istanbul_block: Some(0),
+            berlin_block: Some(0),
+            london_block: Some(0),
+            shanghai_time: if shanghai { Some(0) } else { None },
+            terminal_total_difficulty_passed: true,
+            ..Default::default()
+        };
+        let block_ctx = BlockContext {
+            get_hash: Box::new(|_| common::Hash::default()),
+            coinbase: COINBASE,
+            gas_limit: 30_000_000,
+            block_number: Uint256::one(),
+            time: 1,
+            difficulty: Uint256::zero(),
+            base_fee: Uint256::from_uint64(base_fee),
+            blob_base_fee: Uint256::one(),
+            random: Some(common::Hash::default()),
+        };
+        EVM::new(
+            block_ctx,
+            TxContext::default(),
+            Box::new(state_db),
+            chain_config,
+            Config::default(),
+        )
+    }
+
+    // create_msg returns a creation message with size zero bytes of
+    // initcode, which deploys an empty contract.
+    fn create_msg(size: usize) -> Message {
+        Message {
+            from: SENDER,
+            gas_limit: 1_000_000,
+            data: vec![0; size],
+            ..Default::default()
+        }
+    }
+
+    fn apply(evm: &mut EVM, msg: &Message) -> Result<ExecutionResult, StateTransitionError> {
+        apply_message(evm, msg, &mut GasPool::new(30_000_000))
+    }
+
+    #[test]
+    fn test_intrinsic_gas_init_code() {
+        let access_list = types::AccessList::default();
+        let data = [0u8; 33];
+        let base = params::TX_GAS_CONTRACT_CREATION + 33 * params::TX_DATA_ZERO_GAS;
+        assert_eq!(intrinsic_gas(&data, &access_list, true, true, true, false), Ok(base));
+        // Two words of initcode from Shanghai on
+        assert_eq!(
+            intrinsic_gas(&data, &access_list, true, true, true, true),
+            Ok(base + 2 * params::INIT_CODE_WORD_GAS)
+        );
+        // Calls don't pay for initcode
+        assert_eq!(
+            intrinsic_gas(&data, &access_list, false, true, true, true),
+            Ok(params::TX_GAS + 33 * params::TX_DATA_ZERO_GAS)
+        );
+    }
+
+    #[test]
+    fn test_creation_init_code_limit() {
+        let max = params::MAX_INIT_CODE_SIZE as usize;
+        let result = apply(&mut new_evm(true, 0), &create_msg(max)).unwrap();
+        assert_eq!(result.err, None);
+
+        let err = apply(&mut new_evm(true, 0), &create_msg(max + 1)).unwrap_err();
+        assert_eq!(
+            err,
+            StateTransitionError::MaxInitCodeSizeExceeded {
+                have: max + 1,
+                max: params::MAX_INIT_CODE_SIZE,
+            }
+        );
+        // There is no limit before Shanghai
+        let result = apply(&mut new_evm(false, 0), &create_msg(max + 1)).unwrap();
+        assert_eq!(result.err, None);
+    }
+
+    #[test]
+    fn test_creation_init_code_gas() {
+        let msg = create_msg(params::MAX_INIT_CODE_SIZE as usize);
+        let before = apply(&mut new_evm(false, 0), &msg).unwrap().used_gas;
+        let after = apply(&mut new_evm(true, 0), &msg).unwrap().used_gas;
+        assert_eq!(after - before, params::MAX_INIT_CODE_SIZE / 32 * params::INIT_CODE_WORD_GAS);
+    }
+
+    const RECEIVER: common::Address = common::Address([0xbb; 20]);
+
+    // transfer_msg returns a value transfer paying fee_cap per gas with the
+    // given tip on top of the base fee.
+    fn transfer_msg(nonce: u64, base_fee: u64, fee_cap: u64, tip: u64) -> Message {
+        Message {
+            to: Some(RECEIVER),
+            from: SENDER,
+            nonce,
+            value: Uint256::from_uint64(1),
+            gas_limit: 50_000,
+            gas_price: Uint256::from_uint64(fee_cap.min(base_fee + tip)),
+            gas_fee_cap: Uint256::from_uint64(fee_cap),
+            gas_tip_cap: Uint256::from_uint64(tip),
+            ..Default::default()
+        }
+    }
+
+    fn balance(evm: &EVM, addr: &common::Address) -> u64 {
+        evm.state_db.get_balance(addr).low_u64()
+    }
+
+    #[test]
+    fn test_intrinsic_gas() {
+        let empty = types::AccessList::default();
+        assert_eq!(intrinsic_gas(&[], &empty, false, true, true, false), Ok(params::TX_GAS));
+        assert_eq!(
+            intrinsic_gas(&[], &empty, true, true, true, false),
+            Ok(params::TX_GAS_CONTRACT_CREATION)
+        );
+        // Frontier creations cost the same as calls
+        assert_eq!(intrinsic_gas(&[], &empty, true, false, false, false), Ok(params::TX_GAS));
+
+        let data = [0, 1, 0, 2];
+        assert_eq!(
+            intrinsic_gas(&data, &empty, false, true, true, false),
+            Ok(params::TX_GAS + 2 * params::TX_DATA_ZERO_GAS + 2 * params::TX_DATA_NON_ZERO_GAS_EIP2028)
+        );
+        // Non-zero bytes were more expensive before Istanbul
+        assert_eq!(
+            intrinsic_gas(&data, &empty, false, true, false, false),
+            Ok(params::TX_GAS + 2 * params::TX_DATA_ZERO_GAS + 2 * params::TX_DATA_NON_ZERO_GAS_FRONTIER)
+        );
+
+        let access_list = types::AccessList(vec![types::AccessTuple {
+            address: RECEIVER,
+            storage_keys: vec![common::Hash::default(), common::Hash([1; 32])],
+        }]);
+        assert_eq!(
+            intrinsic_gas(&[], &access_list, false, true, true, false),
+            Ok(params::TX_GAS + params::TX_ACCESS_LIST_ADDRESS_GAS + 2 * params::TX_ACCESS_LIST_STORAGE_KEY_GAS)
+        );
+
+        // A transfer with too little gas is invalid
+        let mut msg = transfer_msg(0, 0, 1, 1);
+        msg.gas_limit = params::TX_GAS - 1;
+        assert_eq!(
+            apply(&mut new_evm(false, 0), &msg).unwrap_err(),
+            StateTransitionError::IntrinsicGas {
+                have: params::TX_GAS - 1,
+                want: params::TX_GAS,
+            }
+        );
+    }
+
+    // clear_slots returns a contract clearing storage slots 0..n.
+    fn clear_slots(evm: &mut EVM, n: u8) -> common::Address {
+        let contract = common::Address([0xcc; 20]);
+        let mut code = Vec::new();
+        for i in 0..n {
+            let mut key = common::Hash::default();
+            key.0[31] = i;
+            evm.state_db.set_state(&contract, key, common::Hash([1; 32]));
+            code.extend_from_slice(&[0x60, 0x00, 0x60, i, 0x55]);
+        }
+        evm.state_db.set_code(&contract, &code);
+        evm.state_db.finalise(true);
+        contract
+    }
+
+    #[test]
+    fn test_refund_cap() {
+        let msg = |to| Message {
+            to: Some(to),
+            from: SENDER,
+            gas_limit: 1_000_000,
+            ..Default::default()
+        };
+
+        // A single cleared slot is refunded in full
+        let mut evm = new_evm(false, 0);
+        let contract = clear_slots(&mut evm, 1);
+        let result = apply(&mut evm, &msg(contract)).unwrap();
+        assert_eq!(result.err, None);
+        assert_eq!(result.refunded_gas, params::SSTORE_CLEARS_SCHEDULE_REFUND_EIP3529);
+
+        // Refunds are capped to a fifth of the gas used
+        let mut evm = new_evm(false, 0);
+        let contract = clear_slots(&mut evm, 10);
+        let result = apply(&mut evm, &msg(contract)).unwrap();
+        assert_eq!(result.err, None);
+        let gas_used = result.used_gas + result.refunded_gas;
+        assert!(result.refunded_gas < 10 * params::SSTORE_CLEARS_SCHEDULE_REFUND_EIP3529);
+        assert_eq!(result.refunded_gas, gas_used / params::REFUND_QUOTIENT_EIP3529);
+    }
+
+    #[test]
+    fn test_fee_payment() {
+        let initial = 1_000_000_000_000;
+
+        // The sender pays base fee plus tip, the coinbase only gets the tip
+        let mut evm = new_evm(false, 10);
+        let result = apply(&mut evm, &transfer_msg(0, 10, 100, 2)).unwrap();
+        assert_eq!(result.used_gas, params::TX_GAS);
+        assert_eq!(balance(&evm, &SENDER), initial - 1 - params::TX_GAS * 12);
+        assert_eq!(balance(&evm, &RECEIVER), 1);
+        assert_eq!(balance(&evm, &COINBASE), params::TX_GAS * 2);
+        assert_eq!(evm.state_db.get_nonce(&SENDER), 1);
+
+        // The tip is capped by what's left of the fee cap above the base fee
+        let mut evm = new_evm(false, 10);
+        apply(&mut evm, &transfer_msg(0, 10, 11, 5)).unwrap();
+        assert_eq!(balance(&evm, &SENDER), initial - 1 - params::TX_GAS * 11);
+        assert_eq!(balance(&evm, &COINBASE), params::TX_GAS);
+
+        // Unused gas is returned to the sender and the block gas pool
+        let mut evm = new_evm(false, 10);
+        let mut gp = GasPool::new(100_000);
+        let msg = transfer_msg(0, 10, 100, 2);
+        apply_message(&mut evm, &msg, &mut gp).unwrap();
+        assert_eq!(gp.gas(), 100_000 - params::TX_GAS);
+    }
+
+    #[test]
+    fn test_fee_cap_errors() {
+        let err = apply(&mut new_evm(false, 10), &transfer_msg(0, 10, 9, 1)).unwrap_err();
+        assert_eq!(
+            err,
+            StateTransitionError::FeeCapTooLow {
+                address: SENDER,
+                fee_cap: Uint256::from_uint64(9),
+                base_fee: Uint256::from_uint64(10),
+            }
+        );
+        let err = apply(&mut new_evm(false, 10), &transfer_msg(0, 10, 20, 21)).unwrap_err();
+        assert_eq!(
+            err,
+            StateTransitionError::TipAboveFeeCap {
+                address: SENDER,
+                tip: Uint256::from_uint64(21),
+                fee_cap: Uint256::from_uint64(20),
+            }
+        );
+    }
+
+    #[test]
+    fn test_nonce_errors() {
+        let err = apply(&mut new_evm(false, 0), &transfer_msg(1, 0, 1, 1)).unwrap_err();
+        assert_eq!(err, StateTransitionError::NonceTooHigh { address: SENDER, tx: 1, state: 0 });
+
+        let mut evm = new_evm(false, 0);
+        evm.state_db.set_nonce(&SENDER, 5);
+        let err = apply(&mut evm, &transfer_msg(3, 0, 1, 1)).unwrap_err();
+        assert_eq!(err, StateTransitionError::NonceTooLow { address: SENDER, tx: 3, state: 5 });
+
+        let mut evm = new_evm(false, 0);
+        evm.state_db.set_nonce(&SENDER, u64::MAX);
+        let err = apply(&mut evm, &transfer_msg(u64::MAX, 0, 1, 1)).unwrap_err();
+        assert_eq!(err, StateTransitionError::NonceMax { address: SENDER, nonce: u64::MAX });
+
+        // eth_call style messages skip the nonce check
+        let mut msg = transfer_msg(7, 0, 1, 1);
+        msg.skip_account_checks = true;
+        assert!(apply(&mut new_evm(false, 0), &msg).is_ok());
+    }
+
+    #[test]
+    fn test_balance_errors() {
+        let initial = 1_000_000_000_000u64;
+
+        // The balance must cover gas_limit * fee_cap plus the value
+        let mut poor = transfer_msg(0, 0, 1_000_000, 1);
+        poor.gas_limit = initial / 1_000_000;
+        let mut evm = new_evm(false, 0);
+        let err = apply(&mut evm, &poor).unwrap_err();
+        assert_eq!(
+            err,
+            StateTransitionError::InsufficientFunds {
+                address: SENDER,
+                have: Uint256::from_uint64(initial),
+                want: Uint256::from_uint64(initial + 1),
+            }
+        );
+        // A failed pre-check doesn't touch the state
+        assert_eq!(balance(&evm, &SENDER), initial);
+        assert_eq!(evm.state_db.get_nonce(&SENDER), 0);
+
+        // The gas pool must cover the gas limit
+        let msg = transfer_msg(0, 0, 1, 1);
+        let err = apply_message(&mut new_evm(false, 0), &msg, &mut GasPool::new(params::TX_GAS)).unwrap_err();
+        assert_eq!(err, StateTransitionError::GasLimitReached { have: params::TX_GAS, want: 50_000 });
+    }
+}
@@ -0,0 +1,1020 @@
+// Transaction types.
+//
+// Next to the original legacy transactions (with the EIP-155 replay
+// protection), EIP-2718 introduced typed transactions, encoded as an envelope
+// of the type byte followed by the RLP encoded payload:
+//
+//     0x01 EIP-2930 access list transactions
+//     0x02 EIP-1559 dynamic fee transactions
+//     0x03 EIP-4844 blob transactions
+//
+// Inside a block body a legacy transaction is an RLP list, a typed
+// transaction the RLP string holding its envelope.
+
+use std::fmt;
+
+use serde::{de::Error as _, Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::crypto::{self, PrivateKey};
+use crate::hexutil;
+use crate::rlp::{self, Decodable, DecoderError, Encodable, Rlp};
+use uint256::Uint256;
+
+// Transaction types.
+pub const LEGACY_TX_TYPE: u8 = 0x00;
+pub const ACCESS_LIST_TX_TYPE: u8 = 0x01;
+pub const DYNAMIC_FEE_TX_TYPE: u8 = 0x02;
+pub const BLOB_TX_TYPE: u8 = 0x03;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TxError {
+    // TxTypeNotSupported is returned if a transaction is not supported in the
+    // current network configuration.
+    TxTypeNotSupported,
+    // InvalidTxType is returned if the transaction envelope is empty.
+    InvalidTxType,
+    // InvalidChainId is returned when the chain id of a transaction doesn't
+    // match the signer's.
+    InvalidChainId,
+    // InvalidSig is returned if the signature values of a transaction are
+    // invalid or the sender can't be recovered from them.
+    InvalidSig,
+    // GasFeeCapTooLow is returned if the transaction fee cap is less than the
+    // base fee of the block.
+    GasFeeCapTooLow,
+    Rlp(DecoderError),
+}
+
+impl fmt::Display for TxError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TxError::TxTypeNotSupported => write!(f, "transaction type not supported"),
+            TxError::InvalidTxType => write!(f, "transaction type not valid in this context"),
+            TxError::InvalidChainId => write!(f, "invalid chain id for signer"),
+            TxError::InvalidSig => write!(f, "invalid transaction v, r, s values"),
+            TxError::GasFeeCapTooLow => write!(f, "fee cap less than base fee"),
+            TxError::Rlp(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl std::error::Error for TxError {}
+
+impl From<DecoderError> for TxError {
+    fn from(err: DecoderError) -> Self {
+        TxError::Rlp(err)
+    }
+}
+
+// AccessTuple is the element type of an access list.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AccessTuple {
+    #[serde(with = "hexutil::address")]
+    pub address: common::Address,
+    #[serde(with = "hexutil::hashes")]
+    pub storage_keys: Vec<common::Hash>,
+}
+
+impl Encodable for AccessTuple {
+    fn rlp_append(&self, out: &mut Vec<u8>) {
+        let mut payload = Vec::new();
+        self.address.rlp_append(&mut payload);
+        rlp::append_list(&self.storage_keys, &mut payload);
+        rlp::append_list_payload(&payload, out);
+    }
+}
+
+impl Decodable for AccessTuple {
+    fn decode(rlp: &Rlp) -> Result<Self, DecoderError> {
+        if rlp.item_count()? != 2 {
+            return Err(DecoderError::RlpIncorrectListLen);
+        }
+        Ok(AccessTuple {
+            address: rlp.val_at(0)?,
+            storage_keys: rlp.list_at(1)?,
+        })
+    }
+}
+
+// AccessList is an EIP-2930 access list.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct AccessList(pub Vec<AccessTuple>);
+
+impl AccessList {
+    // len returns the number of addresses in the list.
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    // storage_keys returns the total number of storage keys in the access list.
+    pub fn storage_keys(&self) -> usize {
+        self.0.iter().map(|tuple| tuple.storage_keys.len()).sum()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &AccessTuple> {
+        self.0.iter()
+    }
+}
+
+impl Encodable for AccessList {
+    fn rlp_append(&self, out: &mut Vec<u8>) {
+        rlp::append_list(&self.0, out);
+    }
+}
+
+impl Decodable for AccessList {
+    fn decode(rlp: &Rlp) -> Result<Self, DecoderError> {
+        Ok(AccessList(rlp.as_list()?))
+    }
+}
+
+// LegacyTx is the transaction data of the original Ethereum transactions.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct LegacyTx {
+    pub nonce: u64,                  // nonce of sender account
+    pub gas_price: Uint256,          // wei per gas
+    pub gas: u64,                    // gas limit
+    pub to: Option<common::Address>, // nil means contract creation
+    pub value: Uint256,              // wei amount
+    pub data: Vec<u8>,               // contract invocation input data
+    pub v: Uint256,                  // signature values
+    pub r: Uint256,
+    pub s: Uint256,
+}
+
+crate::impl_rlp_struct!(LegacyTx { nonce, gas_price, gas, to, value, data, v, r, s });
+
+// AccessListTx is the data of EIP-2930 access list transactions.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct AccessListTx {
+    pub chain_id: Uint256,           // destination chain ID
+    pub nonce: u64,                  // nonce of sender account
+    pub gas_price: Uint256,          // wei per gas
+    pub gas: u64,                    // gas limit
+    pub to: Option<common::Address>, // nil means contract creation
+    pub value: Uint256,              // wei amount
+    pub data: Vec<u8>,               // contract invocation input data
+    pub access_list: AccessList,     // EIP-2930 access list
+    pub v: Uint256,                  // signature values
+    pub r: Uint256,
+    pub s: Uint256,
+}
+
+crate::impl_rlp_struct!(AccessListTx { chain_id, nonce, gas_price, gas, to, value, data, access_list, v, r, s });
+
+// DynamicFeeTx represents an EIP-1559 transaction.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct DynamicFeeTx {
+    pub chain_id: Uint256,
+    pub nonce: u64,
+    pub gas_tip_cap: Uint256, // a.k.a. max_priority_fee_per_gas
+    pub gas_fee_cap: Uint256, // a.k.a. max_fee_per_gas
+    pub gas: u64,
+    pub to: Option<common::Address>, // nil means contract creation
+    pub value: Uint256,
+    pub data: Vec<u8>,
+    pub access_list: AccessList,
+    pub v: Uint256, // signature values
+    pub r: Uint256,
+    pub s: Uint256,
+}
+
+crate::impl_rlp_struct!(DynamicFeeTx { chain_id, nonce, gas_tip_cap, gas_fee_cap, gas, to, value, data, access_list, v, r, s });
+
+// BlobTx represents an EIP-4844 transaction. Blob transactions can't create
+// contracts.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct BlobTx {
+    pub chain_id: Uint256,
+    pub nonce: u64,
+    pub gas_tip_cap: Uint256, // a.k.a. max_priority_fee_per_gas
+    pub gas_fee_cap: Uint256, // a.k.a. max_fee_per_gas
+    pub gas: u64,
+    pub to: common::Address,
+    pub value: Uint256,
+    pub data: Vec<u8>,
+    pub access_list: AccessList,
+    pub blob_fee_cap: Uint256, // a.k.a. max_fee_per_blob_gas
+    pub blob_hashes: Vec<common::Hash>,
+    pub v: Uint256, // signature values
+    pub r: Uint256,
+    pub s: Uint256,
+}
+
+impl BlobTx {
+    fn append_fields(&self, payload: &mut Vec<u8>) {
+        self.chain_id.rlp_append(payload);
+        self.nonce.rlp_append(payload);
+        self.gas_tip_cap.rlp_append(payload);
+        self.gas_fee_cap.rlp_append(payload);
+        self.gas.rlp_append(payload);
+        self.to.rlp_append(payload);
+        self.value.rlp_append(payload);
+        self.data.rlp_append(payload);
+        self.access_list.rlp_append(payload);
+        self.blob_fee_cap.rlp_append(payload);
+        rlp::append_list(&self.blob_hashes, payload);
+    }
+}
+
+impl Encodable for BlobTx {
+    fn rlp_append(&self, out: &mut Vec<u8>) {
+        let mut payload = Vec::new();
+        self.append_fields(&mut payload);
+        self.v.rlp_append(&mut payload);
+        self.r.rlp_append(&mut payload);
+        self.s.rlp_append(&mut payload);
+        rlp::append_list_payload(&payload, out);
+    }
+}
+
+impl Decodable for BlobTx {
+    fn decode(rlp: &Rlp) -> Result<Self, DecoderError> {
+        if rlp.item_count()? != 14 {
+            return Err(DecoderError::RlpIncorrectListLen);
+        }
+        Ok(BlobTx {
+            chain_id: rlp.val_at(0)?,
+            nonce: rlp.val_at(1)?,
+            gas_tip_cap: rlp.val_at(2)?,
+            gas_fee_cap: rlp.val_at(3)?,
+            gas: rlp.val_at(4)?,
+            to: rlp.val_at(5)?,
+            value: rlp.val_at(6)?,
+            data: rlp.val_at(7)?,
+            access_list: rlp.val_at(8)?,
+            blob_fee_cap: rlp.val_at(9)?,
+            blob_hashes: rlp.list_at(10)?,
+            v: rlp.val_at(11)?,
+            r: rlp.val_at(12)?,
+            s: rlp.val_at(13)?,
+        })
+    }
+}
+
+// TxData is the underlying data of a transaction.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TxData {
+    Legacy(LegacyTx),
+    AccessList(AccessListTx),
+    DynamicFee(DynamicFeeTx),
+    Blob(BlobTx),
+}
+
+// Transaction is an Ethereum transaction.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Transaction {
+    inner: TxData,
+}
+
+impl Default for Transaction {
+    fn default() -> Self {
+        Transaction::new(TxData::Legacy(LegacyTx::default()))
+    }
+}
+
+impl From<TxData> for Transaction {
+    fn from(inner: TxData) -> Self {
+        Transaction::new(inner)
+    }
+}
+
+impl Transaction {
+    // new creates a new transaction with the given data.
+    pub fn new(inner: TxData) -> Self {
+        Transaction { inner }
+    }
+
+    pub fn inner(&self) -> &TxData {
+        &self.inner
+    }
+
+    // tx_type returns the transaction type.
+    pub fn tx_type(&self) -> u8 {
+        match &self.inner {
+            TxData::Legacy(_) => LEGACY_TX_TYPE,
+            TxData::AccessList(_) => ACCESS_LIST_TX_TYPE,
+            TxData::DynamicFee(_) => DYNAMIC_FEE_TX_TYPE,
+            TxData::Blob(_) => BLOB_TX_TYPE,
+        }
+    }
+
+    // chain_id returns the EIP-155 chain ID of the transaction. The return value
+    // will always be zero for legacy transactions that are not replay protected.
+    pub fn chain_id(&self) -> Uint256 {
+        match &self.inner {
+            TxData::Legacy(tx) => derive_chain_id(&tx.v),
+            TxData::AccessList(tx) => tx.chain_id.clone(),
+            TxData::DynamicFee(tx) => tx.chain_id.clone(),
+            TxData::Blob(tx) => tx.chain_id.clone(),
+        }
+    }
+
+    // protected says whether the transaction is replay-protected.
+    pub fn protected(&self) -> bool {
+        match &self.inner {
+            TxData::Legacy(tx) => is_protected_v(&tx.v),
+            _ => true,
+        }
+    }
+
+    pub fn nonce(&self) -> u64 {
+        match &self.inner {
+            TxData::Legacy(tx) => tx.nonce,
+            TxData::AccessList(tx) => tx.nonce,
+            TxData::DynamicFee(tx) => tx.nonce,
+            TxData::Blob(tx) => tx.nonce,
+        }
+    }
+
+    // gas returns the gas limit of the transaction.
+    pub fn gas(&self) -> u64 {
+        match &self.inner {
+            TxData::Legacy(tx) => tx.gas,
+            TxData::AccessList(tx) => tx.gas,
+            TxData::DynamicFee(tx) => tx.gas,
+            TxData::Blob(tx) => tx.gas,
+        }
+    }
+
+    // gas_price returns the gas price of the transaction. For dynamic fee
+    // transactions this is the fee cap.
+    pub fn gas_price(&self) -> Uint256 {
+        match &self.inner {
+            TxData::Legacy(tx) => tx.gas_price.clone(),
+            TxData::AccessList(tx) => tx.gas_price.clone(),
+            TxData::DynamicFee(tx) => tx.gas_fee_cap.clone(),
+            TxData::Blob(tx) => tx.gas_fee_cap.clone(),
+        }
+    }
+
+    // gas_tip_cap returns the gasTipCap per gas of the transaction.
+    pub fn gas_tip_cap(&self) -> Uint256 {
+        match &self.inner {
+            TxData::Legacy(tx) => tx.gas_price.clone(),
+            TxData::AccessList(tx) => tx.gas_price.clone(),
+            TxData::DynamicFee(tx) => tx.gas_tip_cap.clone(),
+            TxData::Blob(tx) => tx.gas_tip_cap.clone(),
+        }
+    }
+
+    // gas_fee_cap returns the fee cap per gas of the transaction.
+    pub fn gas_fee_cap(&self) -> Uint256 {
+        self.gas_price()
+    }
+
+    // effective_gas_tip returns the effective miner gas tip for the given base
+    // fee: min(gas_tip_cap, gas_fee_cap - base_fee). It returns an error if
+    // the fee cap is below the base fee.
+    pub fn effective_gas_tip(&self, base_fee: Option<&Uint256>) -> Result<Uint256, TxError> {
+        let base_fee = match base_fee {
+            Some(base_fee) => base_fee,
+            None => return Ok(self.gas_tip_cap()),
+        };
+        let gas_fee_cap = self.gas_fee_cap();
+        let room = gas_fee_cap.checked_sub(base_fee).ok_or(TxError::GasFeeCapTooLow)?;
+        let gas_tip_cap = self.gas_tip_cap();
+        Ok(if gas_tip_cap < room { gas_tip_cap } else { room })
+    }
+
+    // effective_gas_price returns the price per gas paid by the transaction in
+    // a block with the given base fee: min(gas_tip_cap + base_fee,
+    // gas_fee_cap). For legacy and access list transactions this is the gas
+    // price.
+    pub fn effective_gas_price(&self, base_fee: Option<&Uint256>) -> Uint256 {
+        let gas_fee_cap = self.gas_fee_cap();
+        match base_fee.and_then(|base_fee| self.gas_tip_cap().checked_add(base_fee)) {
+            Some(price) if price < gas_fee_cap => price,
+            _ => gas_fee_cap,
+        }
+    }
+
+    pub fn value(&self) -> Uint256 {
+        match &self.inner {
+            TxData::Legacy(tx) => tx.value.clone(),
+            TxData::AccessList(tx) => tx.value.clone(),
+            TxData::DynamicFee(tx) => tx.value.clone(),
+            TxData::Blob(tx) => tx.value.clone(),
+        }
+    }
+
+    // to returns the recipient address of the transaction, or None for
+    // contract creation transactions.
+    pub fn to(&self) -> Option<common::Address> {
+        match &self.inner {
+            TxData::Legacy(tx) => tx.to,
+            TxData::AccessList(tx) => tx.to,
+            TxData::DynamicFee(tx) => tx.to,
+            TxData::Blob(tx) => Some(tx.to),
+        }
+    }
+
+    // data returns the input data of the transaction.
+    pub fn data(&self) -> &[u8] {
+        match &self.inner {
+            TxData::Legacy(tx) => &tx.data,
+            TxData::AccessList(tx) => &tx.data,
+            TxData::DynamicFee(tx) => &tx.data,
+            TxData::Blob(tx) => &tx.data,
+        }
+    }
+
+    // access_list returns the access list of the transaction, which is empty
+    // for legacy transactions.
+    pub fn access_list(&self) -> AccessList {
+        match &self.inner {
+            TxData::Legacy(_) => AccessList::default(),
+            TxData::AccessList(tx) => tx.access_list.clone(),
+            TxData::DynamicFee(tx) => tx.access_list.clone(),
+            TxData::Blob(tx) => tx.access_list.clone(),
+        }
+    }
+
+    // blob_gas_fee_cap returns the blob gas fee cap per blob gas of the
+    // transaction for blob transactions, None otherwise.
+    pub fn blob_gas_fee_cap(&self) -> Option<Uint256> {
+        match &self.inner {
+            TxData::Blob(tx) => Some(tx.blob_fee_cap.clone()),
+            _ => None,
+        }
+    }
+
+    // blob_hashes returns the hashes of the blob commitments for blob
+    // transactions, an empty list otherwise.
+    pub fn blob_hashes(&self) -> &[common::Hash] {
+        match &self.inner {
+            TxData::Blob(tx) => &tx.blob_hashes,
+            _ => &[],
+        }
+    }
+
+    // blob_gas returns the blob gas limit of the transaction.
+    pub fn blob_gas(&self) -> u64 {
+        crate::params::BLOB_TX_BLOB_GAS_PER_BLOB * self.blob_hashes().len() as u64
+    }
+
+    // raw_signature_values returns the V, R, S signature values of the
+    // transaction. The return values should not be modified by the caller.
+    pub fn raw_signature_values(&self) -> (&Uint256, &Uint256, &Uint256) {
+        match &self.inner {
+            TxData::Legacy(tx) => (&tx.v, &tx.r, &tx.s),
+            TxData::AccessList(tx) => (&tx.v, &tx.r, &tx.s),
+            TxData::DynamicFee(tx) => (&tx.v, &tx.r, &tx.s),
+            TxData::Blob(tx) => (&tx.v, &tx.r, &tx.s),
+        }
+    }
+
+    // with_signature_values returns a copy of the transaction with the given
+    // raw signature values.
+    pub fn with_signature_values(&self, v: Uint256, r: Uint256, s: Uint256) -> Self {
+        let mut inner = self.inner.clone();
+        match &mut inner {
+            TxData::Legacy(tx) => (tx.v, tx.r, tx.s) = (v, r, s),
+            TxData::AccessList(tx) => (tx.v, tx.r, tx.s) = (v, r, s),
+            TxData::DynamicFee(tx) => (tx.v, tx.r, tx.s) = (v, r, s),
+            TxData::Blob(tx) => (tx.v, tx.r, tx.s) = (v, r, s),
+        }
+        Transaction { inner }
+    }
+
+    // marshal_binary returns the canonical encoding of the transaction. For
+    // legacy transactions, it returns the RLP encoding. For EIP-2718 typed
+    // transactions, it returns the type and payload.
+    pub fn marshal_binary(&self) -> Vec<u8> {
+        match &self.inner {
+            TxData::Legacy(tx) => rlp::encode(tx),
+            TxData::AccessList(tx) => typed_envelope(ACCESS_LIST_TX_TYPE, tx),
+            TxData::DynamicFee(tx) => typed_envelope(DYNAMIC_FEE_TX_TYPE, tx),
+            TxData::Blob(tx) => typed_envelope(BLOB_TX_TYPE, tx),
+        }
+    }
+
+    // unmarshal_binary decodes the canonical encoding of transactions. It
+    // supports legacy RLP transactions and EIP-2718 typed transactions.
+    pub fn unmarshal_binary(b: &[u8]) -> Result<Self, TxError> {
+        match b.first() {
+            None => Err(TxError::InvalidTxType),
+            // It's a legacy transaction.
+            Some(first) if *first > 0x7f => Ok(Transaction::new(TxData::Legacy(rlp::decode(b)?))),
+            Some(&ACCESS_LIST_TX_TYPE) => Ok(Transaction::new(TxData::AccessList(rlp::decode(&b[1..])?))),
+            Some(&DYNAMIC_FEE_TX_TYPE) => Ok(Transaction::new(TxData::DynamicFee(rlp::decode(&b[1..])?))),
+            Some(&BLOB_TX_TYPE) => Ok(Transaction::new(TxData::Blob(rlp::decode(&b[1..])?))),
+            Some(_) => Err(TxError::TxTypeNotSupported),
+        }
+    }
+
+    // hash returns the transaction hash, the keccak256 hash of its canonical
+    // encoding.
+    pub fn hash(&self) -> common::Hash {
+        crypto::keccak256_hash(&self.marshal_binary())
+    }
+
+    // size returns the encoded storage size of the transaction.
+    pub fn size(&self) -> usize {
+        self.marshal_binary().len()
+    }
+
+    // signing_hash returns the hash to be signed by the sender. Legacy
+    // transactions are hashed with the EIP-155 replay protection if chain_id
+    // is given and as Homestead transactions otherwise; typed transactions
+    // always commit to their own chain id.
+    pub fn signing_hash(&self, chain_id: Option<&Uint256>) -> common::Hash {
+        let mut payload = Vec::new();
+        match &self.inner {
+            TxData::Legacy(tx) => {
+                tx.nonce.rlp_append(&mut payload);
+                tx.gas_price.rlp_append(&mut payload);
+                tx.gas.rlp_append(&mut payload);
+                tx.to.rlp_append(&mut payload);
+                tx.value.rlp_append(&mut payload);
+                tx.data.rlp_append(&mut payload);
+                if let Some(chain_id) = chain_id {
+                    chain_id.rlp_append(&mut payload);
+                    0u8.rlp_append(&mut payload);
+                    0u8.rlp_append(&mut payload);
+                }
+                let mut out = Vec::with_capacity(payload.len() + 9);
+                rlp::append_list_payload(&payload, &mut out);
+                return crypto::keccak256_hash(&out);
+            }
+            TxData::AccessList(tx) => {
+                tx.chain_id.rlp_append(&mut payload);
+                tx.nonce.rlp_append(&mut payload);
+                tx.gas_price.rlp_append(&mut payload);
+                tx.gas.rlp_append(&mut payload);
+                tx.to.rlp_append(&mut payload);
+                tx.value.rlp_append(&mut payload);
+                tx.data.rlp_append(&mut payload);
+                tx.access_list.rlp_append(&mut payload);
+            }
+            TxData::DynamicFee(tx) => {
+                tx.chain_id.rlp_append(&mut payload);
+                tx.nonce.rlp_append(&mut payload);
+                tx.gas_tip_cap.rlp_append(&mut payload);
+                tx.gas_fee_cap.rlp_append(&mut payload);
+                tx.gas.rlp_append(&mut payload);
+                tx.to.rlp_append(&mut payload);
+                tx.value.rlp_append(&mut payload);
+                tx.data.rlp_append(&mut payload);
+                tx.access_list.rlp_append(&mut payload);
+            }
+            TxData::Blob(tx) => tx.append_fields(&mut payload),
+        }
+        let mut out = vec![self.tx_type()];
+        rlp::append_list_payload(&payload, &mut out);
+        crypto::keccak256_hash(&out)
+    }
+}
+
+// sender returns the address derived from the signature (V, R, S) using
+// secp256k1 elliptic curve and an error if it failed deriving or upon an
+// incorrect signature. chain_id is the chain the signer is configured for:
+// replay protected transactions must be signed for it, unprotected legacy
+// transactions are accepted with the Homestead rules.
+pub fn sender(tx: &Transaction, chain_id: &Uint256) -> Result<common::Address, TxError> {
+    let (v, r, s) = tx.raw_signature_values();
+    if let TxData::Legacy(_) = tx.inner() {
+        if !tx.protected() {
+            if v.bits() > 8 || !(27..=28).contains(&v.low_u64()) {
+                return Err(TxError::InvalidSig);
+            }
+            return recover_plain(&tx.signing_hash(None), r, s, v.low_u64() - 27);
+        }
+    }
+    if tx.chain_id() != *chain_id {
+        return Err(TxError::InvalidChainId);
+    }
+    let recid = match tx.inner() {
+        // v = chain_id * 2 + 35 + y_parity
+        TxData::Legacy(_) => {
+            if v.bits() > 64 || chain_id.bits() > 62 {
+                return Err(TxError::InvalidSig);
+            }
+            v.low_u64()
+                .checked_sub(chain_id.low_u64() * 2 + 35)
+                .ok_or(TxError::InvalidSig)?
+        }
+        _ => {
+            if v.bits() > 8 {
+                return Err(TxError::InvalidSig);
+            }
+            v.low_u64()
+        }
+    };
+    recover_plain(&tx.signing_hash(Some(chain_id)), r, s, recid)
+}
+
+fn recover_plain(hash: &common::Hash, r: &Uint256, s: &Uint256, recid: u64) -> Result<common::Address, TxError> {
+    if recid > 1 || !crypto::validate_signature_values(recid as u8, r, s, true) {
+        return Err(TxError::InvalidSig);
+    }
+    // encode the signature in uncompressed format
+    let mut sig = [0u8; crypto::SIGNATURE_LENGTH];
+    sig[..32].copy_from_slice(&r.to_bytes32());
+    sig[32..64].copy_from_slice(&s.to_bytes32());
+    sig[crypto::RECOVERY_ID_OFFSET] = recid as u8;
+    // recover the public key from the signature
+    let pub_key = crypto::ecrecover(hash.as_bytes(), &sig).map_err(|_| TxError::InvalidSig)?;
+    Ok(crypto::pubkey_to_address(&pub_key))
+}
+
+// sign_tx signs the transaction for the given chain with the private key.
+// Legacy transactions are signed with the EIP-155 replay protection.
+pub fn sign_tx(tx: &Transaction, chain_id: &Uint256, key: &PrivateKey) -> Result<Transaction, TxError> {
+    if !matches!(tx.inner(), TxData::Legacy(_)) && tx.chain_id() != *chain_id {
+        return Err(TxError::InvalidChainId);
+    }
+    let hash = tx.signing_hash(Some(chain_id));
+    let sig = crypto::sign(hash.as_bytes(), key).map_err(|_| TxError::InvalidSig)?;
+    let recid = sig[crypto::RECOVERY_ID_OFFSET] as u64;
+    let v = match tx.inner() {
+        TxData::Legacy(_) => {
+            if chain_id.bits() > 62 {
+                return Err(TxError::InvalidChainId);
+            }
+            Uint256::from_uint64(chain_id.low_u64() * 2 + 35 + recid)
+        }
+        _ => Uint256::from_uint64(recid),
+    };
+    Ok(tx.with_signature_values(v, Uint256::from_bytes(&sig[..32]), Uint256::from_bytes(&sig[32..64])))
+}
+
+fn typed_envelope<T: Encodable>(tx_type: u8, tx: &T) -> Vec<u8> {
+    let mut out = vec![tx_type];
+    tx.rlp_append(&mut out);
+    out
+}
+
+// is_protected_v reports whether a legacy V value carries an EIP-155 chain id:
+// unprotected transactions use 27 or 28.
+pub fn is_protected_v(v: &Uint256) -> bool {
+    if v.bits() <= 8 {
+        let v = v.low_u64();
+        return v != 27 && v != 28 && v != 1 && v != 0;
+    }
+    // anything not 27 or 28 is considered protected
+    true
+}
+
+// derive_chain_id derives the chain id from the given legacy V value
+// (v = chain_id * 2 + 35 + y_parity).
+pub fn derive_chain_id(v: &Uint256) -> Uint256 {
+    if v.bits() <= 64 {
+        let v = v.low_u64();
+        if v == 27 || v == 28 {
+            return Uint256::default();
+        }
+        return Uint256::from_uint64(v.saturating_sub(35) / 2);
+    }
+    let v = v.checked_sub(&Uint256::from_uint64(35)).unwrap_or_default();
+    v.rsh(1)
+}
+
+impl Encodable for Transaction {
+    // Legacy transactions are embedded as a list, typed transactions as a
+    // string holding the envelope.
+    fn rlp_append(&self, out: &mut Vec<u8>) {
+        match &self.inner {
+            TxData::Legacy(tx) => tx.rlp_append(out),
+            _ => rlp::append_bytes(&self.marshal_binary(), out),
+        }
+    }
+}
+
+impl Decodable for Transaction {
+    fn decode(rlp: &Rlp) -> Result<Self, DecoderError> {
+        if rlp.is_list() {
+            return Ok(Transaction::new(TxData::Legacy(rlp.as_val()?)));
+        }
+        let envelope = rlp.data()?;
+        if envelope.first().is_none_or(|tx_type| *tx_type > 0x7f) {
+            return Err(DecoderError::Custom("typed transaction too short"));
+        }
+        Transaction::unmarshal_binary(envelope).map_err(|err| match err {
+            TxError::Rlp(err) => err,
+            _ => DecoderError::Custom("transaction type not supported"),
+        })
+    }
+}
+
+// TransactionJson is the JSON representation of a transaction, with the
+// union of the fields of all transaction types.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TransactionJson {
+    #[serde(rename = "type", with = "hexutil::quantity")]
+    tx_type: u64,
+    #[serde(with = "hexutil::big::opt", default, skip_serializing_if = "Option::is_none")]
+    chain_id: Option<Uint256>,
+    #[serde(with = "hexutil::quantity")]
+    nonce: u64,
+    #[serde(with = "hexutil::address::opt", default)]
+    to: Option<common::Address>,
+    #[serde(with = "hexutil::quantity")]
+    gas: u64,
+    #[serde(with = "hexutil::big::opt", default, skip_serializing_if = "Option::is_none")]
+    gas_price: Option<Uint256>,
+    #[serde(with = "hexutil::big::opt", default, skip_serializing_if = "Option::is_none")]
+    max_priority_fee_per_gas: Option<Uint256>,
+    #[serde(with = "hexutil::big::opt", default, skip_serializing_if = "Option::is_none")]
+    max_fee_per_gas: Option<Uint256>,
+    #[serde(with = "hexutil::big::opt", default, skip_serializing_if = "Option::is_none")]
+    max_fee_per_blob_gas: Option<Uint256>,
+    #[serde(with = "hexutil::big")]
+    value: Uint256,
+    #[serde(with = "hexutil::bytes")]
+    input: Vec<u8>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    access_list: Option<AccessList>,
+    #[serde(with = "hexutil::hashes", default, skip_serializing_if = "Vec::is_empty")]
+    blob_versioned_hashes: Vec<common::Hash>,
+    #[serde(with = "hexutil::big")]
+    v: Uint256,
+    #[serde(with = "hexutil::big")]
+    r: Uint256,
+    #[serde(with = "hexutil::big")]
+    s: Uint256,
+    #[serde(with = "hexutil::big::opt", default, skip_serializing_if = "Option::is_none")]
+    y_parity: Option<Uint256>,
+    // Only used for encoding.
+    #[serde(with = "hexutil::hash::opt", default, skip_serializing_if = "Option::is_none")]
+    hash: Option<common::Hash>,
+}
+
+impl Serialize for Transaction {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let (v, r, s) = self.raw_signature_values();
+        let mut enc = TransactionJson {
+            tx_type: self.tx_type() as u64,
+            nonce: self.nonce(),
+            to: self.to(),
+            gas: self.gas(),
+            value: self.value(),
+            input: self.data().to_vec(),
+            v: v.clone(),
+            r: r.clone(),
+            s: s.clone(),
+            hash: Some(self.hash()),
+            ..Default::default()
+        };
+        match &self.inner {
+            TxData::Legacy(tx) => {
+                enc.gas_price = Some(tx.gas_price.clone());
+                if self.protected() {
+                    enc.chain_id = Some(self.chain_id());
+                }
+            }
+            TxData::AccessList(tx) => {
+                enc.chain_id = Some(tx.chain_id.clone());
+                enc.gas_price = Some(tx.gas_price.clone());
+                enc.access_list = Some(tx.access_list.clone());
+                enc.y_parity = Some(tx.v.clone());
+            }
+            TxData::DynamicFee(tx) => {
+                enc.chain_id = Some(tx.chain_id.clone());
+                enc.max_priority_fee_per_gas = Some(tx.gas_tip_cap.clone());
+                enc.max_fee_per_gas = Some(tx.gas_fee_cap.clone());
+                enc.access_list = Some(tx.access_list.clone());
+                enc.y_parity = Some(tx.v.clone());
+            }
+            TxData::Blob(tx) => {
+                enc.chain_id = Some(tx.chain_id.clone());
+                enc.max_priority_fee_per_gas = Some(tx.gas_tip_cap.clone());
+                enc.max_fee_per_gas = Some(tx.gas_fee_cap.clone());
+                enc.max_fee_per_blob_gas = Some(tx.blob_fee_cap.clone());
+                enc.access_list = Some(tx.access_list.clone());
+                enc.blob_versioned_hashes = tx.blob_hashes.clone();
+                enc.y_parity = Some(tx.v.clone());
+            }
+        }
+        enc.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Transaction {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let dec = TransactionJson::deserialize(deserializer)?;
+        let missing = |field: &'static str| D::Error::missing_field(field);
+        let inner = match dec.tx_type as u8 {
+            LEGACY_TX_TYPE => TxData::Legacy(LegacyTx {
+                nonce: dec.nonce,
+                gas_price: dec.gas_price.ok_or_else(|| missing("gasPrice"))?,
+                gas: dec.gas,
+                to: dec.to,
+                value: dec.value,
+                data: dec.input,
+                v: dec.v,
+                r: dec.r,
+                s: dec.s,
+            }),
+            ACCESS_LIST_TX_TYPE => TxData::AccessList(AccessListTx {
+                chain_id: dec.chain_id.ok_or_else(|| missing("chainId"))?,
+                nonce: dec.nonce,
+                gas_price: dec.gas_price.ok_or_else(|| missing("gasPrice"))?,
+                gas: dec.gas,
+                to: dec.to,
+                value: dec.value,
+                data: dec.input,
+                access_list: dec.access_list.ok_or_else(|| missing("accessList"))?,
+                v: dec.v,
+                r: dec.r,
+                s: dec.s,
+            }),
+            DYNAMIC_FEE_TX_TYPE => TxData::DynamicFee(DynamicFeeTx {
+                chain_id: dec.chain_id.ok_or_else(|| missing("chainId"))?,
+                nonce: dec.nonce,
+                gas_tip_cap: dec.max_priority_fee_per_gas.ok_or_else(|| missing("maxPriorityFeePerGas"))?,
+                gas_fee_cap: dec.max_fee_per_gas.ok_or_else(|| missing("maxFeePerGas"))?,
+                gas: dec.gas,
+                to: dec.to,
+                value: dec.value,
+                data: dec.input,
+                access_list: dec.access_list.ok_or_else(|| missing("accessList"))?,
+                v: dec.v,
+                r: dec.r,
+                s: dec.s,
+            }),
+            BLOB_TX_TYPE => TxData::Blob(BlobTx {
+                chain_id: dec.chain_id.ok_or_else(|| missing("chainId"))?,
+                nonce: dec.nonce,
+                gas_tip_cap: dec.max_priority_fee_per_gas.ok_or_else(|| missing("maxPriorityFeePerGas"))?,
+                gas_fee_cap: dec.max_fee_per_gas.ok_or_else(|| missing("maxFeePerGas"))?,
+                gas: dec.gas,
+                to: dec.to.ok_or_else(|| missing("to"))?,
+                value: dec.value,
+                data: dec.input,
+                access_list: dec.access_list.ok_or_else(|| missing("accessList"))?,
+                blob_fee_cap: dec.max_fee_per_blob_gas.ok_or_else(|| missing("maxFeePerBlobGas"))?,
+                blob_hashes: dec.blob_versioned_hashes,
+                v: dec.v,
+                r: dec.r,
+                s: dec.s,
+            }),
+            _ => return Err(D::Error::custom("transaction type not supported")),
+        };
+        Ok(Transaction::new(inner))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hash(s: &str) -> common::Hash {
+        common::Hash(hexutil::decode_fixed::<32>(s).unwrap())
+    }
+
+    fn address(s: &str) -> common::Address {
+        common::Address(hexutil::decode_fixed::<20>(s).unwrap())
+    }
+
+    // decode_raw decodes a signed raw transaction and checks that it encodes
+    // back to the same bytes.
+    fn decode_raw(raw: &str) -> Transaction {
+        let blob = hexutil::decode(raw).unwrap();
+        let tx = Transaction::unmarshal_binary(&blob).unwrap();
+        assert_eq!(tx.marshal_binary(), blob);
+        assert_eq!(tx.hash(), crypto::keccak256_hash(&blob));
+        tx
+    }
+
+    // The example from EIP-155, signed with the private key 0x4646..46.
+    const EIP155_TX: &str = "0xf86c098504a817c800825208943535353535353535353535353535353535353535880de0b6b3a76400008025a028ef61340bd939bc2195fe537567866003e1a15d3c71ff63e1590620aa636276a067cbe9d8997f761aecb703304b3800ccf555c9f3dc64214b297fb1966a3b6d83";
+
+    #[test]
+    fn test_legacy_eip155() {
+        let tx = decode_raw(EIP155_TX);
+        assert_eq!(tx.tx_type(), LEGACY_TX_TYPE);
+        assert!(tx.protected());
+        assert_eq!(tx.chain_id(), Uint256::from_uint64(1));
+        assert_eq!(tx.nonce(), 9);
+        assert_eq!(tx.gas_price(), Uint256::from_uint64(20_000_000_000));
+        assert_eq!(tx.value(), Uint256::from_uint64(1_000_000_000_000_000_000));
+        assert_eq!(
+            tx.signing_hash(Some(&Uint256::from_uint64(1))),
+            hash("0xdaf5a779ae972f972197303d7b574746c7ef83eadac0f2791ad23db92e4c8e53")
+        );
+        let from = address("0x9d8a62f656a8d1615c1294fd71e9cfb3e4855a4f");
+        assert_eq!(sender(&tx, &Uint256::from_uint64(1)).unwrap(), from);
+        assert_eq!(sender(&tx, &Uint256::from_uint64(2)), Err(TxError::InvalidChainId));
+
+        // Signing is deterministic (RFC 6979), so re-signing the unsigned
+        // transaction must reproduce the published encoding.
+        let key = crypto::to_ecdsa(&[0x46; 32]).unwrap();
+        assert_eq!(key.address(), from);
+        let unsigned = tx.with_signature_values(Uint256::default(), Uint256::default(), Uint256::default());
+        let signed = sign_tx(&unsigned, &Uint256::from_uint64(1), &key).unwrap();
+        assert_eq!(signed.marshal_binary(), hexutil::decode(EIP155_TX).unwrap());
+    }
+
+    // Taken from go-ethereum's rightvrsTx, an unprotected Homestead transaction.
+    #[test]
+    fn test_legacy_homestead() {
+        let tx = decode_raw("0xf86103018207d094b94f5374fce5edbc8e2a8697c15331677e6ebf0b0a8255441ca098ff921201554726367d2be8c804a7ff89ccf285ebc57dff8ae4c44b9c19ac4aa08887321be575c8095f789dd4c743dfe42c1820f9231f98a962b210e3ac2452a3");
+        assert!(!tx.protected());
+        assert_eq!(tx.data(), &[0x55, 0x44]);
+        assert_eq!(tx.signing_hash(None), hash("0xfe7a79529ed5f7c3375d06b26b186a8644e0e16c373d7a12be41c62d6042b77a"));
+        // The signature has an s value in the upper half of the curve order,
+        // which Homestead rejects as malleable.
+        assert_eq!(sender(&tx, &Uint256::from_uint64(1)), Err(TxError::InvalidSig));
+
+        // Flipping s to the lower half (and the parity with it) yields a
+        // valid signature by the same sender, on any chain.
+        let (v, r, s) = tx.raw_signature_values();
+        let n = Uint256::from_hex_str("fffffffffffffffffffffffffffffffebaaedce6af48a03bbfd25e8cd0364141").unwrap();
+        let flipped = tx.with_signature_values(
+            Uint256::from_uint64(55 - v.low_u64()),
+            r.clone(),
+            n.checked_sub(s).unwrap(),
+        );
+        let mut sig = [0u8; crypto::SIGNATURE_LENGTH];
+        sig[..32].copy_from_slice(&r.to_bytes32());
+        sig[32..64].copy_from_slice(&s.to_bytes32());
+        sig[crypto::RECOVERY_ID_OFFSET] = (v.low_u64() - 27) as u8;
+        let pub_key = crypto::ecrecover(tx.signing_hash(None).as_bytes(), &sig).unwrap();
+        let from = crypto::pubkey_to_address(&pub_key);
+        assert_eq!(sender(&flipped, &Uint256::from_uint64(1)).unwrap(), from);
+        assert_eq!(sender(&flipped, &Uint256::from_uint64(1337)).unwrap(), from);
+    }
+
+    // Taken from go-ethereum's signedEip2718Tx.
+    #[test]
+    fn test_access_list_tx() {
+        let tx = decode_raw("0x01f8630103018261a894b94f5374fce5edbc8e2a8697c15331677e6ebf0b0a825544c001a0c9519f4f2b30335884581971573fadf60c6204f59a911df35ee8a540456b2660a032f1e8e2c5dd761f9e4f88f41c8310aeaba26a8bfcdacfedfa12ec3862d37521");
+        assert_eq!(tx.tx_type(), ACCESS_LIST_TX_TYPE);
+        assert_eq!(tx.chain_id(), Uint256::from_uint64(1));
+        assert_eq!(tx.nonce(), 3);
+        assert_eq!(tx.gas(), 25000);
+        assert_eq!(tx.to(), Some(address("0xb94f5374fce5edbc8e2a8697c15331677e6ebf0b")));
+        assert_eq!(
+            tx.signing_hash(Some(&Uint256::from_uint64(1))),
+            hash("0x49b486f0ec0a60dfbbca2d30cb07c9e8ffb2a2ff41f29a1ab6737475f6ff69f3")
+        );
+        assert!(sender(&tx, &Uint256::from_uint64(1)).is_ok());
+        assert_eq!(sender(&tx, &Uint256::from_uint64(5)), Err(TxError::InvalidChainId));
+    }
+
+    fn sign_and_check(tx: TxData) -> Transaction {
+        let chain_id = Uint256::from_uint64(1);
+        let key = crypto::to_ecdsa(&[0x46; 32]).unwrap();
+        let signed = sign_tx(&Transaction::new(tx), &chain_id, &key).unwrap();
+        let tx = decode_raw(&hexutil::encode(&signed.marshal_binary()));
+        assert_eq!(tx, signed);
+        assert_eq!(tx.hash(), signed.hash());
+        assert_eq!(
+            sender(&tx, &chain_id).unwrap(),
+            address("0x9d8a62f656a8d1615c1294fd71e9cfb3e4855a4f")
+        );
+        assert_eq!(sender(&tx, &Uint256::from_uint64(5)), Err(TxError::InvalidChainId));
+        tx
+    }
+
+    #[test]
+    fn test_dynamic_fee_tx() {
+        let tx = sign_and_check(TxData::DynamicFee(DynamicFeeTx {
+            chain_id: Uint256::from_uint64(1),
+            nonce: 2,
+            gas_tip_cap: Uint256::from_uint64(1_000_000_000),
+            gas_fee_cap: Uint256::from_uint64(30_000_000_000),
+            gas: 21000,
+            to: Some(address("0x3535353535353535353535353535353535353535")),
+            value: Uint256::from_uint64(1),
+            ..Default::default()
+        }));
+        assert_eq!(tx.tx_type(), DYNAMIC_FEE_TX_TYPE);
+        assert_eq!(tx.marshal_binary()[0], DYNAMIC_FEE_TX_TYPE);
+        assert_eq!(tx.gas_tip_cap(), Uint256::from_uint64(1_000_000_000));
+        assert_eq!(tx.gas_fee_cap(), Uint256::from_uint64(30_000_000_000));
+    }
+
+    #[test]
+    fn test_blob_tx() {
+        let blob_hash = hash("0x01a915e4d060149eb4365960e6a7a45f334393093061116b197e3240065ff2d8");
+        let tx = sign_and_check(TxData::Blob(BlobTx {
+            chain_id: Uint256::from_uint64(1),
+            nonce: 7,
+            gas_tip_cap: Uint256::from_uint64(1),
+            gas_fee_cap: Uint256::from_uint64(10),
+            gas: 21000,
+            to: address("0x3535353535353535353535353535353535353535"),
+            blob_fee_cap: Uint256::from_uint64(100),
+            blob_hashes: vec![blob_hash, blob_hash],
+            ..Default::default()
+        }));
+        assert_eq!(tx.tx_type(), BLOB_TX_TYPE);
+        assert_eq!(tx.blob_hashes(), &[blob_hash, blob_hash]);
+        assert_eq!(tx.blob_gas_fee_cap(), Some(Uint256::from_uint64(100)));
+        assert_eq!(tx.blob_gas(), 2 * 131072);
+
+        // A truncated encoding must be rejected.
+        let mut blob = tx.marshal_binary();
+        blob.truncate(blob.len() - 1);
+        assert!(Transaction::unmarshal_binary(&blob).is_err());
+    }
+}
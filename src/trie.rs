@@ -0,0 +1,865 @@
+// Package trie implements Merkle Patricia Tries.
+//
+// Keys are split into nibbles and stored along a path of branch (16 children plus
+// an optional value), extension (shared key segment pointing to a single child)
+// and leaf (remaining key segment plus value) nodes. A node is referenced by the
+// keccak hash of its RLP encoding, unless the encoding is shorter than 32 bytes
+// in which case it is embedded in its parent.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, RwLock};
+
+use crate::crypto;
+use crate::ethdb::{Batch, KeyValueStore};
+use crate::rawdb;
+use crate::rlp::{self, Rlp};
+
+// Leaf is a key-value pair stored in the trie.
+pub type Leaf = (Vec<u8>, Vec<u8>);
+
+// EMPTY_ROOT_HASH is the known root hash of an empty trie, keccak256(rlp("")).
+pub const EMPTY_ROOT_HASH: common::Hash = common::Hash([
+    0x56, 0xe8, 0x1f, 0x17, 0x1b, 0xcc, 0x55, 0xa6, 0xff, 0x83, 0x45, 0xe6, 0x92, 0xc0, 0xf8, 0x6e,
+    0x5b, 0x48, 0xe0, 0x1b, 0x99, 0x6c, 0xad, 0xc0, 0x01, 0x62, 0x2f, 0xb5, 0xe3, 0x63, 0xb4, 0x21,
+]);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TrieError {
+    // MissingNode is returned by the trie functions when a node referenced by
+    // hash is not present in the database.
+    MissingNode(common::Hash),
+    // InvalidNode is returned when a stored node can not be decoded.
+    InvalidNode(rlp::DecoderError),
+    // Database is returned when the backing store fails.
+    Database(String),
+}
+
+impl From<std::io::Error> for TrieError {
+    fn from(err: std::io::Error) -> Self {
+        TrieError::Database(err.to_string())
+    }
+}
+
+impl From<rlp::DecoderError> for TrieError {
+    fn from(err: rlp::DecoderError) -> Self {
+        TrieError::InvalidNode(err)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Default)]
+pub enum Node {
+    #[default]
+    Empty,
+    Leaf {
+        key: Vec<u8>, // remaining key nibbles
+        value: Vec<u8>,
+    },
+    Extension {
+        key: Vec<u8>, // shared key nibbles
+        child: Box<Node>,
+    },
+    Branch {
+        children: Box<[Node; 16]>,
+        value: Option<Vec<u8>>,
+    },
+    // Hash is a reference to a node that is stored in the database and has not
+    // been loaded yet.
+    Hash(common::Hash),
+}
+
+fn empty_children() -> Box<[Node; 16]> {
+    Box::default()
+}
+
+
+// Database is an intermediate write layer between the trie data structures and
+// the disk database. Nodes written by a trie commit are held in memory until
+// they are flushed into a batch for the disk database. Clones share the same
+// underlying nodes.
+#[derive(Clone, Default)]
+pub struct Database {
+    dirty: Arc<RwLock<HashMap<common::Hash, Vec<u8>>>>,
+    disk: Option<Arc<dyn KeyValueStore>>,
+}
+
+impl fmt::Debug for Database {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Database")
+            .field("dirty", &self.dirty_len())
+            .field("disk", &self.disk.is_some())
+            .finish()
+    }
+}
+
+impl Database {
+    // new creates an in-memory trie database.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    // with_disk creates a trie database that falls back to disk for nodes
+    // that are not held in memory.
+    pub fn with_disk(disk: Arc<dyn KeyValueStore>) -> Self {
+        Database {
+            dirty: Default::default(),
+            disk: Some(disk),
+        }
+    }
+
+    pub fn disk(&self) -> Option<&Arc<dyn KeyValueStore>> {
+        self.disk.as_ref()
+    }
+
+    // node retrieves an encoded trie node from memory, or from disk if it was
+    // already flushed.
+    pub fn node(&self, hash: &common::Hash) -> Option<Vec<u8>> {
+        if let Some(blob) = self.dirty.read().unwrap().get(hash) {
+            return Some(blob.clone());
+        }
+        let disk = self.disk.as_ref()?;
+        rawdb::read_trie_node(disk.as_ref(), hash).ok().flatten()
+    }
+
+    pub fn insert(&self, hash: common::Hash, blob: Vec<u8>) {
+        self.dirty.write().unwrap().insert(hash, blob);
+    }
+
+    // dirty_len returns the number of nodes not yet flushed to disk.
+    pub fn dirty_len(&self) -> usize {
+        self.dirty.read().unwrap().len()
+    }
+
+    // write_dirty adds all in-memory nodes to batch. Call clear_dirty once
+    // the batch has been written.
+    pub fn write_dirty(&self, batch: &mut Batch) {
+        for (hash, blob) in self.dirty.read().unwrap().iter() {
+            rawdb::write_trie_node(batch, hash, blob);
+        }
+    }
+
+    pub fn clear_dirty(&self) {
+        self.dirty.write().unwrap().clear();
+    }
+}
+
+// key_to_nibbles splits every key byte into its high and low nibble.
+pub fn key_to_nibbles(key: &[u8]) -> Vec<u8> {
+    let mut nibbles = Vec::with_capacity(key.len() * 2);
+    for b in key {
+        nibbles.push(b >> 4);
+        nibbles.push(b & 0x0f);
+    }
+    nibbles
+}
+
+// nibbles_to_key joins pairs of nibbles back into key bytes.
+fn nibbles_to_key(nibbles: &[u8]) -> Vec<u8> {
+    nibbles.chunks(2).map(|pair| pair[0] << 4 | pair.get(1).unwrap_or(&0)).collect()
+}
+
+// hex_prefix_encode compacts a nibble path into bytes, storing the node type
+// and the parity of the path in the high nibble of the first byte.
+pub fn hex_prefix_encode(nibbles: &[u8], is_leaf: bool) -> Vec<u8> {
+    let mut flag = if is_leaf { 2u8 } else { 0u8 };
+    let odd = nibbles.len() % 2 == 1;
+    let mut out = Vec::with_capacity(nibbles.len() / 2 + 1);
+    let rest = if odd {
+        flag += 1;
+        out.push(flag << 4 | nibbles[0]);
+        &nibbles[1..]
+    } else {
+        out.push(flag << 4);
+        nibbles
+    };
+    for pair in rest.chunks(2) {
+        out.push(pair[0] << 4 | pair[1]);
+    }
+    out
+}
+
+// hex_prefix_decode reverses hex_prefix_encode, returning the nibble path and
+// whether it terminates in a leaf.
+pub fn hex_prefix_decode(compact: &[u8]) -> Result<(Vec<u8>, bool), TrieError> {
+    if compact.is_empty() {
+        return Err(TrieError::InvalidNode(rlp::DecoderError::RlpIsTooShort));
+    }
+    let flag = compact[0] >> 4;
+    if flag > 3 {
+        return Err(TrieError::InvalidNode(rlp::DecoderError::Custom("invalid hex prefix flag")));
+    }
+    let is_leaf = flag & 2 != 0;
+    let mut nibbles = Vec::with_capacity(compact.len() * 2);
+    if flag & 1 != 0 {
+        nibbles.push(compact[0] & 0x0f);
+    }
+    nibbles.extend(key_to_nibbles(&compact[1..]));
+    Ok((nibbles, is_leaf))
+}
+
+fn prefix_len(a: &[u8], b: &[u8]) -> usize {
+    a.iter().zip(b.iter()).take_while(|(x, y)| x == y).count()
+}
+
+// encode_node returns the RLP encoding of a node. Hash nodes can't be encoded
+// on their own and are only valid as child references.
+pub fn encode_node(node: &Node) -> Vec<u8> {
+    let mut payload = Vec::new();
+    match node {
+        Node::Empty => return vec![rlp::EMPTY_STRING],
+        Node::Leaf { key, value } => {
+            rlp::append_bytes(&hex_prefix_encode(key, true), &mut payload);
+            rlp::append_bytes(value, &mut payload);
+        }
+        Node::Extension { key, child } => {
+            rlp::append_bytes(&hex_prefix_encode(key, false), &mut payload);
+            append_child_ref(child, &mut payload);
+        }
+        Node::Branch { children, value } => {
+            for child in children.iter() {
+                append_child_ref(child, &mut payload);
+            }
+            match value {
+                Some(value) => rlp::append_bytes(value, &mut payload),
+                None => payload.push(rlp::EMPTY_STRING),
+            }
+        }
+        Node::Hash(hash) => return rlp::encode(hash),
+    }
+    let mut out = Vec::with_capacity(payload.len() + 3);
+    rlp::append_list_payload(&payload, &mut out);
+    out
+}
+
+// append_child_ref appends the reference to a child node: the node itself if
+// its encoding is shorter than 32 bytes, its hash otherwise.
+fn append_child_ref(child: &Node, out: &mut Vec<u8>) {
+    match child {
+        Node::Empty => out.push(rlp::EMPTY_STRING),
+        Node::Hash(hash) => rlp::append_bytes(hash.as_bytes(), out),
+        _ => {
+            let enc = encode_node(child);
+            if enc.len() < 32 {
+                out.extend_from_slice(&enc);
+            } else {
+                rlp::append_bytes(crypto::keccak256_hash(&enc).as_bytes(), out);
+            }
+        }
+    }
+}
+
+// decode_node parses the RLP encoding of a node as stored in the database.
+pub fn decode_node(blob: &[u8]) -> Result<Node, TrieError> {
+    decode_node_rlp(&Rlp::new(blob))
+}
+
+fn decode_node_rlp(rlp: &Rlp) -> Result<Node, TrieError> {
+    let items = rlp.items()?;
+    match items.len() {
+        2 => {
+            let (key, is_leaf) = hex_prefix_decode(items[0].data()?)?;
+            if is_leaf {
+                Ok(Node::Leaf {
+                    key,
+                    value: items[1].data()?.to_vec(),
+                })
+            } else {
+                Ok(Node::Extension {
+                    key,
+                    child: Box::new(decode_ref(&items[1])?),
+                })
+            }
+        }
+        17 => {
+            let mut children = empty_children();
+            for (i, item) in items[..16].iter().enumerate() {
+                children[i] = decode_ref(item)?;
+            }
+            let value = items[16].data()?;
+            Ok(Node::Branch {
+                children,
+                value: if value.is_empty() { None } else { Some(value.to_vec()) },
+            })
+        }
+        _ => Err(TrieError::InvalidNode(rlp::DecoderError::RlpIncorrectListLen)),
+    }
+}
+
+fn decode_ref(rlp: &Rlp) -> Result<Node, TrieError> {
+    if rlp.is_list() {
+        // embedded node
+        return decode_node_rlp(rlp);
+    }
+    let data = rlp.data()?;
+    match data.len() {
+        0 => Ok(Node::Empty),
+        32 => {
+            let mut hash = [0u8; 32];
+            hash.copy_from_slice(data);
+            Ok(Node::Hash(common::Hash(hash)))
+        }
+        _ => Err(TrieError::InvalidNode(rlp::DecoderError::Custom("invalid node reference"))),
+    }
+}
+
+// Trie is a Merkle Patricia Trie. Use new to create a trie that sits on top
+// of a database.
+//
+// Trie is not safe for concurrent use.
+#[derive(Debug, Clone, Default)]
+pub struct Trie {
+    root: Node,
+    db: Database,
+}
+
+impl Trie {
+    // new creates an empty trie on top of the given database.
+    pub fn new(db: Database) -> Self {
+        Trie { root: Node::Empty, db }
+    }
+
+    // from_root opens the trie with the given root hash, resolving nodes from
+    // db lazily.
+    pub fn from_root(root: common::Hash, db: Database) -> Result<Self, TrieError> {
+        if root == EMPTY_ROOT_HASH || root == common::Hash::default() {
+            return Ok(Trie::new(db));
+        }
+        if db.node(&root).is_none() {
+            return Err(TrieError::MissingNode(root));
+        }
+        Ok(Trie { root: Node::Hash(root), db })
+    }
+
+    pub fn database(&self) -> &Database {
+        &self.db
+    }
+
+    pub fn into_database(self) -> Database {
+        self.db
+    }
+
+    fn resolve(&self, hash: &common::Hash) -> Result<Node, TrieError> {
+        match self.db.node(hash) {
+            Some(blob) => decode_node(&blob),
+            None => Err(TrieError::MissingNode(*hash)),
+        }
+    }
+
+    // get returns the value for key stored in the trie.
+    pub fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>, TrieError> {
+        let path = key_to_nibbles(key);
+        // The walk borrows the in-memory nodes, only the last node loaded
+        // from the database is owned.
+        let mut resolved: Node;
+        let mut node = &self.root;
+        let mut pos = 0;
+        loop {
+            match node {
+                Node::Empty => return Ok(None),
+                Node::Leaf { key, value } => {
+                    return Ok(if key[..] == path[pos..] { Some(value.clone()) } else { None });
+                }
+                Node::Extension { key, child } => {
+                    if path.len() - pos < key.len() || key[..] != path[pos..pos + key.len()] {
+                        return Ok(None);
+                    }
+                    pos += key.len();
+                    node = child;
+                }
+                Node::Branch { children, value } => {
+                    if pos == path.len() {
+                        return Ok(value.clone());
+                    }
+                    node = &children[path[pos] as usize];
+                    pos += 1;
+                }
+                Node::Hash(hash) => {
+                    resolved = self.resolve(hash)?;
+                    node = &resolved;
+                }
+            }
+        }
+    }
+
+    // prove constructs a merkle proof for key. The result contains the encoded
+    // nodes on the path to the value at key, starting with the root node. Nodes
+    // embedded in their parent are not listed separately.
+    //
+    // If the trie does not contain a value for key, the returned proof contains
+    // all nodes of the longest existing prefix of the key, which proves the
+    // absence of the key.
+    pub fn prove(&self, key: &[u8]) -> Result<Vec<Vec<u8>>, TrieError> {
+        let path = key_to_nibbles(key);
+        let mut proof = Vec::new();
+        let mut resolved: Node;
+        let mut node = &self.root;
+        let mut pos = 0;
+        let mut is_root = true;
+        loop {
+            if let Node::Hash(hash) = node {
+                resolved = self.resolve(hash)?;
+                node = &resolved;
+            }
+            if *node == Node::Empty {
+                return Ok(proof);
+            }
+            let enc = encode_node(node);
+            if is_root || enc.len() >= 32 {
+                proof.push(enc);
+            }
+            is_root = false;
+            match node {
+                Node::Extension { key, child } => {
+                    if path.len() - pos < key.len() || key[..] != path[pos..pos + key.len()] {
+                        return Ok(proof);
+                    }
+                    pos += key.len();
+                    node = child;
+                }
+                Node::Branch { children, .. } => {
+                    if pos == path.len() {
+                        return Ok(proof);
+                    }
+                    node = &children[path[pos] as usize];
+                    pos += 1;
+                }
+                _ => return Ok(proof),
+            }
+        }
+    }
+
+    // insert associates key with value in the trie. If value has length zero, any
+    // existing value is deleted from the trie.
+    pub fn insert(&mut self, key: &[u8], value: &[u8]) -> Result<(), TrieError> {
+        if value.is_empty() {
+            return self.delete(key);
+        }
+        let root = std::mem::take(&mut self.root);
+        self.root = self.insert_at(root, &key_to_nibbles(key), value.to_vec())?;
+        Ok(())
+    }
+
+    fn insert_at(&self, node: Node, path: &[u8], value: Vec<u8>) -> Result<Node, TrieError> {
+        match node {
+            Node::Empty => Ok(Node::Leaf { key: path.to_vec(), value }),
+            Node::Leaf { key, value: old } => {
+                let m = prefix_len(&key, path);
+                // Replace the value of an existing key.
+                if m == key.len() && m == path.len() {
+                    return Ok(Node::Leaf { key, value });
+                }
+                // Otherwise branch out at the index where they differ.
+                let mut children = empty_children();
+                let mut branch_value = None;
+                if m == key.len() {
+                    branch_value = Some(old);
+                } else {
+                    children[key[m] as usize] = Node::Leaf { key: key[m + 1..].to_vec(), value: old };
+                }
+                if m == path.len() {
+                    branch_value = Some(value);
+                } else {
+                    children[path[m] as usize] = Node::Leaf { key: path[m + 1..].to_vec(), value };
+                }
+                Ok(wrap_extension(&path[..m], Node::Branch { children, value: branch_value }))
+            }
+            Node::Extension { key, child } => {
+                let m = prefix_len(&key, path);
+                if m == key.len() {
+                    let child = self.insert_at(*child, &path[m..], value)?;
+                    return Ok(Node::Extension { key, child: Box::new(child) });
+                }
+                // Otherwise branch out at the index where they differ.
+                let mut children = empty_children();
+                let mut branch_value = None;
+                children[key[m] as usize] = wrap_extension(&key[m + 1..], *child);
+                if m == path.len() {
+                    branch_value = Some(value);
+                } else {
+                    children[path[m] as usize] = Node::Leaf { key: path[m + 1..].to_vec(), value };
+                }
+                Ok(wrap_extension(&key[..m], Node::Branch { children, value: branch_value }))
+            }
+            Node::Branch { mut children, value: old } => {
+                if path.is_empty() {
+                    return Ok(Node::Branch { children, value: Some(value) });
+                }
+                let idx = path[0] as usize;
+                let child = std::mem::take(&mut children[idx]);
+                children[idx] = self.insert_at(child, &path[1..], value)?;
+                Ok(Node::Branch { children, value: old })
+            }
+            Node::Hash(hash) => {
+                let node = self.resolve(&hash)?;
+                self.insert_at(node, path, value)
+            }
+        }
+    }
+
+    // delete removes any existing value for key from the trie.
+    pub fn delete(&mut self, key: &[u8]) -> Result<(), TrieError> {
+        let root = std::mem::take(&mut self.root);
+        self.root = self.delete_at(root, &key_to_nibbles(key))?;
+        Ok(())
+    }
+
+    fn delete_at(&self, node: Node, path: &[u8]) -> Result<Node, TrieError> {
+        match node {
+            Node::Empty => Ok(Node::Empty),
+            Node::Leaf { key, value } => {
+                if key[..] == path[..] {
+                    Ok(Node::Empty)
+                } else {
+                    Ok(Node::Leaf { key, value })
+                }
+            }
+            Node::Extension { key, child } => {
+                if path.len() < key.len() || key[..] != path[..key.len()] {
+                    return Ok(Node::Extension { key, child });
+                }
+                let child = self.delete_at(*child, &path[key.len()..])?;
+                // The child might have been reduced to a leaf or extension,
+                // in which case the paths are merged.
+                Ok(match child {
+                    Node::Empty => Node::Empty,
+                    Node::Leaf { key: rest, value } => Node::Leaf { key: concat(&key, &rest), value },
+                    Node::Extension { key: rest, child } => Node::Extension { key: concat(&key, &rest), child },
+                    child => Node::Extension { key, child: Box::new(child) },
+                })
+            }
+            Node::Branch { mut children, mut value } => {
+                if path.is_empty() {
+                    value = None;
+                } else {
+                    let idx = path[0] as usize;
+                    let child = std::mem::take(&mut children[idx]);
+                    children[idx] = self.delete_at(child, &path[1..])?;
+                }
+                self.collapse_branch(children, value)
+            }
+            Node::Hash(hash) => {
+                let node = self.resolve(&hash)?;
+                self.delete_at(node, path)
+            }
+        }
+    }
+
+    // collapse_branch reduces a branch that is left with fewer than two entries
+    // into a leaf or extension.
+    fn collapse_branch(&self, mut children: Box<[Node; 16]>, value: Option<Vec<u8>>) -> Result<Node, TrieError> {
+        let used: Vec<usize> = (0..16).filter(|i| children[*i] != Node::Empty).collect();
+        match (used.len(), value) {
+            (0, None) => Ok(Node::Empty),
+            (0, Some(value)) => Ok(Node::Leaf { key: Vec::new(), value }),
+            (1, None) => {
+                let idx = used[0];
+                let mut child = std::mem::take(&mut children[idx]);
+                if let Node::Hash(hash) = child {
+                    child = self.resolve(&hash)?;
+                }
+                Ok(match child {
+                    Node::Leaf { key, value } => Node::Leaf { key: concat(&[idx as u8], &key), value },
+                    Node::Extension { key, child } => Node::Extension { key: concat(&[idx as u8], &key), child },
+                    child => Node::Extension { key: vec![idx as u8], child: Box::new(child) },
+                })
+            }
+            (_, value) => Ok(Node::Branch { children, value }),
+        }
+    }
+
+    // root_hash returns the root hash of the trie. It does not write to the
+    // database and can be used even if the trie doesn't have one.
+    pub fn root_hash(&self) -> common::Hash {
+        match &self.root {
+            Node::Empty => EMPTY_ROOT_HASH,
+            Node::Hash(hash) => *hash,
+            node => crypto::keccak256_hash(&encode_node(node)),
+        }
+    }
+
+    // leaves returns all key-value pairs stored in the trie, ordered by key.
+    pub fn leaves(&self) -> Result<Vec<Leaf>, TrieError> {
+        let mut leaves = Vec::new();
+        self.collect_leaves(self.root.clone(), Vec::new(), &mut leaves)?;
+        Ok(leaves)
+    }
+
+    fn collect_leaves(&self, node: Node, path: Vec<u8>, leaves: &mut Vec<Leaf>) -> Result<(), TrieError> {
+        match node {
+            Node::Empty => {}
+            Node::Leaf { key, value } => leaves.push((nibbles_to_key(&concat(&path, &key)), value)),
+            Node::Extension { key, child } => self.collect_leaves(*child, concat(&path, &key), leaves)?,
+            Node::Branch { children, value } => {
+                if let Some(value) = value {
+                    leaves.push((nibbles_to_key(&path), value));
+                }
+                for (i, child) in children.into_iter().enumerate() {
+                    self.collect_leaves(child, concat(&path, &[i as u8]), leaves)?;
+                }
+            }
+            Node::Hash(hash) => {
+                let node = self.resolve(&hash)?;
+                self.collect_leaves(node, path, leaves)?;
+            }
+        }
+        Ok(())
+    }
+
+    // commit writes all nodes to the trie's database and collapses the in-memory
+    // trie to its root hash. Nodes are stored under the hash of their encoding;
+    // embedded nodes are written as part of their parent.
+    pub fn commit(&mut self) -> common::Hash {
+        let root = std::mem::take(&mut self.root);
+        if root == Node::Empty {
+            return EMPTY_ROOT_HASH;
+        }
+        let root = self.store(root, true);
+        let hash = match &root {
+            Node::Hash(hash) => *hash,
+            node => crypto::keccak256_hash(&encode_node(node)),
+        };
+        self.root = Node::Hash(hash);
+        hash
+    }
+
+    // store commits the children of node and returns the node with its
+    // stored children replaced by hash references.
+    fn store(&mut self, node: Node, force: bool) -> Node {
+        let node = match node {
+            Node::Extension { key, child } => Node::Extension {
+                key,
+                child: Box::new(self.store(*child, false)),
+            },
+            Node::Branch { children, value } => {
+                let mut stored = empty_children();
+                for (i, child) in children.into_iter().enumerate() {
+                    stored[i] = self.store(child, false);
+                }
+                Node::Branch { children: stored, value }
+            }
+            node => node,
+        };
+        match node {
+            Node::Empty | Node::Hash(_) => node,
+            node => {
+                let enc = encode_node(&node);
+                if enc.len() < 32 && !force {
+                    return node;
+                }
+                let hash = crypto::keccak256_hash(&enc);
+                self.db.insert(hash, enc);
+                Node::Hash(hash)
+            }
+        }
+    }
+}
+
+// wrap_extension puts node below an extension with the given key, unless the
+// key is empty.
+fn wrap_extension(key: &[u8], node: Node) -> Node {
+    if key.is_empty() {
+        return node;
+    }
+    match node {
+        Node::Leaf { key: rest, value } => Node::Leaf { key: concat(key, &rest), value },
+        node => Node::Extension { key: key.to_vec(), child: Box::new(node) },
+    }
+}
+
+fn concat(a: &[u8], b: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(a.len() + b.len());
+    out.extend_from_slice(a);
+    out.extend_from_slice(b);
+    out
+}
+
+// verify_proof checks merkle proofs. The given proof must contain the value for
+// key in a trie with the given root hash. verify_proof returns an error if the
+// proof contains invalid trie nodes or the wrong value, and Ok(None) if the
+// proof shows that the key is absent from the trie.
+pub fn verify_proof(root: &common::Hash, key: &[u8], proof: &[Vec<u8>]) -> Result<Option<Vec<u8>>, TrieError> {
+    let nodes: HashMap<common::Hash, &Vec<u8>> = proof
+        .iter()
+        .map(|blob| (crypto::keccak256_hash(blob), blob))
+        .collect();
+    let lookup = |hash: &common::Hash| -> Result<Node, TrieError> {
+        match nodes.get(hash) {
+            Some(blob) => decode_node(blob),
+            None => Err(TrieError::MissingNode(*hash)),
+        }
+    };
+
+    if *root == EMPTY_ROOT_HASH {
+        return Ok(None);
+    }
+    let path = key_to_nibbles(key);
+    let mut node = lookup(root)?;
+    let mut pos = 0;
+    loop {
+        match node {
+            Node::Empty => return Ok(None),
+            Node::Leaf { key, value } => {
+                return Ok(if key[..] == path[pos..] { Some(value) } else { None });
+            }
+            Node::Extension { key, child } => {
+                if path.len() - pos < key.len() || key[..] != path[pos..pos + key.len()] {
+                    return Ok(None);
+                }
+                pos += key.len();
+                node = *child;
+            }
+            Node::Branch { mut children, value } => {
+                if pos == path.len() {
+                    return Ok(value);
+                }
+                node = std::mem::take(&mut children[path[pos] as usize]);
+                pos += 1;
+            }
+            Node::Hash(hash) => node = lookup(&hash)?,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+    use std::collections::BTreeMap;
+
+    // Ordered tests list the updates in sequence, a null value deletes the
+    // key.
+    type OrderedInput = Vec<(String, Option<String>)>;
+
+    #[derive(Deserialize)]
+    struct TrieTest<T> {
+        #[serde(rename = "in")]
+        input: T,
+        root: String,
+    }
+
+    // decode converts a test key or value, hex if it has the 0x prefix.
+    fn decode(s: &str) -> Vec<u8> {
+        match s.strip_prefix("0x") {
+            Some(hex) => hex::decode(hex).unwrap(),
+            None => s.as_bytes().to_vec(),
+        }
+    }
+
+    fn root(s: &str) -> common::Hash {
+        common::Hash::from_slice(&decode(s))
+    }
+
+    fn build(pairs: &[(&[u8], &[u8])]) -> Trie {
+        let mut trie = Trie::new(Database::new());
+        for (key, value) in pairs {
+            trie.insert(key, value).unwrap();
+        }
+        trie
+    }
+
+    #[test]
+    fn test_trie_vectors() {
+        let tests: BTreeMap<String, TrieTest<OrderedInput>> =
+            serde_json::from_str(include_str!("testdata/trie/trietest.json")).unwrap();
+        for (name, test) in tests {
+            let mut trie = Trie::new(Database::new());
+            for (key, value) in test.input.iter() {
+                match value {
+                    Some(value) => trie.insert(&decode(key), &decode(value)).unwrap(),
+                    None => trie.delete(&decode(key)).unwrap(),
+                }
+            }
+            assert_eq!(trie.root_hash(), root(&test.root), "{}", name);
+        }
+    }
+
+    #[test]
+    fn test_trie_any_order_vectors() {
+        let tests: BTreeMap<String, TrieTest<BTreeMap<String, String>>> =
+            serde_json::from_str(include_str!("testdata/trie/trieanyorder.json")).unwrap();
+        for (name, test) in tests {
+            let pairs: Vec<(Vec<u8>, Vec<u8>)> = test.input.iter().map(|(k, v)| (decode(k), decode(v))).collect();
+            let want = root(&test.root);
+            // The root doesn't depend on the insertion order
+            for order in [pairs.clone(), pairs.iter().rev().cloned().collect()] {
+                let mut trie = Trie::new(Database::new());
+                for (key, value) in order.iter() {
+                    trie.insert(key, value).unwrap();
+                }
+                assert_eq!(trie.root_hash(), want, "{}", name);
+                for (key, value) in pairs.iter() {
+                    assert_eq!(trie.get(key).unwrap().as_ref(), Some(value), "{}", name);
+                }
+                // Committing doesn't change the root, and the committed trie
+                // resolves the same values from the database.
+                assert_eq!(trie.commit(), want, "{}", name);
+                let trie = Trie::from_root(want, trie.into_database()).unwrap();
+                for (key, value) in pairs.iter() {
+                    assert_eq!(trie.get(key).unwrap().as_ref(), Some(value), "{}", name);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_empty_trie() {
+        let mut trie = Trie::new(Database::new());
+        assert_eq!(trie.root_hash(), EMPTY_ROOT_HASH);
+        assert_eq!(trie.get(b"dog").unwrap(), None);
+        trie.delete(b"dog").unwrap();
+        assert_eq!(trie.commit(), EMPTY_ROOT_HASH);
+        assert_eq!(Trie::from_root(EMPTY_ROOT_HASH, Database::new()).unwrap().root_hash(), EMPTY_ROOT_HASH);
+    }
+
+    #[test]
+    fn test_inline_nodes() {
+        // The nodes below the root encode to less than 32 bytes and are
+        // embedded in their parent: a branch at nibble 0 holding two leaves,
+        // and a leaf at nibble 1.
+        let pairs: [(&[u8], &[u8]); 3] = [(b"\x01", b"1"), (b"\x02", b"2"), (b"\x12", b"3")];
+        let mut trie = build(&pairs);
+        let root = trie.root_hash();
+        for (key, value) in pairs.iter() {
+            assert_eq!(trie.get(key).unwrap().as_deref(), Some(*value));
+        }
+        assert_eq!(trie.get(b"\x01\x02").unwrap(), None);
+        assert_eq!(trie.get(b"\x03").unwrap(), None);
+        assert_eq!(trie.get(b"").unwrap(), None);
+
+        // Only the root is stored, the children are decoded from it
+        assert_eq!(trie.commit(), root);
+        let db = trie.into_database();
+        assert_eq!(db.dirty_len(), 1);
+        let mut trie = Trie::from_root(root, db).unwrap();
+        for (key, value) in pairs.iter() {
+            assert_eq!(trie.get(key).unwrap().as_deref(), Some(*value));
+        }
+
+        // Updating an inline value changes the root, restoring it goes back
+        trie.insert(b"\x02", b"5").unwrap();
+        assert_eq!(trie.get(b"\x02").unwrap().as_deref(), Some(&b"5"[..]));
+        assert_ne!(trie.root_hash(), root);
+        trie.insert(b"\x02", b"2").unwrap();
+        assert_eq!(trie.root_hash(), root);
+
+        // Deleting keys yields the root of the trie built without them, the
+        // inline branch collapses into a leaf.
+        trie.delete(b"\x02").unwrap();
+        assert_eq!(trie.get(b"\x02").unwrap(), None);
+        assert_eq!(trie.get(b"\x01").unwrap().as_deref(), Some(&b"1"[..]));
+        assert_eq!(trie.root_hash(), build(&[(b"\x01", b"1"), (b"\x12", b"3")]).root_hash());
+        trie.delete(b"\x12").unwrap();
+        assert_eq!(trie.root_hash(), build(&[(b"\x01", b"1")]).root_hash());
+        // An empty value deletes the key as well
+        trie.insert(b"\x01", b"").unwrap();
+        assert_eq!(trie.root_hash(), EMPTY_ROOT_HASH);
+    }
+
+    #[test]
+    fn test_missing_node() {
+        let mut trie = build(&[(b"doe", b"reindeer"), (b"dog", b"puppy"), (b"dogglesworth", b"cat")]);
+        let root = trie.commit();
+        assert_eq!(Trie::from_root(root, Database::new()).unwrap_err(), TrieError::MissingNode(root));
+    }
+}
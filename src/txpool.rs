@@ -0,0 +1,931 @@
+// Package txpool keeps the transactions waiting to be included in a block.
+//
+// Every transaction is validated on arrival against the current head: its
+// signature, nonce, the sender's balance, the intrinsic gas and its size.
+// Valid transactions are kept per sender in two nonce-sorted lists: pending
+// holds the transactions executable on top of the head, queued the ones
+// behind a nonce gap. Filling a gap promotes the queued transactions that
+// became executable. A transaction with the nonce of a known one replaces it
+// only if it bumps both fee caps by the configured price bump. All
+// transactions are indexed by price, so when the pool is full the cheapest
+// ones are evicted. Once more than global_slots transactions are pending,
+// the accounts with more than account_slots pending transactions are trimmed
+// from their highest nonces down, and once more than global_queue are
+// queued, the largest queues are trimmed the same way. The pending transactions are handed to the
+// block builder in fee-priority order.
+
+use std::collections::{BTreeMap, BTreeSet, BinaryHeap, HashMap};
+use std::fmt;
+
+use crate::block::Header;
+use crate::chain_config::{ChainConfig, Rules};
+use crate::eip1559::{self, div_uint64};
+use crate::params;
+use crate::state_db::StateDB;
+use crate::state_transition::intrinsic_gas;
+use crate::transaction::{self, Transaction, TxError};
+use uint256::Uint256;
+
+// TX_SLOT_SIZE is used to calculate how many data slots a single transaction
+// takes up based on its size.
+pub const TX_SLOT_SIZE: usize = 32 * 1024;
+
+// TX_MAX_SIZE is the maximum size a single transaction can have. This field
+// has non-trivial consequences: larger transactions are significantly harder
+// and more expensive to propagate.
+pub const TX_MAX_SIZE: usize = 4 * TX_SLOT_SIZE; // 128KB
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TxPoolError {
+    // AlreadyKnown is returned if the transaction is already contained
+    // within the pool.
+    AlreadyKnown,
+    // InvalidSender is returned if the transaction contains an invalid
+    // signature.
+    InvalidSender(TxError),
+    // TxTypeNotSupported is returned if the transaction type is not active
+    // at the current head.
+    TxTypeNotSupported,
+    // OversizedData is returned if the input data of a transaction is
+    // greater than some meaningful limit a user might use.
+    OversizedData { size: usize, max: usize },
+    // MaxInitCodeSizeExceeded is returned if a creation transaction carries
+    // more initcode than allowed by EIP-3860.
+    MaxInitCodeSizeExceeded,
+    // GasLimit is returned if a transaction's requested gas limit exceeds
+    // the maximum allowance of the current block.
+    GasLimit { gas: u64, limit: u64 },
+    // TipAboveFeeCap is returned if a transaction's tip cap is higher than
+    // its fee cap.
+    TipAboveFeeCap,
+    // Underpriced is returned if a transaction's gas price is below the
+    // minimum configured for the transaction pool, or lower than the
+    // cheapest transaction of a full pool.
+    Underpriced,
+    // ReplaceUnderpriced is returned if a transaction is attempted to be
+    // replaced with a different one without the required price bump.
+    ReplaceUnderpriced,
+    NonceTooLow { tx: u64, state: u64 },
+    // InsufficientFunds is returned if the total cost of executing a
+    // transaction is higher than the balance of the user's account.
+    InsufficientFunds,
+    // IntrinsicGas is returned if the transaction is specified to use less
+    // gas than required to start the invocation.
+    IntrinsicGas { have: u64, want: u64 },
+    GasUintOverflow,
+    // TxPoolOverflow is returned if the transaction was accepted but didn't
+    // fit within the pending or queued limits of the pool.
+    TxPoolOverflow,
+}
+
+impl fmt::Display for TxPoolError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TxPoolError::AlreadyKnown => write!(f, "already known"),
+            TxPoolError::InvalidSender(err) => write!(f, "invalid sender: {:?}", err),
+            TxPoolError::TxTypeNotSupported => write!(f, "transaction type not supported"),
+            TxPoolError::OversizedData { size, max } => {
+                write!(
+                    f,
+                    "oversized data: transaction size {}, limit {}",
+                    size, max
+                )
+            }
+            TxPoolError::MaxInitCodeSizeExceeded => write!(f, "max initcode size exceeded"),
+            TxPoolError::GasLimit { gas, limit } => {
+                write!(f, "exceeds block gas limit: gas {}, limit {}", gas, limit)
+            }
+            TxPoolError::TipAboveFeeCap => {
+                write!(f, "max priority fee per gas higher than max fee per gas")
+            }
+            TxPoolError::Underpriced => write!(f, "transaction underpriced"),
+            TxPoolError::ReplaceUnderpriced => write!(f, "replacement transaction underpriced"),
+            TxPoolError::NonceTooLow { tx, state } => {
+                write!(f, "nonce too low: next nonce {}, tx nonce {}", state, tx)
+            }
+            TxPoolError::InsufficientFunds => {
+                write!(f, "insufficient funds for gas * price + value")
+            }
+            TxPoolError::IntrinsicGas { have, want } => {
+                write!(f, "intrinsic gas too low: have {}, want {}", have, want)
+            }
+            TxPoolError::GasUintOverflow => write!(f, "gas uint64 overflow"),
+            TxPoolError::TxPoolOverflow => write!(f, "txpool is full"),
+        }
+    }
+}
+
+impl std::error::Error for TxPoolError {}
+
+// Config are the configuration parameters of the transaction pool.
+#[derive(Debug, Clone)]
+pub struct Config {
+    pub price_limit: u64, // Minimum gas tip to enforce for acceptance into the pool
+    pub price_bump: u64, // Minimum price bump percentage to replace an already existing transaction (nonce)
+
+    pub account_slots: usize, // Number of executable transaction slots guaranteed per account
+    pub global_slots: usize,  // Maximum number of executable transaction slots for all accounts
+    pub account_queue: usize, // Maximum number of non-executable transaction slots permitted per account
+    pub global_queue: usize,  // Maximum number of non-executable transaction slots for all accounts
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            price_limit: 1,
+            price_bump: 10,
+            account_slots: 16,
+            global_slots: 4096 + 1024, // urgent + floating queue capacity with 4:1 ratio
+            account_queue: 64,
+            global_queue: 1024,
+        }
+    }
+}
+
+// cost returns gas * fee_cap + value + blob_gas * blob_fee_cap, the most the
+// transaction can take from the sender's balance.
+fn cost(tx: &Transaction) -> Option<Uint256> {
+    let mut total = tx
+        .gas_fee_cap()
+        .checked_mul(&Uint256::from_uint64(tx.gas()))?
+        .checked_add(&tx.value())?;
+    if let Some(blob_fee_cap) = tx.blob_gas_fee_cap() {
+        let blob_cost = blob_fee_cap.checked_mul(&Uint256::from_uint64(tx.blob_gas()))?;
+        total = total.checked_add(&blob_cost)?;
+    }
+    Some(total)
+}
+
+// bumped returns price increased by bump percent.
+fn bumped(price: &Uint256, bump: u64) -> Uint256 {
+    let scaled = price
+        .checked_mul(&Uint256::from_uint64(100 + bump))
+        .unwrap_or_default();
+    div_uint64(&scaled, 100)
+}
+
+// TxList is a nonce-sorted list of the transactions of one account.
+#[derive(Debug, Clone, Default)]
+struct TxList {
+    txs: BTreeMap<u64, Transaction>,
+}
+
+impl TxList {
+    fn len(&self) -> usize {
+        self.txs.len()
+    }
+
+    fn is_empty(&self) -> bool {
+        self.txs.is_empty()
+    }
+
+    fn get(&self, nonce: u64) -> Option<&Transaction> {
+        self.txs.get(&nonce)
+    }
+
+    fn first_nonce(&self) -> Option<u64> {
+        self.txs.keys().next().copied()
+    }
+
+    fn last_nonce(&self) -> Option<u64> {
+        self.txs.keys().next_back().copied()
+    }
+
+    // add tries to insert a new transaction into the list, returning the
+    // transaction it replaced, if any. A transaction with the nonce of a
+    // known one replaces it only if both its fee cap and tip cap are bumped
+    // by price_bump percent.
+    fn add(
+        &mut self,
+        tx: Transaction,
+        price_bump: u64,
+    ) -> Result<Option<Transaction>, TxPoolError> {
+        if let Some(old) = self.txs.get(&tx.nonce()) {
+            if tx.gas_fee_cap() < bumped(&old.gas_fee_cap(), price_bump)
+                || tx.gas_tip_cap() < bumped(&old.gas_tip_cap(), price_bump)
+            {
+                return Err(TxPoolError::ReplaceUnderpriced);
+            }
+        }
+        Ok(self.txs.insert(tx.nonce(), tx))
+    }
+
+    // forward removes all transactions from the list with a nonce lower than
+    // the provided threshold.
+    fn forward(&mut self, threshold: u64) -> Vec<Transaction> {
+        let kept = self.txs.split_off(&threshold);
+        std::mem::replace(&mut self.txs, kept)
+            .into_values()
+            .collect()
+    }
+
+    // filter removes all transactions from the list with a cost higher than
+    // the provided balance or a gas limit higher than the block's. It
+    // returns the removed transactions and, if strict, the transactions
+    // following the first removed one, which are no longer executable.
+    fn filter(
+        &mut self,
+        balance: &Uint256,
+        gas_limit: u64,
+        strict: bool,
+    ) -> (Vec<Transaction>, Vec<Transaction>) {
+        let drops: Vec<u64> = self
+            .txs
+            .values()
+            .filter(|tx| tx.gas() > gas_limit || cost(tx).is_none_or(|cost| cost > *balance))
+            .map(|tx| tx.nonce())
+            .collect();
+        let removed: Vec<Transaction> = drops
+            .iter()
+            .filter_map(|nonce| self.txs.remove(nonce))
+            .collect();
+        let invalids = match (strict, drops.first()) {
+            (true, Some(lowest)) => self.forward_from(*lowest),
+            _ => Vec::new(),
+        };
+        (removed, invalids)
+    }
+
+    // forward_from removes all transactions from the list with a nonce at
+    // least the given one.
+    fn forward_from(&mut self, nonce: u64) -> Vec<Transaction> {
+        self.txs.split_off(&nonce).into_values().collect()
+    }
+
+    // ready removes the sequentially increasing transactions starting at
+    // start from the list.
+    fn ready(&mut self, start: u64) -> Vec<Transaction> {
+        let mut ready = Vec::new();
+        let mut next = start;
+        while let Some(tx) = self.txs.remove(&next) {
+            ready.push(tx);
+            next += 1;
+        }
+        ready
+    }
+
+    // cap places a hard limit on the number of items, returning all
+    // transactions exceeding that limit, highest nonces first.
+    fn cap(&mut self, threshold: usize) -> Vec<Transaction> {
+        let mut drops = Vec::new();
+        while self.txs.len() > threshold {
+            if let Some((_, tx)) = self.txs.pop_last() {
+                drops.push(tx);
+            }
+        }
+        drops
+    }
+
+    fn flatten(&self) -> Vec<Transaction> {
+        self.txs.values().cloned().collect()
+    }
+}
+
+// TxPool contains all currently known transactions. Transactions enter the
+// pool when they are received from the network or submitted locally. They
+// exit the pool when they are included in the blockchain.
+pub struct TxPool {
+    config: Config,
+    chain_config: ChainConfig,
+    chain_id: Uint256,
+
+    head: Header,            // Current head of the blockchain
+    state: Box<dyn StateDB>, // State of the current head to validate against
+    rules: Rules,            // Fork rules of the block following head
+
+    pending: HashMap<common::Address, TxList>, // All currently processable transactions
+    queue: HashMap<common::Address, TxList>,   // Queued but non-processable transactions
+    all: HashMap<common::Hash, (common::Address, Transaction)>, // All transactions to allow lookups
+    priced: BTreeSet<PriceKey>, // All transactions sorted by price, cheapest first
+}
+
+// PriceKey orders the transactions of the pool by their effective tip at the
+// next base fee, breaking ties by the fee cap and the hash.
+type PriceKey = (Uint256, Uint256, common::Hash);
+
+impl TxPool {
+    // new creates a new transaction pool to gather, sort and filter inbound
+    // transactions on top of the given head and its state.
+    pub fn new(
+        config: Config,
+        chain_config: ChainConfig,
+        head: Header,
+        state: Box<dyn StateDB>,
+    ) -> Self {
+        let mut config = config;
+        if config.price_bump < 1 {
+            config.price_bump = Config::default().price_bump;
+        }
+        let rules = next_rules(&chain_config, &head);
+        TxPool {
+            config,
+            chain_id: Uint256::from_uint64(chain_config.chain_id),
+            chain_config,
+            head,
+            state,
+            rules,
+            pending: HashMap::new(),
+            queue: HashMap::new(),
+            all: HashMap::new(),
+            priced: BTreeSet::new(),
+        }
+    }
+
+    // stats retrieves the current pool stats, namely the number of pending
+    // and the number of queued (non-executable) transactions.
+    pub fn stats(&self) -> (usize, usize) {
+        let pending = self.pending.values().map(TxList::len).sum();
+        let queued = self.queue.values().map(TxList::len).sum();
+        (pending, queued)
+    }
+
+    // get returns a transaction if it is contained in the pool.
+    pub fn get(&self, hash: &common::Hash) -> Option<&Transaction> {
+        self.all.get(hash).map(|(_, tx)| tx)
+    }
+
+    pub fn has(&self, hash: &common::Hash) -> bool {
+        self.all.contains_key(hash)
+    }
+
+    // nonce returns the next nonce of an account, with all transactions
+    // executable by the pool already applied on top.
+    pub fn nonce(&self, addr: &common::Address) -> u64 {
+        match self.pending.get(addr).and_then(TxList::last_nonce) {
+            Some(nonce) => nonce + 1,
+            None => self.state.get_nonce(addr),
+        }
+    }
+
+    // content retrieves the data content of the transaction pool, returning
+    // all the pending as well as queued transactions, grouped by account and
+    // sorted by nonce.
+    pub fn content(
+        &self,
+    ) -> (
+        HashMap<common::Address, Vec<Transaction>>,
+        HashMap<common::Address, Vec<Transaction>>,
+    ) {
+        let flatten = |lists: &HashMap<common::Address, TxList>| {
+            lists
+                .iter()
+                .map(|(addr, list)| (*addr, list.flatten()))
+                .collect()
+        };
+        (flatten(&self.pending), flatten(&self.queue))
+    }
+
+    // pending retrieves all currently processable transactions in
+    // fee-priority order: the transaction paying the highest effective tip
+    // at the head's next base fee comes first, while the transactions of
+    // every account keep their nonce order.
+    pub fn pending(&self) -> Vec<Transaction> {
+        let base_fee = self.next_base_fee();
+        let mut lists: HashMap<common::Address, Vec<Transaction>> = HashMap::new();
+        for (addr, list) in self.pending.iter() {
+            let mut txs = list.flatten();
+            txs.reverse();
+            lists.insert(*addr, txs);
+        }
+        let tip = |tx: &Transaction| tx.effective_gas_tip(base_fee.as_ref()).ok();
+        let mut sorted = Vec::new();
+        loop {
+            // Pick the account whose next transaction pays the best tip
+            let best = lists
+                .iter()
+                .filter_map(|(addr, txs)| txs.last().and_then(tip).map(|tip| (*addr, tip)))
+                .max_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal));
+            let addr = match best {
+                Some((addr, _)) => addr,
+                None => break,
+            };
+            let txs = lists.get_mut(&addr).expect("account listed");
+            sorted.extend(txs.pop());
+            if txs.is_empty() {
+                lists.remove(&addr);
+            }
+        }
+        sorted
+    }
+
+    // next_base_fee returns the base fee of the block following the head.
+    fn next_base_fee(&self) -> Option<Uint256> {
+        if self.chain_config.is_london(self.head.number + 1) {
+            Some(eip1559::calc_base_fee(&self.chain_config, &self.head))
+        } else {
+            None
+        }
+    }
+
+    // validate_tx checks whether a transaction is valid according to the
+    // consensus rules and adheres to some heuristic limits of the local
+    // node (price and size). It returns the sender of the transaction.
+    fn validate_tx(&self, tx: &Transaction) -> Result<common::Address, TxPoolError> {
+        // Ensure transactions not implemented by the current head are rejected
+        let supported = match tx.tx_type() {
+            transaction::LEGACY_TX_TYPE => true,
+            transaction::ACCESS_LIST_TX_TYPE => self.rules.is_berlin,
+            transaction::DYNAMIC_FEE_TX_TYPE => self.rules.is_london,
+            transaction::BLOB_TX_TYPE => self.rules.is_cancun,
+            _ => false,
+        };
+        if !supported {
+            return Err(TxPoolError::TxTypeNotSupported);
+        }
+        // Reject transactions over defined size to prevent DOS attacks
+        let size = tx.size();
+        if size > TX_MAX_SIZE {
+            return Err(TxPoolError::OversizedData {
+                size,
+                max: TX_MAX_SIZE,
+            });
+        }
+        // Check whether the init code size has been exceeded
+        if self.rules.is_shanghai
+            && tx.to().is_none()
+            && tx.data().len() as u64 > params::MAX_INIT_CODE_SIZE
+        {
+            return Err(TxPoolError::MaxInitCodeSizeExceeded);
+        }
+        // Ensure the transaction doesn't exceed the current block limit gas
+        if tx.gas() > self.head.gas_limit {
+            return Err(TxPoolError::GasLimit {
+                gas: tx.gas(),
+                limit: self.head.gas_limit,
+            });
+        }
+        // Ensure gasFeeCap is greater than or equal to gasTipCap
+        if tx.gas_fee_cap() < tx.gas_tip_cap() {
+            return Err(TxPoolError::TipAboveFeeCap);
+        }
+        // Make sure the transaction is signed properly
+        let from = transaction::sender(tx, &self.chain_id).map_err(TxPoolError::InvalidSender)?;
+        // Drop non-local transactions under our own minimal accepted gas price or tip
+        if tx.gas_tip_cap() < Uint256::from_uint64(self.config.price_limit) {
+            return Err(TxPoolError::Underpriced);
+        }
+        // Ensure the transaction adheres to nonce ordering
+        let state_nonce = self.state.get_nonce(&from);
+        if tx.nonce() < state_nonce {
+            return Err(TxPoolError::NonceTooLow {
+                tx: tx.nonce(),
+                state: state_nonce,
+            });
+        }
+        // Transactor should have enough funds to cover the costs
+        let cost = cost(tx).ok_or(TxPoolError::InsufficientFunds)?;
+        if self.state.get_balance(&from) < cost {
+            return Err(TxPoolError::InsufficientFunds);
+        }
+        // Ensure the transaction has more gas than the bare minimum needed to
+        // cover the transaction metadata
+        let intrinsic = intrinsic_gas(
+            tx.data(),
+            &tx.access_list(),
+            tx.to().is_none(),
+            self.rules.is_homestead,
+            self.rules.is_istanbul,
+            self.rules.is_shanghai,
+        )
+        .map_err(|_| TxPoolError::GasUintOverflow)?;
+        if tx.gas() < intrinsic {
+            return Err(TxPoolError::IntrinsicGas {
+                have: tx.gas(),
+                want: intrinsic,
+            });
+        }
+        Ok(from)
+    }
+
+    // add validates a transaction and inserts it into the pool. It returns
+    // whether the transaction is pending, that is executable on top of the
+    // head, or was queued behind a nonce gap. A transaction trimmed right
+    // away by the pool limits is reported as TxPoolOverflow.
+    pub fn add(&mut self, tx: Transaction) -> Result<bool, TxPoolError> {
+        // If the transaction is already known, discard it
+        let hash = tx.hash();
+        if self.all.contains_key(&hash) {
+            return Err(TxPoolError::AlreadyKnown);
+        }
+        // Make sure the transaction is valid
+        let from = self.validate_tx(&tx)?;
+        let replaces = self
+            .pending
+            .get(&from)
+            .and_then(|list| list.get(tx.nonce()))
+            .or_else(|| self.queue.get(&from).and_then(|list| list.get(tx.nonce())))
+            .is_some();
+
+        // If the transaction pool is full, discard underpriced transactions
+        if !replaces && self.all.len() >= self.config.global_slots + self.config.global_queue {
+            // If the new transaction is underpriced, don't accept it
+            let cheapest = self.priced.first().ok_or(TxPoolError::Underpriced)?;
+            let key = self.price_key(&hash, &tx);
+            if (&key.0, &key.1) <= (&cheapest.0, &cheapest.1) {
+                return Err(TxPoolError::Underpriced);
+            }
+            // New transaction is better than our worse ones, make room for it
+            let cheapest = cheapest.2;
+            self.remove_tx(&cheapest);
+        }
+
+        // Try to replace an existing transaction in the pending pool
+        if let Some(list) = self.pending.get_mut(&from) {
+            if list.get(tx.nonce()).is_some() {
+                let old = list.add(tx.clone(), self.config.price_bump)?;
+                if let Some(old) = old {
+                    self.untrack(&old.hash());
+                }
+                self.track(from, tx);
+                return Ok(true);
+            }
+        }
+        // New transaction isn't replacing a pending one, push into queue
+        let old = self
+            .queue
+            .entry(from)
+            .or_default()
+            .add(tx.clone(), self.config.price_bump)?;
+        if let Some(old) = old {
+            self.untrack(&old.hash());
+        }
+        self.track(from, tx);
+        self.promote_executables(&[from]);
+        self.truncate_pending();
+        self.truncate_queue();
+        if !self.all.contains_key(&hash) {
+            return Err(TxPoolError::TxPoolOverflow);
+        }
+        Ok(self
+            .pending
+            .get(&from)
+            .is_some_and(|list| list.txs.values().any(|pending| pending.hash() == hash)))
+    }
+
+    // price_key returns the key of a transaction in the price index.
+    fn price_key(&self, hash: &common::Hash, tx: &Transaction) -> PriceKey {
+        let base_fee = self.next_base_fee();
+        let tip = tx.effective_gas_tip(base_fee.as_ref()).unwrap_or_default();
+        (tip, tx.gas_fee_cap(), *hash)
+    }
+
+    // track adds a validated transaction to the lookup and the price index.
+    fn track(&mut self, from: common::Address, tx: Transaction) {
+        let hash = tx.hash();
+        self.priced.insert(self.price_key(&hash, &tx));
+        self.all.insert(hash, (from, tx));
+    }
+
+    // untrack removes a transaction from the lookup and the price index.
+    fn untrack(&mut self, hash: &common::Hash) -> Option<(common::Address, Transaction)> {
+        let (from, tx) = self.all.remove(hash)?;
+        self.priced.remove(&self.price_key(hash, &tx));
+        Some((from, tx))
+    }
+
+    // reheap rebuilds the price index, which depends on the base fee of the
+    // block following the head.
+    fn reheap(&mut self) {
+        let priced = self
+            .all
+            .iter()
+            .map(|(hash, (_, tx))| self.price_key(hash, tx))
+            .collect();
+        self.priced = priced;
+    }
+
+    // remove_tx removes a single transaction from the pool, moving all
+    // subsequent pending transactions of its sender back to the queue.
+    pub fn remove_tx(&mut self, hash: &common::Hash) {
+        let (from, tx) = match self.untrack(hash) {
+            Some(entry) => entry,
+            None => return,
+        };
+        // Remove the transaction from the pending lists and reset the account nonce
+        if let Some(list) = self.pending.get_mut(&from) {
+            if list
+                .get(tx.nonce())
+                .is_some_and(|pending| pending.hash() == *hash)
+            {
+                let invalids = list.forward_from(tx.nonce());
+                if list.is_empty() {
+                    self.pending.remove(&from);
+                }
+                // Reduce the pending counter, postponing the invalidated transactions
+                for invalid in invalids.into_iter().skip(1) {
+                    self.enqueue(from, invalid);
+                }
+                return;
+            }
+        }
+        // Transaction is in the future queue
+        if let Some(list) = self.queue.get_mut(&from) {
+            list.txs.remove(&tx.nonce());
+            if list.is_empty() {
+                self.queue.remove(&from);
+            }
+        }
+    }
+
+    // enqueue moves an already validated transaction into the queue.
+    fn enqueue(&mut self, from: common::Address, tx: Transaction) {
+        self.queue
+            .entry(from)
+            .or_default()
+            .txs
+            .insert(tx.nonce(), tx);
+    }
+
+    // drop_all removes the given transactions from the lookup.
+    fn drop_all(&mut self, txs: Vec<Transaction>) {
+        for tx in txs {
+            self.untrack(&tx.hash());
+        }
+    }
+
+    // promote_executables moves transactions that have become processable
+    // from the future queue to the set of pending transactions. During this
+    // process, all invalidated transactions (low nonce, low balance) are
+    // deleted.
+    fn promote_executables(&mut self, accounts: &[common::Address]) {
+        for addr in accounts {
+            let mut list = match self.queue.remove(addr) {
+                Some(list) => list,
+                None => continue,
+            };
+            // Drop all transactions that are deemed too old (low nonce)
+            let olds = list.forward(self.state.get_nonce(addr));
+            self.drop_all(olds);
+            // Drop all transactions that are too costly (low balance or out of gas)
+            let (drops, _) = list.filter(&self.state.get_balance(addr), self.head.gas_limit, false);
+            self.drop_all(drops);
+            // Gather all executable transactions and promote them
+            let readies = list.ready(self.nonce(addr));
+            if !readies.is_empty() {
+                let pending = self.pending.entry(*addr).or_default();
+                for tx in readies {
+                    pending.txs.insert(tx.nonce(), tx);
+                }
+            }
+            // Drop all transactions over the allowed limit
+            let caps = list.cap(self.config.account_queue);
+            self.drop_all(caps);
+            if !list.is_empty() {
+                self.queue.insert(*addr, list);
+            }
+        }
+    }
+
+    // demote_unexecutables removes invalid and processed transactions from
+    // the pools executable/pending queue and any subsequent transactions
+    // that become unexecutable are moved back into the future queue.
+    fn demote_unexecutables(&mut self) {
+        let accounts: Vec<common::Address> = self.pending.keys().copied().collect();
+        for addr in accounts {
+            let mut list = self.pending.remove(&addr).expect("account listed");
+            let nonce = self.state.get_nonce(&addr);
+            // Drop all transactions that are deemed too old (low nonce)
+            let olds = list.forward(nonce);
+            self.drop_all(olds);
+            // Drop all transactions that are too costly (low balance or out
+            // of gas), and queue any invalids back for later
+            let (drops, invalids) =
+                list.filter(&self.state.get_balance(&addr), self.head.gas_limit, true);
+            self.drop_all(drops);
+            for tx in invalids {
+                self.enqueue(addr, tx);
+            }
+            // If there's a gap in front, alert (should never happen) and
+            // postpone all transactions
+            if list.first_nonce().is_some_and(|first| first != nonce) {
+                for tx in list.forward_from(0) {
+                    self.enqueue(addr, tx);
+                }
+            }
+            if !list.is_empty() {
+                self.pending.insert(addr, list);
+            }
+        }
+    }
+
+    // truncate_pending removes transactions from the pending lists if the
+    // pool is above the pending limit. The algorithm tries to reduce the
+    // transaction counts of all accounts with many pending transactions
+    // evenly, never below account_slots, starting with the largest ones.
+    fn truncate_pending(&mut self) {
+        let mut pending: usize = self.pending.values().map(TxList::len).sum();
+        if pending <= self.config.global_slots {
+            return;
+        }
+        // Penalize the largest transactors first
+        let mut spammers: BinaryHeap<(usize, common::Address)> = self
+            .pending
+            .iter()
+            .filter(|(_, list)| list.len() > self.config.account_slots)
+            .map(|(addr, list)| (list.len(), *addr))
+            .collect();
+        while pending > self.config.global_slots {
+            let (len, addr) = match spammers.pop() {
+                Some(spammer) => spammer,
+                None => break,
+            };
+            let list = self.pending.get_mut(&addr).expect("account listed");
+            let caps = list.cap(len - 1);
+            if list.is_empty() {
+                self.pending.remove(&addr);
+            }
+            self.drop_all(caps);
+            pending -= 1;
+            if len - 1 > self.config.account_slots {
+                spammers.push((len - 1, addr));
+            }
+        }
+    }
+
+    // truncate_queue removes transactions from the queue if the pool is above
+    // the queue limit, trimming the largest queues first from their highest
+    // nonces down.
+    fn truncate_queue(&mut self) {
+        let mut queued: usize = self.queue.values().map(TxList::len).sum();
+        if queued <= self.config.global_queue {
+            return;
+        }
+        let mut queues: BinaryHeap<(usize, common::Address)> = self
+            .queue
+            .iter()
+            .map(|(addr, list)| (list.len(), *addr))
+            .collect();
+        while queued > self.config.global_queue {
+            let (len, addr) = match queues.pop() {
+                Some(queue) => queue,
+                None => break,
+            };
+            let list = self.queue.get_mut(&addr).expect("account listed");
+            let caps = list.cap(len - 1);
+            if list.is_empty() {
+                self.queue.remove(&addr);
+            }
+            self.drop_all(caps);
+            queued -= 1;
+            if len > 1 {
+                queues.push((len - 1, addr));
+            }
+        }
+    }
+
+    // reset retrieves the current state of the blockchain and ensures the
+    // content of the transaction pool is valid with regard to the chain
+    // state. It must be called whenever the chain head changes.
+    pub fn reset(&mut self, head: Header, state: Box<dyn StateDB>) {
+        self.rules = next_rules(&self.chain_config, &head);
+        self.head = head;
+        self.state = state;
+        self.reheap();
+
+        // Remove the transactions included in the new head and the ones made
+        // invalid by it, then promote the executable ones
+        self.demote_unexecutables();
+        let accounts: Vec<common::Address> = self.queue.keys().copied().collect();
+        self.promote_executables(&accounts);
+        self.truncate_pending();
+        self.truncate_queue();
+    }
+}
+
+// next_rules returns the fork rules of the block following head.
+fn next_rules(chain_config: &ChainConfig, head: &Header) -> Rules {
+    let is_merge = head.difficulty.is_zero();
+    chain_config.rules(head.number + 1, is_merge, head.time)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::{self, PrivateKey};
+    use crate::memory_state_db::MemoryStateDB;
+    use crate::transaction::{LegacyTx, TxData};
+
+    const CHAIN_ID: u64 = 1337;
+
+    fn key(seed: u8) -> PrivateKey {
+        crypto::to_ecdsa(&[seed; 32]).unwrap()
+    }
+
+    fn setup(config: Config, keys: &[&PrivateKey]) -> TxPool {
+        let mut state = MemoryStateDB::new();
+        for key in keys {
+            state.add_balance(&key.address(), &Uint256::from_uint64(1_000_000_000));
+        }
+        let chain_config = ChainConfig {
+            chain_id: CHAIN_ID,
+            ..Default::default()
+        };
+        let head = Header {
+            gas_limit: 30_000_000,
+            ..Default::default()
+        };
+        TxPool::new(config, chain_config, head, Box::new(state))
+    }
+
+    fn transaction(nonce: u64, gas_price: u64, key: &PrivateKey) -> Transaction {
+        let tx = Transaction::new(TxData::Legacy(LegacyTx {
+            nonce,
+            gas_price: Uint256::from_uint64(gas_price),
+            gas: 21000,
+            to: Some(common::Address([0x01; 20])),
+            ..Default::default()
+        }));
+        transaction::sign_tx(&tx, &Uint256::from_uint64(CHAIN_ID), key).unwrap()
+    }
+
+    #[test]
+    fn test_underpriced_eviction() {
+        let (a, b, c) = (key(1), key(2), key(3));
+        let config = Config {
+            global_slots: 2,
+            global_queue: 0,
+            ..Default::default()
+        };
+        let mut pool = setup(config, &[&a, &b, &c]);
+        pool.add(transaction(0, 5, &a)).unwrap();
+        let cheap = transaction(0, 3, &b);
+        pool.add(cheap.clone()).unwrap();
+
+        // A full pool only accepts transactions paying more than its cheapest
+        assert_eq!(pool.add(transaction(0, 2, &c)), Err(TxPoolError::Underpriced));
+        assert_eq!(pool.add(transaction(0, 3, &c)), Err(TxPoolError::Underpriced));
+        let better = transaction(0, 4, &c);
+        assert_eq!(pool.add(better.clone()), Ok(true));
+        assert!(!pool.has(&cheap.hash()));
+        assert!(pool.has(&better.hash()));
+        assert_eq!(pool.stats(), (2, 0));
+    }
+
+    #[test]
+    fn test_pending_slot_limits() {
+        let (a, b) = (key(1), key(2));
+        let config = Config {
+            account_slots: 2,
+            global_slots: 4,
+            ..Default::default()
+        };
+        let mut pool = setup(config, &[&a, &b]);
+        pool.add(transaction(0, 1, &b)).unwrap();
+        for nonce in 0..3 {
+            assert_eq!(pool.add(transaction(nonce, 1, &a)), Ok(true));
+        }
+        // Going over the global limit trims the largest account from its
+        // highest nonce down.
+        let dropped = transaction(3, 1, &a);
+        assert_eq!(pool.add(dropped.clone()), Err(TxPoolError::TxPoolOverflow));
+        assert!(!pool.has(&dropped.hash()));
+        assert_eq!(pool.stats(), (4, 0));
+        assert_eq!(pool.nonce(&a.address()), 3);
+
+        // An account is never trimmed below its guaranteed slots
+        let mut pool = setup(
+            Config {
+                account_slots: 2,
+                global_slots: 3,
+                ..Default::default()
+            },
+            &[&a, &b],
+        );
+        for nonce in 0..2 {
+            pool.add(transaction(nonce, 1, &a)).unwrap();
+            pool.add(transaction(nonce, 1, &b)).unwrap();
+        }
+        assert_eq!(pool.stats(), (4, 0));
+    }
+
+    #[test]
+    fn test_queue_limits() {
+        let (a, b) = (key(1), key(2));
+        let config = Config {
+            global_queue: 2,
+            ..Default::default()
+        };
+        let mut pool = setup(config, &[&a, &b]);
+        assert_eq!(pool.add(transaction(1, 1, &a)), Ok(false));
+        assert_eq!(pool.add(transaction(2, 1, &a)), Ok(false));
+
+        // Going over the queue limit trims the largest queue from its highest
+        // nonce down, regardless of the pending limits.
+        assert_eq!(pool.add(transaction(1, 1, &b)), Ok(false));
+        assert_eq!(pool.stats(), (0, 2));
+        assert!(!pool.has(&transaction(2, 1, &a).hash()));
+
+        // A queued transaction trimmed right away is reported
+        let dropped = transaction(3, 1, &a);
+        assert_eq!(pool.add(dropped.clone()), Err(TxPoolError::TxPoolOverflow));
+        assert!(!pool.has(&dropped.hash()));
+        assert_eq!(pool.stats(), (0, 2));
+
+        // Filling the gap promotes the queue and frees its slots
+        assert_eq!(pool.add(transaction(0, 1, &a)), Ok(true));
+        assert_eq!(pool.stats(), (2, 1));
+        assert_eq!(pool.add(transaction(3, 1, &a)), Ok(false));
+        assert_eq!(pool.add(transaction(4, 1, &a)), Err(TxPoolError::TxPoolOverflow));
+        assert_eq!(pool.stats(), (2, 2));
+    }
+}
@@ -0,0 +1,15 @@
+// Package types contains data types related to Ethereum consensus.
+
+pub use crate::block::{
+    calc_uncle_hash, derive_sha, Block, BlockNonce, Body, DerivableItem, Header, Withdrawal,
+};
+pub use crate::bloom::{create_bloom, logs_bloom, Bloom, BLOOM_BIT_LENGTH, BLOOM_BYTE_LENGTH};
+pub use crate::log::Log;
+pub use crate::receipt::{
+    derive_fields, Receipt, ReceiptError, RECEIPT_STATUS_FAILED, RECEIPT_STATUS_SUCCESSFUL,
+};
+pub use crate::transaction::{
+    sender, sign_tx, AccessList, AccessListTx, AccessTuple, BlobTx, DynamicFeeTx, LegacyTx,
+    Transaction, TxData, TxError, ACCESS_LIST_TX_TYPE, BLOB_TX_TYPE, DYNAMIC_FEE_TX_TYPE,
+    LEGACY_TX_TYPE,
+};
@@ -0,0 +1,694 @@
+// Package uint256 implements 256-bit unsigned integers.
+//
+// Uint256 wraps around modulo 2^256 like the words of the EVM: the plain
+// arithmetic methods wrap, the checked_* variants report overflow. The
+// signed methods (sdiv, smod, slt, sgt, sar, sign_extend) interpret the
+// value as a two's complement number, as the EVM does. The value is stored
+// as four little-endian 64-bit limbs.
+
+use std::cmp::Ordering;
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Clone, Default, PartialEq, Eq, Hash)]
+pub struct Uint256([u64; 4]);
+
+// ParseError is returned when a string is not a valid decimal or 0x-prefixed
+// hexadecimal 256-bit number.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseError {
+    Empty,
+    InvalidDigit,
+    Overflow,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::Empty => write!(f, "empty number"),
+            ParseError::InvalidDigit => write!(f, "invalid digit"),
+            ParseError::Overflow => write!(f, "number larger than 256 bits"),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+impl Uint256 {
+    pub const fn zero() -> Self {
+        Uint256([0; 4])
+    }
+
+    pub const fn one() -> Self {
+        Uint256([1, 0, 0, 0])
+    }
+
+    pub const fn max_value() -> Self {
+        Uint256([u64::MAX; 4])
+    }
+
+    pub const fn from_limbs(limbs: [u64; 4]) -> Self {
+        Uint256(limbs)
+    }
+
+    pub const fn from_uint64(v: u64) -> Self {
+        Uint256([v, 0, 0, 0])
+    }
+
+    // from_bytes interprets b as a big-endian number. Only the last 32
+    // bytes are used if b is longer.
+    pub fn from_bytes(b: &[u8]) -> Self {
+        let b = if b.len() > 32 { &b[b.len() - 32..] } else { b };
+        let mut buf = [0u8; 32];
+        buf[32 - b.len()..].copy_from_slice(b);
+        let mut limbs = [0u64; 4];
+        for (i, limb) in limbs.iter_mut().enumerate() {
+            let start = 32 - (i + 1) * 8;
+            *limb = u64::from_be_bytes(buf[start..start + 8].try_into().unwrap());
+        }
+        Uint256(limbs)
+    }
+
+    pub fn as_limbs(&self) -> &[u64; 4] {
+        &self.0
+    }
+
+    // to_bytes32 returns the value as a 32-byte big-endian array.
+    pub fn to_bytes32(&self) -> [u8; 32] {
+        let mut out = [0u8; 32];
+        for (i, limb) in self.0.iter().enumerate() {
+            let start = 32 - (i + 1) * 8;
+            out[start..start + 8].copy_from_slice(&limb.to_be_bytes());
+        }
+        out
+    }
+
+    // to_bytes returns the minimal big-endian encoding of the value, which
+    // is empty for zero.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let b = self.to_bytes32();
+        b[32 - self.byte_len()..].to_vec()
+    }
+
+    // bytes20 returns the lower 20 bytes of the big-endian encoding.
+    pub fn bytes20(&self) -> [u8; 20] {
+        let b = self.to_bytes32();
+        let mut out = [0u8; 20];
+        out.copy_from_slice(&b[12..]);
+        out
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.0 == [0; 4]
+    }
+
+    // is_u64 reports whether the value fits in 64 bits.
+    pub fn is_u64(&self) -> bool {
+        self.0[1] == 0 && self.0[2] == 0 && self.0[3] == 0
+    }
+
+    // low_u64 returns the lower 64 bits of the value.
+    pub fn low_u64(&self) -> u64 {
+        self.0[0]
+    }
+
+    // to_u64 returns the value as a u64, saturating at u64::MAX if it
+    // doesn't fit.
+    pub fn to_u64(&self) -> u64 {
+        if self.is_u64() {
+            self.0[0]
+        } else {
+            u64::MAX
+        }
+    }
+
+    // uint64_with_overflow returns the lower 64 bits of the value and
+    // whether the value doesn't fit in 64 bits.
+    pub fn uint64_with_overflow(&self) -> (u64, bool) {
+        (self.0[0], !self.is_u64())
+    }
+
+    // bits returns the bit length of the value.
+    pub fn bits(&self) -> usize {
+        for i in (0..4).rev() {
+            if self.0[i] != 0 {
+                return i * 64 + 64 - self.0[i].leading_zeros() as usize;
+            }
+        }
+        0
+    }
+
+    // byte_len returns the length of the minimal big-endian encoding.
+    pub fn byte_len(&self) -> usize {
+        self.bits().div_ceil(8)
+    }
+
+    // bit reports whether the n'th bit (counted from the least significant)
+    // is set.
+    pub fn bit(&self, n: usize) -> bool {
+        n < 256 && (self.0[n / 64] >> (n % 64)) & 1 == 1
+    }
+
+    fn set_bit(&mut self, n: usize) {
+        self.0[n / 64] |= 1 << (n % 64);
+    }
+
+    pub fn overflowing_add(&self, other: &Self) -> (Self, bool) {
+        let mut out = [0u64; 4];
+        let mut carry = false;
+        for (i, limb) in out.iter_mut().enumerate() {
+            let (s1, c1) = self.0[i].overflowing_add(other.0[i]);
+            let (s2, c2) = s1.overflowing_add(carry as u64);
+            *limb = s2;
+            carry = c1 || c2;
+        }
+        (Uint256(out), carry)
+    }
+
+    pub fn overflowing_sub(&self, other: &Self) -> (Self, bool) {
+        let mut out = [0u64; 4];
+        let mut borrow = false;
+        for (i, limb) in out.iter_mut().enumerate() {
+            let (d1, b1) = self.0[i].overflowing_sub(other.0[i]);
+            let (d2, b2) = d1.overflowing_sub(borrow as u64);
+            *limb = d2;
+            borrow = b1 || b2;
+        }
+        (Uint256(out), borrow)
+    }
+
+    pub fn overflowing_mul(&self, other: &Self) -> (Self, bool) {
+        let wide = mul_wide(self, other);
+        let mut out = [0u64; 4];
+        out.copy_from_slice(&wide[..4]);
+        (Uint256(out), wide[4..].iter().any(|&limb| limb != 0))
+    }
+
+    pub fn checked_add(&self, other: &Self) -> Option<Self> {
+        match self.overflowing_add(other) {
+            (sum, false) => Some(sum),
+            _ => None,
+        }
+    }
+
+    pub fn checked_sub(&self, other: &Self) -> Option<Self> {
+        match self.overflowing_sub(other) {
+            (diff, false) => Some(diff),
+            _ => None,
+        }
+    }
+
+    pub fn checked_mul(&self, other: &Self) -> Option<Self> {
+        match self.overflowing_mul(other) {
+            (product, false) => Some(product),
+            _ => None,
+        }
+    }
+
+    pub fn wrapping_add(&self, other: &Self) -> Self {
+        self.overflowing_add(other).0
+    }
+
+    pub fn wrapping_sub(&self, other: &Self) -> Self {
+        self.overflowing_sub(other).0
+    }
+
+    pub fn wrapping_mul(&self, other: &Self) -> Self {
+        self.overflowing_mul(other).0
+    }
+
+    pub fn saturating_add(&self, other: &Self) -> Self {
+        self.checked_add(other).unwrap_or_else(Self::max_value)
+    }
+
+    pub fn saturating_sub(&self, other: &Self) -> Self {
+        self.checked_sub(other).unwrap_or_default()
+    }
+
+    // div_rem returns the quotient and the remainder of self / other, which
+    // are both zero if other is zero.
+    pub fn div_rem(&self, other: &Self) -> (Self, Self) {
+        if other.is_zero() {
+            return (Self::zero(), Self::zero());
+        }
+        if self < other {
+            return (Self::zero(), self.clone());
+        }
+        if self.is_u64() {
+            let (n, d) = (self.0[0], other.0[0]);
+            return (Self::from_uint64(n / d), Self::from_uint64(n % d));
+        }
+        let mut quotient = Self::zero();
+        let mut rem = Self::zero();
+        for i in (0..self.bits()).rev() {
+            rem = rem.lsh(1);
+            if self.bit(i) {
+                rem.0[0] |= 1;
+            }
+            if rem >= *other {
+                rem = rem.wrapping_sub(other);
+                quotient.set_bit(i);
+            }
+        }
+        (quotient, rem)
+    }
+
+    // checked_div returns self / other, or None if other is zero.
+    pub fn checked_div(&self, other: &Self) -> Option<Self> {
+        if other.is_zero() {
+            None
+        } else {
+            Some(self.div_rem(other).0)
+        }
+    }
+
+    // udiv returns self / other, or zero if other is zero (EVM DIV).
+    pub fn udiv(&self, other: &Self) -> Self {
+        self.div_rem(other).0
+    }
+
+    // umod returns self % other, or zero if other is zero (EVM MOD).
+    pub fn umod(&self, other: &Self) -> Self {
+        self.div_rem(other).1
+    }
+
+    // sdiv interprets both values as two's complement numbers and returns
+    // the quotient rounded towards zero, or zero if other is zero.
+    pub fn sdiv(&self, other: &Self) -> Self {
+        if other.is_zero() {
+            return Self::zero();
+        }
+        let quotient = self.abs().udiv(&other.abs());
+        if self.is_negative() != other.is_negative() {
+            quotient.neg()
+        } else {
+            quotient
+        }
+    }
+
+    // smod interprets both values as two's complement numbers and returns
+    // the remainder, which takes the sign of self, or zero if other is zero.
+    pub fn smod(&self, other: &Self) -> Self {
+        if other.is_zero() {
+            return Self::zero();
+        }
+        let rem = self.abs().umod(&other.abs());
+        if self.is_negative() {
+            rem.neg()
+        } else {
+            rem
+        }
+    }
+
+    // add_mod returns (self + other) % m without intermediate overflow, or
+    // zero if m is zero.
+    pub fn add_mod(&self, other: &Self, m: &Self) -> Self {
+        if m.is_zero() {
+            return Self::zero();
+        }
+        let a = self.umod(m);
+        let b = other.umod(m);
+        let (sum, overflow) = a.overflowing_add(&b);
+        if overflow || sum >= *m {
+            sum.wrapping_sub(m)
+        } else {
+            sum
+        }
+    }
+
+    // mul_mod returns (self * other) % m without intermediate overflow, or
+    // zero if m is zero.
+    pub fn mul_mod(&self, other: &Self, m: &Self) -> Self {
+        if m.is_zero() {
+            return Self::zero();
+        }
+        let wide = mul_wide(self, other);
+        // Reduce the 512-bit product bit by bit. rem < m always holds, so
+        // rem << 1 fits in 257 bits; the extra bit is tracked in carry.
+        let mut rem = Self::zero();
+        for i in (0..512).rev() {
+            let carry = rem.bit(255);
+            rem = rem.lsh(1);
+            if (wide[i / 64] >> (i % 64)) & 1 == 1 {
+                rem.0[0] |= 1;
+            }
+            if carry || rem >= *m {
+                rem = rem.wrapping_sub(m);
+            }
+        }
+        rem
+    }
+
+    // exp returns self ** exponent modulo 2^256.
+    pub fn exp(&self, exponent: &Self) -> Self {
+        let mut result = Self::one();
+        let mut base = self.clone();
+        for i in 0..exponent.bits() {
+            if exponent.bit(i) {
+                result = result.wrapping_mul(&base);
+            }
+            base = base.wrapping_mul(&base);
+        }
+        result
+    }
+
+    // sign_extend extends the two's complement sign of the number stored in
+    // the lowest byte_num+1 bytes of self (EVM SIGNEXTEND).
+    pub fn sign_extend(&self, byte_num: &Self) -> Self {
+        if !byte_num.is_u64() || byte_num.0[0] >= 31 {
+            return self.clone();
+        }
+        let bit = byte_num.0[0] as usize * 8 + 7;
+        let mask = Self::one().lsh(bit as u32 + 1).wrapping_sub(&Self::one());
+        if self.bit(bit) {
+            self.or(&mask.not())
+        } else {
+            self.and(&mask)
+        }
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.bit(255)
+    }
+
+    // neg returns the two's complement negation of self.
+    pub fn neg(&self) -> Self {
+        Self::zero().wrapping_sub(self)
+    }
+
+    // abs returns the absolute value of self interpreted as a two's
+    // complement number.
+    pub fn abs(&self) -> Self {
+        if self.is_negative() {
+            self.neg()
+        } else {
+            self.clone()
+        }
+    }
+
+    // scmp compares self and other as two's complement numbers.
+    pub fn scmp(&self, other: &Self) -> Ordering {
+        match (self.is_negative(), other.is_negative()) {
+            (true, false) => Ordering::Less,
+            (false, true) => Ordering::Greater,
+            _ => self.cmp(other),
+        }
+    }
+
+    pub fn slt(&self, other: &Self) -> bool {
+        self.scmp(other) == Ordering::Less
+    }
+
+    pub fn sgt(&self, other: &Self) -> bool {
+        self.scmp(other) == Ordering::Greater
+    }
+
+    pub fn and(&self, other: &Self) -> Self {
+        let mut out = self.0;
+        for (limb, o) in out.iter_mut().zip(other.0.iter()) {
+            *limb &= o;
+        }
+        Uint256(out)
+    }
+
+    pub fn or(&self, other: &Self) -> Self {
+        let mut out = self.0;
+        for (limb, o) in out.iter_mut().zip(other.0.iter()) {
+            *limb |= o;
+        }
+        Uint256(out)
+    }
+
+    pub fn xor(&self, other: &Self) -> Self {
+        let mut out = self.0;
+        for (limb, o) in out.iter_mut().zip(other.0.iter()) {
+            *limb ^= o;
+        }
+        Uint256(out)
+    }
+
+    pub fn not(&self) -> Self {
+        Uint256(self.0.map(|limb| !limb))
+    }
+
+    // byte returns the n'th byte of the big-endian encoding of self, or zero
+    // if n is out of range (EVM BYTE).
+    pub fn byte(&self, n: &Self) -> Self {
+        if !n.is_u64() || n.0[0] >= 32 {
+            return Self::zero();
+        }
+        Self::from_uint64(self.to_bytes32()[n.0[0] as usize] as u64)
+    }
+
+    // lsh returns self << n, which is zero for n >= 256.
+    pub fn lsh(&self, n: u32) -> Self {
+        if n >= 256 {
+            return Self::zero();
+        }
+        let (limbs, bits) = ((n / 64) as usize, n % 64);
+        let mut out = [0u64; 4];
+        for i in (limbs..4).rev() {
+            out[i] = self.0[i - limbs] << bits;
+            if bits > 0 && i > limbs {
+                out[i] |= self.0[i - limbs - 1] >> (64 - bits);
+            }
+        }
+        Uint256(out)
+    }
+
+    // rsh returns self >> n, which is zero for n >= 256.
+    pub fn rsh(&self, n: u32) -> Self {
+        if n >= 256 {
+            return Self::zero();
+        }
+        let (limbs, bits) = ((n / 64) as usize, n % 64);
+        let mut out = [0u64; 4];
+        for (i, limb) in out.iter_mut().enumerate().take(4 - limbs) {
+            *limb = self.0[i + limbs] >> bits;
+            if bits > 0 && i + limbs + 1 < 4 {
+                *limb |= self.0[i + limbs + 1] << (64 - bits);
+            }
+        }
+        Uint256(out)
+    }
+
+    // srsh returns the arithmetic right shift of self by n, filling with
+    // the sign bit (EVM SAR).
+    pub fn srsh(&self, n: u32) -> Self {
+        if !self.is_negative() {
+            return self.rsh(n);
+        }
+        if n >= 256 {
+            return Self::max_value();
+        }
+        self.not().rsh(n).not()
+    }
+
+    // shift_amount returns the value as a shift amount for lsh/rsh, which
+    // saturates at 256.
+    pub fn shift_amount(&self) -> u32 {
+        if self.is_u64() && self.0[0] < 256 {
+            self.0[0] as u32
+        } else {
+            256
+        }
+    }
+
+    // from_dec_str parses a decimal number.
+    pub fn from_dec_str(s: &str) -> Result<Self, ParseError> {
+        if s.is_empty() {
+            return Err(ParseError::Empty);
+        }
+        let ten = Self::from_uint64(10);
+        let mut out = Self::zero();
+        for c in s.chars() {
+            let digit = c.to_digit(10).ok_or(ParseError::InvalidDigit)?;
+            out = out
+                .checked_mul(&ten)
+                .and_then(|v| v.checked_add(&Self::from_uint64(digit as u64)))
+                .ok_or(ParseError::Overflow)?;
+        }
+        Ok(out)
+    }
+
+    // from_hex_str parses a hexadecimal number without 0x prefix.
+    pub fn from_hex_str(s: &str) -> Result<Self, ParseError> {
+        if s.is_empty() {
+            return Err(ParseError::Empty);
+        }
+        let s = s.trim_start_matches('0');
+        if s.len() > 64 {
+            return Err(ParseError::Overflow);
+        }
+        let mut out = Self::zero();
+        for c in s.chars() {
+            let digit = c.to_digit(16).ok_or(ParseError::InvalidDigit)?;
+            out = out.lsh(4);
+            out.0[0] |= digit as u64;
+        }
+        Ok(out)
+    }
+}
+
+// mul_wide returns the full 512-bit product of a and b as little-endian
+// limbs.
+fn mul_wide(a: &Uint256, b: &Uint256) -> [u64; 8] {
+    let mut out = [0u64; 8];
+    for i in 0..4 {
+        let mut carry = 0u128;
+        for j in 0..4 {
+            let cur = out[i + j] as u128 + (a.0[i] as u128) * (b.0[j] as u128) + carry;
+            out[i + j] = cur as u64;
+            carry = cur >> 64;
+        }
+        out[i + 4] = carry as u64;
+    }
+    out
+}
+
+impl Ord for Uint256 {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.iter().rev().cmp(other.0.iter().rev())
+    }
+}
+
+impl PartialOrd for Uint256 {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl From<u64> for Uint256 {
+    fn from(v: u64) -> Self {
+        Self::from_uint64(v)
+    }
+}
+
+impl From<u128> for Uint256 {
+    fn from(v: u128) -> Self {
+        Uint256([v as u64, (v >> 64) as u64, 0, 0])
+    }
+}
+
+impl From<usize> for Uint256 {
+    fn from(v: usize) -> Self {
+        Self::from_uint64(v as u64)
+    }
+}
+
+impl FromStr for Uint256 {
+    type Err = ParseError;
+
+    // from_str parses a decimal or a 0x-prefixed hexadecimal number.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+            Some(hex) => Self::from_hex_str(hex),
+            None => Self::from_dec_str(s),
+        }
+    }
+}
+
+impl fmt::Display for Uint256 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_zero() {
+            return f.pad_integral(true, "", "0");
+        }
+        // Peel off 19 decimal digits at a time.
+        let chunk = Self::from_uint64(10_000_000_000_000_000_000);
+        let mut parts = Vec::new();
+        let mut rest = self.clone();
+        while !rest.is_zero() {
+            let (q, r) = rest.div_rem(&chunk);
+            parts.push(r.low_u64());
+            rest = q;
+        }
+        let mut s = parts.pop().unwrap().to_string();
+        for part in parts.iter().rev() {
+            s.push_str(&format!("{:019}", part));
+        }
+        f.pad_integral(true, "", &s)
+    }
+}
+
+impl fmt::LowerHex for Uint256 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut s = String::new();
+        for limb in self.0.iter().rev() {
+            if s.is_empty() {
+                if *limb != 0 {
+                    s = format!("{:x}", limb);
+                }
+            } else {
+                s.push_str(&format!("{:016x}", limb));
+            }
+        }
+        if s.is_empty() {
+            s.push('0');
+        }
+        f.pad_integral(true, "0x", &s)
+    }
+}
+
+impl fmt::Debug for Uint256 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn u(s: &str) -> Uint256 {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn bytes_roundtrip() {
+        let v = u("0x0102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f20");
+        assert_eq!(Uint256::from_bytes(&v.to_bytes32()), v);
+        assert_eq!(v.to_bytes()[0], 1);
+        assert!(Uint256::zero().to_bytes().is_empty());
+        assert_eq!(Uint256::from_bytes(&[1, 0]), Uint256::from_uint64(256));
+    }
+
+    #[test]
+    fn arithmetic() {
+        let max = Uint256::max_value();
+        assert_eq!(max.wrapping_add(&Uint256::one()), Uint256::zero());
+        assert!(max.checked_add(&Uint256::one()).is_none());
+        assert!(Uint256::zero().checked_sub(&Uint256::one()).is_none());
+        assert_eq!(
+            u("340282366920938463463374607431768211456").wrapping_mul(&u("340282366920938463463374607431768211456")),
+            Uint256::zero()
+        );
+        assert_eq!(u("1000000000000000000000000").udiv(&u("1000000000000")), u("1000000000000"));
+        assert_eq!(u("1000000000000000000000007").umod(&u("1000000000000")), u("7"));
+        assert_eq!(u("7").udiv(&Uint256::zero()), Uint256::zero());
+        assert_eq!(u("2").exp(&u("255")).to_string(), "57896044618658097711785492504343953926634992332820282019728792003956564819968");
+        assert_eq!(max.mul_mod(&max, &u("12")), u("9"));
+        assert_eq!(max.add_mod(&u("2"), &u("2")), u("1"));
+    }
+
+    #[test]
+    fn signed() {
+        let minus_one = Uint256::max_value();
+        let minus_two = minus_one.wrapping_sub(&Uint256::one());
+        assert_eq!(u("10").sdiv(&minus_two), u("5").neg());
+        assert_eq!(u("7").neg().smod(&u("3")), u("1").neg());
+        assert!(minus_one.slt(&Uint256::zero()));
+        assert!(Uint256::one().sgt(&minus_one));
+        assert_eq!(minus_two.srsh(1), minus_one);
+        assert_eq!(u("0xff").sign_extend(&Uint256::zero()), minus_one);
+        assert_eq!(u("0x7f").sign_extend(&Uint256::zero()), u("0x7f"));
+    }
+
+    #[test]
+    fn shifts_and_bytes() {
+        assert_eq!(Uint256::one().lsh(255).rsh(255), Uint256::one());
+        assert_eq!(Uint256::one().lsh(256), Uint256::zero());
+        assert_eq!(u("0x0102").byte(&u("30")), u("1"));
+        assert_eq!(Uint256::one().lsh(70).bits(), 71);
+        assert_eq!(format!("{:x}", u("0xabc")), "abc");
+    }
+}